@@ -10,7 +10,7 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{spanned::Spanned, Ident, LitStr};
 
-use super::{FieldReciever, TypeReciever, VariantReciever};
+use super::{util, DenyUnknownFields, FieldReciever, TypeReciever, VariantReciever};
 
 pub fn derive_inner(input: &syn::DeriveInput) -> TokenStream {
     let reciever = match TypeReciever::from_derive_input(input) {
@@ -60,6 +60,11 @@ fn parse_reciever(reciever: &TypeReciever) -> TokenStream {
         return quote! { compile_error!("Cannot specify both `from` and `try_from`") };
     }
 
+    // the class can't be known ahead of time, so there's nothing to enforce
+    if reciever.class_from.is_some() && reciever.enforce_class.is_present() {
+        return quote! { compile_error!("Cannot use `enforce_class` with `class_from`") };
+    }
+
     if let Some(into_ty) = reciever.from_type.as_ref() {
         return quote! {
             #[automatically_derived]
@@ -88,6 +93,16 @@ fn parse_reciever(reciever: &TypeReciever) -> TokenStream {
         };
     }
 
+    if reciever.ruby_struct.is_present() {
+        if let Err(e) = util::check_ruby_struct(reciever) {
+            return e;
+        }
+    }
+
+    if reciever.transparent.is_present() {
+        return parse_transparent(reciever);
+    }
+
     match &reciever.data {
         darling::ast::Data::Enum(e) => parse_enum(reciever, e),
         darling::ast::Data::Struct(f) => parse_struct(reciever, f),
@@ -111,48 +126,85 @@ fn parse_struct(
     }
 
     let ty = reciever.ident.clone();
-    let ty_lifetimes = reciever.generics.lifetimes().map(|l| &l.lifetime);
-    let ty_lifetimes = quote! { <#( #ty_lifetimes ),*> };
-    let visitor_lifetimes = reciever.generics.lifetimes().map(|l| &l.lifetime);
-    let visitor_lifetimes = quote! { <'de, #( #visitor_lifetimes ),*> };
-
-    let lifetimes_iter = reciever.generics.lifetimes().map(|l| &l.lifetime);
-    let de_lifetime = quote! { 'de: #( #lifetimes_iter )+* };
-    // i have no idea why we need to specify this here but rust gets *really* unhappy if we don't
-    let lifetimes_iter = reciever.generics.lifetimes().cloned().map(|mut l| {
-        l.bounds.push(syn::Lifetime::new("'de", l.span()));
-        l
-    });
-    let impl_lifetimes = quote! { <#de_lifetime, #( #lifetimes_iter ),*> };
+    let de_generics = match util::de_generics(reciever) {
+        Ok(generics) => generics,
+        Err(e) => return e,
+    };
+    let (impl_generics, visitor_generics, where_clause) = de_generics.split_for_impl();
+    let (_, ty_generics, _) = reciever.generics.split_for_impl();
 
-    let (field_const, field_lets, field_match, instantiate_fields): ParseUnpack = fields
+    let catch_all = match fields
         .iter()
-        .map(|field| parse_field(reciever.default_fn.is_some(), field))
+        .filter(|f| f.catch_all.is_present())
+        .at_most_one()
+    {
+        Ok(catch_all) => catch_all.map(|f| f.ident.clone().unwrap()),
+        Err(_) => return quote! { compile_error!("Only one field can be `catch_all`") },
+    };
+    if catch_all.is_some() && reciever.deny_unknown_fields.is_some() {
+        return quote! { compile_error!("Cannot use `catch_all` with `deny_unknown_fields`") };
+    }
+
+    let (field_const, field_lets, field_match, mut instantiate_fields): ParseUnpack = fields
+        .iter()
+        .filter(|field| !field.catch_all.is_present())
+        .map(|field| parse_field(reciever, catch_all.is_some(), field))
         .multiunzip();
+    let catch_all_let = catch_all.as_ref().map(|ident| {
+        instantiate_fields.push(quote! { #ident: __catch_all });
+        quote! { let mut __catch_all = _alox_48::RbFields::new(); }
+    });
 
     let classname = reciever.class.clone().unwrap_or_else(|| ty.to_string());
     let enforce_class = if reciever.enforce_class.is_present() {
         let classname_lit = LitStr::new(&classname, ty.span());
         quote! {
             if class != Sym::new(#classname_lit) {
-                return Err(DeError::invalid_type(Unexpected::Class(class), &self));
+                return Err(DeError::wrong_class(Sym::new(#classname_lit), class));
             }
         }
     } else {
         quote! {}
     };
 
-    let unknown_fields = if reciever.deny_unknown_fields.is_present() {
+    let unknown_fields = if catch_all.is_some() {
         quote! {
-            _f => return Err(DeError::unknown_field(Sym::new(_f), __FIELDS))
+            _ => {
+                let __v = _instance_variables.next_value::<_alox_48::Value>()?;
+                __catch_all.insert(f.to_symbol(), __v);
+            }
         }
-    } else {
+    } else if reciever.deny_unknown_fields == Some(DenyUnknownFields::All) {
         quote! {
             _ => {
                 let _ = _instance_variables.next_value::<_alox_48::de::Ignored>()?;
+                __unknown_fields.push(f.to_rust_field_name().unwrap_or(f));
+            }
+        }
+    } else if reciever.deny_unknown_fields.is_some() {
+        quote! {
+            _ => return Err(DeError::unknown_field_in(class, f.to_rust_field_name().unwrap_or(f), __FIELDS))
+        }
+    } else {
+        quote! {
+            _ => {
+                _instance_variables.skip_value()?;
             }
         }
     };
+    let (unknown_fields_let, unknown_fields_check) =
+        if reciever.deny_unknown_fields == Some(DenyUnknownFields::All) {
+            (
+                quote! { let mut __unknown_fields: Vec<&'de Sym> = Vec::new(); },
+                quote! {
+                    if !__unknown_fields.is_empty() {
+                        return Err(DeError::unknown_fields_in(class, &__unknown_fields, __FIELDS));
+                    }
+                },
+            )
+        } else {
+            (quote! {}, quote! {})
+        };
     let default = reciever.default_fn.as_ref().map(|d| {
         if let Some(p) = d.as_ref().explicit() {
             quote! { let default = #p(); }
@@ -161,15 +213,62 @@ fn parse_struct(
         }
     });
 
-    let expecting_text = reciever
-        .expecting
-        .clone()
-        .unwrap_or_else(|| format!("an instance of {classname}",));
-    let expecting_lit = LitStr::new(&expecting_text, ty.span());
+    let field_names = fields
+        .iter()
+        .filter(|f| {
+            !(f.skip.is_present() || f.skip_deserializing.is_present() || f.catch_all.is_present())
+        })
+        .map(|f| {
+            f.rename
+                .as_ref()
+                .map_or_else(|| f.ident.as_ref().unwrap().to_string(), LitStr::value)
+        })
+        .collect_vec();
+    let expecting_fn = match expecting_fn(
+        reciever,
+        reciever.class_from.is_none().then_some(&classname),
+        &field_names,
+    ) {
+        Ok(expecting_fn) => expecting_fn,
+        Err(e) => return e,
+    };
+    let mut implemented = vec!["visit_object"];
+    if reciever.ruby_struct.is_present() {
+        implemented.push("visit_struct");
+    }
+    let (class_field, class_init, class_visits) = class_from_visits(reciever, &implemented);
+
+    // hashes are read as an object of the expected class, so they pass `enforce_class`
+    let visit_hash = reciever.from_hash.is_present().then(|| {
+        let classname_lit = LitStr::new(&classname, ty.span());
+        quote! {
+            fn visit_hash<A>(self, map: A) -> Result<Self::Value, DeError>
+            where
+                A: HashAccess<'de>,
+            {
+                self.visit_object(Sym::new(#classname_lit), _alox_48::de::HashIvarAccess::new(map))
+            }
+        }
+    });
+
+    // ruby structs are read like objects, once their members are checked
+    let visit_struct = reciever.ruby_struct.is_present().then(|| {
+        quote! {
+            fn visit_struct<A>(self, name: &'de Sym, members: A) -> Result<Self::Value, DeError>
+            where
+                A: IvarAccess<'de>,
+            {
+                const __MEMBERS: &[&Sym] = &[
+                    #( Sym::new(#field_names) ),*
+                ];
+                self.visit_object(name, _alox_48::de::StructMembers::new(name, __MEMBERS, members))
+            }
+        }
+    });
 
     quote! {
         #[automatically_derived]
-        impl #impl_lifetimes Deserialize<'de> for #ty #ty_lifetimes {
+        impl #impl_generics Deserialize<'de> for #ty #ty_generics #where_clause {
             fn deserialize<D>(deserializer: D) -> Result<Self, DeError>
             where
                 D: DeserializerTrait<'de>
@@ -178,17 +277,16 @@ fn parse_struct(
                     #( #field_const ),*
                 ];
 
-                struct __Visitor #impl_lifetimes {
-                    _marker: std::marker::PhantomData<#ty #ty_lifetimes >,
+                struct __Visitor #impl_generics #where_clause {
+                    _marker: std::marker::PhantomData<#ty #ty_generics>,
                     _phantom: std::marker::PhantomData<&'de ()>,
+                    #class_field
                 }
 
-                impl #impl_lifetimes Visitor<'de> for __Visitor #visitor_lifetimes {
-                    type Value = #ty #ty_lifetimes;
+                impl #impl_generics Visitor<'de> for __Visitor #visitor_generics #where_clause {
+                    type Value = #ty #ty_generics;
 
-                    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                        formatter.write_str(#expecting_lit)
-                    }
+                    #expecting_fn
 
                     fn visit_object<A>(self, class: &'de Sym, mut _instance_variables: A) -> Result<Self::Value, DeError>
                     where
@@ -197,23 +295,33 @@ fn parse_struct(
                         #enforce_class
 
                         #( #field_lets );*
+                        #catch_all_let
+                        #unknown_fields_let
 
                         while let Some(f) = _instance_variables.next_ivar()? {
-                            match f.to_rust_field_name().unwrap_or(f).as_str() {
+                            match f.as_str() {
                                 #( #field_match ),*
                                 #unknown_fields
                             }
                         }
 
+                        #unknown_fields_check
+
                         #default
 
                         Ok(#ty {
                             #( #instantiate_fields ),*
                         })
                     }
+
+                    #visit_hash
+
+                    #visit_struct
+
+                    #class_visits
                 }
 
-                deserializer.deserialize(__Visitor { _marker: std::marker::PhantomData, _phantom: std::marker::PhantomData })
+                deserializer.deserialize(__Visitor { _marker: std::marker::PhantomData, _phantom: std::marker::PhantomData #class_init })
             }
         }
     }
@@ -221,53 +329,52 @@ fn parse_struct(
 
 fn parse_newtype_struct(reciever: &TypeReciever) -> TokenStream {
     let ty = reciever.ident.clone();
-    let ty_lifetimes = reciever.generics.lifetimes().map(|l| &l.lifetime);
-    let ty_lifetimes = quote! { <#( #ty_lifetimes ),*> };
-    let visitor_lifetimes = reciever.generics.lifetimes().map(|l| &l.lifetime);
-    let visitor_lifetimes = quote! { <'de, #( #visitor_lifetimes ),*> };
-
-    let lifetimes_iter = reciever.generics.lifetimes().map(|l| &l.lifetime);
-    let de_lifetime = quote! { 'de: #( #lifetimes_iter )+* };
-    // i have no idea why we need to specify this here but rust gets *really* unhappy if we don't
-    let lifetimes_iter = reciever.generics.lifetimes().cloned().map(|mut l| {
-        l.bounds.push(syn::Lifetime::new("'de", l.span()));
-        l
-    });
-    let impl_lifetimes = quote! { <#de_lifetime, #( #lifetimes_iter ),*> };
+    let de_generics = match util::de_generics(reciever) {
+        Ok(generics) => generics,
+        Err(e) => return e,
+    };
+    let (impl_generics, visitor_generics, where_clause) = de_generics.split_for_impl();
+    let (_, ty_generics, _) = reciever.generics.split_for_impl();
 
     let classname = reciever.class.clone().unwrap_or_else(|| ty.to_string());
     let enforce_class = if reciever.enforce_class.is_present() {
         let classname_lit = LitStr::new(&classname, ty.span());
         quote! {
             if class != Sym::new(#classname_lit) {
-                return Err(DeError::invalid_type(Unexpected::Class(class), &self));
+                return Err(DeError::wrong_class(Sym::new(#classname_lit), class));
             }
         }
     } else {
         quote! {}
     };
 
-    let expecting_text = reciever
-        .expecting
-        .clone()
-        .unwrap_or_else(|| format!("an instance of {classname}"));
-    let expecting_lit = LitStr::new(&expecting_text, ty.span());
+    let expecting_fn = match expecting_fn(
+        reciever,
+        reciever.class_from.is_none().then_some(&classname),
+        &[],
+    ) {
+        Ok(expecting_fn) => expecting_fn,
+        Err(e) => return e,
+    };
+    let (class_field, class_init, class_visits) =
+        class_from_visits(reciever, &["visit_user_class"]);
 
     quote! {
         #[automatically_derived]
-        impl #impl_lifetimes Deserialize<'de> for #ty #ty_lifetimes {
+        impl #impl_generics Deserialize<'de> for #ty #ty_generics #where_clause {
             fn deserialize<D>(deserializer: D) -> Result<Self, DeError>
             where
                 D: DeserializerTrait<'de>
             {
 
-                struct __Visitor #impl_lifetimes {
-                    _marker: std::marker::PhantomData<#ty #ty_lifetimes >,
+                struct __Visitor #impl_generics #where_clause {
+                    _marker: std::marker::PhantomData<#ty #ty_generics>,
                     _phantom: std::marker::PhantomData<&'de ()>,
+                    #class_field
                 }
 
-                impl #impl_lifetimes Visitor<'de> for __Visitor #visitor_lifetimes {
-                    type Value = #ty #ty_lifetimes;
+                impl #impl_generics Visitor<'de> for __Visitor #visitor_generics #where_clause {
+                    type Value = #ty #ty_generics;
 
                     fn visit_user_class<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value, DeError>
                     where
@@ -278,17 +385,165 @@ fn parse_newtype_struct(reciever: &TypeReciever) -> TokenStream {
                         Ok(#ty(Deserialize::deserialize(deserializer)?))
                     }
 
-                    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                        formatter.write_str(#expecting_lit)
-                    }
+                    #expecting_fn
+
+                    #class_visits
                 }
 
-                deserializer.deserialize(__Visitor { _marker: std::marker::PhantomData, _phantom: std::marker::PhantomData })
+                deserializer.deserialize(__Visitor { _marker: std::marker::PhantomData, _phantom: std::marker::PhantomData #class_init })
+            }
+        }
+    }
+}
+
+fn parse_transparent(reciever: &TypeReciever) -> TokenStream {
+    let member = match util::transparent_member(reciever) {
+        Ok(member) => member,
+        Err(e) => return e,
+    };
+
+    let ty = reciever.ident.clone();
+    let de_generics = match util::de_generics(reciever) {
+        Ok(generics) => generics,
+        Err(e) => return e,
+    };
+    let (impl_generics, _, where_clause) = de_generics.split_for_impl();
+    let (_, ty_generics, _) = reciever.generics.split_for_impl();
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics Deserialize<'de> for #ty #ty_generics #where_clause {
+            fn deserialize<D>(deserializer: D) -> Result<Self, DeError>
+            where
+                D: DeserializerTrait<'de>
+            {
+                Deserialize::deserialize(deserializer).map(|__v| #ty { #member: __v })
             }
         }
     }
 }
 
+/// The `expecting` method of a visitor.
+///
+/// `{class}` is `classname`. Without one, the class is only known once the visitor sees one,
+/// so it's read from the visitor's `__class` (see [`class_from_visits`]).
+fn expecting_fn(
+    reciever: &TypeReciever,
+    classname: Option<&str>,
+    fields: &[String],
+) -> Result<TokenStream, TokenStream> {
+    let span = reciever.ident.span();
+    let parts = match reciever.expecting.as_ref() {
+        Some(expecting) => match util::expand_expecting(expecting, fields) {
+            Ok(parts) => parts,
+            Err(e) => return Err(quote! { compile_error!(#e); }),
+        },
+        None => vec![
+            util::Expecting::Text("an instance of ".to_string()),
+            util::Expecting::Class,
+        ],
+    };
+
+    if let Some(classname) = classname {
+        let text: String = parts
+            .iter()
+            .map(|part| match part {
+                util::Expecting::Text(text) => text.as_str(),
+                util::Expecting::Class => classname,
+            })
+            .collect();
+        let text = LitStr::new(&text, span);
+        return Ok(quote! {
+            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                formatter.write_str(#text)
+            }
+        });
+    }
+
+    let writes = parts.iter().map(|part| match part {
+        util::Expecting::Text(text) => {
+            let text = LitStr::new(text, span);
+            quote! { formatter.write_str(#text)?; }
+        }
+        util::Expecting::Class => quote! {
+            formatter.write_str(self.__class.map_or("any class", Sym::as_str))?;
+        },
+    });
+    Ok(quote! {
+        fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            #( #writes )*
+            Ok(())
+        }
+    })
+}
+
+/// With `class_from`, the visitor keeps the class it saw in `__class`, so errors can name it in `expecting`.
+///
+/// Returns the field, its initializer, and visit methods that record the class before erroring like the defaults do.
+/// Methods in `skip` are already implemented by the visitor.
+fn class_from_visits(
+    reciever: &TypeReciever,
+    skip: &[&str],
+) -> (TokenStream, Option<TokenStream>, TokenStream) {
+    if reciever.class_from.is_none() {
+        return (quote! {}, None, quote! {});
+    }
+
+    let visits = [
+        (
+            "visit_object",
+            quote! {
+                fn visit_object<A>(self, class: &'de Sym, _instance_variables: A) -> Result<Self::Value, DeError>
+                where
+                    A: IvarAccess<'de>,
+                {
+                    Err(DeError::invalid_value(Unexpected::Object(class), &Self { __class: Some(class), ..self }))
+                }
+            },
+        ),
+        (
+            "visit_struct",
+            quote! {
+                fn visit_struct<A>(self, name: &'de Sym, _members: A) -> Result<Self::Value, DeError>
+                where
+                    A: IvarAccess<'de>,
+                {
+                    Err(DeError::invalid_value(Unexpected::Struct(name), &Self { __class: Some(name), ..self }))
+                }
+            },
+        ),
+        (
+            "visit_user_data",
+            quote! {
+                fn visit_user_data(self, class: &'de Sym, _data: &'de [u8]) -> Result<Self::Value, DeError> {
+                    Err(DeError::invalid_value(Unexpected::UserData(class), &Self { __class: Some(class), ..self }))
+                }
+            },
+        ),
+        (
+            "visit_user_class",
+            quote! {
+                fn visit_user_class<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value, DeError>
+                where
+                    D: DeserializerTrait<'de>
+                {
+                    deserializer.deserialize(Self { __class: Some(class), ..self })
+                }
+            },
+        ),
+    ];
+    let visits = visits
+        .into_iter()
+        .filter(|(name, _)| !skip.contains(name))
+        .map(|(_, visit)| visit);
+
+    (
+        quote! { __class: Option<&'de Sym>, },
+        Some(quote! { , __class: None }),
+        quote! { #( #visits )* },
+    )
+}
+
 type ParseTuple<T> = (
     // const field
     T,
@@ -302,7 +557,9 @@ type ParseTuple<T> = (
 type ParseResult = ParseTuple<TokenStream>;
 type ParseUnpack = ParseTuple<Vec<TokenStream>>;
 
-fn parse_field(reciever_has_default: bool, field: &FieldReciever) -> ParseResult {
+#[allow(clippy::too_many_lines)]
+fn parse_field(reciever: &TypeReciever, catch_all: bool, field: &FieldReciever) -> ParseResult {
+    let reciever_has_default = reciever.default_fn.is_some();
     let field_ident = field.ident.as_ref().unwrap();
     let field_str = format!("__field_{field_ident}");
     let field_ty = field.ty.clone();
@@ -314,6 +571,10 @@ fn parse_field(reciever_has_default: bool, field: &FieldReciever) -> ParseResult
         .map_or_else(|| field_ident.to_string(), syn::LitStr::value);
     let field_lit_str = LitStr::new(&field_lit, field_ident.span());
     let const_sym = quote! { Sym::new(#field_lit_str) };
+    // matching the raw instance variable is faster than stripping the '@' first.
+    // struct members have no '@', so they match the plain name.
+    let ivar_lit_str = LitStr::new(&format!("@{field_lit}"), field_ident.span());
+    let field_pat = quote! { #ivar_lit_str | #field_lit_str };
 
     let let_field = quote! { let mut #let_var_ident: Option<#field_ty> = None; };
 
@@ -328,16 +589,89 @@ fn parse_field(reciever_has_default: bool, field: &FieldReciever) -> ParseResult
 
     let skip = field.skip.is_present() || field.skip_deserializing.is_present();
 
-    let match_field = if skip {
+    // values that fail to deserialize are captured, and then treated as missing so the default is used
+    let has_default = field.default_fn.is_some() || reciever_has_default;
+    let default_on_error = field.default_on_error.is_present()
+        || (reciever.default_on_error.is_present()
+            && has_default
+            && !field.byte_string.is_present());
+    // nil is read as `None`, which is then treated as missing too
+    let nil_as_default = field.nil_as_default.is_present()
+        || (reciever.nil_as_default.is_present() && has_default && !field.byte_string.is_present());
+    let (value_ty, store_value) = if nil_as_default {
+        (quote! { Option<#field_ty> }, quote! { __v })
+    } else {
+        (quote! { #field_ty }, quote! { Some(__v) })
+    };
+
+    let expectation = util::expectation(field);
+    let (next_value, next_value_or_capture) = match &expectation {
+        Ok(Some(expectation)) => (
+            quote! { next_value_seed(#expectation.seed(std::marker::PhantomData::<#value_ty>)) },
+            quote! { next_value_seed_or_capture(#expectation.seed(std::marker::PhantomData::<#value_ty>)) },
+        ),
+        // errors are reported in place of the match arm
+        Ok(None) | Err(_) => (
+            quote! { next_value::<#value_ty>() },
+            quote! { next_value_or_capture::<#value_ty>() },
+        ),
+    };
+    let on_capture = if catch_all {
+        quote! { __catch_all.insert(f.to_symbol(), __v); }
+    } else {
+        quote! { drop(__v); }
+    };
+
+    let match_field = if let Err(e) = expectation {
+        quote! {
+            #field_pat => { #e }
+        }
+    } else if field.default_on_error.is_present() && !has_default {
+        quote! {
+            #field_pat => { compile_error!("`default_on_error` fields must have a default") }
+        }
+    } else if field.default_on_error.is_present() && field.byte_string.is_present() {
+        quote! {
+            #field_pat => { compile_error!("Cannot use `default_on_error` with `byte_string`") }
+        }
+    } else if field.nil_as_default.is_present() && !has_default {
+        quote! {
+            #field_pat => { compile_error!("`nil_as_default` fields must have a default") }
+        }
+    } else if field.nil_as_default.is_present() && field.byte_string.is_present() {
+        quote! {
+            #field_pat => { compile_error!("Cannot use `nil_as_default` with `byte_string`") }
+        }
+    } else if skip {
         quote! {
-            #field_lit_str => {
+            #field_pat => {
                 let _ = _instance_variables.next_value::<_alox_48::de::Ignored>()?;
                 // skipped
             }
         }
     } else if let Some(with_fn) = deserialize_with_fn {
+        let (with_ty, store_with) = if nil_as_default {
+            (
+                quote! { Option<__DeserializeField> },
+                quote! { __v.map(|__v| __v.0) },
+            )
+        } else {
+            (quote! { __DeserializeField }, quote! { Some(__v.0) })
+        };
+        let read_with = if default_on_error {
+            quote! {
+                if let Ok(__v) = _instance_variables.next_value_or_capture::<#with_ty>()? {
+                    #let_var_ident = #store_with;
+                }
+            }
+        } else {
+            quote! {
+                let __v = _instance_variables.next_value::<#with_ty>()?;
+                #let_var_ident = #store_with;
+            }
+        };
         quote! {
-            #field_lit_str => {
+            #field_pat => {
                 struct __DeserializeField(#field_ty);
                 impl<'de> Deserialize<'de> for __DeserializeField {
                     fn deserialize<D>(deserializer: D) -> Result<Self, DeError>
@@ -347,15 +681,37 @@ fn parse_field(reciever_has_default: bool, field: &FieldReciever) -> ParseResult
                         #with_fn(deserializer).map(Self)
                     }
                 }
-                let __v = _instance_variables.next_value::<__DeserializeField>()?.0;
+                #read_with
+            }
+        }
+    } else if field.byte_string.is_present() {
+        let byte_string_ty = util::byte_string_type(&field_ty);
+        let byte_string = util::byte_string_from(
+            &field_ty,
+            &quote! { _instance_variables.next_value::<#byte_string_ty>()? },
+        );
+        quote! {
+            #field_pat => {
+                let __v: #field_ty = #byte_string;
                 #let_var_ident = Some(__v);
             }
         }
+    } else if catch_all || default_on_error {
+        quote! {
+            #field_pat => {
+                match _instance_variables.#next_value_or_capture? {
+                    Ok(__v) => #let_var_ident = #store_value,
+                    Err(__v) => {
+                        #on_capture
+                    }
+                }
+            }
+        }
     } else {
         quote! {
-            #field_lit_str => {
-                let __v = _instance_variables.next_value::<#field_ty>()?;
-                #let_var_ident = Some(__v);
+            #field_pat => {
+                let __v = _instance_variables.#next_value?;
+                #let_var_ident = #store_value;
             }
         }
     };
@@ -379,7 +735,7 @@ fn parse_field(reciever_has_default: bool, field: &FieldReciever) -> ParseResult
     } else {
         quote! {
             #field_ident: #let_var_ident.ok_or_else(|| {
-                DeError::missing_field(Sym::new(#field_lit_str))
+                DeError::missing_field_in(class, Sym::new(#field_lit_str))
             })?
         }
     };
@@ -387,8 +743,139 @@ fn parse_field(reciever_has_default: bool, field: &FieldReciever) -> ParseResult
     (const_sym, let_field, match_field, instantiate_field)
 }
 
-fn parse_enum(_reciever: &TypeReciever, _variants: &[VariantReciever]) -> TokenStream {
+#[allow(clippy::too_many_lines)]
+fn parse_enum(reciever: &TypeReciever, variants: &[VariantReciever]) -> TokenStream {
+    let ty = reciever.ident.clone();
+    let de_generics = match util::de_generics(reciever) {
+        Ok(generics) => generics,
+        Err(e) => return e,
+    };
+    let (impl_generics, visitor_generics, where_clause) = de_generics.split_for_impl();
+    let (_, ty_generics, _) = reciever.generics.split_for_impl();
+
+    let Ok(other) = variants
+        .iter()
+        .filter(|v| v.other.is_present())
+        .at_most_one()
+    else {
+        return quote! { compile_error!("Only one variant can be `other`") };
+    };
+
+    let mut classes = vec![];
+    let mut arms = vec![];
+    for variant in variants.iter().filter(|v| !v.other.is_present()) {
+        if !variant.fields.is_newtype() {
+            return quote! {
+                compile_error!("Derive macro only supports newtype variants in enums, or a unit `other` variant")
+            };
+        }
+
+        let ident = &variant.ident;
+        let class = variant.class.clone().unwrap_or_else(|| ident.to_string());
+        let class_lit = LitStr::new(&class, ident.span());
+        arms.push(quote! {
+            #class_lit => Deserialize::deserialize(__deserializer).map(#ty::#ident)
+        });
+        classes.push(class);
+    }
+
+    // the unknown data is skipped for unit variants, and deserialized for newtype variants
+    let (other_data, other_ivars) = match other {
+        Some(v) if v.fields.is_unit() => {
+            let ident = &v.ident;
+            (
+                quote! { Ok(#ty::#ident) },
+                quote! {
+                    {
+                        let mut __access = __deserializer.into_inner();
+                        while __access.next_ivar()?.is_some() {
+                            __access.skip_value()?;
+                        }
+                        Ok(#ty::#ident)
+                    }
+                },
+            )
+        }
+        Some(v) if v.fields.is_newtype() => {
+            let ident = &v.ident;
+            let other = quote! { Deserialize::deserialize(__deserializer).map(#ty::#ident) };
+            (other.clone(), other)
+        }
+        Some(_) => {
+            return quote! {
+                compile_error!("The `other` variant must be a unit or newtype variant")
+            }
+        }
+        None => (
+            quote! { Err(DeError::invalid_value(__unexpected, &self)) },
+            quote! { Err(DeError::invalid_value(__unexpected, &self)) },
+        ),
+    };
+
+    let expected_classes = match classes.as_slice() {
+        [] => String::new(),
+        [class] => class.clone(),
+        [rest @ .., last] => format!("{} or {last}", rest.join(", ")),
+    };
+    let expecting_fn = match expecting_fn(reciever, Some(&expected_classes), &[]) {
+        Ok(expecting_fn) => expecting_fn,
+        Err(e) => return e,
+    };
+
     quote! {
-        compile_error!("Derive macro does not currently automatic deserialize impls for enums!")
+        #[automatically_derived]
+        impl #impl_generics Deserialize<'de> for #ty #ty_generics #where_clause {
+            fn deserialize<D>(deserializer: D) -> Result<Self, DeError>
+            where
+                D: DeserializerTrait<'de>
+            {
+                struct __Visitor #impl_generics #where_clause {
+                    _marker: std::marker::PhantomData<#ty #ty_generics>,
+                    _phantom: std::marker::PhantomData<&'de ()>,
+                }
+
+                impl #impl_generics Visitor<'de> for __Visitor #visitor_generics #where_clause {
+                    type Value = #ty #ty_generics;
+
+                    #expecting_fn
+
+                    fn visit_object<A>(self, class: &'de Sym, __access: A) -> Result<Self::Value, DeError>
+                    where
+                        A: IvarAccess<'de>,
+                    {
+                        let __unexpected = Unexpected::Object(class);
+                        let __deserializer = _alox_48::de::ObjectDeserializer::object(class, __access);
+                        match class.as_str() {
+                            #( #arms, )*
+                            _ => #other_ivars,
+                        }
+                    }
+
+                    fn visit_struct<A>(self, name: &'de Sym, __access: A) -> Result<Self::Value, DeError>
+                    where
+                        A: IvarAccess<'de>,
+                    {
+                        let class = name;
+                        let __unexpected = Unexpected::Struct(class);
+                        let __deserializer = _alox_48::de::ObjectDeserializer::rb_struct(class, __access);
+                        match class.as_str() {
+                            #( #arms, )*
+                            _ => #other_ivars,
+                        }
+                    }
+
+                    fn visit_user_data(self, class: &'de Sym, data: &'de [u8]) -> Result<Self::Value, DeError> {
+                        let __unexpected = Unexpected::UserData(class);
+                        let __deserializer = _alox_48::de::UserDataDeserializer::new(class, data);
+                        match class.as_str() {
+                            #( #arms, )*
+                            _ => #other_data,
+                        }
+                    }
+                }
+
+                deserializer.deserialize(__Visitor { _marker: std::marker::PhantomData, _phantom: std::marker::PhantomData })
+            }
+        }
     }
 }
@@ -1,4 +1,6 @@
 #![warn(rust_2018_idioms, clippy::all, clippy::pedantic)]
+// darling generates code that trips this lint.
+#![allow(clippy::needless_continue)]
 use proc_macro::TokenStream;
 
 use syn::DeriveInput;
@@ -9,7 +11,7 @@ mod util;
 
 use darling::{
     util::{Flag, Override},
-    FromDeriveInput,
+    FromDeriveInput, FromMeta,
 };
 use syn::{Ident, LitStr, Path, Type};
 
@@ -25,9 +27,15 @@ struct TypeReciever {
     alox_crate_path: Option<Path>,
 
     class: Option<String>,
+    class_from: Option<Path>,
 
-    deny_unknown_fields: Flag,
+    deny_unknown_fields: Option<DenyUnknownFields>,
     enforce_class: Flag,
+    from_hash: Flag,
+    ruby_struct: Flag,
+    transparent: Flag,
+    default_on_error: Flag,
+    nil_as_default: Flag,
 
     #[darling(rename = "default")]
     default_fn: Option<Override<Path>>,
@@ -41,6 +49,58 @@ struct TypeReciever {
     try_into_type: Option<Type>,
 
     expecting: Option<String>,
+    #[darling(with = Bound::from_meta_or_string, map = Some, default)]
+    bound: Option<Bound>,
+}
+
+/// Where clauses that replace the bounds added to type parameters.
+///
+/// `bound = "..."` applies to both impls, and `bound(serialize = "...", deserialize = "...")` to each one.
+#[derive(Debug, FromMeta)]
+struct Bound {
+    serialize: Option<LitStr>,
+    deserialize: Option<LitStr>,
+}
+
+impl Bound {
+    fn from_meta_or_string(item: &syn::Meta) -> darling::Result<Self> {
+        match item {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(bound),
+                        ..
+                    }),
+                ..
+            }) => Ok(Self {
+                serialize: Some(bound.clone()),
+                deserialize: Some(bound.clone()),
+            }),
+            _ => Self::from_meta(item),
+        }
+    }
+}
+
+/// How `deny_unknown_fields` reports unknown fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DenyUnknownFields {
+    /// Error at the first unknown field.
+    First,
+    /// Read every field, then error with all of the unknown ones.
+    All,
+}
+
+impl FromMeta for DenyUnknownFields {
+    fn from_word() -> darling::Result<Self> {
+        Ok(Self::First)
+    }
+
+    fn from_string(value: &str) -> darling::Result<Self> {
+        match value {
+            "all" => Ok(Self::All),
+            _ => Err(darling::Error::unknown_value(value)),
+        }
+    }
 }
 
 #[derive(Debug, darling::FromField)]
@@ -58,45 +118,94 @@ struct FieldReciever {
     skip_serializing: Flag,
     skip_deserializing: Flag,
     byte_string: Flag,
+    catch_all: Flag,
+    default_on_error: Flag,
+    nil_as_default: Flag,
+
+    expect: Option<LitStr>,
+    expect_class: Option<LitStr>,
 
     #[darling(rename = "deserialize_with")]
     deserialize_with_fn: Option<Path>,
     #[darling(rename = "serialize_with")]
     serialize_with_fn: Option<Path>,
+    skip_serializing_if: Option<Path>,
     #[darling(rename = "with")]
     with_module: Option<Path>,
 }
 
 #[allow(dead_code)]
 #[derive(Debug, darling::FromVariant)]
+#[darling(attributes(marshal))]
 struct VariantReciever {
     ident: Ident,
     fields: darling::ast::Fields<FieldReciever>,
 
     transparent: Flag,
     class: Option<String>,
+    other: Flag,
 }
 
-/// Derive `Deserialize` for a struct.
+/// Derive `Deserialize` for a struct or enum.
 ///
-/// Does not currently support enums.
+/// Enums pick a variant by the class of an object, struct, or user data.
+/// Every variant must either be a newtype variant, which is deserialized from data of its class,
+/// or the `other` variant.
 ///
 /// Type attributes:
 /// - `alox_crate_path`: The path to the alox-48 crate.
 /// - `class`: Override the class that the class enforcer checks for. By default, the class of structs is the struct name.
 /// - `deny_unknown_fields`: If set, the deserializer will error if it encounters a field not in the struct.
-/// - `enforce_class`: If set, the deserializer will enforce that the class matches.
+///   Use `deny_unknown_fields = "all"` to read the whole object first and report every unknown field at once,
+///   with suggestions for likely typos.
+/// - `enforce_class`: If set, the deserializer will enforce that the class matches. This can't be combined with `class_from`.
+/// - `from_hash`: Also deserialize structs from a hash with symbol keys, like `{ name: "Aluxes", level: 1 }`.
+///   Each key is matched against the field names like an instance variable, and the class is not checked.
 /// - `default`: The default function to use for a field. Leave empty to use `Default::default`.
+/// - `default_on_error`: Use the default of every field that has one when its data fails to deserialize,
+///   like when it's `nil` or an object of the wrong class. See the field attribute of the same name.
+/// - `nil_as_default`: Use the default of every field that has one when its data is `nil`.
+///   See the field attribute of the same name.
 /// - `from`: Deserialize from a different type. That type must implement `Deserialize`.
 /// - `try_from`: Deserialize from a different type. That type must implement `TryFrom`, and its error type must implement `Display`.
+/// - `ruby_struct`: Also deserialize structs from a ruby struct (a `Struct` subclass, rather than an object).
+///   Its members must match the fields exactly, in the order they're declared, like when ruby loads a struct.
+///   Skipped fields aren't members.
+/// - `transparent`: Deserialize a struct with one field as that field, instead of as an object.
+/// - `bound`: Replace the `T: Deserialize<'de>` bounds added to type parameters with a where clause, like `bound = "T: Default"`.
+///   Use `bound(deserialize = "...")` to only replace the bounds of this impl.
 /// - `expecting`: The error message to use if deserialization fails.
+///   `{class}` is replaced with the class name, and `{fields}` with the names of the fields. Use `{{` and `}}` for literal braces.
+///   With `class_from`, `{class}` is the class of the value that was read, or "any class" if it didn't have one.
 ///
 /// Field attributes:
 /// - `rename`: Rename the field.
 /// - `default`: The default function to use for a field. Leave empty to use `Default::default`.
 /// - `skip` or `skip_deserializing`: Skip deserializing the field.
+/// - `default_on_error`: Use the default if the field's data fails to deserialize, instead of erroring.
+///   The field must have a default, from its own `default` attribute or the type's.
+///   Deserializers that can't go back and read the data again (see `IvarAccess::next_value_seed_or_capture`) still error.
+/// - `nil_as_default`: Use the default if the field's data is `nil`, instead of erroring.
+///   Unlike `default_on_error`, data of the wrong type still errors.
+///   The field must have a default, from its own `default` attribute or the type's.
+/// - `byte_string`: Deserialize the field from a ruby string. The field must impl `From<&[u8]>`.
+///   This also sees through `Vec`, `Option`, `Instance`, and `Extended`, however they're nested,
+///   so `Option<Instance<Vec<u8>>>` works too. `Vec<u8>` itself is treated as a byte string, not a container.
+///   The values of a `HashMap`, `BTreeMap`, or `IndexMap` are byte strings too, but not the keys.
 /// - `deserialize_with`: Use a custom function to deserialize the field. That function must have the signature `fn(impl Deserializer<'de>) -> Result<T, DeError>`.
 /// - `with`: Like `deserialize_with`, but the function is in a module.
+/// - `expect`: Check what kind of value the field is before deserializing it, like `expect = "Array"`.
+///   See `alox_48::de::expect::ValueKind` for the kinds.
+/// - `expect_class`: Check the class of the field's value before deserializing it, like `expect_class = "RPG::MoveRoute"`.
+///   `expect` and `expect_class` can't be combined with `byte_string`, `deserialize_with`, or `with`.
+/// - `catch_all`: Collect unknown instance variables into this field, which must be an `RbFields` (so it needs the `value` feature).
+///   Fields whose data fails to deserialize are collected too, instead of erroring, and are then treated as missing.
+///   Only one field can be `catch_all`, and it can't be combined with `deny_unknown_fields`.
+///
+/// Variant attributes:
+/// - `class`: The class of this variant. By default, the class is the variant name.
+/// - `other`: Use this variant for classes that don't match any other variant, instead of erroring.
+///   It can be a unit variant, or a newtype variant that is deserialized from the unknown data (like `Other(alox_48::Value)`).
 #[proc_macro_derive(Deserialize, attributes(marshal))]
 pub fn derive_deserialize(item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as DeriveInput);
@@ -111,14 +220,30 @@ pub fn derive_deserialize(item: TokenStream) -> TokenStream {
 /// Type attributes:
 /// - `alox_crate_path`: The path to the alox-48 crate.
 /// - `class`: Override the class that this type is serialized as. By default, the class is the struct name.
+/// - `class_from`: Get the class to serialize as from a function, for when it's only known at runtime, like `class_from = "Self::class_name"`.
+///   The function must have the signature `fn(&Self) -> impl AsRef<str>`. This can't be combined with `class`.
 /// - `into`: Serialize to a different type. That type must implement `Serialize`, and `Self` must impl `Into<T> + Clone`.
 /// - `try_into`: Serialize to a different type. That type must implement `Serialize`, and Self must impl `TryInto<T> + Clone`.
+/// - `ruby_struct`: Serialize as a ruby struct (a `Struct` subclass) instead of an object.
+///   Fields are written as members without an `@`, in the order they're declared.
+///   This can't be combined with `catch_all` or `skip_serializing_if`, as ruby structs have a fixed list of members.
+/// - `transparent`: Serialize a struct with one field as that field, instead of as an object.
+///   Unlike `into`, this doesn't need `Clone` or a conversion.
+/// - `bound`: Replace the `T: Serialize` bounds added to type parameters with a where clause, like `bound = "T: Default"`.
+///   Use `bound(serialize = "...")` to only replace the bounds of this impl.
 ///
 /// Field attributes:
 /// - `rename`: Rename the field.
 /// - `skip` or `skip_serializing`: Skip serializing the field.
+/// - `skip_serializing_if`: Skip serializing the field if a function returns true, like `skip_serializing_if = "Option::is_none"`.
+///   The function must have the signature `fn(&T) -> bool`. Skipped fields aren't counted in the object's length.
+/// - `byte_string`: Serialize the field as a ruby string instead of an array of integers. The field must impl `AsRef<[u8]>`.
+///   Like when deserializing, this sees through `Vec`, `Option`, `Instance`, `Extended`, and the values of maps.
+///   For other containers, use `SerializeByteString`.
 /// - `serialize_with`: Use a custom function to serialize the field. That function must have the signature `fn(&T, impl Serializer) -> Result<S::Ok, SerError>`.
 /// - `with`: Like `serialize_with`, but the function is in a module.
+/// - `catch_all`: Serialize the instance variables in this `RbFields` after the other fields.
+///   Instance variables named after another field replace that field, so data captured while deserializing is written back as-is.
 #[proc_macro_derive(Serialize, attributes(marshal))]
 pub fn derive_serialize(item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as DeriveInput);
@@ -126,4 +251,5 @@ pub fn derive_serialize(item: TokenStream) -> TokenStream {
     ser::derive_inner(&input).into()
 }
 
-// TODO tests
+#[cfg(test)]
+mod tests;
@@ -10,7 +10,7 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{spanned::Spanned, Ident, LitInt, LitStr};
 
-use super::{FieldReciever, TypeReciever, VariantReciever};
+use super::{util, FieldReciever, TypeReciever, VariantReciever};
 
 pub fn derive_inner(input: &syn::DeriveInput) -> proc_macro2::TokenStream {
     let reciever = match TypeReciever::from_derive_input(input) {
@@ -45,7 +45,7 @@ fn parse_reciever(reciever: &TypeReciever) -> TokenStream {
     let ty = &reciever.ident;
 
     if reciever.try_into_type.is_some() && reciever.into_type.is_some() {
-        return quote! { compile_error!("Cannot specify both `from` and `try_from`") };
+        return quote! { compile_error!("Cannot specify both `into` and `try_into`") };
     }
 
     if let Some(into_ty) = reciever.into_type.as_ref() {
@@ -74,6 +74,20 @@ fn parse_reciever(reciever: &TypeReciever) -> TokenStream {
         };
     }
 
+    if reciever.class.is_some() && reciever.class_from.is_some() {
+        return quote! { compile_error!("Cannot specify both `class` and `class_from`") };
+    }
+
+    if reciever.ruby_struct.is_present() {
+        if let Err(e) = util::check_ruby_struct(reciever) {
+            return e;
+        }
+    }
+
+    if reciever.transparent.is_present() {
+        return parse_transparent(reciever);
+    }
+
     match &reciever.data {
         darling::ast::Data::Enum(e) => parse_enum(reciever, e),
         darling::ast::Data::Struct(f) => parse_struct(reciever, f),
@@ -96,27 +110,101 @@ fn parse_struct(
     }
 
     let ty = reciever.ident.clone();
-    let impl_lifetimes = reciever.generics.lifetimes();
-    let ty_lifetimes = reciever.generics.lifetimes().map(|l| &l.lifetime);
+    let generics = match util::ser_generics(reciever) {
+        Ok(generics) => generics,
+        Err(e) => return e,
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let class = class_sym(reciever);
 
-    let classname = reciever.class.clone().unwrap_or_else(|| ty.to_string());
+    let catch_all = match fields
+        .iter()
+        .filter(|f| f.catch_all.is_present())
+        .at_most_one()
+    {
+        Ok(catch_all) => catch_all.map(|f| f.ident.clone().unwrap()),
+        Err(_) => return quote! { compile_error!("Only one field can be `catch_all`") },
+    };
 
-    let field_impls = fields
+    let serialized_fields = fields
         .iter()
-        .filter(|field| !(field.skip.is_present() || field.skip_serializing.is_present()))
-        .map(parse_field)
+        .filter(|field| {
+            !(field.skip.is_present()
+                || field.skip_serializing.is_present()
+                || field.catch_all.is_present())
+        })
+        .collect_vec();
+    let field_impls = serialized_fields
+        .iter()
+        .map(|field| parse_field(reciever, catch_all.is_some(), field))
         .collect_vec();
     let fields_len = format!("{}_usize", field_impls.len());
     let fields_len = LitInt::new(&fields_len, ty.span());
 
+    // captured ivars replace fields with the same name, and the rest are written afterwards
+    let (catch_all_let, catch_all_count, catch_all_impl) = match &catch_all {
+        Some(ident) => {
+            let ivar_strs = serialized_fields.iter().map(|f| ivar_str(f));
+            (
+                quote! {
+                    let __catch_all: &_alox_48::RbFields = &self.#ident;
+                    let __known = [#( Sym::new(#ivar_strs) ),*];
+                    let __is_known = |ivar: &Sym| __known.iter().any(|f| **f == *ivar);
+                },
+                quote! { + __catch_all.keys().filter(|k| !__is_known(k)).count() },
+                quote! {
+                    for (field, value) in __catch_all {
+                        if !__is_known(field) {
+                            serialize_ivars.serialize_entry(field, value)?;
+                        }
+                    }
+                },
+            )
+        }
+        None => (quote! {}, quote! {}, quote! {}),
+    };
+
+    // fields are skipped before serializing anything, so the length can account for them
+    let (skip_lets, skip_idents): (Vec<_>, Vec<_>) = serialized_fields
+        .iter()
+        .filter_map(|field| {
+            let predicate = field.skip_serializing_if.as_ref()?;
+            let field_ident = field.ident.as_ref().unwrap();
+            let skip_ident = skip_ident(field);
+            // captured values are always written back
+            let captured = catch_all.as_ref().map(|_| {
+                let ivar_str = ivar_str(field);
+                quote! { && !__catch_all.contains_key(Sym::new(#ivar_str)) }
+            });
+            Some((
+                quote! { let #skip_ident: bool = #predicate(&self.#field_ident) #captured; },
+                skip_ident,
+            ))
+        })
+        .unzip();
+    let fields_len_let = quote! {
+        let fields_len = #fields_len #( - usize::from(#skip_idents) )* #catch_all_count;
+    };
+
+    let serialize_fn = if reciever.ruby_struct.is_present() {
+        quote! { serialize_struct }
+    } else {
+        quote! { serialize_object }
+    };
+
     quote! {
         #[automatically_derived]
-        impl < #( #impl_lifetimes ),* > Serialize for #ty < #( #ty_lifetimes ),* > {
+        impl #impl_generics Serialize for #ty #ty_generics #where_clause {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, SerError>
                 where S: SerializerTrait
             {
-                let mut serialize_ivars = serializer.serialize_object(&Sym::new(#classname), #fields_len)?;
+                #catch_all_let
+                #( #skip_lets )*
+                #fields_len_let
+                let mut serialize_ivars = serializer.#serialize_fn(&#class, fields_len)?;
                 #(#field_impls)*
+                #catch_all_impl
                 serialize_ivars.end()
             }
         }
@@ -125,34 +213,90 @@ fn parse_struct(
 
 fn parse_newtype_struct(reciever: &TypeReciever) -> TokenStream {
     let ty = reciever.ident.clone();
-    let impl_lifetimes = reciever.generics.lifetimes();
-    let ty_lifetimes = reciever.generics.lifetimes().map(|l| &l.lifetime);
+    let generics = match util::ser_generics(reciever) {
+        Ok(generics) => generics,
+        Err(e) => return e,
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let classname = reciever.class.clone().unwrap_or_else(|| ty.to_string());
+    let class = class_sym(reciever);
 
     quote! {
         #[automatically_derived]
-        impl < #( #impl_lifetimes ),* > Serialize for #ty < #( #ty_lifetimes ),* > {
+        impl #impl_generics Serialize for #ty #ty_generics #where_clause {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, SerError>
                 where S: SerializerTrait
             {
-                let class = Sym::new(#classname);
-                serializer.serialize_user_class(class, &self.0)
+                serializer.serialize_user_class(#class, &self.0)
             }
         }
     }
 }
 
-type ParseResult = TokenStream;
-fn parse_field(field: &FieldReciever) -> ParseResult {
-    let field_ident = field.ident.as_ref().unwrap();
-    let field_ty = field.ty.clone();
+/// An expression for the class to serialize as, which is a `&Sym` that only lives until the end of the statement.
+fn class_sym(reciever: &TypeReciever) -> TokenStream {
+    if let Some(class_from) = reciever.class_from.as_ref() {
+        quote! { Sym::new(AsRef::<str>::as_ref(&#class_from(self))) }
+    } else {
+        let classname = reciever
+            .class
+            .clone()
+            .unwrap_or_else(|| reciever.ident.to_string());
+        quote! { Sym::new(#classname) }
+    }
+}
 
+fn parse_transparent(reciever: &TypeReciever) -> TokenStream {
+    let member = match util::transparent_member(reciever) {
+        Ok(member) => member,
+        Err(e) => return e,
+    };
+
+    let ty = reciever.ident.clone();
+    let generics = match util::ser_generics(reciever) {
+        Ok(generics) => generics,
+        Err(e) => return e,
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics Serialize for #ty #ty_generics #where_clause {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, SerError>
+                where S: SerializerTrait
+            {
+                Serialize::serialize(&self.#member, serializer)
+            }
+        }
+    }
+}
+
+fn field_str(field: &FieldReciever) -> LitStr {
+    let field_ident = field.ident.as_ref().unwrap();
     let serialize_str = field
         .rename
         .as_ref()
         .map_or_else(|| field_ident.to_string(), syn::LitStr::value);
-    let serialize_str = LitStr::new(&serialize_str, field_ident.span());
+    LitStr::new(&serialize_str, field_ident.span())
+}
+
+/// The instance variable a field is written as.
+///
+/// The '@' is added here rather than with `Sym::to_ivar`, so serializing a field doesn't allocate.
+fn ivar_str(field: &FieldReciever) -> LitStr {
+    let field_str = field_str(field);
+    let value = field_str.value();
+    if value.starts_with('@') {
+        field_str
+    } else {
+        LitStr::new(&format!("@{value}"), field_str.span())
+    }
+}
+
+type ParseResult = TokenStream;
+fn parse_field(reciever: &TypeReciever, catch_all: bool, field: &FieldReciever) -> ParseResult {
+    let field_ident = field.ident.as_ref().unwrap();
+    let field_ty = field.ty.clone();
 
     let serialize_with_fn = field.serialize_with_fn.clone().or_else(|| {
         field.with_module.clone().map(|mut module| {
@@ -163,35 +307,73 @@ fn parse_field(field: &FieldReciever) -> ParseResult {
         })
     });
 
-    if let Some(with_fn) = serialize_with_fn {
+    let serialize_entry = if let Some(with_fn) = serialize_with_fn {
         quote! {
-            {
-                struct __SerializeField<'a>(&'a #field_ty);
-                impl Serialize for __SerializeField<'_> {
-                    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, SerError>
-                    where S: SerializerTrait
-                    {
-                        #with_fn(self.0, serializer)
-                    }
+            struct __SerializeField<'a>(&'a #field_ty);
+            impl Serialize for __SerializeField<'_> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, SerError>
+                where S: SerializerTrait
+                {
+                    #with_fn(self.0, serializer)
                 }
-                let field = Sym::new(#serialize_str).to_ivar();
-                serialize_ivars.serialize_entry(&field, &__SerializeField(&self.#field_ident))?;
             }
+            serialize_ivars.serialize_entry(&field, &__SerializeField(&self.#field_ident))?;
         }
     } else if field.byte_string.is_present() {
+        let byte_string = util::byte_string_to(&field_ty, &quote! { self.#field_ident });
         quote! {
-            let field = Sym::new(#serialize_str).to_ivar();
-            let ty = _alox_48::SerializeByteString(self.#field_ident.as_ref());
+            let ty = #byte_string;
             serialize_ivars.serialize_entry(&field, &ty)?;
         }
     } else {
         quote! {
-            let field = Sym::new(#serialize_str).to_ivar();
             serialize_ivars.serialize_entry(&field, &self.#field_ident)?;
         }
+    };
+
+    let serialize_entry = if catch_all {
+        quote! {
+            if let Some(__v) = __catch_all.get(field) {
+                serialize_ivars.serialize_entry(&field, __v)?;
+            } else {
+                #serialize_entry
+            }
+        }
+    } else {
+        serialize_entry
+    };
+
+    let serialize_entry = if field.skip_serializing_if.is_some() {
+        let skip_ident = skip_ident(field);
+        quote! {
+            if !#skip_ident {
+                #serialize_entry
+            }
+        }
+    } else {
+        serialize_entry
+    };
+
+    // struct members have no '@'
+    let serialize_str = if reciever.ruby_struct.is_present() {
+        field_str(field)
+    } else {
+        ivar_str(field)
+    };
+
+    quote! {
+        {
+            let field = Sym::new(#serialize_str);
+            #serialize_entry
+        }
     }
 }
 
+fn skip_ident(field: &FieldReciever) -> Ident {
+    let field_ident = field.ident.as_ref().unwrap();
+    Ident::new(&format!("__skip_{field_ident}"), field_ident.span())
+}
+
 fn parse_enum(_reciever: &TypeReciever, _variants: &[VariantReciever]) -> TokenStream {
     quote! {
         compile_error!("Derive macro does not currently automatic deserialize impls for enums!")
@@ -0,0 +1,80 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tests for the derive macros, which expand them without compiling the result.
+//!
+//! - `snapshots` compares the expansion of some representative types against the files in `snapshots/`.
+//!   Run the tests with `ALOX_48_BLESS=1` to update those files after changing what the macros generate.
+//!
+//! Invalid attributes are tested by compiling them with trybuild, see `tests/ui`.
+//! What the generated code does at runtime is tested in alox-48, alongside its other tests.
+
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use syn::DeriveInput;
+
+mod snapshots;
+
+fn expand_deserialize(input: &DeriveInput) -> TokenStream {
+    crate::de::derive_inner(input)
+}
+
+fn expand_serialize(input: &DeriveInput) -> TokenStream {
+    crate::ser::derive_inner(input)
+}
+
+/// Lay out tokens on multiple lines, so snapshots and failures can be read (and diffed).
+///
+/// Statements and the contents of braces each go on their own line. Everything else is left as `TokenStream` prints it.
+fn pretty(tokens: TokenStream) -> String {
+    let mut out = String::new();
+    write_tokens(&mut out, tokens, 0);
+    out.trim_end().to_string() + "\n"
+}
+
+fn write_tokens(out: &mut String, tokens: TokenStream, indent: usize) {
+    let mut line = TokenStream::new();
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                write_line(out, &std::mem::take(&mut line));
+                out.push_str(" {");
+                newline(out, indent + 1);
+                write_tokens(out, group.stream(), indent + 1);
+                newline(out, indent);
+                out.push('}');
+                newline(out, indent);
+            }
+            TokenTree::Punct(punct) if punct.as_char() == ';' => {
+                write_line(out, &std::mem::take(&mut line));
+                out.push(';');
+                newline(out, indent);
+            }
+            token => line.extend([token]),
+        }
+    }
+    write_line(out, &line);
+}
+
+fn write_line(out: &mut String, line: &TokenStream) {
+    if line.is_empty() {
+        return;
+    }
+    let starts_line = out.is_empty() || out.ends_with(' ') || out.ends_with('\n');
+    if !starts_line {
+        out.push(' ');
+    }
+    out.push_str(&line.to_string());
+}
+
+fn newline(out: &mut String, indent: usize) {
+    // don't leave blank lines or trailing whitespace behind
+    let trimmed = out.trim_end_matches(' ').len();
+    out.truncate(trimmed);
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&"    ".repeat(indent));
+}
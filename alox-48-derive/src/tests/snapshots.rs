@@ -0,0 +1,156 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::path::PathBuf;
+
+use proc_macro2::TokenStream;
+use syn::{parse_quote, DeriveInput};
+
+use super::{expand_deserialize, expand_serialize, pretty};
+
+#[track_caller]
+fn assert_snapshot(name: &str, expansion: TokenStream) {
+    let expansion = pretty(expansion);
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "src/tests/snapshots", name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension("txt");
+
+    if std::env::var_os("ALOX_48_BLESS").is_some() {
+        std::fs::write(&path, &expansion).expect("failed to write snapshot");
+        return;
+    }
+
+    let snapshot = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        snapshot == expansion,
+        "the expansion of {name} doesn't match {}, run with ALOX_48_BLESS=1 to update it:\n{expansion}",
+        path.display()
+    );
+}
+
+fn plain() -> DeriveInput {
+    parse_quote! {
+        struct Actor {
+            name: String,
+            level: i32,
+        }
+    }
+}
+
+fn field_attributes() -> DeriveInput {
+    parse_quote! {
+        #[marshal(class = "RPG::Actor", enforce_class)]
+        struct Actor {
+            #[marshal(rename = "@nickname")]
+            name: String,
+            #[marshal(default)]
+            level: i32,
+            #[marshal(default = "default_class", skip_serializing_if = "is_default_class")]
+            class_id: i32,
+            #[marshal(skip)]
+            cache: Vec<i32>,
+            #[marshal(byte_string)]
+            face_name: Box<[u8]>,
+            #[marshal(with = "color")]
+            color: Color,
+        }
+    }
+}
+
+fn generic() -> DeriveInput {
+    parse_quote! {
+        #[marshal(deny_unknown_fields)]
+        struct Table<'a, T> {
+            name: &'a str,
+            data: Vec<T>,
+        }
+    }
+}
+
+fn transparent() -> DeriveInput {
+    parse_quote! {
+        #[marshal(transparent)]
+        struct Id(i32);
+    }
+}
+
+fn conversion() -> DeriveInput {
+    parse_quote! {
+        #[marshal(from = "String", into = "String")]
+        struct Name(String);
+    }
+}
+
+#[test]
+fn de_plain() {
+    assert_snapshot("de_plain", expand_deserialize(&plain()));
+}
+
+#[test]
+fn de_field_attributes() {
+    assert_snapshot(
+        "de_field_attributes",
+        expand_deserialize(&field_attributes()),
+    );
+}
+
+#[test]
+fn de_generic() {
+    assert_snapshot("de_generic", expand_deserialize(&generic()));
+}
+
+#[test]
+fn de_transparent() {
+    assert_snapshot("de_transparent", expand_deserialize(&transparent()));
+}
+
+#[test]
+fn de_conversion() {
+    assert_snapshot("de_conversion", expand_deserialize(&conversion()));
+}
+
+#[test]
+fn de_enum() {
+    let input = parse_quote! {
+        enum Command {
+            #[marshal(class = "RPG::MoveCommand")]
+            Move(MoveCommand),
+            Wait(Wait),
+            #[marshal(other)]
+            Other(Value),
+        }
+    };
+    assert_snapshot("de_enum", expand_deserialize(&input));
+}
+
+#[test]
+fn ser_plain() {
+    assert_snapshot("ser_plain", expand_serialize(&plain()));
+}
+
+#[test]
+fn ser_field_attributes() {
+    assert_snapshot(
+        "ser_field_attributes",
+        expand_serialize(&field_attributes()),
+    );
+}
+
+#[test]
+fn ser_generic() {
+    assert_snapshot("ser_generic", expand_serialize(&generic()));
+}
+
+#[test]
+fn ser_transparent() {
+    assert_snapshot("ser_transparent", expand_serialize(&transparent()));
+}
+
+#[test]
+fn ser_conversion() {
+    assert_snapshot("ser_conversion", expand_serialize(&conversion()));
+}
@@ -3,3 +3,418 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use itertools::Itertools;
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::Type;
+
+use super::{FieldReciever, TypeReciever};
+
+/// Which impl generics are being built for.
+#[derive(Clone, Copy)]
+enum Trait {
+    Serialize,
+    Deserialize,
+}
+
+/// A piece of an `expecting` string, split up by [`expand_expecting`].
+pub enum Expecting {
+    Text(String),
+    /// `{class}`, which may only be known at runtime.
+    Class,
+}
+
+/// Expand the placeholders in an `expecting` string.
+///
+/// `{fields}` expands to a list of the field names, and `{class}` is left for the caller to fill in.
+/// Literal braces can be written as `{{` and `}}`.
+pub fn expand_expecting(text: &str, fields: &[String]) -> Result<Vec<Expecting>, String> {
+    let mut parts = vec![];
+    let mut expanded = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let rest = chars.as_str();
+                if let Some(rest) = rest.strip_prefix('{') {
+                    expanded.push('{');
+                    chars = rest.chars();
+                    continue;
+                }
+
+                let Some(end) = rest.find('}') else {
+                    return Err(
+                        "unclosed `{` in `expecting`, use `{{` for a literal brace".to_string()
+                    );
+                };
+                match &rest[..end] {
+                    "class" => {
+                        parts.push(Expecting::Text(std::mem::take(&mut expanded)));
+                        parts.push(Expecting::Class);
+                    }
+                    "fields" => {
+                        expanded.push_str(&fields.iter().map(|f| format!("`{f}`")).join(", "));
+                    }
+                    placeholder => {
+                        return Err(format!(
+                            "unknown placeholder `{{{placeholder}}}` in `expecting`, expected `{{class}}` or `{{fields}}`"
+                        ))
+                    }
+                }
+                chars = rest[end + 1..].chars();
+            }
+            '}' => {
+                if chars.as_str().starts_with('}') {
+                    chars.next();
+                }
+                expanded.push('}');
+            }
+            c => expanded.push(c),
+        }
+    }
+
+    parts.push(Expecting::Text(expanded));
+    parts.retain(|part| !matches!(part, Expecting::Text(text) if text.is_empty()));
+    Ok(parts)
+}
+
+/// A standard container or wrapper that field attributes can see through.
+pub enum Container {
+    Vec,
+    Option,
+    Instance,
+    Extended,
+    /// A `HashMap`, `BTreeMap`, or `IndexMap`, which is seen through to its values.
+    Map,
+}
+
+/// Figure out if a type is a [`Container`], going by its name, returning it and the type it contains.
+///
+/// `Vec<u8>` isn't a container, since it's how byte strings are usually stored.
+/// For maps, the contained type is the value type.
+///
+/// This is purely syntactic, so type aliases and fully qualified paths to other types named `Vec` will confuse it.
+pub fn container_of(ty: &Type) -> Option<(Container, &Type)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    let types = arguments
+        .args
+        .iter()
+        .map(|arg| match arg {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let container = match (segment.ident.to_string().as_str(), types.as_slice()) {
+        ("Vec", [inner]) if is_u8(inner) => return None,
+        ("Vec", [inner]) => (Container::Vec, *inner),
+        ("Option", [inner]) => (Container::Option, *inner),
+        ("Instance", [inner]) => (Container::Instance, *inner),
+        ("Extended", [inner]) => (Container::Extended, *inner),
+        ("HashMap" | "BTreeMap" | "IndexMap", [_, value] | [_, value, _]) => {
+            (Container::Map, *value)
+        }
+        _ => return None,
+    };
+    Some(container)
+}
+
+/// Replace the value type of a map type, keeping its key type and hasher.
+fn with_map_value(ty: &Type, value: &TokenStream) -> TokenStream {
+    let mut ty = ty.clone();
+    if let Type::Path(path) = &mut ty {
+        if let Some(syn::PathArguments::AngleBracketed(arguments)) = path
+            .path
+            .segments
+            .last_mut()
+            .map(|segment| &mut segment.arguments)
+        {
+            arguments.args[1] = syn::parse_quote! { #value };
+        }
+    }
+    ty.into_token_stream()
+}
+
+/// The path of a map type with its arguments left to inference, like `HashMap<_, _>`.
+fn inferred_map(ty: &Type) -> TokenStream {
+    let mut ty = ty.clone();
+    if let Type::Path(path) = &mut ty {
+        if let Some(segment) = path.path.segments.last_mut() {
+            segment.arguments = syn::PathArguments::AngleBracketed(syn::parse_quote! { <_, _> });
+        }
+    }
+    ty.into_token_stream()
+}
+
+fn is_u8(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("u8"))
+}
+
+/// The type a `byte_string` field of type `ty` is deserialized as, with every byte string replaced by a `ByteString`.
+pub fn byte_string_type(ty: &Type) -> TokenStream {
+    match container_of(ty) {
+        Some((Container::Vec, inner)) => {
+            let inner = byte_string_type(inner);
+            quote! { Vec<#inner> }
+        }
+        Some((Container::Option, inner)) => {
+            let inner = byte_string_type(inner);
+            quote! { Option<#inner> }
+        }
+        Some((Container::Instance, inner)) => {
+            let inner = byte_string_type(inner);
+            quote! { _alox_48::Instance<#inner> }
+        }
+        Some((Container::Extended, inner)) => {
+            let inner = byte_string_type(inner);
+            quote! { _alox_48::Extended<#inner> }
+        }
+        Some((Container::Map, inner)) => with_map_value(ty, &byte_string_type(inner)),
+        None => quote! { _alox_48::SerializeByteString<'de> },
+    }
+}
+
+/// Convert `value`, which has the type [`byte_string_type`] returns, into a `ty`.
+pub fn byte_string_from(ty: &Type, value: &TokenStream) -> TokenStream {
+    match container_of(ty) {
+        Some((Container::Vec, inner)) => {
+            let inner = byte_string_from(inner, &quote! { v });
+            quote! { #value.into_iter().map(|v| #inner).collect() }
+        }
+        Some((Container::Option, inner)) => {
+            let inner = byte_string_from(inner, &quote! { v });
+            quote! { #value.map(|v| #inner) }
+        }
+        Some((Container::Instance, inner)) => {
+            let inner = byte_string_from(inner, &quote! { v.value });
+            quote! {{
+                let v = #value;
+                _alox_48::Instance { value: #inner, fields: v.fields }
+            }}
+        }
+        Some((Container::Extended, inner)) => {
+            let inner = byte_string_from(inner, &quote! { v.value });
+            quote! {{
+                let v = #value;
+                _alox_48::Extended { module: v.module, value: #inner }
+            }}
+        }
+        Some((Container::Map, inner)) => {
+            let inner = byte_string_from(inner, &quote! { v });
+            quote! { #value.into_iter().map(|(k, v)| (k, #inner)).collect() }
+        }
+        None => quote! { From::from(#value.0) },
+    }
+}
+
+/// Borrow `value`, a place of type `ty`, as something that serializes every byte string in it as a ruby string.
+pub fn byte_string_to(ty: &Type, value: &TokenStream) -> TokenStream {
+    match container_of(ty) {
+        Some((Container::Vec, inner)) => {
+            let inner = byte_string_to(inner, &quote! { v });
+            quote! { #value.iter().map(|v| #inner).collect::<Vec<_>>() }
+        }
+        Some((Container::Option, inner)) => {
+            let inner = byte_string_to(inner, &quote! { v });
+            quote! { #value.as_ref().map(|v| #inner) }
+        }
+        Some((Container::Instance, inner)) => {
+            let inner = byte_string_to(inner, &quote! { #value.value });
+            quote! {
+                _alox_48::Instance { value: #inner, fields: #value.fields.clone() }
+            }
+        }
+        Some((Container::Extended, inner)) => {
+            let inner = byte_string_to(inner, &quote! { #value.value });
+            quote! {
+                _alox_48::Extended { module: #value.module.clone(), value: #inner }
+            }
+        }
+        Some((Container::Map, inner)) => {
+            let inner = byte_string_to(inner, &quote! { v });
+            let map = inferred_map(ty);
+            quote! { #value.iter().map(|(k, v)| (k, #inner)).collect::<#map>() }
+        }
+        None => quote! { _alox_48::SerializeByteString(#value.as_ref()) },
+    }
+}
+
+/// Find the field that a `transparent` type (de)serializes as, returning it as an expression for accessing it.
+///
+/// Transparent types must be structs with exactly one field.
+pub fn transparent_member(reciever: &TypeReciever) -> Result<TokenStream, TokenStream> {
+    if reciever.from_type.is_some()
+        || reciever.into_type.is_some()
+        || reciever.try_from_type.is_some()
+        || reciever.try_into_type.is_some()
+    {
+        return Err(quote! {
+            compile_error!("Cannot use `transparent` with `from`, `into`, `try_from`, or `try_into`")
+        });
+    }
+
+    let darling::ast::Data::Struct(fields) = &reciever.data else {
+        return Err(quote! { compile_error!("`transparent` can only be used on structs") });
+    };
+    let Ok(field) = fields.iter().exactly_one() else {
+        return Err(quote! {
+            compile_error!("`transparent` structs must have exactly one field")
+        });
+    };
+
+    Ok(field.ident.as_ref().map_or_else(
+        || syn::Index::from(0).into_token_stream(),
+        ToTokens::into_token_stream,
+    ))
+}
+
+/// Check that a `ruby_struct` type can be (de)serialized as a ruby struct.
+///
+/// Ruby structs have a fixed list of members, so every field with a name has to be there.
+pub fn check_ruby_struct(reciever: &TypeReciever) -> Result<(), TokenStream> {
+    let darling::ast::Data::Struct(fields) = &reciever.data else {
+        return Err(quote! { compile_error!("`ruby_struct` can only be used on structs") });
+    };
+    if !fields.is_struct() {
+        return Err(quote! { compile_error!("`ruby_struct` structs must have named fields") });
+    }
+    if fields.iter().any(|f| f.catch_all.is_present()) {
+        return Err(quote! { compile_error!("Cannot use `catch_all` with `ruby_struct`") });
+    }
+    if fields.iter().any(|f| f.skip_serializing_if.is_some()) {
+        return Err(
+            quote! { compile_error!("Cannot use `skip_serializing_if` with `ruby_struct`") },
+        );
+    }
+    Ok(())
+}
+
+/// The kinds accepted by the `expect` field attribute, which are variants of `alox_48::de::expect::ValueKind`.
+const VALUE_KINDS: &[&str] = &[
+    "Nil",
+    "Bool",
+    "Integer",
+    "Float",
+    "Hash",
+    "Array",
+    "String",
+    "Symbol",
+    "Regex",
+    "Object",
+    "Struct",
+    "Class",
+    "Module",
+    "UserData",
+    "UserMarshal",
+    "Data",
+];
+
+/// Build the `Expectation` for a field with `expect` or `expect_class`, if it has either.
+pub fn expectation(field: &FieldReciever) -> Result<Option<TokenStream>, TokenStream> {
+    if field.expect.is_none() && field.expect_class.is_none() {
+        return Ok(None);
+    }
+    if field.byte_string.is_present()
+        || field.deserialize_with_fn.is_some()
+        || field.with_module.is_some()
+    {
+        return Err(quote! {
+            compile_error!("Cannot use `expect` or `expect_class` with `byte_string`, `deserialize_with`, or `with`")
+        });
+    }
+
+    let kind = match field.expect.as_ref() {
+        Some(kind) if VALUE_KINDS.contains(&kind.value().as_str()) => {
+            let kind = syn::Ident::new(&kind.value(), kind.span());
+            Some(quote! { .kind(_alox_48::de::expect::ValueKind::#kind) })
+        }
+        Some(kind) => {
+            let message = format!(
+                "unknown kind `{}` in `expect`, expected one of {}",
+                kind.value(),
+                VALUE_KINDS.iter().map(|k| format!("`{k}`")).join(", ")
+            );
+            return Err(quote! { compile_error!(#message) });
+        }
+        None => None,
+    };
+    let class = field
+        .expect_class
+        .as_ref()
+        .map(|class| quote! { .class(Sym::new(#class)) });
+
+    Ok(Some(quote! {
+        _alox_48::de::expect::Expectation::new() #kind #class
+    }))
+}
+
+/// Generics for a `Serialize` impl.
+///
+/// Type parameters are bounded by `Serialize`, unless the `bound` attribute replaces the bounds.
+pub fn ser_generics(reciever: &TypeReciever) -> Result<syn::Generics, TokenStream> {
+    add_bounds(reciever, reciever.generics.clone(), Trait::Serialize)
+}
+
+/// Generics for a `Deserialize<'de>` impl.
+///
+/// This adds the `'de` lifetime, which outlives (and is outlived by) every other lifetime.
+/// Type parameters are bounded by `Deserialize<'de>`, unless the `bound` attribute replaces the bounds.
+pub fn de_generics(reciever: &TypeReciever) -> Result<syn::Generics, TokenStream> {
+    let de_lifetime = syn::Lifetime::new("'de", proc_macro2::Span::call_site());
+
+    let mut generics = reciever.generics.clone();
+    // i have no idea why we need to specify this here but rust gets *really* unhappy if we don't
+    for lifetime in generics.lifetimes_mut() {
+        lifetime.bounds.push(de_lifetime.clone());
+    }
+    let mut de_param = syn::LifetimeParam::new(de_lifetime);
+    de_param.bounds = reciever
+        .generics
+        .lifetimes()
+        .map(|l| l.lifetime.clone())
+        .collect();
+    generics.params.insert(0, de_param.into());
+
+    add_bounds(reciever, generics, Trait::Deserialize)
+}
+
+fn add_bounds(
+    reciever: &TypeReciever,
+    mut generics: syn::Generics,
+    bound_trait: Trait,
+) -> Result<syn::Generics, TokenStream> {
+    let bound = reciever.bound.as_ref().and_then(|bound| match bound_trait {
+        Trait::Serialize => bound.serialize.as_ref(),
+        Trait::Deserialize => bound.deserialize.as_ref(),
+    });
+
+    let predicates: Vec<syn::WherePredicate> = if let Some(bound) = bound {
+        let clause = syn::parse_str::<syn::WhereClause>(&format!("where {}", bound.value()))
+            .map_err(|e| e.to_compile_error())?;
+        clause.predicates.into_iter().collect()
+    } else {
+        let bound_trait = match bound_trait {
+            Trait::Serialize => quote! { Serialize },
+            Trait::Deserialize => quote! { Deserialize<'de> },
+        };
+        reciever
+            .generics
+            .type_params()
+            .map(|param| {
+                let ident = &param.ident;
+                syn::parse_quote! { #ident: #bound_trait }
+            })
+            .collect()
+    };
+
+    generics.make_where_clause().predicates.extend(predicates);
+    Ok(generics)
+}
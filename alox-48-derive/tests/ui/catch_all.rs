@@ -0,0 +1,18 @@
+use alox_48::RbFields;
+
+#[derive(alox_48::Deserialize, alox_48::Serialize)]
+struct Actor {
+    #[marshal(catch_all)]
+    a: RbFields,
+    #[marshal(catch_all)]
+    b: RbFields,
+}
+
+#[derive(alox_48::Deserialize)]
+#[marshal(deny_unknown_fields)]
+struct Enemy {
+    #[marshal(catch_all)]
+    rest: RbFields,
+}
+
+fn main() {}
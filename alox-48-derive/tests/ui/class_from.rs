@@ -0,0 +1,13 @@
+#[derive(alox_48::Serialize)]
+#[marshal(class = "RPG::Actor", class_from = "Self::class_name")]
+struct Actor {
+    class_name: String,
+}
+
+#[derive(alox_48::Deserialize)]
+#[marshal(enforce_class, class_from = "Self::class_name")]
+struct Enemy {
+    class_name: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+#[derive(alox_48::Deserialize)]
+#[marshal(from = "String", try_from = "String")]
+struct From(String);
+
+#[derive(alox_48::Serialize)]
+#[marshal(into = "String", try_into = "String")]
+struct Into(String);
+
+fn main() {}
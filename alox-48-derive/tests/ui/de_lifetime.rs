@@ -0,0 +1,6 @@
+#[derive(alox_48::Deserialize)]
+struct Borrowed<'de> {
+    name: &'de str,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+#[derive(alox_48::Deserialize)]
+struct Actor {
+    #[marshal(default_on_error)]
+    level: i32,
+}
+
+#[derive(alox_48::Deserialize)]
+struct Enemy {
+    #[marshal(default, default_on_error, byte_string)]
+    name: Vec<u8>,
+}
+
+fn main() {}
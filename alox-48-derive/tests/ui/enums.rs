@@ -0,0 +1,27 @@
+#[derive(alox_48::Deserialize)]
+enum Event {
+    #[marshal(other)]
+    A,
+    #[marshal(other)]
+    B,
+}
+
+#[derive(alox_48::Deserialize)]
+enum Command {
+    Move { x: i32 },
+}
+
+#[derive(alox_48::Deserialize)]
+enum Page {
+    #[marshal(other)]
+    Other(i32, i32),
+}
+
+#[derive(alox_48::Serialize)]
+enum Route {
+    Move(Move),
+}
+
+struct Move;
+
+fn main() {}
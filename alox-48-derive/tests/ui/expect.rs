@@ -0,0 +1,13 @@
+#[derive(alox_48::Deserialize)]
+struct Actor {
+    #[marshal(expect = "Array", byte_string)]
+    name: Vec<u8>,
+}
+
+#[derive(alox_48::Deserialize)]
+struct Enemy {
+    #[marshal(expect = "Number")]
+    level: i32,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+#[derive(alox_48::Deserialize)]
+#[marshal(expecting = "an {actor}")]
+struct Actor {
+    level: i32,
+}
+
+#[derive(alox_48::Deserialize)]
+#[marshal(expecting = "an {class")]
+struct Enemy {
+    level: i32,
+}
+
+fn main() {}
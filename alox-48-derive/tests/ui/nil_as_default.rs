@@ -0,0 +1,13 @@
+#[derive(alox_48::Deserialize)]
+struct Actor {
+    #[marshal(nil_as_default)]
+    level: i32,
+}
+
+#[derive(alox_48::Deserialize)]
+struct Enemy {
+    #[marshal(default, nil_as_default, byte_string)]
+    name: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,32 @@
+use alox_48::RbFields;
+
+#[derive(alox_48::Deserialize)]
+#[marshal(ruby_struct)]
+enum Shape {
+    Point(Point),
+}
+
+#[derive(alox_48::Serialize)]
+#[marshal(ruby_struct)]
+struct Point(i32);
+
+#[derive(alox_48::Deserialize)]
+#[marshal(ruby_struct)]
+struct Vector {
+    x: i32,
+    #[marshal(catch_all)]
+    rest: RbFields,
+}
+
+#[derive(alox_48::Serialize)]
+#[marshal(ruby_struct)]
+struct Size {
+    #[marshal(skip_serializing_if = "is_zero")]
+    width: i32,
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+#[derive(alox_48::Serialize)]
+#[marshal(transparent, from = "String")]
+struct Name(String);
+
+#[derive(alox_48::Deserialize)]
+#[marshal(transparent)]
+enum Nickname {
+    A(String),
+}
+
+#[derive(alox_48::Deserialize, alox_48::Serialize)]
+#[marshal(transparent)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn main() {}
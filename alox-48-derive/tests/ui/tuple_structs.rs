@@ -0,0 +1,4 @@
+#[derive(alox_48::Deserialize, alox_48::Serialize)]
+struct Point(i32, i32);
+
+fn main() {}
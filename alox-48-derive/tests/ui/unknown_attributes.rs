@@ -0,0 +1,19 @@
+#[derive(alox_48::Deserialize)]
+#[marshal(enforce_clas)]
+struct Actor {
+    level: i32,
+}
+
+#[derive(alox_48::Serialize)]
+struct Enemy {
+    #[marshal(renam = "name")]
+    level: i32,
+}
+
+#[derive(alox_48::Deserialize)]
+#[marshal(deny_unknown_fields = "some")]
+struct Troop {
+    level: i32,
+}
+
+fn main() {}
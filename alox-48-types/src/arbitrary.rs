@@ -0,0 +1,174 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`Arbitrary`] impls, for fuzzing.
+//!
+//! Generated values are always something marshal can represent,
+//! so serializing them never fails and deserializing the result gives back the same bytes.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{
+    byte_buf, Instance, Object, RbArray, RbFields, RbHash, RbString, RbStruct, Symbol, Userdata,
+    Value,
+};
+
+/// How deeply generated values can nest.
+///
+/// Values past this depth are always leaves (nil, bools, numbers, strings, and symbols).
+/// This is well under the default depth limit of alox-48's deserializer.
+const MAX_DEPTH: usize = 32;
+
+impl<'a> Arbitrary<'a> for Symbol {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // symbols are always utf8
+        <&str>::arbitrary(u).map(Symbol::from)
+    }
+}
+
+impl<'a> Arbitrary<'a> for RbString {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Vec::arbitrary(u).map(RbString::from)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Userdata {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Userdata {
+            class: class(u)?,
+            data: byte_buf(u.arbitrary()?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Object {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        object(u, 0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for RbStruct {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        rb_struct(u, 0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        value(u, 0)
+    }
+}
+
+// ruby refuses to load class names like `#<Class:0x000055d5>`, so don't generate them
+fn class(u: &mut Unstructured<'_>) -> Result<Symbol> {
+    let name = <&str>::arbitrary(u)?;
+    Ok(name.trim_start_matches('#').into())
+}
+
+fn leaf(u: &mut Unstructured<'_>) -> Result<Value> {
+    Ok(match u.int_in_range(0..=5)? {
+        0 => Value::Nil,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => Value::Integer(u.arbitrary()?),
+        3 => Value::Float(u.arbitrary()?),
+        4 => Value::String(u.arbitrary()?),
+        _ => Value::Symbol(u.arbitrary()?),
+    })
+}
+
+fn value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    if depth >= MAX_DEPTH || u.is_empty() {
+        return leaf(u);
+    }
+    let depth = depth + 1;
+
+    Ok(match u.int_in_range(0..=14)? {
+        0 => Value::Array(array(u, depth)?),
+        1 => Value::Hash(hash(u, depth)?),
+        2 => Value::HashWithDefault {
+            hash: hash(u, depth)?,
+            default: Box::new(value(u, depth)?),
+        },
+        3 => Value::Userdata(u.arbitrary()?),
+        4 => Value::Object(object(u, depth)?),
+        5 => {
+            let value = value(u, depth)?;
+            let fields = fields(u, depth)?;
+            // an instance without fields is serialized as just its value,
+            // so it would reload as a different value (and collide with it as a hash key)
+            if fields.is_empty() {
+                value
+            } else {
+                Value::Instance(Instance {
+                    value: Box::new(value),
+                    fields,
+                })
+            }
+        }
+        6 => Value::Regex {
+            data: u.arbitrary()?,
+            flags: u.arbitrary()?,
+        },
+        7 => Value::RbStruct(rb_struct(u, depth)?),
+        8 => Value::Class(class(u)?),
+        9 => Value::Module(class(u)?),
+        10 => Value::Extended {
+            module: class(u)?,
+            value: Box::new(value(u, depth)?),
+        },
+        11 => Value::UserClass {
+            class: class(u)?,
+            value: Box::new(value(u, depth)?),
+        },
+        12 => Value::UserMarshal {
+            class: class(u)?,
+            value: Box::new(value(u, depth)?),
+        },
+        13 => Value::Data {
+            class: class(u)?,
+            value: Box::new(value(u, depth)?),
+        },
+        _ => leaf(u)?,
+    })
+}
+
+fn array(u: &mut Unstructured<'_>, depth: usize) -> Result<RbArray> {
+    let mut array = RbArray::new();
+    while !u.is_empty() && u.arbitrary()? {
+        array.push(value(u, depth)?);
+    }
+    Ok(array)
+}
+
+fn hash(u: &mut Unstructured<'_>, depth: usize) -> Result<RbHash> {
+    let mut hash = RbHash::new();
+    while !u.is_empty() && u.arbitrary()? {
+        hash.insert(value(u, depth)?, value(u, depth)?);
+    }
+    Ok(hash)
+}
+
+fn fields(u: &mut Unstructured<'_>, depth: usize) -> Result<RbFields> {
+    let mut fields = RbFields::new();
+    while !u.is_empty() && u.arbitrary()? {
+        fields.insert(u.arbitrary()?, value(u, depth)?);
+    }
+    Ok(fields)
+}
+
+fn object(u: &mut Unstructured<'_>, depth: usize) -> Result<Object> {
+    Ok(Object {
+        class: class(u)?,
+        fields: fields(u, depth)?,
+    })
+}
+
+fn rb_struct(u: &mut Unstructured<'_>, depth: usize) -> Result<RbStruct> {
+    Ok(RbStruct {
+        class: class(u)?,
+        fields: fields(u, depth)?,
+    })
+}
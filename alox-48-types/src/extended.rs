@@ -0,0 +1,61 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::Symbol;
+
+/// A type representing a ruby object that has been extended with a module (with `Object#extend`).
+///
+/// This is the typed counterpart to [`Value::Extended`](crate::Value::Extended).
+/// An object extended with several modules can be represented by nesting this type.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Default, Debug, Clone)]
+pub struct Extended<T> {
+    /// The module the value was extended with.
+    pub module: Symbol,
+    /// The inner value.
+    pub value: T,
+}
+
+impl<T> Extended<T> {
+    /// Create a value extended with `module`.
+    pub fn new(module: impl Into<Symbol>, value: T) -> Self {
+        Self {
+            module: module.into(),
+            value,
+        }
+    }
+
+    /// Take the inner value, forgetting the module.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Splits this value into its constituants.
+    pub fn into_parts(self) -> (Symbol, T) {
+        (self.module, self.value)
+    }
+
+    /// Convert the inner value with `f`, keeping the module as-is.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Extended<U> {
+        Extended {
+            module: self.module,
+            value: f(self.value),
+        }
+    }
+}
+
+impl<T> std::ops::Deref for Extended<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> std::ops::DerefMut for Extended<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
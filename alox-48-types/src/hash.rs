@@ -0,0 +1,133 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use indexmap::map::Entry;
+
+use crate::{RbHash, Value};
+
+/// How to resolve a key that is present in both hashes during [`RbHashExt::merge_preserving_order`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MergeStrategy {
+    /// Keep the value that was already in the hash.
+    KeepLeft,
+    /// Replace the value with the one from the other hash.
+    KeepRight,
+    /// If both values are hashes, merge them (using this strategy). Otherwise, behave like [`MergeStrategy::KeepRight`].
+    Recurse,
+}
+
+/// Extra methods for [`RbHash`].
+///
+/// [`RbHash`] is an [`indexmap::IndexMap`], so it already has `entry`, `get`, etc.
+/// This trait provides operations that depend on ruby semantics.
+///
+/// [`Value`] is [`Ord`], so `sort_keys` works too. Use [`Value::normalize`] to sort nested hashes as well.
+pub trait RbHashExt {
+    /// Merge `other` into this hash.
+    ///
+    /// Keys already in this hash keep their position, regardless of the strategy.
+    /// Keys only present in `other` are appended in the order they appear in `other`.
+    fn merge_preserving_order(&mut self, other: RbHash, strategy: MergeStrategy);
+}
+
+impl RbHashExt for RbHash {
+    fn merge_preserving_order(&mut self, other: RbHash, strategy: MergeStrategy) {
+        for (key, value) in other {
+            match self.entry(key) {
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+                Entry::Occupied(mut entry) => match (strategy, entry.get_mut(), value) {
+                    (MergeStrategy::KeepLeft, _, _) => {}
+                    (MergeStrategy::Recurse, Value::Hash(left), Value::Hash(right)) => {
+                        left.merge_preserving_order(right, strategy);
+                    }
+                    (_, left, right) => *left = right,
+                },
+            }
+        }
+    }
+}
+
+impl Value {
+    /// Returns the hash this value holds, looking through wrappers like [`Value::UserClass`] and [`Value::Instance`].
+    pub fn inner_hash(&self) -> Option<&RbHash> {
+        match self {
+            Value::Hash(hash) | Value::HashWithDefault { hash, .. } => Some(hash),
+            Value::Instance(instance) => instance.value.inner_hash(),
+            Value::Extended { value, .. }
+            | Value::UserClass { value, .. }
+            | Value::UserMarshal { value, .. } => value.inner_hash(),
+            _ => None,
+        }
+    }
+
+    /// Mutable version of [`Value::inner_hash`].
+    pub fn inner_hash_mut(&mut self) -> Option<&mut RbHash> {
+        match self {
+            Value::Hash(hash) | Value::HashWithDefault { hash, .. } => Some(hash),
+            Value::Instance(instance) => instance.value.inner_hash_mut(),
+            Value::Extended { value, .. }
+            | Value::UserClass { value, .. }
+            | Value::UserMarshal { value, .. } => value.inner_hash_mut(),
+            _ => None,
+        }
+    }
+
+    /// Gets the entry for `key` in the hash this value holds.
+    ///
+    /// Returns `None` if this value is not a hash (see [`Value::inner_hash`]).
+    pub fn hash_entry(&mut self, key: impl Into<Value>) -> Option<Entry<'_, Value, Value>> {
+        self.inner_hash_mut().map(|hash| hash.entry(key.into()))
+    }
+
+    /// Sorts the keys of every hash in this value, including hashes nested in other values and hash keys.
+    ///
+    /// Hashes compare equal regardless of their order, so this doesn't change what the value is equal to.
+    /// It only makes the order hashes are iterated (and serialized) in deterministic.
+    ///
+    /// ```
+    /// # use alox_48_types::{RbHash, Value};
+    /// let mut value = Value::Hash(RbHash::from([(2.into(), Value::Nil), (1.into(), Value::Nil)]));
+    /// value.normalize();
+    /// let keys: Vec<_> = value.inner_hash().unwrap().keys().cloned().collect();
+    /// assert_eq!(keys, [Value::from(1), Value::from(2)]);
+    /// ```
+    pub fn normalize(&mut self) {
+        match self {
+            Value::Array(array) => array.iter_mut().for_each(Value::normalize),
+            Value::Hash(hash) => normalize_hash(hash),
+            Value::HashWithDefault { hash, default } => {
+                normalize_hash(hash);
+                default.normalize();
+            }
+            Value::Object(object) => object.fields.values_mut().for_each(Value::normalize),
+            Value::RbStruct(rb_struct) => rb_struct.fields.values_mut().for_each(Value::normalize),
+            Value::Instance(instance) => {
+                instance.value.normalize();
+                instance.fields.values_mut().for_each(Value::normalize);
+            }
+            Value::Extended { value, .. }
+            | Value::UserClass { value, .. }
+            | Value::UserMarshal { value, .. }
+            | Value::Data { value, .. } => value.normalize(),
+            _ => {}
+        }
+    }
+}
+
+fn normalize_hash(hash: &mut RbHash) {
+    // keys can contain hashes too, so the map has to be rebuilt
+    *hash = std::mem::take(hash)
+        .into_iter()
+        .map(|(mut key, mut value)| {
+            key.normalize();
+            value.normalize();
+            (key, value)
+        })
+        .collect();
+    hash.sort_keys();
+}
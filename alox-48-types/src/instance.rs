@@ -0,0 +1,171 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::{Encoding, RbFields, RbString, Value};
+
+/// A type representing a ruby object with extra instance variables.
+///
+/// This works as a field type for anything that might carry instance variables (like an array or hash),
+/// so they are kept when the field is serialized again.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Instance<T> {
+    /// The inner value.
+    pub value: T,
+    /// The extra instance variables attached to this object.
+    pub fields: RbFields,
+}
+
+impl<T> Instance<T> {
+    /// Create an instance without any instance variables.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            fields: RbFields::new(),
+        }
+    }
+
+    /// Take the inner value of this instance.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Splits this instance into its constituants.
+    pub fn into_parts(self) -> (T, RbFields) {
+        (self.value, self.fields)
+    }
+
+    /// Convert the inner value with `f`, keeping the instance variables as-is.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Instance<U> {
+        Instance {
+            value: f(self.value),
+            fields: self.fields,
+        }
+    }
+
+    /// Get the instance variable `name` and convert it to a `U`.
+    ///
+    /// Returns `None` if there is no instance variable named `name`,
+    /// and `Some(Err(..))` if it couldn't be converted.
+    ///
+    /// ```
+    /// use alox_48_types::{Instance, RbString};
+    ///
+    /// let string = Instance::<RbString>::from("hello");
+    /// assert_eq!(string.get_ivar::<bool>("E"), Some(Ok(true)));
+    /// assert!(string.get_ivar::<i32>("E").unwrap().is_err());
+    /// assert!(string.get_ivar::<bool>("encoding").is_none());
+    /// ```
+    pub fn get_ivar<U>(&self, name: &str) -> Option<Result<U, U::Error>>
+    where
+        U: TryFrom<Value>,
+    {
+        self.fields.get(name).cloned().map(U::try_from)
+    }
+}
+
+impl<T> std::ops::Deref for Instance<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> std::ops::DerefMut for Instance<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl Instance<RbString> {
+    /// Return the encoding of this string, if it has one.
+    ///
+    /// This is the raw instance variable, use [`Instance::parse_encoding`] to get an [`Encoding`] instead.
+    pub fn encoding(&self) -> Option<&crate::Value> {
+        self.fields.get("E").or_else(|| self.fields.get("encoding"))
+    }
+
+    /// Returns the encoding of this string as an [`Encoding`].
+    ///
+    /// ```
+    /// use alox_48_types::{Encoding, Instance, RbString};
+    ///
+    /// let mut string = Instance::<RbString>::from("hello");
+    /// assert_eq!(string.parse_encoding(), Encoding::Utf8);
+    ///
+    /// string.set_encoding(&Encoding::Other("Shift_JIS".to_string()));
+    /// assert_eq!(string.parse_encoding().name(), "Shift_JIS");
+    /// assert!(string.get_ivar::<bool>("E").is_none());
+    /// ```
+    pub fn parse_encoding(&self) -> Encoding {
+        Encoding::from_fields(&self.fields)
+    }
+
+    /// Change the encoding of this string, without changing its data.
+    ///
+    /// See [`Encoding::apply`].
+    pub fn set_encoding(&mut self, encoding: &Encoding) {
+        encoding.apply(&mut self.fields);
+    }
+}
+
+macro_rules! utf8_enc {
+    () => {{
+        let mut f = RbFields::new();
+        f.insert("E".into(), true.into());
+
+        f
+    }};
+}
+
+impl From<String> for Instance<RbString> {
+    fn from(value: String) -> Self {
+        Self {
+            value: value.into(),
+            fields: utf8_enc!(),
+        }
+    }
+}
+
+impl From<&str> for Instance<RbString> {
+    fn from(value: &str) -> Self {
+        Self {
+            value: value.into(),
+            fields: utf8_enc!(),
+        }
+    }
+}
+
+impl<T> std::hash::Hash for Instance<T>
+where
+    T: std::hash::Hash,
+{
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+        crate::value::hash_unordered(&self.fields, state);
+    }
+}
+
+impl<T> PartialOrd for Instance<T>
+where
+    T: Ord,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Instance<T>
+where
+    T: Ord,
+{
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.value
+            .cmp(&other.value)
+            .then_with(|| crate::value::cmp_unordered(&self.fields, &other.fields))
+    }
+}
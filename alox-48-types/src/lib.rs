@@ -0,0 +1,133 @@
+#![warn(rust_2018_idioms, clippy::all, clippy::pedantic)]
+#![warn(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    clippy::panic,
+    clippy::panic_in_result_fn,
+    clippy::panicking_unwrap
+)]
+#![allow(clippy::must_use_candidate)]
+
+//! alox-48-types
+//!
+//! The data model used by [alox-48](https://crates.io/crates/alox-48), without the (de)serializer.
+//!
+//! This crate only contains the types used to represent ruby values ([`Value`], [`Object`], [`Symbol`], etc).
+//! It's intended for crates that need to pass ruby values around (for example, across a plugin boundary)
+//! without depending on the full (de)serialization framework.
+//!
+//! alox-48 re-exports everything in this crate, and provides `Serialize` and `Deserialize` impls for all of these types.
+//! If you already depend on alox-48, you should use the re-exports instead.
+//!
+//! Everything that holds instance variables or hash entries ([`Value`], [`Object`], [`Instance`], etc) needs the `value` feature,
+//! which is enabled by default and pulls in `indexmap`. Without it, only symbols, strings, and the other leaf types are available.
+
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "value")]
+use indexmap::IndexMap;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+mod extended;
+#[cfg(feature = "value")]
+mod hash;
+#[cfg(feature = "value")]
+mod instance;
+#[cfg(feature = "value")]
+mod object;
+#[cfg(feature = "value")]
+mod rb_open_struct;
+#[cfg(feature = "value")]
+mod rb_set;
+mod rb_string;
+#[cfg(feature = "value")]
+mod rb_struct;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod sym;
+mod symbol;
+mod symbol_or_string;
+mod user_marshal;
+mod userdata;
+#[cfg(feature = "value")]
+mod value;
+
+pub use extended::Extended;
+#[cfg(feature = "value")]
+pub use hash::{MergeStrategy, RbHashExt};
+#[cfg(feature = "value")]
+pub use instance::Instance;
+#[cfg(feature = "value")]
+pub use object::Object;
+#[cfg(feature = "value")]
+pub use rb_open_struct::RbOpenStruct;
+#[cfg(feature = "value")]
+pub use rb_set::RbSet;
+#[cfg(feature = "value")]
+pub use rb_string::RbStringWithIvars;
+pub use rb_string::{Encoding, RbString};
+#[cfg(feature = "value")]
+pub use rb_struct::RbStruct;
+pub use sym::Sym;
+pub use symbol::Symbol;
+pub use symbol_or_string::SymbolOrString;
+pub use user_marshal::UserMarshal;
+pub use userdata::Userdata;
+#[cfg(feature = "value")]
+pub use value::{IntegerOutOfRange, Value};
+
+#[cfg(feature = "bytes")]
+pub use bytes;
+
+/// Shorthand type alias for a ruby array.
+#[cfg(feature = "value")]
+pub type RbArray = Vec<Value>;
+/// Shorthand type alias for a ruby hash.
+#[cfg(feature = "value")]
+pub type RbHash = IndexMap<Value, Value>;
+
+/// A type alias used to represent fields of objects.
+/// All objects store a [`Symbol`] to represent the key for instance variable, and we do that here too.
+///
+/// Fields are kept in insertion order, which is the order they were deserialized in.
+/// Serializing them writes them back in that same order, so nothing is reordered on a round trip.
+/// Use `sorted_fields` on [`Object`] or [`RbStruct`] when you want a stable order by name instead.
+#[cfg(feature = "value")]
+pub type RbFields = IndexMap<Symbol, Value>;
+
+/// The buffer [`RbString`] and [`Userdata`] store their bytes in.
+///
+/// This is a [`Vec<u8>`], unless the `bytes` feature is enabled, where it's a `bytes::Bytes` instead.
+/// Cloning and slicing a `bytes::Bytes` doesn't copy its data, which helps with large strings and user data.
+///
+/// Both can be made with `ByteBuf::from(vec)`, and both dereference to `[u8]`.
+/// `bytes::Bytes` can't be changed in place though, so code that needs to modify the data should build a new buffer.
+#[cfg(not(feature = "bytes"))]
+pub type ByteBuf = Vec<u8>;
+/// The buffer [`RbString`] and [`Userdata`] store their bytes in.
+///
+/// This is a [`Vec<u8>`], unless the `bytes` feature is enabled, where it's a [`bytes::Bytes`] instead.
+/// Cloning and slicing a [`bytes::Bytes`] doesn't copy its data, which helps with large strings and user data.
+///
+/// Both can be made with `ByteBuf::from(vec)`, and both dereference to `[u8]`.
+/// [`bytes::Bytes`] can't be changed in place though, so code that needs to modify the data should build a new buffer.
+#[cfg(feature = "bytes")]
+pub type ByteBuf = bytes::Bytes;
+
+// `ByteBuf::from`, which is a no-op (that clippy would complain about) without the `bytes` feature.
+#[allow(clippy::useless_conversion)]
+pub(crate) fn byte_buf(data: Vec<u8>) -> ByteBuf {
+    data.into()
+}
+
+// `Vec::from`, which is a no-op (that clippy would complain about) without the `bytes` feature.
+#[allow(clippy::useless_conversion)]
+pub(crate) fn into_vec(data: ByteBuf) -> Vec<u8> {
+    data.into()
+}
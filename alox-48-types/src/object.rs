@@ -0,0 +1,59 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use crate::{RbFields, Symbol, Value};
+
+/// A type equivalent to ruby's `Object`.
+///
+/// alox-48 adds more helpers for objects (like `builder`, and `get` to deserialize a field) with its
+/// [`ObjectExt`](https://docs.rs/alox-48/latest/alox_48/trait.ObjectExt.html) trait.
+/// Import it from the crate root or with `use alox_48::prelude::*`.
+#[derive(PartialEq, Eq, Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Object {
+    /// This object's class.
+    pub class: Symbol,
+    /// The fields on this object.
+    pub fields: RbFields,
+}
+
+impl Object {
+    /// Splits this object into its constituants.
+    #[allow(clippy::must_use_candidate)]
+    pub fn into_parts(self) -> (Symbol, RbFields) {
+        (self.class, self.fields)
+    }
+
+    /// Returns the fields on this object, sorted by name.
+    ///
+    /// [`fields`](Self::fields) itself is in insertion order, which is what gets serialized.
+    #[must_use]
+    pub fn sorted_fields(&self) -> Vec<(&Symbol, &Value)> {
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_unstable_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        fields
+    }
+}
+
+impl std::hash::Hash for Object {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.class.hash(state);
+        crate::value::hash_unordered(&self.fields, state);
+    }
+}
+
+impl PartialOrd for Object {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Object {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.class
+            .cmp(&other.class)
+            .then_with(|| crate::value::cmp_unordered(&self.fields, &other.fields))
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use indexmap::IndexMap;
+
+use crate::{Symbol, Value};
+
+/// A type equivalent to ruby's `OpenStruct`.
+///
+/// Ruby marshals open structs with `marshal_dump`, as a hash of symbols to values.
+/// There's no special variant in [`Value`] for open structs, they are loaded as a [`Value::UserMarshal`].
+#[derive(PartialEq, Eq, Default, Debug, Clone)]
+pub struct RbOpenStruct<T = Value> {
+    /// The fields of this open struct, without a leading `@`.
+    pub table: IndexMap<Symbol, T>,
+}
+
+impl<T> RbOpenStruct<T> {
+    /// Create an open struct without any fields.
+    pub fn new() -> Self {
+        Self {
+            table: IndexMap::new(),
+        }
+    }
+
+    /// Returns the field `name`, if it exists.
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.table.get(name)
+    }
+
+    /// Take the fields of this open struct.
+    pub fn into_inner(self) -> IndexMap<Symbol, T> {
+        self.table
+    }
+}
+
+impl<T> From<IndexMap<Symbol, T>> for RbOpenStruct<T> {
+    fn from(table: IndexMap<Symbol, T>) -> Self {
+        Self { table }
+    }
+}
+
+impl<K, T> FromIterator<(K, T)> for RbOpenStruct<T>
+where
+    K: Into<Symbol>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+        Self {
+            table: iter.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use indexmap::IndexSet;
+
+use crate::Value;
+
+/// A type equivalent to ruby's `Set`.
+///
+/// Ruby marshals sets as a `Set` object with a `@hash` instance variable, mapping every item to `true`.
+/// There's no special variant in [`Value`] for sets, they are loaded as a [`Value::Object`].
+#[derive(Default, Debug, Clone)]
+pub struct RbSet<T = Value> {
+    /// The items in this set.
+    pub items: IndexSet<T>,
+}
+
+impl<T> RbSet<T> {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self {
+            items: IndexSet::new(),
+        }
+    }
+
+    /// Take the items of this set.
+    pub fn into_inner(self) -> IndexSet<T> {
+        self.items
+    }
+}
+
+// like ruby, order doesn't matter when comparing sets
+impl<T> PartialEq for RbSet<T>
+where
+    T: std::hash::Hash + Eq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.items == other.items
+    }
+}
+
+impl<T> Eq for RbSet<T> where T: std::hash::Hash + Eq {}
+
+impl<T> From<IndexSet<T>> for RbSet<T> {
+    fn from(items: IndexSet<T>) -> Self {
+        Self { items }
+    }
+}
+
+impl<T> FromIterator<T> for RbSet<T>
+where
+    T: std::hash::Hash + Eq,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            items: iter.into_iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "value")]
+use std::borrow::Cow;
+
+use crate::{byte_buf, into_vec, ByteBuf};
+#[cfg(feature = "value")]
+use crate::{RbFields, Sym, Value};
+
+/// A type equivalent to ruby's `String`.
+/// ruby strings do not have to be utf8 encoded, so this type uses a [`ByteBuf`] instead.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Default, Clone)]
+pub struct RbString {
+    /// The data of this string.
+    pub data: ByteBuf,
+}
+
+#[allow(clippy::must_use_candidate)]
+impl RbString {
+    /// Uses [`String::from_utf8_lossy`] to convert this string to rust string in a lossy manner.
+    pub fn to_string_lossy(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.data)
+    }
+
+    /// Tries to convert this string to a rust string.
+    ///
+    /// # Errors
+    /// Errors when this string is not valid utf8.
+    pub fn to_string(self) -> Result<String, std::string::FromUtf8Error> {
+        String::from_utf8(into_vec(self.data))
+    }
+
+    /// Get the length of the string data.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if the string data is empty.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the string data as a slice.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl std::fmt::Debug for RbString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RbString")
+            .field("data", &self.to_string_lossy())
+            .finish()
+    }
+}
+
+impl std::fmt::Display for RbString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_string_lossy())
+    }
+}
+
+impl<T> PartialEq<T> for RbString
+where
+    [u8]: PartialEq<T>,
+{
+    fn eq(&self, other: &T) -> bool {
+        self.as_slice().eq(other)
+    }
+}
+
+impl std::borrow::Borrow<[u8]> for RbString {
+    fn borrow(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+// `Bytes` can't be changed in place
+#[cfg(not(feature = "bytes"))]
+impl std::borrow::BorrowMut<[u8]> for RbString {
+    fn borrow_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl From<&str> for RbString {
+    fn from(value: &str) -> Self {
+        Self {
+            data: byte_buf(value.as_bytes().to_vec()),
+        }
+    }
+}
+
+impl From<String> for RbString {
+    fn from(value: String) -> Self {
+        Self {
+            data: byte_buf(value.into_bytes()),
+        }
+    }
+}
+
+impl From<&[u8]> for RbString {
+    fn from(value: &[u8]) -> Self {
+        Self {
+            data: byte_buf(value.to_vec()),
+        }
+    }
+}
+
+impl From<Vec<u8>> for RbString {
+    fn from(value: Vec<u8>) -> Self {
+        Self {
+            data: byte_buf(value),
+        }
+    }
+}
+
+/// The encoding of a ruby string.
+///
+/// Ruby stores this as an instance variable on the string, which [`Encoding::from_fields`] and [`Encoding::apply`] read and write.
+/// Marshal doesn't record whether a string is frozen (or tainted, in older rubies), so there's no equivalent for those.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// `UTF-8`, stored as `E = true`.
+    Utf8,
+    /// `US-ASCII`, stored as `E = false`.
+    UsAscii,
+    /// `ASCII-8BIT`, stored as no encoding at all.
+    Binary,
+    /// Any other encoding, stored by name.
+    Other(String),
+}
+
+impl Encoding {
+    /// Read the encoding from the instance variables of a string.
+    ///
+    /// Strings without an encoding (or with an encoding that isn't a `bool` or a string) are binary.
+    #[cfg(feature = "value")]
+    pub fn from_fields(fields: &RbFields) -> Self {
+        if let Some(&Value::Bool(utf8)) = fields.get(Sym::new("E")) {
+            return if utf8 { Self::Utf8 } else { Self::UsAscii };
+        }
+        match fields.get(Sym::new("encoding")) {
+            Some(Value::String(name)) => Self::Other(name.to_string_lossy().into_owned()),
+            _ => Self::Binary,
+        }
+    }
+
+    /// Returns the name ruby uses for this encoding.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::UsAscii => "US-ASCII",
+            Self::Binary => "ASCII-8BIT",
+            Self::Other(name) => name,
+        }
+    }
+
+    /// Store this encoding in the instance variables of a string, replacing the encoding already there.
+    ///
+    /// If the encoding is stored in the same instance variable as before it keeps its place, otherwise it goes first, like ruby writes it.
+    /// The other instance variables are left as they are.
+    #[cfg(feature = "value")]
+    pub fn apply(&self, fields: &mut RbFields) {
+        let (key, other, value) = match self {
+            Self::Utf8 => ("E", "encoding", Value::Bool(true)),
+            Self::UsAscii => ("E", "encoding", Value::Bool(false)),
+            Self::Other(name) => ("encoding", "E", Value::String(name.as_str().into())),
+            Self::Binary => {
+                fields.retain(|ivar, _| !matches!(ivar.as_str(), "E" | "encoding"));
+                return;
+            }
+        };
+
+        // a string only has one of these
+        fields.shift_remove(Sym::new(other));
+        if let Some(existing) = fields.get_mut(Sym::new(key)) {
+            *existing = value;
+            return;
+        }
+        let others = std::mem::take(fields);
+        fields.insert(key.into(), value);
+        fields.extend(others);
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A ruby string along with its instance variables, including its encoding.
+///
+/// [`RbString`] only holds the data of a string. Ruby stores the encoding of a string as an instance variable
+/// (`E` for UTF-8 and US-ASCII, `encoding` for everything else), and strings can have other instance variables too.
+/// This type keeps all of them, in their original order, so they can be written back out unchanged.
+/// Use [`RbStringWithIvars::parse_encoding`] and [`RbStringWithIvars::set_encoding`] to work with the encoding as an [`Encoding`].
+#[derive(PartialEq, Eq, Default, Clone, Debug)]
+#[cfg(feature = "value")]
+pub struct RbStringWithIvars {
+    /// The data of this string.
+    pub string: RbString,
+    /// The instance variables of this string, including its encoding.
+    pub fields: RbFields,
+}
+
+#[cfg(feature = "value")]
+impl RbStringWithIvars {
+    /// Create a new string with the given instance variables.
+    pub fn new(string: impl Into<RbString>, fields: RbFields) -> Self {
+        Self {
+            string: string.into(),
+            fields,
+        }
+    }
+
+    /// Create a new UTF-8 string.
+    pub fn new_utf8(string: &str) -> Self {
+        let mut fields = RbFields::new();
+        fields.insert("E".into(), true.into());
+        Self::new(string, fields)
+    }
+
+    /// Returns the name of the encoding of this string.
+    ///
+    /// Strings without an encoding are `ASCII-8BIT` (binary) in ruby.
+    pub fn encoding(&self) -> Cow<'_, str> {
+        if let Some(&Value::Bool(utf8)) = self.fields.get(Sym::new("E")) {
+            return Cow::Borrowed(if utf8 { "UTF-8" } else { "US-ASCII" });
+        }
+        match self.fields.get(Sym::new("encoding")) {
+            Some(Value::String(name)) => name.to_string_lossy(),
+            _ => Cow::Borrowed("ASCII-8BIT"),
+        }
+    }
+
+    /// Returns the encoding of this string as an [`Encoding`].
+    pub fn parse_encoding(&self) -> Encoding {
+        Encoding::from_fields(&self.fields)
+    }
+
+    /// Change the encoding of this string, without changing its data.
+    ///
+    /// See [`Encoding::apply`].
+    pub fn set_encoding(&mut self, encoding: &Encoding) {
+        encoding.apply(&mut self.fields);
+    }
+
+    /// Returns true if this string doesn't have an encoding.
+    pub fn is_binary(&self) -> bool {
+        self.encoding() == "ASCII-8BIT"
+    }
+
+    /// Returns this string as a rust string, if it's encoded as UTF-8 (or US-ASCII) and is valid UTF-8.
+    pub fn utf8(&self) -> Option<&str> {
+        matches!(self.encoding().as_ref(), "UTF-8" | "US-ASCII")
+            .then(|| std::str::from_utf8(&self.string.data).ok())
+            .flatten()
+    }
+}
+
+#[cfg(feature = "value")]
+impl From<RbString> for RbStringWithIvars {
+    fn from(string: RbString) -> Self {
+        Self {
+            string,
+            fields: RbFields::new(),
+        }
+    }
+}
+
+#[cfg(feature = "value")]
+impl From<&str> for RbStringWithIvars {
+    fn from(value: &str) -> Self {
+        Self::new_utf8(value)
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`serde`] impls, for storing values in other formats.
+//!
+//! [`Value`](crate::Value) is externally tagged by variant name, so `1` is `{"Integer":1}` in JSON and `nil` is `"Nil"`.
+//! Everything else (objects, structs, instances, etc) is (de)serialized like a derived impl would.
+//!
+//! Some values need a different shape than a derive would give them, to work with formats like JSON:
+//! - Symbols are strings.
+//! - Ruby strings and userdata are strings when they are valid UTF-8 and the format is human readable, and bytes otherwise.
+//! - Hashes are sequences of `[key, value]` pairs, since their keys can be any value.
+//!
+//! Floats that JSON can't represent (infinities and NaN) don't survive a round trip through it.
+
+use std::fmt;
+
+use serde::{
+    de::{Error, SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{RbHash, RbString, Symbol, Value};
+
+impl Serialize for Symbol {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // strings don't need to be owned to make a symbol
+        struct SymbolVisitor;
+
+        impl Visitor<'_> for SymbolVisitor {
+            type Value = Symbol;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a symbol")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                Ok(Symbol::from(v))
+            }
+        }
+
+        deserializer.deserialize_str(SymbolVisitor)
+    }
+}
+
+impl Serialize for RbString {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        bytes::serialize(&self.data, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RbString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        bytes::deserialize(deserializer).map(|data| RbString { data })
+    }
+}
+
+/// Byte strings, which are readable in human readable formats when they can be.
+pub(crate) mod bytes {
+    use super::{fmt, Deserializer, Error, SeqAccess, Serializer, Visitor};
+    use crate::{byte_buf, ByteBuf};
+
+    pub(crate) fn serialize<S>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match std::str::from_utf8(data) {
+            Ok(string) if serializer.is_human_readable() => serializer.serialize_str(string),
+            _ => serializer.serialize_bytes(data),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<ByteBuf, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = if deserializer.is_human_readable() {
+            deserializer.deserialize_any(BytesVisitor)
+        } else {
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        };
+        data.map(byte_buf)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter.write_str("a string or bytes")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            Ok(v.as_bytes().to_vec())
+        }
+
+        fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            Ok(v.into_bytes())
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            Ok(v)
+        }
+
+        // how JSON and friends write bytes
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            // the size hint comes from the input, so it can't be trusted too much
+            let mut data = Vec::with_capacity(seq.size_hint().unwrap_or_default().min(4096));
+            while let Some(byte) = seq.next_element()? {
+                data.push(byte);
+            }
+            Ok(data)
+        }
+    }
+}
+
+/// Hashes as sequences of `[key, value]` pairs.
+pub(crate) mod hash {
+    use super::{fmt, Deserializer, RbHash, SeqAccess, SerializeSeq, Serializer, Value, Visitor};
+
+    pub(crate) fn serialize<S>(hash: &RbHash, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(hash.len()))?;
+        for entry in hash {
+            seq.serialize_element(&entry)?;
+        }
+        seq.end()
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<RbHash, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(HashVisitor)
+    }
+
+    struct HashVisitor;
+
+    impl<'de> Visitor<'de> for HashVisitor {
+        type Value = RbHash;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter.write_str("a sequence of key value pairs")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut hash = RbHash::with_capacity(seq.size_hint().unwrap_or_default().min(4096));
+            while let Some((key, value)) = seq.next_element::<(Value, Value)>()? {
+                hash.insert(key, value);
+            }
+            Ok(hash)
+        }
+    }
+}
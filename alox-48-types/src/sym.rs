@@ -0,0 +1,252 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::borrow::{Borrow, Cow};
+
+use crate::Symbol;
+
+/// A borrowed ruby symbol.
+///
+/// [`Sym::new`] is a `const fn`, so symbols can be stored in constants.
+/// The [`sym!`](crate::sym) macro is shorthand for it.
+///
+/// Rust doesn't allow unsized types like [`Sym`] in constant patterns,
+/// so to `match` on a symbol either match on [`Sym::as_str`] or compare against constants in guards.
+///
+/// ```
+/// # use alox_48_types::{sym, Sym};
+/// const EVENT: &Sym = sym!("RPG::Event");
+///
+/// fn describe(class: &Sym) -> &'static str {
+///     match class {
+///         c if c == EVENT => "an event",
+///         c if c == "RPG::Map" => "a map",
+///         _ => match class.as_str() {
+///             "RPG::Troop" => "a troop",
+///             _ => "something else",
+///         },
+///     }
+/// }
+///
+/// assert_eq!(describe(sym!("RPG::Event")), "an event");
+/// assert_eq!(describe(Sym::new("RPG::Map")), "a map");
+/// assert_eq!(describe(Sym::new("RPG::Troop")), "a troop");
+/// ```
+#[repr(transparent)]
+pub struct Sym(pub(crate) str);
+
+impl Sym {
+    /// Create a new symbol from a borrowed string.
+    pub const fn new(str: &str) -> &Self {
+        // SAFETY: Sym is just a wrapper of str and is repr(transparent) so they have identical layouts. This should be safe.
+        //
+        // double checked with miri.
+        // as far as I am aware (especially since this is what the stdlib does) this is only way to convert to a dst like we want.
+        unsafe { std::mem::transmute(str) }
+    }
+
+    /// Fetch the inner string.
+    pub const fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true if the inner string is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns true if the inner string starts with an '@'.
+    ///
+    /// (aka it's an instance variable name.)
+    pub fn is_ivar(&self) -> bool {
+        self.0.starts_with('@')
+    }
+
+    /// Returns a new symbol with an '@' prepended to the inner string.
+    ///
+    /// If the inner string already starts with an '@', this will return a borrowed reference to the original symbol.
+    pub fn to_ivar(&self) -> Cow<'_, Self> {
+        if self.is_ivar() {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(Symbol::new(format!("@{}", self.as_str())))
+        }
+    }
+
+    /// Returns this symbol with the '@' stripped from the inner string.
+    ///
+    /// This borrows from `self`, so it doesn't allocate.
+    /// If the inner string does not start with an '@', this will return None.
+    pub fn to_rust_field_name(&self) -> Option<&Self> {
+        self.0.strip_prefix('@').map(Self::new)
+    }
+
+    /// Returns a new owned symbol.
+    pub fn to_symbol(&self) -> Symbol {
+        self.to_owned()
+    }
+
+    /// Returns the length of the inner string.
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl Borrow<str> for Sym {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Sym {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sym {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl AsRef<Sym> for Sym {
+    fn as_ref(&self) -> &Sym {
+        self
+    }
+}
+
+impl ToOwned for Sym {
+    type Owned = Symbol;
+
+    fn to_owned(&self) -> Self::Owned {
+        Symbol(self.0.into())
+    }
+}
+
+impl From<&Sym> for Box<Sym> {
+    fn from(value: &Sym) -> Self {
+        let string: Box<str> = value.as_str().into();
+        // SAFETY: Sym is repr(transparent) over str, see Sym::new.
+        unsafe { Box::from_raw(Box::into_raw(string) as *mut Sym) }
+    }
+}
+
+impl Default for &Sym {
+    fn default() -> Self {
+        Sym::new("")
+    }
+}
+
+impl std::fmt::Display for Sym {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(":{}", &self.0))
+    }
+}
+
+impl std::fmt::Debug for Sym {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Sym").field(&&self.0).finish()
+    }
+}
+
+impl std::hash::Hash for Sym {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<'a> From<&'a str> for &'a Sym {
+    fn from(value: &'a str) -> Self {
+        Sym::new(value)
+    }
+}
+
+impl<'a> From<&'a Sym> for &'a str {
+    fn from(value: &'a Sym) -> Self {
+        &value.0
+    }
+}
+
+impl PartialEq<str> for Sym {
+    fn eq(&self, other: &str) -> bool {
+        self.0.eq(other)
+    }
+}
+
+impl PartialEq<&str> for Sym {
+    fn eq(&self, other: &&str) -> bool {
+        self.0.eq(*other)
+    }
+}
+
+impl PartialEq<String> for Sym {
+    fn eq(&self, other: &String) -> bool {
+        self.0.eq(other)
+    }
+}
+
+impl PartialEq<Symbol> for Sym {
+    fn eq(&self, other: &Symbol) -> bool {
+        self.0.eq(other.as_str())
+    }
+}
+
+impl PartialEq<Sym> for Sym {
+    fn eq(&self, other: &Sym) -> bool {
+        self.0.eq(&other.0)
+    }
+}
+
+impl Eq for Sym {}
+
+impl PartialEq<Sym> for str {
+    fn eq(&self, other: &Sym) -> bool {
+        self.eq(&other.0)
+    }
+}
+
+impl PartialEq<Sym> for &str {
+    fn eq(&self, other: &Sym) -> bool {
+        (*self).eq(&other.0)
+    }
+}
+
+impl PartialEq<Sym> for String {
+    fn eq(&self, other: &Sym) -> bool {
+        self.as_str().eq(&other.0)
+    }
+}
+
+impl PartialEq<&Sym> for String {
+    fn eq(&self, other: &&Sym) -> bool {
+        self.as_str().eq(&other.0)
+    }
+}
+
+impl PartialEq<&Sym> for Symbol {
+    fn eq(&self, other: &&Sym) -> bool {
+        self.as_str().eq(&other.0)
+    }
+}
+
+/// Create a [`&'static Sym`](crate::Sym) from a string literal.
+///
+/// This expands to [`Sym::new`](crate::Sym::new), so it can be used in constants.
+///
+/// ```
+/// # use alox_48_types::{sym, Sym, Symbol};
+/// const TABLE: &Sym = sym!("Table");
+///
+/// assert_eq!(TABLE, "Table");
+/// assert_eq!(Symbol::from("Table"), TABLE);
+/// ```
+#[macro_export]
+macro_rules! sym {
+    ($name:expr) => {
+        $crate::Sym::new($name)
+    };
+}
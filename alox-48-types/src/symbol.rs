@@ -0,0 +1,213 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{borrow::Borrow, sync::Arc};
+
+use crate::Sym;
+
+/// An owned symbol from ruby.
+/// It's a newtype around an `Arc<str>`, meant to preserve types during (de)serialization.
+///
+/// When serializing, a [`String`] will be serialized as a String, but a [`Symbol`] will be serialized as a Symbol.
+///
+/// Cloning a symbol is cheap, and clones share the same string.
+/// Deserializing a [`Value`](crate::Value) reuses symbols, so every object of a class shares one copy of its class name.
+#[derive(Eq, Clone)]
+pub struct Symbol(pub(crate) Arc<str>);
+
+#[allow(clippy::must_use_candidate)]
+impl Symbol {
+    /// Create a new symbol from a string.
+    pub fn new(string: String) -> Self {
+        Self(string.into())
+    }
+
+    /// Get this symbol as a borrowed str.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get this symbol as a borrowed Sym.
+    pub fn as_sym(&self) -> &Sym {
+        Sym::new(&self.0)
+    }
+
+    /// Returns true if both symbols share the same string, rather than just being equal.
+    pub fn ptr_eq(&self, other: &Symbol) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Get the length of this symbol.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the string data is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns true if the string data starts with an '@'.
+    ///
+    /// (aka it's an instance variable name.)
+    pub fn is_ivar(&self) -> bool {
+        self.0.starts_with('@')
+    }
+
+    /// Returns this symbol with the '@' stripped from the inner string.
+    ///
+    /// This borrows from `self`, so it doesn't allocate.
+    /// If the inner string does not start with an '@', this will return None.
+    pub fn as_rust_field_name(&self) -> Option<&Sym> {
+        self.0.strip_prefix('@').map(Sym::new)
+    }
+}
+
+impl Default for Symbol {
+    fn default() -> Self {
+        Self(Arc::from(""))
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(value: String) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(value: Symbol) -> Self {
+        value.0.to_string()
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(value: &str) -> Self {
+        Self(value.into())
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Symbol").field(&self.0).finish()
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(":{}", self.0))
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str().eq(other)
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str().eq(*other)
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str().eq(other)
+    }
+}
+
+impl PartialEq<Symbol> for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        self.0.eq(&other.0)
+    }
+}
+
+impl PartialEq<Sym> for Symbol {
+    fn eq(&self, other: &Sym) -> bool {
+        self.as_str().eq(&other.0)
+    }
+}
+
+impl PartialEq<Symbol> for str {
+    fn eq(&self, other: &Symbol) -> bool {
+        self.eq(other.as_str())
+    }
+}
+
+impl PartialEq<Symbol> for &str {
+    fn eq(&self, other: &Symbol) -> bool {
+        (*self).eq(other.as_str())
+    }
+}
+
+impl PartialEq<Symbol> for String {
+    fn eq(&self, other: &Symbol) -> bool {
+        self.eq(other.as_str())
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl std::hash::Hash for Symbol {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<Sym> for Symbol {
+    fn borrow(&self) -> &Sym {
+        self.as_sym()
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Symbol {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl AsRef<Sym> for Symbol {
+    fn as_ref(&self) -> &Sym {
+        self.as_sym()
+    }
+}
+
+impl From<&Sym> for Symbol {
+    fn from(value: &Sym) -> Self {
+        value.to_owned()
+    }
+}
+
+impl std::ops::Deref for Symbol {
+    type Target = Sym;
+
+    fn deref(&self) -> &Self::Target {
+        Sym::new(&self.0)
+    }
+}
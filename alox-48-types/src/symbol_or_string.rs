@@ -0,0 +1,86 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::Symbol;
+
+/// Either a symbol or a string, for hashes whose keys are a mix of both.
+///
+/// Deserializing into a `String` accepts symbols too, but forgets which one the key was.
+/// This keeps track of it, so keys are serialized back the same way they were deserialized.
+///
+/// ```
+/// use alox_48_types::{SymbolOrString, Symbol};
+///
+/// let symbol = SymbolOrString::from(Symbol::from("name"));
+/// let string = SymbolOrString::from("name".to_string());
+/// assert_eq!(symbol.as_str(), string.as_str());
+/// assert_ne!(symbol, string);
+/// ```
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum SymbolOrString {
+    /// A symbol.
+    Symbol(Symbol),
+    /// A utf8 string.
+    String(String),
+}
+
+impl SymbolOrString {
+    /// Get the text of this symbol or string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Symbol(symbol) => symbol.as_str(),
+            Self::String(string) => string,
+        }
+    }
+
+    /// Returns `true` if this is a symbol.
+    pub fn is_symbol(&self) -> bool {
+        matches!(self, Self::Symbol(_))
+    }
+
+    /// Returns `true` if this is a string.
+    pub fn is_string(&self) -> bool {
+        matches!(self, Self::String(_))
+    }
+
+    /// Take the text of this symbol or string, forgetting which one it was.
+    pub fn into_string(self) -> String {
+        match self {
+            Self::Symbol(symbol) => symbol.into(),
+            Self::String(string) => string,
+        }
+    }
+}
+
+impl From<Symbol> for SymbolOrString {
+    fn from(value: Symbol) -> Self {
+        Self::Symbol(value)
+    }
+}
+
+impl From<String> for SymbolOrString {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<SymbolOrString> for String {
+    fn from(value: SymbolOrString) -> Self {
+        value.into_string()
+    }
+}
+
+impl AsRef<str> for SymbolOrString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::fmt::Display for SymbolOrString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::Symbol;
+
+/// A type representing a ruby object serialized with `marshal_dump`.
+///
+/// This is the typed counterpart to [`Value::UserMarshal`](crate::Value::UserMarshal).
+/// `value` is whatever `marshal_dump` returned, and is passed to `marshal_load` when ruby loads the object.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Default, Debug, Clone)]
+pub struct UserMarshal<T> {
+    /// The class of the object.
+    pub class: Symbol,
+    /// The value the object was dumped as.
+    pub value: T,
+}
+
+impl<T> UserMarshal<T> {
+    /// Create an object of class `class` dumped as `value`.
+    pub fn new(class: impl Into<Symbol>, value: T) -> Self {
+        Self {
+            class: class.into(),
+            value,
+        }
+    }
+
+    /// Take the dumped value, forgetting the class.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Splits this object into its constituants.
+    pub fn into_parts(self) -> (Symbol, T) {
+        (self.class, self.value)
+    }
+
+    /// Convert the dumped value with `f`, keeping the class as-is.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> UserMarshal<U> {
+        UserMarshal {
+            class: self.class,
+            value: f(self.value),
+        }
+    }
+}
+
+impl<T> std::ops::Deref for UserMarshal<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> std::ops::DerefMut for UserMarshal<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use crate::{byte_buf, ByteBuf, Symbol};
+
+/// This type represents types serialized with `_dump` from ruby.
+/// Its main intended use is in `Value`, but alox-48 can also deserialize it directly,
+/// which is useful with `#[marshal(from = "alox_48::Userdata")]`.
+///
+/// ```
+/// let userdata = alox_48_types::Userdata::new("MyUserData", b"abcd".to_vec());
+///
+/// let (class, data) = userdata.into_parts();
+/// assert_eq!(class, "MyUserData");
+/// assert_eq!(&data[..], b"abcd");
+/// ```
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Userdata {
+    /// Userdata class.
+    pub class: Symbol,
+    /// Userdata data.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::bytes"))]
+    pub data: ByteBuf,
+}
+
+impl Userdata {
+    /// Create userdata of class `class`.
+    pub fn new(class: impl Into<Symbol>, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            class: class.into(),
+            data: byte_buf(data.into()),
+        }
+    }
+
+    /// Splits this userdata into its constituants.
+    #[allow(clippy::must_use_candidate)]
+    pub fn into_parts(self) -> (Symbol, ByteBuf) {
+        (self.class, self.data)
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::BuildHasher,
+};
+
+use indexmap::IndexMap;
+
+use crate::{Instance, RbArray, RbFields, RbStruct};
+
+use super::{Object, RbHash, RbString, Symbol, Userdata, Value};
+
+impl Value {
+    /// Convert a symbol into a value.
+    #[must_use]
+    pub fn from_symbol(symbol: String) -> Self {
+        Self::Symbol(symbol.into())
+    }
+
+    /// Create a [`Value::Symbol`].
+    pub fn symbol(symbol: impl Into<Symbol>) -> Self {
+        Self::Symbol(symbol.into())
+    }
+
+    /// Create a [`Value::Object`].
+    pub fn object(class: impl Into<Symbol>, fields: RbFields) -> Self {
+        Self::Object(Object {
+            class: class.into(),
+            fields,
+        })
+    }
+
+    /// Create a [`Value::Userdata`].
+    pub fn userdata(class: impl Into<Symbol>, data: impl Into<Vec<u8>>) -> Self {
+        Self::Userdata(Userdata::new(class, data))
+    }
+
+    /// Create a [`Value::Instance`].
+    pub fn instance(value: impl Into<Value>, fields: RbFields) -> Self {
+        Self::Instance(Instance {
+            value: Box::new(value.into()),
+            fields,
+        })
+    }
+
+    /// Create a [`Value::Regex`].
+    pub fn regex(data: impl Into<RbString>, flags: u8) -> Self {
+        Self::Regex {
+            data: data.into(),
+            flags,
+        }
+    }
+
+    /// Create a [`Value::RbStruct`].
+    pub fn rb_struct(class: impl Into<Symbol>, fields: RbFields) -> Self {
+        Self::RbStruct(RbStruct {
+            class: class.into(),
+            fields,
+        })
+    }
+
+    /// Create a [`Value::Class`].
+    pub fn class(class: impl Into<Symbol>) -> Self {
+        Self::Class(class.into())
+    }
+
+    /// Create a [`Value::Module`].
+    pub fn module(module: impl Into<Symbol>) -> Self {
+        Self::Module(module.into())
+    }
+
+    /// Create a [`Value::Extended`].
+    pub fn extended(module: impl Into<Symbol>, value: impl Into<Value>) -> Self {
+        Self::Extended {
+            module: module.into(),
+            value: Box::new(value.into()),
+        }
+    }
+
+    /// Create a [`Value::UserClass`].
+    pub fn user_class(class: impl Into<Symbol>, value: impl Into<Value>) -> Self {
+        Self::UserClass {
+            class: class.into(),
+            value: Box::new(value.into()),
+        }
+    }
+
+    /// Create a [`Value::UserMarshal`].
+    pub fn user_marshal(class: impl Into<Symbol>, value: impl Into<Value>) -> Self {
+        Self::UserMarshal {
+            class: class.into(),
+            value: Box::new(value.into()),
+        }
+    }
+
+    /// Create a [`Value::Data`].
+    pub fn data(class: impl Into<Symbol>, value: impl Into<Value>) -> Self {
+        Self::Data {
+            class: class.into(),
+            value: Box::new(value.into()),
+        }
+    }
+}
+
+/// The error returned when converting an integer that doesn't fit in [`Value::Integer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegerOutOfRange;
+
+impl std::fmt::Display for IntegerOutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("integer is out of range of an i64")
+    }
+}
+
+impl std::error::Error for IntegerOutOfRange {}
+
+impl<T> From<Option<T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(v) => v.into(),
+            None => Self::Nil,
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        // FIXME should these use instance?
+        Value::String(RbString::from(value))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(RbString::from(value))
+    }
+}
+
+impl From<RbString> for Value {
+    fn from(value: RbString) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<Symbol> for Value {
+    fn from(value: Symbol) -> Self {
+        Self::Symbol(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Self::Float(value.into())
+    }
+}
+
+macro_rules! from_int {
+    ($($int:ty),*) => {
+        $(
+            impl From<$int> for Value {
+                fn from(value: $int) -> Self {
+                    Self::Integer(value.into())
+                }
+            }
+        )*
+    };
+}
+
+from_int!(i8, u8, i16, u16, i32, u32, i64);
+
+// These may not fit in an i64, which is the largest bignum alox-48 supports.
+macro_rules! try_from_int {
+    ($($int:ty),*) => {
+        $(
+            impl TryFrom<$int> for Value {
+                type Error = IntegerOutOfRange;
+
+                fn try_from(value: $int) -> Result<Self, Self::Error> {
+                    i64::try_from(value)
+                        .map(Self::Integer)
+                        .map_err(|_| IntegerOutOfRange)
+                }
+            }
+        )*
+    };
+}
+
+try_from_int!(u64, isize, usize);
+
+impl From<()> for Value {
+    fn from((): ()) -> Self {
+        Self::Nil
+    }
+}
+
+impl From<Object> for Value {
+    fn from(value: Object) -> Self {
+        Self::Object(value)
+    }
+}
+
+impl From<Userdata> for Value {
+    fn from(value: Userdata) -> Self {
+        Self::Userdata(value)
+    }
+}
+
+impl From<RbStruct> for Value {
+    fn from(value: RbStruct) -> Self {
+        Self::RbStruct(value)
+    }
+}
+
+impl<K, V, S> From<IndexMap<K, V, S>> for Value
+where
+    K: Into<Value>,
+    V: Into<Value>,
+{
+    fn from(value: IndexMap<K, V, S>) -> Self {
+        Self::Hash(
+            value
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl<K, V, S> From<HashMap<K, V, S>> for Value
+where
+    K: Into<Value>,
+    V: Into<Value>,
+    S: BuildHasher,
+{
+    fn from(value: HashMap<K, V, S>) -> Self {
+        Self::Hash(
+            value
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl<K, V> From<BTreeMap<K, V>> for Value
+where
+    K: Into<Value>,
+    V: Into<Value>,
+{
+    fn from(value: BTreeMap<K, V>) -> Self {
+        Self::Hash(
+            value
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl<T> From<Vec<T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(value: Vec<T>) -> Self {
+        Self::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Value
+where
+    T: Into<Value>,
+{
+    fn from(value: [T; N]) -> Self {
+        Self::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+macro_rules! try_from_value {
+    ($($ty:ty => $into:ident),* $(,)?) => {
+        $(
+            impl TryFrom<Value> for $ty {
+                type Error = Value;
+
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    value.$into()
+                }
+            }
+        )*
+    };
+}
+
+try_from_value!(
+    RbString => into_string,
+    Symbol => into_symbol,
+    i64 => into_integer,
+    f64 => into_float,
+    RbArray => into_array,
+    RbHash => into_hash,
+    Object => into_object,
+    Userdata => into_userdata,
+    RbStruct => into_rb_struct,
+);
+
+impl TryFrom<Value> for String {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value
+            .into_string()
+            .map(|str| str.to_string_lossy().into_owned())
+    }
+}
+
+impl TryFrom<Value> for i32 {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Integer(v) => i32::try_from(v).map_err(|_| value),
+            _ => Err(value),
+        }
+    }
+}
+
+impl From<Value> for bool {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Nil => false,
+            Value::Bool(b) => b,
+            _ => true,
+        }
+    }
+}
+
+impl<T> From<Instance<T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(instance: Instance<T>) -> Self {
+        let value = Box::new(instance.value.into());
+        Value::Instance(Instance {
+            value,
+            fields: instance.fields,
+        })
+    }
+}
@@ -3,8 +3,116 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use indexmap::IndexMap;
+
 use super::{Object, RbArray, RbHash, RbString, Symbol, Userdata, Value};
 
+impl Value {
+    /// Compare two values, including the order of hash entries, instance variables, and struct members.
+    ///
+    /// `==` ignores that order, so this is stricter than it.
+    /// Serializing two values that are equal according to this writes the same data.
+    ///
+    /// ```
+    /// # use alox_48_types::{RbHash, Value};
+    /// let a = Value::Hash(RbHash::from_iter([(1.into(), 2.into()), (3.into(), 4.into())]));
+    /// let b = Value::Hash(RbHash::from_iter([(3.into(), 4.into()), (1.into(), 2.into())]));
+    ///
+    /// assert_eq!(a, b);
+    /// assert!(a.eq_unordered(&b));
+    /// assert!(!a.eq_ordered(&b));
+    /// ```
+    #[must_use]
+    pub fn eq_ordered(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Array(a), Value::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.eq_ordered(b))
+            }
+            (Value::Hash(a), Value::Hash(b)) => entries_eq_ordered(a, b, Value::eq_ordered),
+            (
+                Value::HashWithDefault { hash, default },
+                Value::HashWithDefault {
+                    hash: hash2,
+                    default: default2,
+                },
+            ) => entries_eq_ordered(hash, hash2, Value::eq_ordered) && default.eq_ordered(default2),
+            (Value::Object(a), Value::Object(b)) => {
+                a.class == b.class && entries_eq_ordered(&a.fields, &b.fields, Symbol::eq)
+            }
+            (Value::RbStruct(a), Value::RbStruct(b)) => {
+                a.class == b.class && entries_eq_ordered(&a.fields, &b.fields, Symbol::eq)
+            }
+            (Value::Instance(a), Value::Instance(b)) => {
+                a.value.eq_ordered(&b.value) && entries_eq_ordered(&a.fields, &b.fields, Symbol::eq)
+            }
+            (
+                Value::Extended { module, value },
+                Value::Extended {
+                    module: module2,
+                    value: value2,
+                },
+            )
+            | (
+                Value::UserClass {
+                    class: module,
+                    value,
+                },
+                Value::UserClass {
+                    class: module2,
+                    value: value2,
+                },
+            )
+            | (
+                Value::UserMarshal {
+                    class: module,
+                    value,
+                },
+                Value::UserMarshal {
+                    class: module2,
+                    value: value2,
+                },
+            )
+            | (
+                Value::Data {
+                    class: module,
+                    value,
+                },
+                Value::Data {
+                    class: module2,
+                    value: value2,
+                },
+            ) => module == module2 && value.eq_ordered(value2),
+            // nothing else has an order that `==` could ignore
+            _ => self == other,
+        }
+    }
+
+    /// Compare two values, ignoring the order of hash entries, instance variables, and struct members.
+    ///
+    /// This is the same as `==`, and is useful for comparing data that was written independently
+    /// (like by ruby and by alox-48), where the same hash might have been built in a different order.
+    /// Arrays are still compared in order.
+    #[must_use]
+    pub fn eq_unordered(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+fn entries_eq_ordered<K>(
+    a: &IndexMap<K, Value>,
+    b: &IndexMap<K, Value>,
+    key_eq: impl Fn(&K, &K) -> bool,
+) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|((k, v), (k2, v2))| key_eq(k, k2) && v.eq_ordered(v2))
+}
+
+/// Hashes, instance variables, and struct members are equal regardless of their order,
+/// like they are in ruby. Use [`Value::eq_ordered`] to compare that order too.
+///
+/// `NaN` is equal to itself, so this is also [`Eq`].
 impl PartialEq for Value {
     #[allow(clippy::too_many_lines)]
     fn eq(&self, other: &Self) -> bool {
@@ -59,6 +167,17 @@ impl PartialEq for Value {
                     false
                 }
             }
+            Value::HashWithDefault { hash, default } => {
+                if let Value::HashWithDefault {
+                    hash: hash2,
+                    default: default2,
+                } = other
+                {
+                    hash == hash2 && default == default2
+                } else {
+                    false
+                }
+            }
             Value::Object(o) => {
                 if let Value::Object(o2) = other {
                     o == o2
@@ -171,6 +290,15 @@ impl PartialEq<bool> for Value {
 
 impl PartialEq<i32> for Value {
     fn eq(&self, other: &i32) -> bool {
+        match self {
+            Value::Integer(v) => i64::from(*other) == *v,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<i64> for Value {
+    fn eq(&self, other: &i64) -> bool {
         match self {
             Value::Integer(v) => other == v,
             _ => false,
@@ -272,19 +400,17 @@ impl std::hash::Hash for Value {
         match self {
             Value::Nil => {}
             Value::Bool(b) => b.hash(state),
-            Value::Float(f) => f.to_bits().hash(state), // not the best but eh whos using a float as a hash key
+            Value::Float(f) => super::ord::float_bits(*f).hash(state),
             Value::Integer(i) => i.hash(state),
             Value::String(s) => {
                 s.data.hash(state);
             }
             Value::Symbol(s) => s.0.hash(state),
             Value::Array(v) => v.hash(state),
-            Value::Hash(h) => {
-                h.len().hash(state);
-                for (key, value) in h {
-                    key.hash(state);
-                    value.hash(state);
-                }
+            Value::Hash(h) => super::hash_unordered(h, state),
+            Value::HashWithDefault { hash, default } => {
+                super::hash_unordered(hash, state);
+                default.hash(state);
             }
             Value::Object(o) => o.hash(state),
             Value::Userdata(u) => u.hash(state),
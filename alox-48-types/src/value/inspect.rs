@@ -0,0 +1,353 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt::{self, Write};
+
+use crate::{RbFields, Sym, Value};
+
+// the line width pp tries to stay under
+const WIDTH: usize = 80;
+
+const OPERATORS: &[&str] = &[
+    "+", "-", "*", "/", "%", "**", "==", "===", "!=", "<=>", "<", "<=", ">", ">=", "<<", ">>", "!",
+    "~", "+@", "-@", "[]", "[]=", "=~", "!~", "&", "|", "^", "`",
+];
+
+impl Value {
+    /// Format this value like ruby's `inspect`.
+    ///
+    /// This is the same as the [`Display`](std::fmt::Display) impl.
+    /// Formatting with `{:#}` instead lays values out like ruby's `pp`, breaking anything longer than 80 columns over several lines.
+    ///
+    /// Wrappers that ruby's `inspect` doesn't show (string encodings, extended modules, subclasses of builtin classes)
+    /// are left out, and strings are escaped according to their encoding.
+    ///
+    /// ```
+    /// # use alox_48_types::{Object, Value};
+    /// let object = Value::Object(Object {
+    ///     class: "Foo".into(),
+    ///     fields: [
+    ///         ("@name".into(), Value::Symbol("bar".into())),
+    ///         ("@list".into(), Value::Array(vec![1.into(), 2.5.into(), Value::Nil])),
+    ///     ]
+    ///     .into_iter()
+    ///     .collect(),
+    /// });
+    ///
+    /// assert_eq!(object.to_ruby_string(), "#<Foo @name=:bar, @list=[1, 2.5, nil]>");
+    /// ```
+    pub fn to_ruby_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            pretty(self, f, 0)
+        } else {
+            inspect(self, f)
+        }
+    }
+}
+
+fn inspect<W: Write>(value: &Value, out: &mut W) -> fmt::Result {
+    match value {
+        Value::Nil => out.write_str("nil"),
+        Value::Bool(b) => write!(out, "{b}"),
+        Value::Integer(i) => write!(out, "{i}"),
+        Value::Float(f) => write_float(*f, out),
+        Value::String(s) => write_string(s.as_slice(), false, out),
+        Value::Symbol(s) => write_symbol(s, out),
+        Value::Array(array) => {
+            out.write_char('[')?;
+            for (i, v) in array.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+                inspect(v, out)?;
+            }
+            out.write_char(']')
+        }
+        Value::Hash(hash) | Value::HashWithDefault { hash, .. } => {
+            out.write_char('{')?;
+            for (i, (k, v)) in hash.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+                inspect(k, out)?;
+                out.write_str("=>")?;
+                inspect(v, out)?;
+            }
+            out.write_char('}')
+        }
+        Value::Userdata(userdata) => {
+            write!(out, "#<{} _dump=", userdata.class.as_str())?;
+            write_string(&userdata.data, false, out)?;
+            out.write_char('>')
+        }
+        Value::Object(object) => {
+            write_fields(&format!("#<{}", object.class.as_str()), &object.fields, out)
+        }
+        Value::RbStruct(rb_struct) => write_fields(
+            &format!("#<struct {}", rb_struct.class.as_str()),
+            &rb_struct.fields,
+            out,
+        ),
+        Value::Instance(instance) => match &*instance.value {
+            Value::String(s) => write_string(s.as_slice(), is_utf8(&instance.fields), out),
+            Value::Regex { data, flags } => {
+                write_regex(data.as_slice(), *flags, is_utf8(&instance.fields), out)
+            }
+            value => inspect(value, out),
+        },
+        Value::Regex { data, flags } => write_regex(data.as_slice(), *flags, false, out),
+        Value::Class(name) | Value::Module(name) => out.write_str(name.as_str()),
+        Value::Extended { value, .. } | Value::UserClass { value, .. } => inspect(value, out),
+        Value::UserMarshal { class, value } => {
+            write!(out, "#<{} marshal_dump=", class.as_str())?;
+            inspect(value, out)?;
+            out.write_char('>')
+        }
+        Value::Data { class, value } => {
+            write!(out, "#<{} data=", class.as_str())?;
+            inspect(value, out)?;
+            out.write_char('>')
+        }
+    }
+}
+
+// like pp, break values over several lines if they don't fit on the current one
+fn pretty<W: Write>(value: &Value, out: &mut W, indent: usize) -> fmt::Result {
+    let mut inline = String::new();
+    inspect(value, &mut inline)?;
+    if indent + inline.len() <= WIDTH {
+        return out.write_str(&inline);
+    }
+
+    match value {
+        Value::Array(array) if !array.is_empty() => {
+            out.write_char('[')?;
+            for (i, v) in array.iter().enumerate() {
+                if i > 0 {
+                    write!(out, ",\n{:1$}", "", indent + 1)?;
+                }
+                pretty(v, out, indent + 1)?;
+            }
+            out.write_char(']')
+        }
+        Value::Hash(hash) | Value::HashWithDefault { hash, .. } if !hash.is_empty() => {
+            out.write_char('{')?;
+            for (i, (k, v)) in hash.iter().enumerate() {
+                if i > 0 {
+                    write!(out, ",\n{:1$}", "", indent + 1)?;
+                }
+                let mut key = String::new();
+                inspect(k, &mut key)?;
+                write!(out, "{key}=>")?;
+                pretty(v, out, indent + 1 + key.len() + 2)?;
+            }
+            out.write_char('}')
+        }
+        Value::Object(object) if !object.fields.is_empty() => {
+            out.write_str("#<")?;
+            out.write_str(object.class.as_str())?;
+            pretty_fields(&object.fields, out, indent)
+        }
+        Value::RbStruct(rb_struct) if !rb_struct.fields.is_empty() => {
+            out.write_str("#<struct ")?;
+            out.write_str(rb_struct.class.as_str())?;
+            pretty_fields(&rb_struct.fields, out, indent)
+        }
+        Value::Instance(instance)
+            if !matches!(*instance.value, Value::String(_) | Value::Regex { .. }) =>
+        {
+            pretty(&instance.value, out, indent)
+        }
+        Value::Extended { value, .. } | Value::UserClass { value, .. } => {
+            pretty(value, out, indent)
+        }
+        _ => out.write_str(&inline),
+    }
+}
+
+fn write_fields<W: Write>(start: &str, fields: &RbFields, out: &mut W) -> fmt::Result {
+    out.write_str(start)?;
+    for (i, (name, v)) in fields.iter().enumerate() {
+        out.write_str(if i > 0 { ", " } else { " " })?;
+        write!(out, "{}=", name.as_str())?;
+        inspect(v, out)?;
+    }
+    out.write_char('>')
+}
+
+fn pretty_fields<W: Write>(fields: &RbFields, out: &mut W, indent: usize) -> fmt::Result {
+    for (i, (name, v)) in fields.iter().enumerate() {
+        let separator = if i > 0 { "," } else { "" };
+        write!(
+            out,
+            "{separator}\n{:1$}{name}=",
+            "",
+            indent + 1,
+            name = name.as_str()
+        )?;
+        pretty(v, out, indent + 1 + name.len() + 1)?;
+    }
+    out.write_char('>')
+}
+
+fn is_utf8(fields: &RbFields) -> bool {
+    fields.iter().any(|(name, v)| match (name.as_str(), v) {
+        ("E", Value::Bool(utf8)) => *utf8,
+        ("encoding", Value::String(encoding)) => encoding.as_slice() == b"UTF-8",
+        _ => false,
+    })
+}
+
+fn write_float<W: Write>(f: f64, out: &mut W) -> fmt::Result {
+    if f.is_nan() {
+        return out.write_str("NaN");
+    }
+    if f.is_infinite() {
+        return out.write_str(if f > 0.0 { "Infinity" } else { "-Infinity" });
+    }
+
+    // rust switches to exponents at the same magnitudes ruby does, but writes them differently
+    let repr = format!("{f:?}");
+    let Some((mantissa, exponent)) = repr.split_once('e') else {
+        return out.write_str(&repr);
+    };
+    let (sign, digits) = exponent
+        .strip_prefix('-')
+        .map_or(('+', exponent), |digits| ('-', digits));
+    let point = if mantissa.contains('.') { "" } else { ".0" };
+    write!(out, "{mantissa}{point}e{sign}{digits:0>2}")
+}
+
+// Either a character, or a byte that isn't part of one in this encoding.
+fn units(bytes: &[u8], utf8: bool) -> Vec<Result<char, u8>> {
+    if !utf8 {
+        return bytes
+            .iter()
+            .map(|&b| if b.is_ascii() { Ok(b as char) } else { Err(b) })
+            .collect();
+    }
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                units.extend(valid.chars().map(Ok));
+                break;
+            }
+            Err(e) => {
+                let (valid, after) = rest.split_at(e.valid_up_to());
+                // from_utf8 just checked this much
+                let valid = std::str::from_utf8(valid).unwrap_or_default();
+                units.extend(valid.chars().map(Ok));
+
+                let invalid = e.error_len().unwrap_or(after.len());
+                units.extend(after[..invalid].iter().map(|&b| Err(b)));
+                rest = &after[invalid..];
+            }
+        }
+    }
+    units
+}
+
+fn write_string<W: Write>(bytes: &[u8], utf8: bool, out: &mut W) -> fmt::Result {
+    out.write_char('"')?;
+    write_escaped(bytes, utf8, out)?;
+    out.write_char('"')
+}
+
+fn write_escaped<W: Write>(bytes: &[u8], utf8: bool, out: &mut W) -> fmt::Result {
+    let units = units(bytes, utf8);
+    for (i, unit) in units.iter().enumerate() {
+        let c = match *unit {
+            Ok(c) => c,
+            Err(b) => {
+                write!(out, "\\x{b:02X}")?;
+                continue;
+            }
+        };
+
+        match c {
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\t' => out.write_str("\\t")?,
+            '\r' => out.write_str("\\r")?,
+            '\x1B' => out.write_str("\\e")?,
+            '\x07' => out.write_str("\\a")?,
+            '\x08' => out.write_str("\\b")?,
+            '\x0B' => out.write_str("\\v")?,
+            '\x0C' => out.write_str("\\f")?,
+            // interpolation would be ambiguous
+            '#' if matches!(units.get(i + 1), Some(Ok('{' | '$' | '@'))) => out.write_str("\\#")?,
+            '"' => out.write_str("\\\"")?,
+            c if c.is_control() && utf8 => write!(out, "\\u{:04X}", c as u32)?,
+            c if c.is_control() => write!(out, "\\x{:02X}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_symbol<W: Write>(symbol: &Sym, out: &mut W) -> fmt::Result {
+    out.write_char(':')?;
+    if is_plain_symbol(symbol.as_str()) {
+        out.write_str(symbol.as_str())
+    } else {
+        write_string(symbol.as_str().as_bytes(), true, out)
+    }
+}
+
+fn is_plain_symbol(symbol: &str) -> bool {
+    fn is_identifier(name: &str) -> bool {
+        let mut chars = name.chars();
+        chars
+            .next()
+            .is_some_and(|c| c == '_' || c.is_alphabetic() || !c.is_ascii())
+            && chars.all(|c| c == '_' || c.is_alphanumeric() || !c.is_ascii())
+    }
+
+    if OPERATORS.contains(&symbol) {
+        return true;
+    }
+    if let Some(name) = symbol
+        .strip_prefix("@@")
+        .or_else(|| symbol.strip_prefix(['@', '$']))
+    {
+        return is_identifier(name);
+    }
+    is_identifier(symbol.strip_suffix(['?', '!', '=']).unwrap_or(symbol))
+}
+
+fn write_regex<W: Write>(bytes: &[u8], flags: u8, utf8: bool, out: &mut W) -> fmt::Result {
+    out.write_char('/')?;
+    // the source is shown as written, apart from unescaped slashes
+    let mut escaped = false;
+    for unit in units(bytes, utf8) {
+        match unit {
+            Err(b) => write!(out, "\\x{b:02X}")?,
+            Ok('/') if !escaped => out.write_str("\\/")?,
+            Ok('\n') => out.write_str("\\n")?,
+            Ok(c) if c.is_control() => write!(out, "\\x{:02X}", c as u32)?,
+            Ok(c) => out.write_char(c)?,
+        }
+        escaped = !escaped && unit == Ok('\\');
+    }
+    out.write_char('/')?;
+
+    // ruby orders these by the letters in "mix"
+    for (flag, letter) in [(4, 'm'), (1, 'i'), (2, 'x')] {
+        if flags & flag != 0 {
+            out.write_char(letter)?;
+        }
+    }
+    Ok(())
+}
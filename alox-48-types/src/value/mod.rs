@@ -0,0 +1,114 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+mod from;
+mod impls;
+mod inspect;
+mod ord;
+mod size;
+
+pub use from::IntegerOutOfRange;
+pub(crate) use ord::{cmp_unordered, hash_unordered};
+
+use crate::{Instance, Object, RbArray, RbHash, RbString, RbStruct, Symbol, Userdata};
+
+/// An enum representing any ruby value.
+///
+/// Similar to `serde_json::Value`, although much more nuanced.
+///
+/// Converting between values and rust types (`Value::from_serialize` and `value.deserialize_into()`) is provided by
+/// alox-48's [`ValueExt`](https://docs.rs/alox-48/latest/alox_48/trait.ValueExt.html) trait,
+/// which is in [`alox_48::prelude`](https://docs.rs/alox-48/latest/alox_48/prelude/index.html).
+///
+/// With the `serde` feature, this implements `serde::Serialize` and `serde::Deserialize`, externally tagged by variant name.
+/// Hashes are written as sequences of `[key, value]` pairs, since their keys can be any value.
+#[derive(Default, Clone, enum_as_inner::EnumAsInner, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    /// A value equivalent to nil in ruby (or [`()`] in rust.)
+    #[default]
+    Nil,
+    /// A boolean value.
+    Bool(bool),
+    /// A float value.
+    Float(f64),
+    /// An integer value.
+    ///
+    /// Ruby stores integers outside the range of an `i32` as bignums, which are read into this as well.
+    /// alox-48 decides which to write when serializing.
+    Integer(i64),
+    /// A ruby string.
+    /// Because strings in ruby are not guarenteed to be utf8, [`RbString`] stores a [`Vec<u8>`] instead.
+    ///
+    /// See [`RbString`] for more information.
+    String(RbString),
+    /// A symbol from ruby.
+    /// It's a newtype around a String, meant to preserve types during (de)serialization.
+    /// See [`Symbol`] for more information.
+    Symbol(Symbol),
+    /// An array of [`Value`].
+    Array(RbArray),
+    /// Equivalent to a Hash in Ruby.
+    Hash(#[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::hash"))] RbHash),
+    /// A Hash with a default value, like `Hash.new(0)`.
+    HashWithDefault {
+        /// The entries of the hash.
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::hash"))]
+        hash: RbHash,
+        /// The value returned for missing keys.
+        default: Box<Value>,
+    },
+    /// An object serialized by `_dump`.
+    Userdata(Userdata),
+    /// A generic ruby object.
+    Object(Object),
+    /// An "instance".
+    ///
+    /// The naming is a bit of a misnomer, as it's not an instance of a class, but rather a value with attached ivars.
+    /// It's distinct from object though, as it's a value (like a string) which does not usually have ivars.
+    Instance(Instance<Box<Value>>),
+    /// Equivalent to a `Regexp` in Ruby.
+    Regex {
+        /// The regex data.
+        data: RbString,
+        /// Any flags associated with the regex. (global match, case insensitive, etc.)
+        flags: u8,
+    },
+    /// Equivalent to a `Struct` in Ruby.
+    RbStruct(RbStruct),
+    /// Equivalent to a `Class` in Ruby.
+    Class(Symbol),
+    /// Equivalent to a `Module` in Ruby.
+    Module(Symbol),
+    /// A value that has been extended with a module.
+    Extended {
+        /// The module that was extended.
+        module: Symbol,
+        /// The value that was extended.
+        value: Box<Value>,
+    },
+    /// A subclass of a ruby class like `Hash` or `Array`.
+    UserClass {
+        /// The subclass.
+        class: Symbol,
+        /// The value of the subclass.
+        value: Box<Value>,
+    },
+    /// An object that has been serialized as another type.
+    UserMarshal {
+        /// The class of the original object.
+        class: Symbol,
+        /// The value of the object.
+        value: Box<Value>,
+    },
+    /// Unclear what this is? It's releated to C extensions according to the ruby docs.
+    Data {
+        /// The class of the data.
+        class: Symbol,
+        /// The value of the data.
+        value: Box<Value>,
+    },
+}
@@ -0,0 +1,175 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::{
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use indexmap::IndexMap;
+
+use super::Value;
+
+/// Values are ordered by kind first, in the order the variants of [`Value`] are declared in
+/// (so `nil` < bools < floats < integers < strings < symbols < arrays, and so on),
+/// and then by their contents.
+///
+/// This ordering is consistent with `==`:
+/// - `NaN` is equal to itself, and greater than every other float. `0.0` and `-0.0` are equal.
+/// - Hashes, instance variables, and struct members are compared regardless of their order,
+///   as if their keys were sorted first.
+///
+/// It's not the same as ruby's `<=>`, it only exists so values can be sorted and used as keys in a `BTreeMap`.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Float(a), Value::Float(b)) => cmp_f64(*a, *b),
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Symbol(a), Value::Symbol(b))
+            | (Value::Class(a), Value::Class(b))
+            | (Value::Module(a), Value::Module(b)) => a.cmp(b),
+            (Value::Array(a), Value::Array(b)) => a.cmp(b),
+            (Value::Hash(a), Value::Hash(b)) => cmp_unordered(a, b),
+            (
+                Value::HashWithDefault { hash, default },
+                Value::HashWithDefault {
+                    hash: hash2,
+                    default: default2,
+                },
+            ) => cmp_unordered(hash, hash2).then_with(|| default.cmp(default2)),
+            (Value::Userdata(a), Value::Userdata(b)) => a.cmp(b),
+            (Value::Object(a), Value::Object(b)) => a.cmp(b),
+            (Value::Instance(a), Value::Instance(b)) => a.cmp(b),
+            (
+                Value::Regex { data, flags },
+                Value::Regex {
+                    data: data2,
+                    flags: flags2,
+                },
+            ) => data.cmp(data2).then_with(|| flags.cmp(flags2)),
+            (Value::RbStruct(a), Value::RbStruct(b)) => a.cmp(b),
+            (
+                Value::Extended { module, value },
+                Value::Extended {
+                    module: module2,
+                    value: value2,
+                },
+            )
+            | (
+                Value::UserClass {
+                    class: module,
+                    value,
+                },
+                Value::UserClass {
+                    class: module2,
+                    value: value2,
+                },
+            )
+            | (
+                Value::UserMarshal {
+                    class: module,
+                    value,
+                },
+                Value::UserMarshal {
+                    class: module2,
+                    value: value2,
+                },
+            )
+            | (
+                Value::Data {
+                    class: module,
+                    value,
+                },
+                Value::Data {
+                    class: module2,
+                    value: value2,
+                },
+            ) => module.cmp(module2).then_with(|| value.cmp(value2)),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Nil => 0,
+        Value::Bool(_) => 1,
+        Value::Float(_) => 2,
+        Value::Integer(_) => 3,
+        Value::String(_) => 4,
+        Value::Symbol(_) => 5,
+        Value::Array(_) => 6,
+        Value::Hash(_) => 7,
+        Value::HashWithDefault { .. } => 8,
+        Value::Userdata(_) => 9,
+        Value::Object(_) => 10,
+        Value::Instance(_) => 11,
+        Value::Regex { .. } => 12,
+        Value::RbStruct(_) => 13,
+        Value::Class(_) => 14,
+        Value::Module(_) => 15,
+        Value::Extended { .. } => 16,
+        Value::UserClass { .. } => 17,
+        Value::UserMarshal { .. } => 18,
+        Value::Data { .. } => 19,
+    }
+}
+
+fn cmp_f64(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+    }
+}
+
+// The bits to hash a float with, so floats that are `==` hash the same.
+pub(super) fn float_bits(f: f64) -> u64 {
+    if f.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        // adding 0.0 turns -0.0 into 0.0 and leaves everything else alone
+        (f + 0.0).to_bits()
+    }
+}
+
+/// Compare two maps as if their keys were sorted first, since maps are equal regardless of order.
+pub(crate) fn cmp_unordered<K, V, S>(a: &IndexMap<K, V, S>, b: &IndexMap<K, V, S>) -> Ordering
+where
+    K: Ord,
+    V: Ord,
+{
+    let mut a: Vec<_> = a.iter().collect();
+    let mut b: Vec<_> = b.iter().collect();
+    a.sort_unstable_by_key(|(k, _)| *k);
+    b.sort_unstable_by_key(|(k, _)| *k);
+    a.cmp(&b)
+}
+
+/// Hash a map without depending on the order of its entries, since maps are equal regardless of order.
+pub(crate) fn hash_unordered<K, V, S, H>(map: &IndexMap<K, V, S>, state: &mut H)
+where
+    K: Hash,
+    V: Hash,
+    H: Hasher,
+{
+    let sum = map.iter().fold(0u64, |sum, entry| {
+        let mut hasher = DefaultHasher::new();
+        entry.hash(&mut hasher);
+        sum.wrapping_add(hasher.finish())
+    });
+    map.len().hash(state);
+    sum.hash(state);
+}
@@ -0,0 +1,94 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::mem::size_of;
+
+use indexmap::IndexMap;
+
+use crate::{ByteBuf, Symbol, Value};
+
+impl Value {
+    /// Returns roughly how many bytes this value uses, including everything it owns.
+    ///
+    /// This is the size of the value itself, plus the capacity of every buffer, array, and hash in it.
+    /// Symbols are shared, but are counted every time they appear, as are string buffers shared with the `bytes` feature.
+    /// Allocator overhead isn't counted, so the real usage is usually a bit higher.
+    ///
+    /// ```
+    /// # use alox_48_types::Value;
+    /// let string = Value::from("a".repeat(1000));
+    /// assert!(string.deep_size_of() >= 1000);
+    ///
+    /// let array = Value::Array(vec![string.clone(), string]);
+    /// assert!(array.deep_size_of() >= 2000);
+    /// ```
+    pub fn deep_size_of(&self) -> usize {
+        size_of::<Value>() + self.heap_size()
+    }
+
+    // the size of everything this value owns, but not the value itself
+    fn heap_size(&self) -> usize {
+        match self {
+            Value::Nil | Value::Bool(_) | Value::Float(_) | Value::Integer(_) => 0,
+            Value::String(string) | Value::Regex { data: string, .. } => buffer_size(&string.data),
+            Value::Symbol(symbol) | Value::Class(symbol) | Value::Module(symbol) => {
+                symbol_size(symbol)
+            }
+            Value::Array(array) => {
+                array.capacity() * size_of::<Value>()
+                    + array.iter().map(Value::heap_size).sum::<usize>()
+            }
+            Value::Hash(hash) => map_size(hash, |(key, value)| key.heap_size() + value.heap_size()),
+            Value::HashWithDefault { hash, default } => {
+                map_size(hash, |(key, value)| key.heap_size() + value.heap_size())
+                    + default.deep_size_of()
+            }
+            Value::Userdata(userdata) => symbol_size(&userdata.class) + buffer_size(&userdata.data),
+            Value::Object(object) => symbol_size(&object.class) + fields_size(&object.fields),
+            Value::RbStruct(rb_struct) => {
+                symbol_size(&rb_struct.class) + fields_size(&rb_struct.fields)
+            }
+            Value::Instance(instance) => {
+                instance.value.deep_size_of() + fields_size(&instance.fields)
+            }
+            Value::Extended {
+                module: class,
+                value,
+            }
+            | Value::UserClass { class, value }
+            | Value::UserMarshal { class, value }
+            | Value::Data { class, value } => symbol_size(class) + value.deep_size_of(),
+        }
+    }
+}
+
+#[cfg(not(feature = "bytes"))]
+fn buffer_size(buffer: &ByteBuf) -> usize {
+    buffer.capacity()
+}
+
+// `Bytes` doesn't expose its capacity
+#[cfg(feature = "bytes")]
+fn buffer_size(buffer: &ByteBuf) -> usize {
+    buffer.len()
+}
+
+// an `Arc<str>` allocates its reference counts next to the string
+fn symbol_size(symbol: &Symbol) -> usize {
+    2 * size_of::<usize>() + symbol.len()
+}
+
+fn fields_size(fields: &IndexMap<Symbol, Value>) -> usize {
+    map_size(fields, |(name, value)| {
+        symbol_size(name) + value.heap_size()
+    })
+}
+
+// an `IndexMap` stores its entries (with their hashes) in a `Vec`, and indices into that in a hash table
+fn map_size<K, V>(map: &IndexMap<K, V>, entry_size: impl Fn((&K, &V)) -> usize) -> usize {
+    map.capacity() * (size_of::<(usize, K, V)>() + size_of::<usize>())
+        + map.iter().map(entry_size).sum::<usize>()
+}
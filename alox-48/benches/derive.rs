@@ -0,0 +1,97 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+#![allow(missing_docs)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use alox_48::{Deserialize, Serialize};
+
+// shaped like RPG::Actor and friends, which have lots of fields
+#[derive(Deserialize, Serialize, Default)]
+#[marshal(class = "RPG::Actor")]
+struct Actor {
+    id: i32,
+    name: String,
+    class_id: i32,
+    initial_level: i32,
+    final_level: i32,
+    exp_basis: i32,
+    exp_inflation: i32,
+    character_name: String,
+    character_hue: i32,
+    battler_name: String,
+    battler_hue: i32,
+    weapon_id: i32,
+    armor1_id: i32,
+    armor2_id: i32,
+    armor3_id: i32,
+    armor4_id: i32,
+    weapon_fix: bool,
+    armor1_fix: bool,
+    armor2_fix: bool,
+    armor3_fix: bool,
+    armor4_fix: bool,
+    maxhp: i32,
+    maxsp: i32,
+    str: i32,
+    dex: i32,
+    agi: i32,
+    int: i32,
+    atk: i32,
+    pdef: i32,
+    mdef: i32,
+    eva: i32,
+    hit: i32,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+#[marshal(class = "RPG::AudioFile")]
+struct AudioFile {
+    name: String,
+    volume: i32,
+    pitch: i32,
+}
+
+fn fields(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fields");
+
+    let actors: Vec<Actor> = (0..1_000).map(|_| Actor::default()).collect();
+    let bytes = alox_48::to_bytes(&actors).unwrap();
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("many", |b| {
+        b.iter(|| alox_48::from_bytes::<Vec<Actor>>(black_box(&bytes)).unwrap());
+    });
+
+    let files: Vec<AudioFile> = (0..10_000).map(|_| AudioFile::default()).collect();
+    let bytes = alox_48::to_bytes(&files).unwrap();
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("few", |b| {
+        b.iter(|| alox_48::from_bytes::<Vec<AudioFile>>(black_box(&bytes)).unwrap());
+    });
+
+    group.finish();
+}
+
+// 100,000 actors have 3.2 million instance variables, so the cost of each one adds up
+fn ivars(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ivars");
+    group.sample_size(10);
+
+    let actors: Vec<Actor> = (0..100_000).map(|_| Actor::default()).collect();
+    let bytes = alox_48::to_bytes(&actors).unwrap();
+    group.throughput(Throughput::Elements(100_000 * 32));
+    group.bench_function("serialize", |b| {
+        b.iter(|| alox_48::to_bytes(black_box(&actors)).unwrap());
+    });
+    group.bench_function("deserialize", |b| {
+        b.iter(|| alox_48::from_bytes::<Vec<Actor>>(black_box(&bytes)).unwrap());
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, fields, ivars);
+criterion_main!(benches);
@@ -0,0 +1,120 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use alox_48::{Userdata, Value};
+
+// about the size of the Table userdata of a large RPG Maker map
+const TABLE_SIZE: usize = 20 * 1024 * 1024;
+
+fn table() -> Value {
+    Value::Userdata(Userdata {
+        class: "Table".into(),
+        data: (0..TABLE_SIZE).map(|i| i as u8).collect(),
+    })
+}
+
+fn large_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_bytes");
+    group.throughput(Throughput::Bytes(TABLE_SIZE as u64));
+    group.sample_size(20);
+
+    let table = table();
+    let bytes = alox_48::to_bytes(&table).unwrap();
+    group.bench_function("serialize_userdata", |b| {
+        b.iter(|| alox_48::to_bytes(black_box(&table)).unwrap());
+    });
+    group.bench_function("deserialize_userdata", |b| {
+        b.iter(|| alox_48::from_bytes::<Userdata>(black_box(&bytes)).unwrap());
+    });
+    // borrows the data instead of copying it
+    group.bench_function("verify_userdata", |b| {
+        b.iter(|| alox_48::verify_bytes(black_box(&bytes)).unwrap());
+    });
+
+    let string = Value::String(vec![b'a'; TABLE_SIZE].into());
+    let bytes = alox_48::to_bytes(&string).unwrap();
+    group.bench_function("serialize_string", |b| {
+        b.iter(|| alox_48::to_bytes(black_box(&string)).unwrap());
+    });
+    group.bench_function("deserialize_string", |b| {
+        b.iter(|| alox_48::from_bytes::<Value>(black_box(&bytes)).unwrap());
+    });
+
+    group.finish();
+}
+
+fn arrays(c: &mut Criterion) {
+    let mut group = c.benchmark_group("arrays");
+
+    for len in [1_000, 100_000] {
+        let ints: Vec<i32> = (0..len).map(|i| i * 1_000).collect();
+        let bytes = alox_48::to_bytes(&ints).unwrap();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("serialize_ints", len), &ints, |b, ints| {
+            b.iter(|| alox_48::to_bytes(black_box(ints)).unwrap());
+        });
+        group.bench_with_input(
+            BenchmarkId::new("deserialize_ints", len),
+            &bytes,
+            |b, bytes| {
+                b.iter(|| alox_48::from_bytes::<Vec<i32>>(black_box(bytes)).unwrap());
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn floats(c: &mut Criterion) {
+    let mut group = c.benchmark_group("floats");
+
+    let floats: Vec<f64> = (0..100_000).map(|i| f64::from(i) * 0.37).collect();
+    let bytes = alox_48::to_bytes(&floats).unwrap();
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+
+    group.bench_function("serialize", |b| {
+        b.iter(|| alox_48::to_bytes(black_box(&floats)).unwrap());
+    });
+    group.bench_function("deserialize", |b| {
+        b.iter(|| alox_48::from_bytes::<Vec<f64>>(black_box(&bytes)).unwrap());
+    });
+
+    group.finish();
+}
+
+fn rxdata(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rxdata");
+
+    let files: [(&str, &[u8]); 3] = [
+        ("Actors", include_bytes!("../examples/Actors.rxdata")),
+        ("Map223", include_bytes!("../examples/Map223.rxdata")),
+        ("System", include_bytes!("../examples/System.rxdata")),
+    ];
+    for (name, bytes) in files {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("to_value", name), bytes, |b, bytes| {
+            b.iter(|| alox_48::from_bytes::<Value>(black_box(bytes)).unwrap());
+        });
+
+        group.bench_with_input(BenchmarkId::new("to_document", name), bytes, |b, bytes| {
+            b.iter(|| alox_48::from_bytes::<alox_48::arena::Document>(black_box(bytes)).unwrap());
+        });
+
+        let value: Value = alox_48::from_bytes(bytes).unwrap();
+        group.bench_with_input(BenchmarkId::new("from_value", name), &value, |b, value| {
+            b.iter(|| alox_48::to_bytes(black_box(value)).unwrap());
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, large_bytes, arrays, floats, rxdata);
+criterion_main!(benches);
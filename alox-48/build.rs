@@ -0,0 +1,57 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Fail the build on a rustc older than the `rust-version` in Cargo.toml.
+// Cargo checks this too, but `--ignore-rust-version` (or an old cargo) skips that check,
+// and the errors from building with a too old compiler are much less clear than this one.
+
+use std::{env, process::Command};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let Some(msrv) = env::var("CARGO_PKG_RUST_VERSION")
+        .ok()
+        .as_deref()
+        .and_then(parse_version)
+    else {
+        return;
+    };
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    // `rustc 1.85.0 (4d91de4e4 2025-02-17)`
+    let Some(found) = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .and_then(|version| version.split_whitespace().nth(1).and_then(parse_version))
+    else {
+        println!("cargo:warning=could not determine the rustc version, skipping the MSRV check");
+        return;
+    };
+
+    assert!(
+        found >= msrv,
+        "alox-48 needs rustc {}.{}.{} or newer, but this is {}.{}.{}",
+        msrv.0,
+        msrv.1,
+        msrv.2,
+        found.0,
+        found.1,
+        found.2
+    );
+}
+
+// `1.85`, `1.85.0`, or `1.86.0-nightly`
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let version = version.split('-').next()?;
+    let mut parts = version.split('.').map(str::parse);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
@@ -1,13 +1,13 @@
 #![allow(dead_code, missing_docs)]
 
-use alox_48::Deserialize;
+use alox_48::{Deserialize, UserdataExt};
 
 #[derive(Debug, Deserialize)]
 pub struct Color {
-    pub red: f32,
-    pub green: f32,
-    pub blue: f32,
-    pub alpha: f32,
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub alpha: f64,
 }
 
 // Default values
@@ -37,16 +37,9 @@ pub enum ParameterType {
     Bool(bool),
 }
 
-macro_rules! symbol {
-    ($string:literal) => {
-        &alox_48::Symbol::from($string)
-        // &alox_48::Value::Symbol($string.to_string())
-    };
-}
-
 impl From<alox_48::Value> for ParameterType {
     fn from(value: alox_48::Value) -> Self {
-        use alox_48::Value;
+        use alox_48::{ObjectExt, Value};
         println!("{value:#?}");
 
         match value {
@@ -54,59 +47,22 @@ impl From<alox_48::Value> for ParameterType {
             Value::String(str) => Self::String(str.to_string_lossy().into_owned()),
             Value::Object(obj) if obj.class == "RPG::AudioFile" => {
                 Self::AudioFile(rpg::AudioFile {
-                    name: obj.fields[symbol!("name")]
-                        .clone()
-                        .into_string()
-                        .unwrap()
-                        .to_string_lossy()
-                        .into_owned(),
-                    volume: obj.fields[symbol!("volume")]
-                        .clone()
-                        .into_integer()
-                        .unwrap() as _,
-                    pitch: obj.fields[symbol!("pitch")].clone().into_integer().unwrap() as _,
+                    name: obj.get("name").unwrap(),
+                    volume: obj.get("volume").unwrap(),
+                    pitch: obj.get("pitch").unwrap(),
                 })
             }
             Value::Object(obj) if obj.class == "RPG::MoveRoute" => {
                 Self::MoveRoute(rpg::MoveRoute {
-                    repeat: obj.fields[symbol!("repeat")].clone().into_bool().unwrap(),
-                    skippable: obj.fields[symbol!("skippable")]
-                        .clone()
-                        .into_bool()
-                        .unwrap(),
-                    list: obj.fields[symbol!("list")]
-                        .clone()
-                        .into_array()
-                        .unwrap()
-                        .into_iter()
-                        .map(|obj| {
-                            let obj = obj.into_object().unwrap();
-
-                            rpg::MoveCommand {
-                                code: obj.fields[symbol!("code")].clone().into_integer().unwrap()
-                                    as _,
-                                parameters: obj.fields[symbol!("parameters")]
-                                    .clone()
-                                    .into_array()
-                                    .unwrap()
-                                    .into_iter()
-                                    .map(Into::into)
-                                    .collect(),
-                            }
-                        })
-                        .collect(),
+                    repeat: obj.get("repeat").unwrap(),
+                    skippable: obj.get("skippable").unwrap(),
+                    list: obj.get("list").unwrap(),
                 })
             }
             Value::Object(obj) if obj.class == "RPG::MoveCommand" => {
                 Self::MoveCommand(rpg::MoveCommand {
-                    code: obj.fields[symbol!("code")].clone().into_integer().unwrap() as _,
-                    parameters: obj.fields[symbol!("parameters")]
-                        .clone()
-                        .into_array()
-                        .unwrap()
-                        .into_iter()
-                        .map(Into::into)
-                        .collect(),
+                    code: obj.get("code").unwrap(),
+                    parameters: obj.get("parameters").unwrap(),
                 })
             }
             Value::Float(f) => Self::Float(f as _),
@@ -117,23 +73,23 @@ impl From<alox_48::Value> for ParameterType {
             ),
             Value::Bool(b) => Self::Bool(b),
             Value::Userdata(data) if data.class == "Color" => {
-                let floats = bytemuck::cast_slice(&data.data);
+                let [red, green, blue, alpha] = data.reader().read_f64_array().unwrap();
 
                 Self::Color(Color {
-                    red: floats[0],
-                    green: floats[1],
-                    blue: floats[2],
-                    alpha: floats[3],
+                    red,
+                    green,
+                    blue,
+                    alpha,
                 })
             }
             Value::Userdata(data) if data.class == "Tone" => {
-                let floats = bytemuck::cast_slice(&data.data);
+                let [red, green, blue, gray] = data.reader().read_f64_array().unwrap();
 
                 Self::Tone(Tone {
-                    red: floats[0],
-                    green: floats[1],
-                    blue: floats[2],
-                    gray: floats[3],
+                    red,
+                    green,
+                    blue,
+                    gray,
                 })
             }
             _ => panic!("Unexpected type {value:#?}"),
@@ -143,15 +99,15 @@ impl From<alox_48::Value> for ParameterType {
 
 #[derive(Debug, Deserialize)]
 pub struct Tone {
-    pub red: f32,
-    pub green: f32,
-    pub blue: f32,
-    pub gray: f32,
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub gray: f64,
 }
 
 pub mod rpg {
-    use alox_48::Deserialize;
-    use std::collections::HashMap;
+    use alox_48::adapters::IdMap;
+    use alox_48::{Deserialize, UserdataExt};
 
     #[derive(Debug, Deserialize)]
     #[marshal(deny_unknown_fields)]
@@ -166,11 +122,11 @@ pub mod rpg {
         pub encounter_list: Vec<i32>,
         pub encounter_step: i32,
         pub data: Table3,
-        pub events: HashMap<i32, event::Event>,
+        pub events: IdMap<event::Event>,
     }
 
     #[derive(Deserialize, Debug)]
-    #[marshal(from = "alox_48::Userdata")]
+    #[marshal(try_from = "alox_48::Userdata")]
     pub struct Table3 {
         xsize: usize,
         ysize: usize,
@@ -178,28 +134,24 @@ pub mod rpg {
         data: Vec<i16>,
     }
 
-    impl From<alox_48::Userdata> for Table3 {
-        fn from(value: alox_48::Userdata) -> Self {
-            let u32_slice: &[u32] =
-                bytemuck::cast_slice(&value.data[0..std::mem::size_of::<u32>() * 5]);
-
-            assert_eq!(u32_slice[0], 3);
-            let xsize = u32_slice[1] as usize;
-            let ysize = u32_slice[2] as usize;
-            let zsize = u32_slice[3] as usize;
-            let len = u32_slice[4] as usize;
-
-            assert_eq!(xsize * ysize * zsize, len);
-            let data =
-                bytemuck::cast_slice(&value.data[(std::mem::size_of::<u32>() * 5)..]).to_vec();
-            assert_eq!(data.len(), len as _);
-
-            Self {
-                xsize,
-                ysize,
-                zsize,
-                data,
+    impl TryFrom<alox_48::Userdata> for Table3 {
+        type Error = alox_48::DeError;
+
+        fn try_from(value: alox_48::Userdata) -> Result<Self, Self::Error> {
+            let mut reader = value.reader();
+            let [dims, xsize, ysize, zsize, len] = reader.read_u32_array()?;
+            if dims != 3 || xsize * ysize * zsize != len {
+                return Err(alox_48::DeError::custom("invalid Table3 header"));
             }
+            let data = reader.read_i16_slice(len as _)?;
+            reader.finish()?;
+
+            Ok(Self {
+                xsize: xsize as _,
+                ysize: ysize as _,
+                zsize: zsize as _,
+                data,
+            })
         }
     }
 
@@ -326,125 +278,31 @@ pub mod rpg {
     }
 
     #[derive(Debug, Default, Deserialize)]
-    #[marshal(from = "alox_48::Userdata")]
+    #[marshal(try_from = "alox_48::Userdata")]
     pub struct Table2 {
         xsize: usize,
         ysize: usize,
         data: Vec<i16>,
     }
 
-    impl From<alox_48::Userdata> for Table2 {
-        fn from(value: alox_48::Userdata) -> Self {
-            let u32_slice: &[u32] =
-                bytemuck::cast_slice(&value.data[0..std::mem::size_of::<u32>() * 5]);
-
-            assert_eq!(u32_slice[0], 2);
-            let xsize = u32_slice[1] as usize;
-            let ysize = u32_slice[2] as usize;
-            let zsize = u32_slice[3] as usize;
-            let len = u32_slice[4] as usize;
-
-            assert_eq!(xsize * ysize * zsize, len);
-            let data =
-                bytemuck::cast_slice(&value.data[(std::mem::size_of::<u32>() * 5)..]).to_vec();
-            assert_eq!(data.len(), len);
+    impl TryFrom<alox_48::Userdata> for Table2 {
+        type Error = alox_48::DeError;
 
-            Self { xsize, ysize, data }
-        }
-    }
-}
-
-use std::ops::{Deref, DerefMut};
-
-/// An array that is serialized and deserialized as padded with a None element.
-#[derive(Debug, Clone)]
-pub struct NilPadded<T>(Vec<T>);
-
-impl<'de, T> alox_48::Deserialize<'de> for NilPadded<T>
-where
-    T: alox_48::Deserialize<'de>,
-{
-    fn deserialize<D>(deserializer: D) -> Result<Self, alox_48::DeError>
-    where
-        D: alox_48::DeserializerTrait<'de>,
-    {
-        struct Visitor<T> {
-            _marker: core::marker::PhantomData<T>,
-        }
-
-        impl<'de, T> alox_48::de::Visitor<'de> for Visitor<T>
-        where
-            T: alox_48::Deserialize<'de>,
-        {
-            type Value = NilPadded<T>;
-
-            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                formatter.write_str("a nil padded array")
+        fn try_from(value: alox_48::Userdata) -> Result<Self, Self::Error> {
+            let mut reader = value.reader();
+            let [dims, xsize, ysize, zsize, len] = reader.read_u32_array()?;
+            if dims != 2 || xsize * ysize * zsize != len {
+                return Err(alox_48::DeError::custom("invalid Table2 header"));
             }
+            let data = reader.read_i16_slice(len as _)?;
+            reader.finish()?;
 
-            fn visit_array<A>(self, mut seq: A) -> Result<Self::Value, alox_48::DeError>
-            where
-                A: alox_48::de::ArrayAccess<'de>,
-            {
-                let mut values = Vec::with_capacity(seq.len());
-
-                if let Some(v) = seq.next_element::<Option<T>>()? {
-                    if v.is_some() {
-                        return Err(alox_48::DeError::custom("the first element was not nil"));
-                    }
-                }
-
-                while let Some(ele) = seq.next_element::<T>()? {
-                    values.push(ele);
-                }
-
-                Ok(values.into())
-            }
+            Ok(Self {
+                xsize: xsize as _,
+                ysize: ysize as _,
+                data,
+            })
         }
-
-        deserializer.deserialize(Visitor {
-            _marker: core::marker::PhantomData,
-        })
-    }
-}
-
-impl<T> Deref for NilPadded<T> {
-    type Target = Vec<T>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl<T> DerefMut for NilPadded<T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
-
-impl<T: Default> Default for NilPadded<T> {
-    fn default() -> Self {
-        Self(vec![])
-    }
-}
-
-impl<T> From<Vec<Option<T>>> for NilPadded<T> {
-    fn from(value: Vec<Option<T>>) -> Self {
-        let mut iter = value.into_iter();
-
-        assert!(
-            iter.next()
-                .expect("there should be at least one element")
-                .is_none(),
-            "the array should be padded with nil at the first index"
-        );
-        Self(iter.flatten().collect())
-    }
-}
-
-impl<T> From<Vec<T>> for NilPadded<T> {
-    fn from(value: Vec<T>) -> Self {
-        Self(value)
     }
 }
 
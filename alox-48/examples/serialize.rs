@@ -24,5 +24,10 @@ fn main() {
 
     let output: MyStruct = alox_48::from_bytes(&output).unwrap();
 
+    println!("{output:#?}");
+
+    let output = alox_48::to_bytes(Foo("newtype".to_string())).unwrap();
+    let output: Foo = alox_48::from_bytes(&output).unwrap();
+
     println!("{output:#?}")
 }
@@ -1,14 +1,20 @@
 #![allow(dead_code)]
 use std::process::Command;
 
+use alox_48::UserdataExt;
+
 #[derive(alox_48::Deserialize, Debug)]
-#[marshal(from = "alox_48::Userdata")]
+#[marshal(try_from = "alox_48::Userdata")]
 struct Floats([f32; 3]);
 
-impl From<alox_48::Userdata> for Floats {
-    fn from(value: alox_48::Userdata) -> Self {
-        let floats = bytemuck::cast_slice(&value.data);
-        Self(std::array::from_fn(|i| floats[i]))
+impl TryFrom<alox_48::Userdata> for Floats {
+    type Error = alox_48::DeError;
+
+    fn try_from(value: alox_48::Userdata) -> Result<Self, Self::Error> {
+        let mut reader = value.reader();
+        let floats = reader.read_f32_array()?;
+        reader.finish()?;
+        Ok(Self(floats))
     }
 }
 
@@ -0,0 +1,23 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Arbitrary bytes should only ever produce errors, never panics.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(value) = alox_48::from_bytes::<alox_48::Value>(data) {
+        // anything that loads should also dump
+        alox_48::to_bytes(&value).unwrap();
+    }
+    let _ = alox_48::from_bytes::<alox_48::arena::Document>(data);
+
+    if let Ok(mut deserializer) = alox_48::Deserializer::new(data) {
+        deserializer.set_strict(true);
+        deserializer.set_track_path(true);
+        let _ = <alox_48::Value as alox_48::Deserialize>::deserialize(&mut deserializer);
+    }
+});
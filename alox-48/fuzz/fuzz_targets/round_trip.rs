@@ -0,0 +1,16 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Generated values should survive a round trip without changing.
+// Bytes are compared instead of values, because `NaN != NaN`.
+fuzz_target!(|value: alox_48::Value| {
+    let bytes = alox_48::to_bytes(&value).unwrap();
+    let reloaded: alox_48::Value = alox_48::from_bytes(&bytes).unwrap();
+    assert_eq!(alox_48::to_bytes(&reloaded).unwrap(), bytes);
+});
@@ -0,0 +1,397 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Types for the shapes RPG Maker (and other ruby code) commonly stores collections in.
+//!
+//! - [`NilPadded`] is an array with a `nil` at the start, which is dropped when deserializing.
+//! - [`OneIndexed`] is stored the same way, but indexed by ID instead, so `ids[1]` is the first element.
+//! - [`IdMap`] is a hash keyed by integer IDs, kept in ID order.
+//!
+//! ```
+//! # #[cfg(feature = "value")] {
+//! use alox_48::{adapters::{IdMap, NilPadded, OneIndexed}, marshal};
+//!
+//! let value = marshal!([nil, "Aluxes", "Basil"]);
+//! let actors: NilPadded<String> = alox_48::from_value(&value).unwrap();
+//! assert_eq!(actors[0], "Aluxes");
+//!
+//! let actors: OneIndexed<Vec<String>> = alox_48::from_value(&value).unwrap();
+//! assert_eq!(actors[1], "Aluxes");
+//!
+//! let events: IdMap<String> = alox_48::from_value(&marshal!({ 3 => "EV003", 1 => "EV001" })).unwrap();
+//! assert_eq!(events.keys().copied().collect::<Vec<_>>(), [1, 3]);
+//! assert_eq!(events.next_id(), 4);
+//! # }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    ops::{Deref, DerefMut, Index, IndexMut},
+};
+
+use crate::{
+    de::{cautious_capacity, Result as DeResult},
+    ser::Result as SerResult,
+    ArrayAccess, Deserialize, DeserializerTrait, Serialize, SerializeArray, SerializerTrait,
+    Visitor,
+};
+
+/// An array that is stored with a `nil` before its first element.
+///
+/// RPG Maker stores its database like this, so that element `n` has ID `n`.
+/// The `nil` is dropped when deserializing (and must be there), and added back when serializing.
+/// Use [`OneIndexed`] to index by ID instead.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NilPadded<T>(pub Vec<T>);
+
+/// An array that is stored with a `nil` before its first element, and indexed by ID.
+///
+/// This is stored the same way as [`NilPadded`], but element `n` has ID `n`, like it does in ruby.
+/// Indexing by ID 0 panics, as that's where the padding is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OneIndexed<C>(pub C);
+
+/// A hash keyed by integer IDs, like the events of an RPG Maker map.
+///
+/// Entries are kept (and serialized) in ID order, so serializing the same map always writes the same data.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IdMap<T>(pub BTreeMap<i32, T>);
+
+struct PaddedVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for PaddedVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("an array padded with nil")
+    }
+
+    fn visit_array<A>(self, mut array: A) -> DeResult<Self::Value>
+    where
+        A: ArrayAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(cautious_capacity::<T>(array.len().saturating_sub(1)));
+        // the padding, which must be nil
+        if array.next_element::<()>()?.is_some() {
+            while let Some(value) = array.next_element()? {
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+}
+
+fn deserialize_padded<'de, T, D>(deserializer: D) -> DeResult<Vec<T>>
+where
+    T: Deserialize<'de>,
+    D: DeserializerTrait<'de>,
+{
+    deserializer.deserialize(PaddedVisitor(PhantomData))
+}
+
+fn serialize_padded<T, S>(values: &[T], serializer: S) -> SerResult<S::Ok>
+where
+    T: Serialize,
+    S: SerializerTrait,
+{
+    let mut array = serializer.serialize_array(values.len() + 1)?;
+    array.serialize_element(&())?;
+    for value in values {
+        array.serialize_element(value)?;
+    }
+    array.end()
+}
+
+impl<T> NilPadded<T> {
+    /// Unwrap the elements, without the padding.
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Default for NilPadded<T> {
+    fn default() -> Self {
+        Self(vec![])
+    }
+}
+
+impl<T> Deref for NilPadded<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for NilPadded<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for NilPadded<T> {
+    fn from(value: Vec<T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> FromIterator<T> for NilPadded<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<T> IntoIterator for NilPadded<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a NilPadded<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for NilPadded<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserialize_padded(deserializer).map(Self)
+    }
+}
+
+impl<T> Serialize for NilPadded<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serialize_padded(&self.0, serializer)
+    }
+}
+
+impl<T> OneIndexed<Vec<T>> {
+    /// Unwrap the elements, without the padding.
+    ///
+    /// The element with ID `n` is at index `n - 1` of the returned `Vec`.
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+
+    /// Returns the element with ID `id`, or `None` if there isn't one.
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.0.get(id.checked_sub(1)?)
+    }
+
+    /// Returns the element with ID `id` mutably, or `None` if there isn't one.
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut T> {
+        self.0.get_mut(id.checked_sub(1)?)
+    }
+
+    /// Add an element to the end, returning its ID.
+    pub fn push(&mut self, value: T) -> usize {
+        self.0.push(value);
+        self.0.len()
+    }
+
+    /// Returns the number of elements, which is also the highest ID.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the elements and their IDs.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (index + 1, value))
+    }
+
+    /// Iterate over the elements and their IDs, mutably.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.0
+            .iter_mut()
+            .enumerate()
+            .map(|(index, value)| (index + 1, value))
+    }
+}
+
+impl<T> Default for OneIndexed<Vec<T>> {
+    fn default() -> Self {
+        Self(vec![])
+    }
+}
+
+impl<T> Index<usize> for OneIndexed<Vec<T>> {
+    type Output = T;
+
+    #[track_caller]
+    fn index(&self, id: usize) -> &Self::Output {
+        assert!(id != 0, "ID 0 is the padding of a one indexed array");
+        &self.0[id - 1]
+    }
+}
+
+impl<T> IndexMut<usize> for OneIndexed<Vec<T>> {
+    #[track_caller]
+    fn index_mut(&mut self, id: usize) -> &mut Self::Output {
+        assert!(id != 0, "ID 0 is the padding of a one indexed array");
+        &mut self.0[id - 1]
+    }
+}
+
+impl<T> From<Vec<T>> for OneIndexed<Vec<T>> {
+    fn from(value: Vec<T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> FromIterator<T> for OneIndexed<Vec<T>> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<'de, T> Deserialize<'de> for OneIndexed<Vec<T>>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserialize_padded(deserializer).map(Self)
+    }
+}
+
+impl<T> Serialize for OneIndexed<Vec<T>>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serialize_padded(&self.0, serializer)
+    }
+}
+
+impl<T> IdMap<T> {
+    /// Unwrap the entries.
+    pub fn into_inner(self) -> BTreeMap<i32, T> {
+        self.0
+    }
+
+    /// Returns the ID after the highest one, which is where RPG Maker adds new entries.
+    ///
+    /// This is 1 if the map is empty.
+    pub fn next_id(&self) -> i32 {
+        self.0.last_key_value().map_or(1, |(id, _)| id + 1)
+    }
+
+    /// Add an entry with the [next ID](IdMap::next_id), returning that ID.
+    pub fn insert_next(&mut self, value: T) -> i32 {
+        let id = self.next_id();
+        self.0.insert(id, value);
+        id
+    }
+}
+
+impl<T> Default for IdMap<T> {
+    fn default() -> Self {
+        Self(BTreeMap::new())
+    }
+}
+
+impl<T> Deref for IdMap<T> {
+    type Target = BTreeMap<i32, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for IdMap<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<BTreeMap<i32, T>> for IdMap<T> {
+    fn from(value: BTreeMap<i32, T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> FromIterator<(i32, T)> for IdMap<T> {
+    fn from_iter<I: IntoIterator<Item = (i32, T)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<T> IntoIterator for IdMap<T> {
+    type Item = (i32, T);
+    type IntoIter = std::collections::btree_map::IntoIter<i32, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a IdMap<T> {
+    type Item = (&'a i32, &'a T);
+    type IntoIter = std::collections::btree_map::Iter<'a, i32, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for IdMap<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        BTreeMap::deserialize(deserializer).map(Self)
+    }
+}
+
+impl<T> Serialize for IdMap<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        self.0.serialize(serializer)
+    }
+}
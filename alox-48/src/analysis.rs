@@ -0,0 +1,437 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Statistics about marshal data, gathered without deserializing it.
+//!
+//! alox-48 deserializes object links as copies of what they link to (see the crate docs),
+//! so data with a lot of links can take much more time and memory to deserialize than its size suggests.
+//! [`link_report`] counts the links in some marshal data, how much data copying them reads again,
+//! and which objects are linked to the most.
+//!
+//! ```
+//! use alox_48::{analysis::link_report, tag::Tag};
+//!
+//! // Marshal.dump(["hi"] * 3)
+//! let bytes = b"\x04\x08[\x08I\"\x07hi\x06:\x06ET@\x06@\x06";
+//! let report = link_report(bytes).unwrap();
+//!
+//! assert_eq!(report.objects, 2);
+//! assert_eq!(report.links, 2);
+//! assert_eq!(report.shared[0].tag, Tag::String);
+//! assert_eq!(report.shared[0].links, 2);
+//! // the string is 10 bytes, including its encoding, and both links copy it
+//! assert_eq!(report.copied_bytes, 20);
+//! ```
+//!
+//! [`value_stats`] does the same for a [`Value`] that has already been deserialized,
+//! counting what it contains and how much memory it uses.
+
+use std::cmp::Reverse;
+#[cfg(feature = "value")]
+use std::collections::HashMap;
+
+#[cfg(feature = "value")]
+use crate::{de::expect::ValueKind, schema::kind_of, RbString, Value};
+use crate::{
+    de::{Kind, Result, DEFAULT_DEPTH_LIMIT},
+    tag::Tag,
+    DeError, Deserializer, Sym,
+};
+
+/// How many strings [`ValueStats::largest_strings`] holds at most.
+#[cfg(feature = "value")]
+pub const LARGEST_STRINGS: usize = 10;
+
+/// How object links are used in some marshal data, returned by [`link_report`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LinkReport<'de> {
+    /// How many objects there are, which is how many values could be linked to.
+    pub objects: usize,
+    /// How many object links there are, including circular ones.
+    pub links: usize,
+    /// How many object links link to an object that contains them.
+    ///
+    /// [`Deserializer`] fails on these, unless it [tolerates broken links](Deserializer::set_tolerate_broken_links).
+    pub circular_links: usize,
+    /// The size of the marshal data, not counting anything after it.
+    pub size: usize,
+    /// How many bytes of marshal data are read again because of links.
+    ///
+    /// Links are deserialized by reading what they link to again, including the links inside that,
+    /// so this is roughly how much larger the data would be if every link was replaced by a copy.
+    /// Circular links don't count towards this, and it saturates at `usize::MAX` instead of overflowing.
+    pub copied_bytes: usize,
+    /// Every object that is linked to, the most linked to first.
+    ///
+    /// Objects that are linked to equally often are in the order they appear in.
+    pub shared: Vec<SharedObject<'de>>,
+}
+
+/// An object that is linked to, in a [`LinkReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedObject<'de> {
+    /// The index of the object in the object table, which links refer to it by.
+    pub index: usize,
+    /// The offset into the input where the object starts.
+    pub position: usize,
+    /// The tag of the object.
+    ///
+    /// An object with instance variables has the tag of the value they are attached to, not [`Tag::Instance`].
+    pub tag: Tag,
+    /// The class (or module, for [`Tag::Extended`]) of the object, if its tag has one.
+    pub class: Option<&'de Sym>,
+    /// How many times the object is linked to, not counting circular links.
+    pub links: usize,
+    /// How many bytes are read each time the object is copied, including what the links inside it copy.
+    pub size: usize,
+}
+
+impl SharedObject<'_> {
+    /// Returns how many bytes copying this object reads in total.
+    pub fn copied_bytes(&self) -> usize {
+        self.size.saturating_mul(self.links)
+    }
+}
+
+/// Count the object links in `data`, and how much copying them costs.
+///
+/// Nothing is deserialized, so this is fast even when deserializing `data` would copy a lot.
+/// The value is read with the [default depth limit](DEFAULT_DEPTH_LIMIT), and anything after it is ignored.
+///
+/// # Errors
+///
+/// Errors if `data` is malformed, or if a link refers to an object that doesn't exist.
+/// Circular links are counted in [`LinkReport::circular_links`] instead.
+pub fn link_report(data: &[u8]) -> Result<LinkReport<'_>> {
+    let mut walker = Walker {
+        input: Deserializer::new(data)?,
+        objects: vec![],
+        instance: None,
+        depth: 0,
+        links: 0,
+        circular_links: 0,
+    };
+    let copied_bytes = walker.value()?;
+
+    let mut shared: Vec<_> = walker
+        .objects
+        .iter()
+        .enumerate()
+        .filter(|(_, object)| object.links > 0)
+        .map(|(index, object)| SharedObject {
+            index,
+            position: object.position,
+            tag: object.tag,
+            class: object.class,
+            links: object.links,
+            size: object.size.unwrap_or_default(),
+        })
+        .collect();
+    // this is stable, so ties stay in index order
+    shared.sort_by_key(|object| Reverse(object.links));
+
+    Ok(LinkReport {
+        objects: walker.objects.len(),
+        links: walker.links,
+        circular_links: walker.circular_links,
+        size: walker.input.current_position(),
+        copied_bytes,
+        shared,
+    })
+}
+
+/// What a [`Value`] contains, returned by [`value_stats`].
+#[cfg(feature = "value")]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ValueStats<'a> {
+    /// How many values of each kind there are, including hash keys.
+    ///
+    /// Wrappers (instance variables, extended modules, and subclasses of builtin classes)
+    /// are counted as the kind of the value they wrap, like [`ValueKind`] describes.
+    pub kinds: HashMap<ValueKind, usize>,
+    /// How many bytes the strings hold in total, not counting regexes or user data.
+    pub string_bytes: usize,
+    /// The longest strings, longest first, up to [`LARGEST_STRINGS`] of them.
+    ///
+    /// Strings of the same length are in the order they appear in.
+    pub largest_strings: Vec<&'a RbString>,
+    /// Roughly how many bytes of memory the value uses, see [`Value::deep_size_of`].
+    pub size: usize,
+}
+
+#[cfg(feature = "value")]
+impl ValueStats<'_> {
+    /// Returns how many values of `kind` there are.
+    pub fn count(&self, kind: ValueKind) -> usize {
+        self.kinds.get(&kind).copied().unwrap_or_default()
+    }
+}
+
+/// Count what `value` contains, and how much memory it uses.
+///
+/// ```
+/// use alox_48::{analysis::value_stats, de::expect::ValueKind, marshal};
+///
+/// let value = marshal!(RPG::Actor { @name => "Aluxes", @items => [1, 2, "Potion"] });
+/// let stats = value_stats(&value);
+///
+/// assert_eq!(stats.count(ValueKind::Object), 1);
+/// assert_eq!(stats.count(ValueKind::Integer), 2);
+/// assert_eq!(stats.string_bytes, 12);
+/// assert_eq!(stats.largest_strings[0].data[..], *b"Aluxes");
+/// assert!(stats.size >= 12);
+/// ```
+#[cfg(feature = "value")]
+pub fn value_stats(value: &Value) -> ValueStats<'_> {
+    let mut stats = ValueStats {
+        size: value.deep_size_of(),
+        ..Default::default()
+    };
+    count_values(value, &mut stats);
+
+    // this is stable, so ties stay in the order they appear in
+    stats
+        .largest_strings
+        .sort_by_key(|string| Reverse(string.len()));
+    stats.largest_strings.truncate(LARGEST_STRINGS);
+    stats
+}
+
+#[cfg(feature = "value")]
+fn count_values<'a>(value: &'a Value, stats: &mut ValueStats<'a>) {
+    let wrapper = matches!(
+        value,
+        Value::Instance(_) | Value::Extended { .. } | Value::UserClass { .. }
+    );
+    if !wrapper {
+        *stats.kinds.entry(kind_of(value)).or_default() += 1;
+    }
+
+    match value {
+        Value::String(string) => {
+            stats.string_bytes += string.len();
+            stats.largest_strings.push(string);
+        }
+        Value::Array(array) => {
+            for value in array {
+                count_values(value, stats);
+            }
+        }
+        Value::Hash(hash) | Value::HashWithDefault { hash, .. } => {
+            for (key, value) in hash {
+                count_values(key, stats);
+                count_values(value, stats);
+            }
+            if let Value::HashWithDefault { default, .. } = value {
+                count_values(default, stats);
+            }
+        }
+        Value::Object(object) => {
+            for value in object.fields.values() {
+                count_values(value, stats);
+            }
+        }
+        Value::RbStruct(rb_struct) => {
+            for value in rb_struct.fields.values() {
+                count_values(value, stats);
+            }
+        }
+        Value::Instance(instance) => {
+            count_values(&instance.value, stats);
+            for value in instance.fields.values() {
+                count_values(value, stats);
+            }
+        }
+        Value::Extended { value, .. }
+        | Value::UserClass { value, .. }
+        | Value::UserMarshal { value, .. }
+        | Value::Data { value, .. } => count_values(value, stats),
+        Value::Nil
+        | Value::Bool(_)
+        | Value::Float(_)
+        | Value::Integer(_)
+        | Value::Symbol(_)
+        | Value::Userdata(_)
+        | Value::Regex { .. }
+        | Value::Class(_)
+        | Value::Module(_) => {}
+    }
+}
+
+// Reads values from some marshal data, keeping track of the objects in it.
+struct Walker<'de> {
+    input: Deserializer<'de>,
+    objects: Vec<Object<'de>>,
+    // the object the next value is part of, if it's the value of an instance.
+    // see `Deserializer::register_obj`
+    instance: Option<usize>,
+    depth: usize,
+    links: usize,
+    circular_links: usize,
+}
+
+struct Object<'de> {
+    position: usize,
+    tag: Tag,
+    class: Option<&'de Sym>,
+    links: usize,
+    // `None` until the whole object has been read
+    size: Option<usize>,
+}
+
+impl<'de> Walker<'de> {
+    // returns how many bytes the links in the value copy
+    fn value(&mut self) -> Result<usize> {
+        if self.depth >= DEFAULT_DEPTH_LIMIT {
+            return Err(DeError::from(Kind::DepthLimitExceeded(DEFAULT_DEPTH_LIMIT)));
+        }
+        self.depth += 1;
+        let result = self.value_inner();
+        self.depth -= 1;
+        result
+    }
+
+    fn value_inner(&mut self) -> Result<usize> {
+        let position = self.input.current_position();
+        let tag = self.input.peek_value_tag()?;
+
+        // the object this value is (part of), and whether it starts here
+        let (object, registered) = match self.instance.take() {
+            Some(index) => {
+                self.objects[index].tag = tag;
+                (Some(index), false)
+            }
+            None if tag.is_object_link_referenceable() => {
+                self.objects.push(Object {
+                    position,
+                    tag,
+                    class: None,
+                    links: 0,
+                    size: None,
+                });
+                (Some(self.objects.len() - 1), true)
+            }
+            None => (None, false),
+        };
+
+        let input = &mut self.input;
+        input.cursor.next_byte()?;
+
+        let copied = match tag {
+            Tag::Nil | Tag::True | Tag::False => 0,
+            Tag::Integer => {
+                input.read_packed_int()?;
+                0
+            }
+            Tag::Bignum => {
+                input.read_bignum_raw()?;
+                0
+            }
+            Tag::Symbol => {
+                input.read_symbol()?;
+                0
+            }
+            Tag::Symlink => {
+                input.read_symlink()?;
+                0
+            }
+            Tag::Float | Tag::String | Tag::ClassRef | Tag::ModuleRef => {
+                input.read_bytes_len()?;
+                0
+            }
+            Tag::RawRegexp => {
+                input.read_bytes_len()?;
+                input.cursor.next_byte()?;
+                0
+            }
+            Tag::ObjectLink => {
+                let index = input.read_usize()?;
+                self.link(index)?
+            }
+            Tag::UserDef => {
+                let class = input.read_class_name()?;
+                input.read_bytes_len()?;
+                self.set_class(object, class);
+                0
+            }
+            Tag::Array => {
+                let len = input.read_usize()?;
+                self.values(len)?
+            }
+            Tag::Hash | Tag::HashDefault => {
+                let len = input.read_usize()?;
+                let copied = self.values(len * 2)?;
+                if tag == Tag::HashDefault {
+                    copied.saturating_add(self.value()?)
+                } else {
+                    copied
+                }
+            }
+            Tag::Object | Tag::Struct => {
+                let class = input.read_class_name()?;
+                self.set_class(object, class);
+                self.ivars()?
+            }
+            Tag::Instance => {
+                self.instance = object;
+                self.value()?.saturating_add(self.ivars()?)
+            }
+            Tag::Extended | Tag::UserClass | Tag::UserMarshal | Tag::Data => {
+                let class = input.read_class_name()?;
+                self.set_class(object, class);
+                self.value()?
+            }
+        };
+
+        if let (Some(index), true) = (object, registered) {
+            let size = self.input.current_position() - position;
+            self.objects[index].size = Some(size.saturating_add(copied));
+        }
+        Ok(copied)
+    }
+
+    // returns how many bytes following the link copies
+    fn link(&mut self, index: usize) -> Result<usize> {
+        self.links += 1;
+        let object = self
+            .objects
+            .get_mut(index)
+            .ok_or(DeError::from(Kind::UnresolvedObjectlink(index)))?;
+
+        if let Some(size) = object.size {
+            object.links += 1;
+            Ok(size)
+        } else {
+            // the object hasn't been read yet, so the link is inside it
+            self.circular_links += 1;
+            Ok(0)
+        }
+    }
+
+    fn values(&mut self, len: usize) -> Result<usize> {
+        let mut copied = 0usize;
+        for _ in 0..len {
+            copied = copied.saturating_add(self.value()?);
+        }
+        Ok(copied)
+    }
+
+    fn ivars(&mut self) -> Result<usize> {
+        let len = self.input.read_usize()?;
+        let mut copied = 0usize;
+        for _ in 0..len {
+            self.input.read_symbol_either()?;
+            copied = copied.saturating_add(self.value()?);
+        }
+        Ok(copied)
+    }
+
+    fn set_class(&mut self, object: Option<usize>, class: &'de Sym) {
+        if let Some(index) = object {
+            self.objects[index].class = Some(class);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{collections::HashMap, hash::BuildHasher};
+
+use crate::{Sym, Symbol};
+
+/// Renames classes and modules as they are read or written.
+///
+/// Set one with [`Deserializer::set_class_mapper`](crate::Deserializer::set_class_mapper)
+/// or [`Serializer::set_class_mapper`](crate::Serializer::set_class_mapper).
+/// This is handy for moving data between namespaces, like `RPG::Actor` to `Game::Actor`.
+///
+/// Every class and module name is passed through the mapper:
+/// the classes of objects, structs, user classes, user data, user marshal objects, and C extension data,
+/// modules extending a value, and class and module references.
+///
+/// A [`HashMap`] from old names to new names is a mapper.
+///
+/// ```
+/// # #[cfg(feature = "value")] {
+/// use std::collections::HashMap;
+/// use alox_48::{Deserializer, Symbol, Value};
+///
+/// let bytes = alox_48::to_bytes(alox_48::marshal!(RPG::Actor { @id => 1 })).unwrap();
+///
+/// let mapper = HashMap::from([(Symbol::from("RPG::Actor"), Symbol::from("Game::Actor"))]);
+/// let mut deserializer = Deserializer::new(&bytes).unwrap();
+/// deserializer.set_class_mapper(&mapper);
+///
+/// let actor: Value = deserializer.deserialize_value().unwrap();
+/// assert_eq!(actor.as_object().unwrap().class, "Game::Actor");
+/// # }
+/// ```
+pub trait ClassMapper: std::fmt::Debug + Send + Sync {
+    /// Returns the name to use instead of `class`, or `None` to keep it.
+    ///
+    /// Deserializers hand out borrowed names, so the new name has to be owned by the mapper (or be part of `class`).
+    fn map_class<'a>(&'a self, class: &'a Sym) -> Option<&'a Sym>;
+}
+
+impl<S> ClassMapper for HashMap<Symbol, Symbol, S>
+where
+    S: BuildHasher + Send + Sync,
+{
+    fn map_class<'a>(&'a self, class: &'a Sym) -> Option<&'a Sym> {
+        self.get(class).map(Symbol::as_sym)
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::{
+    DeserializeSeed, DeserializerTrait, Error, HashAccess, IvarAccess, Result, Visitor,
+    VisitorInstance, VisitorOption,
+};
+use crate::Sym;
+#[cfg(feature = "value")]
+use crate::Value;
+
+/// A deserializer for an object or struct whose class has already been read.
+///
+/// Visitors that pick a type based on the class use this to hand the rest of the object to that type.
+/// The derived `Deserialize` impl for enums uses this to deserialize variants.
+#[derive(Debug)]
+pub struct ObjectDeserializer<'de, A> {
+    class: &'de Sym,
+    access: A,
+    is_struct: bool,
+}
+
+/// A deserializer for user data whose class has already been read.
+///
+/// Like [`ObjectDeserializer`], but for the data passed to [`Visitor::visit_user_data`].
+#[derive(Debug, Clone, Copy)]
+pub struct UserDataDeserializer<'de> {
+    class: &'de Sym,
+    data: &'de [u8],
+}
+
+/// Reads a hash with symbol keys as if it were instance variables.
+///
+/// Some ruby code stores data in a hash like `{ name: "Aluxes", level: 1 }` instead of an object.
+/// Passing this to [`Visitor::visit_object`] lets the keys be read like the instance variables of that object
+/// (without the `@`, like struct members).
+/// The derived `Deserialize` impl for structs uses this for the `from_hash` attribute.
+///
+/// Keys that aren't symbols error.
+#[derive(Debug)]
+pub struct HashIvarAccess<A> {
+    access: A,
+}
+
+impl<A> HashIvarAccess<A> {
+    /// Read the entries of `map` as instance variables.
+    pub fn new(map: A) -> Self {
+        Self { access: map }
+    }
+
+    /// Unwrap the hash, to read it without this adapter.
+    pub fn into_inner(self) -> A {
+        self.access
+    }
+}
+
+/// Checks that the members of a ruby struct match its definition.
+///
+/// Ruby only loads a struct if it has the same members, in the same order, as the struct's definition.
+/// Passing this to [`Visitor::visit_object`] does the same check on the members passed to [`Visitor::visit_struct`],
+/// erroring with [`Kind::IncompatibleStruct`](super::Kind::IncompatibleStruct) at the first member that doesn't match.
+/// The derived `Deserialize` impl for structs uses this for the `ruby_struct` attribute.
+#[derive(Debug)]
+pub struct StructMembers<'de, 'm, A> {
+    name: &'de Sym,
+    expected: &'m [&'m Sym],
+    access: A,
+    index: usize,
+}
+
+impl<'de, 'm, A> StructMembers<'de, 'm, A> {
+    /// Check that the members of the struct `name` are `expected`, in that order.
+    pub fn new(name: &'de Sym, expected: &'m [&'m Sym], members: A) -> Self {
+        Self {
+            name,
+            expected,
+            access: members,
+            index: 0,
+        }
+    }
+
+    /// Unwrap the members, to read them without checking them.
+    pub fn into_inner(self) -> A {
+        self.access
+    }
+}
+
+impl<'de, A> ObjectDeserializer<'de, A>
+where
+    A: IvarAccess<'de>,
+{
+    /// Replays an object of `class` with these instance variables.
+    pub fn object(class: &'de Sym, instance_variables: A) -> Self {
+        Self {
+            class,
+            access: instance_variables,
+            is_struct: false,
+        }
+    }
+
+    /// Replays a struct named `name` with these members.
+    pub fn rb_struct(name: &'de Sym, members: A) -> Self {
+        Self {
+            class: name,
+            access: members,
+            is_struct: true,
+        }
+    }
+
+    /// Unwrap the instance variables, to read them without a visitor.
+    pub fn into_inner(self) -> A {
+        self.access
+    }
+}
+
+impl<'de> UserDataDeserializer<'de> {
+    /// Replays user data of `class`.
+    pub fn new(class: &'de Sym, data: &'de [u8]) -> Self {
+        Self { class, data }
+    }
+}
+
+impl<'de, A> DeserializerTrait<'de> for ObjectDeserializer<'de, A>
+where
+    A: IvarAccess<'de>,
+{
+    fn deserialize<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.is_struct {
+            visitor.visit_struct(self.class, self.access)
+        } else {
+            visitor.visit_object(self.class, self.access)
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorOption<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_instance<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorInstance<'de>,
+    {
+        visitor.visit(self)
+    }
+}
+
+impl<'de> DeserializerTrait<'de> for UserDataDeserializer<'de> {
+    fn deserialize<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_user_data(self.class, self.data)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorOption<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_instance<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorInstance<'de>,
+    {
+        visitor.visit(self)
+    }
+}
+
+impl<'de, A> IvarAccess<'de> for HashIvarAccess<A>
+where
+    A: HashAccess<'de>,
+{
+    fn next_ivar(&mut self) -> Result<Option<&'de Sym>> {
+        self.access.next_key()
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.access.next_value_seed(seed)
+    }
+
+    fn len(&self) -> usize {
+        self.access.len()
+    }
+
+    fn index(&self) -> usize {
+        self.access.index()
+    }
+}
+
+impl<'de, A> IvarAccess<'de> for StructMembers<'de, '_, A>
+where
+    A: IvarAccess<'de>,
+{
+    fn next_ivar(&mut self) -> Result<Option<&'de Sym>> {
+        let found = self.access.next_ivar()?;
+        let expected = self.expected.get(self.index).copied();
+        if found != expected {
+            return Err(Error::incompatible_struct(
+                self.name, self.index, expected, found,
+            ));
+        }
+        self.index += 1;
+        Ok(found)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.access.next_value_seed(seed)
+    }
+
+    #[cfg(feature = "value")]
+    fn next_value_seed_or_capture<V>(
+        &mut self,
+        seed: V,
+    ) -> Result<std::result::Result<V::Value, Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.access.next_value_seed_or_capture(seed)
+    }
+
+    fn skip_value(&mut self) -> Result<()> {
+        self.access.skip_value()
+    }
+
+    fn len(&self) -> usize {
+        self.access.len()
+    }
+
+    fn index(&self) -> usize {
+        self.access.index()
+    }
+}
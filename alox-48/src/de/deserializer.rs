@@ -9,11 +9,28 @@
 #![allow(clippy::cast_possible_wrap)]
 #![allow(clippy::cast_lossless)]
 
-use super::{ignored::Ignored, DeserializeSeed, Error, Kind, Result};
-use crate::{tag::Tag, Deserialize, Sym, Visitor};
+#[cfg(feature = "value")]
+use std::collections::HashSet;
+use std::{
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{
+    ignored::Ignored, lenient::VisitorOnce, DeserializeSeed, Error, Kind, PathSegment, Progress,
+    Recovered, Result, Skipped,
+};
+#[cfg(feature = "value")]
+use crate::Value;
+use crate::{tag::Tag, ClassMapper, Deserialize, Sym, Visitor, MARSHAL_VERSION};
 
 /// The alox-48 deserializer.
 #[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Deserializer<'de> {
     pub(crate) cursor: Cursor<'de>,
 
@@ -22,8 +39,72 @@ pub struct Deserializer<'de> {
     is_reading_instance: bool,
 
     sym_table: Vec<&'de Sym>,
+    version: [u8; 2],
+
+    depth: usize,
+    depth_limit: usize,
+
+    time_budget: Option<Duration>,
+    deadline: Option<Instant>,
+
+    progress: Option<Progress<'de>>,
+    // the position progress is next reported at
+    next_progress: usize,
+    cancel_token: Option<Arc<AtomicBool>>,
+
+    strict: bool,
+    deny_duplicates: bool,
+    track_path: bool,
+    stats: DeserializerStats,
+
+    lenient: bool,
+    tolerate_broken_links: bool,
+    recovered: Vec<Recovered>,
+    // set while skipping a value that failed, so broken links don't stop the skip
+    recovering: bool,
+    // set once a lenient deserializer can no longer tell where values start
+    truncated: bool,
+
+    class_mapper: Option<&'de dyn ClassMapper>,
 }
 
+/// Counts of data a [`Deserializer`] skipped over because nothing deserialized it.
+///
+/// A nonzero count usually means the rust types are missing something present in the ruby data.
+/// Encoding instance variables on strings are not counted, as they are consumed when converting to a rust string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeserializerStats {
+    /// Skipped array elements.
+    pub elements: usize,
+    /// Skipped hash entries.
+    pub entries: usize,
+    /// Skipped instance variables and struct members, including unknown fields ignored by derived impls.
+    pub ivars: usize,
+    /// Skipped hash default values.
+    pub hash_defaults: usize,
+}
+
+impl DeserializerStats {
+    /// Returns the total amount of skipped data.
+    pub fn total(&self) -> usize {
+        self.elements + self.entries + self.ivars + self.hash_defaults
+    }
+
+    fn record(&mut self, skipped: Skipped) {
+        match skipped {
+            Skipped::Element => self.elements += 1,
+            Skipped::Entry => self.entries += 1,
+            Skipped::Ivar => self.ivars += 1,
+            Skipped::HashDefault => self.hash_defaults += 1,
+        }
+    }
+}
+
+/// The default nesting limit used by [`Deserializer`].
+///
+/// Real world data is nowhere near this deep, but it's low enough to not overflow the stack on malicious input.
+pub const DEFAULT_DEPTH_LIMIT: usize = 128;
+
 #[derive(Debug, Clone)]
 pub(crate) struct Cursor<'de> {
     pub(crate) input: &'de [u8],
@@ -42,6 +123,10 @@ struct IvarAccess<'de, 'a> {
     len: usize,
     index: &'a mut usize,
     state: MapState,
+    // the ivar whose value is next, for tracking paths
+    ivar: Option<&'de Sym>,
+    // only filled when denying duplicates
+    seen: Vec<&'de Sym>,
 }
 
 struct ArrayAccess<'de, 'a> {
@@ -55,6 +140,11 @@ struct HashAccess<'de, 'a> {
     len: usize,
     index: &'a mut usize,
     state: MapState,
+    // whether there is a default value that hasn't been deserialized yet
+    default: &'a mut bool,
+    // only filled when denying duplicates
+    #[cfg(feature = "value")]
+    seen: HashSet<Value>,
 }
 
 enum MapState {
@@ -62,6 +152,19 @@ enum MapState {
     Value,
 }
 
+// everything needed to read a value again after failing to deserialize it
+#[derive(Clone, Copy)]
+struct Snapshot {
+    position: usize,
+    objtable: usize,
+    stack: usize,
+    sym_table: usize,
+    is_reading_instance: bool,
+    stats: DeserializerStats,
+    recovered: usize,
+    truncated: bool,
+}
+
 impl<'de> Cursor<'de> {
     fn new(input: &'de [u8]) -> Self {
         Self { input, position: 0 }
@@ -75,10 +178,10 @@ impl<'de> Cursor<'de> {
         self.input
             .get(self.position)
             .copied()
-            .ok_or(Error { kind: Kind::Eof })
+            .ok_or(Error::from(Kind::Eof))
     }
 
-    fn next_byte(&mut self) -> Result<u8> {
+    pub(crate) fn next_byte(&mut self) -> Result<u8> {
         let byte = self.peek_byte()?;
         self.position += 1;
         Ok(byte)
@@ -86,24 +189,20 @@ impl<'de> Cursor<'de> {
 
     fn peek_tag(&self) -> Result<Tag> {
         let byte = self.peek_byte()?;
-        Tag::from_u8(byte).ok_or(Error {
-            kind: Kind::WrongTag(byte),
-        })
+        Tag::from_u8(byte).ok_or(Error::from(Kind::WrongTag(byte)))
     }
 
-    fn next_tag(&mut self) -> Result<Tag> {
+    pub(super) fn next_tag(&mut self) -> Result<Tag> {
         let byte = self.next_byte()?;
-        Tag::from_u8(byte).ok_or(Error {
-            kind: Kind::WrongTag(byte),
-        })
+        Tag::from_u8(byte).ok_or(Error::from(Kind::WrongTag(byte)))
     }
 
     fn next_bytes_dyn(&mut self, length: usize) -> Result<&'de [u8]> {
-        if length > self.input.len() {
-            return Err(Error { kind: Kind::Eof });
-        }
-
-        let ret = &self.input[self.position..self.position + length];
+        let ret = self
+            .position
+            .checked_add(length)
+            .and_then(|end| self.input.get(self.position..end))
+            .ok_or(Error::from(Kind::Eof))?;
         self.position += length;
         Ok(ret)
     }
@@ -117,18 +216,30 @@ impl<'de> Deserializer<'de> {
     ///
     /// Will error if the input has a version number != to 4.8.
     /// The first two bytes of marshal data encode the version number. [major, minor]
+    /// Use [`Deserializer::with_versions`] to accept other versions.
     pub fn new(input: &'de [u8]) -> Result<Self> {
+        Self::with_versions(input, MARSHAL_VERSION..=MARSHAL_VERSION)
+    }
+
+    /// Create a new deserializer with the given input, accepting any version number in `versions`.
+    ///
+    /// Versions are compared as `[major, minor]`, so `[4, 6]..=[4, 8]` accepts 4.6, 4.7, and 4.8.
+    /// Older versions of marshal are almost identical to 4.8, so data written by old rubies (like 1.6, which writes 4.6) usually loads fine.
+    ///
+    /// # Errors
+    /// Will error if the input has a len < 1.
+    ///
+    /// Will error if the input has a version number outside of `versions`.
+    pub fn with_versions(input: &'de [u8], versions: RangeInclusive<[u8; 2]>) -> Result<Self> {
         let mut cursor = Cursor::new(input);
         if input.len() < 2 {
-            return Err(Error { kind: Kind::Eof });
+            return Err(Error::from(Kind::Eof));
         }
 
         let v1 = cursor.next_byte()?;
         let v2 = cursor.next_byte()?;
-        if [v1, v2] != [4, 8] {
-            return Err(Error {
-                kind: Kind::VersionError([v1, v2]),
-            });
+        if !versions.contains(&[v1, v2]) {
+            return Err(Error::from(Kind::VersionError([v1, v2])));
         }
 
         Ok(Self {
@@ -136,12 +247,273 @@ impl<'de> Deserializer<'de> {
 
             objtable: vec![],
             sym_table: vec![],
+            version: [v1, v2],
             is_reading_instance: false,
 
             stack: vec![],
+
+            depth: 0,
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+
+            time_budget: None,
+            deadline: None,
+
+            progress: None,
+            next_progress: 0,
+            cancel_token: None,
+
+            strict: false,
+            deny_duplicates: false,
+            track_path: false,
+            stats: DeserializerStats::default(),
+
+            lenient: false,
+            tolerate_broken_links: false,
+            recovered: vec![],
+            recovering: false,
+            truncated: false,
+
+            class_mapper: None,
         })
     }
 
+    /// Returns the version number of the input, as `[major, minor]`.
+    pub fn version(&self) -> [u8; 2] {
+        self.version
+    }
+
+    /// Set how deeply values may be nested before deserialization fails.
+    ///
+    /// Every value (array, hash, object, etc) that contains another value adds a level of nesting.
+    /// Exceeding the limit will return [`Kind::DepthLimitExceeded`] instead of overflowing the stack.
+    ///
+    /// Defaults to [`DEFAULT_DEPTH_LIMIT`].
+    pub fn set_depth_limit(&mut self, limit: usize) {
+        self.depth_limit = limit;
+    }
+
+    /// Returns how deeply values may be nested before deserialization fails.
+    pub fn depth_limit(&self) -> usize {
+        self.depth_limit
+    }
+
+    /// Set how long deserialization may take before failing.
+    ///
+    /// The budget starts when the first value is deserialized, and is checked before each value after that.
+    /// Running out of time will return [`Kind::TimeBudgetExceeded`] with the offset deserialization stopped at.
+    ///
+    /// This is intended for interactive tools that would rather give up than freeze on pathological input.
+    /// Wrap this deserializer in a [`crate::path_to_error::Deserializer`] to also find out *where* in the data it stopped.
+    /// There is no time budget by default.
+    pub fn set_time_budget(&mut self, budget: Option<Duration>) {
+        self.time_budget = budget;
+        self.deadline = None;
+    }
+
+    /// Returns how long deserialization may take before failing.
+    pub fn time_budget(&self) -> Option<Duration> {
+        self.time_budget
+    }
+
+    /// Set a callback to report how much of the input has been read, and to cancel deserialization with.
+    ///
+    /// Progress is reported before the next value once enough input was read since the last report (see [`Progress`]).
+    /// Cancelling will return [`Kind::Cancelled`] with the offset deserialization stopped at.
+    /// This is intended for interactive tools that want to show a progress bar while loading large files.
+    /// Progress is not reported by default.
+    pub fn set_progress(&mut self, progress: Option<Progress<'de>>) {
+        self.progress = progress;
+        self.next_progress = 0;
+    }
+
+    /// Returns the callback progress is reported to.
+    pub fn progress(&self) -> Option<Progress<'de>> {
+        self.progress
+    }
+
+    /// Set a token that cancels deserialization once it is set to `true`, from this thread or another one.
+    ///
+    /// The token is checked before each value, and cancelling will return [`Kind::Cancelled`] with the offset deserialization stopped at.
+    /// Use [`set_progress`](Self::set_progress) instead to decide whether to cancel from this thread.
+    /// There is no cancel token by default.
+    ///
+    /// ```
+    /// use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+    ///
+    /// use alox_48::{de::Kind, Deserializer};
+    ///
+    /// let bytes = alox_48::to_bytes(vec![1, 2, 3]).unwrap();
+    /// let token = Arc::new(AtomicBool::new(false));
+    ///
+    /// let mut deserializer = Deserializer::new(&bytes).unwrap();
+    /// deserializer.set_cancel_token(Some(token.clone()));
+    /// token.store(true, Ordering::Relaxed);
+    ///
+    /// let error = deserializer.deserialize_value::<Vec<i32>>().unwrap_err();
+    /// assert!(matches!(error.kind, Kind::Cancelled(2)));
+    /// ```
+    pub fn set_cancel_token(&mut self, token: Option<Arc<AtomicBool>>) {
+        self.cancel_token = token;
+    }
+
+    /// Returns the token that cancels deserialization.
+    pub fn cancel_token(&self) -> Option<&Arc<AtomicBool>> {
+        self.cancel_token.as_ref()
+    }
+
+    /// Set whether skipping data is an error.
+    ///
+    /// Data is skipped when a visitor doesn't deserialize every element, entry, or instance variable it was given,
+    /// or when a derived impl ignores an unknown field.
+    /// In strict mode this returns [`Kind::SkippedData`] instead, otherwise it is counted in [`Deserializer::stats`].
+    ///
+    /// Strict mode is off by default.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Returns whether skipping data is an error.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Set whether repeated instance variables and hash keys are an error.
+    ///
+    /// Ruby never writes these, but malformed data can contain them,
+    /// and deserializing into a map or [`RbFields`](crate::RbFields) silently keeps only the last one.
+    /// When denied, repeated instance variables return [`Kind::DuplicateField`] and repeated hash keys return [`Kind::DuplicateKey`].
+    ///
+    /// Checking hash keys reads every key twice, and needs the `value` feature. Duplicates are allowed by default.
+    pub fn set_deny_duplicates(&mut self, deny: bool) {
+        self.deny_duplicates = deny;
+    }
+
+    /// Returns whether repeated instance variables and hash keys are an error.
+    pub fn denies_duplicates(&self) -> bool {
+        self.deny_duplicates
+    }
+
+    /// Set whether errors record where in the data they happened.
+    ///
+    /// Errors record the array indices, hash entries, and instance variables leading to them in [`Error::path`],
+    /// which is included when they're displayed: `@events{2}.@pages[0].@list: invalid type: ...`.
+    /// The error's `kind` is the same either way.
+    ///
+    /// This is a lighter alternative to [`crate::path_to_error`], which records more but has to wrap every value.
+    /// Paths are only built as an error is returned, so they're nearly free until something fails.
+    /// Paths are not tracked by default.
+    pub fn set_track_path(&mut self, track: bool) {
+        self.track_path = track;
+    }
+
+    /// Returns whether errors record where in the data they happened.
+    pub fn tracks_path(&self) -> bool {
+        self.track_path
+    }
+
+    // adds a path segment to errors coming out of a nested value
+    fn track<T>(&self, result: Result<T>, segment: impl FnOnce() -> PathSegment) -> Result<T> {
+        match result {
+            Err(err) if self.track_path => Err(err.at(segment())),
+            result => result,
+        }
+    }
+
+    /// Set whether values that fail to parse are replaced with `nil` instead of failing.
+    ///
+    /// This is meant for salvaging as much as possible out of corrupted data.
+    /// When a value is malformed (an unknown type byte, a broken object or symbol link, running out of input, etc)
+    /// the error is recorded in [`Deserializer::recovered`], and the value is deserialized as `nil`.
+    ///
+    /// Marshal data doesn't record how long values are, so finding the next value isn't always possible.
+    /// Values that can still be skipped over are replaced with `nil` and deserialization carries on after them.
+    /// Otherwise, everything after the broken value is treated as missing:
+    /// arrays, hashes, and objects containing it end early, and the rest of the input is ignored.
+    ///
+    /// Only values that fail before reaching a visitor are recovered from, so this works best with [`Value`]
+    /// and other types that accept `nil`. Errors from visitors, like a value of the wrong type, are returned as usual.
+    /// Exceeding the depth limit or time budget is never recovered from.
+    ///
+    /// Lenient mode is off by default.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Returns whether values that fail to parse are replaced with `nil`.
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Set whether object links that can't be followed are deserialized as `nil` instead of failing.
+    ///
+    /// Damaged files, or files written by buggy tools, can have links that are off by a few objects.
+    /// When tolerated, links to objects that don't exist ([`Kind::UnresolvedObjectlink`])
+    /// and links to an object that contains them ([`Kind::CircularReference`]) are deserialized as `nil`
+    /// (or `None`, for an `Option`), and the error is recorded in [`Deserializer::recovered`] so it can be reported as a warning.
+    ///
+    /// Unlike [`Deserializer::set_lenient`], nothing else is recovered from.
+    /// Broken links are not tolerated by default.
+    pub fn set_tolerate_broken_links(&mut self, tolerate: bool) {
+        self.tolerate_broken_links = tolerate;
+    }
+
+    /// Returns whether object links that can't be followed are deserialized as `nil`.
+    pub fn tolerates_broken_links(&self) -> bool {
+        self.tolerate_broken_links
+    }
+
+    /// Returns every error recovered from so far, in the order they happened.
+    ///
+    /// See [`Deserializer::set_lenient`] and [`Deserializer::set_tolerate_broken_links`].
+    pub fn recovered(&self) -> &[Recovered] {
+        &self.recovered
+    }
+
+    /// Takes every error recovered from so far, leaving none behind.
+    pub fn take_recovered(&mut self) -> Vec<Recovered> {
+        std::mem::take(&mut self.recovered)
+    }
+
+    /// Set a mapper that renames classes and modules as they are read.
+    ///
+    /// Visitors only see the new names. See [`ClassMapper`] for which names are renamed.
+    pub fn set_class_mapper(&mut self, mapper: &'de dyn ClassMapper) {
+        self.class_mapper = Some(mapper);
+    }
+
+    /// Returns the mapper renaming classes and modules, if there is one.
+    pub fn class_mapper(&self) -> Option<&'de dyn ClassMapper> {
+        self.class_mapper
+    }
+
+    fn map_class(&self, class: &'de Sym) -> &'de Sym {
+        self.class_mapper
+            .and_then(|mapper| mapper.map_class(class))
+            .unwrap_or(class)
+    }
+
+    /// Returns how much data has been skipped so far.
+    pub fn stats(&self) -> DeserializerStats {
+        self.stats
+    }
+
+    /// Returns every symbol read so far, in the order they appeared.
+    ///
+    /// This is ruby's symbol table: symbol links refer to symbols by their index in it.
+    /// It contains every class name and instance variable name in the data that was read,
+    /// so reading a value with [`Ignored`](super::Ignored) is a cheap way to find out what a file contains.
+    pub fn symbols(&self) -> &[&'de Sym] {
+        &self.sym_table
+    }
+
+    /// Returns the offset into [`Deserializer::data`] of every object read so far.
+    ///
+    /// This is ruby's object table: object links refer to objects by their index in it.
+    /// Each offset points at the type byte of the object.
+    pub fn objects(&self) -> &[usize] {
+        &self.objtable
+    }
+
     /// Deserialize a value from the input.
     pub fn deserialize_value<T>(&mut self) -> Result<T>
     where
@@ -150,6 +522,13 @@ impl<'de> Deserializer<'de> {
         T::deserialize(self)
     }
 
+    /// Finish deserializing, returning how many bytes of input were not used.
+    ///
+    /// Marshal data holds a single value, so anything left over after deserializing it is usually garbage.
+    pub fn finish(self) -> usize {
+        self.cursor.input.len() - self.cursor.position
+    }
+
     /// Returns the current position of the deserializer.
     ///
     /// This is useful for debugging.
@@ -164,60 +543,123 @@ impl<'de> Deserializer<'de> {
         self.cursor.input
     }
 
-    fn read_packed_int(&mut self) -> Result<i32> {
-        // The bounds of a Ruby Marshal packed integer are [-(2**30), 2**30 - 1], anything beyond that
-        // gets serialized as a bignum.
+    // Hash entries skip both the key and value.
+    fn skip(&mut self, skipped: Skipped) -> Result<()> {
+        if self.truncated {
+            return Ok(());
+        }
+        if self.strict {
+            return Err(Error::from(Kind::SkippedData(
+                skipped,
+                self.cursor.position,
+            )));
+        }
+        self.stats.record(skipped);
+
+        Ignored::deserialize(&mut *self)?;
+        if skipped == Skipped::Entry {
+            Ignored::deserialize(&mut *self)?;
+        }
+        Ok(())
+    }
+
+    fn skip_ivar(&mut self) -> Result<()> {
+        if self.truncated {
+            return Ok(());
+        }
+        let ivar = self.read_symbol_either()?;
+        // the encoding of strings is used up by converting to a rust string
+        if is_encoding(ivar) {
+            Ignored::deserialize(&mut *self).map(|_| ())
+        } else {
+            self.skip(Skipped::Ivar)
+        }
+    }
+
+    pub(crate) fn read_packed_int(&mut self) -> Result<i32> {
+        // Ruby only writes integers in [-(2**31), 2**31 - 1] like this, anything beyond that
+        // gets serialized as a bignum. That's exactly the bounds of an i32.
         //
-        // The bounds of an i32 are [-(2**31), 2**31 - 1], so we should be safe.
+        // Four bytes can hold values outside of that though, so they're read as an i64 and checked.
         let c = self.cursor.next_byte()? as i8;
 
-        Ok(match c {
+        let x: i64 = match c {
             0 => 0,
             5..=127 => (c - 5) as _,
             -128..=-5 => (c + 5) as _,
+            // c is the amount of little endian bytes that follow
             1..=4 => {
                 let mut x = 0;
-
                 for i in 0..c {
-                    let n = self.cursor.next_byte()? as i32;
-                    let n = n << (8 * i);
-                    x |= n;
+                    let byte = i64::from(self.cursor.next_byte()?);
+                    x |= byte << (8 * i);
                 }
-
                 x
             }
+            // -c is the amount of bytes that follow, replacing the low bytes of -1
             -4..=-1 => {
                 let mut x = -1;
-
                 for i in 0..-c {
-                    let a = !(0xFF << (8 * i)); // wtf is this magic
-                    let b = self.cursor.next_byte()? as i32;
-                    let b = b << (8 * i);
-
-                    x = (x & a) | b;
+                    let byte = i64::from(self.cursor.next_byte()?);
+                    x &= !(0xFF << (8 * i));
+                    x |= byte << (8 * i);
                 }
-
                 x
             }
-        })
+        };
+
+        i32::try_from(x).map_err(|_| Error::from(Kind::PackedIntOutOfRange(x)))
     }
 
-    #[allow(clippy::panic_in_result_fn)]
-    fn read_float(&mut self) -> Result<f64> {
-        let out = self.read_bytes_len()?;
+    // Does not read the tag. Returns the sign and the little endian magnitude.
+    pub(crate) fn read_bignum_raw(&mut self) -> Result<(u8, &'de [u8])> {
+        let sign = self.cursor.next_byte()?;
+        // the length is in 16 bit words, not bytes
+        let len = self.read_usize()?.saturating_mul(2);
+        let bytes = self.cursor.next_bytes_dyn(len)?;
+        Ok((sign, bytes))
+    }
 
-        if let Some(terminator_idx) = out.iter().position(|v| *v == 0) {
-            let (str, [0, mantissa @ ..]) = out.split_at(terminator_idx) else {
-                unreachable!();
-            };
-            let float = str::parse::<f64>(&String::from_utf8_lossy(str)).map_err(|err| Error {
-                kind: Kind::Message(err.to_string()),
-            })?;
+    // Does not read the tag.
+    pub(crate) fn read_bignum(&mut self) -> Result<i64> {
+        let (sign, bytes) = self.read_bignum_raw()?;
+
+        let out_of_range = || Error::from(Kind::BignumOutOfRange);
+        let mut magnitude = 0u64;
+        for (i, &byte) in bytes.iter().enumerate() {
+            if i >= 8 && byte != 0 {
+                return Err(out_of_range());
+            }
+            if i < 8 {
+                magnitude |= u64::from(byte) << (8 * i);
+            }
+        }
+
+        // like ruby, anything other than `+` is negative
+        if sign == b'+' {
+            i64::try_from(magnitude).map_err(|_| out_of_range())
+        } else {
+            0i64.checked_sub_unsigned(magnitude)
+                .ok_or_else(out_of_range)
+        }
+    }
+
+    pub(super) fn read_float(&mut self) -> Result<f64> {
+        let out = self.read_bytes_len()?;
+        let offset = self.cursor.position - out.len();
+        let parse_error = |bytes: &[u8]| {
+            Error::from(Kind::ParseFloat(Box::new(super::ParseFloat {
+                offset,
+                bytes: bytes.to_vec(),
+            })))
+        };
+
+        if let Some(terminator_idx) = find_terminator(out) {
+            let (str, mantissa) = (&out[..terminator_idx], &out[terminator_idx + 1..]);
+            let float = parse_float(str).ok_or_else(|| parse_error(str))?;
             let transmuted = u64::from_ne_bytes(float.to_ne_bytes());
             if mantissa.len() > 4 {
-                return Err(Error {
-                    kind: Kind::ParseFloatMantissaTooLong,
-                });
+                return Err(Error::from(Kind::ParseFloatMantissaTooLong));
             }
             let (mantissa, mask) = mantissa.iter().fold((0u64, 0u64), |(acc, mask), v| {
                 ((acc << 8) | u64::from(*v), (mask << 8) | 0xFF)
@@ -226,15 +668,11 @@ impl<'de> Deserializer<'de> {
             let transmuted = (transmuted & !mask) | mantissa;
             Ok(f64::from_ne_bytes(transmuted.to_ne_bytes()))
         } else {
-            Ok(
-                str::parse::<f64>(&String::from_utf8_lossy(out)).map_err(|err| Error {
-                    kind: Kind::Message(err.to_string()),
-                })?,
-            )
+            parse_float(out).ok_or_else(|| parse_error(out))
         }
     }
 
-    fn read_symbol(&mut self) -> Result<&'de Sym> {
+    pub(crate) fn read_symbol(&mut self) -> Result<&'de Sym> {
         let out = self.read_str_len()?;
 
         let sym = Sym::new(out);
@@ -245,23 +683,44 @@ impl<'de> Deserializer<'de> {
         Ok(sym)
     }
 
-    fn read_symlink(&mut self) -> Result<&'de Sym> {
+    pub(crate) fn read_symlink(&mut self) -> Result<&'de Sym> {
         let index = self.read_packed_int()? as usize;
 
-        self.sym_table.get(index).copied().ok_or(Error {
-            kind: Kind::UnresolvedSymlink(index),
-        })
+        if self.recovering && index >= self.sym_table.len() {
+            return Ok(Sym::new(""));
+        }
+        self.sym_table
+            .get(index)
+            .copied()
+            .ok_or(Error::from(Kind::UnresolvedSymlink(index)))
     }
 
     // FIXME: FIND BETTER NAME
-    fn read_symbol_either(&mut self) -> Result<&'de Sym> {
+    pub(crate) fn read_symbol_either(&mut self) -> Result<&'de Sym> {
         match self.cursor.next_tag()? {
             Tag::Symbol => self.read_symbol(),
             Tag::Symlink => self.read_symlink(),
-            t => Err(Error {
-                kind: Kind::ExpectedSymbol(t),
-            }),
+            t => Err(Error::from(Kind::ExpectedSymbol(t))),
+        }
+    }
+
+    pub(crate) fn read_class_name(&mut self) -> Result<&'de Sym> {
+        let class = self.read_symbol_either()?;
+        if !self.recovering {
+            check_class_name(class)?;
         }
+        Ok(self.map_class(class))
+    }
+
+    pub(crate) fn peek_value_tag(&self) -> Result<Tag> {
+        self.cursor.peek_tag().map_err(|e| match e.kind {
+            // Ruby writes dumps to IO objects as it goes, so if Marshal.dump raises partway through
+            // (and the program dumps again afterwards) we'll find a new header where a value should be.
+            Kind::WrongTag(4) if self.cursor.input.get(self.cursor.position + 1) == Some(&8) => {
+                Error::from(Kind::InterruptedDump(self.cursor.position))
+            }
+            _ => e,
+        })
     }
 
     fn register_obj(&mut self) {
@@ -274,45 +733,81 @@ impl<'de> Deserializer<'de> {
         self.objtable.push(self.cursor.position);
     }
 
-    fn read_usize(&mut self) -> Result<usize> {
+    pub(crate) fn read_usize(&mut self) -> Result<usize> {
         let raw_length = self.read_packed_int()?;
-        usize::try_from(raw_length).map_err(|_| Error {
-            kind: Kind::UnexpectedNegativeLength(raw_length),
-        })
+        usize::try_from(raw_length)
+            .map_err(|_| Error::from(Kind::UnexpectedNegativeLength(raw_length)))
     }
 
-    fn read_bytes_len(&mut self) -> Result<&'de [u8]> {
+    pub(crate) fn read_bytes_len(&mut self) -> Result<&'de [u8]> {
         let len = self.read_usize()?;
         self.cursor.next_bytes_dyn(len)
     }
 
-    fn read_str_len(&mut self) -> Result<&'de str> {
+    pub(crate) fn read_str_len(&mut self) -> Result<&'de str> {
         let len = self.read_usize()?;
         let bytes = self.cursor.next_bytes_dyn(len)?;
 
-        std::str::from_utf8(bytes).map_err(|e| Error {
-            kind: Kind::SymbolInvalidUTF8(e),
-        })
+        std::str::from_utf8(bytes).map_err(|e| Error::from(Kind::SymbolInvalidUTF8(e)))
     }
 }
 
-impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
+#[cfg(feature = "fast-float")]
+fn find_terminator(bytes: &[u8]) -> Option<usize> {
+    memchr::memchr(0, bytes)
+}
+
+#[cfg(not(feature = "fast-float"))]
+fn find_terminator(bytes: &[u8]) -> Option<usize> {
+    bytes.iter().position(|v| *v == 0)
+}
+
+// ruby writes `inf`, `-inf`, and `nan` for special floats, which both of these understand
+#[cfg(feature = "fast-float")]
+fn parse_float(bytes: &[u8]) -> Option<f64> {
+    fast_float2::parse(bytes).ok()
+}
+
+#[cfg(not(feature = "fast-float"))]
+fn parse_float(bytes: &[u8]) -> Option<f64> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+fn is_encoding(ivar: &Sym) -> bool {
+    matches!(ivar.as_str(), "E" | "encoding")
+}
+
+// Anonymous classes and singletons have names like `#<Class:0x000055d5>`.
+// Ruby's Marshal refuses to dump them, so if we see one the data didn't come from Marshal.dump.
+pub(super) fn check_class_name(class: &Sym) -> Result<()> {
+    if class.as_str().starts_with("#<") {
+        return Err(Error::from(Kind::UndumpableClass(
+            class.as_str().to_owned(),
+        )));
+    }
+    Ok(())
+}
+
+impl<'de> Deserializer<'de> {
     // This is just barely over the limit.
     // It's fine, I swear.
     #[allow(clippy::too_many_lines)]
-    fn deserialize<V>(self, visitor: V) -> Result<V::Value>
+    fn deserialize_inner<V>(&mut self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        if self.cursor.peek_tag()?.is_object_link_referenceable() {
+        let tag = self.peek_value_tag()?;
+        if tag.is_object_link_referenceable() {
             self.register_obj();
         }
+        self.cursor.next_byte()?;
 
-        match self.cursor.next_tag()? {
+        match tag {
             Tag::Nil => visitor.visit_nil(),
             Tag::True => visitor.visit_bool(true),
             Tag::False => visitor.visit_bool(false),
             Tag::Integer => visitor.visit_i32(self.read_packed_int()?),
+            Tag::Bignum => visitor.visit_i64(self.read_bignum()?),
             Tag::Float => visitor.visit_f64(self.read_float()?),
             Tag::String => {
                 let data = self.read_bytes_len()?;
@@ -331,7 +826,7 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
                 // Deserialize remaining elements that weren't deserialized
                 while index < len {
                     index += 1;
-                    Ignored::deserialize(&mut *self)?;
+                    self.skip(Skipped::Element)?;
                 }
 
                 Ok(result)
@@ -345,15 +840,15 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
                     len,
                     index: &mut index,
                     state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+                    default: &mut false,
+                    #[cfg(feature = "value")]
+                    seen: HashSet::new(),
                 })?;
 
                 // Deserialize remaining elements that weren't deserialized
                 while index < len {
                     index += 1;
-                    // Key
-                    Ignored::deserialize(&mut *self)?;
-                    // Value
-                    Ignored::deserialize(&mut *self)?;
+                    self.skip(Skipped::Entry)?;
                 }
 
                 Ok(result)
@@ -375,16 +870,13 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
 
                 while index < len {
                     index += 1;
-                    // Ivar
-                    self.read_symbol_either()?;
-                    // Value
-                    Ignored::deserialize(&mut *self)?;
+                    self.skip_ivar()?;
                 }
 
                 Ok(result)
             }
             Tag::Object => {
-                let class = self.read_symbol_either()?;
+                let class = self.read_class_name()?;
 
                 let len = self.read_usize()?;
                 let mut index = 0;
@@ -396,80 +888,82 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
                         len,
                         index: &mut index,
                         state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+                        ivar: None,
+                        seen: vec![],
                     },
                 )?;
 
                 // Deserialize remaining elements that weren't deserialized
                 while index < len {
                     index += 1;
-                    // Ivar
-                    self.read_symbol_either()?;
-                    // Value
-                    Ignored::deserialize(&mut *self)?;
+                    self.skip_ivar()?;
                 }
 
                 Ok(result)
             }
             Tag::ObjectLink => {
+                // the type byte has already been read
+                let position = self.cursor.position - 1;
                 let index = self.read_usize()?;
+                if self.recovering {
+                    // links don't need to be followed to skip them
+                    return visitor.visit_nil();
+                }
 
-                let jump_target = self.objtable.get(index).copied().ok_or(Error {
-                    kind: Kind::UnresolvedObjectlink(index),
-                })?;
-
-                if self.stack.contains(&self.cursor.position) {
-                    return Err(Error {
-                        kind: Kind::CircularReference,
-                    });
+                if let Some(kind) = self.link_error(index) {
+                    self.tolerate_link(position, kind)?;
+                    return visitor.visit_nil();
                 }
 
+                let jump_target = self.objtable[index];
                 self.stack.push(self.cursor.position);
                 self.cursor.seek(jump_target);
 
-                let result = self.deserialize(visitor);
+                // errors at the link target are recovered from at the link, see `deserialize_lenient`
+                let result = self.deserialize_limited(visitor);
 
-                self.cursor
-                    .seek(self.stack.pop().expect("stack should not empty"));
+                let position = self.stack.pop().expect("stack should not empty");
+                if !self.truncated {
+                    self.cursor.seek(position);
+                }
 
                 result
             }
             Tag::UserDef => {
-                let class = self.read_symbol_either()?;
+                let class = self.read_class_name()?;
                 let data = self.read_bytes_len()?;
 
                 visitor.visit_user_data(class, data)
             }
-            // FIXME: this ignores default hash values. we should fix this?
             Tag::HashDefault => {
-                let len = self.read_packed_int()? as _;
+                let len = self.read_usize()?;
                 let mut index = 0;
+                let mut default = true;
 
-                let result = visitor.visit_hash(HashAccess {
+                let result = visitor.visit_hash_with_default(HashAccess {
                     deserializer: self,
                     len,
                     index: &mut index,
                     state: MapState::Value, // we want to enforce getting a key next so we set the state to value
-                });
+                    default: &mut default,
+                    #[cfg(feature = "value")]
+                    seen: HashSet::new(),
+                })?;
 
                 // Deserialize remaining elements that weren't deserialized
                 while index < len {
                     index += 1;
-                    // Key
-                    Ignored::deserialize(&mut *self)?;
-                    // Value
-                    Ignored::deserialize(&mut *self)?;
+                    self.skip(Skipped::Entry)?;
                 }
 
-                // Ignore the default value.
-                // This should work.
-                // Probably.
-                // :)
-                Ignored::deserialize(&mut *self)?;
+                if default {
+                    self.skip(Skipped::HashDefault)?;
+                }
 
-                result
+                Ok(result)
             }
             Tag::UserClass => {
-                let class = self.read_symbol_either()?;
+                let class = self.read_class_name()?;
                 visitor.visit_user_class(class, &mut *self)
             }
             Tag::RawRegexp => {
@@ -479,27 +973,29 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
             }
             Tag::ClassRef => {
                 // In my testing this isn't a symbol. How strange!
-                let class = self.read_str_len()?;
+                let class = Sym::new(self.read_str_len()?);
+                check_class_name(class)?;
 
-                visitor.visit_class(Sym::new(class))
+                visitor.visit_class(self.map_class(class))
             }
             Tag::ModuleRef => {
-                let module = self.read_str_len()?;
+                let module = Sym::new(self.read_str_len()?);
+                check_class_name(module)?;
 
-                visitor.visit_module(Sym::new(module))
+                visitor.visit_module(self.map_class(module))
             }
             // the ruby docs are wrong about this actually!
             // they say the object comes first, then the module, but actually it's the other way around.
             Tag::Extended => {
-                let module = self.read_symbol_either()?;
+                let module = self.read_class_name()?;
                 visitor.visit_extended(module, &mut *self)
             }
             Tag::UserMarshal => {
-                let class = self.read_symbol_either()?;
+                let class = self.read_class_name()?;
                 visitor.visit_user_marshal(class, &mut *self)
             }
             Tag::Struct => {
-                let name = self.read_symbol_either()?;
+                let name = self.read_class_name()?;
 
                 let len = self.read_packed_int()? as _;
                 let mut index = 0;
@@ -511,16 +1007,15 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
                         len,
                         index: &mut index,
                         state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+                        ivar: None,
+                        seen: vec![],
                     },
                 )?;
 
                 // Deserialize remaining elements that weren't deserialized
                 while index < len {
                     index += 1;
-                    // Member
-                    self.read_symbol_either()?;
-                    // Value
-                    Ignored::deserialize(&mut *self)?;
+                    self.skip_ivar()?;
                 }
 
                 Ok(result)
@@ -528,16 +1023,220 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
             // I'm not sure why this exists. The ruby marshal doc mentions that it's for types from C extensions,
             // But Data is functionally identical to UserMarshal.
             Tag::Data => {
-                let class = self.read_symbol_either()?;
+                let class = self.read_class_name()?;
                 visitor.visit_data(class, &mut *self)
             }
         }
     }
+}
+
+impl Deserializer<'_> {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            position: self.cursor.position,
+            objtable: self.objtable.len(),
+            stack: self.stack.len(),
+            sym_table: self.sym_table.len(),
+            is_reading_instance: self.is_reading_instance,
+            stats: self.stats,
+            recovered: self.recovered.len(),
+            truncated: self.truncated,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.cursor.seek(snapshot.position);
+        self.objtable.truncate(snapshot.objtable);
+        self.stack.truncate(snapshot.stack);
+        self.sym_table.truncate(snapshot.sym_table);
+        self.is_reading_instance = snapshot.is_reading_instance;
+        self.stats = snapshot.stats;
+        self.recovered.truncate(snapshot.recovered);
+        self.truncated = snapshot.truncated;
+    }
+
+    // Records an error in lenient mode, and skips over the value that failed (starting at `start`).
+    fn recover(&mut self, start: Snapshot, error: Error) -> Result<()> {
+        if is_fatal(&error) {
+            return Err(error);
+        }
+
+        self.restore(start);
+        self.lenient = false;
+        self.recovering = true;
+        let skipped = Ignored::deserialize(&mut *self);
+        self.lenient = true;
+        self.recovering = false;
+
+        match skipped {
+            Ok(_) => {
+                self.recovered.push(Recovered {
+                    position: start.position,
+                    error: Arc::new(error),
+                });
+                Ok(())
+            }
+            Err(skip_error) if is_fatal(&skip_error) => Err(skip_error),
+            Err(_) => {
+                self.restore(start);
+                self.give_up(start.position, error)
+            }
+        }
+    }
+
+    // Records an error in lenient mode, and ignores the rest of the input.
+    fn give_up(&mut self, position: usize, error: Error) -> Result<()> {
+        if is_fatal(&error) {
+            return Err(error);
+        }
+
+        self.recovered.push(Recovered {
+            position,
+            error: Arc::new(error),
+        });
+        self.cursor.seek(self.cursor.input.len());
+        self.truncated = true;
+        Ok(())
+    }
+}
+
+fn is_fatal(error: &Error) -> bool {
+    matches!(
+        error.kind,
+        Kind::DepthLimitExceeded(_) | Kind::TimeBudgetExceeded(_) | Kind::Cancelled(_)
+    )
+}
+
+impl<'de> Deserializer<'de> {
+    // returns why the link to `index` can't be followed, if it can't
+    fn link_error(&self, index: usize) -> Option<Kind> {
+        if index >= self.objtable.len() {
+            Some(Kind::UnresolvedObjectlink(index))
+        } else if self.stack.contains(&self.cursor.position) {
+            Some(Kind::CircularReference)
+        } else {
+            None
+        }
+    }
+
+    // records a link that can't be followed, if that's tolerated
+    fn tolerate_link(&mut self, position: usize, kind: Kind) -> Result<()> {
+        let error = Error::from(kind);
+        if !self.tolerate_broken_links {
+            return Err(error);
+        }
+
+        self.recovered.push(Recovered {
+            position,
+            error: Arc::new(error),
+        });
+        Ok(())
+    }
+
+    // checks the depth limit, time budget and cancel token, and reports progress, before deserializing a value
+    fn deserialize_limited<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.depth >= self.depth_limit {
+            return Err(Error::from(Kind::DepthLimitExceeded(self.depth_limit)));
+        }
+
+        if let Some(budget) = self.time_budget {
+            let deadline = *self.deadline.get_or_insert_with(|| Instant::now() + budget);
+            if Instant::now() >= deadline {
+                return Err(Error::from(Kind::TimeBudgetExceeded(self.cursor.position)));
+            }
+        }
+
+        if let Some(token) = &self.cancel_token {
+            if token.load(Ordering::Relaxed) {
+                return Err(Error::from(Kind::Cancelled(self.cursor.position)));
+            }
+        }
+
+        if let Some(progress) = self.progress {
+            let position = self.cursor.position;
+            if position >= self.next_progress {
+                self.next_progress = position.saturating_add(progress.interval().max(1));
+                if progress
+                    .report(position, self.cursor.input.len())
+                    .is_break()
+                {
+                    return Err(Error::from(Kind::Cancelled(position)));
+                }
+            }
+        }
+
+        self.depth += 1;
+        let result = self.deserialize_inner(visitor);
+        self.depth -= 1;
+
+        result
+    }
+
+    fn deserialize_lenient<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.truncated {
+            return visitor.visit_nil();
+        }
+
+        let start = self.snapshot();
+        let mut visitor = Some(visitor);
+        match self.deserialize_limited(VisitorOnce(&mut visitor)) {
+            // the visitor was never used, so the value itself is broken (not something inside it)
+            Err(error) => match visitor.take() {
+                Some(visitor) => {
+                    self.recover(start, error)?;
+                    visitor.visit_nil()
+                }
+                None => Err(error),
+            },
+            result => result,
+        }
+    }
+}
+
+impl<'de> super::DeserializerTrait<'de> for &mut Deserializer<'de> {
+    fn deserialize<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.lenient {
+            self.deserialize_lenient(visitor)
+        } else {
+            self.deserialize_limited(visitor)
+        }
+    }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: super::traits::VisitorOption<'de>,
     {
+        if self.lenient {
+            if self.truncated {
+                return visitor.visit_none();
+            }
+            if let Err(error) = self.peek_value_tag() {
+                let start = self.snapshot();
+                self.recover(start, error)?;
+                return visitor.visit_none();
+            }
+        }
+
+        if self.tolerate_broken_links && self.cursor.peek_tag()? == Tag::ObjectLink {
+            let position = self.cursor.position;
+            self.cursor.next_byte()?;
+            let index = self.read_usize()?;
+            if let Some(kind) = self.link_error(index) {
+                self.tolerate_link(position, kind)?;
+                return visitor.visit_none();
+            }
+            self.cursor.seek(position);
+        }
+
         if self.cursor.peek_tag()? == Tag::Nil {
             self.cursor.next_byte()?;
             visitor.visit_none()
@@ -550,6 +1249,10 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
     where
         V: super::traits::VisitorInstance<'de>,
     {
+        if self.lenient && self.cursor.peek_tag().is_err() {
+            return visitor.visit(self);
+        }
+
         if self.cursor.peek_tag()? == Tag::Instance {
             self.register_obj(); // we need to register the object before we start reading it
             self.is_reading_instance = true; // also need to remember NOT to push into the object table
@@ -567,10 +1270,7 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
 
             while index < len {
                 index += 1;
-                // Ivar
-                self.read_symbol_either()?;
-                // Value
-                Ignored::deserialize(&mut *self)?;
+                self.skip_ivar()?;
             }
 
             Ok(result)
@@ -578,6 +1278,18 @@ impl<'de, 'a> super::DeserializerTrait<'de> for &'a mut Deserializer<'de> {
             visitor.visit(self)
         }
     }
+
+    fn deserialize_raw(self) -> Result<crate::RawValue<'de>> {
+        let start = self.cursor.position;
+        let symbols = self.sym_table.len();
+
+        Ignored::deserialize(&mut *self)?;
+
+        Ok(crate::RawValue {
+            bytes: &self.cursor.input[start..self.cursor.position],
+            symbols: self.sym_table[symbols..].to_vec(),
+        })
+    }
 }
 
 impl<'de, 'a> super::InstanceAccess<'de> for &'a mut InstanceAccess<'de, 'a> {
@@ -589,7 +1301,15 @@ impl<'de, 'a> super::InstanceAccess<'de> for &'a mut InstanceAccess<'de, 'a> {
     {
         let result = seed.deserialize(&mut *self.deserializer)?;
 
-        let len = self.deserializer.read_usize()?;
+        let position = self.deserializer.cursor.position;
+        let len = match self.deserializer.read_usize() {
+            _ if self.deserializer.truncated => 0,
+            Err(error) if self.deserializer.lenient => {
+                self.deserializer.give_up(position, error)?;
+                0
+            }
+            result => result?,
+        };
         *self.len = len;
 
         Ok((
@@ -599,29 +1319,55 @@ impl<'de, 'a> super::InstanceAccess<'de> for &'a mut InstanceAccess<'de, 'a> {
                 len,
                 index: self.index,
                 state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+                ivar: None,
+                seen: vec![],
             },
         ))
     }
 }
 
-impl<'de, 'a> super::IvarAccess<'de> for IvarAccess<'de, 'a> {
+impl IvarAccess<'_, '_> {
+    fn track<T>(&self, result: Result<T>) -> Result<T> {
+        match self.ivar {
+            Some(ivar) => self
+                .deserializer
+                .track(result, || PathSegment::Ivar(ivar.to_symbol())),
+            None => result,
+        }
+    }
+}
+
+impl<'de> super::IvarAccess<'de> for IvarAccess<'de, '_> {
     fn next_ivar(&mut self) -> Result<Option<&'de Sym>> {
-        if *self.index >= self.len {
+        if *self.index >= self.len || self.deserializer.truncated {
             return Ok(None);
         }
 
         match self.state {
-            MapState::Key => {
-                return Err(Error {
-                    kind: Kind::KeyAfterKey,
-                })
-            }
+            MapState::Key => return Err(Error::from(Kind::KeyAfterKey)),
             MapState::Value => self.state = MapState::Key,
         }
 
         *self.index += 1;
 
-        self.deserializer.read_symbol_either().map(Some)
+        let position = self.deserializer.cursor.position;
+        let ivar = match self.deserializer.read_symbol_either() {
+            Err(error) if self.deserializer.lenient => {
+                // without a name, the value can't be deserialized or skipped
+                self.deserializer.give_up(position, error)?;
+                return Ok(None);
+            }
+            result => result?,
+        };
+        if self.deserializer.deny_duplicates {
+            if self.seen.contains(&ivar) {
+                return Err(Error::duplicate_field(ivar));
+            }
+            self.seen.push(ivar);
+        }
+        self.ivar = Some(ivar);
+
+        Ok(Some(ivar))
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
@@ -629,15 +1375,44 @@ impl<'de, 'a> super::IvarAccess<'de> for IvarAccess<'de, 'a> {
         V: DeserializeSeed<'de>,
     {
         match self.state {
-            MapState::Value => {
-                return Err(Error {
-                    kind: Kind::ValueAfterValue,
-                })
-            }
+            MapState::Value => return Err(Error::from(Kind::ValueAfterValue)),
+            MapState::Key => self.state = MapState::Value,
+        }
+
+        let result = seed.deserialize(&mut *self.deserializer);
+        self.track(result)
+    }
+
+    #[cfg(feature = "value")]
+    fn next_value_seed_or_capture<V>(
+        &mut self,
+        seed: V,
+    ) -> Result<std::result::Result<V::Value, Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.state {
+            MapState::Value => return Err(Error::from(Kind::ValueAfterValue)),
+            MapState::Key => self.state = MapState::Value,
+        }
+
+        let snapshot = self.deserializer.snapshot();
+        if let Ok(value) = seed.deserialize(&mut *self.deserializer) {
+            Ok(Ok(value))
+        } else {
+            self.deserializer.restore(snapshot);
+            let result = Value::deserialize(&mut *self.deserializer).map(Err);
+            self.track(result)
+        }
+    }
+
+    fn skip_value(&mut self) -> Result<()> {
+        match self.state {
+            MapState::Value => return Err(Error::from(Kind::ValueAfterValue)),
             MapState::Key => self.state = MapState::Value,
         }
 
-        seed.deserialize(&mut *self.deserializer)
+        self.deserializer.skip(Skipped::Ivar)
     }
 
     fn len(&self) -> usize {
@@ -649,17 +1424,20 @@ impl<'de, 'a> super::IvarAccess<'de> for IvarAccess<'de, 'a> {
     }
 }
 
-impl<'de, 'a> super::ArrayAccess<'de> for ArrayAccess<'de, 'a> {
+impl<'de> super::ArrayAccess<'de> for ArrayAccess<'de, '_> {
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
         T: DeserializeSeed<'de>,
     {
-        if *self.index >= self.len {
+        if *self.index >= self.len || self.deserializer.truncated {
             return Ok(None);
         }
         *self.index += 1;
 
-        seed.deserialize(&mut *self.deserializer).map(Some)
+        let result = seed.deserialize(&mut *self.deserializer).map(Some);
+        let index = *self.index - 1;
+        self.deserializer
+            .track(result, || PathSegment::Index(index))
     }
 
     fn len(&self) -> usize {
@@ -671,27 +1449,37 @@ impl<'de, 'a> super::ArrayAccess<'de> for ArrayAccess<'de, 'a> {
     }
 }
 
-impl<'de, 'a> super::HashAccess<'de> for HashAccess<'de, 'a> {
+impl<'de> super::HashAccess<'de> for HashAccess<'de, '_> {
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: DeserializeSeed<'de>,
     {
-        if *self.index >= self.len {
+        if *self.index >= self.len || self.deserializer.truncated {
             return Ok(None);
         }
 
         match self.state {
-            MapState::Key => {
-                return Err(Error {
-                    kind: Kind::KeyAfterKey,
-                })
-            }
+            MapState::Key => return Err(Error::from(Kind::KeyAfterKey)),
             MapState::Value => self.state = MapState::Key,
         }
 
         *self.index += 1;
 
-        seed.deserialize(&mut *self.deserializer).map(Some)
+        #[cfg(feature = "value")]
+        if self.deserializer.deny_duplicates {
+            let snapshot = self.deserializer.snapshot();
+            let key = Value::deserialize(&mut *self.deserializer)?;
+            if self.seen.contains(&key) {
+                return Err(Error::from(Kind::DuplicateKey(Box::new(key))));
+            }
+            self.seen.insert(key);
+            self.deserializer.restore(snapshot);
+        }
+
+        let result = seed.deserialize(&mut *self.deserializer).map(Some);
+        let index = *self.index - 1;
+        self.deserializer
+            .track(result, || PathSegment::Entry(index))
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
@@ -699,15 +1487,35 @@ impl<'de, 'a> super::HashAccess<'de> for HashAccess<'de, 'a> {
         V: DeserializeSeed<'de>,
     {
         match self.state {
-            MapState::Value => {
-                return Err(Error {
-                    kind: Kind::ValueAfterValue,
-                })
-            }
+            MapState::Value => return Err(Error::from(Kind::ValueAfterValue)),
             MapState::Key => self.state = MapState::Value,
         }
 
-        seed.deserialize(&mut *self.deserializer)
+        let result = seed.deserialize(&mut *self.deserializer);
+        let index = *self.index - 1;
+        self.deserializer
+            .track(result, || PathSegment::Entry(index))
+    }
+
+    fn next_default_seed<V>(&mut self, seed: V) -> Result<Option<V::Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        if !*self.default || self.deserializer.truncated {
+            return Ok(None);
+        }
+        if let MapState::Key = self.state {
+            return Err(Error::from(Kind::KeyAfterKey));
+        }
+
+        while *self.index < self.len {
+            *self.index += 1;
+            self.deserializer.skip(Skipped::Entry)?;
+        }
+
+        *self.default = false;
+        let result = seed.deserialize(&mut *self.deserializer).map(Some);
+        self.deserializer.track(result, || PathSegment::HashDefault)
     }
 
     fn len(&self) -> usize {
@@ -7,16 +7,26 @@
 
 use std::str::Utf8Error;
 
-use crate::{tag::Tag, Sym, Visitor};
+#[cfg(feature = "value")]
+use crate::Value;
+use crate::{tag::Tag, Sym, Symbol, Visitor};
 
 /// Type alias around a result.
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, thiserror::Error)]
-#[error("{kind}")]
+#[error("{}{kind}", PathPrefix(self.path()))]
 pub struct Error {
     #[source]
     pub kind: Kind,
+    // boxed to keep `Error` small, like the details in `Kind`
+    path: Option<Box<[PathSegment]>>,
+}
+
+impl From<Kind> for Error {
+    fn from(kind: Kind) -> Self {
+        Error { kind, path: None }
+    }
 }
 
 // TODO: provide error context
@@ -27,6 +37,14 @@ pub enum Kind {
     /// A length was negative when it should not have been.
     #[error("Unexpected negative length {0}")]
     UnexpectedNegativeLength(i32),
+    /// A packed integer (used for integers and lengths) was outside the range of an `i32`.
+    ///
+    /// Ruby writes integers this large as bignums, so the data is malformed.
+    #[error("Packed integer {0} is out of range (Ruby would have written a bignum)")]
+    PackedIntOutOfRange(i64),
+    /// A bignum was outside the range of an `i64`, which is the largest integer alox-48 supports.
+    #[error("Bignum is out of range of an i64")]
+    BignumOutOfRange,
     /// Unrecognized tag was encountered.
     #[error("Wrong tag 0x{0:X} ({})", unknown_tag_to_char(*_0))]
     WrongTag(u8),
@@ -50,11 +68,32 @@ pub enum Kind {
     #[error("End of input")]
     Eof,
     /// Version mismatch.
-    #[error("Version error, expected [4, 8], got {0:?}")]
+    #[error("Version error, unsupported version {0:?}")]
     VersionError([u8; 2]),
     /// A custom error thrown by a visitor.
+    ///
+    /// alox-48 itself never returns this, see [`Error::custom`].
     #[error("{0}")]
     Message(String),
+    /// A float was not a valid number.
+    #[error("{0}")]
+    ParseFloat(Box<ParseFloat>),
+    /// A value was of the wrong type.
+    #[error("invalid type: {0}")]
+    InvalidType(Box<Mismatch>),
+    /// A value was of the right type, but was not valid.
+    #[error("invalid value: {0}")]
+    InvalidValue(Box<Mismatch>),
+    /// A value had the wrong number of elements.
+    #[error("{0}")]
+    InvalidLength(Box<InvalidLength>),
+    /// A field was present more than once.
+    #[error("duplicate field `{0}`")]
+    DuplicateField(Symbol),
+    /// A hash key was present more than once.
+    #[cfg(feature = "value")]
+    #[error("duplicate hash key {0:?}")]
+    DuplicateKey(Box<Value>),
 
     #[error("Tried to deserialize a key without a value")]
     KeyAfterKey,
@@ -62,6 +101,318 @@ pub enum Kind {
     ValueAfterValue,
     #[error("A circular reference was detected while deserializing an object link")]
     CircularReference,
+    /// A field was not present in an object.
+    #[error("{0}")]
+    MissingField(Box<MissingField>),
+    /// A field was present in an object that was not expected.
+    #[error("{0}")]
+    UnknownField(Box<UnknownField>),
+    /// Several fields were present in an object that were not expected.
+    #[error("{0}")]
+    UnknownFields(Box<UnknownFields>),
+    /// An object was not of the expected class.
+    #[error("{0}")]
+    WrongClass(Box<WrongClass>),
+    /// The members of a ruby struct didn't match its definition.
+    #[error("{0}")]
+    IncompatibleStruct(Box<IncompatibleStruct>),
+    /// A class or module name belongs to an anonymous class or singleton.
+    ///
+    /// Ruby's Marshal can't dump these, so this usually means the data was produced by something other than `Marshal.dump`.
+    #[error("Class `{0}` is anonymous or a singleton, which Ruby's Marshal can't dump (was this data produced by Marshal.dump?)")]
+    UndumpableClass(String),
+    /// The start of another Marshal dump was found where a value was expected.
+    /// Contains the offset of the new dump.
+    ///
+    /// This happens when `Marshal.dump` raises partway through writing to an IO (for example, on a singleton or anonymous class)
+    /// and something is written to the same IO afterwards. The problem is with the program that produced the data.
+    #[error("Found the start of another Marshal dump at offset {0}; the dump before it was probably interrupted by an error in Ruby")]
+    InterruptedDump(usize),
+    /// Reading the input failed.
+    #[error("Failed to read input: {0}")]
+    Io(#[from] std::io::Error),
+    /// Deserialization took longer than the deserializer's time budget.
+    /// Contains the offset into the input where deserialization stopped.
+    #[error("Exceeded the time budget at offset {0}")]
+    TimeBudgetExceeded(usize),
+    /// Deserialization was cancelled by the deserializer's [`Progress`](super::Progress) callback or cancel token
+    /// (see [`Deserializer::set_cancel_token`](super::Deserializer::set_cancel_token)).
+    /// Contains the offset into the input where deserialization stopped.
+    #[error("Cancelled at offset {0}")]
+    Cancelled(usize),
+    /// Values were nested deeper than the deserializer allows.
+    #[error("Exceeded the depth limit of {0}")]
+    DepthLimitExceeded(usize),
+    /// Input remained after deserializing a value that should have used all of it.
+    /// Contains the number of bytes left over.
+    #[error("Found {0} trailing bytes after the end of the data")]
+    TrailingBytes(usize),
+    /// A [`RawValue`](crate::RawValue) was deserialized from something other than marshal data.
+    #[error("This deserializer does not support raw values")]
+    RawValueUnsupported,
+    /// The deserializer is in strict mode and would have skipped some data.
+    /// Contains the offset into the input of the skipped data.
+    #[error("Refusing to skip {0} at offset {1} in strict mode")]
+    SkippedData(Skipped, usize),
+}
+
+/// Kinds of data the deserializer can skip over because nothing deserialized it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Skipped {
+    /// An array element.
+    Element,
+    /// A hash key and its value.
+    Entry,
+    /// An instance variable (or struct member) and its value.
+    Ivar,
+    /// The default value of a hash.
+    HashDefault,
+}
+
+impl std::fmt::Display for Skipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Skipped::Element => "an array element",
+            Skipped::Entry => "a hash entry",
+            Skipped::Ivar => "an instance variable",
+            Skipped::HashDefault => "a hash default value",
+        })
+    }
+}
+
+// These are boxed in `Kind` to keep `Error` small, which matters for how deeply the deserializer can recurse.
+
+/// A step in the path to an error.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// An array element, by its index.
+    Index(usize),
+    /// A hash value, by the index of its entry.
+    Entry(usize),
+    /// The default value of a hash.
+    HashDefault,
+    /// An instance variable, or a struct member.
+    Ivar(Symbol),
+}
+
+/// Details of [`Kind::MissingField`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("missing field `{field}`{}", InClass(class.as_ref()))]
+pub struct MissingField {
+    /// The class of the object, if it was known when the error was raised.
+    pub class: Option<Symbol>,
+    /// The field that was missing.
+    pub field: Symbol,
+}
+
+/// Details of [`Kind::UnknownField`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown field `{field}`{}, {}", InClass(class.as_ref()), OneOf(expected))]
+pub struct UnknownField {
+    /// The class of the object, if it was known when the error was raised.
+    pub class: Option<Symbol>,
+    /// The field that was not expected.
+    pub field: Symbol,
+    /// The fields that were expected.
+    pub expected: Vec<Symbol>,
+}
+
+/// Details of [`Kind::UnknownFields`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown fields {}{}, {}", DidYouMean(self), InClass(class.as_ref()), OneOf(expected))]
+pub struct UnknownFields {
+    /// The class of the object, if it was known when the error was raised.
+    pub class: Option<Symbol>,
+    /// The fields that were not expected, in the order they were found.
+    pub fields: Vec<Symbol>,
+    /// The fields that were expected.
+    pub expected: Vec<Symbol>,
+}
+
+impl UnknownFields {
+    /// Returns the expected field most similar to `field`, if any are similar enough to be a likely typo.
+    pub fn suggestion(&self, field: &Sym) -> Option<&Symbol> {
+        // allow roughly one typo for every three characters
+        let max_distance = (field.len() / 3).max(1);
+        self.expected
+            .iter()
+            .map(|expected| (edit_distance(field.as_str(), expected.as_str()), expected))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, expected)| expected)
+    }
+}
+
+/// Details of [`Kind::WrongClass`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("wrong class: expected `{expected}`, found `{found}`")]
+pub struct WrongClass {
+    /// The class that was expected.
+    pub expected: Symbol,
+    /// The class that was found.
+    pub found: Symbol,
+}
+
+/// Details of [`Kind::IncompatibleStruct`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("struct `{name}` is not compatible, {}", MemberMismatch(self))]
+pub struct IncompatibleStruct {
+    /// The name of the struct.
+    pub name: Symbol,
+    /// The index of the first member that didn't match.
+    pub index: usize,
+    /// The member that was expected at `index`, or `None` if the struct had too many members.
+    pub expected: Option<Symbol>,
+    /// The member that was found at `index`, or `None` if the struct had too few members.
+    pub found: Option<Symbol>,
+}
+
+/// Details of [`Kind::ParseFloat`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "failed to parse float {:?} at offset {offset}",
+    String::from_utf8_lossy(bytes)
+)]
+pub struct ParseFloat {
+    /// The offset into the input of the float.
+    pub offset: usize,
+    /// The text of the float, not including the mantissa.
+    pub bytes: Vec<u8>,
+}
+
+/// Details of [`Kind::InvalidType`] and [`Kind::InvalidValue`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{unexpected}, expected `{expected}`")]
+pub struct Mismatch {
+    /// A description of what was found.
+    pub unexpected: String,
+    /// A description of what was expected.
+    pub expected: String,
+}
+
+/// Details of [`Kind::InvalidLength`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid length: {len}, expected `{expected}`")]
+pub struct InvalidLength {
+    /// The length that was found.
+    pub len: usize,
+    /// A description of what was expected.
+    pub expected: String,
+}
+
+struct PathPrefix<'a>(Option<&'a [PathSegment]>);
+
+impl std::fmt::Display for PathPrefix<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(path) => write!(f, "{}: ", DisplayPath(path)),
+            None => Ok(()),
+        }
+    }
+}
+
+pub(crate) struct DisplayPath<'a>(pub(crate) &'a [PathSegment]);
+
+impl std::fmt::Display for DisplayPath<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+                PathSegment::Entry(index) => write!(f, "{{{index}}}")?,
+                PathSegment::HashDefault => f.write_str("{default}")?,
+                PathSegment::Ivar(ivar) if i == 0 => f.write_str(ivar.as_str())?,
+                PathSegment::Ivar(ivar) => write!(f, ".{}", ivar.as_str())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct InClass<'a>(Option<&'a Symbol>);
+
+impl std::fmt::Display for InClass<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(class) => write!(f, " in class `{class}`"),
+            None => Ok(()),
+        }
+    }
+}
+
+struct MemberMismatch<'a>(&'a IncompatibleStruct);
+
+impl std::fmt::Display for MemberMismatch<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let index = self.0.index;
+        match (&self.0.expected, &self.0.found) {
+            (Some(expected), Some(found)) => {
+                write!(
+                    f,
+                    "expected member {index} to be `{expected}`, found `{found}`"
+                )
+            }
+            (Some(expected), None) => write!(f, "member `{expected}` is missing"),
+            (None, Some(found)) => write!(f, "found an extra member `{found}`"),
+            (None, None) => write!(f, "its members don't match"),
+        }
+    }
+}
+
+struct DidYouMean<'a>(&'a UnknownFields);
+
+impl std::fmt::Display for DidYouMean<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, field) in self.0.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "`{field}`")?;
+            if let Some(suggestion) = self.0.suggestion(field) {
+                write!(f, " (did you mean `{suggestion}`?)")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The levenshtein distance between two strings, in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+struct OneOf<'a>(&'a [Symbol]);
+
+impl std::fmt::Display for OneOf<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            [] => write!(f, "there should be none"),
+            [exp] => write!(f, "expected `{exp}`"),
+            [exp1, exp2] => write!(f, "expected `{exp1}` or `{exp2}`"),
+            exp => {
+                write!(f, "expected one of ")?;
+                for (i, exp) in exp.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "`{exp}`")?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 fn unknown_tag_to_char(tag: u8) -> char {
@@ -76,7 +427,7 @@ fn unknown_tag_to_char(tag: u8) -> char {
 pub enum Unexpected<'a> {
     Nil,
     Bool(bool),
-    Integer(i32),
+    Integer(i64),
     Float(f64),
     Hash,
     Array,
@@ -114,13 +465,13 @@ where
     }
 }
 
-impl<'a> std::fmt::Display for dyn Expected + 'a {
+impl std::fmt::Display for dyn Expected + '_ {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         Expected::fmt(self, f)
     }
 }
 
-impl<'a> std::fmt::Display for Unexpected<'a> {
+impl std::fmt::Display for Unexpected<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Unexpected::Nil => f.write_str("nil"),
@@ -154,54 +505,110 @@ impl<'a> std::fmt::Display for Unexpected<'a> {
 
 impl Error {
     pub fn custom(str: impl std::fmt::Display) -> Self {
-        Error {
-            kind: Kind::Message(str.to_string()),
-        }
+        Error::from(Kind::Message(str.to_string()))
     }
 
     pub fn invalid_type(unexpected: Unexpected<'_>, exp: &dyn Expected) -> Self {
-        Self::custom(format!("invalid type: {unexpected}, expected `{exp}`"))
+        Error::from(Kind::InvalidType(Box::new(Mismatch {
+            unexpected: unexpected.to_string(),
+            expected: exp.to_string(),
+        })))
     }
 
     pub fn invalid_value(unexpected: Unexpected<'_>, exp: &dyn Expected) -> Self {
-        Self::custom(format!("invalid value: {unexpected}, expected `{exp}`"))
+        Error::from(Kind::InvalidValue(Box::new(Mismatch {
+            unexpected: unexpected.to_string(),
+            expected: exp.to_string(),
+        })))
     }
 
     pub fn invalid_length(len: usize, exp: &dyn Expected) -> Self {
-        Self::custom(format!("invalid length: {len}, expected `{exp}`"))
+        Error::from(Kind::InvalidLength(Box::new(InvalidLength {
+            len,
+            expected: exp.to_string(),
+        })))
     }
 
     pub fn unknown_field(field: &Sym, expected: &[&Sym]) -> Self {
-        struct OneOf<'a> {
-            expected: &'a [&'a Sym],
-        }
-        impl<'a> std::fmt::Display for OneOf<'a> {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                match self.expected {
-                    [] => write!(f, "there should be none"),
-                    [exp] => write!(f, "expected `{exp}`"),
-                    [exp1, exp2] => write!(f, "expected `{exp1}` or `{exp2}`"),
-                    exp => {
-                        for (i, exp) in exp.iter().enumerate() {
-                            if i > 0 {
-                                write!(f, ", ")?;
-                            }
-                            write!(f, "`{exp}`")?;
-                        }
-                        Ok(())
-                    }
-                }
-            }
-        }
+        Error::from(Kind::UnknownField(Box::new(UnknownField {
+            class: None,
+            field: field.to_symbol(),
+            expected: expected.iter().map(|s| s.to_symbol()).collect(),
+        })))
+    }
+
+    /// Like [`Error::unknown_field`], but also records the class of the object.
+    pub fn unknown_field_in(class: &Sym, field: &Sym, expected: &[&Sym]) -> Self {
+        Error::from(Kind::UnknownField(Box::new(UnknownField {
+            class: Some(class.to_symbol()),
+            field: field.to_symbol(),
+            expected: expected.iter().map(|s| s.to_symbol()).collect(),
+        })))
+    }
 
-        Self::custom(format!("unknown field {field}, {}", OneOf { expected }))
+    /// Like [`Error::unknown_field_in`], but for every unknown field of an object at once.
+    pub fn unknown_fields_in(class: &Sym, fields: &[&Sym], expected: &[&Sym]) -> Self {
+        Error::from(Kind::UnknownFields(Box::new(UnknownFields {
+            class: Some(class.to_symbol()),
+            fields: fields.iter().map(|s| s.to_symbol()).collect(),
+            expected: expected.iter().map(|s| s.to_symbol()).collect(),
+        })))
     }
 
     pub fn missing_field(field: &Sym) -> Self {
-        Self::custom(format!("missing field `{field}`"))
+        Error::from(Kind::MissingField(Box::new(MissingField {
+            class: None,
+            field: field.to_symbol(),
+        })))
+    }
+
+    /// Like [`Error::missing_field`], but also records the class of the object.
+    pub fn missing_field_in(class: &Sym, field: &Sym) -> Self {
+        Error::from(Kind::MissingField(Box::new(MissingField {
+            class: Some(class.to_symbol()),
+            field: field.to_symbol(),
+        })))
+    }
+
+    pub fn wrong_class(expected: &Sym, found: &Sym) -> Self {
+        Error::from(Kind::WrongClass(Box::new(WrongClass {
+            expected: expected.to_symbol(),
+            found: found.to_symbol(),
+        })))
+    }
+
+    /// Error for the member at `index` of a ruby struct, when it isn't the member its definition expects.
+    ///
+    /// `expected` is `None` if the struct has too many members, and `found` is `None` if it has too few.
+    pub fn incompatible_struct(
+        name: &Sym,
+        index: usize,
+        expected: Option<&Sym>,
+        found: Option<&Sym>,
+    ) -> Self {
+        Error::from(Kind::IncompatibleStruct(Box::new(IncompatibleStruct {
+            name: name.to_symbol(),
+            index,
+            expected: expected.map(Sym::to_symbol),
+            found: found.map(Sym::to_symbol),
+        })))
     }
 
     pub fn duplicate_field(field: &Sym) -> Self {
-        Self::custom(format!("duplicate field `{field}`"))
+        Error::from(Kind::DuplicateField(field.to_symbol()))
+    }
+
+    /// Returns where in the data this error happened, starting from the outermost value, if it was tracked.
+    ///
+    /// Only deserializers that track paths record this, see [`Deserializer::set_track_path`](super::Deserializer::set_track_path).
+    pub fn path(&self) -> Option<&[PathSegment]> {
+        self.path.as_deref()
+    }
+
+    // segments are added from the innermost value out
+    pub(crate) fn at(mut self, segment: PathSegment) -> Self {
+        let path = self.path.take().map(Vec::from).unwrap_or_default();
+        self.path = Some(std::iter::once(segment).chain(path).collect());
+        self
     }
 }
@@ -0,0 +1,325 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A low level pull parser for marshal data.
+//!
+//! [`EventReader`] reads marshal data as a flat stream of [`Event`]s in the order they appear in the input.
+//! Unlike [`Deserializer`], it doesn't resolve object links or skip anything,
+//! so it's suited for converting marshal data to other formats without losing information.
+//!
+//! ```
+//! use alox_48::de::events::{Event, EventReader};
+//!
+//! let bytes = alox_48::to_bytes(vec![1, 2]).unwrap();
+//! let events = EventReader::new(&bytes)
+//!     .unwrap()
+//!     .collect::<Result<Vec<_>, _>>()
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     events,
+//!     [
+//!         Event::StartArray(2),
+//!         Event::Integer(1),
+//!         Event::Integer(2),
+//!         Event::End,
+//!     ]
+//! );
+//! ```
+
+use super::{deserializer::check_class_name, Deserializer, Result};
+use crate::{tag::Tag, Sym};
+
+/// A single piece of marshal data, read by an [`EventReader`].
+///
+/// Values containing other values start with a `Start*` event and finish with [`Event::End`].
+/// Symbol links are resolved, object links are not.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event<'de> {
+    /// `nil`.
+    Nil,
+    /// `true` or `false`.
+    Bool(bool),
+    /// An integer.
+    ///
+    /// Bignums are read as integers too, and are an error if they don't fit in an `i64`.
+    Integer(i64),
+    /// A float.
+    Float(f64),
+    /// A string, without its encoding.
+    String(&'de [u8]),
+    /// A symbol.
+    Symbol(&'de Sym),
+    /// A regular expression.
+    RegularExpression {
+        /// The source of the regular expression.
+        regex: &'de [u8],
+        /// The flags of the regular expression.
+        flags: u8,
+    },
+    /// A reference to the object with this index in the object table.
+    ObjectLink(usize),
+    /// A class.
+    Class(&'de Sym),
+    /// A module.
+    Module(&'de Sym),
+    /// User data.
+    UserData {
+        /// The class of the data.
+        class: &'de Sym,
+        /// The data.
+        data: &'de [u8],
+    },
+
+    /// The start of an array with this many elements.
+    StartArray(usize),
+    /// The start of a hash with this many entries, each a key followed by a value.
+    StartHash(usize),
+    /// The start of a hash with this many entries, with its default value after the entries.
+    StartHashWithDefault(usize),
+    /// The start of an object with instance variables.
+    ///
+    /// Each instance variable is an [`Event::Ivar`] followed by its value.
+    StartObject {
+        /// The class of the object.
+        class: &'de Sym,
+        /// How many instance variables the object has.
+        len: usize,
+    },
+    /// The start of a struct with members.
+    ///
+    /// Each member is an [`Event::Ivar`] followed by its value.
+    StartStruct {
+        /// The name of the struct.
+        name: &'de Sym,
+        /// How many members the struct has.
+        len: usize,
+    },
+    /// The start of a value with instance variables.
+    ///
+    /// The value comes first, followed by [`Event::InstanceVariables`].
+    StartInstance,
+    /// The instance variables of a value started by [`Event::StartInstance`].
+    InstanceVariables(usize),
+    /// The name of an instance variable or struct member, which is followed by its value.
+    Ivar(&'de Sym),
+    /// The end of the most recently started value.
+    End,
+
+    /// A value extended by this module, which is followed by the value.
+    Extended(&'de Sym),
+    /// A subclass of a builtin class, which is followed by the value.
+    UserClass(&'de Sym),
+    /// A user marshal object, which is followed by its data.
+    UserMarshal(&'de Sym),
+    /// C extension data, which is followed by its data.
+    Data(&'de Sym),
+}
+
+/// A pull parser reading [`Event`]s from marshal data.
+///
+/// This is also an [`Iterator`], which stops after the first error.
+#[derive(Debug, Clone)]
+pub struct EventReader<'de> {
+    deserializer: Deserializer<'de>,
+    stack: Vec<Frame>,
+    started: bool,
+    failed: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Frame {
+    // remaining values, and whether to read a hash default after them
+    Values { remaining: usize, default: bool },
+    Ivars { remaining: usize, value: bool },
+    // the value of an instance, and then its instance variables
+    Instance { value_read: bool },
+    // exactly one value, without an end event
+    Wrapped,
+}
+
+impl<'de> EventReader<'de> {
+    /// Create a new event reader with the given input.
+    ///
+    /// # Errors
+    /// Errors under the same conditions as [`Deserializer::new`].
+    pub fn new(input: &'de [u8]) -> Result<Self> {
+        Ok(Self {
+            deserializer: Deserializer::new(input)?,
+            stack: vec![],
+            started: false,
+            failed: false,
+        })
+    }
+
+    /// Returns the position of the next event in the input.
+    pub fn position(&self) -> usize {
+        self.deserializer.current_position()
+    }
+
+    /// Read the next event, returning `None` after the last one.
+    ///
+    /// # Errors
+    /// Errors if the input is malformed.
+    pub fn next_event(&mut self) -> Result<Option<Event<'de>>> {
+        let Some(frame) = self.stack.last_mut() else {
+            if self.started {
+                return Ok(None);
+            }
+            self.started = true;
+            return self.read_value().map(Some);
+        };
+
+        match frame {
+            Frame::Values {
+                remaining: 0,
+                default,
+            } if *default => {
+                *default = false;
+            }
+            Frame::Values { remaining: 0, .. }
+            | Frame::Ivars {
+                remaining: 0,
+                value: false,
+            } => {
+                self.stack.pop();
+                return Ok(Some(Event::End));
+            }
+            Frame::Values { remaining, .. } => *remaining -= 1,
+            Frame::Ivars { value, .. } if *value => *value = false,
+            Frame::Ivars { remaining, value } => {
+                *remaining -= 1;
+                *value = true;
+                return self
+                    .deserializer
+                    .read_symbol_either()
+                    .map(Event::Ivar)
+                    .map(Some);
+            }
+            Frame::Instance { value_read } if *value_read => {
+                let len = self.deserializer.read_usize()?;
+                *frame = Frame::Ivars {
+                    remaining: len,
+                    value: false,
+                };
+                return Ok(Some(Event::InstanceVariables(len)));
+            }
+            Frame::Instance { value_read } => *value_read = true,
+            Frame::Wrapped => {
+                self.stack.pop();
+            }
+        }
+
+        self.read_value().map(Some)
+    }
+
+    fn read_value(&mut self) -> Result<Event<'de>> {
+        let de = &mut self.deserializer;
+        let tag = de.peek_value_tag()?;
+        de.cursor.next_byte()?;
+
+        let event = match tag {
+            Tag::Nil => Event::Nil,
+            Tag::True => Event::Bool(true),
+            Tag::False => Event::Bool(false),
+            Tag::Integer => Event::Integer(de.read_packed_int()?.into()),
+            Tag::Bignum => Event::Integer(de.read_bignum()?),
+            Tag::Float => Event::Float(de.read_float()?),
+            Tag::String => Event::String(de.read_bytes_len()?),
+            Tag::Symbol => Event::Symbol(de.read_symbol()?),
+            Tag::Symlink => Event::Symbol(de.read_symlink()?),
+            Tag::RawRegexp => {
+                let regex = de.read_bytes_len()?;
+                let flags = de.cursor.next_byte()?;
+                Event::RegularExpression { regex, flags }
+            }
+            Tag::ObjectLink => Event::ObjectLink(de.read_usize()?),
+            Tag::ClassRef => {
+                let class = Sym::new(de.read_str_len()?);
+                check_class_name(class)?;
+                Event::Class(class)
+            }
+            Tag::ModuleRef => {
+                let module = Sym::new(de.read_str_len()?);
+                check_class_name(module)?;
+                Event::Module(module)
+            }
+            Tag::UserDef => {
+                let class = de.read_class_name()?;
+                let data = de.read_bytes_len()?;
+                Event::UserData { class, data }
+            }
+            Tag::Array => {
+                let len = de.read_usize()?;
+                self.stack.push(Frame::Values {
+                    remaining: len,
+                    default: false,
+                });
+                Event::StartArray(len)
+            }
+            Tag::Hash | Tag::HashDefault => {
+                let len = de.read_usize()?;
+                let default = tag == Tag::HashDefault;
+                self.stack.push(Frame::Values {
+                    remaining: len * 2,
+                    default,
+                });
+                if default {
+                    Event::StartHashWithDefault(len)
+                } else {
+                    Event::StartHash(len)
+                }
+            }
+            Tag::Object => {
+                let class = de.read_class_name()?;
+                let len = de.read_usize()?;
+                self.stack.push(Frame::Ivars {
+                    remaining: len,
+                    value: false,
+                });
+                Event::StartObject { class, len }
+            }
+            Tag::Struct => {
+                let name = de.read_class_name()?;
+                let len = de.read_usize()?;
+                self.stack.push(Frame::Ivars {
+                    remaining: len,
+                    value: false,
+                });
+                Event::StartStruct { name, len }
+            }
+            Tag::Instance => {
+                self.stack.push(Frame::Instance { value_read: false });
+                Event::StartInstance
+            }
+            Tag::Extended | Tag::UserClass | Tag::UserMarshal | Tag::Data => {
+                let class = de.read_class_name()?;
+                self.stack.push(Frame::Wrapped);
+                match tag {
+                    Tag::Extended => Event::Extended(class),
+                    Tag::UserClass => Event::UserClass(class),
+                    Tag::UserMarshal => Event::UserMarshal(class),
+                    _ => Event::Data(class),
+                }
+            }
+        };
+
+        Ok(event)
+    }
+}
+
+impl<'de> Iterator for EventReader<'de> {
+    type Item = Result<Event<'de>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.next_event();
+        self.failed = result.is_err();
+        result.transpose()
+    }
+}
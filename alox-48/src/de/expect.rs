@@ -0,0 +1,501 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Checking the type and class of a value before deserializing it.
+//!
+//! Deeply nested data tends to fail with errors about whatever was deepest, like a missing field of some object.
+//! Checking what a value is up front fails earlier with an error about the value itself.
+//!
+//! The derive macros use this for the `expect` and `expect_class` field attributes.
+//!
+//! ```
+//! # #[cfg(feature = "value")] {
+//! use alox_48::de::expect::{Expectation, ValueKind};
+//! use alox_48::{de::DeserializeSeed, Value};
+//! use std::marker::PhantomData;
+//!
+//! let expectation = Expectation::new().kind(ValueKind::Array);
+//!
+//! let value = Value::Array(vec![Value::Nil]);
+//! let seed = expectation.seed(PhantomData::<Value>);
+//! assert_eq!(seed.deserialize(&value).unwrap(), value);
+//!
+//! let err = expectation.seed(PhantomData::<Value>).deserialize(&Value::Nil).unwrap_err();
+//! assert_eq!(err.to_string(), "invalid type: nil, expected `an array`");
+//! # }
+//! ```
+
+use super::{
+    ArrayAccess, DeserializeSeed, DeserializerTrait, Error, HashAccess, InstanceAccess, IvarAccess,
+    Primitive, Result, Unexpected, Visitor, VisitorInstance, VisitorOption,
+};
+use crate::{RawValue, Sym};
+
+/// The kinds of values that can be expected.
+///
+/// Wrappers like instance variables and extended modules are looked through.
+/// User classes (subclasses of `String`, `Array`, etc) are the kind of the value they wrap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum ValueKind {
+    Nil,
+    Bool,
+    Integer,
+    Float,
+    Hash,
+    Array,
+    String,
+    Symbol,
+    Regex,
+    Object,
+    Struct,
+    Class,
+    Module,
+    UserData,
+    UserMarshal,
+    Data,
+}
+
+/// What a value is expected to be.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Expectation<'a> {
+    kind: Option<ValueKind>,
+    class: Option<&'a Sym>,
+}
+
+/// A deserializer that checks its value against an [`Expectation`] before handing it to a visitor.
+#[derive(Debug)]
+pub struct Expect<'a, D> {
+    deserializer: D,
+    expectation: Expectation<'a>,
+}
+
+/// A seed that checks its value against an [`Expectation`] before handing it to another seed.
+#[derive(Clone, Copy, Debug)]
+pub struct ExpectSeed<'a, S> {
+    seed: S,
+    expectation: Expectation<'a>,
+}
+
+struct ExpectVisitor<'a, V> {
+    visitor: V,
+    expectation: Expectation<'a>,
+}
+
+struct ExpectInstanceAccess<'a, A> {
+    access: A,
+    expectation: Expectation<'a>,
+}
+
+impl std::fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ValueKind::Nil => "nil",
+            ValueKind::Bool => "a bool",
+            ValueKind::Integer => "an integer",
+            ValueKind::Float => "a float",
+            ValueKind::Hash => "a hash",
+            ValueKind::Array => "an array",
+            ValueKind::String => "a string",
+            ValueKind::Symbol => "a symbol",
+            ValueKind::Regex => "a regex",
+            ValueKind::Object => "an object",
+            ValueKind::Struct => "a struct",
+            ValueKind::Class => "a class",
+            ValueKind::Module => "a module",
+            ValueKind::UserData => "a user data object",
+            ValueKind::UserMarshal => "a user marshal object",
+            ValueKind::Data => "a c data object",
+        })
+    }
+}
+
+impl<'a> Expectation<'a> {
+    /// An expectation that anything meets.
+    pub const fn new() -> Self {
+        Self {
+            kind: None,
+            class: None,
+        }
+    }
+
+    /// Expect a kind of value.
+    #[must_use]
+    pub const fn kind(self, kind: ValueKind) -> Self {
+        Self {
+            kind: Some(kind),
+            ..self
+        }
+    }
+
+    /// Expect a value with a class.
+    ///
+    /// This is checked against the class of objects, structs, user data, user marshal objects, and user classes.
+    /// Other values don't have a class, so they never meet this expectation.
+    #[must_use]
+    pub const fn class(self, class: &'a Sym) -> Self {
+        Self {
+            class: Some(class),
+            ..self
+        }
+    }
+
+    /// Check the values read by a deserializer against this expectation.
+    pub fn deserializer<D>(self, deserializer: D) -> Expect<'a, D> {
+        Expect {
+            deserializer,
+            expectation: self,
+        }
+    }
+
+    /// Check the value read by a seed against this expectation.
+    pub fn seed<S>(self, seed: S) -> ExpectSeed<'a, S> {
+        ExpectSeed {
+            seed,
+            expectation: self,
+        }
+    }
+
+    fn check(&self, kind: ValueKind, unexpected: Unexpected<'_>) -> Result<()> {
+        if self.kind.is_some_and(|k| k != kind) {
+            return Err(Error::invalid_type(unexpected, &self));
+        }
+        if self.class.is_some() {
+            return Err(Error::invalid_type(unexpected, &self));
+        }
+        Ok(())
+    }
+
+    fn check_class(&self, kind: ValueKind, class: &Sym, unexpected: Unexpected<'_>) -> Result<()> {
+        if self.kind.is_some_and(|k| k != kind) {
+            return Err(Error::invalid_type(unexpected, &self));
+        }
+        match self.class {
+            Some(expected) if expected != class => Err(Error::wrong_class(expected, class)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl super::error::Expected for &Expectation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.kind, self.class) {
+            (Some(kind), Some(class)) => write!(f, "{kind} of class `{}`", class.as_str()),
+            (Some(kind), None) => write!(f, "{kind}"),
+            (None, Some(class)) => write!(f, "an instance of `{}`", class.as_str()),
+            (None, None) => f.write_str("anything"),
+        }
+    }
+}
+
+impl<'a, D> Expect<'a, D> {
+    /// Wrap a deserializer, checking its values against `expectation`.
+    pub fn new(deserializer: D, expectation: Expectation<'a>) -> Self {
+        expectation.deserializer(deserializer)
+    }
+
+    /// Unwrap the inner deserializer.
+    pub fn into_inner(self) -> D {
+        self.deserializer
+    }
+}
+
+impl<'a, S> ExpectSeed<'a, S> {
+    /// Wrap a seed, checking its value against `expectation`.
+    pub fn new(seed: S, expectation: Expectation<'a>) -> Self {
+        expectation.seed(seed)
+    }
+}
+
+impl<'de, S> DeserializeSeed<'de> for ExpectSeed<'_, S>
+where
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.seed
+            .deserialize(self.expectation.deserializer(deserializer))
+    }
+}
+
+impl<'de, D> DeserializerTrait<'de> for Expect<'_, D>
+where
+    D: DeserializerTrait<'de>,
+{
+    fn deserialize<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserializer.deserialize(ExpectVisitor {
+            visitor,
+            expectation: self.expectation,
+        })
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorOption<'de>,
+    {
+        // nil is `None`, so the expectation only applies to `Some`
+        self.deserializer.deserialize_option(ExpectVisitor {
+            visitor,
+            expectation: self.expectation,
+        })
+    }
+
+    fn deserialize_instance<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorInstance<'de>,
+    {
+        self.deserializer.deserialize_instance(ExpectVisitor {
+            visitor,
+            expectation: self.expectation,
+        })
+    }
+
+    fn deserialize_raw(self) -> Result<RawValue<'de>> {
+        self.deserializer.deserialize_raw()
+    }
+}
+
+impl<'de, V> VisitorOption<'de> for ExpectVisitor<'_, V>
+where
+    V: VisitorOption<'de>,
+{
+    type Value = V::Value;
+
+    fn visit_none(self) -> Result<Self::Value> {
+        self.visitor.visit_none()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.visitor
+            .visit_some(self.expectation.deserializer(deserializer))
+    }
+}
+
+impl<'de, V> VisitorInstance<'de> for ExpectVisitor<'_, V>
+where
+    V: VisitorInstance<'de>,
+{
+    type Value = V::Value;
+
+    fn visit<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.visitor
+            .visit(self.expectation.deserializer(deserializer))
+    }
+
+    fn visit_instance<A>(self, access: A) -> Result<Self::Value>
+    where
+        A: InstanceAccess<'de>,
+    {
+        self.visitor.visit_instance(ExpectInstanceAccess {
+            access,
+            expectation: self.expectation,
+        })
+    }
+}
+
+impl<'de, A> InstanceAccess<'de> for ExpectInstanceAccess<'_, A>
+where
+    A: InstanceAccess<'de>,
+{
+    type IvarAccess = A::IvarAccess;
+
+    fn value_seed<V>(self, seed: V) -> Result<(V::Value, Self::IvarAccess)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.access.value_seed(self.expectation.seed(seed))
+    }
+}
+
+impl<'de, V> Visitor<'de> for ExpectVisitor<'_, V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.visitor.expecting(formatter)
+    }
+
+    fn expected_primitive(&self) -> Option<Primitive> {
+        self.visitor.expected_primitive()
+    }
+
+    fn visit_nil(self) -> Result<Self::Value> {
+        self.expectation.check(ValueKind::Nil, Unexpected::Nil)?;
+        self.visitor.visit_nil()
+    }
+
+    fn visit_bool(self, v: bool) -> Result<Self::Value> {
+        self.expectation
+            .check(ValueKind::Bool, Unexpected::Bool(v))?;
+        self.visitor.visit_bool(v)
+    }
+
+    fn visit_i32(self, v: i32) -> Result<Self::Value> {
+        self.expectation
+            .check(ValueKind::Integer, Unexpected::Integer(v.into()))?;
+        self.visitor.visit_i32(v)
+    }
+
+    fn visit_i64(self, v: i64) -> Result<Self::Value> {
+        self.expectation
+            .check(ValueKind::Integer, Unexpected::Integer(v))?;
+        self.visitor.visit_i64(v)
+    }
+
+    fn visit_f64(self, v: f64) -> Result<Self::Value> {
+        self.expectation
+            .check(ValueKind::Float, Unexpected::Float(v))?;
+        self.visitor.visit_f64(v)
+    }
+
+    fn visit_hash<A>(self, map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        self.expectation.check(ValueKind::Hash, Unexpected::Hash)?;
+        self.visitor.visit_hash(map)
+    }
+
+    fn visit_hash_with_default<A>(self, map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        self.expectation.check(ValueKind::Hash, Unexpected::Hash)?;
+        self.visitor.visit_hash_with_default(map)
+    }
+
+    fn visit_array<A>(self, array: A) -> Result<Self::Value>
+    where
+        A: ArrayAccess<'de>,
+    {
+        self.expectation
+            .check(ValueKind::Array, Unexpected::Array)?;
+        self.visitor.visit_array(array)
+    }
+
+    fn visit_string(self, string: &'de [u8]) -> Result<Self::Value> {
+        self.expectation
+            .check(ValueKind::String, Unexpected::String(string))?;
+        self.visitor.visit_string(string)
+    }
+
+    fn visit_symbol(self, symbol: &'de Sym) -> Result<Self::Value> {
+        self.expectation
+            .check(ValueKind::Symbol, Unexpected::Symbol(symbol))?;
+        self.visitor.visit_symbol(symbol)
+    }
+
+    fn visit_regular_expression(self, regex: &'de [u8], flags: u8) -> Result<Self::Value> {
+        self.expectation
+            .check(ValueKind::Regex, Unexpected::Regex(regex))?;
+        self.visitor.visit_regular_expression(regex, flags)
+    }
+
+    fn visit_object<A>(self, class: &'de Sym, instance_variables: A) -> Result<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        self.expectation
+            .check_class(ValueKind::Object, class, Unexpected::Object(class))?;
+        self.visitor.visit_object(class, instance_variables)
+    }
+
+    fn visit_struct<A>(self, name: &'de Sym, members: A) -> Result<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        self.expectation
+            .check_class(ValueKind::Struct, name, Unexpected::Struct(name))?;
+        self.visitor.visit_struct(name, members)
+    }
+
+    fn visit_class(self, class: &'de Sym) -> Result<Self::Value> {
+        self.expectation
+            .check(ValueKind::Class, Unexpected::Class(class))?;
+        self.visitor.visit_class(class)
+    }
+
+    fn visit_module(self, module: &'de Sym) -> Result<Self::Value> {
+        self.expectation
+            .check(ValueKind::Module, Unexpected::Module(module))?;
+        self.visitor.visit_module(module)
+    }
+
+    fn visit_instance<A>(self, instance: A) -> Result<Self::Value>
+    where
+        A: InstanceAccess<'de>,
+    {
+        self.visitor.visit_instance(ExpectInstanceAccess {
+            access: instance,
+            expectation: self.expectation,
+        })
+    }
+
+    fn visit_extended<D>(self, module: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.visitor
+            .visit_extended(module, self.expectation.deserializer(deserializer))
+    }
+
+    fn visit_user_class<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        // the class is checked here, and the kind against the wrapped value
+        let mut expectation = self.expectation;
+        if let Some(expected) = expectation.class.take() {
+            if expected != class {
+                return Err(Error::wrong_class(expected, class));
+            }
+        }
+        self.visitor
+            .visit_user_class(class, expectation.deserializer(deserializer))
+    }
+
+    fn visit_user_data(self, class: &'de Sym, data: &'de [u8]) -> Result<Self::Value> {
+        self.expectation
+            .check_class(ValueKind::UserData, class, Unexpected::UserData(class))?;
+        self.visitor.visit_user_data(class, data)
+    }
+
+    fn visit_user_marshal<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.expectation.check_class(
+            ValueKind::UserMarshal,
+            class,
+            Unexpected::UserMarshal(class),
+        )?;
+        self.visitor.visit_user_marshal(class, deserializer)
+    }
+
+    fn visit_data<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.expectation
+            .check_class(ValueKind::Data, class, Unexpected::Data(class))?;
+        self.visitor.visit_data(class, deserializer)
+    }
+}
@@ -28,6 +28,9 @@ impl<'de> Visitor<'de> for IgnoredVisitor {
     fn visit_i32(self, _v: i32) -> Result<Self::Value> {
         Ok(Ignored)
     }
+    fn visit_i64(self, _v: i64) -> Result<Self::Value> {
+        Ok(Ignored)
+    }
     fn visit_f64(self, _v: f64) -> Result<Self::Value> {
         Ok(Ignored)
     }
@@ -39,6 +42,14 @@ impl<'de> Visitor<'de> for IgnoredVisitor {
         while let Some((Ignored, Ignored)) = map.next_entry()? {}
         Ok(Ignored)
     }
+    fn visit_hash_with_default<A>(self, mut map: A) -> Result<Self::Value>
+    where
+        A: crate::HashAccess<'de>,
+    {
+        while let Some((Ignored, Ignored)) = map.next_entry()? {}
+        map.next_default::<Ignored>()?;
+        Ok(Ignored)
+    }
     fn visit_array<A>(self, mut array: A) -> Result<Self::Value>
     where
         A: crate::ArrayAccess<'de>,
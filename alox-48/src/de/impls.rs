@@ -4,21 +4,28 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+#[cfg(feature = "value")]
 use indexmap::{IndexMap, IndexSet};
 
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet, LinkedList, VecDeque},
     hash::{BuildHasher, Hash},
     marker::PhantomData,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
     num::{
         NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
         NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
     },
+    ops::{Range, RangeInclusive},
+    path::PathBuf,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use super::{
-    traits::VisitorOption, ArrayAccess, Deserialize, DeserializeSeed, DeserializerTrait, Error,
-    HashAccess, Result, Unexpected, Visitor,
+    cautious_capacity, traits::VisitorOption, ArrayAccess, Deserialize, DeserializeSeed,
+    DeserializerTrait, Error, HashAccess, IvarAccess, Primitive, Result, Unexpected, Visitor,
 };
 use crate::Sym;
 
@@ -38,19 +45,27 @@ where
 
 struct IntVisitor;
 
-impl<'de> Visitor<'de> for IntVisitor {
-    type Value = i32;
+impl Visitor<'_> for IntVisitor {
+    type Value = i64;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         formatter.write_str("an integer")
     }
 
+    fn expected_primitive(&self) -> Option<Primitive> {
+        Some(Primitive::Integer)
+    }
+
     fn visit_i32(self, v: i32) -> Result<Self::Value> {
+        Ok(v.into())
+    }
+
+    fn visit_i64(self, v: i64) -> Result<Self::Value> {
         Ok(v)
     }
 
     fn visit_f64(self, v: f64) -> Result<Self::Value> {
-        Ok(v as i32)
+        Ok(v as i64)
     }
 }
 
@@ -62,7 +77,12 @@ macro_rules! primitive_int_impl {
                 D: DeserializerTrait<'de>,
             {
                 let i = deserializer.deserialize(IntVisitor)?;
-                Ok(i as _)
+                <$primitive>::try_from(i).map_err(|_| {
+                    Error::invalid_value(
+                        Unexpected::Integer(i),
+                        &concat!("an integer in the range of `", stringify!($primitive), "`"),
+                    )
+                })
             }
         })*
     };
@@ -70,21 +90,28 @@ macro_rules! primitive_int_impl {
 
 struct NonZeroIntVisitor;
 
-impl<'de> Visitor<'de> for NonZeroIntVisitor {
-    type Value = std::num::NonZeroI32;
+impl Visitor<'_> for NonZeroIntVisitor {
+    type Value = std::num::NonZeroI64;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         formatter.write_str("a non-zero integer")
     }
 
+    fn expected_primitive(&self) -> Option<Primitive> {
+        Some(Primitive::Integer)
+    }
+
     fn visit_i32(self, v: i32) -> Result<Self::Value> {
-        std::num::NonZeroI32::new(v)
+        self.visit_i64(v.into())
+    }
+
+    fn visit_i64(self, v: i64) -> Result<Self::Value> {
+        std::num::NonZeroI64::new(v)
             .ok_or_else(|| Error::invalid_value(Unexpected::Integer(v), &self))
     }
 
     fn visit_f64(self, v: f64) -> Result<Self::Value> {
-        std::num::NonZeroI32::new(v as i32)
-            .ok_or_else(|| Error::invalid_value(Unexpected::Integer(v as i32), &self))
+        self.visit_i64(v as i64)
     }
 }
 
@@ -98,9 +125,15 @@ macro_rules! nonzero_int_impl {
                 D: DeserializerTrait<'de>,
             {
                 let i = deserializer.deserialize(NonZeroIntVisitor)?.get();
-                // we've already asserted that it's non-zero simply by the fact that NonZeroIntVisitor returns a NonZeroI32.
-                // so this new_unchecked is safe
-                Ok(unsafe { <$primitive>::new_unchecked(i as _) })
+                let i = i.try_into().map_err(|_| {
+                    Error::invalid_value(
+                        Unexpected::Integer(i),
+                        &concat!("an integer in the range of `", stringify!($primitive), "`"),
+                    )
+                })?;
+                // we've already asserted that it's non-zero simply by the fact that NonZeroIntVisitor returns a NonZeroI64,
+                // and it's still the same value after the conversion. so this new_unchecked is safe
+                Ok(unsafe { <$primitive>::new_unchecked(i) })
             }
         })*
     };
@@ -123,7 +156,7 @@ nonzero_int_impl!(
 
 struct UnitVisitor;
 
-impl<'de> Visitor<'de> for UnitVisitor {
+impl Visitor<'_> for UnitVisitor {
     type Value = ();
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -146,13 +179,17 @@ impl<'de> Deserialize<'de> for () {
 
 struct BoolVisitor;
 
-impl<'de> Visitor<'de> for BoolVisitor {
+impl Visitor<'_> for BoolVisitor {
     type Value = bool;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         formatter.write_str("bool")
     }
 
+    fn expected_primitive(&self) -> Option<Primitive> {
+        Some(Primitive::Bool)
+    }
+
     fn visit_bool(self, v: bool) -> Result<Self::Value> {
         Ok(v)
     }
@@ -169,17 +206,26 @@ impl<'de> Deserialize<'de> for bool {
 
 struct FloatVisitor;
 
-impl<'de> Visitor<'de> for FloatVisitor {
+impl Visitor<'_> for FloatVisitor {
     type Value = f64;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         formatter.write_str("a float")
     }
 
+    fn expected_primitive(&self) -> Option<Primitive> {
+        Some(Primitive::Float)
+    }
+
     fn visit_i32(self, v: i32) -> Result<Self::Value> {
         Ok(f64::from(v))
     }
 
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_i64(self, v: i64) -> Result<Self::Value> {
+        Ok(v as f64)
+    }
+
     fn visit_f64(self, v: f64) -> Result<Self::Value> {
         Ok(v)
     }
@@ -264,6 +310,41 @@ impl<'de> Deserialize<'de> for &'de [u8] {
     }
 }
 
+impl<'de> Deserialize<'de> for crate::SerializeByteString<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer
+            .deserialize(BytesVisitor)
+            .map(crate::SerializeByteString)
+    }
+}
+
+// Owned and shared strings, for storing lots of them without keeping the input around.
+// See `seed::StrInterner` to share one allocation between equal strings.
+macro_rules! owned_slice_impl {
+    ($($ty:ty => $visitor:ident),*) => {
+        $(impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D>(deserializer: D) -> Result<Self>
+            where
+                D: DeserializerTrait<'de>,
+            {
+                deserializer.deserialize($visitor).map(Into::into)
+            }
+        })*
+    };
+}
+
+owned_slice_impl!(
+    Box<str> => StrVisitor,
+    Rc<str> => StrVisitor,
+    Arc<str> => StrVisitor,
+    Box<[u8]> => BytesVisitor,
+    Rc<[u8]> => BytesVisitor,
+    Arc<[u8]> => BytesVisitor
+);
+
 struct OptionVisitor<T> {
     marker: PhantomData<T>,
 }
@@ -355,7 +436,12 @@ macro_rules! seq_impl {
     }
 }
 
-seq_impl!(Vec<T>, array, Vec::with_capacity(array.len()), Vec::push);
+seq_impl!(
+    Vec<T>,
+    array,
+    Vec::with_capacity(cautious_capacity::<T>(array.len())),
+    Vec::push
+);
 
 seq_impl!(
     BTreeSet<T: Eq + Ord>,
@@ -374,21 +460,22 @@ seq_impl!(
 seq_impl!(
     HashSet<T: Hash + Eq, H: BuildHasher + Default>,
     array,
-    HashSet::with_capacity_and_hasher(array.len(), H::default()),
+    HashSet::with_capacity_and_hasher(cautious_capacity::<T>(array.len()), H::default()),
     HashSet::insert
 );
 
 seq_impl!(
     VecDeque<T: Hash + Eq>,
     array,
-    VecDeque::with_capacity(array.len()),
+    VecDeque::with_capacity(cautious_capacity::<T>(array.len())),
     VecDeque::push_back
 );
 
 seq_impl!(
+    #[cfg(feature = "value")]
     IndexSet<T: Hash + Eq, H: BuildHasher + Default>,
     array,
-    IndexSet::with_capacity_and_hasher(array.len(), H::default()),
+    IndexSet::with_capacity_and_hasher(cautious_capacity::<T>(array.len()), H::default()),
     IndexSet::insert
 );
 
@@ -404,7 +491,7 @@ where
     type Value = [T; SIZE];
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        formatter.write_fmt(format_args!("an array of length {SIZE}",))
+        formatter.write_fmt(format_args!("an array of length {SIZE}"))
     }
 
     fn visit_array<A>(self, mut array: A) -> Result<Self::Value>
@@ -533,13 +620,14 @@ map_impl!(BTreeMap<K: Ord, V>, map, BTreeMap::new());
 map_impl!(
     HashMap<K: Eq + Hash, V, H: BuildHasher + Default>,
     map,
-    HashMap::with_capacity_and_hasher(map.len(), H::default())
+    HashMap::with_capacity_and_hasher(cautious_capacity::<(K, V)>(map.len()), H::default())
 );
 
 map_impl!(
+    #[cfg(feature = "value")]
     IndexMap<K: Eq + Hash, V, H: BuildHasher + Default>,
     map,
-    IndexMap::with_capacity_and_hasher(map.len(), H::default())
+    IndexMap::with_capacity_and_hasher(cautious_capacity::<(K, V)>(map.len()), H::default())
 );
 
 impl<'de, T> Deserialize<'de> for Box<T>
@@ -554,3 +642,255 @@ where
         Ok(Box::new(value))
     }
 }
+
+struct CharVisitor;
+
+impl<'de> Visitor<'de> for CharVisitor {
+    type Value = char;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a string containing one character")
+    }
+
+    fn visit_string(self, string: &'de [u8]) -> Result<Self::Value> {
+        let mut chars = StrVisitor.visit_string(string)?.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(Error::invalid_value(Unexpected::String(string), &self)),
+        }
+    }
+
+    fn visit_symbol(self, symbol: &'de Sym) -> Result<Self::Value> {
+        let mut chars = symbol.as_str().chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(Error::invalid_value(Unexpected::Symbol(symbol), &self)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for char {
+    fn deserialize<D>(deserializer: D) -> Result<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(CharVisitor)
+    }
+}
+
+macro_rules! tuple_impls {
+    ($($len:expr => ($($n:tt $name:ident)+))+) => {
+        $(
+            impl<'de, $($name),+> Deserialize<'de> for ($($name,)+)
+            where
+                $($name: Deserialize<'de>,)+
+            {
+                fn deserialize<D>(deserializer: D) -> Result<Self>
+                where
+                    D: DeserializerTrait<'de>,
+                {
+                    struct TupleVisitor<$($name),+> {
+                        marker: PhantomData<($($name,)+)>,
+                    }
+
+                    impl<'de, $($name),+> Visitor<'de> for TupleVisitor<$($name),+>
+                    where
+                        $($name: Deserialize<'de>,)+
+                    {
+                        type Value = ($($name,)+);
+
+                        fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                            formatter.write_str(concat!("an array of length ", $len))
+                        }
+
+                        #[allow(non_snake_case)]
+                        fn visit_array<A>(self, mut array: A) -> Result<Self::Value>
+                        where
+                            A: ArrayAccess<'de>,
+                        {
+                            if array.len() != $len {
+                                return Err(Error::invalid_length(array.len(), &self));
+                            }
+                            $(
+                                let Some($name) = array.next_element()? else {
+                                    return Err(Error::invalid_length($n, &self));
+                                };
+                            )+
+                            Ok(($($name,)+))
+                        }
+                    }
+
+                    deserializer.deserialize(TupleVisitor { marker: PhantomData })
+                }
+            }
+        )+
+    }
+}
+
+// same pyramid as ser::impls
+tuple_impls! {
+    1 => (0 T0)
+    2 => (0 T0 1 T1)
+    3 => (0 T0 1 T1 2 T2)
+    4 => (0 T0 1 T1 2 T2 3 T3)
+    5 => (0 T0 1 T1 2 T2 3 T3 4 T4)
+    6 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5)
+    7 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6)
+    8 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7)
+    9 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8)
+    10 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9)
+    11 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10)
+    12 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11)
+    13 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11 12 T12)
+    14 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11 12 T12 13 T13)
+    15 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11 12 T12 13 T13 14 T14)
+    16 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11 12 T12 13 T13 14 T14 15 T15)
+}
+
+impl<'de> Deserialize<'de> for PathBuf {
+    fn deserialize<D>(deserializer: D) -> Result<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(StrVisitor).map(PathBuf::from)
+    }
+}
+
+impl<'de> Deserialize<'de> for Duration {
+    fn deserialize<D>(deserializer: D) -> Result<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let (secs, nanos) = <(u64, u32)>::deserialize(deserializer)?;
+        if nanos >= 1_000_000_000 {
+            return Err(Error::invalid_value(
+                Unexpected::Integer(nanos.into()),
+                &"less than a second of nanoseconds",
+            ));
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+impl<'de> Deserialize<'de> for SystemTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let duration = Duration::deserialize(deserializer)?;
+        UNIX_EPOCH
+            .checked_add(duration)
+            .ok_or_else(|| Error::invalid_value(Unexpected::Array, &"a SystemTime in range"))
+    }
+}
+
+macro_rules! from_str_impl {
+    ($($ty:ty => $expecting:literal),*) => {
+        $(
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D>(deserializer: D) -> Result<Self>
+                where
+                    D: DeserializerTrait<'de>,
+                {
+                    let string = deserializer.deserialize(StrVisitor)?;
+                    string.parse().map_err(|_| {
+                        Error::invalid_value(Unexpected::String(string.as_bytes()), &$expecting)
+                    })
+                }
+            }
+        )*
+    };
+}
+
+from_str_impl!(
+    IpAddr => "an IP address",
+    Ipv4Addr => "an IPv4 address",
+    Ipv6Addr => "an IPv6 address",
+    SocketAddr => "a socket address",
+    SocketAddrV4 => "an IPv4 socket address",
+    SocketAddrV6 => "an IPv6 socket address"
+);
+
+struct RangeVisitor<T> {
+    marker: PhantomData<T>,
+}
+
+impl<'de, T> Visitor<'de> for RangeVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = (T, T, bool);
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a Range")
+    }
+
+    fn visit_object<A>(self, class: &'de Sym, mut fields: A) -> Result<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        if class != "Range" {
+            return Err(Error::invalid_type(Unexpected::Object(class), &self));
+        }
+
+        let (mut start, mut end, mut exclusive) = (None, None, None);
+        while let Some(field) = fields.next_ivar()? {
+            match field.as_str() {
+                "begin" => start = Some(fields.next_value()?),
+                "end" => end = Some(fields.next_value()?),
+                "excl" => exclusive = Some(fields.next_value()?),
+                _ => {
+                    fields.next_value::<super::Ignored>()?;
+                }
+            }
+        }
+
+        Ok((
+            start.ok_or_else(|| Error::missing_field_in(class, Sym::new("begin")))?,
+            end.ok_or_else(|| Error::missing_field_in(class, Sym::new("end")))?,
+            exclusive.ok_or_else(|| Error::missing_field_in(class, Sym::new("excl")))?,
+        ))
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Range<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let (start, end, exclusive) = deserializer.deserialize(RangeVisitor {
+            marker: PhantomData,
+        })?;
+        if !exclusive {
+            return Err(Error::invalid_value(
+                Unexpected::Bool(exclusive),
+                &"an exclusive range (a...b)",
+            ));
+        }
+        Ok(start..end)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for RangeInclusive<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let (start, end, exclusive) = deserializer.deserialize(RangeVisitor {
+            marker: PhantomData,
+        })?;
+        if exclusive {
+            return Err(Error::invalid_value(
+                Unexpected::Bool(exclusive),
+                &"an inclusive range (a..b)",
+            ));
+        }
+        Ok(start..=end)
+    }
+}
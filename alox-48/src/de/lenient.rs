@@ -0,0 +1,169 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::sync::Arc;
+
+use super::{
+    ArrayAccess, DeserializerTrait, Error, HashAccess, InstanceAccess, IvarAccess, Primitive,
+    Result, Visitor,
+};
+use crate::Sym;
+
+/// An error that a lenient [`Deserializer`](super::Deserializer) recovered from.
+///
+/// See [`Deserializer::set_lenient`](super::Deserializer::set_lenient).
+/// Broken object links tolerated by [`Deserializer::set_tolerate_broken_links`](super::Deserializer::set_tolerate_broken_links)
+/// are recorded like this too.
+#[derive(Debug, Clone)]
+pub struct Recovered {
+    /// The offset into the input of the value that was replaced with `nil`.
+    pub position: usize,
+    /// The error that value failed with.
+    ///
+    /// Errors are shared between clones of a deserializer, as they can't be cloned themselves.
+    pub error: Arc<Error>,
+}
+
+// Lends a visitor to the deserializer, so it can be taken back if it's never used.
+pub(super) struct VisitorOnce<'a, V>(pub(super) &'a mut Option<V>);
+
+impl<V> VisitorOnce<'_, V> {
+    fn take(self) -> V {
+        self.0.take().expect("visitor should only be taken once")
+    }
+}
+
+impl<'de, V> Visitor<'de> for VisitorOnce<'_, V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &*self.0 {
+            Some(visitor) => visitor.expecting(formatter),
+            None => formatter.write_str("nothing"),
+        }
+    }
+
+    fn expected_primitive(&self) -> Option<Primitive> {
+        self.0.as_ref().and_then(Visitor::expected_primitive)
+    }
+
+    fn visit_nil(self) -> Result<Self::Value> {
+        self.take().visit_nil()
+    }
+
+    fn visit_bool(self, v: bool) -> Result<Self::Value> {
+        self.take().visit_bool(v)
+    }
+
+    fn visit_i32(self, v: i32) -> Result<Self::Value> {
+        self.take().visit_i32(v)
+    }
+
+    fn visit_i64(self, v: i64) -> Result<Self::Value> {
+        self.take().visit_i64(v)
+    }
+
+    fn visit_f64(self, v: f64) -> Result<Self::Value> {
+        self.take().visit_f64(v)
+    }
+
+    fn visit_hash<A>(self, map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        self.take().visit_hash(map)
+    }
+
+    fn visit_hash_with_default<A>(self, map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        self.take().visit_hash_with_default(map)
+    }
+
+    fn visit_array<A>(self, array: A) -> Result<Self::Value>
+    where
+        A: ArrayAccess<'de>,
+    {
+        self.take().visit_array(array)
+    }
+
+    fn visit_string(self, string: &'de [u8]) -> Result<Self::Value> {
+        self.take().visit_string(string)
+    }
+
+    fn visit_symbol(self, symbol: &'de Sym) -> Result<Self::Value> {
+        self.take().visit_symbol(symbol)
+    }
+
+    fn visit_regular_expression(self, regex: &'de [u8], flags: u8) -> Result<Self::Value> {
+        self.take().visit_regular_expression(regex, flags)
+    }
+
+    fn visit_object<A>(self, class: &'de Sym, instance_variables: A) -> Result<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        self.take().visit_object(class, instance_variables)
+    }
+
+    fn visit_struct<A>(self, name: &'de Sym, members: A) -> Result<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        self.take().visit_struct(name, members)
+    }
+
+    fn visit_class(self, class: &'de Sym) -> Result<Self::Value> {
+        self.take().visit_class(class)
+    }
+
+    fn visit_module(self, module: &'de Sym) -> Result<Self::Value> {
+        self.take().visit_module(module)
+    }
+
+    fn visit_instance<A>(self, instance: A) -> Result<Self::Value>
+    where
+        A: InstanceAccess<'de>,
+    {
+        self.take().visit_instance(instance)
+    }
+
+    fn visit_extended<D>(self, module: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.take().visit_extended(module, deserializer)
+    }
+
+    fn visit_user_class<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.take().visit_user_class(class, deserializer)
+    }
+
+    fn visit_user_data(self, class: &'de Sym, data: &'de [u8]) -> Result<Self::Value> {
+        self.take().visit_user_data(class, data)
+    }
+
+    fn visit_user_marshal<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.take().visit_user_marshal(class, deserializer)
+    }
+
+    fn visit_data<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.take().visit_data(class, deserializer)
+    }
+}
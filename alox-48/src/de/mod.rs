@@ -4,19 +4,46 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+mod classed;
 mod deserializer;
 mod error;
 mod ignored;
 mod impls;
+mod lenient;
+mod options;
+mod progress;
 mod traits;
 
+pub mod events;
+pub mod expect;
+pub mod seed;
+pub mod validate;
+
+pub use classed::{HashIvarAccess, ObjectDeserializer, StructMembers, UserDataDeserializer};
 pub use ignored::Ignored;
+pub use lenient::Recovered;
+pub use options::DeOptions;
+pub use progress::{Progress, DEFAULT_PROGRESS_INTERVAL};
 
+pub(crate) use error::DisplayPath;
 pub use error::Result;
-pub use error::{Error, Kind, Unexpected};
+pub use error::{
+    Error, IncompatibleStruct, InvalidLength, Kind, Mismatch, MissingField, ParseFloat,
+    PathSegment, Skipped, Unexpected, UnknownField, UnknownFields, WrongClass,
+};
+
+pub use events::{Event, EventReader};
+
+/// Limit a capacity taken from the input, so malicious lengths can't allocate huge amounts up front.
+///
+/// Collections with more elements than this still grow as they're deserialized.
+pub(crate) fn cautious_capacity<T>(len: usize) -> usize {
+    const MAX_PREALLOCATED_BYTES: usize = 1024 * 1024;
+    len.min(MAX_PREALLOCATED_BYTES / std::mem::size_of::<T>().max(1))
+}
 
-pub use deserializer::Deserializer;
+pub use deserializer::{Deserializer, DeserializerStats, DEFAULT_DEPTH_LIMIT};
 pub use traits::{
-    ArrayAccess, Deserialize, DeserializeSeed, Deserializer as DeserializerTrait, HashAccess,
-    InstanceAccess, IvarAccess, Visitor, VisitorInstance, VisitorOption,
+    ArrayAccess, Deserialize, DeserializeOwned, DeserializeSeed, Deserializer as DeserializerTrait,
+    HashAccess, InstanceAccess, IvarAccess, Primitive, Visitor, VisitorInstance, VisitorOption,
 };
@@ -0,0 +1,184 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::{
+    ops::RangeInclusive,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use super::{Deserializer, Progress, Result, DEFAULT_DEPTH_LIMIT};
+use crate::{ClassMapper, MARSHAL_VERSION};
+
+/// Everything that can be configured on a [`Deserializer`], in one place.
+///
+/// Pass this to [`from_bytes_with`](crate::from_bytes_with), or use [`DeOptions::deserializer`] to get a configured deserializer.
+/// Each option has a method of the same name, and defaults to the same thing as a new [`Deserializer`].
+/// New options may be added in the future, so this can only be built with those methods.
+///
+/// ```
+/// use alox_48::de::DeOptions;
+///
+/// let options = DeOptions::new().depth_limit(16).strict(true);
+/// let bytes = alox_48::to_bytes(vec![1, 2, 3]).unwrap();
+/// let array: Vec<i32> = alox_48::from_bytes_with(&bytes, &options).unwrap();
+/// assert_eq!(array, [1, 2, 3]);
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+#[allow(clippy::struct_excessive_bools)]
+pub struct DeOptions<'a> {
+    versions: RangeInclusive<[u8; 2]>,
+    depth_limit: usize,
+    time_budget: Option<Duration>,
+    progress: Option<Progress<'a>>,
+    cancel_token: Option<Arc<AtomicBool>>,
+    strict: bool,
+    deny_duplicates: bool,
+    track_path: bool,
+    lenient: bool,
+    tolerate_broken_links: bool,
+    class_mapper: Option<&'a dyn ClassMapper>,
+    deny_trailing_bytes: bool,
+}
+
+impl Default for DeOptions<'_> {
+    fn default() -> Self {
+        Self {
+            versions: MARSHAL_VERSION..=MARSHAL_VERSION,
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+            time_budget: None,
+            progress: None,
+            cancel_token: None,
+            strict: false,
+            deny_duplicates: false,
+            track_path: false,
+            lenient: false,
+            tolerate_broken_links: false,
+            class_mapper: None,
+            deny_trailing_bytes: false,
+        }
+    }
+}
+
+impl<'a> DeOptions<'a> {
+    /// The default options.
+    ///
+    /// Same as [`Default::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Which versions of marshal data are accepted. See [`Deserializer::with_versions`].
+    pub fn versions(mut self, versions: RangeInclusive<[u8; 2]>) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// See [`Deserializer::set_depth_limit`].
+    pub fn depth_limit(mut self, limit: usize) -> Self {
+        self.depth_limit = limit;
+        self
+    }
+
+    /// See [`Deserializer::set_time_budget`].
+    pub fn time_budget(mut self, budget: Option<Duration>) -> Self {
+        self.time_budget = budget;
+        self
+    }
+
+    /// See [`Deserializer::set_progress`].
+    pub fn progress(mut self, progress: Option<Progress<'a>>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// See [`Deserializer::set_cancel_token`].
+    pub fn cancel_token(mut self, token: Option<Arc<AtomicBool>>) -> Self {
+        self.cancel_token = token;
+        self
+    }
+
+    /// See [`Deserializer::set_strict`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// See [`Deserializer::set_deny_duplicates`].
+    pub fn deny_duplicates(mut self, deny: bool) -> Self {
+        self.deny_duplicates = deny;
+        self
+    }
+
+    /// See [`Deserializer::set_track_path`].
+    pub fn track_path(mut self, track: bool) -> Self {
+        self.track_path = track;
+        self
+    }
+
+    /// See [`Deserializer::set_lenient`].
+    ///
+    /// [`from_bytes_with`](crate::from_bytes_with) drops the errors that were recovered from.
+    /// Use [`DeOptions::deserializer`] and [`Deserializer::recovered`] to get them.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// See [`Deserializer::set_tolerate_broken_links`].
+    ///
+    /// Like with [`DeOptions::lenient`], [`from_bytes_with`](crate::from_bytes_with) drops the broken links that were tolerated.
+    pub fn tolerate_broken_links(mut self, tolerate: bool) -> Self {
+        self.tolerate_broken_links = tolerate;
+        self
+    }
+
+    /// See [`Deserializer::set_class_mapper`].
+    pub fn class_mapper(mut self, mapper: Option<&'a dyn ClassMapper>) -> Self {
+        self.class_mapper = mapper;
+        self
+    }
+
+    /// Whether [`from_bytes_with`](crate::from_bytes_with) fails if any bytes are left over,
+    /// like [`from_bytes_exact`](crate::from_bytes_exact).
+    ///
+    /// This has no effect on [`DeOptions::deserializer`], use [`Deserializer::finish`] to check for trailing bytes yourself.
+    pub fn deny_trailing_bytes(mut self, deny: bool) -> Self {
+        self.deny_trailing_bytes = deny;
+        self
+    }
+
+    /// Returns whether [`from_bytes_with`](crate::from_bytes_with) fails if any bytes are left over.
+    pub fn denies_trailing_bytes(&self) -> bool {
+        self.deny_trailing_bytes
+    }
+
+    /// Create a deserializer for `input` with these options.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the input is too short or has the wrong version, like [`Deserializer::with_versions`].
+    pub fn deserializer<'de>(&self, input: &'de [u8]) -> Result<Deserializer<'de>>
+    where
+        'a: 'de,
+    {
+        let mut deserializer = Deserializer::with_versions(input, self.versions.clone())?;
+        deserializer.set_depth_limit(self.depth_limit);
+        deserializer.set_time_budget(self.time_budget);
+        deserializer.set_progress(self.progress);
+        deserializer.set_cancel_token(self.cancel_token.clone());
+        deserializer.set_strict(self.strict);
+        deserializer.set_deny_duplicates(self.deny_duplicates);
+        deserializer.set_track_path(self.track_path);
+        deserializer.set_lenient(self.lenient);
+        deserializer.set_tolerate_broken_links(self.tolerate_broken_links);
+        if let Some(mapper) = self.class_mapper {
+            deserializer.set_class_mapper(mapper);
+        }
+        Ok(deserializer)
+    }
+}
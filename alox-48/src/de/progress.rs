@@ -0,0 +1,80 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::ops::ControlFlow;
+
+/// How often [`Progress`] reports by default, in bytes read.
+pub const DEFAULT_PROGRESS_INTERVAL: usize = 64 * 1024;
+
+/// A callback reporting how much of the input a [`Deserializer`](super::Deserializer) has read,
+/// set with [`Deserializer::set_progress`](super::Deserializer::set_progress).
+///
+/// The callback is given the number of bytes read so far (including the 2 byte header) and the size of the input.
+/// It's called before a value is deserialized, once at least [`every`](Progress::every) bytes were read since the last call,
+/// so it isn't called at all after the last value. Returning [`ControlFlow::Break`] cancels deserialization,
+/// which then fails with [`Kind::Cancelled`](super::Kind::Cancelled).
+///
+/// The callback borrows its state, so use a [`Cell`](std::cell::Cell) (or a channel, or an atomic) to get data out of it.
+///
+/// ```
+/// use std::{cell::Cell, ops::ControlFlow};
+///
+/// use alox_48::{de::Progress, Deserializer};
+///
+/// let bytes = alox_48::to_bytes(vec![1; 1000]).unwrap();
+/// let read = Cell::new(0);
+/// let report = |bytes_read, total| {
+///     assert_eq!(total, 2006);
+///     read.set(bytes_read);
+///     ControlFlow::Continue(())
+/// };
+///
+/// let mut deserializer = Deserializer::new(&bytes).unwrap();
+/// deserializer.set_progress(Some(Progress::new(&report).every(1024)));
+/// let array: Vec<i32> = deserializer.deserialize_value().unwrap();
+/// assert_eq!(array.len(), 1000);
+/// // the last report was at the first element after 1024 bytes
+/// assert_eq!(read.get(), 1026);
+/// ```
+#[derive(Clone, Copy)]
+pub struct Progress<'a> {
+    callback: &'a dyn Fn(usize, usize) -> ControlFlow<()>,
+    every: usize,
+}
+
+impl<'a> Progress<'a> {
+    /// Report progress to `callback`, every [`DEFAULT_PROGRESS_INTERVAL`] bytes.
+    pub fn new(callback: &'a dyn Fn(usize, usize) -> ControlFlow<()>) -> Self {
+        Self {
+            callback,
+            every: DEFAULT_PROGRESS_INTERVAL,
+        }
+    }
+
+    /// Report progress every `bytes` bytes read instead. `0` reports before every value.
+    #[must_use]
+    pub fn every(mut self, bytes: usize) -> Self {
+        self.every = bytes;
+        self
+    }
+
+    /// Returns how many bytes are read between reports.
+    pub fn interval(&self) -> usize {
+        self.every
+    }
+
+    pub(super) fn report(&self, bytes_read: usize, total: usize) -> ControlFlow<()> {
+        (self.callback)(bytes_read, total)
+    }
+}
+
+impl std::fmt::Debug for Progress<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Progress")
+            .field("every", &self.every)
+            .finish_non_exhaustive()
+    }
+}
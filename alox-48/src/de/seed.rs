@@ -0,0 +1,270 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Adapters for building [`DeserializeSeed`]s without writing visitors by hand.
+//!
+//! ```
+//! # #[cfg(feature = "value")] {
+//! use alox_48::de::{seed::{FnSeed, SeqSeed}, DeserializeSeed};
+//! use alox_48::DeError;
+//!
+//! let names = ["Aluxes", "Basil", "Gloria"];
+//! // Look up each ID in `names` while deserializing the array.
+//! let lookup = FnSeed::new(|id: usize| names.get(id).copied().ok_or_else(|| DeError::custom("unknown actor")));
+//!
+//! let value = alox_48::Value::Array(vec![2.into(), 0.into()]);
+//! let actors = SeqSeed::new(lookup).deserialize(&value).unwrap();
+//! assert_eq!(actors, ["Gloria", "Aluxes"]);
+//! # }
+//! ```
+
+#[cfg(feature = "value")]
+use std::hash::Hash;
+use std::{cell::RefCell, collections::HashSet, marker::PhantomData, sync::Arc};
+
+#[cfg(feature = "value")]
+use indexmap::IndexMap;
+
+#[cfg(feature = "value")]
+use super::HashAccess;
+use super::{
+    cautious_capacity, ArrayAccess, Deserialize, DeserializeSeed, DeserializerTrait, Result,
+    Visitor,
+};
+
+/// A seed that deserializes a `T` and then passes it to a closure.
+///
+/// This is the simplest way to make a seed that depends on runtime state, like a lookup table.
+pub struct FnSeed<T, F> {
+    func: F,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// A seed that deserializes an array, using a seed for each element.
+///
+/// The seed is cloned for each element.
+#[derive(Clone, Copy, Debug)]
+pub struct SeqSeed<S> {
+    seed: S,
+}
+
+/// A seed that deserializes a hash, using seeds for each key and value.
+///
+/// The seeds are cloned for each entry.
+#[cfg(feature = "value")]
+#[derive(Clone, Copy, Debug)]
+pub struct MapSeed<K, V> {
+    key_seed: K,
+    value_seed: V,
+}
+
+/// A seed that deserializes strings and symbols into `Arc<str>`s, sharing one allocation between equal strings.
+///
+/// Deserializing `Arc<str>` directly allocates every string separately.
+/// Use this instead when the same strings show up many times, like names and class names in a large file.
+/// The seed is `&StrInterner`, which is `Copy`, so it works with [`SeqSeed`] and [`MapSeed`].
+///
+/// ```
+/// # #[cfg(feature = "value")] {
+/// use std::sync::Arc;
+/// use alox_48::de::{seed::{SeqSeed, StrInterner}, DeserializeSeed};
+///
+/// let value = alox_48::marshal!(["Ghost", :Ghost, "Basilisk"]);
+/// let interner = StrInterner::new();
+/// let names = SeqSeed::new(&interner).deserialize(&value).unwrap();
+/// assert!(Arc::ptr_eq(&names[0], &names[1]));
+/// assert_eq!(interner.len(), 2);
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct StrInterner {
+    strings: RefCell<HashSet<Arc<str>>>,
+}
+
+impl StrInterner {
+    /// Create an empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared copy of `string`, adding it if it hasn't been seen before.
+    pub fn intern(&self, string: &str) -> Arc<str> {
+        let mut strings = self.strings.borrow_mut();
+        if let Some(shared) = strings.get(string) {
+            return shared.clone();
+        }
+        let shared: Arc<str> = string.into();
+        strings.insert(shared.clone());
+        shared
+    }
+
+    /// Returns how many different strings have been interned.
+    pub fn len(&self) -> usize {
+        self.strings.borrow().len()
+    }
+
+    /// Returns `true` if no strings have been interned.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for &StrInterner {
+    type Value = Arc<str>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        <&'de str>::deserialize(deserializer).map(|string| self.intern(string))
+    }
+}
+
+impl<T, F> FnSeed<T, F> {
+    /// Create a new seed from a closure.
+    pub fn new<U>(func: F) -> Self
+    where
+        F: FnOnce(T) -> Result<U>,
+    {
+        Self {
+            func,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, F> Clone for FnSeed<T, F>
+where
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            func: self.func.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, F> Copy for FnSeed<T, F> where F: Copy {}
+
+impl<T, F> std::fmt::Debug for FnSeed<T, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FnSeed").finish_non_exhaustive()
+    }
+}
+
+impl<'de, T, F, U> DeserializeSeed<'de> for FnSeed<T, F>
+where
+    T: Deserialize<'de>,
+    F: FnOnce(T) -> Result<U>,
+{
+    type Value = U;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        T::deserialize(deserializer).and_then(self.func)
+    }
+}
+
+impl<S> SeqSeed<S> {
+    /// Create a new seed that uses `seed` for each element.
+    pub fn new(seed: S) -> Self {
+        Self { seed }
+    }
+}
+
+impl<'de, S> DeserializeSeed<'de> for SeqSeed<S>
+where
+    S: DeserializeSeed<'de> + Clone,
+{
+    type Value = Vec<S::Value>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(self)
+    }
+}
+
+impl<'de, S> Visitor<'de> for SeqSeed<S>
+where
+    S: DeserializeSeed<'de> + Clone,
+{
+    type Value = Vec<S::Value>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("an array")
+    }
+
+    fn visit_array<A>(self, mut array: A) -> Result<Self::Value>
+    where
+        A: ArrayAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(cautious_capacity::<S::Value>(array.len()));
+        while let Some(value) = array.next_element_seed(self.seed.clone())? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(feature = "value")]
+impl<K, V> MapSeed<K, V> {
+    /// Create a new seed that uses `key_seed` for each key and `value_seed` for each value.
+    pub fn new(key_seed: K, value_seed: V) -> Self {
+        Self {
+            key_seed,
+            value_seed,
+        }
+    }
+}
+
+#[cfg(feature = "value")]
+impl<'de, K, V> DeserializeSeed<'de> for MapSeed<K, V>
+where
+    K: DeserializeSeed<'de> + Clone,
+    K::Value: Hash + Eq,
+    V: DeserializeSeed<'de> + Clone,
+{
+    type Value = IndexMap<K::Value, V::Value>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(self)
+    }
+}
+
+#[cfg(feature = "value")]
+impl<'de, K, V> Visitor<'de> for MapSeed<K, V>
+where
+    K: DeserializeSeed<'de> + Clone,
+    K::Value: Hash + Eq,
+    V: DeserializeSeed<'de> + Clone,
+{
+    type Value = IndexMap<K::Value, V::Value>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a hash")
+    }
+
+    fn visit_hash<A>(self, mut hash: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        let mut values =
+            IndexMap::with_capacity(cautious_capacity::<(K::Value, V::Value)>(hash.len()));
+        while let Some(key) = hash.next_key_seed(self.key_seed.clone())? {
+            let value = hash.next_value_seed(self.value_seed.clone())?;
+            values.insert(key, value);
+        }
+        Ok(values)
+    }
+}
@@ -3,8 +3,10 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
-use super::{error::Unexpected, Error, Result};
-use crate::Sym;
+use super::{error::Unexpected, Error, Ignored, Kind, Result};
+#[cfg(feature = "value")]
+use crate::Value;
+use crate::{RawValue, Sym};
 use std::marker::PhantomData;
 
 /// A structure that can be deserialized from ruby marshal format.
@@ -15,6 +17,17 @@ pub trait Deserialize<'de>: Sized {
         D: Deserializer<'de>;
 }
 
+/// A structure that can be deserialized without borrowing from the input.
+///
+/// This is a shorthand for `T: for<'de> Deserialize<'de>`, and is implemented for every such type.
+/// Types like `&'de str`, `&'de Sym`, and `&'de [u8]` borrow from the input, so they aren't `DeserializeOwned`.
+/// Use their owned counterparts instead: [`String`], [`Symbol`](crate::Symbol), and [`RbString`](crate::RbString).
+///
+/// Functions taking a `DeserializeOwned` (like [`from_reader`](crate::from_reader)) can deserialize from a buffer they drop or reuse afterwards.
+pub trait DeserializeOwned: for<'de> Deserialize<'de> {}
+
+impl<T> DeserializeOwned for T where T: for<'de> Deserialize<'de> {}
+
 /// A stateful form of `Deserialize`- useful when you need to pass data into a Deserialize impl.
 pub trait DeserializeSeed<'de>: Sized {
     /// The value that will be produced.
@@ -48,6 +61,25 @@ pub trait Deserializer<'de>: Sized {
     fn deserialize_instance<V>(self, visitor: V) -> Result<V::Value>
     where
         V: VisitorInstance<'de>;
+
+    /// Skip over a value, returning its bytes.
+    ///
+    /// This is used for deserializing [`RawValue`].
+    /// Deserializers that don't read marshal data can't support this, and return [`Kind::RawValueUnsupported`] by default.
+    fn deserialize_raw(self) -> Result<RawValue<'de>> {
+        Err(Error::from(Kind::RawValueUnsupported))
+    }
+}
+
+/// A primitive a [`Visitor`] expects, see [`Visitor::expected_primitive`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    /// A boolean.
+    Bool,
+    /// An integer.
+    Integer,
+    /// A float.
+    Float,
 }
 
 /// This trait represents a visitor that walks through a deserializer.
@@ -58,6 +90,14 @@ pub trait Visitor<'de>: Sized {
     /// Format a message stating what the visitor is expecting to receive.
     fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
 
+    /// The primitive this visitor deserializes, if it deserializes one.
+    ///
+    /// Deserializers that coerce values (see [`ValueDeserializer::set_coerce`](crate::ValueDeserializer::set_coerce))
+    /// use this to decide what to convert a value into. Visitors for anything else can leave this as `None`.
+    fn expected_primitive(&self) -> Option<Primitive> {
+        None
+    }
+
     /// Input contains a `nil` value.
     // Primitives
     fn visit_nil(self) -> Result<Self::Value> {
@@ -69,7 +109,16 @@ pub trait Visitor<'de>: Sized {
     }
     /// Input contains an integer value.
     fn visit_i32(self, v: i32) -> Result<Self::Value> {
-        Err(Error::invalid_value(Unexpected::Integer(v), &self))
+        Err(Error::invalid_value(Unexpected::Integer(v.into()), &self))
+    }
+    /// Input contains an integer value too large for an `i32`, usually from a bignum.
+    ///
+    /// By default this calls [`Visitor::visit_i32`] if `v` fits in an `i32`, and errors otherwise.
+    fn visit_i64(self, v: i64) -> Result<Self::Value> {
+        match i32::try_from(v) {
+            Ok(v) => self.visit_i32(v),
+            Err(_) => Err(Error::invalid_value(Unexpected::Integer(v), &self)),
+        }
     }
     /// Input contains a float value.
     fn visit_f64(self, v: f64) -> Result<Self::Value> {
@@ -84,6 +133,16 @@ pub trait Visitor<'de>: Sized {
     {
         Err(Error::invalid_value(Unexpected::Hash, &self))
     }
+    /// Input contains a hash with a default value.
+    ///
+    /// The default value comes after the entries, see [`HashAccess::next_default_seed`].
+    /// By default this calls [`Visitor::visit_hash`], and the default value is skipped.
+    fn visit_hash_with_default<A>(self, map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        self.visit_hash(map)
+    }
     /// Input contains an array.
     fn visit_array<A>(self, _array: A) -> Result<Self::Value>
     where
@@ -291,6 +350,42 @@ pub trait IvarAccess<'de> {
         self.next_value_seed(PhantomData::<T>)
     }
 
+    /// Get the next value, or capture it as a [`Value`] if `seed` fails to deserialize it.
+    ///
+    /// This should be called after `next_ivar`.
+    /// The outer error is for malformed data, which can't be captured either.
+    ///
+    /// Deserializers that can't go back and read the value again return the error from `seed` instead, which is the default.
+    #[cfg(feature = "value")]
+    fn next_value_seed_or_capture<V>(
+        &mut self,
+        seed: V,
+    ) -> Result<std::result::Result<V::Value, Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.next_value_seed(seed).map(Ok)
+    }
+
+    /// Get the next value, or capture it as a [`Value`] if it isn't a `T`.
+    ///
+    /// This should be called after `next_ivar`.
+    #[cfg(feature = "value")]
+    fn next_value_or_capture<T>(&mut self) -> Result<std::result::Result<T, Value>>
+    where
+        T: Deserialize<'de>,
+    {
+        self.next_value_seed_or_capture(PhantomData::<T>)
+    }
+
+    /// Skip the next value.
+    ///
+    /// This should be called after `next_ivar`, when the instance variable is not recognized.
+    /// Deserializers may count or reject skipped data, so prefer this over deserializing [`Ignored`] for unknown fields.
+    fn skip_value(&mut self) -> Result<()> {
+        self.next_value::<Ignored>().map(|_| ())
+    }
+
     /// Get the next instance variable and value.
     ///
     /// Returns `None` if there are no more instance variables.
@@ -389,6 +484,28 @@ pub trait HashAccess<'de> {
         self.next_entry_seed(PhantomData::<K>, PhantomData::<V>)
     }
 
+    /// Get the default value of the hash.
+    ///
+    /// Any entries that haven't been deserialized yet are skipped.
+    /// Returns `None` if the hash doesn't have a default value, or it was already deserialized.
+    fn next_default_seed<V>(&mut self, seed: V) -> Result<Option<V::Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let _ = seed;
+        Ok(None)
+    }
+
+    /// Get the default value of the hash.
+    ///
+    /// See [`HashAccess::next_default_seed`].
+    fn next_default<V>(&mut self) -> Result<Option<V>>
+    where
+        V: Deserialize<'de>,
+    {
+        self.next_default_seed(PhantomData::<V>)
+    }
+
     /// Get the number of elements.
     fn len(&self) -> usize;
 
@@ -428,7 +545,7 @@ pub trait ArrayAccess<'de> {
     }
 }
 
-impl<'de, 'a, A> IvarAccess<'de> for &'a mut A
+impl<'de, A> IvarAccess<'de> for &mut A
 where
     A: IvarAccess<'de>,
 {
@@ -443,6 +560,21 @@ where
         (**self).next_value_seed(seed)
     }
 
+    #[cfg(feature = "value")]
+    fn next_value_seed_or_capture<V>(
+        &mut self,
+        seed: V,
+    ) -> Result<std::result::Result<V::Value, Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        (**self).next_value_seed_or_capture(seed)
+    }
+
+    fn skip_value(&mut self) -> Result<()> {
+        (**self).skip_value()
+    }
+
     fn next_entry<T>(&mut self) -> Result<Option<(&'de Sym, T)>>
     where
         T: Deserialize<'de>,
@@ -459,7 +591,7 @@ where
     }
 }
 
-impl<'de, 'a, A> HashAccess<'de> for &'a mut A
+impl<'de, A> HashAccess<'de> for &mut A
 where
     A: HashAccess<'de>,
 {
@@ -511,6 +643,13 @@ where
         (**self).next_entry()
     }
 
+    fn next_default_seed<V>(&mut self, seed: V) -> Result<Option<V::Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        (**self).next_default_seed(seed)
+    }
+
     fn len(&self) -> usize {
         (**self).len()
     }
@@ -520,7 +659,7 @@ where
     }
 }
 
-impl<'de, 'a, A> ArrayAccess<'de> for &'a mut A
+impl<'de, A> ArrayAccess<'de> for &mut A
 where
     A: ArrayAccess<'de>,
 {
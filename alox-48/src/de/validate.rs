@@ -0,0 +1,329 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Walking marshal data without deserializing it into anything.
+//!
+//! [`ValidatingVisitor`] visits every value in its input, calling the hooks of a [`Validator`] along the way.
+//! Implement [`Validator`] and override only the hooks you care about to build linters and analysis tools.
+//!
+//! ```
+//! # #[cfg(feature = "value")] {
+//! use alox_48::{de::validate::{ValidatingVisitor, Validator}, DeResult, DeserializerTrait, Sym};
+//!
+//! // Counts objects of each class.
+//! #[derive(Default)]
+//! struct ClassCounter(std::collections::HashMap<String, usize>);
+//!
+//! impl<'de> Validator<'de> for ClassCounter {
+//!     fn visit_object(&mut self, class: &'de Sym, _len: usize) -> DeResult<()> {
+//!         *self.0.entry(class.as_str().to_owned()).or_default() += 1;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let bytes = alox_48::to_bytes(alox_48::Value::Object(alox_48::Object {
+//!     class: "Foo".into(),
+//!     fields: Default::default(),
+//! }))
+//! .unwrap();
+//!
+//! let mut counter = ClassCounter::default();
+//! let mut deserializer = alox_48::Deserializer::new(&bytes).unwrap();
+//! deserializer.deserialize(ValidatingVisitor::new(&mut counter)).unwrap();
+//! assert_eq!(counter.0["Foo"], 1);
+//! # }
+//! ```
+
+use super::{
+    ArrayAccess, DeserializeSeed, DeserializerTrait, HashAccess, InstanceAccess, IvarAccess,
+    Result, Visitor,
+};
+use crate::Sym;
+
+/// Hooks called by a [`ValidatingVisitor`] as it walks through marshal data.
+///
+/// Every hook does nothing by default. Returning an error from a hook stops the walk.
+/// Hooks for values containing other values are called before their contents are visited.
+#[allow(unused_variables)]
+pub trait Validator<'de> {
+    /// Called on `nil`.
+    fn visit_nil(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Called on `true` and `false`.
+    fn visit_bool(&mut self, v: bool) -> Result<()> {
+        Ok(())
+    }
+    /// Called on an integer.
+    fn visit_i32(&mut self, v: i32) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a bignum.
+    ///
+    /// By default this calls [`Validator::visit_i32`] if `v` fits in an `i32`.
+    fn visit_i64(&mut self, v: i64) -> Result<()> {
+        match i32::try_from(v) {
+            Ok(v) => self.visit_i32(v),
+            Err(_) => Ok(()),
+        }
+    }
+    /// Called on a float.
+    fn visit_f64(&mut self, v: f64) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a hash, before its entries.
+    ///
+    /// Hashes with a default value call this too, and their default value is visited after the entries.
+    fn visit_hash(&mut self, len: usize) -> Result<()> {
+        Ok(())
+    }
+    /// Called on an array, before its elements.
+    fn visit_array(&mut self, len: usize) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a string.
+    ///
+    /// The encoding of a string is stored as an instance variable, see [`Validator::visit_instance`].
+    fn visit_string(&mut self, data: &'de [u8]) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a symbol.
+    fn visit_symbol(&mut self, symbol: &'de Sym) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a regular expression.
+    fn visit_regular_expression(&mut self, regex: &'de [u8], flags: u8) -> Result<()> {
+        Ok(())
+    }
+    /// Called on an object, before its instance variables.
+    fn visit_object(&mut self, class: &'de Sym, len: usize) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a struct, before its members.
+    fn visit_struct(&mut self, name: &'de Sym, len: usize) -> Result<()> {
+        Ok(())
+    }
+    /// Called on each instance variable (or struct member) of an object, struct, or instance, before its value.
+    fn visit_ivar(&mut self, ivar: &'de Sym) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a class.
+    fn visit_class(&mut self, class: &'de Sym) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a module.
+    fn visit_module(&mut self, module: &'de Sym) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a value with extra instance variables, after the value and before its instance variables.
+    fn visit_instance(&mut self, len: usize) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a value extended with a module, before the value.
+    fn visit_extended(&mut self, module: &'de Sym) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a subclass of a builtin class, before the value.
+    fn visit_user_class(&mut self, class: &'de Sym) -> Result<()> {
+        Ok(())
+    }
+    /// Called on user data.
+    fn visit_user_data(&mut self, class: &'de Sym, data: &'de [u8]) -> Result<()> {
+        Ok(())
+    }
+    /// Called on a user marshal object, before the value.
+    fn visit_user_marshal(&mut self, class: &'de Sym) -> Result<()> {
+        Ok(())
+    }
+    /// Called on C extension data, before the value.
+    fn visit_data(&mut self, class: &'de Sym) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A validator that accepts everything.
+impl Validator<'_> for () {}
+
+/// A visitor that walks every value in its input, calling the hooks of a [`Validator`].
+///
+/// This is also a [`DeserializeSeed`], so it can be used to walk part of some data.
+#[derive(Debug)]
+pub struct ValidatingVisitor<'a, V> {
+    validator: &'a mut V,
+}
+
+impl<'a, V> ValidatingVisitor<'a, V> {
+    /// Create a new visitor calling the hooks of `validator`.
+    pub fn new(validator: &'a mut V) -> Self {
+        Self { validator }
+    }
+
+    fn reborrow(&mut self) -> ValidatingVisitor<'_, V> {
+        ValidatingVisitor {
+            validator: self.validator,
+        }
+    }
+}
+
+impl<'de, V> ValidatingVisitor<'_, V>
+where
+    V: Validator<'de>,
+{
+    fn walk_ivars<A>(&mut self, mut ivars: A) -> Result<()>
+    where
+        A: IvarAccess<'de>,
+    {
+        while let Some(ivar) = ivars.next_ivar()? {
+            self.validator.visit_ivar(ivar)?;
+            ivars.next_value_seed(self.reborrow())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de, V> DeserializeSeed<'de> for ValidatingVisitor<'_, V>
+where
+    V: Validator<'de>,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(self)
+    }
+}
+
+impl<'de, V> Visitor<'de> for ValidatingVisitor<'_, V>
+where
+    V: Validator<'de>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_nil(self) -> Result<Self::Value> {
+        self.validator.visit_nil()
+    }
+    fn visit_bool(self, v: bool) -> Result<Self::Value> {
+        self.validator.visit_bool(v)
+    }
+    fn visit_i32(self, v: i32) -> Result<Self::Value> {
+        self.validator.visit_i32(v)
+    }
+    fn visit_i64(self, v: i64) -> Result<Self::Value> {
+        self.validator.visit_i64(v)
+    }
+    fn visit_f64(self, v: f64) -> Result<Self::Value> {
+        self.validator.visit_f64(v)
+    }
+
+    fn visit_hash<A>(mut self, mut map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        self.validator.visit_hash(map.len())?;
+        while let Some(()) = map.next_key_seed(self.reborrow())? {
+            map.next_value_seed(self.reborrow())?;
+        }
+        Ok(())
+    }
+    fn visit_hash_with_default<A>(mut self, mut map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        self.validator.visit_hash(map.len())?;
+        while let Some(()) = map.next_key_seed(self.reborrow())? {
+            map.next_value_seed(self.reborrow())?;
+        }
+        map.next_default_seed(self.reborrow())?;
+        Ok(())
+    }
+    fn visit_array<A>(mut self, mut array: A) -> Result<Self::Value>
+    where
+        A: ArrayAccess<'de>,
+    {
+        self.validator.visit_array(array.len())?;
+        while let Some(()) = array.next_element_seed(self.reborrow())? {}
+        Ok(())
+    }
+    fn visit_string(self, string: &'de [u8]) -> Result<Self::Value> {
+        self.validator.visit_string(string)
+    }
+    fn visit_symbol(self, symbol: &'de Sym) -> Result<Self::Value> {
+        self.validator.visit_symbol(symbol)
+    }
+    fn visit_regular_expression(self, regex: &'de [u8], flags: u8) -> Result<Self::Value> {
+        self.validator.visit_regular_expression(regex, flags)
+    }
+
+    fn visit_object<A>(mut self, class: &'de Sym, instance_variables: A) -> Result<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        self.validator
+            .visit_object(class, instance_variables.len())?;
+        self.walk_ivars(instance_variables)
+    }
+    fn visit_struct<A>(mut self, name: &'de Sym, members: A) -> Result<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        self.validator.visit_struct(name, members.len())?;
+        self.walk_ivars(members)
+    }
+
+    fn visit_class(self, class: &'de Sym) -> Result<Self::Value> {
+        self.validator.visit_class(class)
+    }
+    fn visit_module(self, module: &'de Sym) -> Result<Self::Value> {
+        self.validator.visit_module(module)
+    }
+
+    fn visit_instance<A>(mut self, instance: A) -> Result<Self::Value>
+    where
+        A: InstanceAccess<'de>,
+    {
+        let ((), instance_variables) = instance.value_seed(self.reborrow())?;
+        self.validator.visit_instance(instance_variables.len())?;
+        self.walk_ivars(instance_variables)
+    }
+    fn visit_extended<D>(self, module: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.validator.visit_extended(module)?;
+        deserializer.deserialize(self)
+    }
+
+    fn visit_user_class<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.validator.visit_user_class(class)?;
+        deserializer.deserialize(self)
+    }
+    fn visit_user_data(self, class: &'de Sym, data: &'de [u8]) -> Result<Self::Value> {
+        self.validator.visit_user_data(class, data)
+    }
+    fn visit_user_marshal<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.validator.visit_user_marshal(class)?;
+        deserializer.deserialize(self)
+    }
+    fn visit_data<D>(self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.validator.visit_data(class)?;
+        deserializer.deserialize(self)
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use url::Url;
+
+use crate::{
+    de::{Result as DeResult, Unexpected},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializerTrait, Serialize, SerializerTrait,
+};
+
+impl<'de> Deserialize<'de> for Url {
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let string = <&str>::deserialize(deserializer)?;
+        Url::parse(string)
+            .map_err(|_| DeError::invalid_value(Unexpected::String(string.as_bytes()), &"a URL"))
+    }
+}
+
+impl Serialize for Url {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serializer.serialize_rust_string(self.as_str())
+    }
+}
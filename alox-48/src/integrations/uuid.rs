@@ -0,0 +1,57 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use uuid::Uuid;
+
+use crate::{
+    de::{Result as DeResult, Unexpected},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializerTrait, Serialize, SerializerTrait, Sym, Visitor,
+};
+
+struct UuidVisitor;
+
+impl<'de> Visitor<'de> for UuidVisitor {
+    type Value = Uuid;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a UUID string or 16 bytes")
+    }
+
+    fn visit_string(self, string: &'de [u8]) -> DeResult<Self::Value> {
+        // no textual uuid is 16 characters long, so this can't be ambiguous
+        if let Ok(bytes) = <[u8; 16]>::try_from(string) {
+            return Ok(Uuid::from_bytes(bytes));
+        }
+        Uuid::try_parse_ascii(string)
+            .map_err(|_| DeError::invalid_value(Unexpected::String(string), &self))
+    }
+
+    fn visit_symbol(self, symbol: &'de Sym) -> DeResult<Self::Value> {
+        Uuid::try_parse(symbol.as_str())
+            .map_err(|_| DeError::invalid_value(Unexpected::Symbol(symbol), &self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Uuid {
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(UuidVisitor)
+    }
+}
+
+// Serialized as a hyphenated string, like `SecureRandom.uuid`.
+// Wrap the bytes in `SerializeByteString` for the compact form.
+impl Serialize for Uuid {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serializer.serialize_rust_string(self.hyphenated().encode_lower(&mut Uuid::encode_buffer()))
+    }
+}
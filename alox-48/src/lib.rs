@@ -5,8 +5,7 @@
     missing_copy_implementations,
     clippy::panic,
     clippy::panic_in_result_fn,
-    clippy::panicking_unwrap,
-    clippy::all
+    clippy::panicking_unwrap
 )]
 #![allow(
     clippy::must_use_candidate,
@@ -45,6 +44,7 @@
 //!
 //! This behavior could be simulated with `Rc` and/or `Arc` like `thurgood`, however for the sake of ergonomics (and memory cycles)
 //! alox-48 deserializes object links as copies instead. alox-48 does not serialize object links at all.
+//! [`analysis::link_report`] shows how much copying the links in some data costs.
 //!
 //! Some common terminology:
 //! - ivar: Instance variable. These are variables that are attached to an object.
@@ -59,40 +59,90 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+pub mod adapters;
+
+pub mod analysis;
+
 /// A convenience module for getting exact details about where an error occurred.
 pub mod path_to_error;
 
-pub(crate) mod tag;
+pub mod patch;
+
+#[cfg(feature = "value")]
+pub mod schema;
+
+pub mod tag;
+
+pub mod testing;
 
 /// Marshal Deserialization framework and Deserializer.
 pub mod de;
 /// Marshal Serialization framework and Serializer.
 pub mod ser;
 
+#[cfg(feature = "value")]
 mod value;
-pub use value::{from_value, to_value, Serializer as ValueSerializer, Value};
+#[cfg(feature = "value")]
+pub use value::arena;
+#[cfg(feature = "value")]
+pub use value::{
+    from_value, from_value_coerced, from_value_owned, to_value, Deserializer as ValueDeserializer,
+    OwnedDeserializer as OwnedValueDeserializer, Serializer as ValueSerializer, ValueExt,
+};
+
+mod raw_value;
+pub use raw_value::RawValue;
+
+mod macros;
+
+mod class_mapper;
+pub use class_mapper::ClassMapper;
 
 mod rb_types;
+#[cfg(feature = "value")]
+pub use rb_types::{InstanceExt, ObjectBuilder, ObjectExt, RbStructBuilder, RbStructExt};
+pub use rb_types::{UserdataBuilder, UserdataExt, UserdataReader};
+mod integrations;
+pub mod prelude;
+#[cfg(any(feature = "chrono", feature = "time"))]
+mod rb_time;
+pub mod ruby_string;
+#[doc(inline)]
+pub use alox_48_types::sym;
+#[doc(inline)]
+pub use alox_48_types::{
+    ByteBuf, Extended, RbString, Sym, Symbol, SymbolOrString, UserMarshal, Userdata,
+};
+#[cfg(feature = "value")]
 #[doc(inline)]
-pub use rb_types::{
-    Instance, Object, RbArray, RbFields, RbHash, RbString, RbStruct, Sym, Symbol, Userdata,
+pub use alox_48_types::{
+    Instance, IntegerOutOfRange, MergeStrategy, Object, RbArray, RbFields, RbHash, RbHashExt,
+    RbOpenStruct, RbSet, RbStringWithIvars, RbStruct, Value,
 };
+#[cfg(feature = "bytes")]
+pub use bytes;
 
 #[doc(inline)]
 pub use de::{
-    ArrayAccess, Deserialize, Deserializer, DeserializerTrait, Error as DeError, HashAccess,
-    InstanceAccess, IvarAccess, Result as DeResult, Visitor, VisitorInstance, VisitorOption,
+    ArrayAccess, DeOptions, Deserialize, DeserializeOwned, Deserializer, DeserializerTrait,
+    Error as DeError, HashAccess, InstanceAccess, IvarAccess, Result as DeResult, Visitor,
+    VisitorInstance, VisitorOption,
 };
 #[doc(inline)]
 pub use ser::{
-    ByteString as SerializeByteString, Error as SerError, Result as SerResult, Serialize,
-    SerializeArray, SerializeHash, SerializeIvars, Serializer, SerializerTrait,
+    ByteString as SerializeByteString, Error as SerError, Result as SerResult, SerOptions,
+    Serialize, SerializeArray, SerializeHash, SerializeIvars, Serializer, SerializerTrait,
 };
 
 #[cfg(feature = "derive")]
 #[doc(inline)]
 pub use alox_48_derive::{Deserialize, Serialize};
 
+/// The version of marshal written by alox-48, and the only version read by default, as `[major, minor]`.
+///
+/// Every ruby since 1.8 uses this version.
+pub const MARSHAL_VERSION: [u8; 2] = [4, 8];
+
 /// Deserialize data from some bytes.
 /// It's a convenience function over [`Deserializer::new`] and [`Deserialize::deserialize`].
 #[allow(clippy::missing_errors_doc)]
@@ -104,6 +154,191 @@ where
     T::deserialize(&mut deserializer)
 }
 
+/// Deserialize data from some bytes, failing if any bytes are left over.
+///
+/// Like [`from_bytes`], except trailing input returns [`de::Kind::TrailingBytes`] instead of being ignored.
+#[allow(clippy::missing_errors_doc)]
+pub fn from_bytes_exact<'de, T>(data: &'de [u8]) -> Result<T, DeError>
+where
+    T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::new(data)?;
+    let value = T::deserialize(&mut deserializer)?;
+    match deserializer.finish() {
+        0 => Ok(value),
+        remaining => Err(DeError::from(de::Kind::TrailingBytes(remaining))),
+    }
+}
+
+/// Deserialize a [`Value`] from a shared buffer, without copying its strings and user data.
+///
+/// Strings, regular expressions, and user data in the returned value are slices of `data`,
+/// so large blobs (like RPG Maker's `Table`) don't need to be copied out of it.
+/// This keeps all of `data` alive for as long as any of them are.
+///
+/// ```
+/// use alox_48::bytes::Bytes;
+///
+/// let data = Bytes::from(alox_48::to_bytes(alox_48::Value::userdata("Table", [0; 1024])).unwrap());
+/// let value = alox_48::from_bytes_buf(data.clone()).unwrap();
+///
+/// let table = value.into_userdata().unwrap();
+/// assert_eq!(table.data.len(), 1024);
+/// assert!(data.as_ptr_range().contains(&table.data.as_ptr()));
+/// ```
+#[cfg(all(feature = "bytes", feature = "value"))]
+#[allow(clippy::missing_errors_doc, clippy::needless_pass_by_value)]
+pub fn from_bytes_buf(data: bytes::Bytes) -> Result<Value, DeError> {
+    let mut deserializer = Deserializer::new(&data)?;
+    value::deserialize_shared(&mut deserializer, data.clone())
+}
+
+/// Deserialize data from some bytes, with a [`Deserializer`] configured by `options`.
+///
+/// ```
+/// use alox_48::DeOptions;
+///
+/// let bytes = alox_48::to_bytes([[[1]]]).unwrap();
+/// let options = DeOptions::new().depth_limit(2);
+/// assert!(alox_48::from_bytes_with::<Vec<Vec<Vec<i32>>>>(&bytes, &options).is_err());
+/// ```
+#[allow(clippy::missing_errors_doc)]
+pub fn from_bytes_with<'de, T>(data: &'de [u8], options: &DeOptions<'de>) -> Result<T, DeError>
+where
+    T: Deserialize<'de>,
+{
+    let mut deserializer = options.deserializer(data)?;
+    let value = T::deserialize(&mut deserializer)?;
+    match deserializer.finish() {
+        remaining if remaining > 0 && options.denies_trailing_bytes() => {
+            Err(DeError::from(de::Kind::TrailingBytes(remaining)))
+        }
+        _ => Ok(value),
+    }
+}
+
+/// Deserialize data from some bytes, replacing values that fail to parse with `nil`.
+///
+/// Returns every error that was recovered from along with the value.
+/// See [`Deserializer::set_lenient`] for what can be recovered from.
+///
+/// ```
+/// # #[cfg(feature = "value")] {
+/// // [1, <an object link to nothing>, 3]
+/// let bytes = b"\x04\x08[\x08i\x06@\x06i\x08";
+/// let (value, recovered): (alox_48::Value, _) = alox_48::from_bytes_lenient(bytes).unwrap();
+/// assert_eq!(value, alox_48::marshal!([1, nil, 3]));
+/// assert_eq!(recovered.len(), 1);
+/// # }
+/// ```
+#[allow(clippy::missing_errors_doc)]
+pub fn from_bytes_lenient<'de, T>(data: &'de [u8]) -> Result<(T, Vec<de::Recovered>), DeError>
+where
+    T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::new(data)?;
+    deserializer.set_lenient(true);
+    let value = T::deserialize(&mut deserializer)?;
+    Ok((value, deserializer.take_recovered()))
+}
+
+/// Deserialize data from some bytes, tracking the path to any error.
+///
+/// Like [`from_bytes`], but also returns a [`path_to_error::Trace`] of where deserialization failed.
+/// The trace is empty if it succeeded.
+pub fn from_bytes_traced<'de, T>(data: &'de [u8]) -> (Result<T, DeError>, path_to_error::Trace)
+where
+    T: Deserialize<'de>,
+{
+    let mut trace = path_to_error::Trace::new();
+    let result = Deserializer::new(data).and_then(|mut deserializer| {
+        T::deserialize(path_to_error::Deserializer::new(
+            &mut deserializer,
+            &mut trace,
+        ))
+    });
+    (result, trace)
+}
+
+/// Deserialize a `T` from a [`Value`], tracking the path to any error.
+///
+/// Like [`from_value`], but also returns a [`path_to_error::Trace`] of where deserialization failed.
+/// The trace is empty if it succeeded.
+#[cfg(feature = "value")]
+pub fn from_value_traced<'de, T>(value: &'de Value) -> (Result<T, DeError>, path_to_error::Trace)
+where
+    T: Deserialize<'de>,
+{
+    let mut trace = path_to_error::Trace::new();
+    let result = T::deserialize(path_to_error::Deserializer::new(value, &mut trace));
+    (result, trace)
+}
+
+/// Check that some bytes are valid marshal data, without deserializing them into anything.
+///
+/// See [`de::validate`] to run checks of your own while walking the data.
+#[allow(clippy::missing_errors_doc)]
+pub fn verify_bytes(data: &[u8]) -> Result<(), DeError> {
+    let mut deserializer = Deserializer::new(data)?;
+    deserializer.deserialize(de::validate::ValidatingVisitor::new(&mut ()))
+}
+
+/// Check that some bytes are valid marshal data without repeated instance variables or hash keys.
+///
+/// Like [`verify_bytes`], except duplicates return [`de::Kind::DuplicateField`] or [`de::Kind::DuplicateKey`].
+/// Use [`Deserializer::set_deny_duplicates`] to check for duplicates while deserializing.
+#[allow(clippy::missing_errors_doc)]
+pub fn verify_bytes_unique(data: &[u8]) -> Result<(), DeError> {
+    let mut deserializer = Deserializer::new(data)?;
+    deserializer.set_deny_duplicates(true);
+    deserializer.deserialize(de::validate::ValidatingVisitor::new(&mut ()))
+}
+
+/// Deserialize data from a reader.
+///
+/// The input is buffered in full before being handed to a [`Deserializer`].
+/// Because the buffer is dropped before this returns, `T` can't borrow from the input.
+///
+/// ```
+/// let bytes = alox_48::to_bytes("hello").unwrap();
+/// let string: String = alox_48::from_reader(bytes.as_slice()).unwrap();
+/// assert_eq!(string, "hello");
+/// ```
+pub fn from_reader<T, R>(mut reader: R) -> Result<T, DeError>
+where
+    T: DeserializeOwned,
+    R: std::io::Read,
+{
+    let mut data = Vec::new();
+    reader
+        .read_to_end(&mut data)
+        .map_err(|e| DeError::from(de::Kind::Io(e)))?;
+
+    from_bytes(&data)
+}
+
+/// Deserialize data from an async reader.
+///
+/// The visitor API is synchronous, so only reading is async here:
+/// the input is buffered in full before being handed to a [`Deserializer`].
+/// Because the buffer is dropped before this returns, `T` can't borrow from the input.
+#[cfg(feature = "async")]
+pub async fn from_async_reader<T, R>(mut reader: R) -> Result<T, DeError>
+where
+    T: DeserializeOwned,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut data = Vec::new();
+    reader
+        .read_to_end(&mut data)
+        .await
+        .map_err(|e| DeError::from(de::Kind::Io(e)))?;
+
+    from_bytes(&data)
+}
+
 /// Serialize the type into bytes.
 ///
 /// # Errors
@@ -118,636 +353,6485 @@ where
     Ok(serializer.output)
 }
 
+/// Serialize the type into bytes, with a [`Serializer`] configured by `options`.
+///
+/// # Errors
+///
+/// Like [`to_bytes`], and also if the output is larger than [`SerOptions::output_limit`].
+/// The limit is checked as the output is written, so it never grows past it.
+/// To check the size before writing anything, use [`serialized_size_with`] first.
+pub fn to_bytes_with<T>(data: T, options: &SerOptions) -> Result<Vec<u8>, SerError>
+where
+    T: Serialize,
+{
+    let mut serializer = options.serializer();
+    data.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+/// Returns how many bytes serializing the type would take, without writing them.
+///
+/// This is the length of what [`to_bytes`] returns, see [`Serializer::counting`].
+///
+/// # Errors
+///
+/// Like [`to_bytes`].
+pub fn serialized_size<T>(data: T) -> Result<usize, SerError>
+where
+    T: Serialize,
+{
+    let mut counter = Serializer::counting();
+    data.serialize(&mut counter)?;
+    Ok(counter.bytes_written())
+}
+
+/// Returns how many bytes serializing the type with `options` would take, without writing them.
+///
+/// This is the length of what [`to_bytes_with`] returns.
+///
+/// # Errors
+///
+/// Like [`to_bytes_with`].
+pub fn serialized_size_with<T>(data: T, options: &SerOptions) -> Result<usize, SerError>
+where
+    T: Serialize,
+{
+    let mut counter = Serializer::counting();
+    options.apply(&mut counter);
+    data.serialize(&mut counter)?;
+    Ok(counter.bytes_written())
+}
+
+/// Serialize the type into bytes, tracking the path to any error.
+///
+/// Like [`to_bytes`], but also returns a [`path_to_error::Trace`] of where serialization failed.
+/// The trace is empty if it succeeded.
+pub fn to_bytes_traced<T>(data: T) -> (Result<Vec<u8>, SerError>, path_to_error::Trace)
+where
+    T: Serialize,
+{
+    let mut trace = path_to_error::Trace::new();
+    let mut serializer = Serializer::new();
+    let result = data
+        .serialize(path_to_error::Serializer::new(&mut serializer, &mut trace))
+        .map(|()| serializer.output);
+    (result, trace)
+}
+
+/// Convert a `T` into a [`Value`], tracking the path to any error.
+///
+/// Like [`to_value`], but also returns a [`path_to_error::Trace`] of where serialization failed.
+/// The trace is empty if it succeeded.
+#[cfg(feature = "value")]
+pub fn to_value_traced<T>(data: T) -> (Result<Value, SerError>, path_to_error::Trace)
+where
+    T: Serialize,
+{
+    let mut trace = path_to_error::Trace::new();
+    let result = data.serialize(path_to_error::Serializer::new(ValueSerializer, &mut trace));
+    (result, trace)
+}
+
 #[cfg(test)]
-mod ints {
+mod msrv {
+    // The MSRV is checked by `build.rs`, this just keeps the docs in sync with it.
     #[test]
-    fn deserialize() {
-        let bytes = &[0x04, 0x08, 0x69, 0x19];
+    fn readme_matches_manifest() {
+        // the readme is next to the manifest in the published package, and in the workspace root in the repo
+        let manifest_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let readme = std::fs::read_to_string(manifest_dir.join("README.md"))
+            .or_else(|_| std::fs::read_to_string(manifest_dir.join("../README.md")))
+            .unwrap();
+        let msrv = env!("CARGO_PKG_RUST_VERSION");
+
+        assert!(
+            readme.contains(&format!("minimum supported rust version is **{msrv}**")),
+            "README does not mention the MSRV declared in Cargo.toml ({msrv})"
+        );
+    }
+}
 
-        let int: u8 = crate::from_bytes(bytes).unwrap();
+#[cfg(all(test, feature = "value"))]
+mod depth_limit {
+    use crate::{de::Kind, Deserialize, Value};
 
-        assert_eq!(int, 20);
+    fn nested_arrays(depth: usize) -> Vec<u8> {
+        let mut bytes = vec![0x04, 0x08];
+        for _ in 0..depth {
+            bytes.extend_from_slice(&[b'[', 0x06]);
+        }
+        bytes.push(b'0');
+        bytes
     }
 
     #[test]
-    fn round_trip() {
-        let int = 123;
-
-        let bytes = crate::to_bytes(int).unwrap();
+    fn within_limit() {
+        let bytes = nested_arrays(crate::de::DEFAULT_DEPTH_LIMIT - 1);
+        crate::from_bytes::<Value>(&bytes).unwrap();
+    }
 
-        let int2 = crate::from_bytes(&bytes).unwrap();
+    #[test]
+    fn exceeded() {
+        let bytes = nested_arrays(100_000);
+        let err = crate::from_bytes::<Value>(&bytes).unwrap_err();
 
-        assert_eq!(int, int2);
+        assert!(matches!(err.kind, Kind::DepthLimitExceeded(_)));
     }
 
     #[test]
-    fn round_trip_value() {
-        let value = crate::Value::Integer(123);
-
-        let bytes = crate::to_bytes(&value).unwrap();
+    fn custom_limit() {
+        let bytes = nested_arrays(8);
 
-        let value2: crate::Value = crate::from_bytes(&bytes).unwrap();
+        let mut deserializer = crate::Deserializer::new(&bytes).unwrap();
+        deserializer.set_depth_limit(4);
+        let err = Value::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(err.kind, Kind::DepthLimitExceeded(4)));
 
-        assert_eq!(value, value2);
+        let mut deserializer = crate::Deserializer::new(&bytes).unwrap();
+        deserializer.set_depth_limit(16);
+        Value::deserialize(&mut deserializer).unwrap();
     }
 
     #[test]
-    fn negatives() {
-        let bytes = &[0x04, 0x08, 0x69, 0xfd, 0x1d, 0xf0, 0xfc];
+    fn value() {
+        let mut value = Value::Nil;
+        for _ in 0..8 {
+            value = Value::Array(vec![value]);
+        }
 
-        let int: i32 = crate::from_bytes(bytes).unwrap();
+        crate::from_value::<Value>(&value).unwrap();
 
-        assert_eq!(int, -200_675);
+        let mut deserializer = crate::ValueDeserializer::new(&value);
+        deserializer.set_depth_limit(4);
+        let err = Value::deserialize(deserializer).unwrap_err();
+        assert!(matches!(err.kind, Kind::DepthLimitExceeded(4)));
     }
 }
 
 #[cfg(test)]
-mod strings {
+mod versions {
+    use crate::{de::Kind, Deserializer, Serialize, Serializer, MARSHAL_VERSION};
+
     #[test]
-    fn deserialize() {
-        let bytes = &[
-            0x04, 0x08, 0x49, 0x22, 0x11, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65,
-            0x72, 0x65, 0x21, 0x06, 0x3a, 0x06, 0x45, 0x54,
-        ];
+    fn rejects_other_versions() {
+        let err = Deserializer::new(&[4, 6, b'0']).unwrap_err();
+        assert!(matches!(err.kind, Kind::VersionError([4, 6])));
 
-        let str: &str = crate::from_bytes(bytes).unwrap();
+        let deserializer = Deserializer::new(&[4, 8, b'0']).unwrap();
+        assert_eq!(deserializer.version(), MARSHAL_VERSION);
+    }
 
-        assert_eq!(str, "hello there!");
+    #[test]
+    fn accepted_range() {
+        let mut deserializer =
+            Deserializer::with_versions(&[4, 6, b'i', 0x06], [4, 6]..=[4, 8]).unwrap();
+        assert_eq!(deserializer.version(), [4, 6]);
+        assert_eq!(deserializer.deserialize_value::<i32>().unwrap(), 1);
+
+        let err = Deserializer::with_versions(&[4, 9, b'0'], [4, 6]..=[4, 8]).unwrap_err();
+        assert!(matches!(err.kind, Kind::VersionError([4, 9])));
     }
 
     #[test]
-    fn round_trip() {
-        let str = "round trip!!";
+    fn serializer_version() {
+        let mut serializer = Serializer::new();
+        serializer.set_version([4, 6]);
+        1.serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.output, [4, 6, b'i', 0x06]);
+
+        serializer.reset();
+        assert_eq!(serializer.output, [4, 6]);
+    }
+}
 
-        let bytes = crate::to_bytes(str).unwrap();
+#[cfg(all(test, feature = "async"))]
+mod async_reader {
+    use crate::Value;
 
-        let str2: &str = crate::from_bytes(&bytes).unwrap();
+    #[tokio::test]
+    async fn read() {
+        let bytes: &[u8] = &[0x04, 0x08, b'[', 0x07, 0x69, 0x06, 0x54];
+        let value: Value = crate::from_async_reader(bytes).await.unwrap();
 
-        assert_eq!(str, str2);
+        assert_eq!(
+            value,
+            Value::Array(vec![Value::Integer(1), Value::Bool(true)])
+        );
     }
 
+    #[tokio::test]
+    async fn eof() {
+        let bytes: &[u8] = &[0x04, 0x08, b'['];
+        let err = crate::from_async_reader::<Value, _>(bytes)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err.kind, crate::de::Kind::Eof));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod time_budget {
+    use std::time::Duration;
+
+    use crate::{de::Kind, Deserialize, Value};
+
     #[test]
-    fn weird_encoding() {
-        let bytes = &[
-            0x04, 0x08, 0x49, 0x22, 0x11, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65,
-            0x72, 0x65, 0x21, 0x06, 0x3a, 0x0d, 0x65, 0x6e, 0x63, 0x6f, 0x64, 0x69, 0x6e, 0x67,
-            0x22, 0x09, 0x42, 0x69, 0x67, 0x35,
-        ];
+    fn exceeded() {
+        let bytes = &[0x04, 0x08, b'[', 0x07, 0x69, 0x06, 0x69, 0x07];
 
-        let str: crate::Instance<crate::RbString> = crate::from_bytes(bytes).unwrap();
+        let mut deserializer = crate::Deserializer::new(bytes).unwrap();
+        deserializer.set_time_budget(Some(Duration::ZERO));
+        let err = Value::deserialize(&mut deserializer).unwrap_err();
 
-        assert_eq!(
-            str.encoding().unwrap().as_string().unwrap().data, // this is a mess lol, i should fix it
-            "Big5".as_bytes()
-        );
+        assert!(matches!(err.kind, Kind::TimeBudgetExceeded(2)));
     }
 
     #[test]
-    fn weird_encoding_round_trip() {
-        let bytes: &[_] = &[
-            0x04, 0x08, 0x49, 0x22, 0x11, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65,
-            0x72, 0x65, 0x21, 0x06, 0x3a, 0x0d, 0x65, 0x6e, 0x63, 0x6f, 0x64, 0x69, 0x6e, 0x67,
-            0x22, 0x09, 0x42, 0x69, 0x67, 0x35,
-        ];
-
-        let str: crate::Instance<crate::RbString> = crate::from_bytes(bytes).unwrap();
+    fn within_budget() {
+        let bytes = &[0x04, 0x08, b'[', 0x07, 0x69, 0x06, 0x69, 0x07];
 
-        let bytes2 = crate::to_bytes(&str).unwrap();
+        let mut deserializer = crate::Deserializer::new(bytes).unwrap();
+        deserializer.set_time_budget(Some(Duration::from_secs(60)));
+        let value = Value::deserialize(&mut deserializer).unwrap();
 
-        assert_eq!(bytes, bytes2);
+        assert_eq!(
+            value,
+            Value::Array(vec![Value::Integer(1), Value::Integer(2)])
+        );
     }
 }
 
-#[cfg(test)]
-mod floats {
-    #[test]
-    fn deserialize() {
-        let bytes = &[0x04, 0x08, 0x66, 0x07, 0x31, 0x35];
+#[cfg(all(test, feature = "value"))]
+mod progress {
+    use std::{cell::RefCell, ops::ControlFlow};
 
-        let float: f64 = crate::from_bytes(bytes).unwrap();
+    use crate::{
+        de::{DeOptions, Kind, Progress},
+        Deserialize, Value,
+    };
 
-        assert!((float - 15.0).abs() < f64::EPSILON);
-    }
+    const BYTES: &[u8] = &[0x04, 0x08, b'[', 0x08, 0x69, 0x06, 0x69, 0x07, 0x69, 0x08];
 
     #[test]
-    fn round_trip() {
-        let float = 20870.15;
-
-        let bytes = crate::to_bytes(float).unwrap();
+    fn reports_positions() {
+        let reports = RefCell::new(vec![]);
+        let report = |read, total| {
+            reports.borrow_mut().push((read, total));
+            ControlFlow::Continue(())
+        };
 
-        let float2: f64 = crate::from_bytes(&bytes).unwrap();
+        let mut deserializer = crate::Deserializer::new(BYTES).unwrap();
+        deserializer.set_progress(Some(Progress::new(&report).every(0)));
+        Value::deserialize(&mut deserializer).unwrap();
 
-        assert!((float - float2).abs() < f64::EPSILON);
+        assert_eq!(*reports.borrow(), [(2, 10), (4, 10), (6, 10), (8, 10)]);
     }
 
     #[test]
-    fn nan() {
-        let bytes = &[0x04, 0x08, 0x66, 0x08, 0x6e, 0x61, 0x6e];
+    fn interval() {
+        let reports = RefCell::new(vec![]);
+        let report = |read, _| {
+            reports.borrow_mut().push(read);
+            ControlFlow::Continue(())
+        };
 
-        let float: f64 = crate::from_bytes(bytes).unwrap();
+        let options = DeOptions::new().progress(Some(Progress::new(&report).every(3)));
+        let value: Vec<i32> = crate::from_bytes_with(BYTES, &options).unwrap();
 
-        assert!(float.is_nan());
+        assert_eq!(value, [1, 2, 3]);
+        assert_eq!(*reports.borrow(), [2, 6]);
     }
 
     #[test]
-    fn round_trip_nan() {
-        let float = f64::NAN;
-
-        let bytes = crate::to_bytes(float).unwrap();
+    fn cancelled() {
+        let report = |read, _| {
+            if read < 6 {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        };
 
-        let float2: f64 = crate::from_bytes(&bytes).unwrap();
+        let mut deserializer = crate::Deserializer::new(BYTES).unwrap();
+        deserializer.set_progress(Some(Progress::new(&report).every(0)));
+        deserializer.set_lenient(true);
+        let err = Value::deserialize(&mut deserializer).unwrap_err();
 
-        assert!(float.is_nan());
-        assert_eq!(
-            bytemuck::cast::<_, u64>(float),
-            bytemuck::cast::<_, u64>(float2)
-        );
+        assert!(matches!(err.kind, Kind::Cancelled(6)));
     }
 }
 
 #[cfg(test)]
-mod arrays {
+mod cancel_token {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use crate::de::{
+        seed::{FnSeed, SeqSeed},
+        DeOptions, DeserializeSeed, Kind,
+    };
+
+    const BYTES: &[u8] = &[0x04, 0x08, b'[', 0x07, 0x69, 0x06, 0x69, 0x07];
+
     #[test]
-    fn deserialize() {
-        let bytes = &[
-            0x04, 0x08, 0x5b, 0x0a, 0x69, 0x00, 0x69, 0x06, 0x69, 0x07, 0x69, 0x08, 0x69, 0x09,
-        ];
+    fn not_cancelled() {
+        let token = Arc::new(AtomicBool::new(false));
+        let options = DeOptions::new().cancel_token(Some(token));
+        let value: Vec<i32> = crate::from_bytes_with(BYTES, &options).unwrap();
 
-        let ary: Vec<u8> = crate::from_bytes(bytes).unwrap();
+        assert_eq!(value, [1, 2]);
+    }
 
-        assert_eq!(ary, vec![0, 1, 2, 3, 4]);
+    #[test]
+    fn cancelled_partway() {
+        let token = Arc::new(AtomicBool::new(false));
+        let options = DeOptions::new()
+            .cancel_token(Some(token.clone()))
+            .lenient(true);
+        let mut deserializer = options.deserializer(BYTES).unwrap();
+
+        // cancel after the first element
+        let cancel = FnSeed::new(|value: i32| {
+            token.store(true, Ordering::Relaxed);
+            Ok(value)
+        });
+        let err = SeqSeed::new(cancel)
+            .deserialize(&mut deserializer)
+            .unwrap_err();
+
+        assert!(matches!(err.kind, Kind::Cancelled(6)));
     }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod seeds {
+    use crate::{
+        de::{
+            seed::{FnSeed, MapSeed, SeqSeed},
+            DeserializeSeed,
+        },
+        DeError, Deserializer,
+    };
 
     #[test]
-    fn round_trip() {
-        let ary = vec!["hi!", "goodbye!", "pain"];
+    fn seq_seed() {
+        let table = [10, 20, 30];
+        let lookup = FnSeed::new(|i: usize| {
+            table
+                .get(i)
+                .copied()
+                .ok_or_else(|| DeError::custom("out of range"))
+        });
 
-        let bytes = crate::to_bytes(&ary).unwrap();
-        let ary2: Vec<&str> = crate::from_bytes(&bytes).unwrap();
+        // [1, 2]
+        let bytes = &[0x04, 0x08, b'[', 0x07, 0x69, 0x06, 0x69, 0x07];
+        let mut deserializer = Deserializer::new(bytes).unwrap();
+        let values = SeqSeed::new(lookup).deserialize(&mut deserializer).unwrap();
 
-        assert_eq!(ary, ary2);
+        assert_eq!(values, [20, 30]);
     }
-}
 
-mod structs {
     #[test]
-    fn deserialize_borrowed() {
-        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
-        #[marshal(alox_crate_path = "crate")]
-        struct Test<'d> {
-            field1: bool,
-            field2: &'d str,
-        }
+    fn map_seed() {
+        let offset = 5;
+        let key_seed = FnSeed::new(|k: i32| Ok(k + offset));
+        let value_seed = FnSeed::new(|v: bool| Ok(!v));
+
+        // {1 => true, 2 => false}
+        let bytes = &[0x04, 0x08, b'{', 0x07, 0x69, 0x06, b'T', 0x69, 0x07, b'F'];
+        let mut deserializer = Deserializer::new(bytes).unwrap();
+        let map = MapSeed::new(key_seed, value_seed)
+            .deserialize(&mut deserializer)
+            .unwrap();
+
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), [(6, false), (7, true)]);
+    }
 
-        let bytes = &[
-            0x04, 0x08, 0x6f, 0x3a, 0x09, 0x54, 0x65, 0x73, 0x74, 0x07, 0x3a, 0x0c, 0x40, 0x66,
-            0x69, 0x65, 0x6c, 0x64, 0x31, 0x54, 0x3a, 0x0c, 0x40, 0x66, 0x69, 0x65, 0x6c, 0x64,
-            0x32, 0x49, 0x22, 0x10, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65, 0x72,
-            0x65, 0x06, 0x3a, 0x06, 0x45, 0x54,
-        ];
+    #[test]
+    fn fn_seed_error() {
+        let table = [10];
+        let lookup = FnSeed::new(|i: usize| {
+            table
+                .get(i)
+                .copied()
+                .ok_or_else(|| DeError::custom("out of range"))
+        });
 
-        let obj: Test<'_> = crate::from_bytes(bytes).unwrap();
+        let bytes = &[0x04, 0x08, b'[', 0x06, 0x69, 0x07];
+        let mut deserializer = Deserializer::new(bytes).unwrap();
 
-        assert_eq!(
-            obj,
-            Test {
-                field1: true,
-                field2: "hello there"
-            }
-        );
+        assert!(SeqSeed::new(lookup).deserialize(&mut deserializer).is_err());
     }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod dump_errors {
+    use crate::{de::Kind, Value};
 
     #[test]
-    fn deserialize_multi_borrowed() {
-        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
-        #[marshal(alox_crate_path = "crate")]
-        struct Test<'a, 'b> {
-            field1: bool,
-            field2: &'a str,
-            #[marshal(byte_string)]
-            field3: &'b [u8],
-        }
+    fn undumpable_class() {
+        let mut bytes = vec![0x04, 0x08, b'o', b':', 12 + 5];
+        bytes.extend_from_slice(b"#<Class:0x1>");
+        bytes.push(0x00);
 
-        let initial = Test {
-            field1: true,
-            field2: "borrowed from the stack",
-            field3: b"also borrowed from the stack",
-        };
+        let err = crate::from_bytes::<Value>(&bytes).unwrap_err();
+        assert!(matches!(err.kind, Kind::UndumpableClass(c) if c == "#<Class:0x1>"));
+    }
 
-        let bytes = crate::to_bytes(&initial).unwrap();
-        let obj: Test<'_, '_> = crate::from_bytes(&bytes).unwrap();
+    #[test]
+    fn interrupted_dump() {
+        // [1, <interrupted>] followed by a new dump of `true`
+        let bytes = &[0x04, 0x08, b'[', 0x07, 0x69, 0x06, 0x04, 0x08, b'T'];
 
-        assert_eq!(obj, initial);
+        let err = crate::from_bytes::<Value>(bytes).unwrap_err();
+        assert!(matches!(err.kind, Kind::InterruptedDump(6)));
     }
+}
 
+#[cfg(all(test, feature = "value"))]
+mod ints {
     #[test]
-    fn deserialize_multi_bounds() {
-        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
-        #[marshal(alox_crate_path = "crate")]
-        struct Test<'a, 'b: 'a, 'c: 'a + 'b> {
-            field1: bool,
-            field2: &'a str,
-            #[marshal(byte_string)]
-            field3: &'b [u8],
-            field4: &'c str,
+    fn deserialize() {
+        let bytes = &[0x04, 0x08, 0x69, 0x19];
+
+        let int: u8 = crate::from_bytes(bytes).unwrap();
+
+        assert_eq!(int, 20);
+    }
+
+    #[test]
+    fn round_trip() {
+        let int = 123;
+
+        let bytes = crate::to_bytes(int).unwrap();
+
+        let int2: i32 = crate::from_bytes(&bytes).unwrap();
+
+        assert_eq!(int, int2);
+    }
+
+    #[test]
+    fn round_trip_value() {
+        let value = crate::Value::Integer(123);
+
+        let bytes = crate::to_bytes(&value).unwrap();
+
+        let value2: crate::Value = crate::from_bytes(&bytes).unwrap();
+
+        assert_eq!(value, value2);
+    }
+
+    #[test]
+    fn negatives() {
+        let bytes = &[0x04, 0x08, 0x69, 0xfd, 0x1d, 0xf0, 0xfc];
+
+        let int: i32 = crate::from_bytes(bytes).unwrap();
+
+        assert_eq!(int, -200_675);
+    }
+
+    #[test]
+    fn byte_width_boundaries() {
+        for int in [
+            0,
+            -123,
+            122,
+            -124,
+            123,
+            255,
+            256,
+            -256,
+            -257,
+            0xFFFF,
+            0x1_0000,
+            -0x1_0000,
+            -0x1_0001,
+            0xFF_FFFF,
+            0x100_0000,
+            -0x100_0000,
+            -0x100_0001,
+            i32::MAX,
+            i32::MIN,
+        ] {
+            let bytes = crate::to_bytes(int).unwrap();
+            assert_eq!(crate::from_bytes::<i32>(&bytes).unwrap(), int, "{bytes:x?}");
+        }
+        assert_eq!(
+            crate::to_bytes((1 << 30) - 1).unwrap(),
+            [0x04, 0x08, 0x69, 0x04, 0xff, 0xff, 0xff, 0x3f]
+        );
+        assert_eq!(
+            crate::to_bytes(-(1 << 30)).unwrap(),
+            [0x04, 0x08, 0x69, 0xfc, 0x00, 0x00, 0x00, 0xc0]
+        );
+    }
+
+    #[test]
+    fn out_of_range() {
+        use crate::de::Kind;
+
+        // 2**32 - 1, which used to wrap around to -1
+        let err = crate::from_bytes::<i32>(&[0x04, 0x08, 0x69, 0x04, 0xff, 0xff, 0xff, 0xff])
+            .unwrap_err();
+        assert!(matches!(err.kind, Kind::PackedIntOutOfRange(0xFFFF_FFFF)));
+
+        // -(2**32), which used to wrap around to 0
+        let err = crate::from_bytes::<i32>(&[0x04, 0x08, 0x69, 0xfc, 0x00, 0x00, 0x00, 0x00])
+            .unwrap_err();
+        assert!(matches!(
+            err.kind,
+            Kind::PackedIntOutOfRange(-0x1_0000_0000)
+        ));
+
+        // lengths are packed integers too
+        let err =
+            crate::from_bytes::<crate::Value>(&[0x04, 0x08, 0x5b, 0x04, 0x00, 0x00, 0x00, 0x80])
+                .unwrap_err();
+        assert!(matches!(err.kind, Kind::PackedIntOutOfRange(0x8000_0000)));
+    }
+
+    #[test]
+    fn truncated() {
+        for len in 3..8 {
+            let bytes = &[0x04, 0x08, 0x69, 0x04, 0xff, 0xff, 0xff, 0x7f][..len];
+            let err = crate::from_bytes::<i32>(bytes).unwrap_err();
+            assert!(matches!(err.kind, crate::de::Kind::Eof), "{len}");
         }
+    }
+}
 
-        let initial = Test {
-            field1: true,
-            field2: "borrowed from the stack",
-            field3: b"also borrowed from the stack",
-            field4: "multiple bounds",
-        };
+#[cfg(all(test, feature = "value"))]
+mod bignums {
+    use crate::{de::Kind, from_bytes, to_bytes, Value};
 
-        let bytes = crate::to_bytes(&initial).unwrap();
-        let obj: Test<'_, '_, '_> = crate::from_bytes(&bytes).unwrap();
+    // Marshal.dump(2**31), Marshal.dump(-(2**31) - 1), Marshal.dump(2**40),
+    // Marshal.dump(2**63 - 1), and Marshal.dump(-(2**63))
+    const FIXTURES: [(i64, &[u8]); 5] = [
+        (0x8000_0000, b"\x04\x08l+\x07\x00\x00\x00\x80"),
+        (-0x8000_0001, b"\x04\x08l-\x07\x01\x00\x00\x80"),
+        (1 << 40, b"\x04\x08l+\x08\x00\x00\x00\x00\x00\x01"),
+        (i64::MAX, b"\x04\x08l+\x09\xff\xff\xff\xff\xff\xff\xff\x7f"),
+        (i64::MIN, b"\x04\x08l-\x09\x00\x00\x00\x00\x00\x00\x00\x80"),
+    ];
 
-        assert_eq!(obj, initial);
+    #[test]
+    fn deserialize() {
+        for (int, bytes) in FIXTURES {
+            assert_eq!(from_bytes::<i64>(bytes).unwrap(), int);
+            assert_eq!(from_bytes::<Value>(bytes).unwrap(), Value::Integer(int));
+        }
     }
 
     #[test]
-    fn userdata() {
-        #[derive(alox_48_derive::Deserialize, Debug, PartialEq, Eq)]
-        #[marshal(alox_crate_path = "crate")]
-        #[marshal(from = "crate::Userdata")]
-        struct MyUserData {
-            field: [char; 4],
+    fn serialize() {
+        for (int, bytes) in FIXTURES {
+            assert_eq!(to_bytes(int).unwrap(), bytes);
+            assert_eq!(to_bytes(Value::Integer(int)).unwrap(), bytes);
         }
+        assert_eq!(to_bytes(1u64 << 40).unwrap(), FIXTURES[2].1);
+    }
 
-        impl From<crate::Userdata> for MyUserData {
-            fn from(value: crate::Userdata) -> Self {
-                assert_eq!(value.class, "MyUserData");
-                let field = std::array::from_fn(|i| value.data[i] as char);
-                Self { field }
-            }
+    #[test]
+    fn fixnums_stay_fixnums() {
+        for int in [0, 1, -1, (1 << 30) - 1, -(1 << 30)] {
+            let bytes = to_bytes(int).unwrap();
+            assert_eq!(bytes[2], b'i');
+            assert_eq!(to_bytes(i64::from(int)).unwrap(), bytes);
+            assert_eq!(to_bytes(Value::from(int)).unwrap(), bytes);
         }
-        let bytes = &[
-            0x04, 0x08, 0x75, 0x3a, 0x0f, 0x4d, 0x79, 0x55, 0x73, 0x65, 0x72, 0x44, 0x61, 0x74,
-            0x61, 0x09, 0x61, 0x62, 0x63, 0x64,
+    }
+
+    #[test]
+    fn fixnums_fit_in_31_bits() {
+        // Marshal.dump(2**30), Marshal.dump(-(2**30) - 1), and Marshal.dump(2**31 - 1)
+        let fixtures: [(i32, &[u8]); 3] = [
+            (1 << 30, b"\x04\x08l+\x07\x00\x00\x00\x40"),
+            (-(1 << 30) - 1, b"\x04\x08l-\x07\x01\x00\x00\x40"),
+            (i32::MAX, b"\x04\x08l+\x07\xff\xff\xff\x7f"),
         ];
-        let data: MyUserData = crate::from_bytes(bytes).unwrap();
+        for (int, bytes) in fixtures {
+            assert_eq!(to_bytes(int).unwrap(), bytes);
+            assert_eq!(to_bytes(i64::from(int)).unwrap(), bytes);
+            assert_eq!(from_bytes::<i32>(bytes).unwrap(), int);
+        }
 
+        // Marshal.dump(2**30 - 1)
         assert_eq!(
-            data,
-            MyUserData {
-                field: ['a', 'b', 'c', 'd']
-            }
+            to_bytes((1 << 30) - 1).unwrap(),
+            b"\x04\x08i\x04\xff\xff\xff\x3f"
         );
     }
-}
 
-#[cfg(test)]
-mod misc {
     #[test]
-    fn symbol() {
-        let sym = crate::Symbol::from("symbol");
+    fn narrow_integers() {
+        let err = from_bytes::<i32>(FIXTURES[0].1).unwrap_err();
+        assert!(matches!(err.kind, Kind::InvalidValue(_)), "{err}");
+        let err = from_bytes::<u64>(FIXTURES[4].1).unwrap_err();
+        assert!(matches!(err.kind, Kind::InvalidValue(_)), "{err}");
+
+        let err = to_bytes(u64::MAX).unwrap_err();
+        assert!(matches!(err.kind, crate::ser::Kind::IntegerOutOfRange));
+    }
 
-        let bytes = crate::to_bytes(&sym).unwrap();
+    #[test]
+    fn out_of_range() {
+        // Marshal.dump(2**63), Marshal.dump(2**64), and Marshal.dump(-(2**63) - 1)
+        for bytes in [
+            &b"\x04\x08l+\x09\x00\x00\x00\x00\x00\x00\x00\x80"[..],
+            b"\x04\x08l+\x0a\x00\x00\x00\x00\x00\x00\x00\x00\x01\x00",
+            b"\x04\x08l-\x09\x01\x00\x00\x00\x00\x00\x00\x80",
+        ] {
+            let err = from_bytes::<Value>(bytes).unwrap_err();
+            assert!(matches!(err.kind, Kind::BignumOutOfRange), "{err}");
+        }
 
-        let sym2: crate::Symbol = crate::from_bytes(&bytes).unwrap();
+        // zero padding doesn't make a bignum larger
+        let padded = b"\x04\x08l+\x0a\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+        assert_eq!(from_bytes::<i64>(padded).unwrap(), 1);
+    }
 
-        assert_eq!(sym, sym2);
+    #[test]
+    fn object_links() {
+        // a = 2**40; Marshal.dump([a, a, "hi"]), with the string's encoding left out
+        let bytes = b"\x04\x08[\x08l+\x08\x00\x00\x00\x00\x00\x01@\x06\"\x07hi";
+        let (a, b, string): (i64, i64, String) = from_bytes(bytes).unwrap();
+        assert_eq!((a, b), (1 << 40, 1 << 40));
+        assert_eq!(string, "hi");
     }
+}
 
-    // Testing for zero copy symlink deserialization
-    // ALL symbols should be the same reference
+#[cfg(all(test, feature = "value"))]
+mod strings {
     #[test]
-    fn symlink() {
+    fn deserialize() {
         let bytes = &[
-            0x04, 0x08, 0x5b, 0x0a, 0x3a, 0x09, 0x74, 0x65, 0x73, 0x74, 0x3b, 0x00, 0x3b, 0x00,
-            0x3b, 0x00, 0x3b, 0x00,
+            0x04, 0x08, 0x49, 0x22, 0x11, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65,
+            0x72, 0x65, 0x21, 0x06, 0x3a, 0x06, 0x45, 0x54,
         ];
 
-        let symbols: Vec<&str> = crate::from_bytes(bytes).unwrap();
+        let str: &str = crate::from_bytes(bytes).unwrap();
 
-        for sym in symbols.windows(2) {
-            assert_eq!(sym[0].as_ptr(), sym[1].as_ptr());
-        }
+        assert_eq!(str, "hello there!");
     }
-}
 
-#[cfg(test)]
-mod value_test {
     #[test]
-    fn untyped_object() {
-        let bytes = &[
-            0x04, 0x08, 0x6f, 0x3a, 0x09, 0x54, 0x65, 0x73, 0x74, 0x07, 0x3a, 0x0c, 0x40, 0x66,
-            0x69, 0x65, 0x6c, 0x64, 0x31, 0x54, 0x3a, 0x0c, 0x40, 0x66, 0x69, 0x65, 0x6c, 0x64,
-            0x32, 0x49, 0x22, 0x10, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65, 0x72,
-            0x65, 0x06, 0x3a, 0x06, 0x45, 0x54,
-        ];
+    fn round_trip() {
+        let str = "round trip!!";
 
-        let obj: crate::Value = crate::from_bytes(bytes).unwrap();
-        let obj = obj.into_object().unwrap();
+        let bytes = crate::to_bytes(str).unwrap();
 
-        assert_eq!(obj.class, "Test");
-        assert_eq!(obj.fields["@field1"], true);
+        let str2: &str = crate::from_bytes(&bytes).unwrap();
+
+        assert_eq!(str, str2);
     }
 
     #[test]
-    fn untyped_ivar_string() {
+    fn weird_encoding() {
         let bytes = &[
-            0x04, 0x08, 0x49, 0x22, 0x0b, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x21, 0x07, 0x3a, 0x06,
-            0x45, 0x54, 0x3a, 0x0c, 0x40, 0x72, 0x61, 0x6e, 0x64, 0x6f, 0x6d, 0x69, 0x01, 0x7b,
+            0x04, 0x08, 0x49, 0x22, 0x11, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65,
+            0x72, 0x65, 0x21, 0x06, 0x3a, 0x0d, 0x65, 0x6e, 0x63, 0x6f, 0x64, 0x69, 0x6e, 0x67,
+            0x22, 0x09, 0x42, 0x69, 0x67, 0x35,
         ];
 
-        let obj: crate::Value = crate::from_bytes(bytes).unwrap();
-        let instance = obj.into_instance().unwrap();
+        let str: crate::Instance<crate::RbString> = crate::from_bytes(bytes).unwrap();
 
-        assert_eq!(instance.value.as_ref(), "hello!");
-        assert_eq!(instance.fields["@random"], 123);
+        assert_eq!(
+            str.get_ivar::<String>("encoding"),
+            Some(Ok("Big5".to_owned()))
+        );
+        assert_eq!(str.to_string_lossy(), "hello there!");
     }
 
     #[test]
-    fn untyped_ivar_array() {
-        let bytes = &[
-            0x04, 0x08, 0x49, 0x5b, 0x07, 0x49, 0x22, 0x09, 0x74, 0x65, 0x73, 0x74, 0x06, 0x3a,
-            0x06, 0x45, 0x54, 0x69, 0x01, 0x7b, 0x06, 0x3a, 0x0a, 0x40, 0x69, 0x76, 0x61, 0x72,
-            0x66, 0x06, 0x35,
+    fn weird_encoding_round_trip() {
+        let bytes: &[_] = &[
+            0x04, 0x08, 0x49, 0x22, 0x11, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65,
+            0x72, 0x65, 0x21, 0x06, 0x3a, 0x0d, 0x65, 0x6e, 0x63, 0x6f, 0x64, 0x69, 0x6e, 0x67,
+            0x22, 0x09, 0x42, 0x69, 0x67, 0x35,
         ];
 
-        let obj: crate::Value = crate::from_bytes(bytes).unwrap();
-        let instance = obj.into_instance().unwrap();
+        let str: crate::Instance<crate::RbString> = crate::from_bytes(bytes).unwrap();
 
-        let array = instance.value.as_array().unwrap();
-        assert_eq!(&array[0], "test");
-        assert_eq!(array[1], 123);
-        assert_eq!(instance.fields["@ivar"], 5.0);
+        let bytes2 = crate::to_bytes(&str).unwrap();
+
+        assert_eq!(bytes, bytes2);
     }
+}
 
+#[cfg(test)]
+mod floats {
     #[test]
+    fn deserialize() {
+        let bytes = &[0x04, 0x08, 0x66, 0x07, 0x31, 0x35];
 
-    fn untyped_to_borrowed() {
-        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
-        #[marshal(alox_crate_path = "crate")]
-        struct Test<'d> {
-            field1: bool,
-            field2: &'d str,
-        }
+        let float: f64 = crate::from_bytes(bytes).unwrap();
 
-        let bytes = &[
-            0x04, 0x08, 0x6f, 0x3a, 0x09, 0x54, 0x65, 0x73, 0x74, 0x07, 0x3a, 0x0c, 0x40, 0x66,
-            0x69, 0x65, 0x6c, 0x64, 0x31, 0x54, 0x3a, 0x0c, 0x40, 0x66, 0x69, 0x65, 0x6c, 0x64,
-            0x32, 0x49, 0x22, 0x10, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65, 0x72,
-            0x65, 0x06, 0x3a, 0x06, 0x45, 0x54,
-        ];
+        assert!((float - 15.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn round_trip() {
+        let float = 20870.15;
+
+        let bytes = crate::to_bytes(float).unwrap();
+
+        let float2: f64 = crate::from_bytes(&bytes).unwrap();
+
+        assert!((float - float2).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn nan() {
+        let bytes = &[0x04, 0x08, 0x66, 0x08, 0x6e, 0x61, 0x6e];
+
+        let float: f64 = crate::from_bytes(bytes).unwrap();
+
+        assert!(float.is_nan());
+    }
+
+    #[test]
+    fn round_trip_nan() {
+        let float = f64::NAN;
+
+        let bytes = crate::to_bytes(float).unwrap();
+
+        let float2: f64 = crate::from_bytes(&bytes).unwrap();
+
+        assert!(float.is_nan());
+        assert_eq!(
+            bytemuck::cast::<_, u64>(float),
+            bytemuck::cast::<_, u64>(float2)
+        );
+    }
+
+    #[test]
+    fn special_values() {
+        for (text, expected) in [
+            (&b"inf"[..], f64::INFINITY),
+            (b"-inf", f64::NEG_INFINITY),
+            (b"-0", -0.0),
+            (b"1.0e+20", 1e20),
+            (b"2.5e-05", 2.5e-5),
+        ] {
+            let mut bytes = vec![4, 8, b'f', text.len() as u8 + 5];
+            bytes.extend_from_slice(text);
+
+            let float: f64 = crate::from_bytes(&bytes).unwrap();
+            assert_eq!(float.to_bits(), expected.to_bits());
+        }
+    }
+
+    #[test]
+    fn mantissa() {
+        // older rubies wrote extra mantissa bytes after a nul terminator
+        let bytes = b"\x04\x08f\x0b0.1\x00\x9a\x99";
+        let float: f64 = crate::from_bytes(bytes).unwrap();
+        assert_eq!(float.to_bits(), (0.1f64.to_bits() & !0xFFFF) | 0x9a99);
+    }
+
+    #[test]
+    fn invalid() {
+        for text in [&b"1.5x"[..], b"\xFF", b""] {
+            let mut bytes = vec![4, 8, b'f', text.len() as u8 + 5];
+            bytes.extend_from_slice(text);
+
+            let err = crate::from_bytes::<f64>(&bytes).unwrap_err();
+            assert!(matches!(err.kind, crate::de::Kind::ParseFloat(ref e) if e.bytes == text));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod arrays {
+    #[test]
+    fn deserialize() {
+        let bytes = &[
+            0x04, 0x08, 0x5b, 0x0a, 0x69, 0x00, 0x69, 0x06, 0x69, 0x07, 0x69, 0x08, 0x69, 0x09,
+        ];
+
+        let ary: Vec<u8> = crate::from_bytes(bytes).unwrap();
+
+        assert_eq!(ary, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let ary = vec!["hi!", "goodbye!", "pain"];
+
+        let bytes = crate::to_bytes(&ary).unwrap();
+        let ary2: Vec<&str> = crate::from_bytes(&bytes).unwrap();
+
+        assert_eq!(ary, ary2);
+    }
+}
+
+mod structs {
+    #[test]
+    fn deserialize_borrowed() {
+        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
+        #[marshal(alox_crate_path = "crate")]
+        struct Test<'d> {
+            field1: bool,
+            field2: &'d str,
+        }
+
+        let bytes = &[
+            0x04, 0x08, 0x6f, 0x3a, 0x09, 0x54, 0x65, 0x73, 0x74, 0x07, 0x3a, 0x0c, 0x40, 0x66,
+            0x69, 0x65, 0x6c, 0x64, 0x31, 0x54, 0x3a, 0x0c, 0x40, 0x66, 0x69, 0x65, 0x6c, 0x64,
+            0x32, 0x49, 0x22, 0x10, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65, 0x72,
+            0x65, 0x06, 0x3a, 0x06, 0x45, 0x54,
+        ];
+
+        let obj: Test<'_> = crate::from_bytes(bytes).unwrap();
+
+        assert_eq!(
+            obj,
+            Test {
+                field1: true,
+                field2: "hello there"
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_multi_borrowed() {
+        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
+        #[marshal(alox_crate_path = "crate")]
+        struct Test<'a, 'b> {
+            field1: bool,
+            field2: &'a str,
+            #[marshal(byte_string)]
+            field3: &'b [u8],
+        }
+
+        let initial = Test {
+            field1: true,
+            field2: "borrowed from the stack",
+            field3: b"also borrowed from the stack",
+        };
+
+        let bytes = crate::to_bytes(&initial).unwrap();
+        let obj: Test<'_, '_> = crate::from_bytes(&bytes).unwrap();
+
+        assert_eq!(obj, initial);
+    }
+
+    #[test]
+    #[cfg(feature = "value")]
+    fn byte_string_containers() {
+        use std::collections::{BTreeMap, HashMap};
+
+        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
+        #[marshal(alox_crate_path = "crate")]
+        struct Test<'a> {
+            #[marshal(byte_string)]
+            list: Vec<&'a [u8]>,
+            #[marshal(byte_string)]
+            maybe: Option<Vec<u8>>,
+            map: HashMap<String, crate::SerializeByteString<'a>>,
+            #[marshal(byte_string)]
+            hash_map: HashMap<String, Vec<u8>>,
+            #[marshal(byte_string)]
+            btree_map: BTreeMap<i32, Option<Vec<u8>>>,
+            #[marshal(byte_string)]
+            index_map: indexmap::IndexMap<crate::Symbol, &'a [u8]>,
+        }
+
+        let initial = Test {
+            list: vec![b"one", b"two"],
+            maybe: Some(b"three".to_vec()),
+            map: HashMap::from([("four".to_string(), b"four".as_slice().into())]),
+            hash_map: HashMap::from([("five".to_string(), b"five".to_vec())]),
+            btree_map: BTreeMap::from([(6, Some(b"six".to_vec())), (7, None)]),
+            index_map: indexmap::IndexMap::from([("eight".into(), b"eight".as_slice())]),
+        };
+
+        let bytes = crate::to_bytes(&initial).unwrap();
+        let value: crate::Value = crate::from_bytes(&bytes).unwrap();
+        let object = value.into_object().unwrap();
+        assert!(object.fields["@list"].as_array().unwrap()[0].is_string());
+        assert!(object.fields["@maybe"].is_string());
+        for field in ["@map", "@hash_map", "@index_map"] {
+            assert!(object.fields[field]
+                .as_hash()
+                .unwrap()
+                .values()
+                .all(crate::Value::is_string));
+        }
+        assert!(
+            object.fields["@btree_map"].as_hash().unwrap()[&crate::Value::Integer(6)].is_string()
+        );
+
+        let obj: Test<'_> = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(obj, initial);
+    }
+
+    #[test]
+    #[cfg(feature = "value")]
+    fn byte_string_nested_wrappers() {
+        use crate::{Extended, Instance, RbFields};
+
+        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
+        #[marshal(alox_crate_path = "crate")]
+        struct Test {
+            #[marshal(byte_string)]
+            data: Vec<u8>,
+            #[marshal(byte_string)]
+            name: Option<Instance<Vec<u8>>>,
+            #[marshal(byte_string)]
+            missing: Option<Instance<Vec<u8>>>,
+            #[marshal(byte_string)]
+            list: Instance<Vec<Option<Box<[u8]>>>>,
+            #[marshal(byte_string)]
+            tagged: Instance<Extended<Vec<u8>>>,
+        }
+
+        let encoding: RbFields = [("E".into(), true.into())].into_iter().collect();
+        let initial = Test {
+            data: b"\xFF".to_vec(),
+            name: Some(Instance {
+                value: b"Aluxes".to_vec(),
+                fields: encoding.clone(),
+            }),
+            missing: None,
+            list: Instance::new(vec![Some(b"one".as_slice().into()), None]),
+            tagged: Instance {
+                value: Extended {
+                    module: "Foo".into(),
+                    value: b"hi".to_vec(),
+                },
+                fields: encoding,
+            },
+        };
+
+        let bytes = crate::to_bytes(&initial).unwrap();
+        let value: crate::Value = crate::from_bytes(&bytes).unwrap();
+        let object = value.into_object().unwrap();
+        assert!(object.fields["@data"].is_string());
+        assert!(object.fields["@name"]
+            .as_instance()
+            .unwrap()
+            .value
+            .is_string());
+        assert!(object.fields["@missing"].is_nil());
+        assert!(object.fields["@list"].as_array().unwrap()[0].is_string());
+        let tagged = object.fields["@tagged"].as_instance().unwrap();
+        assert!(tagged.value.is_extended());
+
+        let obj: Test = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(obj, initial);
+
+        // the order ruby writes an extended string with an encoding in
+        let ruby = b"\x04\x08o:\tTest\n:\n@data\"\x06\xFF:\n@nameI\"\x0bAluxes\x06:\x06ET:\r@missing0:\n@list[\x07\"\x08one0:\x0c@taggedIe:\x08Foo\"\x07hi\x06;\x08T";
+        let obj: Test = crate::from_bytes(ruby).unwrap();
+        assert_eq!(obj, initial);
+    }
+
+    #[test]
+    fn deserialize_multi_bounds() {
+        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
+        #[marshal(alox_crate_path = "crate")]
+        struct Test<'a, 'b: 'a, 'c: 'a + 'b> {
+            field1: bool,
+            field2: &'a str,
+            #[marshal(byte_string)]
+            field3: &'b [u8],
+            field4: &'c str,
+        }
+
+        let initial = Test {
+            field1: true,
+            field2: "borrowed from the stack",
+            field3: b"also borrowed from the stack",
+            field4: "multiple bounds",
+        };
+
+        let bytes = crate::to_bytes(&initial).unwrap();
+        let obj: Test<'_, '_, '_> = crate::from_bytes(&bytes).unwrap();
+
+        assert_eq!(obj, initial);
+    }
+
+    #[test]
+    fn userdata() {
+        #[derive(alox_48_derive::Deserialize, Debug, PartialEq, Eq)]
+        #[marshal(alox_crate_path = "crate")]
+        #[marshal(from = "crate::Userdata")]
+        struct MyUserData {
+            field: [char; 4],
+        }
+
+        impl From<crate::Userdata> for MyUserData {
+            fn from(value: crate::Userdata) -> Self {
+                assert_eq!(value.class, "MyUserData");
+                let field = std::array::from_fn(|i| value.data[i] as char);
+                Self { field }
+            }
+        }
+        let bytes = &[
+            0x04, 0x08, 0x75, 0x3a, 0x0f, 0x4d, 0x79, 0x55, 0x73, 0x65, 0x72, 0x44, 0x61, 0x74,
+            0x61, 0x09, 0x61, 0x62, 0x63, 0x64,
+        ];
+        let data: MyUserData = crate::from_bytes(bytes).unwrap();
+
+        assert_eq!(
+            data,
+            MyUserData {
+                field: ['a', 'b', 'c', 'd']
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "value")]
+    fn structured_errors() {
+        use crate::de::Kind;
+
+        #[derive(alox_48_derive::Deserialize, Debug)]
+        #[marshal(alox_crate_path = "crate")]
+        #[marshal(deny_unknown_fields, enforce_class)]
+        #[allow(dead_code)]
+        struct Test {
+            field1: bool,
+        }
+
+        let value = crate::Value::Object(crate::Object {
+            class: "Test".into(),
+            fields: crate::RbFields::new(),
+        });
+        let err = crate::from_value::<Test>(&value).unwrap_err();
+        assert!(matches!(
+            &err.kind,
+            Kind::MissingField(e) if e.class.as_ref().is_some_and(|c| c == "Test") && e.field == "field1"
+        ));
+
+        let mut fields = crate::RbFields::new();
+        fields.insert("@field2".into(), true.into());
+        let value = crate::Value::Object(crate::Object {
+            class: "Test".into(),
+            fields,
+        });
+        let err = crate::from_value::<Test>(&value).unwrap_err();
+        assert!(matches!(
+            &err.kind,
+            Kind::UnknownField(e) if e.field == "field2" && e.expected == ["field1"]
+        ));
+
+        let value = crate::Value::Object(crate::Object {
+            class: "Other".into(),
+            fields: crate::RbFields::new(),
+        });
+        let err = crate::from_value::<Test>(&value).unwrap_err();
+        assert!(matches!(
+            &err.kind,
+            Kind::WrongClass(e) if e.expected == "Test" && e.found == "Other"
+        ));
+    }
+
+    #[test]
+    fn expecting_placeholders() {
+        #[derive(alox_48_derive::Deserialize, PartialEq, Debug)]
+        #[marshal(alox_crate_path = "crate")]
+        #[marshal(class = "RPG::Test", expecting = "a {class} with {fields} {{ok}}")]
+        struct Test {
+            field1: bool,
+            #[marshal(rename = "renamed")]
+            field2: bool,
+            #[marshal(skip)]
+            _field3: bool,
+        }
+
+        let bytes = &[0x04, 0x08, 0x69, 0x06];
+        let err = crate::from_bytes::<Test>(bytes).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .ends_with("expected `a RPG::Test with `field1`, `renamed` {ok}`"));
+    }
+
+    #[test]
+    fn expecting_class_from() {
+        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, Debug)]
+        #[marshal(alox_crate_path = "crate", class_from = "Self::class_name")]
+        #[marshal(expecting = "an object ({class}) with {fields}")]
+        struct Script {
+            #[marshal(skip)]
+            class: String,
+            name: String,
+        }
+
+        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, Debug)]
+        #[marshal(alox_crate_path = "crate", class_from = "Self::class_name")]
+        struct Event {
+            #[marshal(skip)]
+            class: String,
+        }
+
+        impl Script {
+            fn class_name(&self) -> &str {
+                &self.class
+            }
+        }
+
+        impl Event {
+            fn class_name(&self) -> &str {
+                &self.class
+            }
+        }
+
+        let bytes = &[0x04, 0x08, 0x69, 0x06];
+        let err = crate::from_bytes::<Script>(bytes).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("expected `an object (any class) with `name``"),
+            "{err}"
+        );
+
+        let err = crate::from_bytes::<Event>(bytes).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("expected `an instance of any class`"),
+            "{err}"
+        );
+
+        // user data of class `Foo`
+        let bytes = b"\x04\x08u:\x08Foo\x06\x00";
+        let err = crate::from_bytes::<Script>(bytes).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("expected `an object (Foo) with `name``"),
+            "{err}"
+        );
+
+        let err = crate::from_bytes::<Event>(bytes).unwrap_err();
+        assert!(
+            err.to_string().ends_with("expected `an instance of Foo`"),
+            "{err}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod misc {
+    #[test]
+    fn symbol() {
+        let sym = crate::Symbol::from("symbol");
+
+        let bytes = crate::to_bytes(&sym).unwrap();
+
+        let sym2: crate::Symbol = crate::from_bytes(&bytes).unwrap();
+
+        assert_eq!(sym, sym2);
+    }
+
+    // Testing for zero copy symlink deserialization
+    // ALL symbols should be the same reference
+    #[test]
+    fn symlink() {
+        let bytes = &[
+            0x04, 0x08, 0x5b, 0x0a, 0x3a, 0x09, 0x74, 0x65, 0x73, 0x74, 0x3b, 0x00, 0x3b, 0x00,
+            0x3b, 0x00, 0x3b, 0x00,
+        ];
+
+        let symbols: Vec<&str> = crate::from_bytes(bytes).unwrap();
+
+        for sym in symbols.windows(2) {
+            assert_eq!(sym[0].as_ptr(), sym[1].as_ptr());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod value_test {
+    #[test]
+    fn untyped_object() {
+        let bytes = &[
+            0x04, 0x08, 0x6f, 0x3a, 0x09, 0x54, 0x65, 0x73, 0x74, 0x07, 0x3a, 0x0c, 0x40, 0x66,
+            0x69, 0x65, 0x6c, 0x64, 0x31, 0x54, 0x3a, 0x0c, 0x40, 0x66, 0x69, 0x65, 0x6c, 0x64,
+            0x32, 0x49, 0x22, 0x10, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65, 0x72,
+            0x65, 0x06, 0x3a, 0x06, 0x45, 0x54,
+        ];
+
+        let obj: crate::Value = crate::from_bytes(bytes).unwrap();
+        let obj = obj.into_object().unwrap();
+
+        assert_eq!(obj.class, "Test");
+        assert_eq!(obj.fields["@field1"], true);
+    }
+
+    #[test]
+    fn untyped_ivar_string() {
+        let bytes = &[
+            0x04, 0x08, 0x49, 0x22, 0x0b, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x21, 0x07, 0x3a, 0x06,
+            0x45, 0x54, 0x3a, 0x0c, 0x40, 0x72, 0x61, 0x6e, 0x64, 0x6f, 0x6d, 0x69, 0x01, 0x7b,
+        ];
+
+        let obj: crate::Value = crate::from_bytes(bytes).unwrap();
+        let instance = obj.into_instance().unwrap();
+
+        assert_eq!(instance.value.as_ref(), "hello!");
+        assert_eq!(instance.fields["@random"], 123);
+    }
+
+    #[test]
+    fn untyped_ivar_array() {
+        let bytes = &[
+            0x04, 0x08, 0x49, 0x5b, 0x07, 0x49, 0x22, 0x09, 0x74, 0x65, 0x73, 0x74, 0x06, 0x3a,
+            0x06, 0x45, 0x54, 0x69, 0x01, 0x7b, 0x06, 0x3a, 0x0a, 0x40, 0x69, 0x76, 0x61, 0x72,
+            0x66, 0x06, 0x35,
+        ];
+
+        let obj: crate::Value = crate::from_bytes(bytes).unwrap();
+        let instance = obj.into_instance().unwrap();
+
+        let array = instance.value.as_array().unwrap();
+        assert_eq!(&array[0], "test");
+        assert_eq!(array[1], 123);
+        assert_eq!(instance.fields["@ivar"], 5.0);
+    }
+
+    #[test]
+
+    fn untyped_to_borrowed() {
+        #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
+        #[marshal(alox_crate_path = "crate")]
+        struct Test<'d> {
+            field1: bool,
+            field2: &'d str,
+        }
+
+        let bytes = &[
+            0x04, 0x08, 0x6f, 0x3a, 0x09, 0x54, 0x65, 0x73, 0x74, 0x07, 0x3a, 0x0c, 0x40, 0x66,
+            0x69, 0x65, 0x6c, 0x64, 0x31, 0x54, 0x3a, 0x0c, 0x40, 0x66, 0x69, 0x65, 0x6c, 0x64,
+            0x32, 0x49, 0x22, 0x10, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x74, 0x68, 0x65, 0x72,
+            0x65, 0x06, 0x3a, 0x06, 0x45, 0x54,
+        ];
+
+        let obj: crate::Value = crate::from_bytes(bytes).unwrap();
+
+        let test: Test<'_> = crate::Deserialize::deserialize(&obj).unwrap();
+
+        assert_eq!(
+            test,
+            Test {
+                field1: true,
+                field2: "hello there"
+            }
+        );
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod round_trip {
+    use crate::{from_bytes, to_bytes, Instance, RbFields, RbHash, RbStruct, Value};
+
+    #[test]
+    fn nil() {
+        let original = Value::Nil;
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn bool() {
+        let original = Value::Bool(true);
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn float() {
+        let original = Value::Float(123.456);
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn integer() {
+        let original = Value::Integer(123);
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn string() {
+        let original = Value::String("round trip".into());
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn symbol() {
+        let original = Value::Symbol("round_trip".into());
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn array() {
+        let original = Value::Array(vec![Value::Integer(1), Value::Float(256.652)]);
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn hash() {
+        let mut hash = RbHash::new();
+        hash.insert(Value::Bool(true), Value::Integer(1));
+        hash.insert(Value::Symbol("a_symbol".into()), Value::Float(256.652));
+        let original = Value::Hash(hash);
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn userdata() {
+        let original = Value::Userdata(crate::Userdata::new("TestUserdata", vec![97, 98, 99, 100]));
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn object() {
+        let mut fields = RbFields::new();
+        fields.insert("@field1".into(), Value::Bool(true));
+        fields.insert(
+            "@field2".into(),
+            Value::String("i've been round tripped".into()),
+        );
+        let original = Value::Object(crate::Object {
+            class: "Test".into(),
+            fields,
+        });
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn instance() {
+        let inner_value = Box::new(Value::String("I've been round tripped, with ivars!".into()));
+        let mut fields = RbFields::new();
+        fields.insert("E".into(), Value::Bool(true));
+        fields.insert("@round_trip".into(), Value::Integer(123));
+        let original = Value::Instance(Instance {
+            value: inner_value,
+            fields,
+        });
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn regex() {
+        let original = Value::Regex {
+            data: "/round trip/".into(),
+            flags: 0b1010,
+        };
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn rb_struct() {
+        let mut fields = RbFields::new();
+        fields.insert("field1".into(), Value::Bool(true));
+        fields.insert("field2".into(), Value::String("round trip".into()));
+        let original = Value::RbStruct(RbStruct {
+            class: "TestStruct".into(),
+            fields,
+        });
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn class() {
+        let original = Value::Class("TestClass".into());
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn module() {
+        let original = Value::Module("TestModule".into());
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn user_class() {
+        let inner_value = Box::new(Value::String("I'm a user class".into()));
+        let original = Value::UserClass {
+            class: "TestUserClass".into(),
+            value: inner_value,
+        };
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn user_marshal() {
+        let inner_value = Box::new(Value::String("I've been serialized as another type".into()));
+        let original = Value::UserMarshal {
+            class: "TestUserMarshal".into(),
+            value: inner_value,
+        };
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+
+    #[test]
+    fn data() {
+        let inner_value = Box::new(Value::String("???".into()));
+        let original = Value::Data {
+            class: "TestData".into(),
+            value: inner_value,
+        };
+
+        let bytes = to_bytes(&original).unwrap();
+
+        let new: Value = from_bytes(&bytes).unwrap();
+
+        assert_eq!(original, new);
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod hash_merge {
+    use crate::{MergeStrategy, RbHash, RbHashExt, Value};
+
+    fn hash<const N: usize>(entries: [(i32, Value); N]) -> RbHash {
+        entries
+            .into_iter()
+            .map(|(k, v)| (Value::Integer(k.into()), v))
+            .collect()
+    }
+
+    #[test]
+    fn keep_left_and_right() {
+        let left = hash([(1, Value::Bool(false)), (2, Value::Nil)]);
+        let right = hash([(3, Value::Nil), (1, Value::Bool(true))]);
+
+        let mut merged = left.clone();
+        merged.merge_preserving_order(right.clone(), MergeStrategy::KeepLeft);
+        assert_eq!(
+            merged,
+            hash([(1, Value::Bool(false)), (2, Value::Nil), (3, Value::Nil)])
+        );
+
+        let mut merged = left;
+        merged.merge_preserving_order(right, MergeStrategy::KeepRight);
+        let keys: Vec<_> = merged.keys().cloned().collect();
+        assert_eq!(
+            keys,
+            [Value::Integer(1), Value::Integer(2), Value::Integer(3)]
+        );
+        assert_eq!(merged[&Value::Integer(1)], Value::Bool(true));
+    }
+
+    #[test]
+    fn recurse() {
+        let mut left = hash([(1, Value::Hash(hash([(10, Value::Nil), (11, Value::Nil)])))]);
+        let right = hash([(
+            1,
+            Value::Hash(hash([(12, Value::Nil), (10, Value::Bool(true))])),
+        )]);
+
+        left.merge_preserving_order(right, MergeStrategy::Recurse);
+
+        let inner = left[&Value::Integer(1)].as_hash().unwrap();
+        let keys: Vec<_> = inner.keys().cloned().collect();
+        assert_eq!(
+            keys,
+            [Value::Integer(10), Value::Integer(11), Value::Integer(12)]
+        );
+        assert_eq!(inner[&Value::Integer(10)], Value::Bool(true));
+    }
+
+    #[test]
+    fn entry_through_wrappers() {
+        let mut value = Value::UserClass {
+            class: "MyHash".into(),
+            value: Box::new(Value::Hash(RbHash::new())),
+        };
+
+        *value.hash_entry(1).unwrap().or_default() = Value::Bool(true);
+
+        assert_eq!(
+            value.inner_hash().unwrap()[&Value::Integer(1)],
+            Value::Bool(true)
+        );
+        assert!(Value::Nil.hash_entry(1).is_none());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod value_conversions {
+    use std::collections::HashMap;
+
+    use crate::{RbFields, Value};
+
+    #[test]
+    fn from() {
+        assert_eq!(Value::from(()), Value::Nil);
+        assert_eq!(Value::from(5u8), Value::Integer(5));
+        assert_eq!(Value::from(None::<i32>), Value::Nil);
+        assert_eq!(
+            Value::from([1, 2]),
+            Value::Array(vec![Value::Integer(1), Value::Integer(2)])
+        );
+        assert_eq!(Value::from(i64::MAX), Value::Integer(i64::MAX));
+        assert_eq!(Value::try_from(5u64), Ok(Value::Integer(5)));
+        assert!(Value::try_from(u64::MAX).is_err());
+
+        let hash = Value::from(HashMap::from([("key", vec![true])]));
+        assert_eq!(
+            hash.as_hash().unwrap()[&Value::from("key")],
+            Value::Array(vec![Value::Bool(true)])
+        );
+    }
+
+    #[test]
+    fn try_from() {
+        assert_eq!(i32::try_from(Value::Integer(1)), Ok(1));
+        assert_eq!(i64::try_from(Value::Integer(1)), Ok(1));
+        assert_eq!(
+            i32::try_from(Value::Integer(i64::MAX)),
+            Err(Value::Integer(i64::MAX))
+        );
+        assert_eq!(String::try_from(Value::from("hi")), Ok("hi".to_string()));
+        assert_eq!(
+            crate::Symbol::try_from(Value::symbol("sym")),
+            Ok(crate::Symbol::from("sym"))
+        );
+        assert_eq!(f64::try_from(Value::Nil), Err(Value::Nil));
+    }
+
+    #[test]
+    fn constructors() {
+        let value = Value::user_class("MyHash", Value::from(HashMap::<i32, i32>::new()));
+        assert!(value.inner_hash().is_some());
+
+        let object = Value::object("Foo", RbFields::new());
+        assert_eq!(object.as_object().unwrap().class, "Foo");
+
+        let regex = Value::regex("a+", 1);
+        assert!(matches!(regex, Value::Regex { flags: 1, .. }));
+    }
+}
+
+#[cfg(test)]
+mod testing_tokens {
+    use crate::{
+        testing::{assert_de_tokens, assert_ser_tokens, AssertSerializer, MockDeserializer, Token},
+        Deserialize, Serialize,
+    };
+
+    #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Test {
+        name: String,
+        count: Option<i32>,
+    }
+
+    const TOKENS: &[Token<'static>] = &[
+        Token::Object {
+            class: "Test",
+            len: 2,
+        },
+        Token::Ivar("@name"),
+        Token::Instance { len: 1 },
+        Token::String(b"hi"),
+        Token::Ivar("E"),
+        Token::Bool(true),
+        Token::IvarsEnd,
+        Token::Ivar("@count"),
+        Token::Nil,
+        Token::IvarsEnd,
+    ];
+
+    #[test]
+    fn round_trip() {
+        let value = Test {
+            name: "hi".to_string(),
+            count: None,
+        };
+
+        assert_ser_tokens(&value, TOKENS);
+        assert_de_tokens(&value, TOKENS);
+    }
+
+    #[test]
+    fn ser_mismatch() {
+        let mut serializer = AssertSerializer::new(&[Token::I32(1)]);
+        assert!(2.serialize(&mut serializer).is_err());
+    }
+
+    #[test]
+    fn de_skips_unread() {
+        let tokens = [
+            Token::Object {
+                class: "Test",
+                len: 3,
+            },
+            Token::Ivar("@extra"),
+            Token::Array { len: 1 },
+            Token::Nil,
+            Token::ArrayEnd,
+            Token::Ivar("@name"),
+            Token::String(b"hi"),
+            Token::Ivar("@count"),
+            Token::I32(1),
+            Token::IvarsEnd,
+        ];
+
+        let mut deserializer = MockDeserializer::new(&tokens);
+        let value = Test::deserialize(&mut deserializer).unwrap();
+        assert_eq!(value.count, Some(1));
+        assert!(deserializer.remaining().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod std_types {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    fn round_trip<T>(value: &T)
+    where
+        T: crate::Serialize + for<'de> crate::Deserialize<'de> + PartialEq + std::fmt::Debug,
+    {
+        let bytes = crate::to_bytes(value).unwrap();
+        let new_value: T = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(&new_value, value);
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(&('a', 1, String::from("two")));
+        round_trip(&PathBuf::from("Data/Map001.rxdata"));
+        round_trip(&Duration::new(5, 500));
+        round_trip(&(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000)));
+        round_trip(&IpAddr::V4(Ipv4Addr::LOCALHOST));
+        round_trip(&(1..5));
+        round_trip(&(1..=5));
+    }
+
+    #[test]
+    fn range_matches_ruby() {
+        // Marshal.dump(1..2)
+        let bytes = b"\x04\x08o:\nRange\x08:\texclF:\nbegini\x06:\x08endi\x07";
+
+        let range: std::ops::RangeInclusive<i32> = crate::from_bytes(bytes).unwrap();
+        assert_eq!(range, 1..=2);
+        assert_eq!(crate::to_bytes(range).unwrap(), bytes);
+
+        assert!(crate::from_bytes::<std::ops::Range<i32>>(bytes).is_err());
+    }
+
+    #[test]
+    fn tuple_length() {
+        let bytes = crate::to_bytes((1, 2, 3)).unwrap();
+        assert!(crate::from_bytes::<(i32, i32)>(&bytes).is_err());
+    }
+
+    #[test]
+    fn char_length() {
+        let bytes = crate::to_bytes("ab").unwrap();
+        assert!(crate::from_bytes::<char>(&bytes).is_err());
+    }
+}
+
+#[cfg(test)]
+mod output_limit {
+    use crate::{ser::Kind, SerOptions, Serialize, Serializer, Symbol};
+
+    #[test]
+    fn exceeded() {
+        let mut serializer = Serializer::new();
+        serializer.set_output_limit(Some(64));
+
+        let err = "a".repeat(128).serialize(&mut serializer).unwrap_err();
+        assert!(matches!(err.kind, Kind::OutputLimitExceeded(64)));
+        assert!(serializer.output.len() <= 64);
+    }
+
+    #[test]
+    fn within_limit() {
+        let mut serializer = Serializer::new();
+        serializer.set_output_limit(Some(64));
+
+        vec![1, 2, 3].serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.output, crate::to_bytes(vec![1, 2, 3]).unwrap());
+    }
+
+    #[test]
+    fn counted() {
+        let data = (vec!["a", "b", "a"], 300, Symbol::from("a"));
+        let bytes = crate::to_bytes(&data).unwrap();
+        assert_eq!(crate::serialized_size(&data).unwrap(), bytes.len());
+
+        let mut counter = Serializer::counting();
+        counter.set_output_limit(Some(bytes.len() - 1));
+        let err = data.serialize(&mut counter).unwrap_err();
+        assert!(matches!(err.kind, Kind::OutputLimitExceeded(_)));
+        assert_eq!(counter.output, crate::MARSHAL_VERSION);
+    }
+
+    #[test]
+    fn counted_with_options() {
+        let data = "a".repeat(128);
+
+        let options = SerOptions::new().output_limit(Some(64));
+        let err = crate::serialized_size_with(&data, &options).unwrap_err();
+        assert!(matches!(err.kind, Kind::OutputLimitExceeded(64)));
+        let err = crate::to_bytes_with(&data, &options).unwrap_err();
+        assert!(matches!(err.kind, Kind::OutputLimitExceeded(64)));
+
+        let options = SerOptions::new().output_limit(Some(256));
+        let size = crate::serialized_size_with(&data, &options).unwrap();
+        let bytes = crate::to_bytes_with(&data, &options).unwrap();
+        assert_eq!(bytes, crate::to_bytes(&data).unwrap());
+        assert_eq!(size, bytes.len());
+    }
+}
+
+#[cfg(all(test, feature = "chrono"))]
+mod chrono_time {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    #[test]
+    fn ruby_utc_time() {
+        // Marshal.dump(Time.utc(2022, 1, 1))
+        let mut bytes = vec![0x04, 0x08, b'I', b'u', b':', 0x09];
+        bytes.extend_from_slice(b"Time");
+        bytes.push(0x0D);
+        bytes.extend_from_slice(&[0x20, 0x80, 0x1E, 0xC0, 0x00, 0x00, 0x00, 0x00]);
+        bytes.extend_from_slice(&[0x06, b':', 0x09]);
+        bytes.extend_from_slice(b"zone");
+        bytes.extend_from_slice(&[b'I', b'"', 0x08]);
+        bytes.extend_from_slice(b"UTC");
+        bytes.extend_from_slice(&[0x06, b':', 0x06, b'E', b'F']);
+
+        let time: DateTime<Utc> = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(time, Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn round_trip() {
+        let time = Utc.with_ymd_and_hms(2024, 2, 29, 13, 37, 5).unwrap()
+            + chrono::Duration::nanoseconds(123_456_789);
+
+        let bytes = crate::to_bytes(time).unwrap();
+        let result: DateTime<Utc> = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(time, result);
+    }
+
+    #[test]
+    fn year_out_of_range() {
+        let time = Utc.with_ymd_and_hms(1800, 1, 1, 0, 0, 0).unwrap();
+        assert!(crate::to_bytes(time).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod time_time {
+    use time::{macros::datetime, OffsetDateTime};
+
+    #[test]
+    fn round_trip_utc() {
+        let time = datetime!(2024-02-29 13:37:05.123_456_789 UTC);
+
+        let bytes = crate::to_bytes(time).unwrap();
+        let result: OffsetDateTime = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(time, result);
+        assert!(result.offset().is_utc());
+    }
+
+    #[test]
+    fn round_trip_offset() {
+        let time = datetime!(2024-01-01 0:00:00.5 +09:00);
+
+        let bytes = crate::to_bytes(time).unwrap();
+        let result: OffsetDateTime = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(time, result);
+        assert_eq!(result.offset().whole_hours(), 9);
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod instance_conversions {
+    use crate::{Instance, InstanceExt, RbFields, RbString, Value};
+
+    #[test]
+    fn untyped_to_typed() {
+        let bytes = crate::to_bytes("hello").unwrap();
+        let value: Value = crate::from_bytes(&bytes).unwrap();
+        let untyped = value.into_instance().unwrap();
+
+        let typed: Instance<RbString> = untyped.clone().try_map().unwrap();
+        assert_eq!(typed.value, RbString::from("hello"));
+        assert_eq!(typed.fields, untyped.fields);
+        assert_eq!(typed.encoding(), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn typed_to_untyped() {
+        let mut fields = RbFields::new();
+        fields.insert("@extra".into(), 1.into());
+
+        let untyped = Instance::from_typed(vec![1, 2], fields.clone()).unwrap();
+        assert_eq!(untyped.fields, fields);
+        assert!(untyped.value.is_array());
+
+        let typed: Instance<Vec<i32>> = untyped.try_map().unwrap();
+        assert_eq!(typed.value, vec![1, 2]);
+    }
+
+    #[test]
+    fn wrong_type() {
+        let untyped = Instance::from_typed(1, RbFields::new()).unwrap();
+        assert!(untyped.try_map::<RbString>().is_err());
+    }
+
+    #[test]
+    fn deref_and_map() {
+        let mut typed: Instance<Vec<i32>> = Instance::from_typed(vec![1, 2], RbFields::new())
+            .unwrap()
+            .try_map()
+            .unwrap();
+        typed.push(3);
+        assert_eq!(typed.len(), 3);
+
+        let mut fields = RbFields::new();
+        fields.insert("@extra".into(), 1.into());
+        typed.fields = fields.clone();
+
+        let summed = typed.map(|v| v.into_iter().sum::<i32>());
+        assert_eq!(summed.get_ivar::<i32>("@extra"), Some(Ok(1)));
+        assert_eq!(summed.into_parts(), (6, fields));
+    }
+}
+
+#[cfg(all(test, feature = "uuid"))]
+mod uuid_strings {
+    use uuid::Uuid;
+
+    const UUID: Uuid = uuid::uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8");
+
+    #[test]
+    fn round_trip() {
+        let bytes = crate::to_bytes(UUID).unwrap();
+        assert_eq!(
+            crate::from_bytes::<String>(&bytes).unwrap(),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+        assert_eq!(crate::from_bytes::<Uuid>(&bytes).unwrap(), UUID);
+    }
+
+    #[test]
+    fn from_bytes() {
+        let bytes = crate::to_bytes(crate::SerializeByteString(UUID.as_bytes())).unwrap();
+        assert_eq!(crate::from_bytes::<Uuid>(&bytes).unwrap(), UUID);
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes = crate::to_bytes("not a uuid").unwrap();
+        assert!(crate::from_bytes::<Uuid>(&bytes).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "url"))]
+mod url_strings {
+    use url::Url;
+
+    #[test]
+    fn round_trip() {
+        let url = Url::parse("https://example.com/saves?slot=1").unwrap();
+
+        let bytes = crate::to_bytes(&url).unwrap();
+        assert_eq!(crate::from_bytes::<Url>(&bytes).unwrap(), url);
+        assert!(crate::from_bytes::<Url>(&crate::to_bytes("not a url").unwrap()).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod strict_mode {
+    use crate::{
+        de::{Kind, Skipped},
+        Deserialize, Deserializer, Object, RbFields, Value,
+    };
+
+    #[derive(crate::Deserialize, Debug)]
+    #[marshal(alox_crate_path = "crate")]
+    #[allow(dead_code)]
+    struct Test {
+        name: String,
+    }
+
+    fn test_object() -> Vec<u8> {
+        let mut fields = RbFields::new();
+        fields.insert("@name".into(), "test".into());
+        fields.insert("@extra".into(), 1.into());
+        crate::to_bytes(Value::Object(Object {
+            class: "Test".into(),
+            fields,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn counts_skipped_data() {
+        let bytes = test_object();
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        Test::deserialize(&mut deserializer).unwrap();
+
+        // the string's encoding is not counted
+        assert_eq!(deserializer.stats().ivars, 1);
+        assert_eq!(deserializer.stats().total(), 1);
+    }
+
+    #[test]
+    fn rejects_skipped_data() {
+        let bytes = test_object();
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        deserializer.set_strict(true);
+
+        let err = Test::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(err.kind, Kind::SkippedData(Skipped::Ivar, _)));
+    }
+
+    #[test]
+    fn trailing_elements() {
+        struct First;
+
+        impl<'de> crate::Visitor<'de> for First {
+            type Value = i32;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                formatter.write_str("an array")
+            }
+
+            fn visit_array<A>(self, mut array: A) -> crate::DeResult<Self::Value>
+            where
+                A: crate::ArrayAccess<'de>,
+            {
+                Ok(array.next_element()?.unwrap_or_default())
+            }
+        }
+
+        let bytes = crate::to_bytes([1, 2, 3]).unwrap();
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        assert_eq!(
+            crate::DeserializerTrait::deserialize(&mut deserializer, First).unwrap(),
+            1
+        );
+        assert_eq!(deserializer.stats().elements, 2);
+
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        deserializer.set_strict(true);
+        let err = crate::DeserializerTrait::deserialize(&mut deserializer, First).unwrap_err();
+        assert!(matches!(err.kind, Kind::SkippedData(Skipped::Element, _)));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod instance_fields {
+    use crate::{Instance, Object, RbFields, RbHash, Value};
+
+    #[derive(crate::Deserialize, crate::Serialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Test {
+        list: Instance<Vec<i32>>,
+        #[marshal(default)]
+        table: Instance<RbHash>,
+    }
+
+    #[test]
+    fn round_trip_array_ivars() {
+        let mut list_fields = RbFields::new();
+        list_fields.insert("@dirty".into(), true.into());
+
+        let mut fields = RbFields::new();
+        fields.insert(
+            "@list".into(),
+            Value::Instance(Instance {
+                value: Box::new(Value::Array(vec![1.into(), 2.into()])),
+                fields: list_fields.clone(),
+            }),
+        );
+        fields.insert("@table".into(), Value::Hash(RbHash::new()));
+        let bytes = crate::to_bytes(Value::Object(Object {
+            class: "Test".into(),
+            fields,
+        }))
+        .unwrap();
+
+        let test: Test = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(test.list.value, [1, 2]);
+        assert_eq!(test.list.fields, list_fields);
+        assert!(test.table.fields.is_empty());
+
+        assert_eq!(crate::to_bytes(&test).unwrap(), bytes);
+    }
+
+    #[test]
+    fn missing_defaults() {
+        let mut fields = RbFields::new();
+        fields.insert("@list".into(), Value::Array(vec![]));
+        let value = Value::Object(Object {
+            class: "Test".into(),
+            fields,
+        });
+
+        let test: Test = crate::from_value(&value).unwrap();
+        assert_eq!(
+            test,
+            Test {
+                list: Instance::new(Vec::new()),
+                table: Instance::default(),
+            }
+        );
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod validation {
+    use crate::{
+        de::validate::{ValidatingVisitor, Validator},
+        DeError, DeResult, Deserializer, DeserializerTrait, Sym, Value,
+    };
+
+    #[test]
+    fn verify() {
+        let bytes = crate::to_bytes(Value::Array(vec!["a".into(), 1.into(), Value::Nil])).unwrap();
+        crate::verify_bytes(&bytes).unwrap();
+        assert!(crate::verify_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn custom_lint() {
+        // Rejects any string that isn't utf8.
+        struct Utf8Only {
+            strings: usize,
+            encodings: usize,
+        }
+
+        impl<'de> Validator<'de> for Utf8Only {
+            fn visit_string(&mut self, data: &'de [u8]) -> DeResult<()> {
+                self.strings += 1;
+                std::str::from_utf8(data)
+                    .map(|_| ())
+                    .map_err(DeError::custom)
+            }
+
+            fn visit_ivar(&mut self, ivar: &'de Sym) -> DeResult<()> {
+                self.encodings += usize::from(ivar == "E");
+                Ok(())
+            }
+        }
+
+        let bytes = crate::to_bytes(vec!["a", "b"]).unwrap();
+        let mut lint = Utf8Only {
+            strings: 0,
+            encodings: 0,
+        };
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        deserializer
+            .deserialize(ValidatingVisitor::new(&mut lint))
+            .unwrap();
+        assert_eq!(lint.strings, 2);
+        assert_eq!(lint.encodings, 2);
+
+        let bytes = crate::to_bytes(crate::SerializeByteString(&[0xFF])).unwrap();
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        assert!(deserializer
+            .deserialize(ValidatingVisitor::new(&mut lint))
+            .is_err());
+    }
+}
+
+#[cfg(test)]
+mod trailing_bytes {
+    use crate::{de::Kind, Deserialize, Deserializer};
+
+    #[test]
+    fn exact() {
+        let bytes = crate::to_bytes(vec![1, 2]).unwrap();
+        assert_eq!(crate::from_bytes_exact::<Vec<i32>>(&bytes).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        let mut bytes = crate::to_bytes(vec![1, 2]).unwrap();
+        bytes.extend_from_slice(b"garbage");
+
+        assert!(crate::from_bytes::<Vec<i32>>(&bytes).is_ok());
+        let err = crate::from_bytes_exact::<Vec<i32>>(&bytes).unwrap_err();
+        assert!(matches!(err.kind, Kind::TrailingBytes(7)));
+
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        Vec::<i32>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(deserializer.finish(), 7);
+    }
+}
+
+#[cfg(test)]
+mod serializer_reuse {
+    use crate::{Serialize, Serializer, Sym};
+
+    #[test]
+    fn reset() {
+        let mut serializer = Serializer::new();
+        Sym::new("a").serialize(&mut serializer).unwrap();
+        let first = serializer.output.clone();
+
+        // the symbol table is cleared too, otherwise this would be a symlink
+        serializer.reset();
+        Sym::new("a").serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.output, first);
+    }
+
+    #[test]
+    fn with_buffer() {
+        let buffer = Vec::with_capacity(64);
+        let ptr = buffer.as_ptr();
+
+        let mut serializer = Serializer::with_buffer(buffer);
+        vec![1, 2, 3].serialize(&mut serializer).unwrap();
+        let output = serializer.into_inner();
+
+        assert_eq!(output, crate::to_bytes(vec![1, 2, 3]).unwrap());
+        assert_eq!(output.as_ptr(), ptr);
+
+        let serializer = Serializer::with_buffer(output);
+        assert_eq!(serializer.into_inner(), [4, 8]);
+    }
+}
+
+#[cfg(test)]
+mod typed_errors {
+    use crate::de::Kind;
+
+    #[test]
+    fn parse_float() {
+        let bytes = [0x04, 0x08, b'f', 0x08, b'1', b'.', b'x'];
+        let err = crate::from_bytes::<f64>(&bytes).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            Kind::ParseFloat(e) if e.offset == 4 && e.bytes == b"1.x"
+        ));
+    }
+
+    #[test]
+    fn invalid_value() {
+        let bytes = crate::to_bytes(1).unwrap();
+        let err = crate::from_bytes::<String>(&bytes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value: integer `1`, expected `a utf8 string`"
+        );
+        assert!(matches!(
+            err.kind,
+            Kind::InvalidValue(e) if e.unexpected == "integer `1`" && e.expected == "a utf8 string"
+        ));
+    }
+
+    #[test]
+    fn invalid_length() {
+        let bytes = crate::to_bytes([1, 2, 3]).unwrap();
+        let err = crate::from_bytes::<(i32, i32)>(&bytes).unwrap_err();
+        assert!(matches!(err.kind, Kind::InvalidLength(e) if e.len == 3));
+    }
+
+    #[test]
+    fn refcell_borrowed() {
+        let cell = std::cell::RefCell::new(1);
+        let _borrow = cell.borrow_mut();
+        let err = crate::to_bytes(&cell).unwrap_err();
+        assert!(matches!(err.kind, crate::ser::Kind::AlreadyBorrowed));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod provided_lengths {
+    use crate::{
+        ser::Kind, SerResult, Serialize, SerializeArray, SerializeHash, SerializeIvars,
+        SerializerTrait, Sym,
+    };
+
+    enum Broken {
+        ArrayOvershot,
+        HashUndershot,
+        DanglingField,
+    }
+
+    impl Serialize for Broken {
+        fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+        where
+            S: SerializerTrait,
+        {
+            match self {
+                Broken::ArrayOvershot => {
+                    let mut array = serializer.serialize_array(1)?;
+                    array.serialize_element(&1)?;
+                    array.serialize_element(&2)?;
+                    array.end()
+                }
+                Broken::HashUndershot => {
+                    let mut hash = serializer.serialize_hash(2)?;
+                    hash.serialize_entry(&1, &2)?;
+                    hash.end()
+                }
+                Broken::DanglingField => {
+                    let mut ivars = serializer.serialize_object(Sym::new("Test"), 1)?;
+                    ivars.serialize_field(Sym::new("@a"))?;
+                    ivars.end()
+                }
+            }
+        }
+    }
+
+    fn check(broken: &Broken, expected: fn(&Kind) -> bool) {
+        assert!(expected(&crate::to_bytes(broken).unwrap_err().kind));
+        assert!(expected(&crate::to_value(broken).unwrap_err().kind));
+    }
+
+    #[test]
+    fn overshot() {
+        check(&Broken::ArrayOvershot, |k| {
+            matches!(k, Kind::OvershotProvidedLen(2, 1))
+        });
+    }
+
+    #[test]
+    fn undershot() {
+        check(&Broken::HashUndershot, |k| {
+            matches!(k, Kind::UndershotProvidedLen(1, 2))
+        });
+    }
+
+    #[test]
+    fn dangling_field() {
+        check(&Broken::DanglingField, |k| matches!(k, Kind::KeyAfterKey));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod ruby_strings {
+    use crate::{
+        ruby_string::{decode, encode, Encoding, Policy},
+        Deserializer, Instance, RbFields, RbString, Value,
+    };
+
+    fn decode_bytes(bytes: &[u8], policy: Policy) -> crate::DeResult<String> {
+        let mut deserializer = Deserializer::new(bytes)?;
+        decode(&mut deserializer, policy)
+    }
+
+    #[test]
+    fn plain() {
+        let bytes = crate::to_bytes(RbString::from("hi")).unwrap();
+        assert_eq!(decode_bytes(&bytes, Policy::Strict).unwrap(), "hi");
+    }
+
+    #[test]
+    fn round_trip() {
+        for encoding in [Encoding::Utf8, Encoding::UsAscii, Encoding::Binary] {
+            let bytes = crate::to_bytes(encode("héllo", encoding)).unwrap();
+            assert_eq!(decode_bytes(&bytes, Policy::Strict).unwrap(), "héllo");
+        }
+    }
+
+    #[test]
+    fn user_class() {
+        let value = Value::Instance(Instance {
+            value: Box::new(Value::UserClass {
+                class: "MyString".into(),
+                value: Box::new(Value::String("subclass".into())),
+            }),
+            fields: encode("", Encoding::Utf8).fields,
+        });
+        assert_eq!(decode(&value, Policy::Strict).unwrap(), "subclass");
+
+        let bytes = crate::to_bytes(&value).unwrap();
+        assert_eq!(decode_bytes(&bytes, Policy::Strict).unwrap(), "subclass");
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let value = Value::String(RbString::from(vec![b'a', 0xFF]));
+        assert!(decode(&value, Policy::Strict).is_err());
+        assert_eq!(decode(&value, Policy::Lossy).unwrap(), "a\u{FFFD}");
+    }
+
+    #[test]
+    fn other_encoding() {
+        let string = encode("abc", Encoding::Other("Shift_JIS".to_owned()));
+        let bytes = crate::to_bytes(&string).unwrap();
+        assert!(decode_bytes(&bytes, Policy::Strict).is_err());
+        assert_eq!(decode_bytes(&bytes, Policy::Lossy).unwrap(), "abc");
+    }
+
+    #[test]
+    fn not_a_string() {
+        let value = Value::Instance(Instance {
+            value: Box::new(Value::Symbol("sym".into())),
+            fields: RbFields::new(),
+        });
+        assert!(decode(&value, Policy::Lossy).is_err());
+        assert!(decode(&Value::Integer(1), Policy::Lossy).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod events {
+    use crate::{
+        de::{Event, EventReader},
+        Instance, Object, RbFields, RbHash, Sym, Value,
+    };
+
+    fn events(bytes: &[u8]) -> Vec<Event<'_>> {
+        EventReader::new(bytes)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn nested() {
+        let mut hash = RbHash::new();
+        hash.insert(Value::Symbol("a".into()), Value::Array(vec![Value::Nil]));
+        let bytes = crate::to_bytes(Value::Hash(hash)).unwrap();
+
+        assert_eq!(
+            events(&bytes),
+            [
+                Event::StartHash(1),
+                Event::Symbol(Sym::new("a")),
+                Event::StartArray(1),
+                Event::Nil,
+                Event::End,
+                Event::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn instance() {
+        let mut fields = RbFields::new();
+        fields.insert("E".into(), true.into());
+        let value = Value::Array(vec![
+            Value::Instance(Instance {
+                value: Box::new(Value::String("hi".into())),
+                fields,
+            }),
+            Value::Symbol("E".into()),
+        ]);
+        let bytes = crate::to_bytes(value).unwrap();
+
+        assert_eq!(
+            events(&bytes),
+            [
+                Event::StartArray(2),
+                Event::StartInstance,
+                Event::String(b"hi"),
+                Event::InstanceVariables(1),
+                Event::Ivar(Sym::new("E")),
+                Event::Bool(true),
+                Event::End,
+                // resolved from a symlink
+                Event::Symbol(Sym::new("E")),
+                Event::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn object_links() {
+        // [obj, obj], where the second is a link to the first
+        let mut fields = RbFields::new();
+        fields.insert(
+            "@a".into(),
+            Value::UserClass {
+                class: "Foo".into(),
+                value: Box::new(Value::Integer(1)),
+            },
+        );
+        let object = Value::Object(Object {
+            class: "Test".into(),
+            fields,
+        });
+        let mut bytes = crate::to_bytes(Value::Array(vec![object])).unwrap();
+        bytes[3] += 1;
+        bytes.extend_from_slice(b"@\x06");
+
+        assert_eq!(
+            events(&bytes),
+            [
+                Event::StartArray(2),
+                Event::StartObject {
+                    class: Sym::new("Test"),
+                    len: 1
+                },
+                Event::Ivar(Sym::new("@a")),
+                Event::UserClass(Sym::new("Foo")),
+                Event::Integer(1),
+                Event::End,
+                Event::ObjectLink(1),
+                Event::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_on_error() {
+        let mut reader = EventReader::new(b"\x04\x08[\x07i").unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), Event::StartArray(2));
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod raw_values {
+    use crate::{de::Kind, Object, RawValue, RbFields, Value};
+
+    #[derive(crate::Deserialize, crate::Serialize, Debug)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Test<'a> {
+        raw: RawValue<'a>,
+        after: Value,
+    }
+
+    fn test_object(raw: Value) -> Value {
+        let mut fields = RbFields::new();
+        fields.insert("@raw".into(), raw);
+        fields.insert("@after".into(), Value::Symbol("sym".into()));
+        Value::Object(Object {
+            class: "Test".into(),
+            fields,
+        })
+    }
+
+    #[test]
+    fn round_trip() {
+        // the raw value defines a symbol that is linked to afterwards
+        let raw = Value::Array(vec![Value::Symbol("sym".into()), Value::Float(1.5)]);
+        let bytes = crate::to_bytes(test_object(raw.clone())).unwrap();
+
+        let test: Test<'_> = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(test.after, Value::Symbol("sym".into()));
+        assert_eq!(test.raw.get(), &crate::to_bytes(&raw).unwrap()[2..],);
+        assert_eq!(crate::to_bytes(&test).unwrap(), bytes);
+    }
+
+    #[test]
+    fn unsupported() {
+        let value = test_object(Value::Nil);
+        let err = crate::from_value::<Test<'_>>(&value).unwrap_err();
+        assert!(matches!(err.kind, Kind::RawValueUnsupported));
+
+        let bytes = crate::to_bytes(&value).unwrap();
+        let test: Test<'_> = crate::from_bytes(&bytes).unwrap();
+        let err = crate::to_value(&test).unwrap_err();
+        assert!(matches!(err.kind, crate::ser::Kind::RawValueUnsupported));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod hash_defaults {
+    use std::collections::HashMap;
+
+    use crate::{
+        testing::{assert_de_tokens, assert_ser_tokens, Token},
+        RbHash, SerializeHash, SerializerTrait, Value,
+    };
+
+    // Marshal.dump(Hash.new(0).merge(1 => 2))
+    const BYTES: &[u8] = b"\x04\x08}\x06i\x06i\x07i\x00";
+
+    fn value() -> Value {
+        let mut hash = RbHash::new();
+        hash.insert(1.into(), 2.into());
+        Value::HashWithDefault {
+            hash,
+            default: Box::new(0.into()),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let value: Value = crate::from_bytes(BYTES).unwrap();
+        assert_eq!(value, self::value());
+        assert_eq!(crate::to_bytes(&value).unwrap(), BYTES);
+
+        let copy: Value = crate::from_value(&crate::to_value(&value).unwrap()).unwrap();
+        assert_eq!(copy, value);
+    }
+
+    #[test]
+    fn ignored_by_plain_hashes() {
+        let hash: HashMap<i32, i32> = crate::from_bytes(BYTES).unwrap();
+        assert_eq!(hash, HashMap::from([(1, 2)]));
+
+        let mut deserializer = crate::Deserializer::new(BYTES).unwrap();
+        let _: HashMap<i32, i32> = deserializer.deserialize_value().unwrap();
+        assert_eq!(deserializer.stats().hash_defaults, 1);
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = [
+            Token::HashWithDefault { len: 1 },
+            Token::I32(1),
+            Token::I32(2),
+            Token::I32(0),
+            Token::HashEnd,
+        ];
+        assert_ser_tokens(&value(), &tokens);
+        assert_de_tokens(&value(), &tokens);
+    }
+
+    #[test]
+    fn default_errors() {
+        let mut serializer = crate::Serializer::new();
+        let mut hash = (&mut serializer).serialize_hash_with_default(0).unwrap();
+        hash.serialize_default(&1).unwrap();
+        assert!(matches!(
+            hash.serialize_default(&1).unwrap_err().kind,
+            crate::ser::Kind::UnexpectedHashDefault
+        ));
+
+        let hash = crate::ValueSerializer
+            .serialize_hash_with_default(0)
+            .unwrap();
+        assert!(matches!(
+            hash.end().unwrap_err().kind,
+            crate::ser::Kind::MissingHashDefault
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod string_ivars {
+    use crate::{RbString, RbStringWithIvars, Value};
+
+    #[test]
+    fn extra_ivars() {
+        // s = "hi"; s.instance_variable_set(:@x, 1); Marshal.dump(s)
+        let bytes = b"\x04\x08I\"\x07hi\x07:\x06ET:\x07@xi\x06";
+        let string: RbStringWithIvars = crate::from_bytes(bytes).unwrap();
+
+        assert_eq!(string.string, RbString::from("hi"));
+        assert_eq!(string.encoding(), "UTF-8");
+        assert_eq!(string.utf8(), Some("hi"));
+        assert_eq!(
+            string.fields.get_index(1),
+            Some((&"@x".into(), &Value::Integer(1)))
+        );
+        assert_eq!(crate::to_bytes(&string).unwrap(), bytes);
+    }
+
+    #[test]
+    fn encodings() {
+        let binary: RbStringWithIvars = crate::from_bytes(b"\x04\x08\"\x06a").unwrap();
+        assert!(binary.is_binary());
+        assert_eq!(binary.utf8(), None);
+        assert_eq!(crate::to_bytes(&binary).unwrap(), b"\x04\x08\"\x06a");
+
+        let bytes = b"\x04\x08I\"\x06a\x06:\rencoding\"\x0eShift_JIS";
+        let sjis: RbStringWithIvars = crate::from_bytes(bytes).unwrap();
+        assert_eq!(sjis.encoding(), "Shift_JIS");
+        assert_eq!(sjis.utf8(), None);
+        assert_eq!(crate::to_bytes(&sjis).unwrap(), bytes);
+
+        let string = RbStringWithIvars::from("hello");
+        assert_eq!(
+            crate::to_bytes(&string).unwrap(),
+            crate::to_bytes("hello").unwrap()
+        );
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod string_encodings {
+    use crate::{ser::StringEncoding, Serialize, Serializer};
+
+    fn dump(encoding: StringEncoding, string: &str) -> Vec<u8> {
+        let mut serializer = Serializer::new();
+        serializer.set_string_encoding(encoding);
+        string.serialize(&mut serializer).unwrap();
+        serializer.into_inner()
+    }
+
+    #[test]
+    fn utf8() {
+        assert_eq!(
+            dump(StringEncoding::Utf8, "abc"),
+            b"\x04\x08I\"\x08abc\x06:\x06ET"
+        );
+    }
+
+    #[test]
+    fn ascii_aware() {
+        assert_eq!(
+            dump(StringEncoding::AsciiAware, "abc"),
+            b"\x04\x08I\"\x08abc\x06:\x06EF"
+        );
+        assert_eq!(
+            dump(StringEncoding::AsciiAware, "é"),
+            b"\x04\x08I\"\x07\xC3\xA9\x06:\x06ET"
+        );
+    }
+
+    #[test]
+    fn skip_for_ascii() {
+        assert_eq!(
+            dump(StringEncoding::SkipForAscii, "abc"),
+            b"\x04\x08\"\x08abc"
+        );
+        assert_eq!(
+            dump(StringEncoding::SkipForAscii, "é"),
+            b"\x04\x08I\"\x07\xC3\xA9\x06:\x06ET"
+        );
+    }
+
+    #[test]
+    fn none() {
+        assert_eq!(dump(StringEncoding::None, "é"), b"\x04\x08\"\x07\xC3\xA9");
+    }
+
+    #[test]
+    fn named() {
+        let bytes = dump(StringEncoding::Named("Shift_JIS".to_string()), "a");
+        assert_eq!(bytes, b"\x04\x08I\"\x06a\x06:\rencoding\"\x0eShift_JIS");
+
+        let string: crate::RbStringWithIvars = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(string.encoding(), "Shift_JIS");
+    }
+
+    #[test]
+    fn symbols_are_linked() {
+        let mut serializer = Serializer::new();
+        serializer.set_string_encoding(StringEncoding::AsciiAware);
+        ["a", "b"].serialize(&mut serializer).unwrap();
+        assert_eq!(
+            serializer.into_inner(),
+            b"\x04\x08[\x07I\"\x06a\x06:\x06EFI\"\x06b\x06;\x00F"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod catch_all {
+    use crate::{Object, RbFields, Value};
+
+    #[derive(alox_48_derive::Deserialize, alox_48_derive::Serialize, PartialEq, Debug)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Test<'a> {
+        name: &'a str,
+        #[marshal(default)]
+        id: i32,
+        #[marshal(catch_all)]
+        rest: RbFields,
+    }
+
+    fn object() -> Value {
+        Value::Object(Object {
+            class: "Test".into(),
+            fields: [
+                (
+                    "@name".into(),
+                    Value::from(crate::ruby_string::encode(
+                        "test",
+                        crate::ruby_string::Encoding::Utf8,
+                    )),
+                ),
+                ("@id".into(), Value::from("one")),
+                ("@extra".into(), Value::Array(vec![Value::Nil, 1.into()])),
+            ]
+            .into_iter()
+            .collect(),
+        })
+    }
+
+    #[test]
+    fn captures_unknown_and_mismatching() {
+        let bytes = crate::to_bytes(object()).unwrap();
+        let test: Test<'_> = crate::from_bytes(&bytes).unwrap();
+
+        assert_eq!(test.name, "test");
+        assert_eq!(test.id, 0);
+        assert_eq!(
+            test.rest,
+            [
+                ("@id".into(), Value::from("one")),
+                ("@extra".into(), Value::Array(vec![Value::Nil, 1.into()])),
+            ]
+            .into_iter()
+            .collect::<RbFields>()
+        );
+
+        let value = object();
+        let from_value: Test<'_> = crate::from_value(&value).unwrap();
+        assert_eq!(from_value, test);
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = crate::to_bytes(object()).unwrap();
+        let test: Test<'_> = crate::from_bytes(&bytes).unwrap();
+
+        assert_eq!(crate::to_bytes(&test).unwrap(), bytes);
+    }
+
+    #[test]
+    fn mismatch_after_links() {
+        // the failed attempt at reading @id must not leave symbols or objects behind
+        #[derive(alox_48_derive::Deserialize, PartialEq, Debug)]
+        #[marshal(alox_crate_path = "crate")]
+        struct Links {
+            #[marshal(default)]
+            id: Vec<i32>,
+            other: Value,
+            #[marshal(catch_all)]
+            rest: RbFields,
+        }
+
+        let shared = Value::Array(vec![Value::Symbol("sym".into()), Value::Nil]);
+        let bytes = crate::to_bytes(Value::Object(Object {
+            class: "Links".into(),
+            fields: [
+                ("@id".into(), shared.clone()),
+                ("@other".into(), Value::Symbol("sym".into())),
+            ]
+            .into_iter()
+            .collect(),
+        }))
+        .unwrap();
+
+        let links: Links = crate::from_bytes(&bytes).unwrap();
+        assert!(links.id.is_empty());
+        assert_eq!(links.other, Value::Symbol("sym".into()));
+        assert_eq!(links.rest.get(crate::Sym::new("@id")), Some(&shared));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod duplicates {
+    use crate::{de::Kind, Deserializer, RbFields, RbHash, Value};
+
+    // Object with `@a` twice
+    const IVARS: &[u8] = b"\x04\x08o:\x08Foo\x07:\x07@ai\x06;\x06i\x07";
+    // { :a => 1, :a => 2 }
+    const KEYS: &[u8] = b"\x04\x08{\x07:\x06ai\x06;\x00i\x07";
+
+    #[test]
+    fn allowed_by_default() {
+        let value: Value = crate::from_bytes(IVARS).unwrap();
+        let object = value.into_object().unwrap();
+        assert_eq!(object.fields.len(), 1);
+
+        let hash: RbHash = crate::from_bytes(KEYS).unwrap();
+        assert_eq!(hash.len(), 1);
+
+        crate::verify_bytes(IVARS).unwrap();
+    }
+
+    #[test]
+    fn duplicate_ivars() {
+        let mut deserializer = Deserializer::new(IVARS).unwrap();
+        deserializer.set_deny_duplicates(true);
+        let err = deserializer.deserialize_value::<Value>().unwrap_err();
+        assert!(matches!(err.kind, Kind::DuplicateField(ref f) if f.as_str() == "@a"));
+
+        let err = crate::verify_bytes_unique(IVARS).unwrap_err();
+        assert!(matches!(err.kind, Kind::DuplicateField(_)));
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let mut deserializer = Deserializer::new(KEYS).unwrap();
+        deserializer.set_deny_duplicates(true);
+        let err = deserializer.deserialize_value::<RbHash>().unwrap_err();
+        assert!(matches!(err.kind, Kind::DuplicateKey(ref k) if **k == Value::Symbol("a".into())));
+
+        let err = crate::verify_bytes_unique(KEYS).unwrap_err();
+        assert!(matches!(err.kind, Kind::DuplicateKey(_)));
+    }
+
+    #[test]
+    fn unique_data() {
+        let mut fields = RbFields::new();
+        fields.insert("@a".into(), Value::Symbol("b".into()));
+        fields.insert("@b".into(), Value::Symbol("b".into()));
+        let bytes = crate::to_bytes(Value::Hash(
+            [
+                (
+                    Value::Symbol("a".into()),
+                    Value::Object(crate::Object {
+                        class: "A".into(),
+                        fields,
+                    }),
+                ),
+                (Value::Symbol("b".into()), Value::Integer(1)),
+            ]
+            .into_iter()
+            .collect(),
+        ))
+        .unwrap();
+
+        crate::verify_bytes_unique(&bytes).unwrap();
+
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        deserializer.set_deny_duplicates(true);
+        let value: Value = deserializer.deserialize_value().unwrap();
+        assert_eq!(value, crate::from_bytes::<Value>(&bytes).unwrap());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod inspect {
+    use crate::{
+        ruby_string::{encode, Encoding},
+        Instance, Object, RbFields, RbStruct, Userdata, Value,
+    };
+
+    fn object(class: &str, fields: Vec<(&str, Value)>) -> Value {
+        Value::Object(Object {
+            class: class.into(),
+            fields: fields
+                .into_iter()
+                .map(|(k, v)| (k.into(), v))
+                .collect::<RbFields>(),
+        })
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(Value::Nil.to_ruby_string(), "nil");
+        assert_eq!(Value::Bool(true).to_ruby_string(), "true");
+        assert_eq!(Value::Integer(-5).to_ruby_string(), "-5");
+        assert_eq!(Value::Float(1.0).to_ruby_string(), "1.0");
+        assert_eq!(Value::Float(1e20).to_ruby_string(), "1.0e+20");
+        assert_eq!(Value::Float(1.5e-7).to_ruby_string(), "1.5e-07");
+        assert_eq!(
+            Value::Float(f64::NEG_INFINITY).to_ruby_string(),
+            "-Infinity"
+        );
+        assert_eq!(Value::Float(f64::NAN).to_ruby_string(), "NaN");
+        assert_eq!(Value::Class("Foo::Bar".into()).to_ruby_string(), "Foo::Bar");
+    }
+
+    #[test]
+    fn symbols() {
+        assert_eq!(Value::Symbol("foo".into()).to_ruby_string(), ":foo");
+        assert_eq!(Value::Symbol("foo?".into()).to_ruby_string(), ":foo?");
+        assert_eq!(Value::Symbol("@foo".into()).to_ruby_string(), ":@foo");
+        assert_eq!(Value::Symbol("<=>".into()).to_ruby_string(), ":<=>");
+        assert_eq!(
+            Value::Symbol("foo bar".into()).to_ruby_string(),
+            ":\"foo bar\""
+        );
+    }
+
+    #[test]
+    fn strings() {
+        let utf8 = Value::from(encode("é\"\n#{x}", Encoding::Utf8));
+        assert_eq!(utf8.to_ruby_string(), r#""é\"\n\#{x}""#);
+
+        let binary = Value::String("é\x01".into());
+        assert_eq!(binary.to_ruby_string(), r#""\xC3\xA9\x01""#);
+
+        let control = Value::from(encode("\x01", Encoding::Utf8));
+        assert_eq!(control.to_ruby_string(), r#""\u0001""#);
+    }
+
+    #[test]
+    fn containers() {
+        let hash = Value::Hash(
+            [
+                (Value::Symbol("a".into()), Value::Integer(1)),
+                (Value::String("b".into()), Value::Array(vec![Value::Nil])),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(hash.to_ruby_string(), r#"{:a=>1, "b"=>[nil]}"#);
+
+        let value = object("Foo", vec![("@a", Value::Integer(1)), ("@b", hash)]);
+        assert_eq!(value.to_string(), r#"#<Foo @a=1, @b={:a=>1, "b"=>[nil]}>"#);
+        assert_eq!(object("Empty", vec![]).to_string(), "#<Empty>");
+
+        let rb_struct = Value::RbStruct(RbStruct {
+            class: "Point".into(),
+            fields: [("x".into(), Value::Integer(1))].into_iter().collect(),
+        });
+        assert_eq!(rb_struct.to_string(), "#<struct Point x=1>");
+
+        let userdata = Value::Userdata(Userdata::new("Table", vec![0, 1]));
+        assert_eq!(userdata.to_string(), r#"#<Table _dump="\x00\x01">"#);
+    }
+
+    #[test]
+    fn wrappers() {
+        let regex = Value::Instance(Instance {
+            value: Box::new(Value::Regex {
+                data: "a/b\\/c".into(),
+                flags: 1 | 4,
+            }),
+            fields: [("E".into(), Value::Bool(false))].into_iter().collect(),
+        });
+        assert_eq!(regex.to_string(), r"/a\/b\/c/mi");
+
+        let user_class = Value::UserClass {
+            class: "MyArray".into(),
+            value: Box::new(Value::Array(vec![1.into()])),
+        };
+        assert_eq!(user_class.to_string(), "[1]");
+    }
+
+    #[test]
+    fn pretty() {
+        let long = Value::Array((0..30).map(Value::Integer).collect());
+        let value = object(
+            "Foo",
+            vec![("@short", Value::Integer(1)), ("@long", long.clone())],
+        );
+
+        let expected = "#<Foo
+ @short=1,
+ @long=[0,
+        1,";
+        let pretty = format!("{value:#}");
+        assert!(pretty.starts_with(expected), "{pretty}");
+        assert!(pretty.ends_with("\n        29]>"), "{pretty}");
+
+        // values that fit stay on one line
+        assert_eq!(format!("{:#}", Value::Integer(1)), "1");
+        let short = Value::Array(vec![1.into(), 2.into()]);
+        assert_eq!(format!("{short:#}"), short.to_string());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod marshal_macro {
+    use crate::{marshal, Object, RbFields, RbHash, Symbol, Value};
+
+    #[test]
+    fn scalars() {
+        assert_eq!(marshal!(nil), Value::Nil);
+        assert_eq!(marshal!(true), Value::Bool(true));
+        assert_eq!(marshal!(-5), Value::Integer(-5));
+        assert_eq!(marshal!(1.5), Value::Float(1.5));
+        assert_eq!(marshal!(:foo), Value::Symbol("foo".into()));
+        assert_eq!(marshal!(:"foo bar"), Value::Symbol("foo bar".into()));
+        assert_eq!(marshal!("hi"), crate::to_value("hi").unwrap());
+    }
+
+    #[test]
+    fn containers() {
+        assert_eq!(marshal!([]), Value::Array(vec![]));
+        assert_eq!(marshal!({}), Value::Hash(RbHash::new()));
+        assert_eq!(
+            marshal!([1, [nil, :a], -2,]),
+            Value::Array(vec![
+                1.into(),
+                Value::Array(vec![Value::Nil, Value::Symbol("a".into())]),
+                (-2).into()
+            ])
+        );
+
+        let x = 3;
+        let hash: RbHash = [
+            (Value::Symbol("a".into()), Value::Integer(1)),
+            (Value::Integer(2), Value::Array(vec![Value::Integer(6)])),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(marshal!({ :a => 1, 2 => [(x * 2)] }), Value::Hash(hash));
+    }
+
+    #[test]
+    fn objects() {
+        let value = marshal!(Foo::Bar {
+            @a => 1,
+            "@b c" => Baz {},
+        });
+
+        let fields: RbFields = [
+            (Symbol::from("@a"), Value::Integer(1)),
+            (
+                Symbol::from("@b c"),
+                Value::Object(Object {
+                    class: "Baz".into(),
+                    fields: RbFields::new(),
+                }),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            value,
+            Value::Object(Object {
+                class: "Foo::Bar".into(),
+                fields
+            })
+        );
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod huge_lengths {
+    // an array claiming to have i32::MAX elements, without any of them
+    const BYTES: &[u8] = b"\x04\x08[\x04\xFF\xFF\xFF\x7F";
+
+    #[test]
+    fn does_not_preallocate() {
+        let err = crate::from_bytes::<Vec<crate::Value>>(BYTES).unwrap_err();
+        assert!(matches!(err.kind, crate::de::Kind::Eof));
+
+        let err = crate::from_bytes::<crate::Value>(BYTES).unwrap_err();
+        assert!(matches!(err.kind, crate::de::Kind::Eof));
+    }
+
+    #[test]
+    fn past_end_of_input() {
+        // a string claiming 9 bytes, where only 3 are left (but the whole input is longer than 9)
+        let err =
+            crate::from_bytes::<crate::Value>(b"\x04\x08[\x09i\0i\0i\0\"\x0Eabc").unwrap_err();
+        assert!(matches!(err.kind, crate::de::Kind::Eof));
+
+        let err = crate::from_bytes::<crate::Value>(b"\x04\x08[\x09i\0i\0i\0:\x0Eabc").unwrap_err();
+        assert!(matches!(err.kind, crate::de::Kind::Eof));
+    }
+}
+
+#[cfg(test)]
+mod symbol_eq {
+    use crate::{sym, Sym, Symbol};
+
+    const EVENT: &Sym = sym!("RPG::Event");
+
+    #[test]
+    fn consts() {
+        let class = Symbol::from("RPG::Event");
+        assert_eq!(class, EVENT);
+        assert_eq!(EVENT.as_str(), "RPG::Event");
+        assert!(matches!(class.as_sym(), c if c == EVENT));
+    }
+
+    #[test]
+    fn both_directions() {
+        let symbol = Symbol::from("a");
+        let sym = sym!("a");
+        let string = String::from("a");
+
+        assert!(sym == "a");
+        assert!("a" == sym);
+        assert!(*sym == *"a");
+        assert!(*"a" == *sym);
+        assert!(*sym == string);
+        assert!(string == *sym);
+        assert!(string == sym);
+        assert!(symbol == "a");
+        assert!("a" == symbol);
+        assert!(*"a" == symbol);
+        assert!(symbol == string);
+        assert!(string == symbol);
+        assert!(symbol == sym);
+        assert!(*sym == symbol);
+        assert!(symbol == *sym);
+        assert!("b" != symbol);
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod tables {
+    use crate::{de::Ignored, marshal, Deserialize, Deserializer};
+
+    #[test]
+    fn symbols_and_objects() {
+        let value = marshal!([Foo { @a => 1 }, Foo { @b => "x" }, :a]);
+        let bytes = crate::to_bytes(value).unwrap();
+
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        Ignored::deserialize(&mut deserializer).unwrap();
+
+        assert_eq!(deserializer.symbols(), ["Foo", "@a", "@b", "E", "a"]);
+        let tags: Vec<u8> = deserializer.objects().iter().map(|&o| bytes[o]).collect();
+        assert_eq!(tags, b"[ooI");
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod transparent {
+    use crate::{Deserialize, Serialize, Value};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", transparent)]
+    struct Parameter(Value);
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", transparent)]
+    struct Name<'a> {
+        name: &'a str,
+    }
+
+    #[test]
+    fn newtype() {
+        let value = crate::marshal!([1, :a, nil]);
+        let bytes = crate::to_bytes(&value).unwrap();
+
+        let parameter: Parameter = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(parameter, Parameter(value));
+        assert_eq!(crate::to_bytes(&parameter).unwrap(), bytes);
+    }
+
+    #[test]
+    fn named_field() {
+        let bytes = crate::to_bytes("Alice").unwrap();
+
+        let name: Name<'_> = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(name, Name { name: "Alice" });
+        assert_eq!(crate::to_bytes(&name).unwrap(), bytes);
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod expect {
+    use crate::{de::Kind, marshal, Deserialize, Value};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    struct MoveRoute {
+        #[marshal(expect = "Array")]
+        list: Vec<Value>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Event {
+        #[marshal(expect_class = "RPG::MoveRoute")]
+        route: Option<MoveRoute>,
+        #[marshal(expect = "String", expect_class = "Name")]
+        name: String,
+    }
+
+    fn event(route: &Value, name: &Value) -> Result<Event, crate::DeError> {
+        let value = marshal!(Event { @route => (route), @name => (name) });
+        crate::from_bytes(&crate::to_bytes(value).unwrap())
+    }
+
+    #[test]
+    fn matching() {
+        let route = marshal!(RPG::MoveRoute { @list => [1] });
+        let name = Value::UserClass {
+            class: "Name".into(),
+            value: Box::new(marshal!("Alice")),
+        };
+
+        assert_eq!(
+            event(&route, &name).unwrap(),
+            Event {
+                route: Some(MoveRoute {
+                    list: vec![Value::Integer(1)]
+                }),
+                name: "Alice".to_string(),
+            }
+        );
+
+        let err = event(&Value::Nil, &marshal!("Alice")).unwrap_err();
+        assert!(matches!(
+            &err.kind,
+            Kind::InvalidType(m) if m.unexpected == "string \"Alice\"" && m.expected == "a string of class `Name`"
+        ));
+    }
+
+    #[test]
+    fn wrong_kind() {
+        let route = marshal!(RPG::MoveRoute { @list => { 1 => 2 } });
+        let err = crate::from_value::<MoveRoute>(&route).unwrap_err();
+        assert!(matches!(
+            &err.kind,
+            Kind::InvalidType(m) if m.unexpected == "hash" && m.expected == "an array"
+        ));
+    }
+
+    #[test]
+    fn wrong_class() {
+        let route = marshal!(RPG::Troop { @list => [] });
+        let err = event(&route, &Value::Nil).unwrap_err();
+        assert!(matches!(
+            &err.kind,
+            Kind::WrongClass(e) if e.expected == "RPG::MoveRoute" && e.found == "RPG::Troop"
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod unknown_fields {
+    use crate::{de::Kind, marshal, Deserialize, Sym};
+
+    #[derive(Deserialize, Debug)]
+    #[marshal(alox_crate_path = "crate", deny_unknown_fields = "all")]
+    #[allow(dead_code)]
+    struct Actor {
+        name: String,
+        character_name: String,
+        initial_level: i32,
+    }
+
+    #[test]
+    fn reports_all() {
+        let value = marshal!(Actor {
+            @name => "Alice",
+            @charcter_name => "001-Fighter01",
+            @intial_level => 1,
+            @weapon => nil,
+        });
+        let err = crate::from_value::<Actor>(&value).unwrap_err();
+        let Kind::UnknownFields(e) = &err.kind else {
+            unreachable!("{err}")
+        };
+
+        assert_eq!(e.class.as_ref().unwrap(), "Actor");
+        assert_eq!(e.fields, ["charcter_name", "intial_level", "weapon"]);
+        assert_eq!(
+            e.suggestion(Sym::new("charcter_name")).unwrap(),
+            "character_name"
+        );
+        assert_eq!(
+            e.suggestion(Sym::new("intial_level")).unwrap(),
+            "initial_level"
+        );
+        assert_eq!(e.suggestion(Sym::new("weapon")), None);
+        assert_eq!(
+            err.to_string(),
+            "unknown fields `:charcter_name` (did you mean `:character_name`?), \
+             `:intial_level` (did you mean `:initial_level`?), `:weapon` in class `:Actor`, \
+             expected one of `:name`, `:character_name`, `:initial_level`"
+        );
+    }
+
+    #[test]
+    fn known_fields() {
+        let value = marshal!(Actor {
+            @name => "Alice",
+            @character_name => "001-Fighter01",
+            @initial_level => 1,
+        });
+        assert!(crate::from_value::<Actor>(&value).is_ok());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod enums {
+    use crate::{de::Kind, marshal, Deserialize, Userdata, Value};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    struct AudioFile {
+        name: String,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    enum Parameter {
+        #[marshal(class = "RPG::AudioFile")]
+        AudioFile(AudioFile),
+        Table(Userdata),
+        #[marshal(other)]
+        Unknown,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    enum Capturing {
+        #[marshal(class = "RPG::AudioFile")]
+        AudioFile(AudioFile),
+        #[marshal(other)]
+        Other(Value),
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    enum Strict {
+        #[marshal(class = "RPG::AudioFile")]
+        AudioFile(AudioFile),
+    }
+
+    fn values() -> Vec<u8> {
+        let table = Value::Userdata(Userdata::new("Table", vec![1, 2, 3]));
+        let value = marshal!([
+            RPG::AudioFile { @name => "001-Battle01" },
+            Mod::Thing { @a => [1, 2], @b => "x" },
+            (table),
+            RPG::AudioFile { @name => "002-Battle02" },
+        ]);
+        crate::to_bytes(value).unwrap()
+    }
+
+    #[test]
+    fn by_class() {
+        let parameters: Vec<Parameter> = crate::from_bytes(&values()).unwrap();
+        assert_eq!(
+            parameters,
+            [
+                Parameter::AudioFile(AudioFile {
+                    name: "001-Battle01".to_string()
+                }),
+                Parameter::Unknown,
+                Parameter::Table(Userdata::new("Table", vec![1, 2, 3])),
+                Parameter::AudioFile(AudioFile {
+                    name: "002-Battle02".to_string()
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_newtype() {
+        let parameters: Vec<Capturing> = crate::from_bytes(&values()).unwrap();
+        assert_eq!(
+            parameters[1],
+            Capturing::Other(marshal!(Mod::Thing { @a => [1, 2], @b => "x" }))
+        );
+        assert!(matches!(
+            parameters[2],
+            Capturing::Other(Value::Userdata(_))
+        ));
+        assert!(matches!(parameters[3], Capturing::AudioFile(_)));
+    }
+
+    #[test]
+    fn unknown_class() {
+        let err = crate::from_bytes::<Vec<Strict>>(&values()).unwrap_err();
+        assert!(matches!(
+            &err.kind,
+            Kind::InvalidValue(e) if e.unexpected == "an instance of `:Mod::Thing`" && e.expected == "an instance of RPG::AudioFile"
+        ));
+    }
+}
+
+#[cfg(test)]
+mod generics {
+    use std::marker::PhantomData;
+
+    use crate::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Wrapper<'a, T, U> {
+        value: T,
+        values: Vec<U>,
+        name: &'a str,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", class = "Id")]
+    struct Id<T>(T);
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", transparent)]
+    struct Transparent<T>(T);
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    enum Either<T, U> {
+        #[marshal(class = "Wrapper")]
+        Left(T),
+        #[marshal(class = "Split")]
+        Right(U),
+    }
+
+    struct NotMarshal;
+
+    #[derive(Deserialize, Serialize, Debug)]
+    #[marshal(alox_crate_path = "crate", bound = "")]
+    struct Tagged<T> {
+        #[marshal(skip)]
+        marker: PhantomData<T>,
+        id: i32,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[marshal(
+        alox_crate_path = "crate",
+        bound(
+            serialize = "T: Serialize + Copy",
+            deserialize = "T: Deserialize<'de> + Copy"
+        )
+    )]
+    struct Split<T> {
+        value: T,
+    }
+
+    #[test]
+    fn type_params() {
+        let wrapper = Wrapper {
+            value: 1,
+            values: vec![true, false],
+            name: "a",
+        };
+        let bytes = crate::to_bytes(&wrapper).unwrap();
+        assert_eq!(
+            crate::from_bytes::<Wrapper<'_, i32, bool>>(&bytes).unwrap(),
+            wrapper
+        );
+
+        let bytes = crate::to_bytes(Id(1.5)).unwrap();
+        assert_eq!(crate::from_bytes::<Id<f64>>(&bytes).unwrap(), Id(1.5));
+
+        let bytes = crate::to_bytes(Transparent(Id(2))).unwrap();
+        assert_eq!(
+            crate::from_bytes::<Transparent<Id<i32>>>(&bytes).unwrap(),
+            Transparent(Id(2))
+        );
+
+        let bytes = crate::to_bytes(Split { value: 3 }).unwrap();
+        assert_eq!(
+            crate::from_bytes::<Either<Wrapper<'_, i32, i32>, Split<i32>>>(&bytes).unwrap(),
+            Either::Right(Split { value: 3 })
+        );
+
+        let bytes = crate::to_bytes(Split { value: 4 }).unwrap();
+        assert_eq!(
+            crate::from_bytes::<Split<i32>>(&bytes).unwrap(),
+            Split { value: 4 }
+        );
+    }
+
+    #[test]
+    fn replaced_bounds() {
+        let tagged = Tagged::<NotMarshal> {
+            marker: PhantomData,
+            id: 5,
+        };
+        let bytes = crate::to_bytes(&tagged).unwrap();
+        let tagged: Tagged<NotMarshal> = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(tagged.id, 5);
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod skip_serializing_if {
+    use crate::{marshal, Deserialize, RbFields, Serialize, Value};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Event {
+        id: i32,
+        #[marshal(skip_serializing_if = "Option::is_none", default)]
+        name: Option<String>,
+        #[marshal(skip_serializing_if = "Vec::is_empty", default)]
+        pages: Vec<i32>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+    #[marshal(alox_crate_path = "crate", class = "Event")]
+    struct Capturing {
+        id: i32,
+        #[marshal(skip_serializing_if = "Option::is_none", default)]
+        name: Option<String>,
+        #[marshal(catch_all)]
+        rest: RbFields,
+    }
+
+    #[test]
+    fn skipped() {
+        let event = Event {
+            id: 1,
+            ..Default::default()
+        };
+        // the length of the object has to match for this to round trip
+        let bytes = crate::to_bytes(&event).unwrap();
+        assert_eq!(
+            crate::from_bytes::<Value>(&bytes).unwrap(),
+            marshal!(Event { @id => 1 })
+        );
+        assert_eq!(crate::from_bytes::<Event>(&bytes).unwrap(), event);
+
+        let event = Event {
+            id: 2,
+            name: None,
+            pages: vec![1],
+        };
+        let bytes = crate::to_bytes(&event).unwrap();
+        assert_eq!(
+            crate::from_bytes::<Value>(&bytes).unwrap(),
+            marshal!(Event { @id => 2, @pages => [1] })
+        );
+    }
+
+    #[test]
+    fn captured_fields() {
+        let value = marshal!(Event { @id => 1, @name => 5, @extra => true });
+        let capturing: Capturing = crate::from_value(&value).unwrap();
+        assert_eq!(capturing.name, None);
+
+        let bytes = crate::to_bytes(&capturing).unwrap();
+        assert_eq!(crate::from_bytes::<Value>(&bytes).unwrap(), value);
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod default_on_error {
+    use crate::{marshal, Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq, Default)]
+    #[marshal(alox_crate_path = "crate", class = "Table", enforce_class)]
+    struct Table {
+        width: i32,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", class = "Map")]
+    struct Map {
+        id: i32,
+        #[marshal(default, default_on_error)]
+        data: Table,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Default)]
+    #[marshal(alox_crate_path = "crate", class = "Map", default, default_on_error)]
+    struct LenientMap {
+        id: i32,
+        data: Table,
+    }
+
+    #[test]
+    fn wrong_class() {
+        let value = marshal!(Map { @id => 1, @data => Tilemap { @width => 5 } });
+        let map: Map = crate::from_value(&value).unwrap();
+        assert_eq!(map.data, Table::default());
+
+        let bytes = crate::to_bytes(&value).unwrap();
+        let map: Map = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(map.data, Table::default());
+    }
+
+    #[test]
+    fn nil() {
+        let value = marshal!(Map { @id => 1, @data => nil });
+        let map: Map = crate::from_value(&value).unwrap();
+        assert_eq!(map.data, Table::default());
+    }
+
+    #[test]
+    fn valid_data_is_kept() {
+        let value = marshal!(Map { @id => 1, @data => Table { @width => 5 } });
+        let map: Map = crate::from_value(&value).unwrap();
+        assert_eq!(map.data, Table { width: 5 });
+    }
+
+    #[test]
+    fn container() {
+        let value = marshal!(Map { @id => "corrupt", @data => nil });
+        let bytes = crate::to_bytes(&value).unwrap();
+        let map: LenientMap = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(map, LenientMap::default());
+
+        // fields without a default still error
+        assert!(crate::from_bytes::<Map>(&bytes).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod nil_as_default {
+    use crate::{marshal, DeError, Deserialize, DeserializerTrait};
+
+    fn five<'de, D>(deserializer: D) -> Result<i32, DeError>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        i32::deserialize(deserializer).map(|_| 5)
+    }
+
+    fn default_name() -> String {
+        "Unnamed".to_string()
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", class = "RPG::Actor")]
+    struct Actor {
+        #[marshal(default = "default_name", nil_as_default)]
+        name: String,
+        #[marshal(default, nil_as_default, expect = "Array")]
+        skills: Vec<i32>,
+        #[marshal(default, nil_as_default, deserialize_with = "five")]
+        level: i32,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Default)]
+    #[marshal(
+        alox_crate_path = "crate",
+        class = "RPG::Actor",
+        default,
+        nil_as_default
+    )]
+    struct LenientActor {
+        name: String,
+        skills: Vec<i32>,
+        level: i32,
+    }
+
+    #[test]
+    fn nil_fields() {
+        let value = marshal!(RPG::Actor { @name => nil, @skills => nil, @level => nil });
+        let expected = Actor {
+            name: "Unnamed".to_string(),
+            skills: vec![],
+            level: 0,
+        };
+        let actor: Actor = crate::from_value(&value).unwrap();
+        assert_eq!(actor, expected);
+
+        let bytes = crate::to_bytes(&value).unwrap();
+        let actor: Actor = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(actor, expected);
+    }
+
+    #[test]
+    fn other_values_are_kept() {
+        let value = marshal!(RPG::Actor { @name => "Aluxes", @skills => [1, 2], @level => 1 });
+        let actor: Actor = crate::from_value(&value).unwrap();
+        assert_eq!(
+            actor,
+            Actor {
+                name: "Aluxes".to_string(),
+                skills: vec![1, 2],
+                level: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn wrong_type_still_errors() {
+        let value = marshal!(RPG::Actor { @name => nil, @skills => 1, @level => nil });
+        assert!(crate::from_value::<Actor>(&value).is_err());
+    }
+
+    #[test]
+    fn container() {
+        let value = marshal!(RPG::Actor { @name => nil, @skills => [3], @level => nil });
+        let bytes = crate::to_bytes(&value).unwrap();
+        let actor: LenientActor = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            actor,
+            LenientActor {
+                skills: vec![3],
+                ..Default::default()
+            }
+        );
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod from_hash {
+    use crate::{marshal, Deserialize, RbFields, Value};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", class = "Config", from_hash, enforce_class)]
+    struct Config {
+        title: String,
+        volume: i32,
+        #[marshal(default)]
+        fullscreen: bool,
+        #[marshal(catch_all)]
+        extra: RbFields,
+    }
+
+    #[test]
+    fn hash_with_symbol_keys() {
+        let value = marshal!({ :title => "Game", :volume => 80, :vsync => true });
+        let expected = Config {
+            title: "Game".to_string(),
+            volume: 80,
+            fullscreen: false,
+            extra: [("vsync".into(), Value::Bool(true))].into_iter().collect(),
+        };
+
+        let config: Config = crate::from_value(&value).unwrap();
+        assert_eq!(config, expected);
+
+        let bytes = crate::to_bytes(&value).unwrap();
+        let config: Config = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn objects_still_work() {
+        let value = marshal!(Config { @title => "Game", @volume => 80, @fullscreen => true });
+        let config: Config = crate::from_value(&value).unwrap();
+        assert!(config.fullscreen);
+        assert!(config.extra.is_empty());
+    }
+
+    #[test]
+    fn missing_fields_and_other_keys() {
+        let value = marshal!({ :title => "Game" });
+        let err = crate::from_value::<Config>(&value).unwrap_err();
+        assert!(err.to_string().contains("volume"), "{err}");
+
+        let value = marshal!({ 1 => "Game" });
+        assert!(crate::from_value::<Config>(&value).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod schema_validation {
+    use crate::{
+        de::expect::ValueKind,
+        marshal,
+        schema::{PathSegment, Schema, ViolationKind},
+        Symbol,
+    };
+
+    fn map_schema() -> Schema {
+        Schema::object("RPG::Map")
+            .ivar("@width", ValueKind::Integer)
+            .ivar(
+                "@events",
+                Schema::hash(
+                    ValueKind::Integer,
+                    Schema::object("RPG::Event")
+                        .ivar("@name", ValueKind::String)
+                        .deny_unknown_ivars(),
+                ),
+            )
+            .optional_ivar("@bgm", Schema::object("RPG::AudioFile").nullable())
+            .into()
+    }
+
+    #[test]
+    fn valid() {
+        let map = marshal!(RPG::Map {
+            @width => 20,
+            @events => { 1 => RPG::Event { @name => "EV001" } },
+            @bgm => nil,
+        });
+        assert!(map_schema().is_valid(&map));
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let map = marshal!(RPG::Map {
+            @width => "wide",
+            @events => {
+                1 => RPG::Event { @name => "EV001", @extra => 1 },
+                "2" => RPG::Event {},
+            },
+            @bgm => RPG::BGM {},
+        });
+        let violations = map_schema().validate(&map);
+        let kinds: Vec<_> = violations.iter().map(|v| &v.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                &ViolationKind::WrongKind {
+                    expected: ValueKind::Integer,
+                    found: ValueKind::String
+                },
+                &ViolationKind::UnknownIvar(Symbol::from("@extra")),
+                &ViolationKind::WrongKind {
+                    expected: ValueKind::Integer,
+                    found: ValueKind::String
+                },
+                &ViolationKind::MissingIvar(Symbol::from("@name")),
+                &ViolationKind::WrongClass {
+                    expected: Symbol::from("RPG::AudioFile"),
+                    found: Symbol::from("RPG::BGM")
+                },
+            ]
+        );
+        assert_eq!(
+            violations[3].path,
+            [
+                PathSegment::Ivar("@events".into()),
+                PathSegment::HashValue(marshal!("2"))
+            ]
+        );
+        assert_eq!(
+            violations[3].to_string(),
+            "@events[\"2\"]: missing instance variable `@name`"
+        );
+    }
+
+    #[test]
+    fn one_of() {
+        let schema = Schema::array(Schema::OneOf(vec![
+            ValueKind::Integer.into(),
+            ValueKind::Float.into(),
+        ]));
+        assert!(schema.is_valid(&marshal!([1, 2.5])));
+
+        let violations = schema.validate(&marshal!([1, :two]));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].to_string(),
+            "[1]: value did not match any schema"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod traced {
+    use crate::{
+        marshal,
+        path_to_error::{Context, Trace},
+        Deserialize, Serialize,
+    };
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Actor {
+        name: String,
+        level: i32,
+    }
+
+    #[test]
+    fn from_bytes() {
+        let bytes =
+            crate::to_bytes(marshal!(Actor { @name => "Alice", @level => "high" })).unwrap();
+        let (result, trace) = crate::from_bytes_traced::<Actor>(&bytes);
+        assert!(result.is_err());
+        assert!(trace
+            .context
+            .iter()
+            .any(|c| matches!(c, Context::Field(Some(field), _) if field == "@level")));
+
+        let bytes = crate::to_bytes(marshal!(Actor { @name => "Alice", @level => 5 })).unwrap();
+        let (result, trace) = crate::from_bytes_traced::<Actor>(&bytes);
+        assert_eq!(result.unwrap().level, 5);
+        assert!(trace.context.is_empty());
+    }
+
+    #[test]
+    fn from_value() {
+        let value = marshal!(Actor { @name => 5, @level => 1 });
+        let (result, trace) = crate::from_value_traced::<Actor>(&value);
+        assert!(result.is_err());
+        assert!(!trace.context.is_empty());
+    }
+
+    #[test]
+    fn to_bytes_and_value() {
+        let actor = Actor {
+            name: "Alice".to_string(),
+            level: 5,
+        };
+        let (bytes, trace) = crate::to_bytes_traced(&actor);
+        assert_eq!(bytes.unwrap(), crate::to_bytes(&actor).unwrap());
+        assert!(trace.context.is_empty());
+
+        let (value, trace) = crate::to_value_traced(&actor);
+        assert_eq!(value.unwrap(), crate::to_value(&actor).unwrap());
+        assert!(trace.context.is_empty());
+    }
+
+    // Skips elements that aren't integers.
+    #[derive(Debug, PartialEq)]
+    struct Integers(Vec<i32>);
+
+    impl<'de> Deserialize<'de> for Integers {
+        fn deserialize<D>(deserializer: D) -> crate::DeResult<Self>
+        where
+            D: crate::DeserializerTrait<'de>,
+        {
+            struct IntegersVisitor;
+
+            impl<'de> crate::Visitor<'de> for IntegersVisitor {
+                type Value = Integers;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    formatter.write_str("an array")
+                }
+
+                fn visit_array<A>(self, mut array: A) -> crate::DeResult<Self::Value>
+                where
+                    A: crate::ArrayAccess<'de>,
+                {
+                    let mut integers = vec![];
+                    for _ in 0..array.len() {
+                        if let Ok(Some(int)) = array.next_element() {
+                            integers.push(int);
+                        }
+                    }
+                    Ok(Integers(integers))
+                }
+            }
+
+            deserializer.deserialize(IntegersVisitor)
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[marshal(alox_crate_path = "crate")]
+    #[allow(dead_code)]
+    struct Stats {
+        integers: Integers,
+        level: i32,
+    }
+
+    #[test]
+    fn handled_errors_are_discarded() {
+        // values can carry on after an element fails, unlike marshal data
+        let value = marshal!([1, "two", 3]);
+        let (result, trace) = crate::from_value_traced::<Integers>(&value);
+        assert_eq!(result.unwrap(), Integers(vec![1, 3]));
+        assert!(trace.context.is_empty());
+
+        let value = marshal!(Stats { @integers => [1, "two"], @level => "high" });
+        let (result, trace) = crate::from_value_traced::<Stats>(&value);
+        assert!(result.is_err());
+        assert!(!trace.context.iter().any(|c| match c {
+            Context::ArrayIndex(_) | Context::Array(_) => true,
+            Context::String(s) => s == "two",
+            _ => false,
+        }));
+        assert!(matches!(
+            trace.context.last(),
+            Some(Context::Object(class, 2)) if class == "Stats"
+        ));
+        assert!(trace
+            .context
+            .iter()
+            .any(|c| matches!(c, Context::Field(Some(field), _) if field == "@level")));
+    }
+
+    #[test]
+    fn breadcrumbs() {
+        let bytes =
+            crate::to_bytes(marshal!([Actor { @name => "Alice", @level => "high" }])).unwrap();
+        let mut trace = Trace::breadcrumbs();
+        let mut deserializer = crate::Deserializer::new(&bytes).unwrap();
+        let result = Vec::<Actor>::deserialize(crate::path_to_error::Deserializer::new(
+            &mut deserializer,
+            &mut trace,
+        ));
+        assert!(result.is_err());
+        assert!(trace.is_breadcrumbs());
+        assert!(trace.context.iter().all(Context::is_breadcrumb));
+        assert!(matches!(
+            trace.context.as_slice(),
+            [Context::Field(Some(field), _), Context::ArrayIndex(_)] if field == "@level"
+        ));
+
+        let mut trace = Trace::breadcrumbs();
+        let actor = Actor {
+            name: "Alice".to_string(),
+            level: 5,
+        };
+        let result = actor.serialize(crate::path_to_error::Serializer::new(
+            crate::ValueSerializer,
+            &mut trace,
+        ));
+        assert_eq!(result.unwrap(), crate::to_value(&actor).unwrap());
+        assert!(trace.context.is_empty());
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[marshal(alox_crate_path = "crate")]
+    #[allow(dead_code)]
+    struct Map {
+        events: Vec<Option<Actor>>,
+    }
+
+    #[test]
+    fn path_and_location() {
+        let value = marshal!(Map {
+            @events => [nil, nil, nil, Actor { @name => "Alice", @level => "high" }]
+        });
+        let (result, trace) = crate::from_value_traced::<Map>(&value);
+        assert!(result.is_err());
+        assert_eq!(trace.path(), "$.@events[3].@level");
+        assert!(matches!(trace.location(), Some(Context::String(s)) if s == "high"));
+
+        let bytes = crate::to_bytes(marshal!({ 1 => [1], 2 => [2, nil] })).unwrap();
+        let (result, trace) =
+            crate::from_bytes_traced::<std::collections::HashMap<i32, Vec<i32>>>(&bytes);
+        assert!(result.is_err());
+        assert_eq!(trace.path(), "${1}[1]");
+
+        let (result, trace) =
+            crate::from_bytes_traced::<std::collections::HashMap<String, Vec<i32>>>(&bytes);
+        assert!(result.is_err());
+        assert_eq!(trace.path(), "${key 0}");
+
+        let trace = Trace::new();
+        assert_eq!(trace.path(), "$");
+        assert!(trace.location().is_none());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod track_path {
+    use crate::{
+        de::{Kind, PathSegment},
+        marshal, Deserialize, Deserializer, RbHash, Value,
+    };
+
+    #[derive(Deserialize, Debug)]
+    #[marshal(alox_crate_path = "crate")]
+    #[allow(dead_code)]
+    struct Page {
+        list: Vec<i32>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[marshal(alox_crate_path = "crate")]
+    #[allow(dead_code)]
+    struct Event {
+        pages: Vec<Page>,
+    }
+
+    fn bytes() -> Vec<u8> {
+        let value = marshal!([
+            nil,
+            {
+                1 => Event { @pages => [Page { @list => [1] }] },
+                2 => Event { @pages => [Page { @list => [1, "two"] }] },
+            },
+        ]);
+        crate::to_bytes(value).unwrap()
+    }
+
+    #[test]
+    fn untracked() {
+        let bytes = bytes();
+        let err = crate::from_bytes::<(Option<i32>, std::collections::HashMap<i32, Event>)>(&bytes)
+            .unwrap_err();
+        assert!(err.path().is_none());
+        assert!(matches!(err.kind, Kind::InvalidValue(_)));
+    }
+
+    #[test]
+    fn tracked() {
+        let bytes = bytes();
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        deserializer.set_track_path(true);
+        let err =
+            <(Option<i32>, std::collections::HashMap<i32, Event>)>::deserialize(&mut deserializer)
+                .unwrap_err();
+
+        assert_eq!(
+            err.path().unwrap(),
+            [
+                PathSegment::Index(1),
+                PathSegment::Entry(1),
+                PathSegment::Ivar("@pages".into()),
+                PathSegment::Index(0),
+                PathSegment::Ivar("@list".into()),
+                PathSegment::Index(1),
+            ]
+        );
+        assert!(matches!(err.kind, Kind::InvalidValue(_)));
+        assert!(err
+            .to_string()
+            .starts_with("[1]{1}.@pages[0].@list[1]: invalid value"));
+    }
+
+    #[test]
+    fn hash_default() {
+        let value = Value::HashWithDefault {
+            hash: RbHash::new(),
+            default: Box::new(marshal!(:oops)),
+        };
+        let bytes = crate::to_bytes(value).unwrap();
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        deserializer.set_track_path(true);
+        deserializer.set_depth_limit(1);
+        let err = Value::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(err.path().unwrap(), [PathSegment::HashDefault]);
+        assert!(matches!(err.kind, Kind::DepthLimitExceeded(1)));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod interned_symbols {
+    use crate::{marshal, Value};
+
+    #[test]
+    fn shared_between_objects() {
+        let bytes = crate::to_bytes(marshal!([
+            RPG::Event { @name => :a },
+            RPG::Event { @name => :a },
+        ]))
+        .unwrap();
+        let value: Value = crate::from_bytes(&bytes).unwrap();
+
+        let array = value.as_array().unwrap();
+        let (first, second) = (&array[0], &array[1]);
+        let (first, second) = (first.as_object().unwrap(), second.as_object().unwrap());
+        assert!(first.class.ptr_eq(&second.class));
+
+        let (first_ivar, first_name) = first.fields.first().unwrap();
+        let (second_ivar, second_name) = second.fields.first().unwrap();
+        assert!(first_ivar.ptr_eq(second_ivar));
+        assert!(first_name
+            .as_symbol()
+            .unwrap()
+            .ptr_eq(second_name.as_symbol().unwrap()));
+    }
+
+    #[test]
+    fn cheap_clones() {
+        let symbol = crate::Symbol::from("RPG::Event");
+        assert!(symbol.clone().ptr_eq(&symbol));
+        assert_eq!(String::from(symbol.clone()), "RPG::Event");
+        assert_eq!(symbol, "RPG::Event");
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod arena_document {
+    use crate::{arena::Document, de::expect::ValueKind, marshal, RbHash, Value};
+
+    fn value() -> Value {
+        marshal!([
+            nil,
+            true,
+            1,
+            2.5,
+            "text",
+            :sym,
+            { 1 => [RPG::Event { @name => "EV001", @pages => [1, 2] }] },
+            (Value::rb_struct(
+                "Point",
+                [("x".into(), Value::Integer(1)), ("y".into(), Value::Integer(2))]
+                    .into_iter()
+                    .collect(),
+            )),
+            (Value::HashWithDefault {
+                hash: RbHash::new(),
+                default: Box::new(Value::Integer(0)),
+            }),
+            (Value::extended("Mod", Value::Array(vec![]))),
+            (Value::userdata("Table", vec![1, 2, 3])),
+            (Value::class("Foo")),
+        ])
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = value();
+        let bytes = crate::to_bytes(&value).unwrap();
+
+        let document: Document = crate::from_bytes(&bytes).unwrap();
+        let expected: Value = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(document.root().to_value(), expected);
+
+        let document: Document = crate::from_value(&value).unwrap();
+        assert_eq!(document.root().to_value(), value);
+    }
+
+    #[test]
+    fn accessors() {
+        let bytes = crate::to_bytes(value()).unwrap();
+        let document: Document = crate::from_bytes(&bytes).unwrap();
+        let root = document.root();
+
+        assert_eq!(root.kind(), ValueKind::Array);
+        assert_eq!(root.len(), 12);
+        assert!(root.get(0).unwrap().is_nil());
+        assert_eq!(root.get(1).unwrap().as_bool(), Some(true));
+        assert_eq!(root.get(2).unwrap().as_i32(), Some(1));
+        assert_eq!(root.get(3).unwrap().as_f64(), Some(2.5));
+        assert_eq!(root.get(4).unwrap().as_str(), Some("text"));
+        assert_eq!(root.get(4).unwrap().instance_fields().len(), 1);
+        assert_eq!(root.get(5).unwrap().as_symbol().unwrap(), "sym");
+        assert!(root.get(12).is_none());
+
+        let (key, events) = root.get(6).unwrap().entries().next().unwrap();
+        assert_eq!(key.as_i32(), Some(1));
+        let event = events.get(0).unwrap();
+        assert_eq!(event.class().unwrap(), "RPG::Event");
+        let pages: Vec<_> = event
+            .ivar("@pages")
+            .unwrap()
+            .elements()
+            .map(|page| page.as_i32().unwrap())
+            .collect();
+        assert_eq!(pages, [1, 2]);
+
+        let point = root.get(7).unwrap();
+        assert_eq!(point.kind(), ValueKind::Struct);
+        assert_eq!(point.ivar("y").unwrap().as_i32(), Some(2));
+
+        let hash = root.get(8).unwrap();
+        assert_eq!(hash.hash_default().unwrap().as_i32(), Some(0));
+
+        let extended = root.get(9).unwrap();
+        assert_eq!(extended.kind(), ValueKind::Array);
+        assert_eq!(extended.inner().unwrap().kind(), ValueKind::Array);
+
+        assert_eq!(root.get(10).unwrap().kind(), ValueKind::UserData);
+        assert_eq!(root.get(11).unwrap().class().unwrap(), "Foo");
+    }
+
+    #[test]
+    fn shares_symbols() {
+        let bytes = crate::to_bytes(marshal!([Foo {}, Foo {}])).unwrap();
+        let document: Document = crate::from_bytes(&bytes).unwrap();
+        let root = document.root();
+        let first = root.get(0).unwrap().class().unwrap();
+        let second = root.get(1).unwrap().class().unwrap();
+        assert!(first.ptr_eq(second));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod collect_helpers {
+    use crate::{marshal, Object, RbFields, SerResult, Serialize, SerializerTrait, Sym, Value};
+
+    // serializes only the first `n` ivars of an object, without copying them
+    struct Truncated<'a>(&'a Object, usize);
+
+    impl Serialize for Truncated<'_> {
+        fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+        where
+            S: SerializerTrait,
+        {
+            serializer.collect_object(&self.0.class, self.0.fields.iter().take(self.1))
+        }
+    }
+
+    #[test]
+    fn borrowed_ivars() {
+        let value = marshal!(Actor { @name => "Alice", @level => 5, @hp => 100 });
+        let object = value.as_object().unwrap();
+
+        let truncated = crate::to_value(Truncated(object, 2)).unwrap();
+        assert_eq!(truncated, marshal!(Actor { @name => "Alice", @level => 5 }));
+
+        let bytes = crate::to_bytes(Truncated(object, 3)).unwrap();
+        assert_eq!(bytes, crate::to_bytes(&value).unwrap());
+    }
+
+    // serializes as `{ name => [ids...] }` straight from borrowed data
+    struct Index<'a>(&'a [(&'a str, Vec<i32>)]);
+
+    impl Serialize for Index<'_> {
+        fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+        where
+            S: SerializerTrait,
+        {
+            struct Ids<'a>(&'a [i32]);
+            impl Serialize for Ids<'_> {
+                fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+                where
+                    S: SerializerTrait,
+                {
+                    serializer.collect_array(self.0.iter().map(|id| id * 10))
+                }
+            }
+            serializer.collect_hash(self.0.iter().map(|(name, ids)| (Sym::new(name), Ids(ids))))
+        }
+    }
+
+    #[test]
+    fn collect_array_and_hash() {
+        let data = [("a", vec![1, 2]), ("b", vec![])];
+        let value = crate::to_value(Index(&data)).unwrap();
+        assert_eq!(value, marshal!({ :a => [10, 20], :b => [] }));
+    }
+
+    #[test]
+    fn collect_struct() {
+        let members = [(Sym::new("x"), 1), (Sym::new("y"), 2)];
+        let value = crate::ValueSerializer
+            .collect_struct(Sym::new("Point"), members)
+            .unwrap();
+
+        let fields: RbFields = [
+            ("x".into(), Value::Integer(1)),
+            ("y".into(), Value::Integer(2)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(value, Value::rb_struct("Point", fields));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod ivar_order {
+    use crate::{
+        from_bytes, marshal, ser::IvarOrder, to_bytes, Object, RbFields, Serialize, Serializer,
+        Symbol, Value,
+    };
+
+    const CORPUS: [(&str, &[u8]); 4] = [
+        ("Actors", include_bytes!("../examples/Actors.rxdata")),
+        ("Map001", include_bytes!("../examples/Map001.rxdata")),
+        ("Map223", include_bytes!("../examples/Map223.rxdata")),
+        ("System", include_bytes!("../examples/System.rxdata")),
+    ];
+
+    fn object() -> Object {
+        let fields: RbFields = [("@b", 1), ("@c", 2), ("@a", 3)]
+            .into_iter()
+            .map(|(k, v)| (k.into(), Value::Integer(v)))
+            .collect();
+        Object {
+            class: "Test".into(),
+            fields,
+        }
+    }
+
+    #[test]
+    fn byte_identical_corpus() {
+        // these files don't contain any object links, which `Value` doesn't keep
+        for (name, bytes) in [CORPUS[0], CORPUS[3]] {
+            let value: Value = from_bytes(bytes).unwrap();
+            assert!(to_bytes(&value).unwrap() == bytes, "{name} changed");
+        }
+    }
+
+    #[test]
+    fn corpus_is_stable() {
+        for (name, bytes) in CORPUS {
+            let value: Value = from_bytes(bytes).unwrap();
+            let first = to_bytes(&value).unwrap();
+            let reloaded: Value = from_bytes(&first).unwrap();
+            assert_eq!(value, reloaded, "{name} changed");
+            assert!(to_bytes(&reloaded).unwrap() == first, "{name} changed");
+        }
+    }
+
+    #[test]
+    fn insertion_order() {
+        let bytes = to_bytes(object()).unwrap();
+        let object: Object = from_bytes(&bytes).unwrap();
+        let names: Vec<_> = object.fields.keys().map(Symbol::as_str).collect();
+        assert_eq!(names, ["@b", "@c", "@a"]);
+
+        let sorted: Vec<_> = object
+            .sorted_fields()
+            .into_iter()
+            .map(|(k, v)| (k.as_str(), *v.as_integer().unwrap()))
+            .collect();
+        assert_eq!(sorted, [("@a", 3), ("@b", 1), ("@c", 2)]);
+    }
+
+    #[test]
+    fn sorted() {
+        let mut object = object();
+        object
+            .fields
+            .insert("@nested".into(), marshal!([:sym, { :key => :sym }]));
+
+        let mut serializer = Serializer::new();
+        serializer.set_ivar_order(IvarOrder::Sorted);
+        object.serialize(&mut serializer).unwrap();
+
+        let mut expected = object.clone();
+        expected
+            .fields
+            .sort_by(|a, _, b, _| a.as_str().cmp(b.as_str()));
+        assert_eq!(serializer.output, to_bytes(&expected).unwrap());
+    }
+}
+
+#[cfg(all(test, feature = "arbitrary"))]
+mod arbitrary_values {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use crate::{from_bytes, to_bytes, Value};
+
+    #[test]
+    fn round_trip() {
+        // a simple xorshift, so this test doesn't need a rng crate
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut data = vec![0; 4096];
+
+        for _ in 0..256 {
+            for byte in &mut data {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                *byte = state as u8;
+            }
+
+            let value = Value::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            let bytes = to_bytes(&value).unwrap();
+            let reloaded: Value = from_bytes(&bytes).unwrap();
+            assert!(to_bytes(&reloaded).unwrap() == bytes);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "proptest"))]
+mod proptest_strategies {
+    use proptest::prelude::*;
+
+    use crate::{
+        from_bytes,
+        testing::strategies::{marshal_data, value},
+        to_bytes, Value,
+    };
+
+    proptest! {
+        #[test]
+        fn values_round_trip(value in value()) {
+            let bytes = to_bytes(&value).unwrap();
+            let reloaded: Value = from_bytes(&bytes).unwrap();
+            prop_assert_eq!(reloaded, value);
+        }
+
+        #[test]
+        fn marshal_data_loads(data in marshal_data()) {
+            let value: Value = from_bytes(&data.bytes).unwrap();
+            prop_assert_eq!(value, data.value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod userdata_layouts {
+    use crate::{de::Kind, Userdata, UserdataExt};
+
+    fn table() -> Userdata {
+        Userdata::builder("Table")
+            .write_u32_slice(&[1, 3, 1, 1, 3])
+            .write_i16_slice(&[-1, 0, 1])
+            .build()
+    }
+
+    #[test]
+    fn round_trip() {
+        let table = table();
+        assert_eq!(table.class, "Table");
+        assert_eq!(table.data.len(), 5 * 4 + 3 * 2);
+
+        let mut reader = table.reader();
+        assert_eq!(reader.read_u32_le().unwrap(), 1);
+        assert_eq!(reader.read_u32_array::<4>().unwrap(), [3, 1, 1, 3]);
+        assert_eq!(reader.read_i16_slice(3).unwrap(), [-1, 0, 1]);
+        assert_eq!(reader.position(), table.data.len());
+        reader.finish().unwrap();
+    }
+
+    #[test]
+    fn too_short() {
+        let table = table();
+        let mut reader = table.reader();
+        reader.read_bytes(20).unwrap();
+
+        let err = reader.read_i16_slice(4).unwrap_err();
+        assert!(matches!(err.kind, Kind::InvalidLength(ref len) if len.len == 6));
+        // failed reads don't move the reader
+        assert_eq!(reader.remaining().len(), 6);
+
+        assert!(reader.read_i16_slice(usize::MAX).is_err());
+        assert!(reader.read_f64_le().is_err());
+    }
+
+    #[test]
+    fn trailing_bytes() {
+        let table = table();
+        let mut reader = table.reader();
+        reader.read_u32_array::<5>().unwrap();
+
+        let err = reader.finish().unwrap_err();
+        assert!(matches!(err.kind, Kind::TrailingBytes(6)));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod sets_and_open_structs {
+    use crate::{de::Kind, from_bytes, from_value, marshal, to_bytes, RbOpenStruct, RbSet, Value};
+
+    // Marshal.dump(Set[1, 2])
+    const SET: &[u8] = b"\x04\x08o:\x08Set\x06:\x0a@hash}\x07i\x06Ti\x07TF";
+    // Marshal.dump(OpenStruct.new(a: 1, b: nil))
+    const OPEN_STRUCT: &[u8] = b"\x04\x08U:\x0fOpenStruct{\x07:\x06ai\x06:\x06b0";
+
+    #[test]
+    fn set() {
+        let set: RbSet<i32> = from_bytes(SET).unwrap();
+        assert_eq!(set, [2, 1].into_iter().collect());
+        assert_eq!(to_bytes(&set).unwrap(), SET);
+
+        let value: Value = from_bytes(SET).unwrap();
+        assert_eq!(value.as_object().unwrap().class, "Set");
+        let set: RbSet = from_value(&value).unwrap();
+        assert_eq!(
+            set,
+            [Value::Integer(1), Value::Integer(2)].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn old_set() {
+        // older rubies use `Hash.new`, without a default
+        let value = marshal!(Set { @hash => { 1 => true } });
+        let set: RbSet<i32> = from_value(&value).unwrap();
+        assert_eq!(set, [1].into_iter().collect());
+    }
+
+    #[test]
+    fn wrong_class() {
+        let value = marshal!(Tset { @hash => {} });
+        let err = from_value::<RbSet>(&value).unwrap_err();
+        assert!(matches!(err.kind, Kind::WrongClass(_)));
+
+        let err = from_value::<RbSet>(&marshal!(Set {})).unwrap_err();
+        assert!(matches!(err.kind, Kind::MissingField(_)));
+    }
+
+    #[test]
+    fn open_struct() {
+        let open_struct: RbOpenStruct<Option<i32>> = from_bytes(OPEN_STRUCT).unwrap();
+        assert_eq!(open_struct.get("a"), Some(&Some(1)));
+        assert_eq!(open_struct.get("b"), Some(&None));
+        assert_eq!(to_bytes(&open_struct).unwrap(), OPEN_STRUCT);
+
+        let value: Value = from_bytes(OPEN_STRUCT).unwrap();
+        let open_struct: RbOpenStruct = from_value(&value).unwrap();
+        assert_eq!(open_struct.get("a"), Some(&Value::Integer(1)));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod value_ordering {
+    use std::{
+        collections::{hash_map::DefaultHasher, BTreeSet},
+        hash::{Hash, Hasher},
+    };
+
+    use crate::{marshal, RbHash, Value};
+
+    fn hash_of(value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn across_variants() {
+        let values = [
+            Value::Nil,
+            Value::Bool(false),
+            Value::Bool(true),
+            Value::Float(f64::NEG_INFINITY),
+            Value::Float(1.5),
+            Value::Float(f64::NAN),
+            Value::Integer(-1),
+            Value::Integer(3),
+            Value::from("a"),
+            Value::Symbol("a".into()),
+            Value::Array(vec![]),
+            Value::Hash(RbHash::new()),
+        ];
+        for pair in values.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} < {:?}", pair[0], pair[1]);
+        }
+
+        let set: BTreeSet<_> = values.iter().rev().cloned().collect();
+        assert!(set.into_iter().eq(values));
+    }
+
+    #[test]
+    fn consistent_with_eq() {
+        let pairs = [
+            (Value::Float(0.0), Value::Float(-0.0)),
+            (Value::Float(f64::NAN), Value::Float(-f64::NAN)),
+            (
+                marshal!({ 1 => 2, "a" => nil }),
+                marshal!({ "a" => nil, 1 => 2 }),
+            ),
+            (
+                marshal!(Foo { @a => 1, @b => 2 }),
+                marshal!(Foo { @b => 2, @a => 1 }),
+            ),
+        ];
+        for (a, b) in pairs {
+            assert_eq!(a, b);
+            assert_eq!(a.cmp(&b), std::cmp::Ordering::Equal);
+            assert_eq!(hash_of(&a), hash_of(&b));
+        }
+    }
+
+    #[test]
+    fn sort_keys() {
+        let mut hash = RbHash::new();
+        hash.insert(Value::Symbol("b".into()), Value::Nil);
+        hash.insert(Value::Integer(2), Value::Nil);
+        hash.insert(Value::Nil, Value::Nil);
+        hash.insert(Value::Integer(1), Value::Nil);
+        hash.sort_keys();
+        assert!(hash.keys().eq(&[
+            Value::Nil,
+            Value::Integer(1),
+            Value::Integer(2),
+            Value::Symbol("b".into()),
+        ]));
+    }
+
+    #[test]
+    fn normalize() {
+        let mut value = marshal!([{ 2 => { :b => 1, :a => 2 }, 1 => nil }]);
+        let original = value.clone();
+        value.normalize();
+        assert_eq!(value, original);
+
+        let outer = value.as_array().unwrap()[0].inner_hash().unwrap();
+        assert!(outer.keys().eq(&[Value::Integer(1), Value::Integer(2)]));
+        let inner = outer[&Value::Integer(2)].inner_hash().unwrap();
+        assert!(inner
+            .keys()
+            .eq(&[Value::Symbol("a".into()), Value::Symbol("b".into())]));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod lenient_mode {
+    use crate::{de::Kind, from_bytes, from_bytes_lenient, marshal, Deserializer, Value};
+
+    #[test]
+    fn broken_ivar() {
+        // Foo with @a linking to an object that doesn't exist
+        let bytes = b"\x04\x08o:\x08Foo\x07:\x07@a@\x06:\x07@bi\x06";
+        assert!(from_bytes::<Value>(bytes).is_err());
+
+        let (value, recovered): (Value, _) = from_bytes_lenient(bytes).unwrap();
+        assert_eq!(value, marshal!(Foo { @a => nil, @b => 1 }));
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].position, 13);
+        assert!(matches!(
+            recovered[0].error.kind,
+            Kind::UnresolvedObjectlink(1)
+        ));
+    }
+
+    #[test]
+    fn unknown_tag_ends_containers() {
+        // [[1, <garbage>, 2], 3]
+        let bytes = b"\x04\x08[\x07[\x08i\x06\xffi\x07i\x08";
+        let (value, recovered): (Value, _) = from_bytes_lenient(bytes).unwrap();
+        assert_eq!(value, marshal!([[1, nil]]));
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].position, 8);
+        assert!(matches!(recovered[0].error.kind, Kind::WrongTag(0xFF)));
+    }
+
+    #[test]
+    fn truncated_file() {
+        let bytes = include_bytes!("../examples/Actors.rxdata");
+        let truncated = &bytes[..bytes.len() / 2];
+        assert!(from_bytes::<Value>(truncated).is_err());
+
+        let mut deserializer = Deserializer::new(truncated).unwrap();
+        deserializer.set_lenient(true);
+        let value: Value = deserializer.deserialize_value().unwrap();
+        assert_eq!(deserializer.recovered().len(), 1);
+        assert!(matches!(deserializer.recovered()[0].error.kind, Kind::Eof));
+        assert_eq!(deserializer.finish(), 0);
+
+        // everything before the cut is still there
+        let full: Value = from_bytes(bytes).unwrap();
+        let (full, salvaged) = (full.as_array().unwrap(), value.as_array().unwrap());
+        assert!(salvaged.len() > 1);
+        let complete = salvaged.len() - 1;
+        assert_eq!(full[..complete], salvaged[..complete]);
+    }
+
+    #[test]
+    fn options() {
+        let bytes = b"\x04\x08[\x07i\x06\xff";
+        let (value, recovered): (Vec<Option<i32>>, _) = from_bytes_lenient(bytes).unwrap();
+        assert_eq!(value, [Some(1), None]);
+        assert_eq!(recovered.len(), 1);
+    }
+
+    #[test]
+    fn depth_limit_is_fatal() {
+        let bytes = b"\x04\x08[\x06[\x06[\x06i\x06";
+        for track_path in [false, true] {
+            let mut deserializer = Deserializer::new(bytes).unwrap();
+            deserializer.set_lenient(true);
+            deserializer.set_track_path(track_path);
+            deserializer.set_depth_limit(2);
+            let err = deserializer.deserialize_value::<Value>().unwrap_err();
+            assert!(matches!(err.kind, Kind::DepthLimitExceeded(2)));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod patching {
+    use crate::{
+        de::PathSegment,
+        from_bytes, marshal,
+        patch::{patch, Error},
+        to_bytes, to_value, Value,
+    };
+
+    #[test]
+    fn same_as_editing_a_value() {
+        let bytes = include_bytes!("../examples/Actors.rxdata");
+        let path = [PathSegment::Index(1), PathSegment::Ivar("@name".into())];
+        let patched = patch(bytes, &path, "Bob").unwrap();
+
+        let mut expected: Value = from_bytes(bytes).unwrap();
+        expected.as_array_mut().unwrap()[1]
+            .as_object_mut()
+            .unwrap()
+            .fields
+            .insert("@name".into(), to_value("Bob").unwrap());
+        let patched: Value = from_bytes(&patched).unwrap();
+        assert_eq!(patched, expected);
+    }
+
+    #[test]
+    fn data_with_links() {
+        let bytes = include_bytes!("../examples/Map001.rxdata");
+        let patched = patch(bytes, &[PathSegment::Ivar("@width".into())], &99).unwrap();
+
+        let mut expected: Value = from_bytes(bytes).unwrap();
+        expected
+            .as_object_mut()
+            .unwrap()
+            .fields
+            .insert("@width".into(), Value::Integer(99));
+        let patched: Value = from_bytes(&patched).unwrap();
+        assert_eq!(patched, expected);
+    }
+
+    #[test]
+    fn links_to_the_replaced_value() {
+        // ["a", <link to "a">]
+        let bytes = b"\x04\x08[\x07\"\x06a@\x06";
+        let patched = patch(bytes, &[PathSegment::Index(0)], &vec![1, 2]).unwrap();
+        assert_eq!(patched, b"\x04\x08[\x07[\x07i\x06i\x07@\x06");
+        let value: Value = from_bytes(&patched).unwrap();
+        assert_eq!(value, marshal!([[1, 2], [1, 2]]));
+    }
+
+    #[test]
+    fn renumbers_links() {
+        // ["a", "b", <link to "b">]
+        let bytes = b"\x04\x08[\x08\"\x06a\"\x06b@\x07";
+        let patched = patch(bytes, &[PathSegment::Index(0)], &5).unwrap();
+        assert_eq!(patched, b"\x04\x08[\x08i\x0a\"\x06b@\x06");
+    }
+
+    #[test]
+    fn dangling_links() {
+        // [["a"], <link to "a">]
+        let bytes = b"\x04\x08[\x07[\x06\"\x06a@\x07";
+        let err = patch(bytes, &[PathSegment::Index(0)], &()).unwrap_err();
+        assert!(matches!(err, Error::DanglingLink(2)));
+    }
+
+    #[test]
+    fn symbols_defined_in_the_replaced_value() {
+        let bytes = to_bytes(marshal!([Foo { @a => 1 }, Foo { @a => 2 }])).unwrap();
+        let patched = patch(&bytes, &[PathSegment::Index(0)], &1).unwrap();
+        let value: Value = from_bytes(&patched).unwrap();
+        assert_eq!(value, marshal!([1, Foo { @a => 2 }]));
+    }
+
+    #[test]
+    fn hash_entries() {
+        let bytes = to_bytes(marshal!({ :a => 1, :b => [2] })).unwrap();
+        let path = [PathSegment::Entry(1), PathSegment::Index(0)];
+        let patched = patch(&bytes, &path, &3).unwrap();
+        let value: Value = from_bytes(&patched).unwrap();
+        assert_eq!(value, marshal!({ :a => 1, :b => [3] }));
+    }
+
+    #[test]
+    fn not_found() {
+        let bytes = to_bytes(marshal!([1, 2])).unwrap();
+        let err = patch(&bytes, &[PathSegment::Index(2)], &3).unwrap_err();
+        assert!(matches!(err, Error::NotFound(_)));
+        assert_eq!(err.to_string(), "there is no value at [2]");
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod class_mapping {
+    use std::{collections::HashMap, sync::Arc};
+
+    use crate::{
+        from_bytes, to_bytes, Deserializer, RbStruct, Serialize, Serializer, Symbol, Value,
+    };
+
+    fn mapping(pairs: &[(&str, &str)]) -> HashMap<Symbol, Symbol> {
+        pairs
+            .iter()
+            .map(|&(from, to)| (from.into(), to.into()))
+            .collect()
+    }
+
+    #[test]
+    fn rename_namespace() {
+        let bytes = include_bytes!("../examples/Actors.rxdata");
+        let to_game = mapping(&[("RPG::Actor", "Game::Actor"), ("Table", "Game::Table")]);
+
+        let mut deserializer = Deserializer::new(bytes).unwrap();
+        deserializer.set_class_mapper(&to_game);
+        let actors: Value = deserializer.deserialize_value().unwrap();
+        for actor in actors.as_array().unwrap().iter().skip(1) {
+            let actor = actor.as_object().unwrap();
+            assert_eq!(actor.class, "Game::Actor");
+            let parameters = actor.fields["@parameters"].as_userdata().unwrap();
+            assert_eq!(parameters.class, "Game::Table");
+        }
+
+        let to_rpg = mapping(&[("Game::Actor", "RPG::Actor"), ("Game::Table", "Table")]);
+        let mut serializer = Serializer::new();
+        serializer.set_class_mapper(Some(Arc::new(to_rpg)));
+        actors.serialize(&mut serializer).unwrap();
+
+        let original: Value = from_bytes(bytes).unwrap();
+        assert_eq!(serializer.output, to_bytes(original).unwrap());
+    }
+
+    fn every_kind_of_class(name: &str) -> Value {
+        Value::Array(vec![
+            Value::RbStruct(RbStruct {
+                class: name.into(),
+                fields: [("x".into(), Value::Integer(1))].into_iter().collect(),
+            }),
+            Value::UserClass {
+                class: name.into(),
+                value: Box::new(Value::Array(vec![])),
+            },
+            Value::Extended {
+                module: name.into(),
+                value: Box::new(Value::Nil),
+            },
+            Value::Class(name.into()),
+            Value::Module(name.into()),
+        ])
+    }
+
+    #[test]
+    fn structs_classes_and_modules() {
+        let mut serializer = Serializer::new();
+        serializer.set_class_mapper(Some(Arc::new(mapping(&[("A", "B")]))));
+        every_kind_of_class("A").serialize(&mut serializer).unwrap();
+
+        let renamed: Value = from_bytes(&serializer.output).unwrap();
+        assert_eq!(renamed, every_kind_of_class("B"));
+
+        let to_a = mapping(&[("B", "A")]);
+        let mut deserializer = Deserializer::new(&serializer.output).unwrap();
+        deserializer.set_class_mapper(&to_a);
+        let value: Value = deserializer.deserialize_value().unwrap();
+        assert_eq!(value, every_kind_of_class("A"));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod typed_wrappers {
+    use crate::{from_bytes, to_bytes, Extended, Instance, RbString, UserMarshal, Value};
+
+    #[test]
+    fn extended() {
+        let untyped = Value::extended("Comparable", vec![Value::Integer(1), Value::Integer(2)]);
+        let bytes = to_bytes(&untyped).unwrap();
+
+        let typed: Extended<Vec<i32>> = from_bytes(&bytes).unwrap();
+        assert_eq!(typed, Extended::new("Comparable", vec![1, 2]));
+        assert_eq!(to_bytes(&typed).unwrap(), bytes);
+    }
+
+    #[test]
+    fn extended_twice() {
+        let untyped = Value::extended("A", Value::extended("B", Value::Nil));
+        let bytes = to_bytes(&untyped).unwrap();
+
+        let typed: Extended<Extended<Option<i32>>> = from_bytes(&bytes).unwrap();
+        assert_eq!(typed, Extended::new("A", Extended::new("B", None)));
+        assert_eq!(to_bytes(&typed).unwrap(), bytes);
+    }
+
+    #[test]
+    fn extended_string_with_ivars() {
+        let mut typed = Instance::new(Extended::new("Mod", RbString::from("hello")));
+        typed.fields.insert("E".into(), Value::Bool(true));
+        let bytes = to_bytes(&typed).unwrap();
+
+        let value: Value = from_bytes(&bytes).unwrap();
+        let instance = value.as_instance().unwrap();
+        assert_eq!(
+            *instance.value,
+            Value::extended("Mod", Value::String("hello".into()))
+        );
+
+        let typed2: Instance<Extended<RbString>> = from_bytes(&bytes).unwrap();
+        assert_eq!(typed2, typed);
+    }
+
+    #[test]
+    fn user_marshal() {
+        let untyped = Value::user_marshal("Point", vec![Value::Integer(3), Value::Integer(4)]);
+        let bytes = to_bytes(&untyped).unwrap();
+
+        let typed: UserMarshal<(i32, i32)> = from_bytes(&bytes).unwrap();
+        assert_eq!(typed, UserMarshal::new("Point", (3, 4)));
+        assert_eq!(to_bytes(&typed).unwrap(), bytes);
+    }
+
+    #[test]
+    fn wrong_kind_of_value() {
+        let bytes = to_bytes(vec![1, 2]).unwrap();
+        assert!(from_bytes::<Extended<Vec<i32>>>(&bytes).is_err());
+        assert!(from_bytes::<UserMarshal<Vec<i32>>>(&bytes).is_err());
+
+        let bytes = to_bytes(Value::user_marshal("Point", Value::Nil)).unwrap();
+        assert!(from_bytes::<Extended<Value>>(&bytes).is_err());
+        let bytes = to_bytes(Value::extended("Point", Value::Nil)).unwrap();
+        assert!(from_bytes::<UserMarshal<Value>>(&bytes).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod string_keys {
+    use std::collections::{BTreeMap, HashMap};
+
+    use crate::{from_bytes, to_bytes, Instance, RbHash, RbString, Symbol, SymbolOrString, Value};
+
+    fn mixed_keys() -> Value {
+        let mut hash = RbHash::new();
+        hash.insert(Value::Symbol("a".into()), Value::Integer(1));
+        hash.insert(Value::String("b".into()), Value::Integer(2));
+        let encoded = Instance::<RbString>::from("c");
+        hash.insert(
+            Value::Instance(Instance {
+                value: Box::new(Value::String(encoded.value)),
+                fields: encoded.fields,
+            }),
+            Value::Integer(3),
+        );
+        Value::Hash(hash)
+    }
+
+    #[test]
+    fn symbol_keys_as_strings() {
+        let mut hash = RbHash::new();
+        hash.insert(Value::Symbol("volume".into()), Value::Integer(80));
+        let bytes = to_bytes(Value::Hash(hash)).unwrap();
+
+        let map: HashMap<String, i32> = from_bytes(&bytes).unwrap();
+        assert_eq!(map["volume"], 80);
+        let map: BTreeMap<Box<str>, i32> = from_bytes(&bytes).unwrap();
+        assert_eq!(map["volume"], 80);
+    }
+
+    #[test]
+    fn mixed_keys_as_strings() {
+        let bytes = to_bytes(mixed_keys()).unwrap();
+        let map: HashMap<String, i32> = from_bytes(&bytes).unwrap();
+        let expected = [("a", 1), ("b", 2), ("c", 3)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        assert_eq!(map, expected);
+    }
+
+    #[test]
+    fn symbol_or_string_keys() {
+        let bytes = to_bytes(mixed_keys()).unwrap();
+        let map: BTreeMap<SymbolOrString, i32> = from_bytes(&bytes).unwrap();
+        assert_eq!(map[&SymbolOrString::Symbol(Symbol::from("a"))], 1);
+        assert_eq!(map[&SymbolOrString::String("b".to_string())], 2);
+        assert_eq!(map[&SymbolOrString::String("c".to_string())], 3);
+        assert!(!map.contains_key(&SymbolOrString::String("a".to_string())));
+
+        // the key kinds survive a round trip (rust strings are always written with an encoding)
+        let value: Value = from_bytes(&to_bytes(&map).unwrap()).unwrap();
+        let keys: Vec<_> = value.as_hash().unwrap().keys().collect();
+        assert!(keys[0].is_symbol());
+        assert!(keys[1].is_instance() && keys[2].is_instance());
+    }
+
+    #[test]
+    fn chars_from_symbols() {
+        let bytes = to_bytes(Symbol::from("x")).unwrap();
+        assert_eq!(from_bytes::<char>(&bytes).unwrap(), 'x');
+        let bytes = to_bytes(Symbol::from("xy")).unwrap();
+        assert!(from_bytes::<char>(&bytes).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod options {
+    use std::{collections::HashMap, sync::Arc};
+
+    use crate::{
+        de::Kind, from_bytes_with, marshal, ser::IvarOrder, to_bytes, to_bytes_with, DeOptions,
+        SerOptions, Symbol, Value,
+    };
+
+    #[test]
+    fn defaults_match_from_bytes() {
+        let value = marshal!(Foo { @b => [1, :two], @a => "three" });
+        let bytes = to_bytes(&value).unwrap();
+        assert_eq!(to_bytes_with(&value, &SerOptions::new()).unwrap(), bytes);
+        assert_eq!(
+            from_bytes_with::<Value>(&bytes, &DeOptions::new()).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn deserializer_options() {
+        let mapper: HashMap<Symbol, Symbol> = [("Foo".into(), "Bar".into())].into();
+        let options = DeOptions::new()
+            .class_mapper(Some(&mapper))
+            .track_path(true);
+
+        let bytes = to_bytes(marshal!(Foo { @a => 1 })).unwrap();
+        let value: Value = from_bytes_with(&bytes, &options).unwrap();
+        assert_eq!(value, marshal!(Bar { @a => 1 }));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(from_bytes_with::<Value>(&trailing, &options).is_ok());
+        let err =
+            from_bytes_with::<Value>(&trailing, &options.deny_trailing_bytes(true)).unwrap_err();
+        assert!(matches!(err.kind, Kind::TrailingBytes(1)));
+
+        let bytes = b"\x04\x08[\x07i\x06@\x06";
+        assert!(from_bytes_with::<Value>(bytes, &DeOptions::new()).is_err());
+        let lenient = DeOptions::new().lenient(true);
+        let value: Value = from_bytes_with(bytes, &lenient).unwrap();
+        assert_eq!(value, marshal!([1, nil]));
+
+        let old = b"\x04\x06i\x06";
+        assert!(from_bytes_with::<i32>(old, &DeOptions::new()).is_err());
+        let options = DeOptions::new().versions([4, 6]..=[4, 8]);
+        assert_eq!(from_bytes_with::<i32>(old, &options).unwrap(), 1);
+    }
+
+    #[test]
+    fn serializer_options() {
+        let mapper: HashMap<Symbol, Symbol> = [("Foo".into(), "Bar".into())].into();
+        let options = SerOptions::new()
+            .ivar_order(IvarOrder::Sorted)
+            .class_mapper(Some(Arc::new(mapper)))
+            .version([4, 6]);
+
+        let bytes = to_bytes_with(marshal!(Foo { @b => 1, @a => 2 }), &options).unwrap();
+        assert_eq!(&bytes[..2], [4, 6]);
+        let value: Value =
+            from_bytes_with(&bytes, &DeOptions::new().versions([4, 6]..=[4, 6])).unwrap();
+        let object = value.into_object().unwrap();
+        assert_eq!(object.class, "Bar");
+        assert_eq!(object.fields.keys().collect::<Vec<_>>(), ["@a", "@b"]);
+
+        let options = SerOptions::new().output_limit(Some(4));
+        assert!(to_bytes_with(vec![1, 2, 3], &options).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod owned_values {
+    use crate::{de::Kind, from_value_owned, marshal, to_value, Deserialize, Value, ValueExt};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    fn temporary() -> Value {
+        marshal!(Point { @x => 1, @y => 2 })
+    }
+
+    #[test]
+    fn temporaries() {
+        let point: Point = from_value_owned(temporary()).unwrap();
+        assert_eq!(point, Point { x: 1, y: 2 });
+
+        let points: Vec<Point> = to_value(vec![temporary(), temporary()])
+            .unwrap()
+            .into_deserializer()
+            .deserialize()
+            .unwrap();
+        assert_eq!(points.len(), 2);
+    }
+
+    #[test]
+    fn borrowing_and_limits() {
+        let mut deserializer = marshal!([[[1]]]).into_deserializer();
+        let value = Value::deserialize(deserializer.borrow()).unwrap();
+        assert_eq!(value, marshal!([[[1]]]));
+
+        deserializer.set_depth_limit(2);
+        let err = deserializer.clone().deserialize::<Value>().unwrap_err();
+        assert!(matches!(err.kind, Kind::DepthLimitExceeded(2)));
+        assert_eq!(deserializer.into_inner(), value);
+    }
+}
+
+#[cfg(test)]
+mod owned_deserialization {
+    use crate::{from_reader, DeserializeOwned, RbString, Symbol};
+
+    fn load<T: DeserializeOwned>(scratch: &mut Vec<u8>, value: impl crate::Serialize) -> T {
+        // the buffer is reused for every value, so nothing can borrow from it
+        scratch.clear();
+        scratch.extend(crate::to_bytes(value).unwrap());
+        crate::from_bytes(scratch).unwrap()
+    }
+
+    #[test]
+    fn reused_buffer() {
+        let mut scratch = Vec::new();
+        let string: String = load(&mut scratch, Symbol::from("sym"));
+        let symbol: Symbol = load(&mut scratch, Symbol::from("sym"));
+        let bytes: RbString = load(&mut scratch, crate::SerializeByteString(b"\xFF"));
+        let strings: Vec<String> = load(&mut scratch, ["a", "b"]);
+
+        assert_eq!(string, "sym");
+        assert_eq!(symbol, "sym");
+        assert_eq!(bytes.as_slice(), b"\xFF");
+        assert_eq!(strings, ["a", "b"]);
+    }
+
+    #[test]
+    fn reader() {
+        let bytes = crate::to_bytes(vec![Symbol::from("a")]).unwrap();
+        let symbols: Vec<Symbol> = from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(symbols, [Symbol::from("a")]);
+
+        assert!(from_reader::<String, _>(&bytes[..3]).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod shared_strings {
+    use std::{rc::Rc, sync::Arc};
+
+    use crate::{
+        de::{
+            seed::{MapSeed, StrInterner},
+            DeserializeSeed,
+        },
+        from_bytes, to_bytes, Deserializer, SerializeByteString, Sym, Symbol,
+    };
+
+    #[test]
+    fn owned_slices() {
+        let bytes = to_bytes("text").unwrap();
+        assert_eq!(&*from_bytes::<Box<str>>(&bytes).unwrap(), "text");
+        assert_eq!(&*from_bytes::<Rc<str>>(&bytes).unwrap(), "text");
+        assert_eq!(&*from_bytes::<Arc<str>>(&bytes).unwrap(), "text");
+
+        let bytes = to_bytes(SerializeByteString(b"\x00\xFF")).unwrap();
+        assert_eq!(&*from_bytes::<Box<[u8]>>(&bytes).unwrap(), b"\x00\xFF");
+        assert_eq!(&*from_bytes::<Rc<[u8]>>(&bytes).unwrap(), b"\x00\xFF");
+        assert_eq!(&*from_bytes::<Arc<[u8]>>(&bytes).unwrap(), b"\x00\xFF");
+
+        let bytes = to_bytes(Symbol::from("sym")).unwrap();
+        assert_eq!(&*from_bytes::<Box<Sym>>(&bytes).unwrap(), "sym");
+        assert_eq!(&*from_bytes::<Arc<str>>(&bytes).unwrap(), "sym");
+    }
+
+    #[test]
+    fn interning() {
+        let mut hash = crate::RbHash::new();
+        for (i, class) in ["Ghost", "Ghost", "Slime"].into_iter().enumerate() {
+            hash.insert(
+                crate::Value::Integer(i as i64),
+                crate::Value::String(class.into()),
+            );
+        }
+        let bytes = to_bytes(crate::Value::Hash(hash)).unwrap();
+
+        let interner = StrInterner::new();
+        let mut deserializer = Deserializer::new(&bytes).unwrap();
+        let enemies = MapSeed::new(std::marker::PhantomData::<i32>, &interner)
+            .deserialize(&mut deserializer)
+            .unwrap();
+        assert!(Arc::ptr_eq(&enemies[&0], &enemies[&1]));
+        assert!(!Arc::ptr_eq(&enemies[&0], &enemies[&2]));
+        assert_eq!(interner.len(), 2);
+        assert!(Arc::ptr_eq(&interner.intern("Slime"), &enemies[&2]));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod shared_cycles {
+    use std::{
+        cell::RefCell,
+        rc::{Rc, Weak},
+        sync::Arc,
+    };
+
+    use crate::{marshal, ser::Kind, Serialize};
+
+    #[derive(Serialize, Default)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Node {
+        name: String,
+        children: Vec<Rc<RefCell<Node>>>,
+        parent: Option<Weak<RefCell<Node>>>,
+    }
+
+    #[test]
+    fn shared_values_serialize_every_time() {
+        let shared = Arc::new(5);
+        let bytes = crate::to_bytes([shared.clone(), shared]).unwrap();
+        assert_eq!(bytes, crate::to_bytes([5, 5]).unwrap());
+
+        let leaf = Rc::new(RefCell::new(Node {
+            name: "leaf".to_string(),
+            ..Default::default()
+        }));
+        let root = Node {
+            name: "root".to_string(),
+            children: vec![leaf.clone(), leaf],
+            parent: None,
+        };
+        let value = crate::to_value(&root).unwrap();
+        let leaf = marshal!(Node { @name => "leaf", @children => [], @parent => nil });
+        assert_eq!(
+            value,
+            marshal!(Node { @name => "root", @children => [leaf.clone(), leaf], @parent => nil })
+        );
+    }
+
+    #[test]
+    fn cycles_error() {
+        let root = Rc::new(RefCell::new(Node {
+            name: "root".to_string(),
+            ..Default::default()
+        }));
+        let child = Rc::new(RefCell::new(Node {
+            name: "child".to_string(),
+            children: vec![],
+            parent: Some(Rc::downgrade(&root)),
+        }));
+        root.borrow_mut().children.push(child);
+
+        let err = crate::to_bytes(&root).unwrap_err();
+        assert!(matches!(err.kind, Kind::CircularReference));
+        let err = crate::to_value(&root).unwrap_err();
+        assert!(matches!(err.kind, Kind::CircularReference));
+
+        // nothing is left marked as being serialized after an error
+        let child = root.borrow_mut().children.pop().unwrap();
+        assert!(crate::to_bytes(&child).is_ok());
+        assert!(crate::to_bytes(&root).is_ok());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod coerce {
+    use crate::{marshal, Deserialize, ValueDeserializer};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate")]
+    struct Settings {
+        volume: u8,
+        muted: bool,
+        scale: f32,
+        name: String,
+    }
+
+    #[test]
+    fn primitives() {
+        let value =
+            marshal!(Settings { @volume => " 50 ", @muted => 0, @scale => "1.5", @name => :main });
+        assert!(crate::from_value::<Settings>(&value).is_err());
+        let settings: Settings = crate::from_value_coerced(&value).unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                volume: 50,
+                muted: false,
+                scale: 1.5,
+                name: "main".to_string(),
+            }
+        );
+
+        let value = marshal!([true, "false", 7, "x"]);
+        let coerced: (i32, bool, bool, String) = crate::from_value_coerced(&value).unwrap();
+        assert_eq!(coerced, (1, false, true, "x".to_string()));
+    }
+
+    #[test]
+    fn nested_and_optional() {
+        let value = marshal!({ "a" => ["1", "2"], "b" => [nil, "3"] });
+        let coerced: std::collections::HashMap<String, Vec<Option<i32>>> =
+            crate::from_value_coerced(&value).unwrap();
+        assert_eq!(coerced["a"], [Some(1), Some(2)]);
+        assert_eq!(coerced["b"], [None, Some(3)]);
+    }
+
+    #[test]
+    fn failed_conversions_error_as_usual() {
+        let value = marshal!("five");
+        let coerced = crate::from_value_coerced::<i32>(&value).unwrap_err();
+        let plain = crate::from_value::<i32>(&value).unwrap_err();
+        assert_eq!(coerced.to_string(), plain.to_string());
+
+        // strings aren't coerced into anything that isn't a primitive
+        assert!(crate::from_value_coerced::<Vec<i32>>(&marshal!("[1]")).is_err());
+    }
+
+    #[test]
+    fn off_by_default() {
+        let value = marshal!(1);
+        let mut deserializer = ValueDeserializer::new(&value);
+        assert!(!deserializer.coerce());
+        assert!(bool::deserialize(deserializer).is_err());
+
+        deserializer.set_coerce(true);
+        assert!(deserializer.coerce());
+        assert!(bool::deserialize(deserializer).unwrap());
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod derive_attributes {
+    use crate::{
+        marshal, DeError, Deserialize, DeserializerTrait, SerError, Serialize, SerializerTrait,
+        Value,
+    };
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", class = "RPG::Actor", enforce_class)]
+    struct Actor {
+        #[marshal(rename = "nickname")]
+        name: String,
+        #[marshal(default = "default_level")]
+        level: i32,
+        #[marshal(default)]
+        skills: Vec<i32>,
+        #[marshal(skip)]
+        cache: Option<i32>,
+        #[marshal(byte_string)]
+        face_name: Box<[u8]>,
+        #[marshal(with = "hex")]
+        color: u32,
+    }
+
+    fn default_level() -> i32 {
+        1
+    }
+
+    mod hex {
+        use super::{
+            DeError, Deserialize, DeserializerTrait, SerError, Serialize, SerializerTrait,
+        };
+
+        #[allow(clippy::trivially_copy_pass_by_ref)]
+        pub fn serialize_with<S>(color: &u32, serializer: S) -> Result<S::Ok, SerError>
+        where
+            S: SerializerTrait,
+        {
+            format!("{color:06x}").serialize(serializer)
+        }
+
+        pub fn deserialize_with<'de, D>(deserializer: D) -> Result<u32, DeError>
+        where
+            D: DeserializerTrait<'de>,
+        {
+            let hex = String::deserialize(deserializer)?;
+            u32::from_str_radix(&hex, 16).map_err(DeError::custom)
+        }
+    }
+
+    fn actor() -> Actor {
+        Actor {
+            name: "Aluxes".to_string(),
+            level: 12,
+            skills: vec![1, 2],
+            cache: None,
+            face_name: b"001-Fighter01".as_slice().into(),
+            color: 0x00ff_8800,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let actor = actor();
+        let bytes = crate::to_bytes(&actor).unwrap();
+        let deserialized: Actor = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized, actor);
+
+        let value = crate::to_value(&actor).unwrap();
+        let deserialized: Actor = crate::from_value(&value).unwrap();
+        assert_eq!(deserialized, actor);
+    }
+
+    #[test]
+    fn rename() {
+        let value = crate::to_value(actor()).unwrap().into_object().unwrap();
+        assert_eq!(value.fields["@nickname"], marshal!("Aluxes"));
+        assert!(!value.fields.contains_key("@name"));
+    }
+
+    #[test]
+    fn with() {
+        let value = crate::to_value(actor()).unwrap().into_object().unwrap();
+        assert_eq!(value.fields["@color"], marshal!("ff8800"));
+
+        let value = marshal!(RPG::Actor {
+            @nickname => "Basil", @face_name => "", @color => "zz"
+        });
+        assert!(crate::from_value::<Actor>(&value).is_err());
+    }
+
+    #[test]
+    fn skip() {
+        let mut actor = actor();
+        actor.cache = Some(5);
+        let value = crate::to_value(&actor).unwrap();
+        assert!(!value.as_object().unwrap().fields.contains_key("@cache"));
+
+        let deserialized: Actor = crate::from_value(&value).unwrap();
+        assert_eq!(deserialized.cache, None);
+    }
+
+    #[test]
+    fn default() {
+        let value = marshal!(RPG::Actor {
+            @nickname => "Basil", @face_name => "", @color => "0"
+        });
+        let actor: Actor = crate::from_value(&value).unwrap();
+        assert_eq!(actor.level, 1);
+        assert!(actor.skills.is_empty());
+    }
+
+    #[test]
+    fn enforce_class() {
+        let mut object = crate::to_value(actor()).unwrap().into_object().unwrap();
+        assert_eq!(object.class, "RPG::Actor");
+
+        object.class = "RPG::Enemy".into();
+        let err = crate::from_value::<Actor>(&Value::Object(object)).unwrap_err();
+        assert!(matches!(err.kind, crate::de::Kind::WrongClass(_)));
+    }
+
+    #[test]
+    fn byte_string() {
+        let mut actor = actor();
+        // not valid UTF-8, which a byte string doesn't need to be
+        actor.face_name = [0xff, 0xfe].as_slice().into();
+        let value = crate::to_value(&actor).unwrap();
+        let face_name = &value.as_object().unwrap().fields["@face_name"];
+        assert_eq!(face_name.as_string().unwrap().as_slice(), [0xff, 0xfe]);
+
+        let deserialized: Actor = crate::from_value(&value).unwrap();
+        assert_eq!(*deserialized.face_name, [0xff, 0xfe]);
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod class_from {
+    use crate::{Deserialize, Serialize, Symbol};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", class_from = "Self::class_name")]
+    struct Script {
+        #[marshal(skip)]
+        class: Symbol,
+        name: String,
+    }
+
+    impl Script {
+        fn class_name(&self) -> &Symbol {
+            &self.class
+        }
+    }
+
+    #[derive(Serialize, Debug)]
+    #[marshal(alox_crate_path = "crate", class_from = "Tagged::class_name")]
+    struct Tagged(Vec<u8>);
+
+    impl Tagged {
+        fn class_name(&self) -> String {
+            format!("Tagged{}", self.0.len())
+        }
+    }
+
+    #[test]
+    fn objects() {
+        let script = Script {
+            class: "Game_Interpreter".into(),
+            name: "Main".to_string(),
+        };
+        let object = crate::to_value(&script).unwrap().into_object().unwrap();
+        assert_eq!(object.class, "Game_Interpreter");
+
+        let bytes = crate::to_bytes(&script).unwrap();
+        let deserialized: Script = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized.name, "Main");
+    }
+
+    #[test]
+    fn user_classes() {
+        let value = crate::to_value(Tagged(vec![1, 2])).unwrap();
+        assert!(matches!(value, crate::Value::UserClass { class, .. } if class == "Tagged2"));
+    }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod ruby_structs {
+    use crate::{de::Kind, Deserialize, RbFields, RbStruct, Serialize, Value};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[marshal(alox_crate_path = "crate", class = "Point", ruby_struct)]
+    struct Point {
+        x: i32,
+        #[marshal(rename = "height")]
+        y: i32,
+        #[marshal(skip)]
+        cached: Option<i32>,
+    }
+
+    fn rb_struct(members: &[(&str, i32)]) -> Value {
+        let fields = members
+            .iter()
+            .map(|&(name, value)| (name.into(), value.into()))
+            .collect::<RbFields>();
+        Value::RbStruct(RbStruct {
+            class: "Point".into(),
+            fields,
+        })
+    }
+
+    #[test]
+    fn round_trip() {
+        let point = Point {
+            x: 1,
+            y: 2,
+            cached: Some(3),
+        };
+        let value = crate::to_value(&point).unwrap();
+        assert_eq!(value, rb_struct(&[("x", 1), ("height", 2)]));
+
+        let bytes = crate::to_bytes(&point).unwrap();
+        let deserialized: Point = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            deserialized,
+            Point {
+                cached: None,
+                ..point
+            }
+        );
+    }
+
+    #[test]
+    fn members_must_match() {
+        let incompatible = |value: &Value| {
+            let err = crate::from_value::<Point>(value).unwrap_err();
+            let Kind::IncompatibleStruct(details) = err.kind else {
+                unreachable!("expected an incompatible struct error, got {err}")
+            };
+            details
+        };
+
+        let details = incompatible(&rb_struct(&[("height", 2), ("x", 1)]));
+        assert_eq!(details.index, 0);
+        assert_eq!(details.expected.unwrap(), "x");
+        assert_eq!(details.found.unwrap(), "height");
 
-        let obj: crate::Value = crate::from_bytes(bytes).unwrap();
+        let details = incompatible(&rb_struct(&[("x", 1)]));
+        assert_eq!(details.index, 1);
+        assert_eq!(details.expected.unwrap(), "height");
+        assert!(details.found.is_none());
 
-        let test: Test<'_> = crate::Deserialize::deserialize(&obj).unwrap();
+        let details = incompatible(&rb_struct(&[("x", 1), ("height", 2), ("z", 3)]));
+        assert_eq!(details.index, 2);
+        assert!(details.expected.is_none());
+        assert_eq!(details.found.unwrap(), "z");
 
+        let bytes = crate::to_bytes(rb_struct(&[("y", 1), ("x", 2)])).unwrap();
+        let err = crate::from_bytes::<Point>(&bytes).unwrap_err();
         assert_eq!(
-            test,
-            Test {
-                field1: true,
-                field2: "hello there"
-            }
+            err.to_string(),
+            "struct `:Point` is not compatible, expected member 0 to be `:x`, found `:y`"
         );
     }
+
+    #[test]
+    fn objects_are_still_accepted() {
+        let value = crate::marshal!(Point { @x => 1, @height => 2 });
+        let point: Point = crate::from_value(&value).unwrap();
+        assert_eq!(point.y, 2);
+    }
 }
 
-#[cfg(test)]
-mod round_trip {
-    use crate::{from_bytes, to_bytes, Instance, RbFields, RbHash, RbStruct, Value};
+#[cfg(all(test, feature = "value"))]
+mod collection_adapters {
+    use crate::{
+        adapters::{IdMap, NilPadded, OneIndexed},
+        marshal, Value,
+    };
 
     #[test]
-    fn nil() {
-        let original = Value::Nil;
+    fn nil_padded() {
+        let padded = NilPadded(vec![1, 2, 3]);
+        let value = crate::to_value(&padded).unwrap();
+        assert_eq!(value, marshal!([nil, 1, 2, 3]));
 
-        let bytes = to_bytes(&original).unwrap();
+        let bytes = crate::to_bytes(&padded).unwrap();
+        let deserialized: NilPadded<i32> = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized, padded);
 
-        let new: Value = from_bytes(&bytes).unwrap();
+        // nothing to pad
+        let empty: NilPadded<i32> = crate::from_value(&marshal!([])).unwrap();
+        assert!(empty.is_empty());
 
-        assert_eq!(original, new);
+        assert!(crate::from_value::<NilPadded<i32>>(&marshal!([0, 1, 2])).is_err());
     }
 
     #[test]
-    fn bool() {
-        let original = Value::Bool(true);
-
-        let bytes = to_bytes(&original).unwrap();
+    fn one_indexed() {
+        let value = marshal!([nil, "Aluxes", "Basil"]);
+        let mut actors: OneIndexed<Vec<&str>> = crate::from_value(&value).unwrap();
+        assert_eq!(actors[1], "Aluxes");
+        assert_eq!(actors.get(2), Some(&"Basil"));
+        assert_eq!(actors.get(0), None);
+        assert_eq!(actors.get(3), None);
+
+        assert_eq!(actors.push("Cyrus"), 3);
+        actors[3] = "Dorothy";
+        let ids = actors.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(ids, [1, 2, 3]);
+
+        let value = crate::to_value(&actors).unwrap();
+        assert_eq!(value, marshal!([nil, "Aluxes", "Basil", "Dorothy"]));
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    #[should_panic = "ID 0 is the padding"]
+    fn one_indexed_id_zero() {
+        let actors = OneIndexed(vec![1]);
+        let _ = actors[0];
+    }
 
-        assert_eq!(original, new);
+    #[test]
+    fn id_map() {
+        let value = marshal!({ 5 => "EV005", 2 => "EV002" });
+        let mut events: IdMap<String> = crate::from_value(&value).unwrap();
+        assert_eq!(events[&2], "EV002");
+        assert_eq!(events.insert_next("EV006".to_string()), 6);
+
+        // written in ID order
+        let Value::Hash(hash) = crate::to_value(&events).unwrap() else {
+            unreachable!("id maps are hashes")
+        };
+        let ids = hash
+            .keys()
+            .map(|k| *k.as_integer().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [2, 5, 6]);
+
+        assert_eq!(IdMap::<i32>::default().next_id(), 1);
+        assert!(crate::from_value::<IdMap<i32>>(&marshal!({ "1" => 1 })).is_err());
     }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod broken_links {
+    use crate::{de::Kind, from_bytes, from_bytes_with, marshal, DeOptions, Deserializer, Value};
 
     #[test]
-    fn float() {
-        let original = Value::Float(123.456);
+    fn unresolved() {
+        // [@1], without an object 1
+        let bytes = b"\x04\x08[\x06@\x06";
+        let err = from_bytes::<Value>(bytes).unwrap_err();
+        assert!(matches!(err.kind, Kind::UnresolvedObjectlink(1)));
+
+        let mut deserializer = Deserializer::new(bytes).unwrap();
+        deserializer.set_tolerate_broken_links(true);
+        let value: Value = deserializer.deserialize_value().unwrap();
+        assert_eq!(value, marshal!([nil]));
+        assert_eq!(deserializer.recovered().len(), 1);
+        assert_eq!(deserializer.recovered()[0].position, 4);
+        assert!(matches!(
+            deserializer.recovered()[0].error.kind,
+            Kind::UnresolvedObjectlink(1)
+        ));
+    }
 
-        let bytes = to_bytes(&original).unwrap();
+    #[test]
+    fn circular() {
+        // a = []; a << a
+        let bytes = b"\x04\x08[\x06@\x00";
+        let err = from_bytes::<Value>(bytes).unwrap_err();
+        assert!(matches!(err.kind, Kind::CircularReference));
+
+        let mut deserializer = Deserializer::new(bytes).unwrap();
+        deserializer.set_tolerate_broken_links(true);
+        let value: Value = deserializer.deserialize_value().unwrap();
+        // the link is followed once, and is nil the second time around
+        assert_eq!(value, marshal!([[nil]]));
+        assert_eq!(deserializer.recovered().len(), 1);
+        assert!(matches!(
+            deserializer.recovered()[0].error.kind,
+            Kind::CircularReference
+        ));
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn other_errors_still_fail() {
+        let bytes = b"\x04\x08[\x07@\x06\xff";
+        let mut deserializer = Deserializer::new(bytes).unwrap();
+        deserializer.set_tolerate_broken_links(true);
+        let err = deserializer.deserialize_value::<Value>().unwrap_err();
+        assert!(matches!(err.kind, Kind::WrongTag(0xFF)));
+    }
 
-        assert_eq!(original, new);
+    #[test]
+    fn options() {
+        let bytes = b"\x04\x08[\x06@\x06";
+        let options = DeOptions::new().tolerate_broken_links(true);
+        let value: Vec<Option<i32>> = from_bytes_with(bytes, &options).unwrap();
+        assert_eq!(value, [None]);
+        assert!(options
+            .deserializer(bytes)
+            .unwrap()
+            .tolerates_broken_links());
     }
+}
+
+#[cfg(all(test, feature = "value"))]
+mod link_reports {
+    use crate::{
+        analysis::{link_report, SharedObject},
+        de::Kind,
+        marshal,
+        tag::Tag,
+        Value,
+    };
 
     #[test]
-    fn integer() {
-        let original = Value::Integer(123);
+    fn no_links() {
+        let bytes = crate::to_bytes(marshal!([1, "a", [2]])).unwrap();
+        let report = link_report(&bytes).unwrap();
+        assert_eq!(report.objects, 3);
+        assert_eq!(report.links, 0);
+        assert_eq!(report.copied_bytes, 0);
+        assert_eq!(report.size, bytes.len());
+        assert!(report.shared.is_empty());
+    }
 
-        let bytes = to_bytes(&original).unwrap();
+    #[test]
+    fn nested_links() {
+        // a = "x"; b = [a, a]; [b, b]
+        let bytes = b"\x04\x08[\x07[\x07I\"\x06x\x06:\x06ET@\x07@\x06";
+        let value: Value = crate::from_bytes(bytes).unwrap();
+        assert_eq!(value, marshal!([["x", "x"], ["x", "x"]]));
+
+        let report = link_report(bytes).unwrap();
+        assert_eq!(report.objects, 3);
+        assert_eq!(report.links, 2);
+        assert_eq!(report.circular_links, 0);
+
+        let [b, a] = report.shared.as_slice() else {
+            unreachable!("{:?}", report.shared)
+        };
+        assert_eq!((a.index, a.position, a.tag, a.size), (2, 6, Tag::String, 9));
+        // b copies a once itself
+        assert_eq!(
+            (b.index, b.position, b.tag, b.size),
+            (1, 4, Tag::Array, 13 + 9)
+        );
+        assert_eq!(report.copied_bytes, a.copied_bytes() + b.copied_bytes());
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn most_linked_first() {
+        // a, b = Foo.new, Foo.new; [a, b, a, b, b, b]
+        let bytes = b"\x04\x08[\x0bo:\x08Foo\x00o;\x00\x00@\x06@\x07@\x07@\x07";
+        let report = link_report(bytes).unwrap();
+        assert_eq!(report.links, 4);
+        let indices: Vec<_> = report.shared.iter().map(|o| (o.index, o.links)).collect();
+        assert_eq!(indices, [(2, 3), (1, 1)]);
+        assert!(report
+            .shared
+            .iter()
+            .all(|o| o.tag == Tag::Object && o.class.unwrap() == "Foo"));
+    }
 
-        assert_eq!(original, new);
+    #[test]
+    fn circular_links() {
+        // a = []; a << a
+        let report = link_report(b"\x04\x08[\x06@\x00").unwrap();
+        assert_eq!(report.links, 1);
+        assert_eq!(report.circular_links, 1);
+        assert_eq!(report.copied_bytes, 0);
+        assert!(report.shared.is_empty());
     }
 
     #[test]
-    fn string() {
-        let original = Value::String("round trip".into());
+    fn unresolved_links() {
+        let err = link_report(b"\x04\x08[\x06@\x06").unwrap_err();
+        assert!(matches!(err.kind, Kind::UnresolvedObjectlink(1)));
+    }
 
-        let bytes = to_bytes(&original).unwrap();
+    #[test]
+    fn real_data() {
+        let bytes = include_bytes!("../examples/Actors.rxdata");
+        let report = link_report(bytes).unwrap();
+        assert_eq!(report.size, bytes.len());
+        assert_eq!(report.circular_links, 0);
+        assert_eq!(
+            report.copied_bytes,
+            report
+                .shared
+                .iter()
+                .map(SharedObject::copied_bytes)
+                .sum::<usize>()
+        );
+    }
+}
 
-        let new: Value = from_bytes(&bytes).unwrap();
+#[cfg(all(test, feature = "value"))]
+mod value_stats {
+    use crate::{
+        analysis::{value_stats, LARGEST_STRINGS},
+        de::expect::ValueKind,
+        marshal, Value,
+    };
 
-        assert_eq!(original, new);
+    #[test]
+    fn counts_kinds() {
+        let value = marshal!({ :a => [1, 2.0, nil], "b" => Foo { @c => :d } });
+        let stats = value_stats(&value);
+
+        assert_eq!(stats.count(ValueKind::Hash), 1);
+        assert_eq!(stats.count(ValueKind::Symbol), 2);
+        assert_eq!(stats.count(ValueKind::Array), 1);
+        assert_eq!(stats.count(ValueKind::Integer), 1);
+        assert_eq!(stats.count(ValueKind::Float), 1);
+        assert_eq!(stats.count(ValueKind::Nil), 1);
+        assert_eq!(stats.count(ValueKind::String), 1);
+        assert_eq!(stats.count(ValueKind::Object), 1);
+        assert_eq!(stats.count(ValueKind::Struct), 0);
+        assert_eq!(stats.size, value.deep_size_of());
     }
 
     #[test]
-    fn symbol() {
-        let original = Value::Symbol("round_trip".into());
+    fn largest_strings() {
+        let strings: Vec<Value> = (0..20).map(|len| "x".repeat(len % 15).into()).collect();
+        let value = Value::Array(strings);
+        let stats = value_stats(&value);
 
-        let bytes = to_bytes(&original).unwrap();
+        assert_eq!(
+            stats.string_bytes,
+            (0..20).map(|len| len % 15).sum::<usize>()
+        );
+        let lengths: Vec<_> = stats.largest_strings.iter().map(|s| s.len()).collect();
+        assert_eq!(lengths.len(), LARGEST_STRINGS);
+        assert_eq!(lengths, [14, 13, 12, 11, 10, 9, 8, 7, 6, 5]);
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn deep_size() {
+        let small = Value::from("x");
+        let large = Value::from("x".repeat(4096));
+        assert!(large.deep_size_of() >= small.deep_size_of() + 4095);
 
-        assert_eq!(original, new);
+        // containers count their own capacity and their elements
+        let array = Value::Array(vec![large.clone(), large.clone()]);
+        assert!(array.deep_size_of() > large.deep_size_of() * 2);
+
+        let nested = marshal!([[[[1]]]]);
+        assert!(nested.deep_size_of() >= std::mem::size_of::<Value>() * 5);
     }
 
     #[test]
-    fn array() {
-        let original = Value::Array(vec![Value::Integer(1), Value::Float(256.652)]);
+    fn real_data() {
+        let bytes = include_bytes!("../examples/Actors.rxdata");
+        let value: Value = crate::from_bytes(bytes).unwrap();
+        let stats = value_stats(&value);
+
+        let actors = value.as_array().unwrap();
+        let objects = actors.iter().filter(|actor| !actor.is_nil()).count();
+        assert!(stats.count(ValueKind::Object) >= objects);
+        assert!(stats.size > bytes.len());
+        assert!(stats
+            .largest_strings
+            .windows(2)
+            .all(|w| w[0].len() >= w[1].len()));
+    }
+}
 
-        let bytes = to_bytes(&original).unwrap();
+#[cfg(all(test, feature = "value"))]
+mod encoding_metadata {
+    use crate::{
+        ruby_string::{encode, Encoding},
+        Instance, RbString, RbStringWithIvars, Symbol, Value,
+    };
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn parse() {
+        let string: RbStringWithIvars = crate::from_bytes(b"\x04\x08I\"\x06a\x06:\x06EF").unwrap();
+        assert_eq!(string.parse_encoding(), Encoding::UsAscii);
+        assert_eq!(string.parse_encoding().name(), string.encoding());
 
-        assert_eq!(original, new);
+        let string: RbStringWithIvars = crate::from_bytes(b"\x04\x08\"\x06a").unwrap();
+        assert_eq!(string.parse_encoding(), Encoding::Binary);
+
+        let bytes = crate::to_bytes(encode("a", Encoding::Other("EUC-JP".to_string()))).unwrap();
+        let string: Instance<RbString> = crate::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            string.parse_encoding(),
+            Encoding::Other("EUC-JP".to_string())
+        );
+        assert_eq!(string.parse_encoding().to_string(), "EUC-JP");
     }
 
     #[test]
-    fn hash() {
-        let mut hash = RbHash::new();
-        hash.insert(Value::Bool(true), Value::Integer(1));
-        hash.insert(Value::Symbol("a_symbol".into()), Value::Float(256.652));
-        let original = Value::Hash(hash);
+    fn set_keeps_other_ivars() {
+        let mut string = RbStringWithIvars::new_utf8("a");
+        string.fields.insert("@note".into(), 1.into());
 
-        let bytes = to_bytes(&original).unwrap();
+        // same ivar, so it keeps its place
+        string.set_encoding(&Encoding::UsAscii);
+        let ivars: Vec<_> = string.fields.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        assert_eq!(
+            ivars,
+            [("E", &Value::Bool(false)), ("@note", &Value::Integer(1))]
+        );
 
-        let new: Value = from_bytes(&bytes).unwrap();
+        string.set_encoding(&Encoding::Other("Shift_JIS".to_string()));
+        let ivars: Vec<_> = string.fields.keys().map(Symbol::as_str).collect();
+        assert_eq!(ivars, ["encoding", "@note"]);
 
-        assert_eq!(original, new);
+        string.set_encoding(&Encoding::Binary);
+        let ivars: Vec<_> = string.fields.keys().map(Symbol::as_str).collect();
+        assert_eq!(ivars, ["@note"]);
+        assert!(string.is_binary());
     }
 
     #[test]
-    fn userdata() {
-        let original = Value::Userdata(crate::Userdata {
-            class: "TestUserdata".into(),
-            data: vec![97, 98, 99, 100],
-        });
+    fn round_trip() {
+        let mut string = Instance::<RbString>::from("a");
+        string.set_encoding(&Encoding::Other("Shift_JIS".to_string()));
+        let bytes = crate::to_bytes(&string).unwrap();
+        assert_eq!(bytes, b"\x04\x08I\"\x06a\x06:\rencoding\"\x0eShift_JIS");
+    }
+}
 
-        let bytes = to_bytes(&original).unwrap();
+#[cfg(all(test, feature = "value"))]
+mod class_refs {
+    use std::{collections::HashMap, sync::Arc};
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    use crate::{Serializer, SerializerTrait, Sym, Symbol, Value};
 
-        assert_eq!(original, new);
+    #[test]
+    fn round_trip_exactly() {
+        // Marshal.dump([String, Comparable, :String])
+        let data = b"\x04\x08[\x08c\x0bStringm\x0fComparable:\x0bString";
+        let value: Value = crate::from_bytes(data).unwrap();
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                Value::class("String"),
+                Value::module("Comparable"),
+                Value::symbol("String"),
+            ])
+        );
+        // the symbol isn't a symlink, since the class ref isn't in the symbol table
+        assert_eq!(crate::to_bytes(&value).unwrap(), data);
     }
 
     #[test]
-    fn object() {
-        let mut fields = RbFields::new();
-        fields.insert("@field1".into(), Value::Bool(true));
-        fields.insert(
-            "@field2".into(),
-            Value::String("i've been round tripped".into()),
+    fn raw_refs_are_not_mapped() {
+        let mapper: HashMap<Symbol, Symbol> = [("Game::Actor".into(), "Game_Actor".into())].into();
+        let mut serializer = Serializer::new();
+        serializer.set_class_mapper(Some(Arc::new(mapper)));
+
+        serializer.serialize_class_raw("Game::Actor").unwrap();
+        serializer.serialize_module_raw("Game::Actor").unwrap();
+        (&mut serializer)
+            .serialize_class(Sym::new("Game::Actor"))
+            .unwrap();
+        assert_eq!(
+            serializer.into_inner(),
+            b"\x04\x08c\x10Game::Actorm\x10Game::Actorc\x0fGame_Actor"
         );
-        let original = Value::Object(crate::Object {
-            class: "Test".into(),
-            fields,
-        });
+    }
+}
 
-        let bytes = to_bytes(&original).unwrap();
+#[cfg(all(test, feature = "value"))]
+mod untyped_builders {
+    use crate::{
+        de::Kind, Instance, InstanceExt, Object, ObjectExt, RbFields, RbStruct, RbStructExt,
+        Symbol, Value, ValueExt,
+    };
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn objects() {
+        let actor = Object::builder("RPG::Actor")
+            .field("name", "Aluxes")
+            .field("@level", 1)
+            .serialize_field("weapon_ids", &[1, 2])
+            .unwrap()
+            .build();
+        let names: Vec<_> = actor.fields.keys().map(Symbol::as_str).collect();
+        assert_eq!(names, ["@name", "@level", "@weapon_ids"]);
+
+        let name: &str = actor.get("@name").unwrap();
+        assert_eq!(name, "Aluxes");
+        assert_eq!(actor.get::<i64>("level").unwrap(), 1);
+        assert_eq!(actor.get::<Vec<u8>>("@weapon_ids").unwrap(), [1, 2]);
+
+        let Kind::MissingField(missing) = actor.get::<i32>("@class_id").unwrap_err().kind else {
+            unreachable!("the field is missing")
+        };
+        assert_eq!(missing.class.unwrap(), "RPG::Actor");
+        assert_eq!(missing.field, "@class_id");
 
-        assert_eq!(original, new);
+        assert!(actor.get::<bool>("name").is_err());
     }
 
     #[test]
-    fn instance() {
-        let inner_value = Box::new(Value::String("I've been round tripped, with ivars!".into()));
-        let mut fields = RbFields::new();
-        fields.insert("E".into(), Value::Bool(true));
-        fields.insert("@round_trip".into(), Value::Integer(123));
-        let original = Value::Instance(Instance {
-            value: inner_value,
-            fields,
-        });
-
-        let bytes = to_bytes(&original).unwrap();
+    fn structs() {
+        let point = RbStruct::builder("Point")
+            .member("x", 1)
+            .member("y", 2)
+            .build();
+        // Point = Struct.new(:x, :y); Marshal.dump(Point.new(1, 2))
+        assert_eq!(
+            crate::to_bytes(Value::RbStruct(point.clone())).unwrap(),
+            b"\x04\x08S:\nPoint\x07:\x06xi\x06:\x06yi\x07"
+        );
+        assert_eq!(point.get::<i32>("y").unwrap(), 2);
+        assert!(point.get::<i32>("z").is_err());
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn typed_boundary() {
+        #[derive(crate::Deserialize, crate::Serialize, PartialEq, Debug)]
+        #[marshal(alox_crate_path = "crate", class = "RPG::Actor")]
+        struct Actor<'a> {
+            name: &'a str,
+            level: i32,
+        }
 
-        assert_eq!(original, new);
+        let actor = Actor {
+            name: "Aluxes",
+            level: 1,
+        };
+        let object = Object::from_serialize(&actor).unwrap();
+        assert_eq!(object.class, "RPG::Actor");
+        assert_eq!(object.get::<i32>("@level").unwrap(), 1);
+        assert_eq!(object.deserialize_into::<Actor<'_>>().unwrap(), actor);
+
+        let value = Value::from_serialize(&actor).unwrap();
+        assert_eq!(value, Value::Object(object));
+        assert_eq!(value.deserialize_into::<Actor<'_>>().unwrap(), actor);
+
+        assert!(Object::from_serialize(&1).is_err());
+        assert!(matches!(
+            Object::builder("RPG::Actor")
+                .field("name", "Aluxes")
+                .build()
+                .deserialize_into::<Actor<'_>>()
+                .unwrap_err()
+                .kind,
+            Kind::MissingField(_)
+        ));
     }
 
     #[test]
-    fn regex() {
-        let original = Value::Regex {
-            data: "/round trip/".into(),
-            flags: 0b1010,
-        };
+    fn instances() {
+        let fields: RbFields = [("E".into(), true.into())].into_iter().collect();
+        let instance = Instance::from_typed("hi", fields).unwrap();
+        assert!(instance.get::<bool>("E").unwrap());
+        assert!(matches!(
+            instance.get::<bool>("encoding").unwrap_err().kind,
+            Kind::MissingField(_)
+        ));
+    }
+}
 
-        let bytes = to_bytes(&original).unwrap();
+#[cfg(all(test, feature = "value"))]
+mod value_equality {
+    use crate::{testing::assert_marshal_eq, Instance, Object, RbFields, RbHash, Value};
+
+    fn hash(entries: &[(i32, i32)]) -> Value {
+        Value::Hash(
+            entries
+                .iter()
+                .map(|&(k, v)| (k.into(), v.into()))
+                .collect::<RbHash>(),
+        )
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    fn fields(names: &[&str]) -> RbFields {
+        names
+            .iter()
+            .map(|&name| (name.into(), Value::Nil))
+            .collect()
+    }
 
-        assert_eq!(original, new);
+    #[test]
+    fn hashes() {
+        let a = hash(&[(1, 2), (3, 4)]);
+        let b = hash(&[(3, 4), (1, 2)]);
+
+        assert_eq!(a, b);
+        assert!(a.eq_unordered(&b));
+        assert!(!a.eq_ordered(&b));
+        assert!(a.eq_ordered(&a.clone()));
+
+        let c = hash(&[(1, 2), (3, 5)]);
+        assert!(!a.eq_unordered(&c));
+        assert!(!a.eq_ordered(&c));
     }
 
     #[test]
-    fn rb_struct() {
-        let mut fields = RbFields::new();
-        fields.insert("field1".into(), Value::Bool(true));
-        fields.insert("field2".into(), Value::String("round trip".into()));
-        let original = Value::RbStruct(RbStruct {
-            class: "TestStruct".into(),
-            fields,
+    fn fields_and_nesting() {
+        let a = Value::Object(Object {
+            class: "Foo".into(),
+            fields: fields(&["@a", "@b"]),
+        });
+        let b = Value::Object(Object {
+            class: "Foo".into(),
+            fields: fields(&["@b", "@a"]),
         });
+        assert!(a.eq_unordered(&b));
+        assert!(!a.eq_ordered(&b));
+
+        let a = Value::Array(vec![Value::Instance(Instance {
+            value: Box::new(hash(&[(1, 2), (3, 4)])),
+            fields: fields(&["E"]),
+        })]);
+        let b = Value::Array(vec![Value::Instance(Instance {
+            value: Box::new(hash(&[(3, 4), (1, 2)])),
+            fields: fields(&["E"]),
+        })]);
+        assert!(a.eq_unordered(&b));
+        assert!(!a.eq_ordered(&b));
+
+        // arrays are ordered either way
+        let c = Value::Array(vec![1.into(), 2.into()]);
+        let d = Value::Array(vec![2.into(), 1.into()]);
+        assert!(!c.eq_unordered(&d));
+        assert!(!c.eq_ordered(&d));
+    }
 
-        let bytes = to_bytes(&original).unwrap();
+    #[test]
+    fn marshal_data() {
+        // {1 => 2, 3 => 4} and {3 => 4, 1 => 2}
+        assert_marshal_eq(
+            b"\x04\x08{\x07i\x06i\x07i\x08i\x09",
+            b"\x04\x08{\x07i\x08i\x09i\x06i\x07",
+        );
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    #[should_panic(expected = "marshal data differs")]
+    fn marshal_data_differs() {
+        // [1, 2] and [2, 1]
+        assert_marshal_eq(b"\x04\x08[\x07i\x06i\x07", b"\x04\x08[\x07i\x07i\x06");
+    }
+}
 
-        assert_eq!(original, new);
+#[cfg(all(test, feature = "bytes"))]
+mod shared_buffers {
+    use bytes::Bytes;
+
+    use crate::{from_bytes, from_bytes_buf, to_bytes, RbString, Userdata, Value};
+
+    fn shares(data: &Bytes, slice: &[u8]) -> bool {
+        data.as_ptr_range().contains(&slice.as_ptr())
     }
 
     #[test]
-    fn class() {
-        let original = Value::Class("TestClass".into());
-
-        let bytes = to_bytes(&original).unwrap();
+    fn values_share_the_input() {
+        let value = Value::Array(vec![
+            Value::String(RbString::from(vec![1; 64])),
+            Value::Userdata(Userdata::new("Table", vec![2; 64])),
+            Value::Regex {
+                data: RbString::from("a+"),
+                flags: 0,
+            },
+        ]);
+        let data = Bytes::from(to_bytes(&value).unwrap());
+
+        let reloaded = from_bytes_buf(data.clone()).unwrap();
+        assert_eq!(reloaded, value);
+        assert_eq!(reloaded, from_bytes::<Value>(&data).unwrap());
+
+        let array = reloaded.as_array().unwrap();
+        assert!(shares(&data, &array[0].as_string().unwrap().data));
+        assert!(shares(&data, &array[1].as_userdata().unwrap().data));
+        let Value::Regex { data: regex, .. } = &array[2] else {
+            unreachable!("the third element is a regex")
+        };
+        assert!(shares(&data, &regex.data));
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn linked_strings_share_the_same_bytes() {
+        // s = "hi"; Marshal.dump([s, s]), with the string's encoding left out
+        let data = Bytes::from_static(b"\x04\x08[\x07\"\x07hi@\x06");
+        let value = from_bytes_buf(data.clone()).unwrap();
+
+        let array = value.as_array().unwrap();
+        let (a, b) = (array[0].as_string().unwrap(), array[1].as_string().unwrap());
+        assert_eq!(a, b);
+        assert_eq!(a.data.as_ptr(), b.data.as_ptr());
+        assert!(shares(&data, &a.data));
+    }
 
-        assert_eq!(original, new);
+    #[test]
+    fn other_deserializers_copy() {
+        let data = to_bytes(Value::from("hi")).unwrap();
+        let value: Value = from_bytes(&data).unwrap();
+        let string = value.as_string().unwrap();
+        assert!(!data.as_ptr_range().contains(&string.data.as_ptr()));
     }
 
     #[test]
-    fn module() {
-        let original = Value::Module("TestModule".into());
+    fn errors() {
+        let err = from_bytes_buf(Bytes::from_static(b"\x04\x08\"\x07h")).unwrap_err();
+        assert!(matches!(err.kind, crate::de::Kind::Eof));
+    }
+}
 
-        let bytes = to_bytes(&original).unwrap();
+#[cfg(all(test, feature = "serde-interop"))]
+mod serde_interop {
+    use crate::{marshal, RbString, Userdata, Value};
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn tagged() {
+        let value = marshal!([nil, 1, "a", :b]);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"Array":["Nil",{"Integer":1},"#,
+                r#"{"Instance":{"value":{"String":"a"},"fields":{"E":{"Bool":true}}}},"#,
+                r#"{"Symbol":"b"}]}"#
+            )
+        );
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+    }
 
-        assert_eq!(original, new);
+    #[test]
+    fn hashes() {
+        let value = marshal!({ 1 => :a, [2] => nil });
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            json,
+            r#"{"Hash":[[{"Integer":1},{"Symbol":"a"}],[{"Array":[{"Integer":2}]},"Nil"]]}"#
+        );
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
     }
 
     #[test]
-    fn user_class() {
-        let inner_value = Box::new(Value::String("I'm a user class".into()));
-        let original = Value::UserClass {
-            class: "TestUserClass".into(),
-            value: inner_value,
-        };
+    fn binary_strings() {
+        let value = Value::String(RbString::from(&b"\xff\x00"[..]));
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"String":[255,0]}"#);
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+    }
 
-        let bytes = to_bytes(&original).unwrap();
+    #[test]
+    fn round_trip() {
+        let value: Value = crate::from_bytes(include_bytes!("../examples/Actors.rxdata")).unwrap();
+        let json = serde_json::to_vec(&value).unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&json).unwrap(), value);
 
-        let new: Value = from_bytes(&bytes).unwrap();
+        let value = Value::Userdata(Userdata::new("Table", vec![1, 2, 3]));
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            json,
+            r#"{"Userdata":{"class":"Table","data":"\u0001\u0002\u0003"}}"#
+        );
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+    }
+}
 
-        assert_eq!(original, new);
+#[cfg(all(test, feature = "conformance"))]
+mod conformance {
+    use crate::{
+        marshal,
+        testing::conformance::{check, check_fixture, definitions, Error, Ruby},
+        RbStruct, Userdata, Value,
+    };
+
+    #[test]
+    fn definitions_cover_every_class() {
+        let table = Value::Userdata(Userdata::new("Table", b"abcd".to_vec()));
+        let point = Value::RbStruct(RbStruct {
+            class: "Point".into(),
+            fields: [("x".into(), 1.into()), ("y".into(), 2.into())]
+                .into_iter()
+                .collect(),
+        });
+        let color = Value::Userdata(Userdata::new("Color", vec![]));
+        let value = marshal!([
+            RPG::Actor { @name => "Aluxes", @table => table },
+            RPG::Actor { @name => "Basil" },
+            point,
+            color,
+        ]);
+
+        let code = definitions(&value);
+        let lines: Vec<_> = code
+            .lines()
+            .filter(|l| l.starts_with("__alox_define"))
+            .collect();
+        assert_eq!(lines.len(), 4, "{code}");
+        assert!(lines[0].starts_with("__alox_define('Color') { Class.new { def self._load"));
+        assert_eq!(
+            lines[1],
+            "__alox_define('Point') { Struct.new(:'x', :'y') }"
+        );
+        assert_eq!(lines[2], "__alox_define('RPG::Actor') { Class.new }");
+        assert!(lines[3].starts_with("__alox_define('Table') { Class.new { def self._load"));
     }
 
     #[test]
-    fn user_marshal() {
-        let inner_value = Box::new(Value::String("I've been serialized as another type".into()));
-        let original = Value::UserMarshal {
-            class: "TestUserMarshal".into(),
-            value: inner_value,
-        };
+    fn definitions_escape_names() {
+        let point = Value::RbStruct(RbStruct {
+            class: "P#{exit}".into(),
+            fields: [("it's".into(), 1.into()), ("a\\".into(), 2.into())]
+                .into_iter()
+                .collect(),
+        });
 
-        let bytes = to_bytes(&original).unwrap();
+        let code = definitions(&point);
+        let line = code
+            .lines()
+            .find(|l| l.starts_with("__alox_define"))
+            .unwrap();
+        assert_eq!(
+            line,
+            r"__alox_define('P#{exit}') { Struct.new(:'it\'s', :'a\\') }"
+        );
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn ruby_errors() {
+        let err = Ruby::new("alox-48-missing-ruby").version().unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
 
-        assert_eq!(original, new);
+        // exits with an error, like ruby does when it raises
+        let err = Ruby::new("false").dump("1").unwrap_err();
+        assert!(matches!(err, Error::Ruby { .. }));
     }
 
+    // these need ruby, and pass trivially without it
     #[test]
-    fn data() {
-        let inner_value = Box::new(Value::String("???".into()));
-        let original = Value::Data {
-            class: "TestData".into(),
-            value: inner_value,
+    fn round_trips() {
+        let Some(ruby) = Ruby::find() else {
+            return;
         };
 
-        let bytes = to_bytes(&original).unwrap();
+        check(&ruby, &vec![1, 2, 3]).unwrap();
+        check(&ruby, &"Aluxes".to_string()).unwrap();
+        let value: Value = crate::from_bytes(include_bytes!("../examples/Actors.rxdata")).unwrap();
+        check(&ruby, &value).unwrap();
+    }
 
-        let new: Value = from_bytes(&bytes).unwrap();
+    #[test]
+    fn fixtures() {
+        let Some(ruby) = Ruby::find() else {
+            return;
+        };
 
-        assert_eq!(original, new);
+        let name: String = check_fixture(&ruby, r#""Aluxes""#).unwrap();
+        assert_eq!(name, "Aluxes");
+
+        let value: Value = check_fixture(
+            &ruby,
+            "Point = Struct.new(:x, :y)\nshared = 'a'\n[Point.new(1, 2), shared, shared, { 1 => 2.5 }]",
+        )
+        .unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 4);
+
+        let err = check_fixture::<i32>(&ruby, "'a'").unwrap_err();
+        assert!(matches!(err, Error::De(_)));
+
+        // a binary string comes back as UTF-8, since that's how `String` is serialized
+        let err = check_fixture::<String>(&ruby, "'a'.b").unwrap_err();
+        assert!(matches!(err, Error::Mismatch { .. }));
     }
 }
@@ -0,0 +1,137 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Construct a [`Value`](crate::Value) from ruby-like syntax.
+///
+/// - `nil`, `true`, and `false` are themselves.
+/// - `:name` is a symbol. Use `:"some name"` for symbols that aren't identifiers.
+/// - `[a, b]` is an array, and `{ a => b }` is a hash.
+/// - `Some::Class { @a => b }` is an object of that class with those instance variables.
+///   Instance variables that aren't identifiers can be written as string literals, like `"@a" => b`.
+/// - Anything else is an expression, converted with [`to_value`](crate::to_value).
+///   This makes rust strings into UTF-8 ruby strings. Wrap expressions in parentheses if they contain `,` or `=>`.
+///
+/// # Panics
+///
+/// Panics if an expression fails to serialize.
+///
+/// ```
+/// use alox_48::{marshal, Value};
+///
+/// let level = 5;
+/// let actor = marshal!(RPG::Actor {
+///     @name => "Alice",
+///     @level => level,
+///     @skills => [:fire, :ice],
+///     @stats => { :hp => 100, :mp => (level * 10) },
+///     @weapon => nil,
+/// });
+///
+/// let actor = actor.into_object().unwrap();
+/// assert_eq!(actor.class, "RPG::Actor");
+/// assert_eq!(actor.fields["@level"], Value::Integer(5));
+/// assert_eq!(actor.fields["@skills"], Value::Array(vec![
+///     Value::Symbol("fire".into()),
+///     Value::Symbol("ice".into()),
+/// ]));
+/// ```
+#[cfg(feature = "value")]
+#[macro_export]
+macro_rules! marshal {
+    ($($value:tt)+) => {
+        $crate::__marshal_internal!(@value $($value)+)
+    };
+}
+
+#[cfg(feature = "value")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __marshal_internal {
+    // Values
+
+    (@value nil) => {
+        $crate::Value::Nil
+    };
+    (@value true) => {
+        $crate::Value::Bool(true)
+    };
+    (@value false) => {
+        $crate::Value::Bool(false)
+    };
+    (@value : $symbol:ident) => {
+        $crate::Value::Symbol($crate::Symbol::from(stringify!($symbol)))
+    };
+    (@value : $symbol:literal) => {
+        $crate::Value::Symbol($crate::Symbol::from($symbol))
+    };
+    (@value [ $($elements:tt)* ]) => {
+        $crate::Value::Array($crate::__marshal_internal!(@array [] () $($elements)*))
+    };
+    (@value { $($entries:tt)* }) => {
+        $crate::Value::Hash($crate::__marshal_internal!(@entries hash [] () $($entries)*))
+    };
+    (@value $first:ident $(:: $rest:ident)* { $($ivars:tt)* }) => {
+        $crate::Value::Object($crate::Object {
+            class: $crate::Symbol::from(concat!(stringify!($first) $(, "::", stringify!($rest))*)),
+            fields: $crate::__marshal_internal!(@entries object [] () $($ivars)*),
+        })
+    };
+    (@value $other:expr) => {
+        $crate::to_value(&$other).expect("failed to convert expression to a value")
+    };
+
+    // Arrays, munching one element at a time
+
+    (@array [$($done:expr,)*] ($($element:tt)+) , $($rest:tt)*) => {
+        $crate::__marshal_internal!(@array [$($done,)* $crate::__marshal_internal!(@value $($element)+),] () $($rest)*)
+    };
+    (@array [$($done:expr,)*] ($($element:tt)+)) => {
+        vec![$($done,)* $crate::__marshal_internal!(@value $($element)+)]
+    };
+    (@array [$($done:expr,)*] ()) => {
+        vec![$($done),*]
+    };
+    (@array [$($done:expr,)*] ($($element:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::__marshal_internal!(@array [$($done,)*] ($($element)* $next) $($rest)*)
+    };
+
+    // Hash entries and instance variables, munching a key and then its value
+
+    (@entries $kind:ident [$($done:tt)*] ($($key:tt)+) => $($rest:tt)*) => {
+        $crate::__marshal_internal!(@entry_value $kind [$($done)*] ($($key)+) () $($rest)*)
+    };
+    (@entries $kind:ident [$(( ($($key:tt)+) ($($value:tt)+) ))*] ()) => {
+        [$((
+            $crate::__marshal_internal!(@key $kind $($key)+),
+            $crate::__marshal_internal!(@value $($value)+),
+        )),*]
+        .into_iter()
+        .collect()
+    };
+    (@entries $kind:ident [$($done:tt)*] ($($key:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::__marshal_internal!(@entries $kind [$($done)*] ($($key)* $next) $($rest)*)
+    };
+
+    (@entry_value $kind:ident [$($done:tt)*] ($($key:tt)+) ($($value:tt)+) , $($rest:tt)*) => {
+        $crate::__marshal_internal!(@entries $kind [$($done)* (($($key)+) ($($value)+))] () $($rest)*)
+    };
+    (@entry_value $kind:ident [$($done:tt)*] ($($key:tt)+) ($($value:tt)+)) => {
+        $crate::__marshal_internal!(@entries $kind [$($done)* (($($key)+) ($($value)+))] ())
+    };
+    (@entry_value $kind:ident [$($done:tt)*] ($($key:tt)+) ($($value:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::__marshal_internal!(@entry_value $kind [$($done)*] ($($key)+) ($($value)* $next) $($rest)*)
+    };
+
+    (@key hash $($key:tt)+) => {
+        $crate::__marshal_internal!(@value $($key)+)
+    };
+    (@key object @ $ivar:ident) => {
+        $crate::Symbol::from(concat!("@", stringify!($ivar)))
+    };
+    (@key object $ivar:literal) => {
+        $crate::Symbol::from($ivar)
+    };
+}
@@ -0,0 +1,346 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Replacing a single value in marshal data, without deserializing the rest of it.
+//!
+//! [`patch`] copies marshal data, replacing the value at a path with a new one.
+//! Nothing is deserialized along the way, so patching a large file is much faster than
+//! loading it into a [`Value`](crate::Value), editing it, and serializing it again.
+//!
+//! Symbol and object links are rewritten as the data is copied, so they stay correct
+//! when the new value has a different amount of symbols and objects than the old one.
+//! Object links to the old value link to the new value afterwards.
+//!
+//! ```
+//! # #[cfg(feature = "value")] {
+//! use alox_48::{de::PathSegment, marshal, patch::patch};
+//!
+//! let bytes = alox_48::to_bytes(marshal!([1, [2, 3], :four])).unwrap();
+//! let path = [PathSegment::Index(1), PathSegment::Index(0)];
+//! let patched = patch(&bytes, &path, &vec![5, 6]).unwrap();
+//!
+//! let value: alox_48::Value = alox_48::from_bytes(&patched).unwrap();
+//! assert_eq!(value, marshal!([1, [[5, 6], 3], :four]));
+//! # }
+//! ```
+
+use crate::{
+    de::{DisplayPath, Kind as DeKind, PathSegment, DEFAULT_DEPTH_LIMIT},
+    tag::Tag,
+    DeError, Deserializer, SerError, Serialize, Serializer, Sym,
+};
+
+/// Type alias around a result.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Why patching failed.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The data being patched is malformed.
+    #[error(transparent)]
+    De(#[from] DeError),
+    /// The new value failed to serialize.
+    #[error(transparent)]
+    Ser(#[from] SerError),
+    /// There is no value at the path.
+    #[error("there is no value at {}", DisplayPath(.0))]
+    NotFound(Vec<PathSegment>),
+    /// An object link after the replaced value refers to an object inside it, which doesn't exist anymore.
+    /// Contains the index of the object in the original data.
+    #[error("object {0} is linked to, but it was inside the replaced value")]
+    DanglingLink(usize),
+}
+
+/// Replace the value at `path` in `data` with `value`, returning the patched data.
+///
+/// The path starts from the outermost value, like the paths in [`DeError`]s.
+/// Wrappers (instances, extended values, user classes, etc) don't have a segment of their own,
+/// so paths continue into the value they wrap, and an empty path replaces the whole value.
+///
+/// `value` is serialized by a default [`Serializer`]. Anything after the end of the marshal data is kept as is.
+///
+/// # Errors
+///
+/// Errors if `data` is malformed, if `value` fails to serialize, if there's nothing at `path`,
+/// or if something later in the data links to an object inside the replaced value.
+pub fn patch<T>(data: &[u8], path: &[PathSegment], value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let replacement = crate::to_bytes(value)?;
+
+    let mut walker = Walker::new(data, &replacement)?;
+    let mut writer = Writer {
+        output: Serializer::new(),
+        objects: 0,
+        enabled: true,
+    };
+    walker.value(&mut writer, Some(path))?;
+    if !walker.found {
+        return Err(Error::NotFound(path.to_vec()));
+    }
+
+    let rest = &data[walker.input.current_position()..];
+    writer.output.write_bytes(rest)?;
+    Ok(writer.output.output)
+}
+
+// Reads values from some marshal data, copying them to a `Writer`.
+struct Walker<'de, 'r> {
+    input: Deserializer<'de>,
+    // the index each object in the input has in the output, or `None` if it was replaced
+    objects: Vec<Option<usize>>,
+    // the value of an instance is part of the same object, see `Deserializer::register_obj`
+    reading_instance: bool,
+    depth: usize,
+
+    replacement: &'r [u8],
+    found: bool,
+}
+
+struct Writer {
+    output: Serializer,
+    // how many objects have been written
+    objects: usize,
+    // whether anything is written, turned off while skipping the replaced value
+    enabled: bool,
+}
+
+impl<'de, 'r> Walker<'de, 'r> {
+    fn new(input: &'de [u8], replacement: &'r [u8]) -> Result<Self> {
+        Ok(Self {
+            input: Deserializer::new(input)?,
+            objects: vec![],
+            reading_instance: false,
+            depth: 0,
+            replacement,
+            found: false,
+        })
+    }
+
+    // `path` is where the value to replace is relative to this one, if it's inside this one.
+    fn value(&mut self, out: &mut Writer, path: Option<&[PathSegment]>) -> Result<()> {
+        if path.is_some_and(<[_]>::is_empty) {
+            return self.replace(out);
+        }
+
+        if self.depth >= DEFAULT_DEPTH_LIMIT {
+            return Err(DeError::from(DeKind::DepthLimitExceeded(DEFAULT_DEPTH_LIMIT)).into());
+        }
+        self.depth += 1;
+        let result = self.value_inner(out, path);
+        self.depth -= 1;
+        result
+    }
+
+    fn value_inner(&mut self, out: &mut Writer, path: Option<&[PathSegment]>) -> Result<()> {
+        let input = &mut self.input;
+        let tag = input.peek_value_tag()?;
+        if tag.is_object_link_referenceable() {
+            if self.reading_instance {
+                self.reading_instance = false;
+            } else {
+                self.objects.push(out.register());
+            }
+        }
+        input.cursor.next_byte()?;
+        // symbols may be written as links or not depending on what's already in the output, and object links are renumbered
+        if !matches!(tag, Tag::Symbol | Tag::Symlink | Tag::ObjectLink) {
+            out.tag(tag)?;
+        }
+
+        match tag {
+            Tag::Nil | Tag::True | Tag::False => {}
+            Tag::Integer => {
+                let int = input.read_packed_int()?;
+                out.int(int.into())?;
+            }
+            Tag::Bignum => {
+                // copied as is, so bignums too large for alox-48 survive
+                let (sign, bytes) = input.read_bignum_raw()?;
+                out.byte(sign)?;
+                out.len(bytes.len() / 2)?;
+                out.bytes(bytes)?;
+            }
+            Tag::Symbol => {
+                let symbol = input.read_symbol()?;
+                out.symbol(symbol)?;
+            }
+            Tag::Symlink => {
+                let symbol = input.read_symlink()?;
+                out.symbol(symbol)?;
+            }
+            Tag::ObjectLink => {
+                let index = input.read_usize()?;
+                let new_index = self
+                    .objects
+                    .get(index)
+                    .copied()
+                    .ok_or(DeError::from(DeKind::UnresolvedObjectlink(index)))?;
+                if out.enabled {
+                    let new_index = new_index.ok_or(Error::DanglingLink(index))?;
+                    out.tag(tag)?;
+                    out.len(new_index)?;
+                }
+            }
+            Tag::Float | Tag::String | Tag::ClassRef | Tag::ModuleRef => {
+                let bytes = input.read_bytes_len()?;
+                out.bytes_len(bytes)?;
+            }
+            Tag::RawRegexp => {
+                let bytes = input.read_bytes_len()?;
+                let flags = input.cursor.next_byte()?;
+                out.bytes_len(bytes)?;
+                out.byte(flags)?;
+            }
+            Tag::UserDef => {
+                let class = input.read_class_name()?;
+                let bytes = input.read_bytes_len()?;
+                out.symbol(class)?;
+                out.bytes_len(bytes)?;
+            }
+            Tag::Array => {
+                let len = input.read_usize()?;
+                out.len(len)?;
+                for i in 0..len {
+                    let path = descend(path, |segment| *segment == PathSegment::Index(i));
+                    self.value(out, path)?;
+                }
+            }
+            Tag::Hash | Tag::HashDefault => {
+                let len = input.read_usize()?;
+                out.len(len)?;
+                for i in 0..len {
+                    self.value(out, None)?;
+                    let path = descend(path, |segment| *segment == PathSegment::Entry(i));
+                    self.value(out, path)?;
+                }
+                if tag == Tag::HashDefault {
+                    let path = descend(path, |segment| *segment == PathSegment::HashDefault);
+                    self.value(out, path)?;
+                }
+            }
+            Tag::Object | Tag::Struct => {
+                let class = input.read_class_name()?;
+                out.symbol(class)?;
+                self.ivars(out, path)?;
+            }
+            Tag::Instance => {
+                self.reading_instance = true;
+                self.value(out, path)?;
+                self.ivars(out, path)?;
+            }
+            Tag::Extended | Tag::UserClass | Tag::UserMarshal | Tag::Data => {
+                let class = input.read_class_name()?;
+                out.symbol(class)?;
+                self.value(out, path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn ivars(&mut self, out: &mut Writer, path: Option<&[PathSegment]>) -> Result<()> {
+        let len = self.input.read_usize()?;
+        out.len(len)?;
+        for _ in 0..len {
+            let ivar = self.input.read_symbol_either()?;
+            out.symbol(ivar)?;
+            let path = descend(
+                path,
+                |segment| matches!(segment, PathSegment::Ivar(name) if name.as_str() == ivar.as_str()),
+            );
+            self.value(out, path)?;
+        }
+        Ok(())
+    }
+
+    fn replace(&mut self, out: &mut Writer) -> Result<()> {
+        self.found = true;
+
+        let old_root = self.objects.len();
+        out.enabled = false;
+        let skipped = self.value(out, None);
+        out.enabled = true;
+        skipped?;
+
+        let new_root = out.objects;
+        let mut replacement = Walker::new(self.replacement, &[])?;
+        replacement.value(out, None)?;
+
+        // links to the old value now link to the new one
+        if out.objects > new_root {
+            if let Some(object) = self.objects.get_mut(old_root) {
+                *object = Some(new_root);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Returns the rest of the path if the value to replace is inside the child matching `segment`.
+fn descend(
+    path: Option<&[PathSegment]>,
+    segment: impl FnOnce(&PathSegment) -> bool,
+) -> Option<&[PathSegment]> {
+    match path? {
+        [first, rest @ ..] if segment(first) => Some(rest),
+        _ => None,
+    }
+}
+
+#[allow(clippy::cast_possible_wrap)]
+impl Writer {
+    fn register(&mut self) -> Option<usize> {
+        self.enabled.then(|| {
+            self.objects += 1;
+            self.objects - 1
+        })
+    }
+
+    fn tag(&mut self, tag: Tag) -> Result<()> {
+        self.byte(tag as u8)
+    }
+
+    fn byte(&mut self, byte: u8) -> Result<()> {
+        if self.enabled {
+            self.output.write(byte)?;
+        }
+        Ok(())
+    }
+
+    fn int(&mut self, int: i64) -> Result<()> {
+        if self.enabled {
+            self.output.write_int(int)?;
+        }
+        Ok(())
+    }
+
+    fn len(&mut self, len: usize) -> Result<()> {
+        self.int(len as i64)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        if self.enabled {
+            self.output.write_bytes(bytes)?;
+        }
+        Ok(())
+    }
+
+    fn bytes_len(&mut self, bytes: &[u8]) -> Result<()> {
+        if self.enabled {
+            self.output.write_bytes_len(bytes)?;
+        }
+        Ok(())
+    }
+
+    fn symbol(&mut self, symbol: &Sym) -> Result<()> {
+        if self.enabled {
+            self.output.write_symbol(symbol)?;
+        }
+        Ok(())
+    }
+}
@@ -5,9 +5,11 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use super::{add_context, Context, Trace};
+#[cfg(feature = "value")]
+use crate::Value;
 use crate::{
-    de::{DeserializeSeed, DeserializerTrait},
-    ArrayAccess, DeResult, HashAccess, InstanceAccess, IvarAccess, Sym, Symbol, Visitor,
+    de::{DeserializeSeed, DeserializerTrait, Primitive},
+    ArrayAccess, DeResult, HashAccess, InstanceAccess, IvarAccess, RawValue, Sym, Visitor,
     VisitorInstance, VisitorOption,
 };
 
@@ -37,7 +39,7 @@ where
     }
 }
 
-impl<'de, 'trace, T> DeserializerTrait<'de> for Deserializer<'trace, T>
+impl<'de, T> DeserializerTrait<'de> for Deserializer<'_, T>
 where
     T: DeserializerTrait<'de>,
 {
@@ -70,9 +72,13 @@ where
             trace: self.trace,
         })
     }
+
+    fn deserialize_raw(self) -> DeResult<RawValue<'de>> {
+        self.deserializer.deserialize_raw()
+    }
 }
 
-impl<'de, 'trace, X> Visitor<'de> for Wrapped<'trace, X>
+impl<'de, X> Visitor<'de> for Wrapped<'_, X>
 where
     X: Visitor<'de>,
 {
@@ -82,34 +88,79 @@ where
         self.inner.expecting(formatter)
     }
 
+    fn expected_primitive(&self) -> Option<Primitive> {
+        self.inner.expected_primitive()
+    }
+
     fn visit_nil(self) -> DeResult<Self::Value> {
-        add_context!(self.inner.visit_nil(), self.trace.push(Context::Nil))
+        add_context!(
+            self.trace,
+            self.inner.visit_nil(),
+            self.trace.push_detail(|| Context::Nil)
+        )
     }
 
     fn visit_bool(self, v: bool) -> DeResult<Self::Value> {
-        add_context!(self.inner.visit_bool(v), self.trace.push(Context::Bool(v)))
+        add_context!(
+            self.trace,
+            self.inner.visit_bool(v),
+            self.trace.push_detail(|| Context::Bool(v))
+        )
     }
 
     fn visit_i32(self, v: i32) -> DeResult<Self::Value> {
-        add_context!(self.inner.visit_i32(v), self.trace.push(Context::Int(v)))
+        add_context!(
+            self.trace,
+            self.inner.visit_i32(v),
+            self.trace.push_detail(|| Context::Int(v.into()))
+        )
+    }
+
+    fn visit_i64(self, v: i64) -> DeResult<Self::Value> {
+        add_context!(
+            self.trace,
+            self.inner.visit_i64(v),
+            self.trace.push_detail(|| Context::Int(v))
+        )
     }
 
     fn visit_f64(self, v: f64) -> DeResult<Self::Value> {
-        add_context!(self.inner.visit_f64(v), self.trace.push(Context::Float(v)))
+        add_context!(
+            self.trace,
+            self.inner.visit_f64(v),
+            self.trace.push_detail(|| Context::Float(v))
+        )
     }
 
     fn visit_hash<A>(self, map: A) -> DeResult<Self::Value>
     where
         A: HashAccess<'de>,
     {
-        let wrapped = Wrapped {
-            inner: map,
-            trace: self.trace,
-        };
-        let len = wrapped.len();
+        let len = map.len();
+        add_context!(
+            self.trace,
+            self.inner.visit_hash(WrappedHashAccess {
+                inner: map,
+                trace: self.trace,
+                entry_index: 0,
+            }),
+            self.trace.push_detail(|| Context::Hash(len))
+        )
+    }
+
+    fn visit_hash_with_default<A>(self, map: A) -> DeResult<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        let len = map.len();
         add_context!(
-            self.inner.visit_hash(wrapped),
-            self.trace.push(Context::Hash(len))
+            self.trace,
+            self.inner.visit_hash_with_default(WrappedHashAccess {
+                inner: map,
+                trace: self.trace,
+                entry_index: 0,
+            }),
+            self.trace.push_detail(|| Context::Hash(len))
         )
     }
 
@@ -117,40 +168,41 @@ where
     where
         A: ArrayAccess<'de>,
     {
-        let wrapped = Wrapped {
-            inner: array,
-            trace: self.trace,
-        };
-        let len = wrapped.len();
+        let len = array.len();
         add_context!(
-            self.inner.visit_array(wrapped),
-            self.trace.push(Context::Array(len))
+            self.trace,
+            self.inner.visit_array(Wrapped {
+                inner: array,
+                trace: self.trace,
+            }),
+            self.trace.push_detail(|| Context::Array(len))
         )
     }
 
     fn visit_string(self, string: &'de [u8]) -> DeResult<Self::Value> {
         add_context!(
+            self.trace,
             self.inner.visit_string(string),
-            self.trace.push(Context::String(
-                String::from_utf8_lossy(string).into_owned()
-            ))
+            self.trace
+                .push_detail(|| Context::String(String::from_utf8_lossy(string).into_owned()))
         )
     }
 
     fn visit_symbol(self, symbol: &'de Sym) -> DeResult<Self::Value> {
         add_context!(
+            self.trace,
             self.inner.visit_symbol(symbol),
-            self.trace.push(Context::Symbol(symbol.to_symbol()))
+            self.trace
+                .push_detail(|| Context::Symbol(symbol.to_symbol()))
         )
     }
 
     fn visit_regular_expression(self, regex: &'de [u8], flags: u8) -> DeResult<Self::Value> {
         add_context!(
+            self.trace,
             self.inner.visit_regular_expression(regex, flags),
-            self.trace.push(Context::Regex(
-                String::from_utf8_lossy(regex).into_owned(),
-                flags
-            ))
+            self.trace
+                .push_detail(|| Context::Regex(String::from_utf8_lossy(regex).into_owned(), flags))
         )
     }
 
@@ -158,15 +210,20 @@ where
     where
         A: IvarAccess<'de>,
     {
-        let wrapped = WrappedIvarAccess {
-            inner: instance_variables,
-            trace: self.trace,
-            current_field: None,
-        };
-        let len = wrapped.len();
+        let len = instance_variables.len();
         add_context!(
-            self.inner.visit_object(class, wrapped),
-            self.trace.push(Context::Object(class.to_symbol(), len))
+            self.trace,
+            self.inner.visit_object(
+                class,
+                WrappedIvarAccess {
+                    inner: instance_variables,
+                    trace: self.trace,
+                    current_field: None,
+                    field_index: 0,
+                }
+            ),
+            self.trace
+                .push_detail(|| Context::Object(class.to_symbol(), len))
         )
     }
 
@@ -174,29 +231,37 @@ where
     where
         A: IvarAccess<'de>,
     {
-        let wrapped = WrappedIvarAccess {
-            inner: members,
-            trace: self.trace,
-            current_field: None,
-        };
-        let len = wrapped.len();
+        let len = members.len();
         add_context!(
-            self.inner.visit_struct(name, wrapped),
-            self.trace.push(Context::Struct(name.to_symbol(), len))
+            self.trace,
+            self.inner.visit_struct(
+                name,
+                WrappedIvarAccess {
+                    inner: members,
+                    trace: self.trace,
+                    current_field: None,
+                    field_index: 0,
+                }
+            ),
+            self.trace
+                .push_detail(|| Context::Struct(name.to_symbol(), len))
         )
     }
 
     fn visit_class(self, class: &'de Sym) -> DeResult<Self::Value> {
         add_context!(
+            self.trace,
             self.inner.visit_class(class),
-            self.trace.push(Context::Class(class.to_symbol()))
+            self.trace.push_detail(|| Context::Class(class.to_symbol()))
         )
     }
 
     fn visit_module(self, module: &'de Sym) -> DeResult<Self::Value> {
         add_context!(
+            self.trace,
             self.inner.visit_module(module),
-            self.trace.push(Context::Module(module.to_symbol()))
+            self.trace
+                .push_detail(|| Context::Module(module.to_symbol()))
         )
     }
 
@@ -204,13 +269,13 @@ where
     where
         A: InstanceAccess<'de>,
     {
-        let wrapped = Wrapped {
-            inner: instance,
-            trace: self.trace,
-        };
         add_context!(
-            self.inner.visit_instance(wrapped),
-            self.trace.push(Context::Instance)
+            self.trace,
+            self.inner.visit_instance(Wrapped {
+                inner: instance,
+                trace: self.trace,
+            }),
+            self.trace.push_detail(|| Context::Instance)
         )
     }
 
@@ -218,10 +283,12 @@ where
     where
         D: DeserializerTrait<'de>,
     {
-        let wrapped = Deserializer::new(deserializer, self.trace);
         add_context!(
-            self.inner.visit_extended(module, wrapped),
-            self.trace.push(Context::Extended(module.to_symbol()))
+            self.trace,
+            self.inner
+                .visit_extended(module, Deserializer::new(deserializer, self.trace)),
+            self.trace
+                .push_detail(|| Context::Extended(module.to_symbol()))
         )
     }
 
@@ -229,17 +296,21 @@ where
     where
         D: DeserializerTrait<'de>,
     {
-        let wrapped = Deserializer::new(deserializer, self.trace);
         add_context!(
-            self.inner.visit_user_class(class, wrapped),
-            self.trace.push(Context::UserClass(class.to_symbol()))
+            self.trace,
+            self.inner
+                .visit_user_class(class, Deserializer::new(deserializer, self.trace)),
+            self.trace
+                .push_detail(|| Context::UserClass(class.to_symbol()))
         )
     }
 
     fn visit_user_data(self, class: &'de Sym, data: &'de [u8]) -> DeResult<Self::Value> {
         add_context!(
+            self.trace,
             self.inner.visit_user_data(class, data),
-            self.trace.push(Context::UserData(class.to_symbol()))
+            self.trace
+                .push_detail(|| Context::UserData(class.to_symbol()))
         )
     }
 
@@ -247,10 +318,12 @@ where
     where
         D: DeserializerTrait<'de>,
     {
-        let wrapped = Deserializer::new(deserializer, self.trace);
         add_context!(
-            self.inner.visit_user_marshal(class, wrapped),
-            self.trace.push(Context::UserMarshal(class.to_symbol()))
+            self.trace,
+            self.inner
+                .visit_user_marshal(class, Deserializer::new(deserializer, self.trace)),
+            self.trace
+                .push_detail(|| Context::UserMarshal(class.to_symbol()))
         )
     }
 
@@ -258,15 +331,16 @@ where
     where
         D: DeserializerTrait<'de>,
     {
-        let wrapped = Deserializer::new(deserializer, self.trace);
         add_context!(
-            self.inner.visit_data(class, wrapped),
-            self.trace.push(Context::Data(class.to_symbol()))
+            self.trace,
+            self.inner
+                .visit_data(class, Deserializer::new(deserializer, self.trace)),
+            self.trace.push_detail(|| Context::Data(class.to_symbol()))
         )
     }
 }
 
-impl<'de, 'trace, X> VisitorOption<'de> for Wrapped<'trace, X>
+impl<'de, X> VisitorOption<'de> for Wrapped<'_, X>
 where
     X: VisitorOption<'de>,
 {
@@ -285,7 +359,7 @@ where
     }
 }
 
-impl<'de, 'trace, X> VisitorInstance<'de> for Wrapped<'trace, X>
+impl<'de, X> VisitorInstance<'de> for Wrapped<'_, X>
 where
     X: VisitorInstance<'de>,
 {
@@ -314,7 +388,7 @@ impl<'de, 'trace, X> InstanceAccess<'de> for Wrapped<'trace, X>
 where
     X: InstanceAccess<'de>,
 {
-    type IvarAccess = WrappedIvarAccess<'trace, X::IvarAccess>;
+    type IvarAccess = WrappedIvarAccess<'de, 'trace, X::IvarAccess>;
 
     fn value_seed<V>(self, seed: V) -> DeResult<(V::Value, Self::IvarAccess)>
     where
@@ -329,27 +403,33 @@ where
             inner: access,
             trace: self.trace,
             current_field: None,
+            field_index: 0,
         };
         Ok((value, wrapped_access))
     }
 }
 
-struct WrappedIvarAccess<'trace, X> {
+struct WrappedIvarAccess<'de, 'trace, X> {
     inner: X,
     trace: &'trace mut Trace,
-    current_field: Option<Symbol>,
+    current_field: Option<&'de Sym>,
+    // accessors count fields at different times, so this is the index when the field was fetched
+    field_index: usize,
 }
 
-impl<'de, 'trace, X> IvarAccess<'de> for WrappedIvarAccess<'trace, X>
+impl<'de, X> IvarAccess<'de> for WrappedIvarAccess<'de, '_, X>
 where
     X: IvarAccess<'de>,
 {
     fn next_ivar(&mut self) -> DeResult<Option<&'de Sym>> {
+        let index = self.index();
         let symbol = add_context!(
+            self.trace,
             self.inner.next_ivar(),
-            self.trace.push(Context::FetchingField(self.index()))
+            self.trace.push(Context::FetchingField(index))
         )?;
-        self.current_field = symbol.map(Sym::to_symbol);
+        self.current_field = symbol;
+        self.field_index = index;
         Ok(symbol)
     }
 
@@ -357,14 +437,43 @@ where
     where
         V: DeserializeSeed<'de>,
     {
-        let wrapped_seed = Wrapped {
-            inner: seed,
-            trace: self.trace,
-        };
         add_context!(
-            self.inner.next_value_seed(wrapped_seed),
-            self.trace
-                .push(Context::Field(self.current_field.clone(), self.index()))
+            self.trace,
+            self.inner.next_value_seed(Wrapped {
+                inner: seed,
+                trace: self.trace,
+            }),
+            self.trace.push(Context::Field(
+                self.current_field.map(Sym::to_symbol),
+                self.field_index
+            ))
+        )
+    }
+
+    // the seed isn't wrapped, as errors from it are captured instead of returned
+    #[cfg(feature = "value")]
+    fn next_value_seed_or_capture<V>(&mut self, seed: V) -> DeResult<Result<V::Value, Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        add_context!(
+            self.trace,
+            self.inner.next_value_seed_or_capture(seed),
+            self.trace.push(Context::Field(
+                self.current_field.map(Sym::to_symbol),
+                self.field_index
+            ))
+        )
+    }
+
+    fn skip_value(&mut self) -> DeResult<()> {
+        add_context!(
+            self.trace,
+            self.inner.skip_value(),
+            self.trace.push(Context::Field(
+                self.current_field.map(Sym::to_symbol),
+                self.field_index
+            ))
         )
     }
 
@@ -377,7 +486,14 @@ where
     }
 }
 
-impl<'de, 'trace, X> HashAccess<'de> for Wrapped<'trace, X>
+struct WrappedHashAccess<'trace, X> {
+    inner: X,
+    trace: &'trace mut Trace,
+    // the index of the last key, as accessors count entries at different times
+    entry_index: usize,
+}
+
+impl<'de, X> HashAccess<'de> for WrappedHashAccess<'_, X>
 where
     X: HashAccess<'de>,
 {
@@ -385,12 +501,14 @@ where
     where
         K: DeserializeSeed<'de>,
     {
+        self.entry_index = self.index();
         add_context!(
+            self.trace,
             self.inner.next_key_seed(Wrapped {
                 inner: seed,
                 trace: self.trace,
             }),
-            self.trace.push(Context::HashKey(self.index()))
+            self.trace.push(Context::HashKey(self.entry_index))
         )
     }
 
@@ -399,11 +517,26 @@ where
         V: DeserializeSeed<'de>,
     {
         add_context!(
+            self.trace,
             self.inner.next_value_seed(Wrapped {
                 inner: seed,
                 trace: self.trace,
             }),
-            self.trace.push(Context::HashValue(self.index()))
+            self.trace.push(Context::HashValue(self.entry_index))
+        )
+    }
+
+    fn next_default_seed<V>(&mut self, seed: V) -> DeResult<Option<V::Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        add_context!(
+            self.trace,
+            self.inner.next_default_seed(Wrapped {
+                inner: seed,
+                trace: self.trace,
+            }),
+            self.trace.push(Context::HashDefault)
         )
     }
 
@@ -416,7 +549,7 @@ where
     }
 }
 
-impl<'de, 'trace, X> ArrayAccess<'de> for Wrapped<'trace, X>
+impl<'de, X> ArrayAccess<'de> for Wrapped<'_, X>
 where
     X: ArrayAccess<'de>,
 {
@@ -424,12 +557,14 @@ where
     where
         T: DeserializeSeed<'de>,
     {
+        let index = self.index();
         add_context!(
+            self.trace,
             self.inner.next_element_seed(Wrapped {
                 inner: seed,
                 trace: self.trace,
             }),
-            self.trace.push(Context::ArrayIndex(self.index()))
+            self.trace.push(Context::ArrayIndex(index))
         )
     }
 
@@ -442,7 +577,7 @@ where
     }
 }
 
-impl<'de, 'trace, X> DeserializeSeed<'de> for Wrapped<'trace, X>
+impl<'de, X> DeserializeSeed<'de> for Wrapped<'_, X>
 where
     X: DeserializeSeed<'de>,
 {
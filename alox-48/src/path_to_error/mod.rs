@@ -3,6 +3,8 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::fmt::Write;
+
 use crate::{
     DeError, Deserialize, DeserializerTrait, SerError, Serialize, SerializerTrait, Symbol,
 };
@@ -16,6 +18,14 @@ pub use ser::Serializer;
 /// Like a stack trace, but for deserialization.
 ///
 /// This is used to track the path to an error in a deserialization.
+///
+/// Context is only kept for the error that was actually returned.
+/// If an error is handled (by a visitor trying something else, for example),
+/// the context it left behind is discarded as soon as deserialization carries on.
+///
+/// Tracing is cheap when nothing fails, so it's fine to leave it on.
+/// [`Trace::breadcrumbs`] makes it even cheaper by only recording where the error happened,
+/// and not the values it happened in.
 #[derive(Debug, Default, Clone)]
 pub struct Trace {
     /// The context of the error.
@@ -23,19 +33,19 @@ pub struct Trace {
     /// This will be in reverse order!
     /// The context furthest down the stack is the first element.
     pub context: Vec<Context>,
+    breadcrumbs: bool,
 }
 
 /// Part of the context of the error.
 #[derive(Debug, Clone)]
 // TODO deserializer position (no clue how to do this)
-// FIXME this doesn't account for discarding errors!
 pub enum Context {
     /// Error occurred while processing a `nil`.
     Nil,
     /// Error occurred while processing a boolean.
     Bool(bool),
     /// Error occurred while processing an integer.
-    Int(i32),
+    Int(i64),
     /// Error occurred while processing a float.
     Float(f64),
 
@@ -51,6 +61,8 @@ pub enum Context {
     ///
     /// The usize is the index of the value.
     HashValue(usize),
+    /// Error occurred while processing the default value of a hash.
+    HashDefault,
 
     /// Error occurred while processing an array.
     ///
@@ -171,9 +183,99 @@ impl Trace {
         Self::default()
     }
 
+    /// Create a new trace that only records breadcrumbs.
+    ///
+    /// Breadcrumbs are the array indices, hash entries, and fields leading to the error.
+    /// The values along the way (strings, classes, and so on) aren't recorded,
+    /// so nothing is copied out of the input.
+    pub fn breadcrumbs() -> Self {
+        Self {
+            context: vec![],
+            breadcrumbs: true,
+        }
+    }
+
+    /// Does this trace only record breadcrumbs?
+    pub fn is_breadcrumbs(&self) -> bool {
+        self.breadcrumbs
+    }
+
+    /// The path to the error on a single line, like `$.@events[3].@pages[0]`.
+    ///
+    /// This is built from the breadcrumbs in the trace, so it works in either mode.
+    /// Hash values are written as `{index}`, keys as `{key index}`, and the default value of a hash as `{default}`.
+    /// Fields that couldn't be read are written as `.<index>`.
+    pub fn path(&self) -> String {
+        let mut path = String::from("$");
+        for context in self.context.iter().rev() {
+            let _ = match context {
+                Context::ArrayIndex(index) => write!(path, "[{index}]"),
+                Context::HashValue(index) => write!(path, "{{{index}}}"),
+                Context::HashKey(index) => write!(path, "{{key {index}}}"),
+                Context::HashDefault => write!(path, "{{default}}"),
+                Context::Field(Some(field), _) | Context::WritingField(field, _) => {
+                    write!(path, ".{}", field.as_str())
+                }
+                Context::Field(None, index) | Context::FetchingField(index) => {
+                    write!(path, ".<{index}>")
+                }
+                _ => Ok(()),
+            };
+        }
+        path
+    }
+
+    /// The most specific context of the error, i.e. the one furthest down the stack.
+    ///
+    /// Returns `None` if the trace is empty.
+    pub fn location(&self) -> Option<&Context> {
+        self.context.first()
+    }
+
+    // An empty trace with the same mode as this one.
+    pub(crate) fn nested(&self) -> Self {
+        Self {
+            context: vec![],
+            breadcrumbs: self.breadcrumbs,
+        }
+    }
+
+    // Push a breadcrumb. These are always recorded.
     pub(crate) fn push(&mut self, context: Context) {
+        debug_assert!(context.is_breadcrumb());
         self.context.push(context);
     }
+
+    // Push the value an error happened in, unless only breadcrumbs are recorded.
+    pub(crate) fn push_detail(&mut self, context: impl FnOnce() -> Context) {
+        if !self.breadcrumbs {
+            self.context.push(context());
+        }
+    }
+
+    // Anything in the trace when an operation starts or succeeds is from an error that was handled,
+    // as context is only pushed while an error is being returned.
+    pub(crate) fn discard_handled(&mut self) {
+        self.context.clear();
+    }
+}
+
+impl Context {
+    /// Is this context a breadcrumb?
+    ///
+    /// Breadcrumbs say where in their parent an error happened, rather than what it happened in.
+    pub fn is_breadcrumb(&self) -> bool {
+        matches!(
+            self,
+            Context::HashKey(_)
+                | Context::HashValue(_)
+                | Context::HashDefault
+                | Context::ArrayIndex(_)
+                | Context::FetchingField(_)
+                | Context::WritingField(..)
+                | Context::Field(..)
+        )
+    }
 }
 
 impl std::fmt::Display for Trace {
@@ -189,18 +291,19 @@ impl std::fmt::Display for Context {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Context::{
             Array, ArrayIndex, Bool, Class, Data, Extended, FetchingField, Field, Float, Hash,
-            HashKey, HashValue, Instance, Int, Module, Nil, Object, Regex, String, Struct, Symbol,
-            UserClass, UserData, UserMarshal, WritingField, WritingFields,
+            HashDefault, HashKey, HashValue, Instance, Int, Module, Nil, Object, Regex, String,
+            Struct, Symbol, UserClass, UserData, UserMarshal, WritingField, WritingFields,
         };
         match self {
             Nil => write!(f, "while processing a nil"),
             Bool(v) => write!(f, "while processing a boolean: {v}"),
             Int(v) => write!(f, "while processing an integer: {v}"),
             Float(v) => write!(f, "while processing a float: {v}"),
-            Hash(len) => write!(f, "while processing a hash with {len} entries",),
-            HashKey(index) => write!(f, "while processing the {index} key of a hash",),
+            Hash(len) => write!(f, "while processing a hash with {len} entries"),
+            HashKey(index) => write!(f, "while processing the {index} key of a hash"),
             HashValue(index) => write!(f, "while processing the {index} value of a hash"),
-            Array(len) => write!(f, "while processing an array with {len} elements",),
+            HashDefault => write!(f, "while processing the default value of a hash"),
+            Array(len) => write!(f, "while processing an array with {len} elements"),
             ArrayIndex(index) => write!(f, "while processing the {index} element of an array"),
             String(s) => write!(f, "while processing a string: {s}"),
             Symbol(s) => write!(f, "while processing a symbol: {s}"),
@@ -232,14 +335,18 @@ impl std::fmt::Display for Context {
 }
 
 macro_rules! add_context {
-    ($erroring_expr:expr, $context:expr) => {
+    ($trace:expr, $erroring_expr:expr, $context:expr) => {{
+        $trace.discard_handled();
         match $erroring_expr {
-            Ok(value) => Ok(value),
+            Ok(value) => {
+                $trace.discard_handled();
+                Ok(value)
+            }
             Err(err) => {
                 $context;
                 Err(err)
             }
         }
-    };
+    }};
 }
 pub(crate) use add_context;
@@ -7,8 +7,8 @@ use std::cell::Cell;
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 use super::{add_context, Context, Trace};
 use crate::{
-    SerResult, Serialize, SerializeArray, SerializeHash, SerializeIvars, SerializerTrait, Sym,
-    Symbol,
+    RawValue, SerResult, Serialize, SerializeArray, SerializeHash, SerializeIvars, SerializerTrait,
+    Sym, Symbol,
 };
 
 /// A serializer that tracks the path to an error.
@@ -37,8 +37,8 @@ pub struct WrappedIvars<'trace, X> {
 
     trace: &'trace mut Trace,
     // because of the way serializers work, we can't actually add the calling context like with deserializers
-    // so we have to store it here
-    calling_context: Context,
+    // so we have to store it here (unless the trace only records breadcrumbs)
+    calling_context: Option<Context>,
 
     symbol: Option<Symbol>,
     len: usize,
@@ -71,36 +71,63 @@ where
 
     fn serialize_nil(self) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_nil(),
-            self.trace.push(Context::Nil)
+            self.trace.push_detail(|| Context::Nil)
         )
     }
 
     fn serialize_bool(self, v: bool) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_bool(v),
-            self.trace.push(Context::Bool(v))
+            self.trace.push_detail(|| Context::Bool(v))
         )
     }
 
     fn serialize_i32(self, v: i32) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_i32(v),
-            self.trace.push(Context::Int(v))
+            self.trace.push_detail(|| Context::Int(v.into()))
+        )
+    }
+
+    fn serialize_i64(self, v: i64) -> SerResult<Self::Ok> {
+        add_context!(
+            self.trace,
+            self.serializer.serialize_i64(v),
+            self.trace.push_detail(|| Context::Int(v))
         )
     }
 
     fn serialize_f64(self, v: f64) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_f64(v),
-            self.trace.push(Context::Float(v))
+            self.trace.push_detail(|| Context::Float(v))
         )
     }
 
     fn serialize_hash(self, len: usize) -> SerResult<Self::SerializeHash> {
         add_context!(
+            self.trace,
             self.serializer.serialize_hash(len),
-            self.trace.push(Context::Hash(len))
+            self.trace.push_detail(|| Context::Hash(len))
+        )
+        .map(|inner| Wrapped {
+            inner,
+            trace: self.trace,
+            len,
+            index: 0,
+        })
+    }
+
+    fn serialize_hash_with_default(self, len: usize) -> SerResult<Self::SerializeHash> {
+        add_context!(
+            self.trace,
+            self.serializer.serialize_hash_with_default(len),
+            self.trace.push_detail(|| Context::Hash(len))
         )
         .map(|inner| Wrapped {
             inner,
@@ -112,8 +139,9 @@ where
 
     fn serialize_array(self, len: usize) -> SerResult<Self::SerializeArray> {
         add_context!(
+            self.trace,
             self.serializer.serialize_array(len),
-            self.trace.push(Context::Array(len))
+            self.trace.push_detail(|| Context::Array(len))
         )
         .map(|inner| Wrapped {
             inner,
@@ -125,38 +153,43 @@ where
 
     fn serialize_string(self, data: &[u8]) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_string(data),
             self.trace
-                .push(Context::String(String::from_utf8_lossy(data).to_string()))
+                .push_detail(|| Context::String(String::from_utf8_lossy(data).to_string()))
         )
     }
 
     fn serialize_symbol(self, sym: &crate::Sym) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_symbol(sym),
-            self.trace.push(Context::Symbol(sym.to_symbol()))
+            self.trace.push_detail(|| Context::Symbol(sym.to_symbol()))
         )
     }
 
     fn serialize_regular_expression(self, regex: &[u8], flags: u8) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_regular_expression(regex, flags),
-            self.trace.push(Context::Regex(
-                String::from_utf8_lossy(regex).to_string(),
-                flags
-            ))
+            self.trace
+                .push_detail(|| Context::Regex(String::from_utf8_lossy(regex).to_string(), flags))
         )
     }
 
     fn serialize_object(self, class: &crate::Sym, len: usize) -> SerResult<Self::SerializeIvars> {
+        let calling_context =
+            (!self.trace.is_breadcrumbs()).then(|| Context::Object(class.to_symbol(), len));
         add_context!(
+            self.trace,
             self.serializer.serialize_object(class, len),
-            self.trace.push(Context::Object(class.to_symbol(), len))
+            self.trace
+                .push_detail(|| Context::Object(class.to_symbol(), len))
         )
         .map(|inner| WrappedIvars {
             inner,
             trace: self.trace,
-            calling_context: Context::Object(class.to_symbol(), len),
+            calling_context,
             symbol: None,
             len,
             index: 0,
@@ -164,14 +197,18 @@ where
     }
 
     fn serialize_struct(self, name: &crate::Sym, len: usize) -> SerResult<Self::SerializeIvars> {
+        let calling_context =
+            (!self.trace.is_breadcrumbs()).then(|| Context::Struct(name.to_symbol(), len));
         add_context!(
+            self.trace,
             self.serializer.serialize_struct(name, len),
-            self.trace.push(Context::Struct(name.to_symbol(), len))
+            self.trace
+                .push_detail(|| Context::Struct(name.to_symbol(), len))
         )
         .map(|inner| WrappedIvars {
             inner,
             trace: self.trace,
-            calling_context: Context::Struct(name.to_symbol(), len),
+            calling_context,
             symbol: None,
             len,
             index: 0,
@@ -180,15 +217,18 @@ where
 
     fn serialize_class(self, class: &crate::Sym) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_class(class),
-            self.trace.push(Context::Class(class.to_symbol()))
+            self.trace.push_detail(|| Context::Class(class.to_symbol()))
         )
     }
 
     fn serialize_module(self, module: &crate::Sym) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_module(module),
-            self.trace.push(Context::Module(module.to_symbol()))
+            self.trace
+                .push_detail(|| Context::Module(module.to_symbol()))
         )
     }
 
@@ -196,21 +236,26 @@ where
     where
         V: Serialize + ?Sized,
     {
-        let trace = Cell::default();
+        let calling_context = (!self.trace.is_breadcrumbs()).then_some(Context::Instance);
+        let trace = Cell::new(self.trace.nested());
         let wrapped = WrappedSerialize {
             inner: value,
             trace: &trace,
         };
 
-        add_context!(self.serializer.serialize_instance(&wrapped, len), {
-            let trace = trace.into_inner();
-            self.trace.context.extend(trace.context);
-            self.trace.push(Context::Instance);
-        })
+        add_context!(
+            self.trace,
+            self.serializer.serialize_instance(&wrapped, len),
+            {
+                let trace = trace.into_inner();
+                self.trace.context.extend(trace.context);
+                self.trace.push_detail(|| Context::Instance);
+            }
+        )
         .map(|inner| WrappedIvars {
             inner,
             trace: self.trace,
-            calling_context: Context::Instance,
+            calling_context,
             symbol: None,
             len,
             index: 0,
@@ -221,40 +266,52 @@ where
     where
         V: Serialize + ?Sized,
     {
-        let trace = Cell::default();
+        let trace = Cell::new(self.trace.nested());
         let wrapped = WrappedSerialize {
             inner: value,
             trace: &trace,
         };
 
-        add_context!(self.serializer.serialize_extended(module, &wrapped), {
-            let trace = trace.into_inner();
-            self.trace.context.extend(trace.context);
-            self.trace.push(Context::Extended(module.to_symbol()));
-        })
+        add_context!(
+            self.trace,
+            self.serializer.serialize_extended(module, &wrapped),
+            {
+                let trace = trace.into_inner();
+                self.trace.context.extend(trace.context);
+                self.trace
+                    .push_detail(|| Context::Extended(module.to_symbol()));
+            }
+        )
     }
 
     fn serialize_user_class<V>(self, class: &crate::Sym, value: &V) -> SerResult<Self::Ok>
     where
         V: Serialize + ?Sized,
     {
-        let trace = Cell::default();
+        let trace = Cell::new(self.trace.nested());
         let wrapped = WrappedSerialize {
             inner: value,
             trace: &trace,
         };
 
-        add_context!(self.serializer.serialize_user_class(class, &wrapped), {
-            let trace = trace.into_inner();
-            self.trace.context.extend(trace.context);
-            self.trace.push(Context::UserClass(class.to_symbol()));
-        })
+        add_context!(
+            self.trace,
+            self.serializer.serialize_user_class(class, &wrapped),
+            {
+                let trace = trace.into_inner();
+                self.trace.context.extend(trace.context);
+                self.trace
+                    .push_detail(|| Context::UserClass(class.to_symbol()));
+            }
+        )
     }
 
     fn serialize_user_data(self, class: &crate::Sym, data: &[u8]) -> SerResult<Self::Ok> {
         add_context!(
+            self.trace,
             self.serializer.serialize_user_data(class, data),
-            self.trace.push(Context::UserData(class.to_symbol()))
+            self.trace
+                .push_detail(|| Context::UserData(class.to_symbol()))
         )
     }
 
@@ -262,38 +319,51 @@ where
     where
         V: Serialize + ?Sized,
     {
-        let trace = Cell::default();
+        let trace = Cell::new(self.trace.nested());
         let wrapped = WrappedSerialize {
             inner: value,
             trace: &trace,
         };
 
-        add_context!(self.serializer.serialize_user_marshal(class, &wrapped), {
-            let trace = trace.into_inner();
-            self.trace.context.extend(trace.context);
-            self.trace.push(Context::UserMarshal(class.to_symbol()));
-        })
+        add_context!(
+            self.trace,
+            self.serializer.serialize_user_marshal(class, &wrapped),
+            {
+                let trace = trace.into_inner();
+                self.trace.context.extend(trace.context);
+                self.trace
+                    .push_detail(|| Context::UserMarshal(class.to_symbol()));
+            }
+        )
     }
 
     fn serialize_data<V>(self, class: &crate::Sym, value: &V) -> SerResult<Self::Ok>
     where
         V: Serialize + ?Sized,
     {
-        let trace = Cell::default();
+        let trace = Cell::new(self.trace.nested());
         let wrapped = WrappedSerialize {
             inner: value,
             trace: &trace,
         };
 
-        add_context!(self.serializer.serialize_data(class, &wrapped), {
-            let trace = trace.into_inner();
-            self.trace.context.extend(trace.context);
-            self.trace.push(Context::Data(class.to_symbol()));
-        })
+        add_context!(
+            self.trace,
+            self.serializer.serialize_data(class, &wrapped),
+            {
+                let trace = trace.into_inner();
+                self.trace.context.extend(trace.context);
+                self.trace.push_detail(|| Context::Data(class.to_symbol()));
+            }
+        )
+    }
+
+    fn serialize_raw(self, raw: &RawValue<'_>) -> SerResult<Self::Ok> {
+        self.serializer.serialize_raw(raw)
     }
 }
 
-impl<'trace, X> SerializeArray for Wrapped<'trace, X>
+impl<X> SerializeArray for Wrapped<'_, X>
 where
     X: SerializeArray,
 {
@@ -303,15 +373,15 @@ where
     where
         T: Serialize + ?Sized,
     {
-        let trace = Cell::default();
+        let trace = Cell::new(self.trace.nested());
         let wrapped = WrappedSerialize {
             inner: v,
             trace: &trace,
         };
 
         self.index += 1;
-        add_context!(self.inner.serialize_element(&wrapped), {
-            self.trace.push(Context::Array(self.len));
+        add_context!(self.trace, self.inner.serialize_element(&wrapped), {
+            self.trace.push_detail(|| Context::Array(self.len));
             let trace = trace.into_inner();
             self.trace.context.extend(trace.context);
             self.trace.push(Context::ArrayIndex(self.index - 1));
@@ -319,11 +389,15 @@ where
     }
 
     fn end(self) -> SerResult<Self::Ok> {
-        add_context!(self.inner.end(), self.trace.push(Context::Array(self.len)))
+        add_context!(
+            self.trace,
+            self.inner.end(),
+            self.trace.push_detail(|| Context::Array(self.len))
+        )
     }
 }
 
-impl<'trace, X> SerializeHash for Wrapped<'trace, X>
+impl<X> SerializeHash for Wrapped<'_, X>
 where
     X: SerializeHash,
 {
@@ -333,14 +407,14 @@ where
     where
         T: Serialize + ?Sized,
     {
-        let trace = Cell::default();
+        let trace = Cell::new(self.trace.nested());
         let wrapped = WrappedSerialize {
             inner: v,
             trace: &trace,
         };
 
-        add_context!(self.inner.serialize_key(&wrapped), {
-            self.trace.push(Context::Hash(self.len));
+        add_context!(self.trace, self.inner.serialize_key(&wrapped), {
+            self.trace.push_detail(|| Context::Hash(self.len));
             let trace = trace.into_inner();
             self.trace.context.extend(trace.context);
             self.trace.push(Context::HashKey(self.index));
@@ -351,27 +425,49 @@ where
     where
         T: Serialize + ?Sized,
     {
-        let trace = Cell::default();
+        let trace = Cell::new(self.trace.nested());
         let wrapped = WrappedSerialize {
             inner: v,
             trace: &trace,
         };
 
         self.index += 1;
-        add_context!(self.inner.serialize_value(&wrapped), {
-            self.trace.push(Context::Hash(self.len));
+        add_context!(self.trace, self.inner.serialize_value(&wrapped), {
+            self.trace.push_detail(|| Context::Hash(self.len));
             let trace = trace.into_inner();
             self.trace.context.extend(trace.context);
             self.trace.push(Context::HashValue(self.index - 1));
         })
     }
 
+    fn serialize_default<T>(&mut self, v: &T) -> SerResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let trace = Cell::new(self.trace.nested());
+        let wrapped = WrappedSerialize {
+            inner: v,
+            trace: &trace,
+        };
+
+        add_context!(self.trace, self.inner.serialize_default(&wrapped), {
+            self.trace.push_detail(|| Context::Hash(self.len));
+            let trace = trace.into_inner();
+            self.trace.context.extend(trace.context);
+            self.trace.push(Context::HashDefault);
+        })
+    }
+
     fn end(self) -> SerResult<Self::Ok> {
-        add_context!(self.inner.end(), self.trace.push(Context::Hash(self.len)))
+        add_context!(
+            self.trace,
+            self.inner.end(),
+            self.trace.push_detail(|| Context::Hash(self.len))
+        )
     }
 }
 
-impl<'trace, X> SerializeIvars for WrappedIvars<'trace, X>
+impl<X> SerializeIvars for WrappedIvars<'_, X>
 where
     X: SerializeIvars,
 {
@@ -379,8 +475,8 @@ where
 
     fn serialize_field(&mut self, k: &Sym) -> SerResult<()> {
         self.symbol = Some(k.to_symbol());
-        add_context!(self.inner.serialize_field(k), {
-            self.trace.push(self.calling_context.clone());
+        add_context!(self.trace, self.inner.serialize_field(k), {
+            self.trace.context.extend(self.calling_context.clone());
             self.trace
                 .push(Context::WritingField(k.to_symbol(), self.index));
         })
@@ -390,18 +486,18 @@ where
     where
         V: Serialize + ?Sized,
     {
-        let trace = Cell::default();
+        let trace = Cell::new(self.trace.nested());
         let wrapped = WrappedSerialize {
             inner: v,
             trace: &trace,
         };
 
         self.index += 1;
-        add_context!(self.inner.serialize_value(&wrapped), {
+        add_context!(self.trace, self.inner.serialize_value(&wrapped), {
             let trace = trace.into_inner();
             self.trace.context.extend(trace.context);
             {
-                self.trace.push(self.calling_context.clone());
+                self.trace.context.extend(self.calling_context.clone());
                 self.trace
                     .push(Context::Field(self.symbol.clone(), self.index - 1));
             };
@@ -409,14 +505,14 @@ where
     }
 
     fn end(self) -> SerResult<Self::Ok> {
-        add_context!(self.inner.end(), {
-            self.trace.push(self.calling_context.clone());
-            self.trace.push(Context::WritingFields(self.len));
+        add_context!(self.trace, self.inner.end(), {
+            self.trace.context.extend(self.calling_context.clone());
+            self.trace.push_detail(|| Context::WritingFields(self.len));
         })
     }
 }
 
-impl<'trace, X> Serialize for WrappedSerialize<'trace, X>
+impl<X> Serialize for WrappedSerialize<'_, X>
 where
     X: Serialize,
 {
@@ -424,7 +520,7 @@ where
     where
         S: SerializerTrait,
     {
-        let mut trace = Trace::new();
+        let mut trace = self.trace.take();
         let result = self
             .inner
             .serialize(Serializer::new(serializer, &mut trace));
@@ -0,0 +1,27 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The extension traits for the types alox-48 re-exports from alox-48-types.
+//!
+//! Types like [`Object`](crate::Object) and [`Value`](crate::Value) are defined in alox-48-types,
+//! so the helpers alox-48 adds to them (like [`ObjectExt::builder`] and [`ValueExt::deserialize_into`]) are trait methods,
+//! which only work with the trait in scope. Glob importing this module brings in all of them.
+//!
+//! ```
+//! # #[cfg(feature = "value")] {
+//! use alox_48::{prelude::*, Object, Value};
+//!
+//! let actor = Object::builder("RPG::Actor").field("name", "Aluxes").build();
+//! let value = Value::from_serialize(&actor)?;
+//! let name: String = value.into_object().unwrap().get("@name")?;
+//! assert_eq!(name, "Aluxes");
+//! # }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub use crate::UserdataExt;
+#[cfg(feature = "value")]
+pub use crate::{InstanceExt, ObjectExt, RbHashExt, RbStructExt, ValueExt};
@@ -0,0 +1,77 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::{
+    de::Result as DeResult, ser::Result as SerResult, Deserialize, DeserializerTrait, Serialize,
+    SerializerTrait, Sym,
+};
+
+/// The exact bytes of a single value in some marshal data.
+///
+/// Deserializing a `RawValue` skips over a value and keeps its bytes untouched,
+/// and serializing it writes those same bytes back out.
+/// This is useful for passing through parts of some data you don't care about without losing anything.
+///
+/// Only [`Deserializer`](crate::Deserializer) and [`Serializer`](crate::Serializer) support raw values.
+///
+/// Symbol and object links inside a raw value refer to the data it was read from,
+/// so it will only be correct when written back into the same place in equivalent data.
+/// If the value itself is an object link, only the link is captured.
+///
+/// ```
+/// # #[cfg(feature = "value")] {
+/// #[derive(alox_48::Deserialize, alox_48::Serialize)]
+/// struct Test<'a> {
+///     id: i32,
+///     rest: alox_48::RawValue<'a>,
+/// }
+///
+/// let bytes = alox_48::to_bytes(alox_48::Value::Object(alox_48::Object {
+///     class: "Test".into(),
+///     fields: [
+///         ("@id".into(), alox_48::Value::Integer(1)),
+///         ("@rest".into(), alox_48::Value::Array(vec![1.into(), 2.5.into()])),
+///     ]
+///     .into_iter()
+///     .collect(),
+/// }))
+/// .unwrap();
+///
+/// let test: Test<'_> = alox_48::from_bytes(&bytes).unwrap();
+/// assert_eq!(alox_48::to_bytes(&test).unwrap(), bytes);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawValue<'de> {
+    pub(crate) bytes: &'de [u8],
+    // symbols first seen inside this value, which a serializer needs to know about to keep symbol links correct
+    pub(crate) symbols: Vec<&'de Sym>,
+}
+
+impl<'de> RawValue<'de> {
+    /// Returns the bytes of this value.
+    pub fn get(&self) -> &'de [u8] {
+        self.bytes
+    }
+}
+
+impl<'de> Deserialize<'de> for RawValue<'de> {
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize_raw()
+    }
+}
+
+impl Serialize for RawValue<'_> {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serializer.serialize_raw(self)
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Utc};
+
+use super::{RbTime, TIME};
+use crate::{
+    de::{Result as DeResult, Unexpected},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializerTrait, Serialize, SerializerTrait,
+};
+
+impl Serialize for DateTime<Utc> {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        // chrono represents leap seconds as a nanosecond overflow
+        let (second, nanosecond) = match self.nanosecond() {
+            nanos if nanos >= 1_000_000_000 => (60, nanos - 1_000_000_000),
+            nanos => (self.second() as u8, nanos),
+        };
+
+        RbTime {
+            year: self.year(),
+            month: self.month() as u8,
+            day: self.day() as u8,
+            hour: self.hour() as u8,
+            minute: self.minute() as u8,
+            second,
+            nanosecond,
+            offset: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DateTime<Utc> {
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let time = RbTime::deserialize(deserializer)?;
+
+        let (second, nanosecond) = match time.second {
+            60 => (59, time.nanosecond + 1_000_000_000),
+            second => (second, time.nanosecond),
+        };
+
+        // the fields are always utc, the offset only affects how ruby displays the time
+        NaiveDate::from_ymd_opt(time.year, time.month.into(), time.day.into())
+            .and_then(|date| {
+                date.and_hms_nano_opt(
+                    time.hour.into(),
+                    time.minute.into(),
+                    second.into(),
+                    nanosecond,
+                )
+            })
+            .map(|naive| Utc.from_utc_datetime(&naive))
+            .ok_or_else(|| {
+                DeError::invalid_value(Unexpected::UserData(TIME), &"a valid date and time")
+            })
+    }
+}
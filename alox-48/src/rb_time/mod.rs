@@ -0,0 +1,211 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Ruby dumps `Time` as 8 bytes of user data holding the broken-down UTC time,
+// with sub-microsecond precision and the utc offset stored as ivars on the user data.
+// See `time_mdump` and `time_mload` in ruby's time.c.
+
+#[cfg(feature = "chrono")]
+mod chrono;
+#[cfg(feature = "time")]
+mod time;
+
+use crate::{
+    de::{Ignored, Result as DeResult, Unexpected},
+    ser::{Kind as SerKind, Result as SerResult},
+    DeError, Deserialize, DeserializerTrait, InstanceAccess, IvarAccess, SerError, Serialize,
+    SerializeByteString, SerializeIvars, SerializerTrait, Sym, Visitor,
+};
+
+pub(crate) const TIME: &Sym = Sym::new("Time");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RbTime {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    // 60 during a leap second
+    pub second: u8,
+    pub nanosecond: u32,
+    // offset from utc in seconds, `None` if the time is in utc
+    pub offset: Option<i32>,
+}
+
+struct TimeData([u8; 8]);
+
+struct TimeVisitor;
+
+impl RbTime {
+    fn pack(&self) -> SerResult<[u8; 8]> {
+        let year = self
+            .year
+            .checked_sub(1900)
+            .and_then(|y| u32::try_from(y).ok())
+            .filter(|&y| y <= 0xFFFF)
+            .ok_or(SerError {
+                kind: SerKind::YearOutOfRange(self.year),
+            })?;
+
+        let p = 1 << 31
+            | u32::from(self.offset.is_none()) << 30
+            | year << 14
+            | u32::from(self.month - 1) << 10
+            | u32::from(self.day) << 5
+            | u32::from(self.hour);
+        let s =
+            u32::from(self.minute) << 26 | u32::from(self.second) << 20 | (self.nanosecond / 1000);
+
+        let mut data = [0; 8];
+        data[..4].copy_from_slice(&p.to_le_bytes());
+        data[4..].copy_from_slice(&s.to_le_bytes());
+        Ok(data)
+    }
+
+    fn unpack(data: &[u8]) -> DeResult<Self> {
+        let Ok(data) = <[u8; 8]>::try_from(data) else {
+            return Err(DeError::invalid_length(data.len(), &"8 bytes of Time data"));
+        };
+        let p = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let s = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+
+        // ruby before 1.8 dumped seconds and microseconds since the epoch instead
+        if p & (1 << 31) == 0 {
+            return Err(DeError::invalid_value(
+                Unexpected::UserData(TIME),
+                &"a Time dumped by ruby 1.8 or newer",
+            ));
+        }
+
+        Ok(Self {
+            year: ((p >> 14) & 0xFFFF) as i32 + 1900,
+            month: ((p >> 10) & 0xF) as u8 + 1,
+            day: ((p >> 5) & 0x1F) as u8,
+            hour: (p & 0x1F) as u8,
+            minute: ((s >> 26) & 0x3F) as u8,
+            second: ((s >> 20) & 0x3F) as u8,
+            nanosecond: (s & 0xF_FFFF) * 1000,
+            offset: None,
+        })
+    }
+}
+
+// submicro is the nanoseconds past the microsecond as packed BCD, i.e. 0x12 0x30 for 123ns
+fn encode_submicro(nanos: u32) -> ([u8; 2], usize) {
+    let buf = [
+        ((nanos / 100 % 10) << 4 | (nanos / 10 % 10)) as u8,
+        ((nanos % 10) << 4) as u8,
+    ];
+    let len = if buf[1] == 0 { 1 } else { 2 };
+    (buf, len)
+}
+
+fn decode_submicro(buf: &[u8]) -> u32 {
+    let digit = |i: usize, shift: u8| buf.get(i).map_or(0, |b| u32::from(b >> shift & 0xF));
+    digit(0, 4) * 100 + digit(0, 0) * 10 + digit(1, 4)
+}
+
+impl Serialize for TimeData {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serializer.serialize_user_data(TIME, &self.0)
+    }
+}
+
+impl Serialize for RbTime {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        let data = TimeData(self.pack()?);
+        let submicro = self.nanosecond % 1000;
+
+        let len = usize::from(self.offset.is_some()) + if submicro == 0 { 0 } else { 3 };
+        if len == 0 {
+            return data.serialize(serializer);
+        }
+
+        let mut ivars = serializer.serialize_instance(&data, len)?;
+        if let Some(offset) = self.offset {
+            ivars.serialize_entry(Sym::new("offset"), &offset)?;
+        }
+        if submicro != 0 {
+            // newer rubies read nano_num/nano_den, submicro is kept for 1.9.1
+            let (buf, len) = encode_submicro(submicro);
+            ivars.serialize_entry(Sym::new("nano_num"), &submicro)?;
+            ivars.serialize_entry(Sym::new("nano_den"), &1)?;
+            ivars.serialize_entry(Sym::new("submicro"), &SerializeByteString(&buf[..len]))?;
+        }
+        ivars.end()
+    }
+}
+
+impl<'de> Visitor<'de> for TimeVisitor {
+    type Value = RbTime;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a Time")
+    }
+
+    fn visit_user_data(self, class: &'de Sym, data: &'de [u8]) -> DeResult<Self::Value> {
+        if class != TIME {
+            return Err(DeError::wrong_class(TIME, class));
+        }
+        RbTime::unpack(data)
+    }
+
+    fn visit_instance<A>(self, instance: A) -> DeResult<Self::Value>
+    where
+        A: InstanceAccess<'de>,
+    {
+        let (mut time, mut ivars) = instance.value::<RbTime>()?;
+
+        let mut nano_num = None;
+        let mut nano_den = None;
+        let mut submicro = None;
+        while let Some(ivar) = ivars.next_ivar()? {
+            match ivar.as_str() {
+                "offset" => time.offset = Some(ivars.next_value()?),
+                "nano_num" => nano_num = Some(ivars.next_value::<i32>()?),
+                "nano_den" => nano_den = Some(ivars.next_value::<i32>()?),
+                "submicro" => submicro = Some(ivars.next_value::<&'de [u8]>()?),
+                _ => {
+                    ivars.next_value::<Ignored>()?;
+                }
+            }
+        }
+
+        let extra = match (nano_num, nano_den, submicro) {
+            (Some(num), Some(den), _) => num
+                .checked_div(den)
+                .and_then(|nanos| u32::try_from(nanos).ok())
+                .filter(|&nanos| nanos < 1000)
+                .ok_or_else(|| {
+                    DeError::invalid_value(
+                        Unexpected::Integer(num.into()),
+                        &"less than 1000 nanoseconds",
+                    )
+                })?,
+            (_, _, Some(submicro)) => decode_submicro(submicro),
+            _ => 0,
+        };
+        time.nanosecond += extra;
+
+        Ok(time)
+    }
+}
+
+impl<'de> Deserialize<'de> for RbTime {
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(TimeVisitor)
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+use super::{RbTime, TIME};
+use crate::{
+    de::{Result as DeResult, Unexpected},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializerTrait, Serialize, SerializerTrait,
+};
+
+impl Serialize for OffsetDateTime {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        let offset = self.offset();
+        let utc = self.to_offset(UtcOffset::UTC);
+
+        RbTime {
+            year: utc.year(),
+            month: utc.month().into(),
+            day: utc.day(),
+            hour: utc.hour(),
+            minute: utc.minute(),
+            second: utc.second(),
+            nanosecond: utc.nanosecond(),
+            offset: (!offset.is_utc()).then(|| offset.whole_seconds()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OffsetDateTime {
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let time = RbTime::deserialize(deserializer)?;
+
+        let invalid =
+            |_| DeError::invalid_value(Unexpected::UserData(TIME), &"a valid date and time");
+
+        let month = Month::try_from(time.month).map_err(invalid)?;
+        let date = Date::from_calendar_date(time.year, month, time.day).map_err(invalid)?;
+        let clock = Time::from_hms_nano(time.hour, time.minute, time.second, time.nanosecond)
+            .map_err(invalid)?;
+        let utc = PrimitiveDateTime::new(date, clock).assume_utc();
+
+        match time.offset {
+            Some(offset) => {
+                let offset = UtcOffset::from_whole_seconds(offset).map_err(|_| {
+                    DeError::invalid_value(Unexpected::Integer(offset.into()), &"a utc offset")
+                })?;
+                utc.checked_to_offset(offset).ok_or_else(|| {
+                    DeError::invalid_value(
+                        Unexpected::UserData(TIME),
+                        &"a Time in range with its utc offset",
+                    )
+                })
+            }
+            None => Ok(utc),
+        }
+    }
+}
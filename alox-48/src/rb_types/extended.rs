@@ -0,0 +1,60 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::marker::PhantomData;
+
+use crate::{
+    de::Result as DeResult, ser::Result as SerResult, Deserialize, DeserializerTrait, Extended,
+    Serialize, SerializerTrait, Sym, Visitor,
+};
+
+struct ExtendedVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for ExtendedVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = Extended<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("an extended object")
+    }
+
+    fn visit_extended<D>(self, module: &'de Sym, deserializer: D) -> DeResult<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+        Ok(Extended {
+            module: module.to_symbol(),
+            value,
+        })
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Extended<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(ExtendedVisitor(PhantomData))
+    }
+}
+
+impl<T> Serialize for Extended<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serializer.serialize_extended(&self.module, &self.value)
+    }
+}
@@ -7,17 +7,79 @@
 use std::marker::PhantomData;
 
 use crate::{
-    de::Result as DeResult, Deserialize, DeserializerTrait, IvarAccess, RbFields, RbString,
-    Serialize, SerializeIvars, VisitorInstance,
+    de::{cautious_capacity, Result as DeResult},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializeOwned, DeserializerTrait, Instance, IvarAccess, RbFields,
+    Serialize, SerializeIvars, Sym, Symbol, Value, VisitorInstance,
 };
 
-/// A type representing a ruby object with extra instance variables.
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Instance<T> {
-    /// The inner value.
-    pub value: T,
-    /// The extra instance variables attached to this object.
-    pub fields: RbFields,
+/// Conversions between untyped instances (like the one in [`Value::Instance`]) and typed instances.
+///
+/// ```
+/// use alox_48::{Instance, InstanceExt, RbFields, RbString, Value};
+///
+/// let fields = RbFields::from_iter([("E".into(), true.into())]);
+/// let untyped = Instance::from_typed(RbString::from("hello"), fields).unwrap();
+/// assert_eq!(*untyped.value, Value::String("hello".into()));
+///
+/// let utf8: bool = untyped.get("E").unwrap();
+/// assert!(utf8);
+///
+/// let typed: Instance<RbString> = untyped.try_map().unwrap();
+/// assert_eq!(typed.value, RbString::from("hello"));
+/// ```
+pub trait InstanceExt: Sized {
+    /// Deserialize the inner value into a `T`, keeping the instance variables as-is.
+    fn try_map<T>(self) -> DeResult<Instance<T>>
+    where
+        T: DeserializeOwned;
+
+    /// Serialize `value` into an untyped instance with the provided instance variables.
+    fn from_typed<T>(value: T, fields: RbFields) -> SerResult<Self>
+    where
+        T: Serialize;
+
+    /// Deserialize the instance variable `name` into a `T`, with [`from_value`](crate::from_value).
+    ///
+    /// # Errors
+    ///
+    /// Errors if there is no instance variable named `name`, or if it can't be deserialized into a `T`.
+    fn get<'de, T>(&'de self, name: &str) -> DeResult<T>
+    where
+        T: Deserialize<'de>;
+}
+
+impl InstanceExt for Instance<Box<Value>> {
+    fn try_map<T>(self) -> DeResult<Instance<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let value = crate::from_value(&self.value)?;
+        Ok(Instance {
+            value,
+            fields: self.fields,
+        })
+    }
+
+    fn from_typed<T>(value: T, fields: RbFields) -> SerResult<Self>
+    where
+        T: Serialize,
+    {
+        let value = crate::to_value(value)?;
+        Ok(Instance {
+            value: Box::new(value),
+            fields,
+        })
+    }
+
+    fn get<'de, T>(&'de self, name: &str) -> DeResult<T>
+    where
+        T: Deserialize<'de>,
+    {
+        let value = super::find_field(&self.fields, name)
+            .ok_or_else(|| DeError::missing_field(Sym::new(name)))?;
+        crate::from_value(value)
+    }
 }
 
 struct InstanceVisitor<T>(PhantomData<T>);
@@ -43,7 +105,7 @@ where
     {
         let (value, mut ivar) = access.value()?;
 
-        let mut fields = RbFields::with_capacity(ivar.len());
+        let mut fields = RbFields::with_capacity(cautious_capacity::<(Symbol, Value)>(ivar.len()));
         while let Some((field, value)) = ivar.next_entry()? {
             fields.insert(field.to_symbol(), value);
         }
@@ -82,63 +144,3 @@ where
         }
     }
 }
-
-impl<T> Instance<T> {
-    /// Take the inner value of this instance.
-    pub fn into_inner(self) -> T {
-        self.value
-    }
-
-    /// Splits this string into its constituants.
-    pub fn into_parts(self) -> (T, RbFields) {
-        (self.value, self.fields)
-    }
-}
-
-impl Instance<RbString> {
-    /// Return the encoding of this string, if it has one.
-    pub fn encoding(&self) -> Option<&crate::Value> {
-        self.fields.get("E").or_else(|| self.fields.get("encoding"))
-    }
-}
-
-macro_rules! utf8_enc {
-    () => {{
-        let mut f = RbFields::new();
-        f.insert("E".into(), true.into());
-
-        f
-    }};
-}
-
-impl From<String> for Instance<RbString> {
-    fn from(value: String) -> Self {
-        Self {
-            value: value.into(),
-            fields: utf8_enc!(),
-        }
-    }
-}
-
-impl From<&str> for Instance<RbString> {
-    fn from(value: &str) -> Self {
-        Self {
-            value: value.into(),
-            fields: utf8_enc!(),
-        }
-    }
-}
-
-impl<T> std::hash::Hash for Instance<T>
-where
-    T: std::hash::Hash,
-{
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.value.hash(state);
-        self.fields.len().hash(state);
-        for (var, field) in &self.fields {
-            var.hash(state);
-            field.hash(state);
-        }
-    }
-}
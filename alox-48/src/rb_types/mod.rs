@@ -3,30 +3,45 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
-use super::Value;
-use indexmap::IndexMap;
 
+// The types themselves live in alox-48-types, these modules only provide (de)serialization impls for them.
+mod extended;
+#[cfg(feature = "value")]
 mod instance;
+#[cfg(feature = "value")]
 mod object;
+#[cfg(feature = "value")]
+mod rb_open_struct;
+#[cfg(feature = "value")]
+mod rb_set;
 mod rb_string;
+#[cfg(feature = "value")]
 mod rb_struct;
 mod sym;
 mod symbol;
+mod symbol_or_string;
+mod user_marshal;
 mod userdata;
 
-pub use instance::Instance;
-pub use object::Object;
-pub use rb_string::RbString;
-pub use rb_struct::RbStruct;
-pub use sym::Sym;
-pub use symbol::Symbol;
-pub use userdata::Userdata;
+#[cfg(feature = "value")]
+pub use instance::InstanceExt;
+#[cfg(feature = "value")]
+pub use object::{ObjectBuilder, ObjectExt};
+#[cfg(feature = "value")]
+pub use rb_struct::{RbStructBuilder, RbStructExt};
+pub use userdata::{UserdataBuilder, UserdataExt, UserdataReader};
 
-/// Shorthand type alias for a ruby array.
-pub type RbArray = Vec<Value>;
-/// Shorthand type alias for a ruby hash.
-pub type RbHash = IndexMap<Value, Value>;
+#[cfg(feature = "value")]
+use crate::{RbFields, Value};
 
-/// A type alias used to represent fields of objects.
-/// All objects store a [`Symbol`] to represent the key for instance variable, and we do that here too.
-pub type RbFields = IndexMap<Symbol, Value>;
+// Find a field by name, like derived impls do: `name` also matches `@name`.
+#[cfg(feature = "value")]
+fn find_field<'a>(fields: &'a RbFields, name: &str) -> Option<&'a Value> {
+    fields.get(name).or_else(|| {
+        if name.starts_with('@') {
+            None
+        } else {
+            fields.get(format!("@{name}").as_str())
+        }
+    })
+}
@@ -3,42 +3,13 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
-use super::{RbFields, Symbol};
-
 use crate::{
-    de::Result as DeResult, ser::Result as SerResult, Deserialize, DeserializerTrait, IvarAccess,
-    Serialize, SerializeIvars, SerializerTrait, Sym, Visitor,
+    de::{cautious_capacity, Result as DeResult},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializerTrait, IvarAccess, Object, RbFields, SerError, Serialize,
+    SerializerTrait, Sym, Symbol, Value, Visitor,
 };
 
-/// A type equivalent to ruby's `Object`.
-#[derive(PartialEq, Eq, Default, Debug, Clone)]
-pub struct Object {
-    /// This object's class.
-    pub class: Symbol,
-    /// The fields on this object.
-    pub fields: RbFields,
-}
-
-impl Object {
-    /// Splits this object into its constituants.
-    #[allow(clippy::must_use_candidate)]
-    pub fn into_parts(self) -> (Symbol, RbFields) {
-        (self.class, self.fields)
-    }
-}
-
-impl std::hash::Hash for Object {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.class.hash(state);
-        self.fields.len().hash(state);
-
-        for (var, field) in &self.fields {
-            var.hash(state);
-            field.hash(state);
-        }
-    }
-}
-
 struct ObjectVisitor;
 
 impl<'de> Visitor<'de> for ObjectVisitor {
@@ -53,7 +24,9 @@ impl<'de> Visitor<'de> for ObjectVisitor {
         A: IvarAccess<'de>,
     {
         let class = class.to_symbol();
-        let mut fields = RbFields::with_capacity(instance_variables.len());
+        let mut fields = RbFields::with_capacity(cautious_capacity::<(Symbol, Value)>(
+            instance_variables.len(),
+        ));
 
         while let Some((k, v)) = instance_variables.next_entry()? {
             fields.insert(k.to_symbol(), v);
@@ -77,10 +50,148 @@ impl Serialize for Object {
     where
         S: SerializerTrait,
     {
-        let mut ivars = serializer.serialize_object(&self.class, self.fields.len())?;
-        for (k, v) in &self.fields {
-            ivars.serialize_entry(k, v)?;
+        serializer.collect_object(&self.class, &self.fields)
+    }
+}
+
+/// Helpers for working with untyped objects, without matching on [`Value`] for every field.
+///
+/// ```
+/// use alox_48::{Object, ObjectExt};
+///
+/// let actor = Object::builder("RPG::Actor")
+///     .field("name", "Aluxes")
+///     .field("level", 1)
+///     .build();
+/// assert!(actor.fields.contains_key("@name"));
+///
+/// let name: &str = actor.get("@name")?;
+/// let level: u8 = actor.get("level")?;
+/// assert_eq!((name, level), ("Aluxes", 1));
+/// assert!(actor.get::<i32>("@class_id").is_err());
+/// # Ok::<(), alox_48::DeError>(())
+/// ```
+pub trait ObjectExt {
+    /// Start building an object of class `class`.
+    fn builder(class: impl Into<Symbol>) -> ObjectBuilder;
+
+    /// Deserialize the field `name` into a `T`, with [`from_value`](crate::from_value).
+    ///
+    /// Like derived impls, `name` also matches `@name`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if there is no field named `name`, or if it can't be deserialized into a `T`.
+    fn get<'de, T>(&'de self, name: &str) -> DeResult<T>
+    where
+        T: Deserialize<'de>;
+
+    /// Deserialize this whole object into a `T`, like [`from_value`](crate::from_value) would a [`Value::Object`].
+    ///
+    /// The object isn't cloned into a [`Value`] first, so `T` can borrow from it.
+    ///
+    /// # Errors
+    ///
+    /// Errors if this object can't be deserialized into a `T`.
+    fn deserialize_into<'de, T>(&'de self) -> DeResult<T>
+    where
+        T: Deserialize<'de>;
+
+    /// Serialize `value`, which must serialize as an object, into an untyped object.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `value` fails to serialize, or if it serializes as something other than an object.
+    fn from_serialize<T>(value: &T) -> SerResult<Self>
+    where
+        T: Serialize + ?Sized,
+        Self: Sized;
+}
+
+impl ObjectExt for Object {
+    fn builder(class: impl Into<Symbol>) -> ObjectBuilder {
+        ObjectBuilder::new(class)
+    }
+
+    fn get<'de, T>(&'de self, name: &str) -> DeResult<T>
+    where
+        T: Deserialize<'de>,
+    {
+        let value = super::find_field(&self.fields, name)
+            .ok_or_else(|| DeError::missing_field_in(&self.class, Sym::new(name)))?;
+        crate::from_value(value)
+    }
+
+    fn deserialize_into<'de, T>(&'de self) -> DeResult<T>
+    where
+        T: Deserialize<'de>,
+    {
+        T::deserialize(self)
+    }
+
+    fn from_serialize<T>(value: &T) -> SerResult<Self>
+    where
+        T: Serialize + ?Sized,
+    {
+        match crate::to_value(value)? {
+            Value::Object(object) => Ok(object),
+            _ => Err(SerError::custom(
+                "expected a value that serializes as an object",
+            )),
+        }
+    }
+}
+
+/// A builder for objects, returned by [`ObjectExt::builder`].
+#[derive(Debug, Clone)]
+pub struct ObjectBuilder {
+    class: Symbol,
+    fields: RbFields,
+}
+
+impl ObjectBuilder {
+    /// Start building an object of class `class`.
+    pub fn new(class: impl Into<Symbol>) -> Self {
+        Self {
+            class: class.into(),
+            fields: RbFields::new(),
         }
-        ivars.end()
+    }
+
+    /// Set the field `name` to `value`.
+    ///
+    /// Instance variables start with `@`, so it's added to `name` if it's not there, like derived impls do.
+    #[must_use]
+    pub fn field(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.fields.insert(ivar_name(name), value.into());
+        self
+    }
+
+    /// Serialize `value` with [`to_value`](crate::to_value), and set the field `name` to it.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `value` fails to serialize.
+    pub fn serialize_field<T>(self, name: &str, value: &T) -> SerResult<Self>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(self.field(name, crate::to_value(value)?))
+    }
+
+    /// Finish building the object.
+    pub fn build(self) -> Object {
+        Object {
+            class: self.class,
+            fields: self.fields,
+        }
+    }
+}
+
+fn ivar_name(name: &str) -> Symbol {
+    if name.starts_with('@') {
+        Symbol::from(name)
+    } else {
+        Symbol::from(format!("@{name}"))
     }
 }
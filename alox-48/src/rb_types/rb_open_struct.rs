@@ -0,0 +1,63 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::marker::PhantomData;
+
+use indexmap::IndexMap;
+
+use crate::{
+    de::Result as DeResult, ser::Result as SerResult, DeError, Deserialize, DeserializerTrait,
+    RbOpenStruct, Serialize, SerializerTrait, Sym, Symbol, Visitor,
+};
+
+const CLASS: &Sym = Sym::new("OpenStruct");
+
+struct OpenStructVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for OpenStructVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = RbOpenStruct<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("an open struct")
+    }
+
+    fn visit_user_marshal<D>(self, class: &'de Sym, deserializer: D) -> DeResult<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        if class != CLASS {
+            return Err(DeError::wrong_class(CLASS, class));
+        }
+
+        IndexMap::<Symbol, T>::deserialize(deserializer).map(|table| RbOpenStruct { table })
+    }
+}
+
+impl<'de, T> Deserialize<'de> for RbOpenStruct<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(OpenStructVisitor(PhantomData))
+    }
+}
+
+impl<T> Serialize for RbOpenStruct<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serializer.serialize_user_marshal(CLASS, &self.table)
+    }
+}
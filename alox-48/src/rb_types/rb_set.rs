@@ -0,0 +1,140 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::{hash::Hash, marker::PhantomData};
+
+use indexmap::IndexSet;
+
+use crate::{
+    de::{cautious_capacity, Ignored, Result as DeResult},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializerTrait, HashAccess, IvarAccess, RbSet, Serialize,
+    SerializeHash, SerializeIvars, SerializerTrait, Sym, Visitor,
+};
+
+const CLASS: &Sym = Sym::new("Set");
+const HASH: &Sym = Sym::new("@hash");
+
+struct SetVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for SetVisitor<T>
+where
+    T: Deserialize<'de> + Hash + Eq,
+{
+    type Value = RbSet<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a set")
+    }
+
+    fn visit_object<A>(self, class: &'de Sym, mut instance_variables: A) -> DeResult<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        if class != CLASS {
+            return Err(DeError::wrong_class(CLASS, class));
+        }
+
+        let mut items = None;
+        while let Some(ivar) = instance_variables.next_ivar()? {
+            if ivar == HASH {
+                let SetItems(set) = instance_variables.next_value()?;
+                items = Some(set);
+            } else {
+                instance_variables.next_value::<Ignored>()?;
+            }
+        }
+
+        items
+            .map(|items| RbSet { items })
+            .ok_or_else(|| DeError::missing_field_in(CLASS, HASH))
+    }
+}
+
+// The `@hash` of a set, which maps every item to `true`.
+struct SetItems<T>(IndexSet<T>);
+
+impl<'de, T> Deserialize<'de> for SetItems<T>
+where
+    T: Deserialize<'de> + Hash + Eq,
+{
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(SetItemsVisitor(PhantomData))
+    }
+}
+
+struct SetItemsVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for SetItemsVisitor<T>
+where
+    T: Deserialize<'de> + Hash + Eq,
+{
+    type Value = SetItems<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a hash of set items")
+    }
+
+    fn visit_hash<A>(self, mut map: A) -> DeResult<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        let mut items = IndexSet::with_capacity(cautious_capacity::<T>(map.len()));
+        while let Some(item) = map.next_key()? {
+            map.next_value::<Ignored>()?;
+            items.insert(item);
+        }
+        Ok(SetItems(items))
+    }
+}
+
+impl<'de, T> Deserialize<'de> for RbSet<T>
+where
+    T: Deserialize<'de> + Hash + Eq,
+{
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(SetVisitor(PhantomData))
+    }
+}
+
+struct SerializeItems<'a, T>(&'a IndexSet<T>);
+
+impl<T> Serialize for SerializeItems<'_, T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        // ruby creates this hash with `Hash.new(false)`
+        let mut hash = serializer.serialize_hash_with_default(self.0.len())?;
+        for item in self.0 {
+            hash.serialize_entry(item, &true)?;
+        }
+        hash.serialize_default(&false)?;
+        hash.end()
+    }
+}
+
+impl<T> Serialize for RbSet<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        let mut ivars = serializer.serialize_object(CLASS, 1)?;
+        ivars.serialize_entry(HASH, &SerializeItems(&self.items))?;
+        ivars.end()
+    }
+}
@@ -4,159 +4,102 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+#[cfg(feature = "value")]
 use crate::{
-    de::Result as DeResult, ser::Result as SerResult, Deserialize, DeserializerTrait, Serialize,
-    SerializerTrait, Visitor,
+    de::cautious_capacity, InstanceAccess, IvarAccess, RbFields, RbStringWithIvars, SerializeIvars,
+    Symbol, Value,
+};
+use crate::{
+    de::Result as DeResult, ser::Result as SerResult, Deserialize, DeserializerTrait, RbString,
+    Serialize, SerializerTrait, Visitor,
 };
 
-/// A type equivalent to ruby's `String`.
-/// ruby strings do not have to be utf8 encoded, so this type uses [`Vec<u8>`] instead.
-#[derive(PartialEq, Eq, Default, Clone)]
-pub struct RbString {
-    /// The data of this string.
-    pub data: Vec<u8>,
-}
-
-#[allow(clippy::must_use_candidate)]
-impl RbString {
-    /// Uses [`String::from_utf8_lossy`] to convert this string to rust string in a lossy manner.
-    pub fn to_string_lossy(&self) -> std::borrow::Cow<'_, str> {
-        String::from_utf8_lossy(&self.data)
-    }
-
-    /// Tries to convert this string to a rust string.
-    ///
-    /// # Errors
-    /// Errors when this string is not valid utf8.
-    pub fn to_string(self) -> Result<String, std::string::FromUtf8Error> {
-        String::from_utf8(self.data)
-    }
-
-    /// Get the length of the string data.
-    pub fn len(&self) -> usize {
-        self.data.len()
-    }
-
-    /// Returns true if the string data is empty.
-    pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
-    }
-
-    /// Returns the string data as a slice.
-    pub fn as_slice(&self) -> &[u8] {
-        &self.data
-    }
-}
-
-impl std::fmt::Debug for RbString {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("RbString")
-            .field("data", &self.to_string_lossy())
-            .finish()
-    }
-}
-
-impl std::fmt::Display for RbString {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.to_string_lossy())
-    }
-}
-
-impl<T> PartialEq<T> for RbString
-where
-    [u8]: PartialEq<T>,
-{
-    fn eq(&self, other: &T) -> bool {
-        self.data.as_slice().eq(other)
-    }
-}
-
-impl std::borrow::Borrow<[u8]> for RbString {
-    fn borrow(&self) -> &[u8] {
-        &self.data
-    }
-}
+struct StringVisitor;
 
-impl std::borrow::BorrowMut<[u8]> for RbString {
-    fn borrow_mut(&mut self) -> &mut [u8] {
-        &mut self.data
-    }
-}
+impl<'de> Visitor<'de> for StringVisitor {
+    type Value = RbString;
 
-impl From<&str> for RbString {
-    fn from(value: &str) -> Self {
-        Self {
-            data: value.as_bytes().to_vec(),
-        }
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a ruby string")
     }
-}
 
-impl From<String> for RbString {
-    fn from(value: String) -> Self {
-        Self {
-            data: value.into_bytes(),
-        }
+    fn visit_string(self, string: &'de [u8]) -> DeResult<Self::Value> {
+        Ok(RbString::from(string))
     }
 }
 
-impl From<&[u8]> for RbString {
-    fn from(value: &[u8]) -> Self {
-        Self {
-            data: value.to_vec(),
-        }
+impl<'de> Deserialize<'de> for RbString {
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(StringVisitor)
     }
 }
 
-impl From<Vec<u8>> for RbString {
-    fn from(value: Vec<u8>) -> Self {
-        Self { data: value }
+impl Serialize for RbString {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serializer.serialize_string(&self.data)
     }
 }
 
-struct StringVisitor;
-
-struct BytesVisitor;
+#[cfg(feature = "value")]
+struct StringIvarsVisitor;
 
-impl<'de> Visitor<'de> for BytesVisitor {
-    type Value = &'de [u8];
+#[cfg(feature = "value")]
+impl<'de> Visitor<'de> for StringIvarsVisitor {
+    type Value = RbStringWithIvars;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         formatter.write_str("a ruby string")
     }
 
     fn visit_string(self, string: &'de [u8]) -> DeResult<Self::Value> {
-        Ok(string)
+        Ok(RbString::from(string).into())
     }
-}
 
-impl<'de> Visitor<'de> for StringVisitor {
-    type Value = RbString;
+    fn visit_instance<A>(self, instance: A) -> DeResult<Self::Value>
+    where
+        A: InstanceAccess<'de>,
+    {
+        let (string, mut ivars) = instance.value::<RbString>()?;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        formatter.write_str("a ruby string")
-    }
+        let mut fields = RbFields::with_capacity(cautious_capacity::<(Symbol, Value)>(ivars.len()));
+        while let Some((field, value)) = ivars.next_entry::<Value>()? {
+            fields.insert(field.to_symbol(), value);
+        }
 
-    fn visit_string(self, string: &'de [u8]) -> DeResult<Self::Value> {
-        Ok(RbString {
-            data: string.to_vec(),
-        })
+        Ok(RbStringWithIvars { string, fields })
     }
 }
 
-impl<'de> Deserialize<'de> for RbString {
+#[cfg(feature = "value")]
+impl<'de> Deserialize<'de> for RbStringWithIvars {
     fn deserialize<D>(deserializer: D) -> DeResult<Self>
     where
         D: DeserializerTrait<'de>,
     {
-        deserializer.deserialize(StringVisitor)
+        deserializer.deserialize(StringIvarsVisitor)
     }
 }
 
-impl Serialize for RbString {
+#[cfg(feature = "value")]
+impl Serialize for RbStringWithIvars {
     fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
     where
         S: SerializerTrait,
     {
-        serializer.serialize_string(&self.data)
+        if self.fields.is_empty() {
+            return self.string.serialize(serializer);
+        }
+
+        let mut ivars = serializer.serialize_instance(&self.string, self.fields.len())?;
+        for (field, value) in &self.fields {
+            ivars.serialize_entry(field, value)?;
+        }
+        ivars.end()
     }
 }
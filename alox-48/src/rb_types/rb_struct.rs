@@ -3,42 +3,13 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
-use super::{RbFields, Symbol};
-
 use crate::{
-    de::Result as DeResult, ser::Result as SerResult, Deserialize, DeserializerTrait, IvarAccess,
-    Serialize, SerializeIvars, SerializerTrait, Sym, Visitor,
+    de::{cautious_capacity, Result as DeResult},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializerTrait, IvarAccess, RbFields, RbStruct, Serialize,
+    SerializerTrait, Sym, Symbol, Value, Visitor,
 };
 
-/// A type equivalent to ruby's `Struct`.
-#[derive(PartialEq, Eq, Default, Debug, Clone)]
-pub struct RbStruct {
-    /// This struct's class.
-    pub class: Symbol,
-    /// The fields on this struct.
-    pub fields: RbFields,
-}
-
-impl RbStruct {
-    /// Splits this struct into its constituants.
-    #[allow(clippy::must_use_candidate)]
-    pub fn into_parts(self) -> (Symbol, RbFields) {
-        (self.class, self.fields)
-    }
-}
-
-impl std::hash::Hash for RbStruct {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.class.hash(state);
-        self.fields.len().hash(state);
-
-        for (var, field) in &self.fields {
-            var.hash(state);
-            field.hash(state);
-        }
-    }
-}
-
 struct StructVisitor;
 
 impl<'de> Visitor<'de> for StructVisitor {
@@ -53,7 +24,9 @@ impl<'de> Visitor<'de> for StructVisitor {
         A: IvarAccess<'de>,
     {
         let class = class.to_symbol();
-        let mut fields = RbFields::with_capacity(instance_variables.len());
+        let mut fields = RbFields::with_capacity(cautious_capacity::<(Symbol, Value)>(
+            instance_variables.len(),
+        ));
 
         while let Some((k, v)) = instance_variables.next_entry()? {
             fields.insert(k.to_symbol(), v);
@@ -77,10 +50,94 @@ impl Serialize for RbStruct {
     where
         S: SerializerTrait,
     {
-        let mut ivars = serializer.serialize_struct(&self.class, self.fields.len())?;
-        for (k, v) in &self.fields {
-            ivars.serialize_entry(k, v)?;
+        serializer.collect_struct(&self.class, &self.fields)
+    }
+}
+
+/// Helpers for working with untyped ruby structs, like [`ObjectExt`](crate::ObjectExt) is for objects.
+///
+/// ```
+/// use alox_48::{RbStruct, RbStructExt};
+///
+/// let point = RbStruct::builder("Point").member("x", 1).member("y", 2).build();
+///
+/// let x: i32 = point.get("x")?;
+/// assert_eq!(x, 1);
+/// # Ok::<(), alox_48::DeError>(())
+/// ```
+pub trait RbStructExt {
+    /// Start building a struct of class `class`.
+    fn builder(class: impl Into<Symbol>) -> RbStructBuilder;
+
+    /// Deserialize the member `name` into a `T`, with [`from_value`](crate::from_value).
+    ///
+    /// # Errors
+    ///
+    /// Errors if there is no member named `name`, or if it can't be deserialized into a `T`.
+    fn get<'de, T>(&'de self, name: &str) -> DeResult<T>
+    where
+        T: Deserialize<'de>;
+}
+
+impl RbStructExt for RbStruct {
+    fn builder(class: impl Into<Symbol>) -> RbStructBuilder {
+        RbStructBuilder::new(class)
+    }
+
+    fn get<'de, T>(&'de self, name: &str) -> DeResult<T>
+    where
+        T: Deserialize<'de>,
+    {
+        let value = super::find_field(&self.fields, name)
+            .ok_or_else(|| DeError::missing_field_in(&self.class, Sym::new(name)))?;
+        crate::from_value(value)
+    }
+}
+
+/// A builder for ruby structs, returned by [`RbStructExt::builder`].
+///
+/// Members are written in the order they're added, which should be the order the struct declares them in.
+#[derive(Debug, Clone)]
+pub struct RbStructBuilder {
+    class: Symbol,
+    fields: RbFields,
+}
+
+impl RbStructBuilder {
+    /// Start building a struct of class `class`.
+    pub fn new(class: impl Into<Symbol>) -> Self {
+        Self {
+            class: class.into(),
+            fields: RbFields::new(),
+        }
+    }
+
+    /// Set the member `name` to `value`.
+    ///
+    /// Unlike instance variables, struct members don't start with `@`.
+    #[must_use]
+    pub fn member(mut self, name: impl Into<Symbol>, value: impl Into<Value>) -> Self {
+        self.fields.insert(name.into(), value.into());
+        self
+    }
+
+    /// Serialize `value` with [`to_value`](crate::to_value), and set the member `name` to it.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `value` fails to serialize.
+    pub fn serialize_member<T>(self, name: impl Into<Symbol>, value: &T) -> SerResult<Self>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(self.member(name, crate::to_value(value)?))
+    }
+
+    /// Finish building the struct.
+    pub fn build(self) -> RbStruct {
+        RbStruct {
+            class: self.class,
+            fields: self.fields,
         }
-        ivars.end()
     }
 }
@@ -0,0 +1,53 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::{
+    de::{Result as DeResult, Unexpected},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializerTrait, Serialize, SerializerTrait, Sym, SymbolOrString,
+    Visitor,
+};
+
+struct SymbolOrStringVisitor;
+
+impl<'de> Visitor<'de> for SymbolOrStringVisitor {
+    type Value = SymbolOrString;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a symbol or a utf8 string")
+    }
+
+    fn visit_symbol(self, symbol: &'de Sym) -> DeResult<Self::Value> {
+        Ok(SymbolOrString::Symbol(symbol.to_symbol()))
+    }
+
+    fn visit_string(self, string: &'de [u8]) -> DeResult<Self::Value> {
+        std::str::from_utf8(string)
+            .map(|string| SymbolOrString::String(string.to_owned()))
+            .map_err(|_| DeError::invalid_value(Unexpected::String(string), &self))
+    }
+}
+
+impl<'de> Deserialize<'de> for SymbolOrString {
+    fn deserialize<D>(deserializer: D) -> DeResult<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(SymbolOrStringVisitor)
+    }
+}
+
+impl Serialize for SymbolOrString {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        match self {
+            SymbolOrString::Symbol(symbol) => symbol.serialize(serializer),
+            SymbolOrString::String(string) => string.serialize(serializer),
+        }
+    }
+}
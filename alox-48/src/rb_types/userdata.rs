@@ -4,63 +4,11 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 use crate::{
-    de::Result as DeResult, ser::Result as SerResult, Deserialize, DeserializerTrait, Serialize,
-    SerializerTrait, Symbol, Visitor,
+    de::{Kind as DeKind, Result as DeResult},
+    ser::Result as SerResult,
+    DeError, Deserialize, DeserializerTrait, Serialize, SerializerTrait, Symbol, Userdata, Visitor,
 };
 
-/// This type represents types serialized with `_dump` from ruby.
-/// Its main intended use is in `Value`, but you can also use it with `Deserialize`:
-///
-/// ```
-/// #[derive(alox_48::Deserialize, Debug, PartialEq, Eq)]
-/// #[marshal(from = "alox_48::Userdata")]
-/// struct MyUserData {
-///     field: [char; 4],
-/// }
-///
-/// impl From<alox_48::Userdata> for MyUserData {
-///     fn from(value: alox_48::Userdata) -> Self {
-///         assert_eq!(value.class, "MyUserData");
-///         let field = std::array::from_fn(|i| {
-///             value.data[i] as char
-///         });
-///
-///         Self {
-///             field
-///         }
-///     }
-/// }
-///
-/// let bytes = &[
-///     0x04, 0x08, 0x75, 0x3a, 0x0f, 0x4d, 0x79, 0x55, 0x73, 0x65, 0x72, 0x44, 0x61, 0x74, 0x61, 0x09, 0x61, 0x62, 0x63, 0x64
-/// ];
-///
-/// let data: MyUserData = alox_48::from_bytes(bytes).expect("invalid marshal data");
-/// assert_eq!(
-///     data,
-///     MyUserData {
-///         field: ['a', 'b', 'c', 'd']
-///     }
-/// )
-///     
-///
-/// ```
-#[derive(Hash, PartialEq, Eq, Default, Debug, Clone)]
-pub struct Userdata {
-    /// Userdata class.
-    pub class: Symbol,
-    /// Userdata data.
-    pub data: Vec<u8>,
-}
-
-impl Userdata {
-    /// Splits this userdata into its constituants.
-    #[allow(clippy::must_use_candidate)]
-    pub fn into_parts(self) -> (Symbol, Vec<u8>) {
-        (self.class, self.data)
-    }
-}
-
 struct UserdataVisitor;
 
 impl<'de> Visitor<'de> for UserdataVisitor {
@@ -71,10 +19,7 @@ impl<'de> Visitor<'de> for UserdataVisitor {
     }
 
     fn visit_user_data(self, class: &'de crate::Sym, data: &'de [u8]) -> DeResult<Self::Value> {
-        Ok(Userdata {
-            class: class.to_symbol(),
-            data: data.to_vec(),
-        })
+        Ok(Userdata::new(class.to_symbol(), data))
     }
 }
 
@@ -95,3 +40,243 @@ impl Serialize for Userdata {
         serializer.serialize_user_data(&self.class, &self.data)
     }
 }
+
+/// Helpers for user data with a packed binary layout, like RGSS's `Table` and `Color`.
+///
+/// ```
+/// use alox_48::{Userdata, UserdataExt};
+///
+/// let color = Userdata::builder("Color")
+///     .write_f64_slice(&[255.0, 128.0, 0.0, 255.0])
+///     .build();
+///
+/// let mut reader = color.reader();
+/// let [red, green, blue, alpha] = reader.read_f64_array()?;
+/// reader.finish()?;
+/// assert_eq!((red, green, blue, alpha), (255.0, 128.0, 0.0, 255.0));
+/// # Ok::<(), alox_48::DeError>(())
+/// ```
+pub trait UserdataExt {
+    /// Returns a reader over the data of this user data.
+    fn reader(&self) -> UserdataReader<'_>;
+
+    /// Start building user data of class `class`.
+    fn builder(class: impl Into<Symbol>) -> UserdataBuilder;
+}
+
+impl UserdataExt for Userdata {
+    fn reader(&self) -> UserdataReader<'_> {
+        UserdataReader::new(&self.data)
+    }
+
+    fn builder(class: impl Into<Symbol>) -> UserdataBuilder {
+        UserdataBuilder::new(class)
+    }
+}
+
+/// A cursor for reading little endian values out of user data.
+///
+/// Reading past the end of the data returns a [`Kind::InvalidLength`](crate::de::Kind::InvalidLength) error.
+#[derive(Debug, Clone)]
+pub struct UserdataReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+macro_rules! read_le {
+    ($($ty:ident => $read:ident, $read_slice:ident, $read_array:ident);* $(;)?) => {
+        $(
+            #[doc = concat!("Read a little endian `", stringify!($ty), "`.")]
+            ///
+            /// # Errors
+            ///
+            /// Errors if there isn't enough data left.
+            pub fn $read(&mut self) -> DeResult<$ty> {
+                self.read_bytes_array().map($ty::from_le_bytes)
+            }
+
+            #[doc = concat!("Read `len` little endian `", stringify!($ty), "`s.")]
+            ///
+            /// # Errors
+            ///
+            /// Errors if there isn't enough data left.
+            pub fn $read_slice(&mut self, len: usize) -> DeResult<Vec<$ty>> {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+                let bytes = len
+                    .checked_mul(SIZE)
+                    .ok_or_else(|| self.too_short(usize::MAX))
+                    .and_then(|size| self.read_bytes(size))?;
+
+                Ok(bytes
+                    .chunks_exact(SIZE)
+                    .map(|chunk| {
+                        let mut array = [0; SIZE];
+                        array.copy_from_slice(chunk);
+                        $ty::from_le_bytes(array)
+                    })
+                    .collect())
+            }
+
+            #[doc = concat!("Read `N` little endian `", stringify!($ty), "`s.")]
+            ///
+            /// # Errors
+            ///
+            /// Errors if there isn't enough data left.
+            pub fn $read_array<const N: usize>(&mut self) -> DeResult<[$ty; N]> {
+                let mut array = [<$ty>::default(); N];
+                for v in &mut array {
+                    *v = self.$read()?;
+                }
+                Ok(array)
+            }
+        )*
+    };
+}
+
+impl<'a> UserdataReader<'a> {
+    /// Create a reader over `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// Returns the data that hasn't been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.position..]
+    }
+
+    /// Returns how many bytes have been read.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Read the next `len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Errors if there are less than `len` bytes left.
+    pub fn read_bytes(&mut self, len: usize) -> DeResult<&'a [u8]> {
+        let bytes = self
+            .remaining()
+            .get(..len)
+            .ok_or_else(|| self.too_short(len))?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    /// Read a byte.
+    ///
+    /// # Errors
+    ///
+    /// Errors if there isn't any data left.
+    pub fn read_u8(&mut self) -> DeResult<u8> {
+        self.read_bytes_array().map(u8::from_le_bytes)
+    }
+
+    read_le! {
+        i8 => read_i8, read_i8_slice, read_i8_array;
+        u16 => read_u16_le, read_u16_slice, read_u16_array;
+        i16 => read_i16_le, read_i16_slice, read_i16_array;
+        u32 => read_u32_le, read_u32_slice, read_u32_array;
+        i32 => read_i32_le, read_i32_slice, read_i32_array;
+        u64 => read_u64_le, read_u64_slice, read_u64_array;
+        i64 => read_i64_le, read_i64_slice, read_i64_array;
+        f32 => read_f32_le, read_f32_slice, read_f32_array;
+        f64 => read_f64_le, read_f64_slice, read_f64_array;
+    }
+
+    /// Check that all of the data has been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Kind::TrailingBytes`](crate::de::Kind::TrailingBytes) if there is data left.
+    pub fn finish(self) -> DeResult<()> {
+        match self.remaining().len() {
+            0 => Ok(()),
+            len => Err(DeError::from(DeKind::TrailingBytes(len))),
+        }
+    }
+
+    fn read_bytes_array<const N: usize>(&mut self) -> DeResult<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    fn too_short(&self, len: usize) -> DeError {
+        DeError::invalid_length(
+            self.remaining().len(),
+            &format!("{len} more bytes of user data").as_str(),
+        )
+    }
+}
+
+/// A builder for user data with a packed binary layout. Values are written in little endian.
+#[derive(Debug, Clone)]
+pub struct UserdataBuilder {
+    class: Symbol,
+    data: Vec<u8>,
+}
+
+macro_rules! write_le {
+    ($($ty:ident => $write:ident, $write_slice:ident);* $(;)?) => {
+        $(
+            #[doc = concat!("Write a little endian `", stringify!($ty), "`.")]
+            #[must_use]
+            pub fn $write(mut self, v: $ty) -> Self {
+                self.data.extend_from_slice(&v.to_le_bytes());
+                self
+            }
+
+            #[doc = concat!("Write every `", stringify!($ty), "` in `slice`, in little endian.")]
+            #[must_use]
+            pub fn $write_slice(mut self, slice: &[$ty]) -> Self {
+                self.data.reserve(std::mem::size_of_val(slice));
+                for v in slice {
+                    self.data.extend_from_slice(&v.to_le_bytes());
+                }
+                self
+            }
+        )*
+    };
+}
+
+impl UserdataBuilder {
+    /// Start building user data of class `class`.
+    pub fn new(class: impl Into<Symbol>) -> Self {
+        Self {
+            class: class.into(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Write `bytes` as-is.
+    #[must_use]
+    pub fn write_bytes(mut self, bytes: &[u8]) -> Self {
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    /// Write a byte.
+    #[must_use]
+    pub fn write_u8(mut self, v: u8) -> Self {
+        self.data.push(v);
+        self
+    }
+
+    write_le! {
+        i8 => write_i8, write_i8_slice;
+        u16 => write_u16_le, write_u16_slice;
+        i16 => write_i16_le, write_i16_slice;
+        u32 => write_u32_le, write_u32_slice;
+        i32 => write_i32_le, write_i32_slice;
+        u64 => write_u64_le, write_u64_slice;
+        i64 => write_i64_le, write_i64_slice;
+        f32 => write_f32_le, write_f32_slice;
+        f64 => write_f64_le, write_f64_slice;
+    }
+
+    /// Finish building the user data.
+    pub fn build(self) -> Userdata {
+        Userdata::new(self.class, self.data)
+    }
+}
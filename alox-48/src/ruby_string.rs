@@ -0,0 +1,152 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Converting between ruby strings and rust strings.
+//!
+//! A ruby string can show up as a bare string (binary), a string with an encoding instance variable,
+//! or a subclass of `String`. [`decode`] handles all of these from either marshal data or a [`Value`](crate::Value).
+//!
+//! ```
+//! # #[cfg(feature = "value")] {
+//! use alox_48::ruby_string::{decode, encode, Encoding, Policy};
+//!
+//! let string = encode("hello", Encoding::Utf8);
+//! let bytes = alox_48::to_bytes(&string).unwrap();
+//!
+//! let mut deserializer = alox_48::Deserializer::new(&bytes).unwrap();
+//! assert_eq!(decode(&mut deserializer, Policy::Strict).unwrap(), "hello");
+//!
+//! let value = alox_48::to_value(&string).unwrap();
+//! assert_eq!(decode(&value, Policy::Strict).unwrap(), "hello");
+//! # }
+//! ```
+
+use crate::{
+    de::{DeserializeSeed, Ignored, Result as DeResult, Unexpected},
+    DeError, DeserializerTrait, InstanceAccess, IvarAccess, Visitor,
+};
+#[cfg(feature = "value")]
+use crate::{Instance, RbFields, RbString};
+
+#[doc(inline)]
+pub use alox_48_types::Encoding;
+
+/// How [`decode`] handles strings that aren't valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Policy {
+    /// Fail on invalid UTF-8, or an encoding other than UTF-8, US-ASCII, or binary.
+    Strict,
+    /// Replace invalid UTF-8 with `U+FFFD`, regardless of the string's encoding.
+    Lossy,
+}
+
+/// Create a ruby string with the given encoding.
+#[cfg(feature = "value")]
+#[allow(clippy::needless_pass_by_value)] // taking it by value is nicer to call
+pub fn encode(string: &str, encoding: Encoding) -> Instance<RbString> {
+    let mut fields = RbFields::new();
+    encoding.apply(&mut fields);
+
+    Instance {
+        value: string.into(),
+        fields,
+    }
+}
+
+/// Read a ruby string as a rust string.
+///
+/// `deserializer` can be a [`Deserializer`](crate::Deserializer) or a `&Value`.
+pub fn decode<'de, D>(deserializer: D, policy: Policy) -> DeResult<String>
+where
+    D: DeserializerTrait<'de>,
+{
+    deserializer.deserialize(DecodeVisitor { policy })
+}
+
+struct DecodeVisitor {
+    policy: Policy,
+}
+
+struct BytesSeed;
+
+impl DecodeVisitor {
+    fn finish(&self, bytes: &[u8], encoding: &Encoding) -> DeResult<String> {
+        match self.policy {
+            Policy::Lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+            Policy::Strict => {
+                if let Encoding::Other(_) = encoding {
+                    return Err(DeError::invalid_value(
+                        Unexpected::String(bytes),
+                        &"a UTF-8, US-ASCII, or binary string",
+                    ));
+                }
+                std::str::from_utf8(bytes)
+                    .map(ToOwned::to_owned)
+                    .map_err(|_| DeError::invalid_value(Unexpected::String(bytes), &"valid UTF-8"))
+            }
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for DecodeVisitor {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a ruby string")
+    }
+
+    fn visit_string(self, string: &'de [u8]) -> DeResult<Self::Value> {
+        self.finish(string, &Encoding::Binary)
+    }
+
+    fn visit_instance<A>(self, instance: A) -> DeResult<Self::Value>
+    where
+        A: InstanceAccess<'de>,
+    {
+        let (bytes, mut ivars) = instance.value_seed(BytesSeed)?;
+
+        let mut encoding = Encoding::Binary;
+        while let Some(ivar) = ivars.next_ivar()? {
+            match ivar.as_str() {
+                "E" if ivars.next_value::<bool>()? => encoding = Encoding::Utf8,
+                "E" => encoding = Encoding::UsAscii,
+                "encoding" => {
+                    let name = ivars.next_value::<&[u8]>()?;
+                    encoding = Encoding::Other(String::from_utf8_lossy(name).into_owned());
+                }
+                _ => {
+                    ivars.next_value::<Ignored>()?;
+                }
+            }
+        }
+
+        self.finish(bytes, &encoding)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for BytesSeed {
+    type Value = &'de [u8];
+
+    fn deserialize<D>(self, deserializer: D) -> DeResult<&'de [u8]>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(self)
+    }
+}
+
+// Also handles subclasses of String, as visit_user_class passes through by default.
+impl<'de> Visitor<'de> for BytesSeed {
+    type Value = &'de [u8];
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a ruby string")
+    }
+
+    fn visit_string(self, string: &'de [u8]) -> DeResult<Self::Value> {
+        Ok(string)
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Checking the shape of a [`Value`] against a schema.
+//!
+//! A [`Schema`] describes what a value should look like: its kind, the class of objects, which instance variables they have,
+//! and what the elements of arrays and hashes are.
+//! Unlike deserializing, validating doesn't stop at the first problem. Every [`Violation`] is reported, along with where it is.
+//!
+//! ```
+//! use alox_48::{de::expect::ValueKind, marshal, schema::Schema};
+//!
+//! let schema = Schema::object("RPG::Actor")
+//!     .ivar("@name", Schema::kind(ValueKind::String))
+//!     .ivar("@skills", Schema::array(Schema::kind(ValueKind::Symbol)))
+//!     .optional_ivar("@weapon", Schema::object("RPG::Weapon").nullable());
+//!
+//! let actor = marshal!(RPG::Actor { @name => "Alice", @skills => [:fire, 5] });
+//!
+//! let violations = schema.validate(&actor);
+//! assert_eq!(violations.len(), 1);
+//! assert_eq!(violations[0].to_string(), "@skills[1]: expected a symbol, found an integer");
+//! ```
+
+use crate::{de::expect::ValueKind, RbFields, Symbol, Value};
+
+/// The expected shape of a value.
+///
+/// Wrappers like instance variables and extended modules are looked through, like with [`Expectation`](crate::de::expect::Expectation).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schema {
+    /// Anything is valid.
+    Any,
+    /// A value of some kind, with no other checks.
+    Kind(ValueKind),
+    /// An array, with every element matching the schema.
+    Array(Box<Schema>),
+    /// A hash, with every key and value matching their schemas.
+    Hash {
+        /// The schema of the keys.
+        key: Box<Schema>,
+        /// The schema of the values.
+        value: Box<Schema>,
+    },
+    /// An object or struct.
+    Object(ObjectSchema),
+    /// `nil`, or a value matching the schema.
+    Nullable(Box<Schema>),
+    /// A value matching at least one of the schemas.
+    OneOf(Vec<Schema>),
+}
+
+/// The expected shape of an object or struct.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjectSchema {
+    class: Option<Symbol>,
+    ivars: Vec<IvarSchema>,
+    deny_unknown_ivars: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct IvarSchema {
+    name: Symbol,
+    schema: Schema,
+    required: bool,
+}
+
+/// A place where a value doesn't match its schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// The path to the value, from the value that was validated.
+    pub path: Vec<PathSegment>,
+    /// What is wrong with the value.
+    pub kind: ViolationKind,
+}
+
+/// A step in the path to a [`Violation`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    /// An instance variable of an object, or a member of a struct.
+    Ivar(Symbol),
+    /// An element of an array.
+    Index(usize),
+    /// The key of a hash entry, by its index.
+    HashKey(usize),
+    /// The value of a hash entry, by its key.
+    HashValue(Value),
+}
+
+/// What is wrong with a value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViolationKind {
+    /// The value is the wrong kind.
+    WrongKind {
+        /// The kind the schema expected.
+        expected: ValueKind,
+        /// The kind the value was.
+        found: ValueKind,
+    },
+    /// The object has the wrong class.
+    WrongClass {
+        /// The class the schema expected.
+        expected: Symbol,
+        /// The class the object had.
+        found: Symbol,
+    },
+    /// A required instance variable is missing.
+    MissingIvar(Symbol),
+    /// An instance variable isn't in the schema, and the schema denies unknown instance variables.
+    UnknownIvar(Symbol),
+    /// The value didn't match any schema of a [`Schema::OneOf`].
+    NoneMatched,
+}
+
+impl Schema {
+    /// A schema for a value of some kind.
+    pub const fn kind(kind: ValueKind) -> Self {
+        Self::Kind(kind)
+    }
+
+    /// A schema for an array of values matching `element`.
+    pub fn array(element: impl Into<Schema>) -> Self {
+        Self::Array(Box::new(element.into()))
+    }
+
+    /// A schema for a hash with keys and values matching `key` and `value`.
+    pub fn hash(key: impl Into<Schema>, value: impl Into<Schema>) -> Self {
+        Self::Hash {
+            key: Box::new(key.into()),
+            value: Box::new(value.into()),
+        }
+    }
+
+    /// A schema for an object or struct of a class.
+    ///
+    /// Add instance variables to it with [`ObjectSchema::ivar`].
+    pub fn object(class: impl Into<Symbol>) -> ObjectSchema {
+        ObjectSchema {
+            class: Some(class.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Allow `nil` as well as values matching this schema.
+    #[must_use]
+    pub fn nullable(self) -> Self {
+        Self::Nullable(Box::new(self))
+    }
+
+    /// Check a value against this schema, returning every place it doesn't match.
+    ///
+    /// The value is valid if there are no violations.
+    pub fn validate(&self, value: &Value) -> Vec<Violation> {
+        let mut validator = Validator::default();
+        validator.validate(self, value);
+        validator.violations
+    }
+
+    /// Check if a value matches this schema.
+    pub fn is_valid(&self, value: &Value) -> bool {
+        self.validate(value).is_empty()
+    }
+}
+
+impl ObjectSchema {
+    /// A schema for an object or struct of any class.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require an instance variable matching `schema`.
+    ///
+    /// The name is matched as is, so object ivars should start with `@`, and struct members shouldn't.
+    #[must_use]
+    pub fn ivar(mut self, name: impl Into<Symbol>, schema: impl Into<Schema>) -> Self {
+        self.ivars.push(IvarSchema {
+            name: name.into(),
+            schema: schema.into(),
+            required: true,
+        });
+        self
+    }
+
+    /// Allow an instance variable, which must match `schema` if it is present.
+    #[must_use]
+    pub fn optional_ivar(mut self, name: impl Into<Symbol>, schema: impl Into<Schema>) -> Self {
+        self.ivars.push(IvarSchema {
+            name: name.into(),
+            schema: schema.into(),
+            required: false,
+        });
+        self
+    }
+
+    /// Report instance variables that aren't in this schema.
+    #[must_use]
+    pub fn deny_unknown_ivars(mut self) -> Self {
+        self.deny_unknown_ivars = true;
+        self
+    }
+
+    /// Check a value against this schema, like [`Schema::validate`].
+    pub fn validate(&self, value: &Value) -> Vec<Violation> {
+        let mut validator = Validator::default();
+        validator.validate_object(self, unwrap(value));
+        validator.violations
+    }
+
+    /// Allow `nil` as well as objects matching this schema.
+    pub fn nullable(self) -> Schema {
+        Schema::from(self).nullable()
+    }
+}
+
+impl From<ObjectSchema> for Schema {
+    fn from(value: ObjectSchema) -> Self {
+        Self::Object(value)
+    }
+}
+
+impl From<ValueKind> for Schema {
+    fn from(value: ValueKind) -> Self {
+        Self::Kind(value)
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            return write!(f, "{}", self.kind);
+        }
+        for (i, segment) in self.path.iter().enumerate() {
+            match segment {
+                PathSegment::Ivar(name) if i == 0 => f.write_str(name.as_str())?,
+                PathSegment::Ivar(name) => write!(f, ".{}", name.as_str())?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+                PathSegment::HashKey(index) => write!(f, ".keys[{index}]")?,
+                PathSegment::HashValue(key) => write!(f, "[{key}]")?,
+            }
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl std::fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ViolationKind::WrongKind { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            ViolationKind::WrongClass { expected, found } => {
+                write!(
+                    f,
+                    "expected an instance of `{}`, found `{}`",
+                    expected.as_str(),
+                    found.as_str()
+                )
+            }
+            ViolationKind::MissingIvar(name) => {
+                write!(f, "missing instance variable `{}`", name.as_str())
+            }
+            ViolationKind::UnknownIvar(name) => {
+                write!(f, "unknown instance variable `{}`", name.as_str())
+            }
+            ViolationKind::NoneMatched => f.write_str("value did not match any schema"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Validator {
+    path: Vec<PathSegment>,
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    fn report(&mut self, kind: ViolationKind) {
+        self.violations.push(Violation {
+            path: self.path.clone(),
+            kind,
+        });
+    }
+
+    fn nested(&mut self, segment: PathSegment, schema: &Schema, value: &Value) {
+        self.path.push(segment);
+        self.validate(schema, value);
+        self.path.pop();
+    }
+
+    fn validate(&mut self, schema: &Schema, value: &Value) {
+        let value = unwrap(value);
+        match schema {
+            Schema::Any => {}
+            Schema::Kind(kind) => {
+                self.expect_kind(*kind, value);
+            }
+            Schema::Nullable(_) if matches!(value, Value::Nil) => {}
+            Schema::Nullable(schema) => self.validate(schema, value),
+            Schema::OneOf(schemas) => {
+                if !schemas.iter().any(|schema| schema.is_valid(value)) {
+                    self.report(ViolationKind::NoneMatched);
+                }
+            }
+            Schema::Array(element) => {
+                let Some(Value::Array(array)) = self.expect_kind(ValueKind::Array, value) else {
+                    return;
+                };
+                for (index, v) in array.iter().enumerate() {
+                    self.nested(PathSegment::Index(index), element, v);
+                }
+            }
+            Schema::Hash {
+                key: key_schema,
+                value: value_schema,
+            } => {
+                let Some(Value::Hash(hash) | Value::HashWithDefault { hash, .. }) =
+                    self.expect_kind(ValueKind::Hash, value)
+                else {
+                    return;
+                };
+                for (index, (k, v)) in hash.iter().enumerate() {
+                    self.nested(PathSegment::HashKey(index), key_schema, k);
+                    self.nested(PathSegment::HashValue(k.clone()), value_schema, v);
+                }
+            }
+            Schema::Object(object) => self.validate_object(object, value),
+        }
+    }
+
+    fn expect_kind<'v>(&mut self, expected: ValueKind, value: &'v Value) -> Option<&'v Value> {
+        let found = kind_of(value);
+        if found == expected {
+            Some(value)
+        } else {
+            self.report(ViolationKind::WrongKind { expected, found });
+            None
+        }
+    }
+
+    fn validate_object(&mut self, schema: &ObjectSchema, value: &Value) {
+        let (class, fields): (&Symbol, &RbFields) = match value {
+            Value::Object(object) => (&object.class, &object.fields),
+            Value::RbStruct(rb_struct) => (&rb_struct.class, &rb_struct.fields),
+            _ => {
+                self.report(ViolationKind::WrongKind {
+                    expected: ValueKind::Object,
+                    found: kind_of(value),
+                });
+                return;
+            }
+        };
+
+        if let Some(expected) = &schema.class {
+            if expected != class {
+                self.report(ViolationKind::WrongClass {
+                    expected: expected.clone(),
+                    found: class.clone(),
+                });
+                return;
+            }
+        }
+
+        for ivar in &schema.ivars {
+            match fields.get(&ivar.name) {
+                Some(v) => self.nested(PathSegment::Ivar(ivar.name.clone()), &ivar.schema, v),
+                None if ivar.required => self.report(ViolationKind::MissingIvar(ivar.name.clone())),
+                None => {}
+            }
+        }
+
+        if schema.deny_unknown_ivars {
+            for name in fields.keys() {
+                if !schema.ivars.iter().any(|ivar| &ivar.name == name) {
+                    self.report(ViolationKind::UnknownIvar(name.clone()));
+                }
+            }
+        }
+    }
+}
+
+fn unwrap(value: &Value) -> &Value {
+    match value {
+        Value::Instance(instance) => unwrap(&instance.value),
+        Value::Extended { value, .. } | Value::UserClass { value, .. } => unwrap(value),
+        _ => value,
+    }
+}
+
+pub(crate) fn kind_of(value: &Value) -> ValueKind {
+    match unwrap(value) {
+        Value::Nil => ValueKind::Nil,
+        Value::Bool(_) => ValueKind::Bool,
+        Value::Float(_) => ValueKind::Float,
+        Value::Integer(_) => ValueKind::Integer,
+        Value::String(_) => ValueKind::String,
+        Value::Symbol(_) => ValueKind::Symbol,
+        Value::Array(_) => ValueKind::Array,
+        Value::Hash(_) | Value::HashWithDefault { .. } => ValueKind::Hash,
+        Value::Userdata(_) => ValueKind::UserData,
+        Value::Object(_) => ValueKind::Object,
+        Value::Regex { .. } => ValueKind::Regex,
+        Value::RbStruct(_) => ValueKind::Struct,
+        Value::Class(_) => ValueKind::Class,
+        Value::Module(_) => ValueKind::Module,
+        Value::UserMarshal { .. } => ValueKind::UserMarshal,
+        Value::Data { .. } => ValueKind::Data,
+        // unwrap looks through these
+        Value::Instance(_) | Value::Extended { .. } | Value::UserClass { .. } => unreachable!(),
+    }
+}
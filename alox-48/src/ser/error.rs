@@ -18,16 +18,60 @@ pub struct Error {
 /// Error type for this crate.
 #[derive(Debug, thiserror::Error)]
 pub enum Kind {
+    /// More elements, entries, or fields were serialized than the length given up front.
+    /// Contains the number serialized so far and the provided length.
     #[error("Overshot the provided len {0} > {1}")]
     OvershotProvidedLen(usize, usize),
+    /// Fewer elements, entries, or fields were serialized than the length given up front.
+    /// Contains the number serialized and the provided length.
     #[error("Undershot the provided len {0} < {1}")]
     UndershotProvidedLen(usize, usize),
+    /// A custom error thrown by a `Serialize` impl.
+    ///
+    /// alox-48 itself never returns this, see [`Error::custom`].
     #[error("Custom error: {0}")]
     Message(String),
     #[error("Tried to serialize a key without a value")]
     KeyAfterKey,
     #[error("Tried to serialize a value before its key")]
     ValueAfterValue,
+    /// A default value was serialized for a hash without one, or more than once.
+    #[error("Tried to serialize a default value for a hash without one")]
+    UnexpectedHashDefault,
+    /// A hash with a default value was ended without serializing its default value.
+    #[error("Tried to end a hash without serializing its default value")]
+    MissingHashDefault,
+    /// A hash with a default value was serialized into a serializer that doesn't support them.
+    #[error("This serializer does not support hashes with default values")]
+    HashDefaultUnsupported,
+    /// A `RefCell` was mutably borrowed while serializing it.
+    #[error("Tried to serialize a RefCell that is mutably borrowed")]
+    AlreadyBorrowed,
+    /// An `Rc` or `Arc` was serialized while it was already being serialized,
+    /// which would have recursed forever.
+    #[error("Tried to serialize a shared value that contains itself")]
+    CircularReference,
+    /// A path contained invalid UTF-8.
+    #[error("Path contains invalid UTF-8 characters")]
+    NonUtf8Path,
+    /// A `SystemTime` was earlier than `UNIX_EPOCH`.
+    #[error("SystemTime must be later than UNIX_EPOCH")]
+    TimeBeforeEpoch,
+    /// A time was outside of the range of years ruby's `Time` can store (1900 to 67435).
+    #[error("Year {0} is out of range for a Time")]
+    YearOutOfRange(i32),
+    /// An integer was outside the range the serializer supports.
+    ///
+    /// For [`Serializer`](crate::Serializer) that's an `i64`, which is the largest bignum alox-48 supports.
+    /// Serializers that don't implement [`serialize_i64`](super::SerializerTrait::serialize_i64) only support an `i32`.
+    #[error("Integer is out of range for this serializer")]
+    IntegerOutOfRange,
+    /// A [`RawValue`](crate::RawValue) was serialized into something other than marshal data.
+    #[error("This serializer does not support raw values")]
+    RawValueUnsupported,
+    /// The output would have been larger than the serializer's output limit.
+    #[error("Exceeded the output limit of {0} bytes")]
+    OutputLimitExceeded(usize),
 }
 
 impl Error {
@@ -8,17 +8,26 @@ use std::{
     collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque},
     ffi::{CStr, CString},
     hash::{BuildHasher, Hash},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
     num::{
         NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
         NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
     },
-    sync::atomic::{
-        AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicU16, AtomicU32,
-        AtomicU64, AtomicU8, AtomicUsize, Ordering,
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        atomic::{
+            AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicU16,
+            AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering,
+        },
+        Arc,
     },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use super::{Error, Kind, Result, Serialize, SerializeArray, SerializerTrait};
+use super::{Error, Kind, Result, Serialize, SerializeArray, SerializeIvars, SerializerTrait};
+use crate::Sym;
 
 // some of these macros are lifted directly from serde.
 // serde is under a fairly permissive license (and any macro i would write would likely look identical) so this is okay.
@@ -29,17 +38,34 @@ macro_rules! primitive_int_impl {
             where
                 S: SerializerTrait
             {
-                serializer.serialize_i32(*self as i32)
+                serializer.serialize_i32((*self).into())
             }
         })*
     };
 }
 
-primitive_int_impl! {
-    u8, u16, u32, u64, u128, usize,
-    i8, i16, i32, i64, i128, isize
+primitive_int_impl!(u8, u16, i8, i16, i32);
+
+// these may be too large for a fixnum, and are written as bignums if they are
+macro_rules! large_int_impl {
+    ($($primitive:ty),*) => {
+        $(impl Serialize for $primitive {
+            #[allow(clippy::useless_conversion)]
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
+            where
+                S: SerializerTrait
+            {
+                let v = i64::try_from(*self).map_err(|_| Error {
+                    kind: Kind::IntegerOutOfRange,
+                })?;
+                serializer.serialize_i64(v)
+            }
+        })*
+    };
 }
 
+large_int_impl!(u32, u64, u128, usize, i64, i128, isize);
+
 impl Serialize for f32 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
     where
@@ -214,7 +240,7 @@ array_impl!(<T> Serialize for BTreeSet<T> where T: Serialize + Ord);
 
 array_impl!(<T, H> Serialize for HashSet<T, H> where T: Serialize + Hash, H: BuildHasher + Default);
 
-array_impl!(<T, H> Serialize for indexmap::IndexSet<T, H> where T: Serialize + Hash, H: BuildHasher);
+array_impl!(#[cfg(feature = "value")] <T, H> Serialize for indexmap::IndexSet<T, H> where T: Serialize + Hash, H: BuildHasher);
 
 // i despise you, linked list.
 array_impl!(<T> Serialize for LinkedList<T> where T: Serialize);
@@ -244,7 +270,7 @@ map_impl!(<K, V> Serialize for BTreeMap<K, V> where K: Ord + Serialize, V: Seria
 
 map_impl!(<K, V, H> Serialize for HashMap<K, V, H> where K: Hash + Serialize, V: Serialize, H: BuildHasher);
 
-map_impl!(<K, V, H> Serialize for indexmap::IndexMap<K, V, H> where K: Hash + Serialize, V: Serialize, H: BuildHasher);
+map_impl!(#[cfg(feature = "value")] <K, V, H> Serialize for indexmap::IndexMap<K, V, H> where K: Hash + Serialize, V: Serialize, H: BuildHasher);
 
 macro_rules! deref_impl {
     (
@@ -280,14 +306,57 @@ deref_impl! {
     <T: ?Sized> Serialize for Box<T> where T: Serialize
 }
 
-deref_impl! {
-    <T: ?Sized> Serialize for std::rc::Rc<T> where T: Serialize
+thread_local! {
+    // the addresses of the shared values being serialized on this thread, innermost last
+    static SERIALIZING_SHARED: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
 }
 
-deref_impl! {
-    <T: ?Sized> Serialize for std::sync::Arc<T> where T: Serialize
+// Marks a shared value as being serialized until it's dropped.
+struct SharedGuard;
+
+impl SharedGuard {
+    fn enter<T: ?Sized>(value: &T) -> Result<Self> {
+        let address = std::ptr::from_ref(value).cast::<()>() as usize;
+        SERIALIZING_SHARED.with(|serializing| {
+            let mut serializing = serializing.borrow_mut();
+            // a value that is already being serialized contains itself
+            if serializing.contains(&address) {
+                return Err(Error {
+                    kind: Kind::CircularReference,
+                });
+            }
+            serializing.push(address);
+            Ok(Self)
+        })
+    }
+}
+
+impl Drop for SharedGuard {
+    fn drop(&mut self) {
+        SERIALIZING_SHARED.with(|serializing| serializing.borrow_mut().pop());
+    }
 }
 
+macro_rules! shared_impl {
+    ($ty:ident) => {
+        impl<T: ?Sized> Serialize for $ty<T>
+        where
+            T: Serialize,
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
+            where
+                S: SerializerTrait,
+            {
+                let _guard = SharedGuard::enter::<T>(self)?;
+                (**self).serialize(serializer)
+            }
+        }
+    };
+}
+
+shared_impl!(Rc);
+shared_impl!(Arc);
+
 impl<T: ?Sized> Serialize for std::rc::Weak<T>
 where
     T: Serialize,
@@ -342,7 +411,7 @@ nonzero_integers! {
     NonZeroUsize,
 }
 
-impl<T: ?Sized + Copy> Serialize for Cell<T>
+impl<T: Copy> Serialize for Cell<T>
 where
     T: Serialize,
 {
@@ -365,7 +434,7 @@ where
         match self.try_borrow() {
             Ok(v) => v.serialize(serializer),
             Err(_) => Err(Error {
-                kind: Kind::Message("already mutably borrowed".to_string()),
+                kind: Kind::AlreadyBorrowed,
             }),
         }
     }
@@ -401,3 +470,109 @@ atomic_impl! {
     AtomicU64 "64"
     AtomicUsize "ptr"
 }
+
+impl Serialize for Path {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        match self.to_str() {
+            Some(path) => serializer.serialize_rust_string(path),
+            None => Err(Error {
+                kind: Kind::NonUtf8Path,
+            }),
+        }
+    }
+}
+
+impl Serialize for PathBuf {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        self.as_path().serialize(serializer)
+    }
+}
+
+// Durations and times are [secs, nanos], like serde.
+impl Serialize for Duration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        (self.as_secs(), self.subsec_nanos()).serialize(serializer)
+    }
+}
+
+impl Serialize for SystemTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        let duration = self.duration_since(UNIX_EPOCH).map_err(|_| Error {
+            kind: Kind::TimeBeforeEpoch,
+        })?;
+        duration.serialize(serializer)
+    }
+}
+
+macro_rules! display_impl {
+    ($($ty:ty),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
+                where
+                    S: SerializerTrait,
+                {
+                    serializer.serialize_rust_string(&self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+display_impl!(
+    IpAddr,
+    Ipv4Addr,
+    Ipv6Addr,
+    SocketAddr,
+    SocketAddrV4,
+    SocketAddrV6
+);
+
+// Ruby dumps ranges as an object with (non-ivar) `excl`, `begin`, and `end` fields.
+fn serialize_range<S, T>(serializer: S, start: &T, end: &T, exclusive: bool) -> Result<S::Ok>
+where
+    S: SerializerTrait,
+    T: Serialize,
+{
+    let mut ivars = serializer.serialize_object(Sym::new("Range"), 3)?;
+    ivars.serialize_entry(Sym::new("excl"), &exclusive)?;
+    ivars.serialize_entry(Sym::new("begin"), start)?;
+    ivars.serialize_entry(Sym::new("end"), end)?;
+    ivars.end()
+}
+
+impl<T> Serialize for Range<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serialize_range(serializer, &self.start, &self.end, true)
+    }
+}
+
+impl<T> Serialize for RangeInclusive<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        serialize_range(serializer, self.start(), self.end(), false)
+    }
+}
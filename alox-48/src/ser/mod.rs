@@ -5,13 +5,15 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 mod error;
 mod impls;
+mod options;
 mod serializer;
 mod traits;
 
 pub use error::Result;
 
 pub use error::{Error, Kind};
-pub use serializer::Serializer;
+pub use options::SerOptions;
+pub use serializer::{IvarOrder, Serializer, StringEncoding};
 
 pub use traits::{
     Serialize, SerializeArray, SerializeHash, SerializeIvars, Serializer as SerializerTrait,
@@ -23,6 +25,7 @@ pub use traits::{
 /// Without specialization, this isn't really possible to fix.
 ///
 /// This type is a workaround for that issue.
+/// It also implements `Deserialize`, so it can be used inside other types (like `Vec<ByteString<'_>>`) to get arrays of strings.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ByteString<'a>(pub &'a [u8]);
 
@@ -34,3 +37,23 @@ impl Serialize for ByteString<'_> {
         serializer.serialize_string(self.0)
     }
 }
+
+impl<'a> From<&'a [u8]> for ByteString<'a> {
+    fn from(value: &'a [u8]) -> Self {
+        Self(value)
+    }
+}
+
+impl std::ops::Deref for ByteString<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for ByteString<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::sync::Arc;
+
+use super::{IvarOrder, Serializer, StringEncoding};
+use crate::{ClassMapper, MARSHAL_VERSION};
+
+/// Everything that can be configured on a [`Serializer`], in one place.
+///
+/// Pass this to [`to_bytes_with`](crate::to_bytes_with), or use [`SerOptions::serializer`] to get a configured serializer.
+/// Each option has a method of the same name, and defaults to the same thing as a new [`Serializer`].
+/// New options may be added in the future, so this can only be built with those methods.
+///
+/// ```
+/// use alox_48::ser::{IvarOrder, SerOptions, StringEncoding};
+///
+/// let options = SerOptions::new()
+///     .string_encoding(StringEncoding::None)
+///     .ivar_order(IvarOrder::Preserve);
+/// let bytes = alox_48::to_bytes_with("hi", &options).unwrap();
+/// assert_eq!(bytes, b"\x04\x08\"\x07hi");
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SerOptions {
+    output_limit: Option<usize>,
+    string_encoding: StringEncoding,
+    version: [u8; 2],
+    ivar_order: IvarOrder,
+    class_mapper: Option<Arc<dyn ClassMapper>>,
+}
+
+impl Default for SerOptions {
+    fn default() -> Self {
+        Self {
+            output_limit: None,
+            string_encoding: StringEncoding::Utf8,
+            version: MARSHAL_VERSION,
+            ivar_order: IvarOrder::Preserve,
+            class_mapper: None,
+        }
+    }
+}
+
+impl SerOptions {
+    /// The default options.
+    ///
+    /// Same as [`Default::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [`Serializer::set_output_limit`].
+    pub fn output_limit(mut self, limit: Option<usize>) -> Self {
+        self.output_limit = limit;
+        self
+    }
+
+    /// See [`Serializer::set_string_encoding`].
+    pub fn string_encoding(mut self, encoding: StringEncoding) -> Self {
+        self.string_encoding = encoding;
+        self
+    }
+
+    /// See [`Serializer::set_version`].
+    pub fn version(mut self, version: [u8; 2]) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// See [`Serializer::set_ivar_order`].
+    pub fn ivar_order(mut self, order: IvarOrder) -> Self {
+        self.ivar_order = order;
+        self
+    }
+
+    /// See [`Serializer::set_class_mapper`].
+    pub fn class_mapper(mut self, mapper: Option<Arc<dyn ClassMapper>>) -> Self {
+        self.class_mapper = mapper;
+        self
+    }
+
+    /// Create a serializer with these options.
+    pub fn serializer(&self) -> Serializer {
+        let mut serializer = Serializer::new();
+        self.apply(&mut serializer);
+        serializer
+    }
+
+    /// Set every option on an existing serializer, like one made with [`Serializer::with_buffer`].
+    pub fn apply(&self, serializer: &mut Serializer) {
+        serializer.set_output_limit(self.output_limit);
+        serializer.set_string_encoding(self.string_encoding.clone());
+        serializer.set_version(self.version);
+        serializer.set_ivar_order(self.ivar_order);
+        serializer.set_class_mapper(self.class_mapper.clone());
+    }
+}
@@ -5,17 +5,70 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 #![allow(clippy::cast_possible_wrap)]
 
-use indexmap::IndexSet;
+use std::{collections::HashMap, sync::Arc};
 
 use super::{Error, Kind, Result};
-use crate::{tag::Tag, Sym, Symbol};
+#[cfg(feature = "value")]
+use crate::Value;
+use crate::{tag::Tag, ClassMapper, Sym, Symbol, MARSHAL_VERSION};
+
+// ruby only writes integers that fit in 31 bits as fixnums, everything else is a bignum
+const FIXNUM_RANGE: std::ops::RangeInclusive<i64> = -(1 << 30)..=(1 << 30) - 1;
 
 /// The `alox_48` serializer.
 #[derive(Debug, Clone)]
 pub struct Serializer {
     /// The underlying output of the serializer.
     pub output: Vec<u8>,
-    symlink: IndexSet<Symbol>,
+    // every symbol written so far, and its index in the symbol table
+    symlink: HashMap<Symbol, usize>,
+    // how many bytes would have been written, when only counting them
+    counted: Option<usize>,
+    output_limit: Option<usize>,
+    string_encoding: StringEncoding,
+    version: [u8; 2],
+    ivar_order: IvarOrder,
+    class_mapper: Option<Arc<dyn ClassMapper>>,
+}
+
+/// Which encoding instance variable [`Serializer`] writes for rust strings.
+///
+/// This only affects [`serialize_rust_string`](super::SerializerTrait::serialize_rust_string),
+/// which is what `String` and `&str` use. Strings written with `serialize_string` never get an encoding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum StringEncoding {
+    /// Always write `E = true`, marking strings as `UTF-8`.
+    ///
+    /// This is the default.
+    #[default]
+    Utf8,
+    /// Write `E = false` (`US-ASCII`) for ASCII strings, and `E = true` for everything else.
+    ///
+    /// This matches what ruby does for string literals in a file with a `US-ASCII` magic comment.
+    AsciiAware,
+    /// Don't write an encoding for ASCII strings, and write `E = true` for everything else.
+    SkipForAscii,
+    /// Never write an encoding, so strings are loaded as `ASCII-8BIT`.
+    None,
+    /// Always write this encoding by name, in an `encoding` instance variable.
+    Named(String),
+}
+
+/// The order [`Serializer`] writes instance variables in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum IvarOrder {
+    /// Write instance variables in exactly the order they are serialized in.
+    ///
+    /// [`RbFields`](crate::RbFields) keeps the order it was deserialized in,
+    /// so values round trip without any reordering. This is the default.
+    #[default]
+    Preserve,
+    /// Write instance variables sorted by name.
+    ///
+    /// Every instance variable is converted to a [`Value`] first so they can be reordered,
+    /// which is slower than [`IvarOrder::Preserve`]. This needs the `value` feature.
+    #[cfg(feature = "value")]
+    Sorted,
 }
 
 #[derive(Debug)]
@@ -24,6 +77,9 @@ pub struct SerializeIvars<'a> {
     len: usize,
     index: usize,
     state: MapState,
+    // only used with `IvarOrder::Sorted`
+    #[cfg(feature = "value")]
+    sorted: Option<Vec<(Symbol, Value)>>,
 }
 
 #[derive(Debug)]
@@ -32,6 +88,7 @@ pub struct SerializeHash<'a> {
     len: usize,
     index: usize,
     state: MapState,
+    default: HashDefault,
 }
 
 #[derive(Debug)]
@@ -44,8 +101,14 @@ pub struct SerializeArray<'a> {
 impl Default for Serializer {
     fn default() -> Self {
         Self {
-            output: vec![4, 8],
-            symlink: IndexSet::new(),
+            output: MARSHAL_VERSION.to_vec(),
+            symlink: HashMap::new(),
+            counted: None,
+            output_limit: None,
+            string_encoding: StringEncoding::Utf8,
+            version: MARSHAL_VERSION,
+            ivar_order: IvarOrder::Preserve,
+            class_mapper: None,
         }
     }
 }
@@ -56,6 +119,13 @@ enum MapState {
     Value,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+enum HashDefault {
+    None,
+    Pending,
+    Written,
+}
+
 impl Serializer {
     /// Creates a new deserializer.
     ///
@@ -65,6 +135,202 @@ impl Serializer {
         Serializer::default()
     }
 
+    /// Creates a new serializer that writes into `buffer`.
+    ///
+    /// The buffer is cleared first, but keeps its capacity.
+    #[must_use]
+    pub fn with_buffer(mut buffer: Vec<u8>) -> Self {
+        buffer.clear();
+        buffer.extend_from_slice(&MARSHAL_VERSION);
+        Self {
+            output: buffer,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new serializer that only counts how many bytes it would write.
+    ///
+    /// Nothing is written to [`output`](Self::output), use [`Serializer::bytes_written`] to get the count.
+    /// Everything else (including the output limit) works like a normal serializer, so the count is exact.
+    ///
+    /// ```
+    /// use alox_48::{Serialize, Serializer};
+    ///
+    /// let mut counter = Serializer::counting();
+    /// "hi".serialize(&mut counter).unwrap();
+    /// assert_eq!(counter.bytes_written(), alox_48::to_bytes("hi").unwrap().len());
+    /// assert_eq!(counter.output, b"\x04\x08");
+    /// ```
+    #[must_use]
+    pub fn counting() -> Self {
+        Self {
+            counted: Some(MARSHAL_VERSION.len()),
+            ..Default::default()
+        }
+    }
+
+    /// Returns how many bytes were written so far, including the 2 byte header.
+    ///
+    /// For a [counting](Self::counting) serializer, this is how many bytes would have been written.
+    pub fn bytes_written(&self) -> usize {
+        self.counted.unwrap_or(self.output.len())
+    }
+
+    /// Clear the output and symbol table so this serializer can be used again.
+    ///
+    /// The output keeps its capacity, and the output limit, string encoding, and version are unchanged.
+    pub fn reset(&mut self) {
+        self.output.clear();
+        self.output.extend_from_slice(&self.version);
+        self.symlink.clear();
+        if let Some(counted) = &mut self.counted {
+            *counted = self.version.len();
+        }
+    }
+
+    /// Take the output of this serializer.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.output
+    }
+
+    /// Set the maximum size of the output, in bytes.
+    ///
+    /// Serialization fails with [`Kind::OutputLimitExceeded`] instead of writing past the limit.
+    /// The limit includes the 2 byte header. There is no limit by default.
+    ///
+    /// The limit is checked before every write, so the output never grows past it,
+    /// but anything written before the error is left in the output.
+    /// To check the limit before writing anything, count the size first with [`Serializer::counting`],
+    /// or [`serialized_size_with`](crate::serialized_size_with).
+    pub fn set_output_limit(&mut self, limit: Option<usize>) {
+        self.output_limit = limit;
+    }
+
+    /// Returns the maximum size of the output, in bytes.
+    pub fn output_limit(&self) -> Option<usize> {
+        self.output_limit
+    }
+
+    /// Set which encoding instance variable is written for rust strings.
+    ///
+    /// See [`StringEncoding`] for the options.
+    pub fn set_string_encoding(&mut self, encoding: StringEncoding) {
+        self.string_encoding = encoding;
+    }
+
+    /// Returns which encoding instance variable is written for rust strings.
+    pub fn string_encoding(&self) -> &StringEncoding {
+        &self.string_encoding
+    }
+
+    /// Set the version number written in the header, as `[major, minor]`.
+    ///
+    /// This only changes the header, the data is still written the way 4.8 does.
+    /// Defaults to [`MARSHAL_VERSION`].
+    pub fn set_version(&mut self, version: [u8; 2]) {
+        self.version = version;
+        if let Some(header) = self.output.get_mut(..2) {
+            header.copy_from_slice(&version);
+        }
+    }
+
+    /// Returns the version number written in the header.
+    pub fn version(&self) -> [u8; 2] {
+        self.version
+    }
+
+    /// Set the order instance variables are written in.
+    ///
+    /// See [`IvarOrder`] for the options.
+    pub fn set_ivar_order(&mut self, order: IvarOrder) {
+        self.ivar_order = order;
+    }
+
+    /// Returns the order instance variables are written in.
+    pub fn ivar_order(&self) -> IvarOrder {
+        self.ivar_order
+    }
+
+    /// Set a mapper that renames classes and modules as they are written, or `None` to stop renaming them.
+    ///
+    /// See [`ClassMapper`] for which names are renamed.
+    pub fn set_class_mapper(&mut self, mapper: Option<Arc<dyn ClassMapper>>) {
+        self.class_mapper = mapper;
+    }
+
+    /// Returns the mapper renaming classes and modules, if there is one.
+    pub fn class_mapper(&self) -> Option<&Arc<dyn ClassMapper>> {
+        self.class_mapper.as_ref()
+    }
+
+    /// Write a reference to the class `name`, exactly as given.
+    ///
+    /// Class references are raw strings rather than symbols, so they aren't added to the symbol table,
+    /// and a symbol with the same name written later isn't a symlink to them.
+    /// Unlike [`SerializerTrait::serialize_class`](super::SerializerTrait::serialize_class),
+    /// the [class mapper](Self::set_class_mapper) isn't applied.
+    ///
+    /// ```
+    /// use alox_48::Serializer;
+    ///
+    /// let mut serializer = Serializer::new();
+    /// serializer.serialize_class_raw("String").unwrap();
+    /// // Marshal.dump(String)
+    /// assert_eq!(serializer.into_inner(), b"\x04\x08c\x0bString");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the [output limit](Self::set_output_limit) is exceeded.
+    pub fn serialize_class_raw(&mut self, name: &str) -> Result<()> {
+        self.write(Tag::ClassRef)?;
+        self.write_bytes_len(name)
+    }
+
+    /// Write a reference to the module `name`, exactly as given.
+    ///
+    /// See [`Serializer::serialize_class_raw`], this is the same but for modules.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the [output limit](Self::set_output_limit) is exceeded.
+    pub fn serialize_module_raw(&mut self, name: &str) -> Result<()> {
+        self.write(Tag::ModuleRef)?;
+        self.write_bytes_len(name)
+    }
+
+    fn write_class(&mut self, class: &Sym) -> Result<()> {
+        self.with_mapped_class(class, Self::write_symbol)
+    }
+
+    fn with_mapped_class(
+        &mut self,
+        class: &Sym,
+        write: impl FnOnce(&mut Self, &Sym) -> Result<()>,
+    ) -> Result<()> {
+        // the mapper can't be borrowed from self while writing
+        match self.class_mapper.clone() {
+            Some(mapper) => write(self, mapper.map_class(class).unwrap_or(class)),
+            None => write(self, class),
+        }
+    }
+
+    fn serialize_ivars(&mut self, len: usize) -> SerializeIvars<'_> {
+        #[cfg(feature = "value")]
+        let sorted = match self.ivar_order {
+            IvarOrder::Preserve => None,
+            IvarOrder::Sorted => Some(Vec::with_capacity(len)),
+        };
+        SerializeIvars {
+            serializer: self,
+            len,
+            index: 0,
+            state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+            #[cfg(feature = "value")]
+            sorted,
+        }
+    }
+
     // Does not emit a type byte.
     // FIXME: find a way around these warnings
     #[allow(
@@ -72,17 +338,18 @@ impl Serializer {
         clippy::cast_sign_loss,
         clippy::cast_possible_truncation
     )]
-    fn write_int(&mut self, v: i64) {
+    pub(crate) fn write_int(&mut self, v: i64) -> Result<()> {
         match v {
-            0 => self.write(0),
-            1..=122 => self.write(v as u8 + 5),
-            -122..=0 => self.write((256 + v - 5) as u8),
+            0 => self.write(0)?,
+            1..=122 => self.write(v as u8 + 5)?,
+            -122..=0 => self.write((256 + v - 5) as u8)?,
             mut v => {
-                let mut res = vec![];
+                let mut res = [0; 4];
+                let mut len = 0;
 
-                for _ in 0..4 {
-                    let b = v & 255;
-                    res.push(b as _);
+                for b in &mut res {
+                    *b = (v & 255) as u8;
+                    len += 1;
 
                     v >>= 8;
 
@@ -91,47 +358,84 @@ impl Serializer {
                     }
                 }
 
-                let l_byte = if v < 0 {
-                    (256 - res.len()) as u8
-                } else {
-                    res.len() as _
-                };
+                let l_byte = if v < 0 { (256 - len) as u8 } else { len as _ };
 
-                self.write(l_byte);
-                self.write_bytes(res);
+                self.write(l_byte)?;
+                self.write_bytes(&res[..len])?;
             }
         }
+
+        Ok(())
+    }
+
+    // Does not emit a type byte.
+    fn write_bignum(&mut self, v: i64) -> Result<()> {
+        self.write(if v < 0 { b'-' } else { b'+' })?;
+
+        let bytes = v.unsigned_abs().to_le_bytes();
+        let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        // the magnitude is stored in 16 bit words
+        let words = len.div_ceil(2);
+        self.write_int(words as _)?;
+        self.write_bytes(&bytes[..words * 2])
+    }
+
+    fn reserve(&mut self, additional: usize) -> Result<()> {
+        match self.output_limit {
+            Some(limit) if self.bytes_written() + additional > limit => Err(Error {
+                kind: Kind::OutputLimitExceeded(limit),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn write(&mut self, b: impl Into<u8>) -> Result<()> {
+        self.reserve(1)?;
+        match &mut self.counted {
+            Some(counted) => *counted += 1,
+            None => self.output.push(b.into()),
+        }
+
+        Ok(())
     }
 
-    fn write(&mut self, b: impl Into<u8>) {
-        self.output.push(b.into());
+    fn add_symlink(&mut self, symbol: &Sym) {
+        let index = self.symlink.len();
+        self.symlink.entry(symbol.to_symbol()).or_insert(index);
     }
 
-    fn write_symbol(&mut self, symbol: &Sym) {
-        if let Some(idx) = self.symlink.get_index_of(symbol) {
-            self.write(Tag::Symlink);
-            self.write_int(idx as _);
+    pub(crate) fn write_symbol(&mut self, symbol: &Sym) -> Result<()> {
+        if let Some(&idx) = self.symlink.get(symbol) {
+            self.write(Tag::Symlink)?;
+            self.write_int(idx as _)?;
         } else {
-            self.symlink.insert(symbol.to_symbol());
+            self.add_symlink(symbol);
 
-            self.write(Tag::Symbol);
-            self.write_int(symbol.len() as _);
+            self.write(Tag::Symbol)?;
+            self.write_int(symbol.len() as _)?;
 
-            self.write_bytes(symbol);
+            self.write_bytes(symbol)?;
         }
+
+        Ok(())
     }
 
-    fn write_bytes(&mut self, bytes: impl AsRef<[u8]>) {
-        for &b in bytes.as_ref() {
-            self.write(b);
+    pub(crate) fn write_bytes(&mut self, bytes: impl AsRef<[u8]>) -> Result<()> {
+        let bytes = bytes.as_ref();
+        self.reserve(bytes.len())?;
+        match &mut self.counted {
+            Some(counted) => *counted += bytes.len(),
+            None => self.output.extend_from_slice(bytes),
         }
+
+        Ok(())
     }
 
-    fn write_bytes_len(&mut self, bytes: impl AsRef<[u8]>) {
+    pub(crate) fn write_bytes_len(&mut self, bytes: impl AsRef<[u8]>) -> Result<()> {
         let bytes = bytes.as_ref();
 
-        self.write_int(bytes.len() as _);
-        self.write_bytes(bytes);
+        self.write_int(bytes.len() as _)?;
+        self.write_bytes(bytes)
     }
 }
 
@@ -143,48 +447,69 @@ impl<'a> super::SerializerTrait for &'a mut Serializer {
     type SerializeArray = SerializeArray<'a>;
 
     fn serialize_nil(self) -> Result<Self::Ok> {
-        self.write(Tag::Nil);
+        self.write(Tag::Nil)?;
 
         Ok(())
     }
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
-        self.write(if v { Tag::True } else { Tag::False });
+        self.write(if v { Tag::True } else { Tag::False })?;
 
         Ok(())
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
-        self.write(Tag::Integer);
-        self.write_int(v as i64);
+        self.serialize_i64(v.into())
+    }
 
-        Ok(())
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        if FIXNUM_RANGE.contains(&v) {
+            self.write(Tag::Integer)?;
+            self.write_int(v)
+        } else {
+            self.write(Tag::Bignum)?;
+            self.write_bignum(v)
+        }
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
-        self.write(Tag::Float);
+        self.write(Tag::Float)?;
 
         let str = v.to_string();
-        self.write_bytes_len(str);
+        self.write_bytes_len(str)?;
 
         Ok(())
     }
 
     fn serialize_hash(self, len: usize) -> Result<Self::SerializeHash> {
-        self.write(Tag::Hash);
-        self.write_int(len as _);
+        self.write(Tag::Hash)?;
+        self.write_int(len as _)?;
 
         Ok(SerializeHash {
             serializer: self,
             len,
             index: 0,
             state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+            default: HashDefault::None,
+        })
+    }
+
+    fn serialize_hash_with_default(self, len: usize) -> Result<Self::SerializeHash> {
+        self.write(Tag::HashDefault)?;
+        self.write_int(len as _)?;
+
+        Ok(SerializeHash {
+            serializer: self,
+            len,
+            index: 0,
+            state: MapState::Value,
+            default: HashDefault::Pending,
         })
     }
 
     fn serialize_array(self, len: usize) -> Result<Self::SerializeArray> {
-        self.write(Tag::Array);
-        self.write_int(len as _);
+        self.write(Tag::Array)?;
+        self.write_int(len as _)?;
 
         Ok(SerializeArray {
             serializer: self,
@@ -194,81 +519,89 @@ impl<'a> super::SerializerTrait for &'a mut Serializer {
     }
 
     fn serialize_string(self, data: &[u8]) -> Result<Self::Ok> {
-        self.write(Tag::String);
-        self.write_bytes_len(data);
+        self.write(Tag::String)?;
+        self.write_bytes_len(data)?;
 
         Ok(())
     }
 
     fn serialize_symbol(self, sym: &Sym) -> Result<Self::Ok> {
-        self.write_symbol(sym);
+        self.write_symbol(sym)?;
 
         Ok(())
     }
 
     fn serialize_regular_expression(self, regex: &[u8], flags: u8) -> Result<Self::Ok> {
-        self.write(Tag::RawRegexp);
-        self.write_bytes_len(regex);
-        self.write(flags);
+        self.write(Tag::RawRegexp)?;
+        self.write_bytes_len(regex)?;
+        self.write(flags)?;
 
         Ok(())
     }
 
     fn serialize_object(self, class: &Sym, len: usize) -> Result<Self::SerializeIvars> {
-        self.write(Tag::Object);
-        self.write_symbol(class);
-        self.write_int(len as _);
+        self.write(Tag::Object)?;
+        self.write_class(class)?;
+        self.write_int(len as _)?;
 
-        Ok(SerializeIvars {
-            serializer: self,
-            len,
-            index: 0,
-            state: MapState::Value, // we want to enforce getting a key next so we set the state to value
-        })
+        Ok(self.serialize_ivars(len))
     }
 
     fn serialize_struct(self, name: &Sym, len: usize) -> Result<Self::SerializeIvars> {
-        self.write(Tag::Struct);
-        self.write_symbol(name);
-        self.write_int(len as _);
+        self.write(Tag::Struct)?;
+        self.write_class(name)?;
+        self.write_int(len as _)?;
 
-        Ok(SerializeIvars {
-            serializer: self,
-            len,
-            index: 0,
-            state: MapState::Value, // we want to enforce getting a key next so we set the state to value
-        })
+        Ok(self.serialize_ivars(len))
     }
 
     fn serialize_class(self, class: &Sym) -> Result<Self::Ok> {
-        self.write(Tag::ClassRef);
-        // Apparently, this isn't a symbol. How strange!
-        self.write_bytes_len(class);
-
-        Ok(())
+        self.with_mapped_class(class, |this, class| {
+            this.serialize_class_raw(class.as_str())
+        })
     }
 
     fn serialize_module(self, module: &Sym) -> Result<Self::Ok> {
-        self.write(Tag::ModuleRef);
-        self.write_bytes_len(module);
+        self.with_mapped_class(module, |this, module| {
+            this.serialize_module_raw(module.as_str())
+        })
+    }
 
-        Ok(())
+    fn serialize_rust_string(self, string: &str) -> Result<Self::Ok> {
+        let is_ascii = string.is_ascii();
+        let skip = match self.string_encoding {
+            StringEncoding::None => true,
+            StringEncoding::SkipForAscii => is_ascii,
+            _ => false,
+        };
+        if skip {
+            return self.serialize_string(string.as_bytes());
+        }
+
+        self.write(Tag::Instance)?;
+        self.serialize_string(string.as_bytes())?;
+        self.write_int(1)?;
+
+        // the encoding name can't be borrowed from self while writing it
+        if let StringEncoding::Named(name) = &self.string_encoding {
+            let name = name.clone();
+            self.serialize_symbol(Sym::new("encoding"))?;
+            return self.serialize_string(name.as_bytes());
+        }
+        let utf8 = !(is_ascii && self.string_encoding == StringEncoding::AsciiAware);
+        self.serialize_symbol(Sym::new("E"))?;
+        self.serialize_bool(utf8)
     }
 
     fn serialize_instance<V>(self, value: &V, len: usize) -> Result<Self::SerializeIvars>
     where
         V: crate::Serialize + ?Sized,
     {
-        self.write(Tag::Instance);
+        self.write(Tag::Instance)?;
         value.serialize(&mut *self)?;
-        self.write_int(len as _);
+        self.write_int(len as _)?;
 
-        Ok(SerializeIvars {
-            serializer: self,
-            len,
-            index: 0,
-            state: MapState::Value, // we want to enforce getting a key next so we set the state to value
-        })
+        Ok(self.serialize_ivars(len))
     }
 
     fn serialize_extended<V>(self, module: &Sym, value: &V) -> Result<Self::Ok>
@@ -276,8 +609,8 @@ impl<'a> super::SerializerTrait for &'a mut Serializer {
         V: crate::Serialize + ?Sized,
     {
         // the ruby docs lie! it is the module which comes before the value.
-        self.write(Tag::Extended);
-        self.write_symbol(module);
+        self.write(Tag::Extended)?;
+        self.write_class(module)?;
         value.serialize(self)
     }
 
@@ -285,15 +618,15 @@ impl<'a> super::SerializerTrait for &'a mut Serializer {
     where
         V: crate::Serialize + ?Sized,
     {
-        self.write(Tag::UserClass);
-        self.write_symbol(class);
+        self.write(Tag::UserClass)?;
+        self.write_class(class)?;
         value.serialize(self)
     }
 
     fn serialize_user_data(self, class: &Sym, data: &[u8]) -> Result<Self::Ok> {
-        self.write(Tag::UserDef);
-        self.write_symbol(class);
-        self.write_bytes_len(data);
+        self.write(Tag::UserDef)?;
+        self.write_class(class)?;
+        self.write_bytes_len(data)?;
 
         Ok(())
     }
@@ -302,8 +635,8 @@ impl<'a> super::SerializerTrait for &'a mut Serializer {
     where
         V: crate::Serialize + ?Sized,
     {
-        self.write(Tag::UserMarshal);
-        self.write_symbol(class);
+        self.write(Tag::UserMarshal)?;
+        self.write_class(class)?;
         value.serialize(self)
     }
 
@@ -311,13 +644,22 @@ impl<'a> super::SerializerTrait for &'a mut Serializer {
     where
         V: crate::Serialize + ?Sized,
     {
-        self.write(Tag::Data);
-        self.write_symbol(class);
+        self.write(Tag::Data)?;
+        self.write_class(class)?;
         value.serialize(self)
     }
+
+    fn serialize_raw(self, raw: &crate::RawValue<'_>) -> Result<Self::Ok> {
+        self.write_bytes(raw.bytes)?;
+        // later symbol links need to account for symbols the raw value adds to the symbol table
+        for &symbol in &raw.symbols {
+            self.add_symlink(symbol);
+        }
+        Ok(())
+    }
 }
 
-impl<'a> super::SerializeIvars for SerializeIvars<'a> {
+impl super::SerializeIvars for SerializeIvars<'_> {
     type Ok = ();
 
     fn serialize_field(&mut self, k: &Sym) -> Result<()> {
@@ -336,7 +678,12 @@ impl<'a> super::SerializeIvars for SerializeIvars<'a> {
             MapState::Value => self.state = MapState::Key,
         }
 
-        self.serializer.write_symbol(k);
+        #[cfg(feature = "value")]
+        if let Some(sorted) = &mut self.sorted {
+            sorted.push((k.to_symbol(), Value::Nil));
+            return Ok(());
+        }
+        self.serializer.write_symbol(k)?;
 
         Ok(())
     }
@@ -353,6 +700,11 @@ impl<'a> super::SerializeIvars for SerializeIvars<'a> {
             }
             MapState::Key => self.state = MapState::Value,
         }
+        #[cfg(feature = "value")]
+        if let Some((_, value)) = self.sorted.as_mut().and_then(|s| s.last_mut()) {
+            *value = v.serialize(crate::ValueSerializer)?;
+            return Ok(());
+        }
         v.serialize(&mut *self.serializer)?;
 
         Ok(())
@@ -363,13 +715,41 @@ impl<'a> super::SerializeIvars for SerializeIvars<'a> {
             Err(Error {
                 kind: Kind::UndershotProvidedLen(self.index, self.len),
             })
+        } else if let MapState::Key = self.state {
+            Err(Error {
+                kind: Kind::KeyAfterKey,
+            })
         } else {
+            #[cfg(feature = "value")]
+            if let Some(mut sorted) = self.sorted {
+                sorted.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+                for (k, v) in &sorted {
+                    self.serializer.write_symbol(k)?;
+                    crate::Serialize::serialize(v, &mut *self.serializer)?;
+                }
+            }
             Ok(())
         }
     }
 }
 
-impl<'a> super::SerializeHash for SerializeHash<'a> {
+impl SerializeHash<'_> {
+    fn check_entries(&self) -> Result<()> {
+        if self.index < self.len {
+            Err(Error {
+                kind: Kind::UndershotProvidedLen(self.index, self.len),
+            })
+        } else if let MapState::Key = self.state {
+            Err(Error {
+                kind: Kind::KeyAfterKey,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl super::SerializeHash for SerializeHash<'_> {
     type Ok = ();
 
     fn serialize_key<K>(&mut self, k: &K) -> Result<()>
@@ -411,18 +791,33 @@ impl<'a> super::SerializeHash for SerializeHash<'a> {
         v.serialize(&mut *self.serializer)
     }
 
+    fn serialize_default<V>(&mut self, v: &V) -> Result<()>
+    where
+        V: crate::Serialize + ?Sized,
+    {
+        if self.default != HashDefault::Pending {
+            return Err(Error {
+                kind: Kind::UnexpectedHashDefault,
+            });
+        }
+        self.check_entries()?;
+
+        self.default = HashDefault::Written;
+        v.serialize(&mut *self.serializer)
+    }
+
     fn end(self) -> Result<Self::Ok> {
-        if self.index < self.len {
-            Err(Error {
-                kind: Kind::UndershotProvidedLen(self.index, self.len),
-            })
-        } else {
-            Ok(())
+        self.check_entries()?;
+        if self.default == HashDefault::Pending {
+            return Err(Error {
+                kind: Kind::MissingHashDefault,
+            });
         }
+        Ok(())
     }
 }
 
-impl<'a> super::SerializeArray for SerializeArray<'a> {
+impl super::SerializeArray for SerializeArray<'_> {
     type Ok = ();
 
     fn serialize_element<T>(&mut self, v: &T) -> Result<()>
@@ -430,6 +825,11 @@ impl<'a> super::SerializeArray for SerializeArray<'a> {
         T: crate::Serialize + ?Sized,
     {
         self.index += 1;
+        if self.index > self.len {
+            return Err(Error {
+                kind: Kind::OvershotProvidedLen(self.index, self.len),
+            });
+        }
         v.serialize(&mut *self.serializer)
     }
 
@@ -1,11 +1,11 @@
-use crate::Sym;
+use crate::{RawValue, Sym};
 
 // Copyright (c) 2024 Lily Lyons
 //
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
-use super::Result;
+use super::{Error, Kind, Result};
 
 /// A structure that can be serialized into ruby marshal data.
 pub trait Serialize {
@@ -38,12 +38,39 @@ pub trait Serializer: Sized {
     /// Serialize an integer value.
     fn serialize_i32(self, v: i32) -> Result<Self::Ok>;
 
+    /// Serialize an integer value that might not fit in an `i32`.
+    ///
+    /// Like ruby, [`Serializer`](crate::Serializer) writes integers that fit in 31 bits as fixnums and everything else as bignums
+    /// (this applies to [`serialize_i32`](Self::serialize_i32) too).
+    ///
+    /// By default this calls [`serialize_i32`](Self::serialize_i32) if `v` fits in an `i32`,
+    /// and errors with [`Kind::IntegerOutOfRange`](super::Kind::IntegerOutOfRange) otherwise.
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        match i32::try_from(v) {
+            Ok(v) => self.serialize_i32(v),
+            Err(_) => Err(Error {
+                kind: Kind::IntegerOutOfRange,
+            }),
+        }
+    }
+
     /// Serialize a float value.
     fn serialize_f64(self, v: f64) -> Result<Self::Ok>;
 
     /// Serialize a hash.
     fn serialize_hash(self, len: usize) -> Result<Self::SerializeHash>;
 
+    /// Serialize a hash with a default value.
+    ///
+    /// The default value must be serialized with [`SerializeHash::serialize_default`] after all the entries.
+    /// Serializers that can't represent a default value return [`Kind::HashDefaultUnsupported`] by default.
+    fn serialize_hash_with_default(self, len: usize) -> Result<Self::SerializeHash> {
+        let _ = len;
+        Err(Error {
+            kind: Kind::HashDefaultUnsupported,
+        })
+    }
+
     /// Serialize an array.
     fn serialize_array(self, len: usize) -> Result<Self::SerializeArray>;
 
@@ -63,9 +90,15 @@ pub trait Serializer: Sized {
     fn serialize_struct(self, name: &Sym, len: usize) -> Result<Self::SerializeIvars>;
 
     /// Serialize a class.
+    ///
+    /// Ruby writes class references as raw strings, not symbols, so they don't go through the symbol table.
+    /// Serializing a [`Value::Class`](crate::Value::Class) writes the same bytes ruby does,
+    /// unless the serializer renames classes (see [`Serializer::serialize_class_raw`](crate::Serializer::serialize_class_raw)).
     fn serialize_class(self, class: &Sym) -> Result<Self::Ok>;
 
     /// Serialize a module.
+    ///
+    /// Like classes, modules are written as raw strings, not symbols.
     fn serialize_module(self, module: &Sym) -> Result<Self::Ok>;
 
     /// Serialize an instance.
@@ -96,10 +129,21 @@ pub trait Serializer: Sized {
     where
         V: Serialize + ?Sized;
 
+    /// Write the bytes of a value verbatim.
+    ///
+    /// This is used for serializing [`RawValue`].
+    /// Serializers that don't write marshal data can't support this, and return [`Kind::RawValueUnsupported`] by default.
+    fn serialize_raw(self, raw: &RawValue<'_>) -> Result<Self::Ok> {
+        let _ = raw;
+        Err(Error {
+            kind: Kind::RawValueUnsupported,
+        })
+    }
+
     /// A convenience method for serializing a string.
     fn serialize_rust_string(self, string: &str) -> Result<Self::Ok> {
         struct StringSerialize<'a>(&'a str);
-        impl<'a> Serialize for StringSerialize<'a> {
+        impl Serialize for StringSerialize<'_> {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok>
             where
                 S: Serializer,
@@ -142,6 +186,42 @@ pub trait Serializer: Sized {
         }
         serialize_hash.end()
     }
+
+    /// A convenience method for serializing an object from its instance variables.
+    ///
+    /// The instance variables can be borrowed from anywhere, so there's no need to collect them into [`RbFields`](crate::RbFields) first.
+    fn collect_object<K, V, I>(self, class: &Sym, iter: I) -> Result<Self::Ok>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        I::IntoIter: ExactSizeIterator,
+        K: AsRef<Sym>,
+        V: Serialize,
+    {
+        let iter = iter.into_iter();
+        let mut serialize_ivars = self.serialize_object(class, iter.len())?;
+        for (field, value) in iter {
+            serialize_ivars.serialize_entry(field.as_ref(), &value)?;
+        }
+        serialize_ivars.end()
+    }
+
+    /// A convenience method for serializing a struct from its members.
+    ///
+    /// Like [`Serializer::collect_object`], but for [`Serializer::serialize_struct`].
+    fn collect_struct<K, V, I>(self, name: &Sym, iter: I) -> Result<Self::Ok>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        I::IntoIter: ExactSizeIterator,
+        K: AsRef<Sym>,
+        V: Serialize,
+    {
+        let iter = iter.into_iter();
+        let mut serialize_ivars = self.serialize_struct(name, iter.len())?;
+        for (field, value) in iter {
+            serialize_ivars.serialize_entry(field.as_ref(), &value)?;
+        }
+        serialize_ivars.end()
+    }
 }
 
 /// A structure that can serialize instance variables of an object.
@@ -209,6 +289,20 @@ pub trait SerializeHash {
         self.serialize_value(v)
     }
 
+    /// Serialize the default value of a hash started with [`Serializer::serialize_hash_with_default`].
+    ///
+    /// Must be called after all the entries.
+    /// By default this returns [`Kind::UnexpectedHashDefault`], for serializers that don't support hashes with default values.
+    fn serialize_default<V>(&mut self, v: &V) -> Result<()>
+    where
+        V: Serialize + ?Sized,
+    {
+        let _ = v;
+        Err(Error {
+            kind: Kind::UnexpectedHashDefault,
+        })
+    }
+
     /// End the serialization.
     fn end(self) -> Result<Self::Ok>;
 }
@@ -3,65 +3,98 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
-#![allow(dead_code)]
 
+//! The type tags used by the marshal format.
+//!
+//! Every value in marshal data starts with one of these bytes.
+
+/// A byte identifying the type of a marshal value.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Tag {
+    /// `nil`.
     Nil = b'0',
 
+    /// `true`.
     True = b'T',
 
+    /// `false`.
     False = b'F',
 
+    /// A fixnum, stored as a packed integer.
     Integer = b'i',
 
+    /// An integer too large for a fixnum, followed by its sign (`+` or `-`),
+    /// its length in 16 bit words, and its magnitude as little endian bytes.
+    Bignum = b'l',
+
+    /// A float, stored as a length-prefixed string.
     Float = b'f',
 
+    /// A string. Its encoding is stored by wrapping it in a [`Tag::Instance`].
     String = b'\"',
 
+    /// An array, followed by its length and elements.
     Array = b'[',
 
+    /// A hash, followed by its length and entries.
     Hash = b'{',
 
+    /// A hash with a default value, which follows its entries.
     HashDefault = b'}',
 
+    /// A new symbol, which is added to the symbol table.
     Symbol = b':',
 
+    /// A reference to a symbol already in the symbol table, by index.
     Symlink = b';',
 
+    /// A value followed by extra instance variables, usually a string and its encoding.
     Instance = b'I',
 
+    /// A regular expression, followed by its source and flags.
     RawRegexp = b'/',
 
+    /// A reference to a class, by name.
     ClassRef = b'c',
 
+    /// A reference to a module, by name.
     ModuleRef = b'm',
 
+    /// An object, followed by its class and instance variables.
     Object = b'o',
 
+    /// A reference to an object already seen, by index.
     ObjectLink = b'@',
 
+    /// An object serialized with `_dump`, followed by its class and data.
     UserDef = b'u',
 
+    /// A struct, followed by its name and members.
     Struct = b'S',
 
+    /// A subclass of `String`, `Array`, `Hash`, or `Regexp`, followed by its class and the value.
     UserClass = b'C',
 
+    /// A value extended with a module, followed by the module and the value.
     Extended = b'e',
 
+    /// An object serialized with `marshal_dump`, followed by its class and the value.
     UserMarshal = b'U',
 
+    /// A C extension object serialized with `_dump_data`, followed by its class and the value.
     Data = b'd',
 }
 
 impl Tag {
+    /// Returns the tag with the given byte, or `None` if there isn't one.
     pub fn from_u8(value: u8) -> Option<Tag> {
         match value {
             b'0' => Some(Tag::Nil),
             b'T' => Some(Tag::True),
             b'F' => Some(Tag::False),
             b'i' => Some(Tag::Integer),
+            b'l' => Some(Tag::Bignum),
             b'f' => Some(Tag::Float),
             b'\"' => Some(Tag::String),
             b'[' => Some(Tag::Array),
@@ -85,6 +118,7 @@ impl Tag {
         }
     }
 
+    /// Returns whether values with this tag are added to the object table, and so can be referenced by an object link.
     pub fn is_object_link_referenceable(self) -> bool {
         !matches!(
             self,
@@ -0,0 +1,366 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Checking (de)serialization against a real ruby interpreter.
+//!
+//! alox-48's own tests can only check that it reads back what it writes. The functions here
+//! shell out to `ruby` instead, to check that Ruby's `Marshal.load` accepts what a type serializes to,
+//! and that a type keeps everything in data dumped by Ruby's `Marshal.dump`.
+//!
+//! Ruby needs the classes in the data to be defined before loading it, so [`definitions`] generates
+//! placeholders for them: plain classes for objects, `_load`/`_dump` for user data, `marshal_load`/`marshal_dump`
+//! for user marshal objects, structs with the right members, and so on. Classes Ruby already has are left alone.
+//!
+//! ```no_run
+//! use alox_48::testing::conformance::{check, check_fixture, Ruby};
+//!
+//! let ruby = Ruby::find().expect("ruby is not installed");
+//! check(&ruby, &vec![1, 2, 3]).unwrap();
+//!
+//! let name: String = check_fixture(&ruby, r#""Aluxes""#).unwrap();
+//! assert_eq!(name, "Aluxes");
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fmt::{Debug, Write as _},
+    io::Write as _,
+    process::{Command, ExitStatus, Stdio},
+};
+
+use crate::{DeError, DeserializeOwned, SerError, Serialize, Symbol, Value};
+
+/// Type alias around a result.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Why a conformance check failed.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Running ruby failed.
+    #[error("failed to run ruby: {0}")]
+    Io(#[from] std::io::Error),
+    /// Ruby ran, but raised an error (for example, because it couldn't load the data).
+    #[error("ruby exited with {status}: {stderr}")]
+    Ruby {
+        /// How ruby exited.
+        status: ExitStatus,
+        /// What ruby printed to stderr.
+        stderr: String,
+    },
+    /// alox-48 couldn't deserialize the data.
+    #[error(transparent)]
+    De(#[from] DeError),
+    /// alox-48 couldn't serialize the value.
+    #[error(transparent)]
+    Ser(#[from] SerError),
+    /// The data changed on its way through ruby or alox-48.
+    #[error("data changed after a round trip\nexpected: {expected}\n   found: {found}")]
+    Mismatch {
+        /// What the data should have been, in debug format.
+        expected: String,
+        /// What the data was, in debug format.
+        found: String,
+    },
+}
+
+/// A ruby interpreter.
+#[derive(Debug, Clone)]
+pub struct Ruby {
+    program: OsString,
+}
+
+impl Ruby {
+    /// Use the ruby interpreter at `program`, which is looked up in `PATH` if it's just a name.
+    pub fn new(program: impl Into<OsString>) -> Self {
+        Self {
+            program: program.into(),
+        }
+    }
+
+    /// Find a ruby interpreter, returning `None` if there isn't one.
+    ///
+    /// This uses the `ALOX_48_RUBY` environment variable if it's set, and `ruby` otherwise.
+    /// Tests that need ruby can skip themselves when this returns `None`.
+    pub fn find() -> Option<Self> {
+        let program = std::env::var_os("ALOX_48_RUBY").unwrap_or_else(|| "ruby".into());
+        let ruby = Self::new(program);
+        ruby.version().is_ok().then_some(ruby)
+    }
+
+    /// Returns the version of ruby, like `3.3.0`.
+    pub fn version(&self) -> Result<String> {
+        let output = self.run("print RUBY_VERSION", &[])?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    /// Evaluate some ruby code, returning the result dumped by `Marshal.dump`.
+    ///
+    /// This is how fixtures are made. The code can define classes before its last expression, which is what's dumped.
+    pub fn dump(&self, code: &str) -> Result<Vec<u8>> {
+        let script = format!(
+            "__alox_value = begin\n{code}\nend\nSTDOUT.binmode.write(Marshal.dump(__alox_value))"
+        );
+        self.run(&script, &[])
+    }
+
+    /// Load `data` with `Marshal.load` and dump it again with `Marshal.dump`, returning the result.
+    ///
+    /// This fails if ruby can't load the data. Placeholders from [`definitions`] are defined first.
+    /// The result may not be identical to `data`, as ruby writes object links (and alox-48 doesn't).
+    pub fn reload(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let value: Value = crate::from_bytes(data)?;
+        let script = format!(
+            "{}STDOUT.binmode.write(Marshal.dump(Marshal.load(STDIN.binmode.read)))",
+            definitions(&value)
+        );
+        self.run(&script, data)
+    }
+
+    /// Load `data` with `Marshal.load`, returning what `inspect` says about it.
+    ///
+    /// Useful for showing what ruby thinks some data is when a check fails.
+    pub fn inspect(&self, data: &[u8]) -> Result<String> {
+        let value: Value = crate::from_bytes(data)?;
+        let script = format!(
+            "{}print Marshal.load(STDIN.binmode.read).inspect",
+            definitions(&value)
+        );
+        let output = self.run(&script, data)?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    fn run(&self, script: &str, input: &[u8]) -> Result<Vec<u8>> {
+        let mut child = Command::new(&self.program)
+            .arg("-e")
+            .arg(script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // written from another thread, so ruby can't block on a full stdout while we're writing
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+        let output = std::thread::scope(|scope| {
+            let writer = scope.spawn(move || stdin.write_all(input));
+            let output = child.wait_with_output();
+            // ruby doesn't have to read all of its input
+            let _ = writer.join();
+            output
+        })?;
+
+        if !output.status.success() {
+            return Err(Error::Ruby {
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Check that ruby can load what `value` serializes to, and that `value` deserializes from what ruby dumps it as.
+///
+/// # Errors
+///
+/// Errors if ruby can't load the data, or if the value deserialized afterwards isn't equal to `value`.
+pub fn check<T>(ruby: &Ruby, value: &T) -> Result<()>
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let bytes = crate::to_bytes(value)?;
+    let reloaded = ruby.reload(&bytes)?;
+    let found: T = crate::from_bytes(&reloaded)?;
+
+    if found != *value {
+        return Err(Error::Mismatch {
+            expected: format!("{value:?}"),
+            found: format!("{found:?}"),
+        });
+    }
+    Ok(())
+}
+
+/// Check that `T` keeps everything in a fixture dumped by ruby, returning the deserialized fixture.
+///
+/// `code` is evaluated by [`Ruby::dump`]. The result is deserialized as a `T`, serialized again, and loaded by ruby,
+/// which should give back the same data that it dumped.
+///
+/// # Errors
+///
+/// Errors if `T` can't deserialize the fixture, if ruby can't load what `T` serializes to,
+/// or if anything was lost on the way.
+pub fn check_fixture<T>(ruby: &Ruby, code: &str) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let fixture = ruby.dump(code)?;
+    let value: T = crate::from_bytes(&fixture)?;
+    let reloaded = ruby.reload(&crate::to_bytes(&value)?)?;
+
+    // compared as values, since ruby only writes object links for the fixture
+    let expected: Value = crate::from_bytes(&fixture)?;
+    let found: Value = crate::from_bytes(&reloaded)?;
+    if !found.eq_unordered(&expected) {
+        return Err(Error::Mismatch {
+            expected: format!("{expected:?}"),
+            found: format!("{found:?}"),
+        });
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Definition {
+    Class,
+    Module,
+    Userdata,
+    UserMarshal,
+    Struct(Vec<String>),
+    Subclass(&'static str),
+}
+
+/// Generate ruby code defining placeholders for the classes and modules in `value`, so ruby can load it.
+///
+/// Classes and modules that are already defined (like `Time` or `Set`) are left alone.
+/// Classes are defined before anything nested in them, and namespaces that aren't classes themselves are defined as modules.
+pub fn definitions(value: &Value) -> String {
+    let mut definitions = BTreeMap::new();
+    collect(value, &mut definitions);
+
+    let mut code = String::from(DEFINE);
+    for (name, definition) in definitions {
+        let body = match definition {
+            Definition::Class => "Class.new".to_string(),
+            Definition::Module => "Module.new".to_string(),
+            Definition::Userdata => USERDATA.to_string(),
+            Definition::UserMarshal => USER_MARSHAL.to_string(),
+            Definition::Struct(members) => {
+                let members: Vec<_> = members
+                    .iter()
+                    .map(|m| format!(":{}", ruby_string(m)))
+                    .collect();
+                format!("Struct.new({})", members.join(", "))
+            }
+            Definition::Subclass(base) => format!("Class.new({base})"),
+        };
+        let _ = writeln!(code, "__alox_define({}) {{ {body} }}", ruby_string(&name));
+    }
+    code
+}
+
+// single quoted, so names can't interpolate (`#{...}`) or otherwise escape into the script
+fn ruby_string(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('\'');
+    for c in string.chars() {
+        if matches!(c, '\\' | '\'') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+// `name` may be nested in modules that don't exist yet
+const DEFINE: &str = r#"def __alox_define(name)
+  *scopes, last = name.split("::")
+  scope = scopes.inject(Object) do |scope, part|
+    scope.const_defined?(part, false) ? scope.const_get(part, false) : scope.const_set(part, Module.new)
+  end
+  scope.const_set(last, yield) unless scope.const_defined?(last, false)
+end
+"#;
+
+// the data is kept as is, so it's dumped unchanged
+const USERDATA: &str = "Class.new { def self._load(data); o = allocate; o.instance_variable_set(:@__alox_data, data); o; end; def _dump(_level); @__alox_data; end }";
+
+const USER_MARSHAL: &str = "Class.new { def marshal_load(data); @__alox_data = data; end; def marshal_dump; @__alox_data; end }";
+
+// the first definition of a name wins
+fn define(definitions: &mut BTreeMap<String, Definition>, name: &Symbol, definition: Definition) {
+    definitions
+        .entry(name.as_str().to_string())
+        .or_insert(definition);
+}
+
+fn collect(value: &Value, definitions: &mut BTreeMap<String, Definition>) {
+    match value {
+        Value::Object(object) => {
+            define(definitions, &object.class, Definition::Class);
+            object.fields.values().for_each(|v| collect(v, definitions));
+        }
+        Value::RbStruct(rb_struct) => {
+            let members = rb_struct
+                .fields
+                .keys()
+                .map(|member| member.as_str().to_string())
+                .collect();
+            define(definitions, &rb_struct.class, Definition::Struct(members));
+            rb_struct
+                .fields
+                .values()
+                .for_each(|v| collect(v, definitions));
+        }
+        Value::Userdata(userdata) => define(definitions, &userdata.class, Definition::Userdata),
+        Value::Class(class) => define(definitions, class, Definition::Class),
+        Value::Module(module) => define(definitions, module, Definition::Module),
+        Value::UserMarshal { class, value } => {
+            define(definitions, class, Definition::UserMarshal);
+            collect(value, definitions);
+        }
+        Value::Extended { module, value } => {
+            define(definitions, module, Definition::Module);
+            collect(value, definitions);
+        }
+        Value::UserClass { class, value } => {
+            if let Some(base) = builtin_class(value) {
+                define(definitions, class, Definition::Subclass(base));
+            }
+            collect(value, definitions);
+        }
+        Value::Data { value, .. } => collect(value, definitions),
+        Value::Instance(instance) => {
+            collect(&instance.value, definitions);
+            instance
+                .fields
+                .values()
+                .for_each(|v| collect(v, definitions));
+        }
+        Value::Array(array) => array.iter().for_each(|v| collect(v, definitions)),
+        Value::Hash(hash) => hash.iter().for_each(|(k, v)| {
+            collect(k, definitions);
+            collect(v, definitions);
+        }),
+        Value::HashWithDefault { hash, default } => {
+            hash.iter().for_each(|(k, v)| {
+                collect(k, definitions);
+                collect(v, definitions);
+            });
+            collect(default, definitions);
+        }
+        Value::Nil
+        | Value::Bool(_)
+        | Value::Float(_)
+        | Value::Integer(_)
+        | Value::String(_)
+        | Value::Symbol(_)
+        | Value::Regex { .. } => {}
+    }
+}
+
+// the builtin class a user class is a subclass of
+fn builtin_class(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::String(_) => Some("String"),
+        Value::Array(_) => Some("Array"),
+        Value::Hash(_) | Value::HashWithDefault { .. } => Some("Hash"),
+        Value::Regex { .. } => Some("Regexp"),
+        Value::Instance(instance) => builtin_class(&instance.value),
+        _ => None,
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::Token;
+use crate::{
+    de::{DeserializeSeed, Ignored},
+    ArrayAccess, DeError, DeResult, Deserialize, DeserializerTrait, HashAccess, InstanceAccess,
+    IvarAccess, Sym, Visitor, VisitorInstance, VisitorOption,
+};
+
+/// A deserializer that produces values from a list of [`Token`]s.
+///
+/// See [`super::assert_de_tokens`] for a convenient wrapper.
+#[derive(Debug)]
+pub struct MockDeserializer<'de> {
+    tokens: &'de [Token<'de>],
+}
+
+#[derive(Debug)]
+struct Access<'a, 'de> {
+    deserializer: &'a mut MockDeserializer<'de>,
+    len: usize,
+    index: usize,
+    default: bool,
+}
+
+impl<'de> MockDeserializer<'de> {
+    /// Create a new deserializer that produces `tokens`.
+    pub fn new(tokens: &'de [Token<'de>]) -> Self {
+        Self { tokens }
+    }
+
+    /// Returns the tokens that have not been deserialized yet.
+    pub fn remaining(&self) -> &'de [Token<'de>] {
+        self.tokens
+    }
+
+    fn peek(&self) -> DeResult<Token<'de>> {
+        self.tokens
+            .first()
+            .copied()
+            .ok_or_else(|| DeError::custom("ran out of tokens"))
+    }
+
+    fn next(&mut self) -> DeResult<Token<'de>> {
+        let token = self.peek()?;
+        self.tokens = &self.tokens[1..];
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token<'_>) -> DeResult<()> {
+        let token = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(DeError::custom(format!(
+                "expected token {expected}, found {token}"
+            )))
+        }
+    }
+
+    // Skips whatever the visitor didn't read, up to and including `end`.
+    fn end(&mut self, end: Token<'_>) -> DeResult<()> {
+        loop {
+            match self.peek()? {
+                token if token == end => return self.expect(end),
+                Token::Ivar(_) => {
+                    self.next()?;
+                }
+                _ => {
+                    Ignored::deserialize(&mut *self)?;
+                }
+            }
+        }
+    }
+
+    fn access(&mut self, len: usize) -> Access<'_, 'de> {
+        Access {
+            deserializer: self,
+            len,
+            index: 0,
+            default: false,
+        }
+    }
+}
+
+impl<'de> DeserializerTrait<'de> for &mut MockDeserializer<'de> {
+    fn deserialize<V>(self, visitor: V) -> DeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next()? {
+            Token::Nil => visitor.visit_nil(),
+            Token::Bool(v) => visitor.visit_bool(v),
+            Token::I32(v) => visitor.visit_i32(v),
+            Token::I64(v) => visitor.visit_i64(v),
+            Token::F64(v) => visitor.visit_f64(v),
+            Token::String(data) => visitor.visit_string(data),
+            Token::Symbol(sym) => visitor.visit_symbol(Sym::new(sym)),
+            Token::Regex { data, flags } => visitor.visit_regular_expression(data, flags),
+            Token::Array { len } => {
+                let value = visitor.visit_array(self.access(len))?;
+                self.end(Token::ArrayEnd)?;
+                Ok(value)
+            }
+            Token::Hash { len } => {
+                let value = visitor.visit_hash(self.access(len))?;
+                self.end(Token::HashEnd)?;
+                Ok(value)
+            }
+            Token::HashWithDefault { len } => {
+                let mut access = self.access(len);
+                access.default = true;
+                let value = visitor.visit_hash_with_default(access)?;
+                self.end(Token::HashEnd)?;
+                Ok(value)
+            }
+            Token::Object { class, len } => {
+                let value = visitor.visit_object(Sym::new(class), self.access(len))?;
+                self.end(Token::IvarsEnd)?;
+                Ok(value)
+            }
+            Token::Struct { name, len } => {
+                let value = visitor.visit_struct(Sym::new(name), self.access(len))?;
+                self.end(Token::IvarsEnd)?;
+                Ok(value)
+            }
+            Token::Instance { len } => {
+                let value = visitor.visit_instance(self.access(len))?;
+                self.end(Token::IvarsEnd)?;
+                Ok(value)
+            }
+            Token::Class(class) => visitor.visit_class(Sym::new(class)),
+            Token::Module(module) => visitor.visit_module(Sym::new(module)),
+            Token::Extended { module } => visitor.visit_extended(Sym::new(module), self),
+            Token::UserClass { class } => visitor.visit_user_class(Sym::new(class), self),
+            Token::UserData { class, data } => visitor.visit_user_data(Sym::new(class), data),
+            Token::UserMarshal { class } => visitor.visit_user_marshal(Sym::new(class), self),
+            Token::Data { class } => visitor.visit_data(Sym::new(class), self),
+            token @ (Token::ArrayEnd | Token::HashEnd | Token::Ivar(_) | Token::IvarsEnd) => Err(
+                DeError::custom(format!("expected the start of a value, found {token}")),
+            ),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> DeResult<V::Value>
+    where
+        V: VisitorOption<'de>,
+    {
+        if self.peek()? == Token::Nil {
+            self.next()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_instance<V>(self, visitor: V) -> DeResult<V::Value>
+    where
+        V: VisitorInstance<'de>,
+    {
+        if let Token::Instance { len } = self.peek()? {
+            self.next()?;
+            let value = visitor.visit_instance(self.access(len))?;
+            self.end(Token::IvarsEnd)?;
+            Ok(value)
+        } else {
+            visitor.visit(self)
+        }
+    }
+}
+
+impl<'de> InstanceAccess<'de> for Access<'_, 'de> {
+    type IvarAccess = Self;
+
+    fn value_seed<V>(self, seed: V) -> DeResult<(V::Value, Self::IvarAccess)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(&mut *self.deserializer)?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> IvarAccess<'de> for Access<'_, 'de> {
+    fn next_ivar(&mut self) -> DeResult<Option<&'de Sym>> {
+        if self.index >= self.len {
+            return Ok(None);
+        }
+        self.index += 1;
+
+        match self.deserializer.next()? {
+            Token::Ivar(ivar) => Ok(Some(Sym::new(ivar))),
+            token => Err(DeError::custom(format!("expected an ivar, found {token}"))),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> DeResult<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.deserializer)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<'de> HashAccess<'de> for Access<'_, 'de> {
+    fn next_key_seed<K>(&mut self, seed: K) -> DeResult<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.index >= self.len {
+            return Ok(None);
+        }
+        self.index += 1;
+
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> DeResult<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.deserializer)
+    }
+
+    fn next_default_seed<V>(&mut self, seed: V) -> DeResult<Option<V::Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        if !self.default {
+            return Ok(None);
+        }
+        while self.index < self.len {
+            self.index += 1;
+            Ignored::deserialize(&mut *self.deserializer)?;
+            Ignored::deserialize(&mut *self.deserializer)?;
+        }
+
+        self.default = false;
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<'de> ArrayAccess<'de> for Access<'_, 'de> {
+    fn next_element_seed<T>(&mut self, seed: T) -> DeResult<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.index >= self.len {
+            return Ok(None);
+        }
+        self.index += 1;
+
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn index(&self) -> usize {
+        self.index
+    }
+}
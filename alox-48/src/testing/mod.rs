@@ -0,0 +1,211 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Test doubles for checking `Serialize` and `Deserialize` impls.
+//!
+//! Instead of going through bytes, these work with a list of [`Token`]s, one for each call made on the (de)serializer.
+//!
+//! ```
+//! use alox_48::testing::{assert_de_tokens, assert_ser_tokens, Token};
+//!
+//! let tokens = [Token::Array { len: 2 }, Token::I32(1), Token::I32(2), Token::ArrayEnd];
+//!
+//! assert_ser_tokens(&vec![1, 2], &tokens);
+//! assert_de_tokens(&vec![1, 2], &tokens);
+//! ```
+
+#[cfg(feature = "value")]
+use crate::Value;
+use crate::{Deserialize, Serialize};
+
+#[cfg(feature = "conformance")]
+pub mod conformance;
+mod de;
+mod ser;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+
+pub use de::MockDeserializer;
+pub use ser::AssertSerializer;
+
+/// A single call made on a serializer, or a single value a deserializer will produce.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Token<'a> {
+    /// `nil`.
+    Nil,
+    /// A boolean.
+    Bool(bool),
+    /// An integer.
+    I32(i32),
+    /// An integer that might not fit in an `i32`, like a bignum.
+    I64(i64),
+    /// A float.
+    F64(f64),
+    /// A string.
+    ///
+    /// Rust strings are usually (de)serialized as an instance with an encoding, so they look like this:
+    /// `[Instance { len: 1 }, String(b"..."), Ivar("E"), Bool(true), IvarsEnd]`
+    String(&'a [u8]),
+    /// A symbol.
+    Symbol(&'a str),
+    /// A regular expression.
+    Regex {
+        /// The regex source.
+        data: &'a [u8],
+        /// The regex flags.
+        flags: u8,
+    },
+
+    /// The start of an array. Followed by `len` values, and then [`Token::ArrayEnd`].
+    Array {
+        /// The number of elements.
+        len: usize,
+    },
+    /// The end of an array.
+    ArrayEnd,
+    /// The start of a hash. Followed by `len` key-value pairs, and then [`Token::HashEnd`].
+    Hash {
+        /// The number of entries.
+        len: usize,
+    },
+    /// The start of a hash with a default value.
+    /// Followed by `len` key-value pairs, the default value, and then [`Token::HashEnd`].
+    HashWithDefault {
+        /// The number of entries.
+        len: usize,
+    },
+    /// The end of a hash.
+    HashEnd,
+
+    /// The start of an object. Followed by `len` ivars, and then [`Token::IvarsEnd`].
+    Object {
+        /// The class of the object.
+        class: &'a str,
+        /// The number of ivars.
+        len: usize,
+    },
+    /// The start of a struct. Followed by `len` members, and then [`Token::IvarsEnd`].
+    Struct {
+        /// The name of the struct.
+        name: &'a str,
+        /// The number of members.
+        len: usize,
+    },
+    /// The start of an instance. Followed by the value, `len` ivars, and then [`Token::IvarsEnd`].
+    Instance {
+        /// The number of ivars.
+        len: usize,
+    },
+    /// The name of an ivar (or struct member). Followed by its value.
+    Ivar(&'a str),
+    /// The end of an object, struct, or instance.
+    IvarsEnd,
+
+    /// A class.
+    Class(&'a str),
+    /// A module.
+    Module(&'a str),
+    /// An extended value. Followed by the value.
+    Extended {
+        /// The module the value was extended with.
+        module: &'a str,
+    },
+    /// A user class. Followed by the value.
+    UserClass {
+        /// The subclass.
+        class: &'a str,
+    },
+    /// User data.
+    UserData {
+        /// The class of the user data.
+        class: &'a str,
+        /// The data.
+        data: &'a [u8],
+    },
+    /// A user marshal value. Followed by the value.
+    UserMarshal {
+        /// The class of the original object.
+        class: &'a str,
+    },
+    /// A C data value. Followed by the value.
+    Data {
+        /// The class of the value.
+        class: &'a str,
+    },
+}
+
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Assert that `value` serializes to exactly `tokens`.
+///
+/// # Panics
+///
+/// Panics if the tokens don't match, or if serialization fails.
+#[track_caller]
+pub fn assert_ser_tokens<T>(value: &T, tokens: &[Token<'_>])
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = AssertSerializer::new(tokens);
+    value
+        .serialize(&mut serializer)
+        .expect("serialization failed");
+    assert!(
+        serializer.remaining().is_empty(),
+        "serialization finished, but these tokens were not emitted: {:?}",
+        serializer.remaining()
+    );
+}
+
+/// Assert that deserializing `tokens` produces `value`, and that all the tokens were used.
+///
+/// # Panics
+///
+/// Panics if the value doesn't match, or if deserialization fails.
+#[track_caller]
+pub fn assert_de_tokens<'de, T>(value: &T, tokens: &'de [Token<'de>])
+where
+    T: Deserialize<'de> + PartialEq + std::fmt::Debug,
+{
+    let mut deserializer = MockDeserializer::new(tokens);
+    let result = T::deserialize(&mut deserializer).expect("deserialization failed");
+    assert_eq!(&result, value);
+    assert!(
+        deserializer.remaining().is_empty(),
+        "deserialization finished, but these tokens were not used: {:?}",
+        deserializer.remaining()
+    );
+}
+
+/// Assert that two pieces of marshal data hold the same value.
+///
+/// The order of hash entries, instance variables, and struct members is ignored (see [`Value::eq_unordered`]),
+/// so this works for comparing files that were written independently, like by ruby and by alox-48.
+///
+/// ```
+/// use alox_48::testing::assert_marshal_eq;
+///
+/// // {1 => 2, 3 => 4} and {3 => 4, 1 => 2}
+/// assert_marshal_eq(b"\x04\x08{\x07i\x06i\x07i\x08i\x09", b"\x04\x08{\x07i\x08i\x09i\x06i\x07");
+/// ```
+///
+/// # Panics
+///
+/// Panics if either can't be deserialized, or if the values aren't equal.
+#[cfg(feature = "value")]
+#[track_caller]
+pub fn assert_marshal_eq(left: &[u8], right: &[u8]) {
+    let left: Value = crate::from_bytes(left).expect("deserializing the left side failed");
+    let right: Value = crate::from_bytes(right).expect("deserializing the right side failed");
+    assert!(
+        left.eq_unordered(&right),
+        "marshal data differs\n  left: {left:#}\n right: {right:#}"
+    );
+}
@@ -0,0 +1,269 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::Token;
+use crate::{
+    SerError, SerResult, Serialize, SerializeArray, SerializeHash, SerializeIvars, SerializerTrait,
+    Sym,
+};
+
+/// A serializer that checks each call against a list of expected [`Token`]s.
+///
+/// Serialization fails as soon as a call doesn't match the next expected token.
+/// See [`super::assert_ser_tokens`] for a convenient wrapper.
+#[derive(Debug)]
+pub struct AssertSerializer<'a> {
+    tokens: &'a [Token<'a>],
+}
+
+#[derive(Debug)]
+pub struct Compound<'s, 'a> {
+    serializer: &'s mut AssertSerializer<'a>,
+    end: Token<'static>,
+}
+
+impl<'a> AssertSerializer<'a> {
+    /// Create a new serializer that expects `tokens`.
+    pub fn new(tokens: &'a [Token<'a>]) -> Self {
+        Self { tokens }
+    }
+
+    /// Returns the tokens that have not been emitted yet.
+    pub fn remaining(&self) -> &'a [Token<'a>] {
+        self.tokens
+    }
+
+    fn expect(&mut self, token: Token<'_>) -> SerResult<()> {
+        match self.tokens.split_first() {
+            Some((expected, rest)) if *expected == token => {
+                self.tokens = rest;
+                Ok(())
+            }
+            Some((expected, _)) => Err(SerError::custom(format!(
+                "expected token {expected}, but {token} was serialized"
+            ))),
+            None => Err(SerError::custom(format!(
+                "{token} was serialized after the end of the expected tokens"
+            ))),
+        }
+    }
+}
+
+impl<'s, 'a> SerializerTrait for &'s mut AssertSerializer<'a> {
+    type Ok = ();
+
+    type SerializeIvars = Compound<'s, 'a>;
+    type SerializeHash = Compound<'s, 'a>;
+    type SerializeArray = Compound<'s, 'a>;
+
+    fn serialize_nil(self) -> SerResult<Self::Ok> {
+        self.expect(Token::Nil)
+    }
+
+    fn serialize_bool(self, v: bool) -> SerResult<Self::Ok> {
+        self.expect(Token::Bool(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> SerResult<Self::Ok> {
+        self.expect(Token::I32(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> SerResult<Self::Ok> {
+        self.expect(Token::I64(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> SerResult<Self::Ok> {
+        self.expect(Token::F64(v))
+    }
+
+    fn serialize_hash(self, len: usize) -> SerResult<Self::SerializeHash> {
+        self.expect(Token::Hash { len })?;
+        Ok(Compound {
+            serializer: self,
+            end: Token::HashEnd,
+        })
+    }
+
+    fn serialize_hash_with_default(self, len: usize) -> SerResult<Self::SerializeHash> {
+        self.expect(Token::HashWithDefault { len })?;
+        Ok(Compound {
+            serializer: self,
+            end: Token::HashEnd,
+        })
+    }
+
+    fn serialize_array(self, len: usize) -> SerResult<Self::SerializeArray> {
+        self.expect(Token::Array { len })?;
+        Ok(Compound {
+            serializer: self,
+            end: Token::ArrayEnd,
+        })
+    }
+
+    fn serialize_string(self, data: &[u8]) -> SerResult<Self::Ok> {
+        self.expect(Token::String(data))
+    }
+
+    fn serialize_symbol(self, sym: &Sym) -> SerResult<Self::Ok> {
+        self.expect(Token::Symbol(sym.as_str()))
+    }
+
+    fn serialize_regular_expression(self, regex: &[u8], flags: u8) -> SerResult<Self::Ok> {
+        self.expect(Token::Regex { data: regex, flags })
+    }
+
+    fn serialize_object(self, class: &Sym, len: usize) -> SerResult<Self::SerializeIvars> {
+        self.expect(Token::Object {
+            class: class.as_str(),
+            len,
+        })?;
+        Ok(Compound {
+            serializer: self,
+            end: Token::IvarsEnd,
+        })
+    }
+
+    fn serialize_struct(self, name: &Sym, len: usize) -> SerResult<Self::SerializeIvars> {
+        self.expect(Token::Struct {
+            name: name.as_str(),
+            len,
+        })?;
+        Ok(Compound {
+            serializer: self,
+            end: Token::IvarsEnd,
+        })
+    }
+
+    fn serialize_class(self, class: &Sym) -> SerResult<Self::Ok> {
+        self.expect(Token::Class(class.as_str()))
+    }
+
+    fn serialize_module(self, module: &Sym) -> SerResult<Self::Ok> {
+        self.expect(Token::Module(module.as_str()))
+    }
+
+    fn serialize_instance<V>(self, value: &V, len: usize) -> SerResult<Self::SerializeIvars>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.expect(Token::Instance { len })?;
+        value.serialize(&mut *self)?;
+        Ok(Compound {
+            serializer: self,
+            end: Token::IvarsEnd,
+        })
+    }
+
+    fn serialize_extended<V>(self, module: &Sym, value: &V) -> SerResult<Self::Ok>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.expect(Token::Extended {
+            module: module.as_str(),
+        })?;
+        value.serialize(self)
+    }
+
+    fn serialize_user_class<V>(self, class: &Sym, value: &V) -> SerResult<Self::Ok>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.expect(Token::UserClass {
+            class: class.as_str(),
+        })?;
+        value.serialize(self)
+    }
+
+    fn serialize_user_data(self, class: &Sym, data: &[u8]) -> SerResult<Self::Ok> {
+        self.expect(Token::UserData {
+            class: class.as_str(),
+            data,
+        })
+    }
+
+    fn serialize_user_marshal<V>(self, class: &Sym, value: &V) -> SerResult<Self::Ok>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.expect(Token::UserMarshal {
+            class: class.as_str(),
+        })?;
+        value.serialize(self)
+    }
+
+    fn serialize_data<V>(self, class: &Sym, value: &V) -> SerResult<Self::Ok>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.expect(Token::Data {
+            class: class.as_str(),
+        })?;
+        value.serialize(self)
+    }
+}
+
+impl SerializeIvars for Compound<'_, '_> {
+    type Ok = ();
+
+    fn serialize_field(&mut self, k: &Sym) -> SerResult<()> {
+        self.serializer.expect(Token::Ivar(k.as_str()))
+    }
+
+    fn serialize_value<V>(&mut self, v: &V) -> SerResult<()>
+    where
+        V: Serialize + ?Sized,
+    {
+        v.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> SerResult<Self::Ok> {
+        self.serializer.expect(self.end)
+    }
+}
+
+impl SerializeHash for Compound<'_, '_> {
+    type Ok = ();
+
+    fn serialize_key<K>(&mut self, k: &K) -> SerResult<()>
+    where
+        K: Serialize + ?Sized,
+    {
+        k.serialize(&mut *self.serializer)
+    }
+
+    fn serialize_value<V>(&mut self, v: &V) -> SerResult<()>
+    where
+        V: Serialize + ?Sized,
+    {
+        v.serialize(&mut *self.serializer)
+    }
+
+    fn serialize_default<V>(&mut self, v: &V) -> SerResult<()>
+    where
+        V: Serialize + ?Sized,
+    {
+        v.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> SerResult<Self::Ok> {
+        self.serializer.expect(self.end)
+    }
+}
+
+impl SerializeArray for Compound<'_, '_> {
+    type Ok = ();
+
+    fn serialize_element<T>(&mut self, v: &T) -> SerResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        v.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> SerResult<Self::Ok> {
+        self.serializer.expect(self.end)
+    }
+}
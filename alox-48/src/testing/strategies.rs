@@ -0,0 +1,217 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`proptest`] strategies for generating marshal data.
+//!
+//! Symbols are picked from a small set, so generated data is full of symbol links.
+//! [`marshal_data`] also writes object links, and gives you the [`Value`] the data should load as.
+//!
+//! ```
+//! use alox_48::testing::strategies::marshal_data;
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(data in marshal_data())| {
+//!     let value: alox_48::Value = alox_48::from_bytes(&data.bytes).unwrap();
+//!     prop_assert_eq!(value, data.value);
+//! });
+//! ```
+
+use std::ops::Range;
+
+use proptest::{collection::vec, prelude::*, sample::select};
+
+use crate::{
+    ser::Result as SerResult, Instance, Object, RawValue, RbFields, RbHash, RbString, RbStruct,
+    Serialize, SerializeArray, SerializerTrait, Symbol, Userdata, Value,
+};
+
+const SYMBOLS: &[&str] = &[
+    "@id", "@name", "@data", "@x", "@y", "E", "Foo", "Bar", "Baz",
+];
+
+/// Some marshal data, and the value it loads as.
+#[derive(Debug, Clone)]
+pub struct MarshalData {
+    /// The marshal data, including the header.
+    pub bytes: Vec<u8>,
+    /// The value `bytes` loads as, with every object link replaced by what it links to.
+    pub value: Value,
+}
+
+/// Generates symbols.
+pub fn symbol() -> impl Strategy<Value = Symbol> {
+    select(SYMBOLS).prop_map(Symbol::from)
+}
+
+/// Generates values that don't contain other values.
+///
+/// Floats are never `NaN`, so generated values can be compared with `==`.
+pub fn leaf() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Nil),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::Integer),
+        float().prop_map(Value::Float),
+        string().prop_map(Value::String),
+        symbol().prop_map(Value::Symbol),
+    ]
+}
+
+/// Generates value trees of any kind of value.
+///
+/// Floats are never `NaN`, so generated values can be compared with `==`.
+pub fn value() -> impl Strategy<Value = Value> {
+    leaf().prop_recursive(6, 64, 8, |inner| {
+        let boxed = inner.clone().prop_map(Box::new);
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(Value::Array),
+            hash(inner.clone()).prop_map(Value::Hash),
+            (hash(inner.clone()), boxed.clone())
+                .prop_map(|(hash, default)| Value::HashWithDefault { hash, default }),
+            (symbol(), any::<Vec<u8>>())
+                .prop_map(|(class, data)| Value::Userdata(Userdata::new(class, data))),
+            (symbol(), fields(inner.clone(), 0..8))
+                .prop_map(|(class, fields)| Value::Object(Object { class, fields })),
+            // instances without any ivars load as the inner value
+            (boxed.clone(), fields(inner.clone(), 1..8))
+                .prop_map(|(value, fields)| Value::Instance(Instance { value, fields })),
+            (string(), any::<u8>()).prop_map(|(data, flags)| Value::Regex { data, flags }),
+            (symbol(), fields(inner, 0..8))
+                .prop_map(|(class, fields)| Value::RbStruct(RbStruct { class, fields })),
+            symbol().prop_map(Value::Class),
+            symbol().prop_map(Value::Module),
+            (symbol(), boxed.clone()).prop_map(|(module, value)| Value::Extended { module, value }),
+            (symbol(), boxed.clone()).prop_map(|(class, value)| Value::UserClass { class, value }),
+            (symbol(), boxed.clone())
+                .prop_map(|(class, value)| Value::UserMarshal { class, value }),
+            (symbol(), boxed).prop_map(|(class, value)| Value::Data { class, value }),
+        ]
+    })
+}
+
+/// Generates marshal data containing object links.
+///
+/// The data is always an array. It starts with some values that are linked to later in the array,
+/// followed by a mix of links to those values and values from [`value`].
+///
+/// # Panics
+///
+/// Generating data panics if it fails to serialize, which would be a bug in alox-48.
+pub fn marshal_data() -> impl Strategy<Value = MarshalData> {
+    vec(shared(), 1..4)
+        .prop_flat_map(|shared| {
+            let len = shared.len();
+            let element = prop_oneof![
+                value().prop_map(Element::Value),
+                (0..len).prop_map(Element::Link),
+            ];
+            (Just(shared), vec(element, 0..8))
+        })
+        .prop_map(|(shared, elements)| {
+            let stream = Stream {
+                shared: &shared,
+                elements: &elements,
+            };
+            let bytes = crate::to_bytes(&stream).expect("generated values should serialize");
+
+            let array = shared
+                .iter()
+                .cloned()
+                .chain(elements.into_iter().map(|element| match element {
+                    Element::Value(value) => value,
+                    Element::Link(index) => shared[index].clone(),
+                }))
+                .collect();
+            MarshalData {
+                bytes,
+                value: Value::Array(array),
+            }
+        })
+}
+
+fn float() -> impl Strategy<Value = f64> {
+    use proptest::num::f64::{INFINITE, NEGATIVE, NORMAL, POSITIVE, SUBNORMAL, ZERO};
+    POSITIVE | NEGATIVE | NORMAL | SUBNORMAL | ZERO | INFINITE
+}
+
+fn string() -> impl Strategy<Value = RbString> {
+    any::<Vec<u8>>().prop_map(RbString::from)
+}
+
+fn hash(inner: impl Strategy<Value = Value>) -> impl Strategy<Value = RbHash> {
+    vec((leaf(), inner), 0..8).prop_map(|entries| entries.into_iter().collect())
+}
+
+fn fields(
+    inner: impl Strategy<Value = Value>,
+    len: Range<usize>,
+) -> impl Strategy<Value = RbFields> {
+    vec((symbol(), inner), len).prop_map(|fields| fields.into_iter().collect())
+}
+
+// Values that add exactly one entry to the object table, so we know where they are in it.
+fn shared() -> impl Strategy<Value = Value> {
+    // bignums are objects too, so integers are kept to fixnums (which fit in 31 bits)
+    let scalar = prop_oneof![
+        Just(Value::Nil),
+        any::<bool>().prop_map(Value::Bool),
+        (-(1_i64 << 30)..(1 << 30)).prop_map(Value::Integer),
+        symbol().prop_map(Value::Symbol),
+    ];
+    prop_oneof![
+        float().prop_map(Value::Float),
+        string().prop_map(Value::String),
+        vec(scalar.clone(), 0..8).prop_map(Value::Array),
+        (symbol(), fields(scalar, 0..8))
+            .prop_map(|(class, fields)| Value::Object(Object { class, fields })),
+    ]
+}
+
+#[derive(Debug, Clone)]
+enum Element {
+    Value(Value),
+    // an index into the shared values
+    Link(usize),
+}
+
+struct Stream<'a> {
+    shared: &'a [Value],
+    elements: &'a [Element],
+}
+
+impl Serialize for Stream<'_> {
+    fn serialize<S>(&self, serializer: S) -> SerResult<S::Ok>
+    where
+        S: SerializerTrait,
+    {
+        let mut array = serializer.serialize_array(self.shared.len() + self.elements.len())?;
+        for value in self.shared {
+            array.serialize_element(value)?;
+        }
+        for element in self.elements {
+            match element {
+                Element::Value(value) => array.serialize_element(value)?,
+                Element::Link(index) => {
+                    // the array itself is object 0, and each shared value is one object
+                    let bytes = object_link(index + 1);
+                    array.serialize_element(&RawValue {
+                        bytes: &bytes,
+                        symbols: vec![],
+                    })?;
+                }
+            }
+        }
+        array.end()
+    }
+}
+
+fn object_link(index: usize) -> Vec<u8> {
+    let index = i32::try_from(index).expect("object index should fit in an i32");
+    // reuse the serializer's integer encoding, skipping the header and the integer tag
+    let mut bytes = crate::to_bytes(index).expect("integers should serialize");
+    bytes.splice(..3, [b'@']);
+    bytes
+}
@@ -0,0 +1,752 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A [`Value`] stored in a few flat arrays instead of a tree of boxes.
+//!
+//! Every value in a [`Document`] is a node in one `Vec`, and the elements, entries, and instance variables of nodes
+//! are stored next to each other in a few more. Nodes refer to each other by [`NodeId`].
+//! Loading, walking, and dropping a huge document touches much less memory than the equivalent [`Value`].
+//!
+//! Documents are read-only. Walk them with [`NodeRef`], or convert part of one back with [`NodeRef::to_value`].
+//!
+//! ```
+//! use alox_48::{arena::Document, marshal};
+//!
+//! let bytes = alox_48::to_bytes(marshal!(RPG::Map {
+//!     @events => { 1 => RPG::Event { @name => "EV001", @pages => [] } },
+//! }))
+//! .unwrap();
+//!
+//! let document: Document = alox_48::from_bytes(&bytes).unwrap();
+//! let map = document.root();
+//! assert_eq!(map.class().unwrap(), "RPG::Map");
+//!
+//! let (id, event) = map.ivar("@events").unwrap().entries().next().unwrap();
+//! assert_eq!(id.as_i32(), Some(1));
+//! assert_eq!(event.ivar("@name").unwrap().as_str(), Some("EV001"));
+//! ```
+
+use super::de::Interner;
+use crate::{
+    de::{cautious_capacity, expect::ValueKind, DeserializeSeed, Result},
+    ArrayAccess, Deserialize, DeserializerTrait, HashAccess, Instance, InstanceAccess, IvarAccess,
+    Object, RbFields, RbHash, RbString, Sym, Symbol, Userdata, Value, Visitor,
+};
+
+/// A value stored as flat arrays of nodes.
+///
+/// See the [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    nodes: Vec<Node>,
+    // the elements of arrays
+    children: Vec<NodeId>,
+    // the instance variables of objects, structs, and instances
+    fields: Vec<(Symbol, NodeId)>,
+    // the entries of hashes
+    entries: Vec<(NodeId, NodeId)>,
+    // the contents of strings, regexes, and user data
+    bytes: Vec<u8>,
+    root: NodeId,
+}
+
+/// The index of a node in a [`Document`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(u32);
+
+/// A node of a [`Document`], along with the document it's in.
+#[derive(Clone, Copy, Debug)]
+pub struct NodeRef<'a> {
+    document: &'a Document,
+    id: NodeId,
+}
+
+// a range of one of the document's arrays
+#[derive(Clone, Copy, Debug, Default)]
+struct Span {
+    start: u32,
+    len: u32,
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Nil,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(Span),
+    Symbol(Symbol),
+    Regex {
+        data: Span,
+        flags: u8,
+    },
+    Array(Span),
+    Hash {
+        entries: Span,
+        default: Option<NodeId>,
+    },
+    Object {
+        class: Symbol,
+        fields: Span,
+    },
+    Struct {
+        class: Symbol,
+        fields: Span,
+    },
+    Instance {
+        value: NodeId,
+        fields: Span,
+    },
+    Class(Symbol),
+    Module(Symbol),
+    Extended {
+        module: Symbol,
+        value: NodeId,
+    },
+    UserClass {
+        class: Symbol,
+        value: NodeId,
+    },
+    UserData {
+        class: Symbol,
+        data: Span,
+    },
+    UserMarshal {
+        class: Symbol,
+        value: NodeId,
+    },
+    Data {
+        class: Symbol,
+        value: NodeId,
+    },
+}
+
+impl Span {
+    fn range(self) -> std::ops::Range<usize> {
+        self.start as usize..(self.start + self.len) as usize
+    }
+}
+
+impl NodeId {
+    /// Returns the index of this node.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Document {
+    /// Returns the outermost value of this document.
+    pub fn root(&self) -> NodeRef<'_> {
+        self.node(self.root)
+    }
+
+    /// Returns a node of this document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not from this document.
+    pub fn node(&self, id: NodeId) -> NodeRef<'_> {
+        assert!(
+            id.index() < self.nodes.len(),
+            "node is not in this document"
+        );
+        NodeRef { document: self, id }
+    }
+
+    /// Returns how many values are in this document, including nested ones.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if this document has no values.
+    ///
+    /// Only [`Document::default`] is empty, deserialized documents always have a root.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn push(&mut self, node: Node) -> NodeId {
+        self.nodes.push(node);
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8]) -> Span {
+        let start = self.bytes.len() as u32;
+        self.bytes.extend_from_slice(bytes);
+        Span {
+            start,
+            len: bytes.len() as u32,
+        }
+    }
+
+    fn push_children(&mut self, children: Vec<NodeId>) -> Span {
+        let start = self.children.len() as u32;
+        let len = children.len() as u32;
+        self.children.extend(children);
+        Span { start, len }
+    }
+
+    fn push_fields(&mut self, fields: Vec<(Symbol, NodeId)>) -> Span {
+        let start = self.fields.len() as u32;
+        let len = fields.len() as u32;
+        self.fields.extend(fields);
+        Span { start, len }
+    }
+
+    fn push_entries(&mut self, entries: Vec<(NodeId, NodeId)>) -> Span {
+        let start = self.entries.len() as u32;
+        let len = entries.len() as u32;
+        self.entries.extend(entries);
+        Span { start, len }
+    }
+}
+
+impl<'a> NodeRef<'a> {
+    /// Returns the id of this node.
+    pub fn id(self) -> NodeId {
+        self.id
+    }
+
+    /// Returns the document this node is in.
+    pub fn document(self) -> &'a Document {
+        self.document
+    }
+
+    fn node(self) -> &'a Node {
+        &self.document.nodes[self.id.index()]
+    }
+
+    fn with(self, id: NodeId) -> Self {
+        Self {
+            document: self.document,
+            id,
+        }
+    }
+
+    // looks through the same wrappers as `ValueKind`
+    fn unwrapped(self) -> Self {
+        match self.node() {
+            Node::Instance { value, .. }
+            | Node::Extended { value, .. }
+            | Node::UserClass { value, .. } => self.with(*value).unwrapped(),
+            _ => self,
+        }
+    }
+
+    fn bytes(self, span: Span) -> &'a [u8] {
+        &self.document.bytes[span.range()]
+    }
+
+    /// Returns what kind of value this is.
+    ///
+    /// Instance variables, extended modules, and user classes are looked through, like the `as_*` methods do.
+    pub fn kind(self) -> ValueKind {
+        match self.unwrapped().node() {
+            Node::Nil => ValueKind::Nil,
+            Node::Bool(_) => ValueKind::Bool,
+            Node::Integer(_) => ValueKind::Integer,
+            Node::Float(_) => ValueKind::Float,
+            Node::String(_) => ValueKind::String,
+            Node::Symbol(_) => ValueKind::Symbol,
+            Node::Regex { .. } => ValueKind::Regex,
+            Node::Array(_) => ValueKind::Array,
+            Node::Hash { .. } => ValueKind::Hash,
+            Node::Object { .. } => ValueKind::Object,
+            Node::Struct { .. } => ValueKind::Struct,
+            Node::Class(_) => ValueKind::Class,
+            Node::Module(_) => ValueKind::Module,
+            Node::UserData { .. } => ValueKind::UserData,
+            Node::UserMarshal { .. } => ValueKind::UserMarshal,
+            Node::Data { .. } => ValueKind::Data,
+            // looked through by unwrapped
+            Node::Instance { .. } | Node::Extended { .. } | Node::UserClass { .. } => {
+                unreachable!()
+            }
+        }
+    }
+
+    /// Returns true if this is `nil`.
+    pub fn is_nil(self) -> bool {
+        matches!(self.unwrapped().node(), Node::Nil)
+    }
+
+    /// Returns this value if it's a bool.
+    pub fn as_bool(self) -> Option<bool> {
+        match self.unwrapped().node() {
+            Node::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns this value if it's an integer that fits in an `i32`.
+    pub fn as_i32(self) -> Option<i32> {
+        self.as_i64().and_then(|v| i32::try_from(v).ok())
+    }
+
+    /// Returns this value if it's an integer.
+    pub fn as_i64(self) -> Option<i64> {
+        match self.unwrapped().node() {
+            Node::Integer(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns this value if it's a float.
+    pub fn as_f64(self) -> Option<f64> {
+        match self.unwrapped().node() {
+            Node::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of this string or regex.
+    pub fn as_bytes(self) -> Option<&'a [u8]> {
+        match self.unwrapped().node() {
+            Node::String(span) | Node::Regex { data: span, .. } => Some(self.bytes(*span)),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of this string, if it's valid UTF-8.
+    pub fn as_str(self) -> Option<&'a str> {
+        self.as_bytes()
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    /// Returns this value if it's a symbol.
+    pub fn as_symbol(self) -> Option<&'a Symbol> {
+        match self.unwrapped().node() {
+            Node::Symbol(symbol) => Some(symbol),
+            _ => None,
+        }
+    }
+
+    /// Returns the class of this object, struct, user data, user marshal, or c data object,
+    /// or the name of this class or module.
+    pub fn class(self) -> Option<&'a Symbol> {
+        match self.unwrapped().node() {
+            Node::Object { class, .. }
+            | Node::Struct { class, .. }
+            | Node::UserData { class, .. }
+            | Node::UserMarshal { class, .. }
+            | Node::Data { class, .. }
+            | Node::Class(class)
+            | Node::Module(class) => Some(class),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of elements, entries, or instance variables of this value.
+    ///
+    /// Returns 0 for values without any.
+    pub fn len(self) -> usize {
+        match self.unwrapped().node() {
+            Node::Array(span)
+            | Node::Hash { entries: span, .. }
+            | Node::Object { fields: span, .. }
+            | Node::Struct { fields: span, .. } => span.len as usize,
+            _ => 0,
+        }
+    }
+
+    /// Returns true if this value has no elements, entries, or instance variables.
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an element of this array.
+    pub fn get(self, index: usize) -> Option<NodeRef<'a>> {
+        self.elements().nth(index)
+    }
+
+    /// Returns the elements of this array, or nothing if it isn't one.
+    pub fn elements(self) -> impl ExactSizeIterator<Item = NodeRef<'a>> {
+        let span = match self.unwrapped().node() {
+            Node::Array(span) => *span,
+            _ => Span::default(),
+        };
+        self.document.children[span.range()]
+            .iter()
+            .map(move |&id| self.with(id))
+    }
+
+    /// Returns the entries of this hash, or nothing if it isn't one.
+    pub fn entries(self) -> impl ExactSizeIterator<Item = (NodeRef<'a>, NodeRef<'a>)> {
+        let span = match self.unwrapped().node() {
+            Node::Hash { entries, .. } => *entries,
+            _ => Span::default(),
+        };
+        self.document.entries[span.range()]
+            .iter()
+            .map(move |&(k, v)| (self.with(k), self.with(v)))
+    }
+
+    /// Returns the default value of this hash, if it has one.
+    pub fn hash_default(self) -> Option<NodeRef<'a>> {
+        match self.unwrapped().node() {
+            Node::Hash { default, .. } => default.map(|id| self.with(id)),
+            _ => None,
+        }
+    }
+
+    /// Returns the instance variables of this object or struct, or nothing if it isn't one.
+    ///
+    /// The extra instance variables of an instance (like the encoding of a string) are not included,
+    /// see [`NodeRef::instance_fields`].
+    pub fn fields(self) -> impl ExactSizeIterator<Item = (&'a Symbol, NodeRef<'a>)> {
+        let span = match self.unwrapped().node() {
+            Node::Object { fields, .. } | Node::Struct { fields, .. } => *fields,
+            _ => Span::default(),
+        };
+        self.fields_in(span)
+    }
+
+    /// Returns the extra instance variables of this instance, or nothing if it isn't one.
+    pub fn instance_fields(self) -> impl ExactSizeIterator<Item = (&'a Symbol, NodeRef<'a>)> {
+        let span = match self.node() {
+            Node::Instance { fields, .. } => *fields,
+            _ => Span::default(),
+        };
+        self.fields_in(span)
+    }
+
+    fn fields_in(self, span: Span) -> impl ExactSizeIterator<Item = (&'a Symbol, NodeRef<'a>)> {
+        self.document.fields[span.range()]
+            .iter()
+            .map(move |(name, id)| (name, self.with(*id)))
+    }
+
+    /// Returns an instance variable of this object, or a member of this struct.
+    ///
+    /// Object instance variables start with `@`, struct members don't.
+    pub fn ivar(self, name: &str) -> Option<NodeRef<'a>> {
+        self.fields()
+            .find(|(field, _)| field.as_str() == name)
+            .map(|(_, node)| node)
+    }
+
+    /// Returns the value wrapped by this instance, extended value, user class, user marshal, or c data object.
+    pub fn inner(self) -> Option<NodeRef<'a>> {
+        match self.node() {
+            Node::Instance { value, .. }
+            | Node::Extended { value, .. }
+            | Node::UserClass { value, .. }
+            | Node::UserMarshal { value, .. }
+            | Node::Data { value, .. } => Some(self.with(*value)),
+            _ => None,
+        }
+    }
+
+    /// Copy this node and everything in it into a [`Value`].
+    pub fn to_value(self) -> Value {
+        let fields = |span: Span| -> RbFields {
+            self.fields_in(span)
+                .map(|(name, node)| (name.clone(), node.to_value()))
+                .collect()
+        };
+        let boxed = |id: NodeId| Box::new(self.with(id).to_value());
+
+        match self.node() {
+            Node::Nil => Value::Nil,
+            Node::Bool(v) => Value::Bool(*v),
+            Node::Integer(v) => Value::Integer(*v),
+            Node::Float(v) => Value::Float(*v),
+            Node::String(span) => Value::String(RbString::from(self.bytes(*span))),
+            Node::Symbol(symbol) => Value::Symbol(symbol.clone()),
+            Node::Regex { data, flags } => Value::Regex {
+                data: RbString::from(self.bytes(*data)),
+                flags: *flags,
+            },
+            Node::Array(_) => Value::Array(self.elements().map(NodeRef::to_value).collect()),
+            Node::Hash { default, .. } => {
+                let hash: RbHash = self
+                    .entries()
+                    .map(|(k, v)| (k.to_value(), v.to_value()))
+                    .collect();
+                match default {
+                    Some(default) => Value::HashWithDefault {
+                        hash,
+                        default: boxed(*default),
+                    },
+                    None => Value::Hash(hash),
+                }
+            }
+            Node::Object {
+                class,
+                fields: span,
+            } => Value::Object(Object {
+                class: class.clone(),
+                fields: fields(*span),
+            }),
+            Node::Struct {
+                class,
+                fields: span,
+            } => Value::RbStruct(crate::RbStruct {
+                class: class.clone(),
+                fields: fields(*span),
+            }),
+            Node::Instance {
+                value,
+                fields: span,
+            } => Value::Instance(Instance {
+                value: boxed(*value),
+                fields: fields(*span),
+            }),
+            Node::Class(class) => Value::Class(class.clone()),
+            Node::Module(module) => Value::Module(module.clone()),
+            Node::Extended { module, value } => Value::Extended {
+                module: module.clone(),
+                value: boxed(*value),
+            },
+            Node::UserClass { class, value } => Value::UserClass {
+                class: class.clone(),
+                value: boxed(*value),
+            },
+            Node::UserData { class, data } => {
+                Value::Userdata(Userdata::new(class.clone(), self.bytes(*data)))
+            }
+            Node::UserMarshal { class, value } => Value::UserMarshal {
+                class: class.clone(),
+                value: boxed(*value),
+            },
+            Node::Data { class, value } => Value::Data {
+                class: class.clone(),
+                value: boxed(*value),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Document {
+    fn deserialize<D>(deserializer: D) -> Result<Self>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let mut document = Document::default();
+        let mut interner = Interner::default();
+        document.root = NodeSeed {
+            document: &mut document,
+            interner: &mut interner,
+        }
+        .deserialize(deserializer)?;
+        Ok(document)
+    }
+}
+
+struct NodeSeed<'a, 'de> {
+    document: &'a mut Document,
+    interner: &'a mut Interner<'de>,
+}
+
+impl<'de> NodeSeed<'_, 'de> {
+    fn reborrow(&mut self) -> NodeSeed<'_, 'de> {
+        NodeSeed {
+            document: self.document,
+            interner: self.interner,
+        }
+    }
+
+    fn push(self, node: Node) -> NodeId {
+        self.document.push(node)
+    }
+
+    fn visit_fields<A>(&mut self, mut access: A) -> Result<Span>
+    where
+        A: IvarAccess<'de>,
+    {
+        let mut fields = Vec::with_capacity(cautious_capacity::<(Symbol, NodeId)>(access.len()));
+        while let Some(field) = access.next_ivar()? {
+            let field = self.interner.intern(field);
+            let value = access.next_value_seed(self.reborrow())?;
+            fields.push((field, value));
+        }
+        Ok(self.document.push_fields(fields))
+    }
+
+    fn visit_entries<A>(&mut self, access: &mut A) -> Result<Span>
+    where
+        A: HashAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(cautious_capacity::<(NodeId, NodeId)>(access.len()));
+        while let Some(k) = access.next_key_seed(self.reborrow())? {
+            let v = access.next_value_seed(self.reborrow())?;
+            entries.push((k, v));
+        }
+        Ok(self.document.push_entries(entries))
+    }
+
+    fn visit_wrapped<D>(mut self, deserializer: D) -> Result<NodeId>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        self.reborrow().deserialize(deserializer)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_, 'de> {
+    type Value = NodeId;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodeSeed<'_, 'de> {
+    type Value = NodeId;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("any ruby value")
+    }
+
+    fn visit_nil(self) -> Result<Self::Value> {
+        Ok(self.push(Node::Nil))
+    }
+
+    fn visit_bool(self, v: bool) -> Result<Self::Value> {
+        Ok(self.push(Node::Bool(v)))
+    }
+
+    fn visit_i32(self, v: i32) -> Result<Self::Value> {
+        Ok(self.push(Node::Integer(v.into())))
+    }
+
+    fn visit_i64(self, v: i64) -> Result<Self::Value> {
+        Ok(self.push(Node::Integer(v)))
+    }
+
+    fn visit_f64(self, v: f64) -> Result<Self::Value> {
+        Ok(self.push(Node::Float(v)))
+    }
+
+    fn visit_hash<A>(mut self, mut map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        let entries = self.visit_entries(&mut map)?;
+        Ok(self.push(Node::Hash {
+            entries,
+            default: None,
+        }))
+    }
+
+    fn visit_hash_with_default<A>(mut self, mut map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        let entries = self.visit_entries(&mut map)?;
+        let default = map.next_default_seed(self.reborrow())?;
+        Ok(self.push(Node::Hash { entries, default }))
+    }
+
+    fn visit_array<A>(mut self, mut access: A) -> Result<Self::Value>
+    where
+        A: ArrayAccess<'de>,
+    {
+        let mut children = Vec::with_capacity(cautious_capacity::<NodeId>(access.len()));
+        while let Some(id) = access.next_element_seed(self.reborrow())? {
+            children.push(id);
+        }
+        let span = self.document.push_children(children);
+        Ok(self.push(Node::Array(span)))
+    }
+
+    fn visit_string(self, string: &'de [u8]) -> Result<Self::Value> {
+        let span = self.document.push_bytes(string);
+        Ok(self.push(Node::String(span)))
+    }
+
+    fn visit_symbol(self, symbol: &'de Sym) -> Result<Self::Value> {
+        let symbol = self.interner.intern(symbol);
+        Ok(self.push(Node::Symbol(symbol)))
+    }
+
+    fn visit_regular_expression(self, regex: &'de [u8], flags: u8) -> Result<Self::Value> {
+        let data = self.document.push_bytes(regex);
+        Ok(self.push(Node::Regex { data, flags }))
+    }
+
+    fn visit_object<A>(mut self, class: &'de Sym, instance_variables: A) -> Result<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        let class = self.interner.intern(class);
+        let fields = self.visit_fields(instance_variables)?;
+        Ok(self.push(Node::Object { class, fields }))
+    }
+
+    fn visit_struct<A>(mut self, name: &'de Sym, members: A) -> Result<Self::Value>
+    where
+        A: IvarAccess<'de>,
+    {
+        let class = self.interner.intern(name);
+        let fields = self.visit_fields(members)?;
+        Ok(self.push(Node::Struct { class, fields }))
+    }
+
+    fn visit_class(self, class: &'de Sym) -> Result<Self::Value> {
+        let class = self.interner.intern(class);
+        Ok(self.push(Node::Class(class)))
+    }
+
+    fn visit_module(self, module: &'de Sym) -> Result<Self::Value> {
+        let module = self.interner.intern(module);
+        Ok(self.push(Node::Module(module)))
+    }
+
+    fn visit_instance<A>(mut self, instance: A) -> Result<Self::Value>
+    where
+        A: InstanceAccess<'de>,
+    {
+        let (value, instance_fields) = instance.value_seed(self.reborrow())?;
+        let fields = self.visit_fields(instance_fields)?;
+        Ok(self.push(Node::Instance { value, fields }))
+    }
+
+    fn visit_extended<D>(mut self, module: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let module = self.interner.intern(module);
+        let value = self.reborrow().visit_wrapped(deserializer)?;
+        Ok(self.push(Node::Extended { module, value }))
+    }
+
+    fn visit_user_class<D>(mut self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let class = self.interner.intern(class);
+        let value = self.reborrow().visit_wrapped(deserializer)?;
+        Ok(self.push(Node::UserClass { class, value }))
+    }
+
+    fn visit_user_data(self, class: &'de Sym, data: &'de [u8]) -> Result<Self::Value> {
+        let class = self.interner.intern(class);
+        let data = self.document.push_bytes(data);
+        Ok(self.push(Node::UserData { class, data }))
+    }
+
+    fn visit_user_marshal<D>(mut self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let class = self.interner.intern(class);
+        let value = self.reborrow().visit_wrapped(deserializer)?;
+        Ok(self.push(Node::UserMarshal { class, value }))
+    }
+
+    fn visit_data<D>(mut self, class: &'de Sym, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        let class = self.interner.intern(class);
+        let value = self.reborrow().visit_wrapped(deserializer)?;
+        Ok(self.push(Node::Data { class, value }))
+    }
+}
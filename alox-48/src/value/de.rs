@@ -3,16 +3,114 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+
 use crate::{
-    de::{DeserializeSeed, Error, Kind, Result},
-    ArrayAccess, Deserialize, DeserializerTrait, HashAccess, Instance, InstanceAccess, IvarAccess,
-    Object, RbFields, RbHash, RbString, Sym, Userdata, Value, Visitor, VisitorInstance,
-    VisitorOption,
+    de::{cautious_capacity, DeserializeSeed, Error, Kind, Primitive, Result, DEFAULT_DEPTH_LIMIT},
+    ArrayAccess, ByteBuf, Deserialize, DeserializerTrait, HashAccess, Instance, InstanceAccess,
+    IvarAccess, Object, RbFields, RbHash, RbString, Sym, Symbol, Userdata, Value, Visitor,
+    VisitorInstance, VisitorOption,
 };
 
-struct ValueVisitor;
+// Symbols already read, so repeated class and ivar names share one string.
+#[derive(Default)]
+pub(super) struct Interner<'de> {
+    symbols: HashMap<&'de Sym, Symbol>,
+    // the input, when it's a buffer strings and user data can share instead of copying
+    #[cfg(feature = "bytes")]
+    source: Option<bytes::Bytes>,
+}
+
+impl<'de> Interner<'de> {
+    pub(super) fn intern(&mut self, sym: &'de Sym) -> Symbol {
+        self.symbols
+            .entry(sym)
+            .or_insert_with(|| sym.to_symbol())
+            .clone()
+    }
+
+    #[cfg(not(feature = "bytes"))]
+    #[allow(clippy::unused_self)]
+    fn bytes(&self, data: &[u8]) -> ByteBuf {
+        data.to_vec()
+    }
+
+    // shares `data` with the source if it's part of it, and copies it otherwise
+    #[cfg(feature = "bytes")]
+    fn bytes(&self, data: &[u8]) -> ByteBuf {
+        match &self.source {
+            Some(source) if contains(source.as_ptr_range(), data.as_ptr_range()) => {
+                source.slice_ref(data)
+            }
+            _ => bytes::Bytes::copy_from_slice(data),
+        }
+    }
+}
+
+#[cfg(feature = "bytes")]
+fn contains(outer: std::ops::Range<*const u8>, inner: std::ops::Range<*const u8>) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+/// Deserialize a [`Value`], sharing `source` for its strings and user data.
+///
+/// `deserializer` must be reading from `source`.
+#[cfg(feature = "bytes")]
+pub(crate) fn deserialize_shared<'de, D>(deserializer: D, source: bytes::Bytes) -> Result<Value>
+where
+    D: DeserializerTrait<'de>,
+{
+    let mut interner = Interner {
+        symbols: HashMap::new(),
+        source: Some(source),
+    };
+    deserializer.deserialize(ValueVisitor(&mut interner))
+}
+
+struct ValueSeed<'a, 'de>(&'a mut Interner<'de>);
+
+struct ValueVisitor<'a, 'de>(&'a mut Interner<'de>);
+
+impl<'de> DeserializeSeed<'de> for ValueSeed<'_, 'de> {
+    type Value = Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value>
+    where
+        D: DeserializerTrait<'de>,
+    {
+        deserializer.deserialize(ValueVisitor(self.0))
+    }
+}
+
+impl<'de> ValueVisitor<'_, 'de> {
+    fn visit_fields<A>(&mut self, mut access: A) -> Result<RbFields>
+    where
+        A: IvarAccess<'de>,
+    {
+        let mut fields =
+            RbFields::with_capacity(cautious_capacity::<(Symbol, Value)>(access.len()));
+        while let Some(field) = access.next_ivar()? {
+            let field = self.0.intern(field);
+            let value = access.next_value_seed(ValueSeed(self.0))?;
+            fields.insert(field, value);
+        }
+        Ok(fields)
+    }
+
+    fn visit_entries<A>(&mut self, access: &mut A) -> Result<RbHash>
+    where
+        A: HashAccess<'de>,
+    {
+        let mut hash = RbHash::with_capacity(cautious_capacity::<(Value, Value)>(access.len()));
+        while let Some(k) = access.next_key_seed(ValueSeed(self.0))? {
+            let v = access.next_value_seed(ValueSeed(self.0))?;
+            hash.insert(k, v);
+        }
+        Ok(hash)
+    }
+}
 
-impl<'de> Visitor<'de> for ValueVisitor {
+impl<'de> Visitor<'de> for ValueVisitor<'_, 'de> {
     type Value = Value;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -28,6 +126,10 @@ impl<'de> Visitor<'de> for ValueVisitor {
     }
 
     fn visit_i32(self, v: i32) -> Result<Self::Value> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn visit_i64(self, v: i64) -> Result<Self::Value> {
         Ok(Value::Integer(v))
     }
 
@@ -35,23 +137,34 @@ impl<'de> Visitor<'de> for ValueVisitor {
         Ok(Value::Float(v))
     }
 
-    fn visit_hash<A>(self, mut map: A) -> Result<Self::Value>
+    fn visit_hash<A>(mut self, mut map: A) -> Result<Self::Value>
     where
         A: HashAccess<'de>,
     {
-        let mut hash = RbHash::with_capacity(map.len());
-        while let Some((k, v)) = map.next_entry()? {
-            hash.insert(k, v);
-        }
+        let hash = self.visit_entries(&mut map)?;
         Ok(Value::Hash(hash))
     }
 
+    fn visit_hash_with_default<A>(mut self, mut map: A) -> Result<Self::Value>
+    where
+        A: HashAccess<'de>,
+    {
+        let hash = self.visit_entries(&mut map)?;
+        let default = map
+            .next_default_seed(ValueSeed(self.0))?
+            .unwrap_or_default();
+        Ok(Value::HashWithDefault {
+            hash,
+            default: Box::new(default),
+        })
+    }
+
     fn visit_array<A>(self, mut access: A) -> Result<Self::Value>
     where
         A: ArrayAccess<'de>,
     {
-        let mut array = Vec::with_capacity(access.len());
-        while let Some(v) = access.next_element()? {
+        let mut array = Vec::with_capacity(cautious_capacity::<Value>(access.len()));
+        while let Some(v) = access.next_element_seed(ValueSeed(self.0))? {
             array.push(v);
         }
         Ok(Value::Array(array))
@@ -59,66 +172,59 @@ impl<'de> Visitor<'de> for ValueVisitor {
 
     fn visit_string(self, string: &'de [u8]) -> Result<Self::Value> {
         Ok(Value::String(RbString {
-            data: string.to_vec(),
+            data: self.0.bytes(string),
         }))
     }
 
     fn visit_symbol(self, symbol: &'de Sym) -> Result<Self::Value> {
-        Ok(Value::Symbol(symbol.to_symbol()))
+        Ok(Value::Symbol(self.0.intern(symbol)))
     }
 
     fn visit_regular_expression(self, data: &'de [u8], flags: u8) -> Result<Self::Value> {
         Ok(Value::Regex {
-            data: RbString::from(data),
+            data: RbString {
+                data: self.0.bytes(data),
+            },
             flags,
         })
     }
 
-    fn visit_object<A>(self, class: &'de Sym, mut instance_variables: A) -> Result<Self::Value>
+    fn visit_object<A>(mut self, class: &'de Sym, instance_variables: A) -> Result<Self::Value>
     where
         A: IvarAccess<'de>,
     {
-        let mut fields = RbFields::with_capacity(instance_variables.len());
-        while let Some((k, v)) = instance_variables.next_entry()? {
-            fields.insert(k.to_symbol(), v);
-        }
+        let fields = self.visit_fields(instance_variables)?;
         Ok(Value::Object(Object {
-            class: class.to_symbol(),
+            class: self.0.intern(class),
             fields,
         }))
     }
 
-    fn visit_struct<A>(self, name: &'de Sym, mut members: A) -> Result<Self::Value>
+    fn visit_struct<A>(mut self, name: &'de Sym, members: A) -> Result<Self::Value>
     where
         A: IvarAccess<'de>,
     {
-        let mut fields = RbFields::with_capacity(members.len());
-        while let Some((k, v)) = members.next_entry()? {
-            fields.insert(k.to_symbol(), v);
-        }
+        let fields = self.visit_fields(members)?;
         Ok(Value::RbStruct(crate::RbStruct {
-            class: name.to_symbol(),
+            class: self.0.intern(name),
             fields,
         }))
     }
 
     fn visit_class(self, class: &'de Sym) -> Result<Self::Value> {
-        Ok(Value::Class(class.to_symbol()))
+        Ok(Value::Class(self.0.intern(class)))
     }
 
     fn visit_module(self, module: &'de Sym) -> Result<Self::Value> {
-        Ok(Value::Module(module.to_symbol()))
+        Ok(Value::Module(self.0.intern(module)))
     }
 
-    fn visit_instance<A>(self, instance: A) -> Result<Self::Value>
+    fn visit_instance<A>(mut self, instance: A) -> Result<Self::Value>
     where
         A: InstanceAccess<'de>,
     {
-        let (value, mut instance_fields) = instance.value()?;
-        let mut fields = RbFields::with_capacity(instance_fields.len());
-        while let Some((field, value)) = instance_fields.next_entry()? {
-            fields.insert(field.to_symbol(), value);
-        }
+        let (value, instance_fields) = instance.value_seed(ValueSeed(self.0))?;
+        let fields = self.visit_fields(instance_fields)?;
         let instance = Instance {
             value: Box::new(value),
             fields,
@@ -131,9 +237,10 @@ impl<'de> Visitor<'de> for ValueVisitor {
     where
         D: DeserializerTrait<'de>,
     {
-        let value = deserializer.deserialize(ValueVisitor)?;
+        let module = self.0.intern(module);
+        let value = deserializer.deserialize(ValueVisitor(self.0))?;
         Ok(Value::Extended {
-            module: module.to_symbol(),
+            module,
             value: Box::new(value),
         })
     }
@@ -142,17 +249,18 @@ impl<'de> Visitor<'de> for ValueVisitor {
     where
         D: DeserializerTrait<'de>,
     {
-        let value = deserializer.deserialize(ValueVisitor)?;
+        let class = self.0.intern(class);
+        let value = deserializer.deserialize(ValueVisitor(self.0))?;
         Ok(Value::UserClass {
-            class: class.to_symbol(),
+            class,
             value: Box::new(value),
         })
     }
 
     fn visit_user_data(self, class: &'de Sym, data: &'de [u8]) -> Result<Self::Value> {
         Ok(Value::Userdata(Userdata {
-            class: class.to_symbol(),
-            data: data.to_vec(),
+            class: self.0.intern(class),
+            data: self.0.bytes(data),
         }))
     }
 
@@ -160,9 +268,10 @@ impl<'de> Visitor<'de> for ValueVisitor {
     where
         D: DeserializerTrait<'de>,
     {
-        let value = deserializer.deserialize(ValueVisitor)?;
+        let class = self.0.intern(class);
+        let value = deserializer.deserialize(ValueVisitor(self.0))?;
         Ok(Value::UserMarshal {
-            class: class.to_symbol(),
+            class,
             value: Box::new(value),
         })
     }
@@ -171,9 +280,10 @@ impl<'de> Visitor<'de> for ValueVisitor {
     where
         D: DeserializerTrait<'de>,
     {
-        let value = deserializer.deserialize(ValueVisitor)?;
+        let class = self.0.intern(class);
+        let value = deserializer.deserialize(ValueVisitor(self.0))?;
         Ok(Value::Data {
-            class: class.to_symbol(),
+            class,
             value: Box::new(value),
         })
     }
@@ -184,30 +294,48 @@ impl<'de> Deserialize<'de> for Value {
     where
         D: DeserializerTrait<'de>,
     {
-        deserializer.deserialize(ValueVisitor)
+        deserializer.deserialize(ValueVisitor(&mut Interner::default()))
     }
 }
 
+/// Deserializer whose input is a `Value`.
+///
+/// This is the deserializer that backs `from_value`.
+/// `&Value` implements [`DeserializerTrait`] too, which is the same as using this deserializer with the default settings.
+#[derive(Clone, Copy, Debug)]
+pub struct Deserializer<'de> {
+    value: &'de Value,
+    depth: usize,
+    depth_limit: usize,
+    coerce: bool,
+}
+
 struct ValueInstanceAccess<'de> {
     value: &'de Value,
     fields: &'de RbFields,
+    deserializer: Deserializer<'de>,
 }
 
 struct ValueIVarAccess<'de> {
     fields: &'de RbFields,
     index: usize,
     state: MapState,
+    deserializer: Deserializer<'de>,
 }
 
 struct ValueArrayAccess<'de> {
     array: &'de [Value],
     index: usize,
+    deserializer: Deserializer<'de>,
 }
 
 struct ValueHashAccess<'de> {
     hash: &'de RbHash,
     index: usize,
     state: MapState,
+    // taken once deserialized
+    default: Option<&'de Value>,
+    deserializer: Deserializer<'de>,
 }
 
 enum MapState {
@@ -215,24 +343,168 @@ enum MapState {
     Value,
 }
 
-impl<'de> DeserializerTrait<'de> for &'de Value {
-    fn deserialize<V>(self, visitor: V) -> Result<V::Value>
+impl<'de> Deserializer<'de> {
+    /// Create a new deserializer that reads from `value`.
+    pub fn new(value: &'de Value) -> Self {
+        Self {
+            value,
+            depth: 0,
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+            coerce: false,
+        }
+    }
+
+    /// Set how deeply values may be nested before deserialization fails.
+    ///
+    /// Defaults to [`DEFAULT_DEPTH_LIMIT`].
+    pub fn set_depth_limit(&mut self, limit: usize) {
+        self.depth_limit = limit;
+    }
+
+    /// Returns how deeply values may be nested before deserialization fails.
+    pub fn depth_limit(&self) -> usize {
+        self.depth_limit
+    }
+
+    /// Set whether values are converted into the primitive that's expected, if they aren't one already.
+    ///
+    /// Ruby data is often loose about types, so this saves writing a custom visitor for every field that is.
+    /// Only primitives are converted, and only when the conversion makes sense:
+    ///
+    /// | Expected | Converted from |
+    /// |----------|----------------|
+    /// | `bool` | integers (`0` is `false`, everything else is `true`), and the strings `"true"` and `"false"` |
+    /// | integers | booleans (`true` is `1`, `false` is `0`), and strings containing an integer |
+    /// | floats | strings containing a number |
+    ///
+    /// Whitespace around numbers in strings is ignored.
+    /// Integers and floats already convert into each other, even when this is off.
+    /// Anything else (including strings that don't convert) is deserialized as usual,
+    /// so the error is the same as it would be without coercion.
+    ///
+    /// Off by default. What's expected comes from [`Visitor::expected_primitive`].
+    pub fn set_coerce(&mut self, coerce: bool) {
+        self.coerce = coerce;
+    }
+
+    /// Returns whether values are converted into the primitive that's expected.
+    ///
+    /// See [`Deserializer::set_coerce`].
+    pub fn coerce(&self) -> bool {
+        self.coerce
+    }
+
+    fn nested(self, value: &'de Value) -> Self {
+        Self {
+            value,
+            depth: self.depth + 1,
+            ..self
+        }
+    }
+}
+
+enum Coerced {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+}
+
+impl Coerced {
+    fn visit<'de, V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
         match self {
+            Coerced::Bool(v) => visitor.visit_bool(v),
+            Coerced::Integer(v) => visit_integer(visitor, v),
+            Coerced::Float(v) => visitor.visit_f64(v),
+        }
+    }
+}
+
+// The conversions documented on `Deserializer::set_coerce`.
+fn coerce(value: &Value, primitive: Primitive) -> Option<Coerced> {
+    match (primitive, value) {
+        // strings with an encoding may be wrapped in an instance
+        (_, Value::Instance(instance)) => coerce(&instance.value, primitive),
+        (Primitive::Bool, Value::Integer(v)) => Some(Coerced::Bool(*v != 0)),
+        (Primitive::Bool, Value::String(string)) => match string.as_slice() {
+            b"true" => Some(Coerced::Bool(true)),
+            b"false" => Some(Coerced::Bool(false)),
+            _ => None,
+        },
+        (Primitive::Integer, Value::Bool(v)) => Some(Coerced::Integer(i64::from(*v))),
+        (Primitive::Integer, Value::String(string)) => {
+            trimmed(string)?.parse().ok().map(Coerced::Integer)
+        }
+        (Primitive::Float, Value::String(string)) => {
+            trimmed(string)?.parse().ok().map(Coerced::Float)
+        }
+        _ => None,
+    }
+}
+
+// integers that fit in an i32 are visited as one, so visitors that only implement `visit_i32` work
+fn visit_integer<'de, V>(visitor: V, v: i64) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    match i32::try_from(v) {
+        Ok(v) => visitor.visit_i32(v),
+        Err(_) => visitor.visit_i64(v),
+    }
+}
+
+fn trimmed(string: &RbString) -> Option<&str> {
+    std::str::from_utf8(&string.data).ok().map(str::trim)
+}
+
+impl<'de> DeserializerTrait<'de> for Deserializer<'de> {
+    fn deserialize<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.depth >= self.depth_limit {
+            return Err(Error::from(Kind::DepthLimitExceeded(self.depth_limit)));
+        }
+
+        if self.coerce {
+            if let Some(coerced) = visitor
+                .expected_primitive()
+                .and_then(|primitive| coerce(self.value, primitive))
+            {
+                return coerced.visit(visitor);
+            }
+        }
+
+        match self.value {
             Value::Nil => visitor.visit_nil(),
             Value::Bool(v) => visitor.visit_bool(*v),
             Value::Float(f) => visitor.visit_f64(*f),
-            Value::Integer(i) => visitor.visit_i32(*i),
+            Value::Integer(i) => visit_integer(visitor, *i),
             Value::String(s) => visitor.visit_string(&s.data),
             Value::Symbol(s) => visitor.visit_symbol(s),
-            Value::Array(array) => visitor.visit_array(ValueArrayAccess { array, index: 0 }),
+            Value::Array(array) => visitor.visit_array(ValueArrayAccess {
+                array,
+                index: 0,
+                deserializer: self,
+            }),
             Value::Hash(hash) => visitor.visit_hash(ValueHashAccess {
                 hash,
                 index: 0,
                 state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+                default: None,
+                deserializer: self,
             }),
+            Value::HashWithDefault { hash, default } => {
+                visitor.visit_hash_with_default(ValueHashAccess {
+                    hash,
+                    index: 0,
+                    state: MapState::Value,
+                    default: Some(default),
+                    deserializer: self,
+                })
+            }
             Value::Userdata(u) => visitor.visit_user_data(&u.class, &u.data),
             Value::Object(o) => visitor.visit_object(
                 &o.class,
@@ -240,11 +512,13 @@ impl<'de> DeserializerTrait<'de> for &'de Value {
                     fields: &o.fields,
                     index: 0,
                     state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+                    deserializer: self,
                 },
             ),
             Value::Instance(i) => visitor.visit_instance(ValueInstanceAccess {
                 value: &i.value,
                 fields: &i.fields,
+                deserializer: self,
             }),
             Value::Regex { data, flags } => visitor.visit_regular_expression(&data.data, *flags),
             Value::RbStruct(s) => visitor.visit_struct(
@@ -253,16 +527,19 @@ impl<'de> DeserializerTrait<'de> for &'de Value {
                     fields: &s.fields,
                     index: 0,
                     state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+                    deserializer: self,
                 },
             ),
             Value::Class(c) => visitor.visit_class(c),
             Value::Module(m) => visitor.visit_module(m),
-            Value::Extended { module, value } => visitor.visit_extended(module, value.as_ref()),
-            Value::UserClass { class, value } => visitor.visit_user_class(class, value.as_ref()),
+            Value::Extended { module, value } => visitor.visit_extended(module, self.nested(value)),
+            Value::UserClass { class, value } => {
+                visitor.visit_user_class(class, self.nested(value))
+            }
             Value::UserMarshal { class, value } => {
-                visitor.visit_user_marshal(class, value.as_ref())
+                visitor.visit_user_marshal(class, self.nested(value))
             }
-            Value::Data { class, value } => visitor.visit_data(class, value.as_ref()),
+            Value::Data { class, value } => visitor.visit_data(class, self.nested(value)),
         }
     }
 
@@ -270,7 +547,7 @@ impl<'de> DeserializerTrait<'de> for &'de Value {
     where
         V: VisitorOption<'de>,
     {
-        if matches!(self, Value::Nil) {
+        if matches!(self.value, Value::Nil) {
             visitor.visit_none()
         } else {
             visitor.visit_some(self)
@@ -281,10 +558,11 @@ impl<'de> DeserializerTrait<'de> for &'de Value {
     where
         V: VisitorInstance<'de>,
     {
-        if let Value::Instance(i) = self {
+        if let Value::Instance(i) = self.value {
             visitor.visit_instance(ValueInstanceAccess {
                 value: &i.value,
                 fields: &i.fields,
+                deserializer: self,
             })
         } else {
             visitor.visit(self)
@@ -292,6 +570,64 @@ impl<'de> DeserializerTrait<'de> for &'de Value {
     }
 }
 
+impl<'de> DeserializerTrait<'de> for &'de Value {
+    fn deserialize<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Deserializer::new(self).deserialize(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorOption<'de>,
+    {
+        Deserializer::new(self).deserialize_option(visitor)
+    }
+
+    fn deserialize_instance<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorInstance<'de>,
+    {
+        Deserializer::new(self).deserialize_instance(visitor)
+    }
+}
+
+// What deserializing a bare `Object` nests its fields in, since it isn't part of a `Value`.
+static NIL: Value = Value::Nil;
+
+/// An object deserializes like a [`Value::Object`] holding it, without being cloned into one.
+impl<'de> DeserializerTrait<'de> for &'de Object {
+    fn deserialize<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_object(
+            &self.class,
+            ValueIVarAccess {
+                fields: &self.fields,
+                index: 0,
+                state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+                deserializer: Deserializer::new(&NIL),
+            },
+        )
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorOption<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_instance<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: VisitorInstance<'de>,
+    {
+        visitor.visit(self)
+    }
+}
+
 impl<'de> InstanceAccess<'de> for ValueInstanceAccess<'de> {
     type IvarAccess = ValueIVarAccess<'de>;
 
@@ -299,11 +635,12 @@ impl<'de> InstanceAccess<'de> for ValueInstanceAccess<'de> {
     where
         V: DeserializeSeed<'de>,
     {
-        let value = seed.deserialize(self.value)?;
+        let value = seed.deserialize(self.deserializer.nested(self.value))?;
         let access = ValueIVarAccess {
             fields: self.fields,
             index: 0,
             state: MapState::Value, // we want to enforce getting a key next so we set the state to value
+            deserializer: self.deserializer,
         };
         Ok((value, access))
     }
@@ -316,11 +653,7 @@ impl<'de> IvarAccess<'de> for ValueIVarAccess<'de> {
         };
 
         match self.state {
-            MapState::Key => {
-                return Err(Error {
-                    kind: Kind::KeyAfterKey,
-                })
-            }
+            MapState::Key => return Err(Error::from(Kind::KeyAfterKey)),
             MapState::Value => self.state = MapState::Key,
         }
 
@@ -331,13 +664,33 @@ impl<'de> IvarAccess<'de> for ValueIVarAccess<'de> {
     where
         V: DeserializeSeed<'de>,
     {
-        let (_, value) = self.fields.get_index(self.index).ok_or(Error {
-            kind: Kind::ValueAfterValue,
-        })?;
+        let (_, value) = self
+            .fields
+            .get_index(self.index)
+            .ok_or(Error::from(Kind::ValueAfterValue))?;
         self.state = MapState::Value;
         self.index += 1;
 
-        seed.deserialize(value)
+        seed.deserialize(self.deserializer.nested(value))
+    }
+
+    fn next_value_seed_or_capture<V>(
+        &mut self,
+        seed: V,
+    ) -> Result<std::result::Result<V::Value, Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let (_, value) = self
+            .fields
+            .get_index(self.index)
+            .ok_or(Error::from(Kind::ValueAfterValue))?;
+        self.state = MapState::Value;
+        self.index += 1;
+
+        Ok(seed
+            .deserialize(self.deserializer.nested(value))
+            .map_err(|_| value.clone()))
     }
 
     fn len(&self) -> usize {
@@ -358,7 +711,7 @@ impl<'de> ArrayAccess<'de> for ValueArrayAccess<'de> {
             return Ok(None);
         };
         self.index += 1;
-        seed.deserialize(value).map(Some)
+        seed.deserialize(self.deserializer.nested(value)).map(Some)
     }
 
     fn len(&self) -> usize {
@@ -380,28 +733,41 @@ impl<'de> HashAccess<'de> for ValueHashAccess<'de> {
         };
 
         match self.state {
-            MapState::Key => {
-                return Err(Error {
-                    kind: Kind::KeyAfterKey,
-                })
-            }
+            MapState::Key => return Err(Error::from(Kind::KeyAfterKey)),
             MapState::Value => self.state = MapState::Key,
         }
 
-        seed.deserialize(key).map(Some)
+        seed.deserialize(self.deserializer.nested(key)).map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
         V: DeserializeSeed<'de>,
     {
-        let (_, value) = self.hash.get_index(self.index).ok_or(Error {
-            kind: Kind::ValueAfterValue,
-        })?;
+        let (_, value) = self
+            .hash
+            .get_index(self.index)
+            .ok_or(Error::from(Kind::ValueAfterValue))?;
         self.state = MapState::Value;
         self.index += 1;
 
-        seed.deserialize(value)
+        seed.deserialize(self.deserializer.nested(value))
+    }
+
+    fn next_default_seed<V>(&mut self, seed: V) -> Result<Option<V::Value>>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let Some(default) = self.default.take() else {
+            return Ok(None);
+        };
+        if let MapState::Key = self.state {
+            return Err(Error::from(Kind::KeyAfterKey));
+        }
+
+        self.index = self.hash.len();
+        seed.deserialize(self.deserializer.nested(default))
+            .map(Some)
     }
 
     fn len(&self) -> usize {
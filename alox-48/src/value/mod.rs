@@ -4,96 +4,18 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+pub mod arena;
 mod de;
-mod from;
-mod impls;
+mod owned;
 mod ser;
 
+#[cfg(feature = "bytes")]
+pub(crate) use de::deserialize_shared;
+pub use de::Deserializer;
+pub use owned::{OwnedDeserializer, ValueExt};
 pub use ser::Serializer;
 
-use crate::{
-    rb_types::{Object, RbArray, RbFields, RbHash, RbString, Symbol, Userdata},
-    Instance, RbStruct,
-};
-
-/// An enum representing any ruby value.
-///
-/// Similar to `serde_json::Value`, although much more nuanced.
-#[derive(Default, Clone, enum_as_inner::EnumAsInner, Debug)]
-pub enum Value {
-    /// A value equivalent to nil in ruby (or [`()`] in rust.)
-    #[default]
-    Nil,
-    /// A boolean value.
-    Bool(bool),
-    /// A float value.
-    Float(f64),
-    /// An integer value.
-    Integer(i32),
-    /// A ruby string.
-    /// Because strings in ruby are not guarenteed to be utf8, [`RbString`] stores a [`Vec<u8>`] instead.
-    ///
-    /// See [`RbString`] for more information.
-    String(RbString),
-    /// A symbol from ruby.
-    /// It's a newtype around a String, meant to preserve types during (de)serialization.
-    /// See [`Symbol`] for more information.
-    Symbol(Symbol),
-    /// An array of [`Value`].
-    Array(RbArray),
-    /// Equivalent to a Hash in Ruby.
-    Hash(RbHash),
-    /// An object serialized by `_dump`.
-    Userdata(Userdata),
-    /// A generic ruby object.
-    Object(Object),
-    /// An "instance".
-    ///
-    /// The naming is a bit of a misnomer, as it's not an instance of a class, but rather a value with attached ivars.
-    /// It's distinct from object though, as it's a value (like a string) which does not usually have ivars.
-    Instance(Instance<Box<Value>>),
-    /// Equivalent to a `Regexp` in Ruby.
-    Regex {
-        /// The regex data.
-        data: RbString,
-        /// Any flags associated with the regex. (global match, case insensitive, etc.)
-        flags: u8,
-    },
-    /// Equivalent to a `Struct` in Ruby.
-    RbStruct(RbStruct),
-    /// Equivalent to a `Class` in Ruby.
-    Class(Symbol),
-    /// Equivalent to a `Module` in Ruby.
-    Module(Symbol),
-    /// A value that has been extended with a module.
-    Extended {
-        /// The module that was extended.
-        module: Symbol,
-        /// The value that was extended.
-        value: Box<Value>,
-    },
-    /// A subclass of a ruby class like `Hash` or `Array`.
-    UserClass {
-        /// The subclass.
-        class: Symbol,
-        /// The value of the subclass.
-        value: Box<Value>,
-    },
-    /// An object that has been serialized as another type.
-    UserMarshal {
-        /// The class of the original object.
-        class: Symbol,
-        /// The value of the object.
-        value: Box<Value>,
-    },
-    /// Unclear what this is? It's releated to C extensions according to the ruby docs.
-    Data {
-        /// The class of the data.
-        class: Symbol,
-        /// The value of the data.
-        value: Box<Value>,
-    },
-}
+use crate::Value;
 
 /// Interpret a `Value` as an instance of type `T`.
 ///
@@ -130,6 +52,57 @@ where
     T::deserialize(value)
 }
 
+/// Like [`from_value`], but converts values into the primitive that's expected if they aren't one already.
+///
+/// See [`Deserializer::set_coerce`] for what is converted.
+///
+/// ```
+/// #[derive(alox_48::Deserialize, Debug, PartialEq)]
+/// struct Switch {
+///     id: i32,
+///     on: bool,
+/// }
+///
+/// let value = alox_48::marshal!(Switch { @id => "5", @on => 1 });
+/// let switch: Switch = alox_48::from_value_coerced(&value).unwrap();
+/// assert_eq!(switch, Switch { id: 5, on: true });
+/// ```
+///
+/// # Errors
+///
+/// This conversion can fail if the structure of the Value does not match the structure of `T`,
+/// even after converting primitives.
+#[allow(clippy::module_name_repetitions)]
+pub fn from_value_coerced<'de, T>(value: &'de Value) -> Result<T, crate::DeError>
+where
+    T: crate::Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::new(value);
+    deserializer.set_coerce(true);
+    T::deserialize(deserializer)
+}
+
+/// Interpret a `Value` as an instance of type `T`, consuming the value.
+///
+/// Unlike [`from_value`], `T` can't borrow from the value, so the value doesn't need to outlive it.
+/// This is useful for temporary values, like one that was just returned from a function.
+///
+/// ```
+/// let numbers: Vec<i32> = alox_48::from_value_owned(alox_48::marshal!([1, 2])).unwrap();
+/// assert_eq!(numbers, [1, 2]);
+/// ```
+///
+/// # Errors
+///
+/// This conversion can fail if the structure of the Value does not match the structure of `T`.
+#[allow(clippy::module_name_repetitions)]
+pub fn from_value_owned<T>(value: Value) -> Result<T, crate::DeError>
+where
+    T: crate::DeserializeOwned,
+{
+    OwnedDeserializer::new(value).deserialize()
+}
+
 /// Convert a `T` into `Value`.
 ///
 /// # Example
@@ -0,0 +1,139 @@
+// Copyright (c) 2024 Lily Lyons
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::Deserializer;
+use crate::{
+    de::{Result, DEFAULT_DEPTH_LIMIT},
+    ser::Result as SerResult,
+    Deserialize, DeserializeOwned, Serialize, Value,
+};
+
+/// A deserializer that owns the [`Value`] it reads from.
+///
+/// [`DeserializerTrait`](crate::DeserializerTrait) can't be implemented for an owned value:
+/// visitors are handed strings and symbols borrowed for `'de`, and an owned value would be dropped while they're still borrowed.
+/// Instead, this deserializes types that don't borrow anything ([`DeserializeOwned`]),
+/// so a temporary value can be deserialized without keeping it alive somewhere with a matching lifetime.
+///
+/// Use [`OwnedDeserializer::borrow`] when you need something that implements `DeserializerTrait`.
+///
+/// ```
+/// use alox_48::{marshal, ValueExt};
+///
+/// fn load() -> alox_48::Value {
+///     marshal!([1, 2, 3])
+/// }
+///
+/// let numbers: Vec<i32> = load().into_deserializer().deserialize().unwrap();
+/// assert_eq!(numbers, [1, 2, 3]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct OwnedDeserializer {
+    value: Value,
+    depth_limit: usize,
+}
+
+impl OwnedDeserializer {
+    /// Create a new deserializer that reads from `value`.
+    pub fn new(value: Value) -> Self {
+        Self {
+            value,
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+        }
+    }
+
+    /// Set how deeply values may be nested before deserialization fails.
+    ///
+    /// Defaults to [`DEFAULT_DEPTH_LIMIT`].
+    pub fn set_depth_limit(&mut self, limit: usize) {
+        self.depth_limit = limit;
+    }
+
+    /// Returns how deeply values may be nested before deserialization fails.
+    pub fn depth_limit(&self) -> usize {
+        self.depth_limit
+    }
+
+    /// Deserialize the value into a `T`, consuming this deserializer.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value doesn't match the structure of `T`.
+    pub fn deserialize<T>(self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        T::deserialize(self.borrow())
+    }
+
+    /// Returns a deserializer borrowing the value, with the same settings.
+    pub fn borrow(&self) -> Deserializer<'_> {
+        let mut deserializer = Deserializer::new(&self.value);
+        deserializer.set_depth_limit(self.depth_limit);
+        deserializer
+    }
+
+    /// Take the value back out of this deserializer.
+    pub fn into_inner(self) -> Value {
+        self.value
+    }
+}
+
+/// Conversion helpers for [`Value`].
+///
+/// [`Value`] is defined in alox-48-types, so these are provided by a trait.
+///
+/// ```
+/// use alox_48::{Value, ValueExt};
+///
+/// let value = Value::from_serialize(&[1, 2, 3])?;
+/// let numbers: Vec<i32> = value.deserialize_into()?;
+/// assert_eq!(numbers, [1, 2, 3]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub trait ValueExt {
+    /// Turn this value into a deserializer that owns it. See [`OwnedDeserializer`].
+    fn into_deserializer(self) -> OwnedDeserializer;
+
+    /// Deserialize this value into a `T`. This is [`from_value`](crate::from_value).
+    ///
+    /// # Errors
+    ///
+    /// Errors if this value can't be deserialized into a `T`.
+    fn deserialize_into<'de, T>(&'de self) -> Result<T>
+    where
+        T: Deserialize<'de>;
+
+    /// Serialize `value` into a [`Value`]. This is [`to_value`](crate::to_value).
+    ///
+    /// # Errors
+    ///
+    /// Errors if `value` fails to serialize.
+    fn from_serialize<T>(value: &T) -> SerResult<Self>
+    where
+        T: Serialize + ?Sized,
+        Self: Sized;
+}
+
+impl ValueExt for Value {
+    fn into_deserializer(self) -> OwnedDeserializer {
+        OwnedDeserializer::new(self)
+    }
+
+    fn deserialize_into<'de, T>(&'de self) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        crate::from_value(self)
+    }
+
+    fn from_serialize<T>(value: &T) -> SerResult<Self>
+    where
+        T: Serialize + ?Sized,
+    {
+        crate::to_value(value)
+    }
+}
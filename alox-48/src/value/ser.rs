@@ -3,10 +3,10 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
-use super::{Object, RbFields, RbHash, RbString, Symbol, Userdata, Value};
 use crate::{
     ser::{Error, Kind, Result, Serialize},
-    Instance, RbArray, RbStruct, SerializerTrait, Sym,
+    Instance, Object, RbArray, RbFields, RbHash, RbString, RbStruct, SerializeHash as _,
+    SerializerTrait, Sym, Symbol, Userdata, Value,
 };
 
 impl Serialize for Value {
@@ -18,11 +18,23 @@ impl Serialize for Value {
             Value::Nil => serializer.serialize_nil(),
             Value::Bool(v) => serializer.serialize_bool(*v),
             Value::Float(f) => serializer.serialize_f64(*f),
-            Value::Integer(i) => serializer.serialize_i32(*i),
+            // integers that fit are serialized the same way as an i32 would be
+            Value::Integer(i) => match i32::try_from(*i) {
+                Ok(i) => serializer.serialize_i32(i),
+                Err(_) => serializer.serialize_i64(*i),
+            },
             Value::String(s) => s.serialize(serializer),
             Value::Symbol(s) => s.serialize(serializer),
             Value::Array(a) => a.serialize(serializer),
             Value::Hash(h) => h.serialize(serializer),
+            Value::HashWithDefault { hash, default } => {
+                let mut serialize_hash = serializer.serialize_hash_with_default(hash.len())?;
+                for (key, value) in hash {
+                    serialize_hash.serialize_entry(key, value)?;
+                }
+                serialize_hash.serialize_default(default)?;
+                serialize_hash.end()
+            }
             Value::Userdata(d) => d.serialize(serializer),
             Value::Object(o) => o.serialize(serializer),
             Value::Instance(i) => i.serialize(serializer),
@@ -53,6 +65,8 @@ pub struct SerializeIvars {
     fields: RbFields,
     next_field: Option<Symbol>,
     value: SerializeIvarsValue,
+    len: usize,
+    index: usize,
 }
 
 #[derive(Debug)]
@@ -66,10 +80,45 @@ enum SerializeIvarsValue {
 pub struct SerializeHash {
     hash: RbHash,
     next_key: Option<Value>,
+    len: usize,
+    index: usize,
+    default: HashDefault,
+}
+
+#[derive(Debug)]
+enum HashDefault {
+    None,
+    Pending,
+    Written(Value),
 }
 
 #[derive(Debug)]
-pub struct SerializeArray(RbArray);
+pub struct SerializeArray {
+    array: RbArray,
+    len: usize,
+}
+
+// The resulting value doesn't need the length like marshal does,
+// but checking it anyway means to_value rejects the same broken Serialize impls that to_bytes does.
+fn check_overshot(index: usize, len: usize) -> Result<()> {
+    if index > len {
+        Err(Error {
+            kind: Kind::OvershotProvidedLen(index, len),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn check_undershot(index: usize, len: usize) -> Result<()> {
+    if index < len {
+        Err(Error {
+            kind: Kind::UndershotProvidedLen(index, len),
+        })
+    } else {
+        Ok(())
+    }
+}
 
 impl SerializerTrait for Serializer {
     type Ok = Value;
@@ -87,6 +136,10 @@ impl SerializerTrait for Serializer {
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
         Ok(Value::Integer(v))
     }
 
@@ -98,17 +151,31 @@ impl SerializerTrait for Serializer {
         Ok(SerializeHash {
             hash: RbHash::with_capacity(len),
             next_key: None,
+            len,
+            index: 0,
+            default: HashDefault::None,
+        })
+    }
+
+    fn serialize_hash_with_default(self, len: usize) -> Result<Self::SerializeHash> {
+        Ok(SerializeHash {
+            hash: RbHash::with_capacity(len),
+            next_key: None,
+            len,
+            index: 0,
+            default: HashDefault::Pending,
         })
     }
 
     fn serialize_array(self, len: usize) -> Result<Self::SerializeArray> {
-        Ok(SerializeArray(Vec::with_capacity(len)))
+        Ok(SerializeArray {
+            array: Vec::with_capacity(len),
+            len,
+        })
     }
 
     fn serialize_string(self, data: &[u8]) -> Result<Self::Ok> {
-        Ok(Value::String(RbString {
-            data: data.to_vec(),
-        }))
+        Ok(Value::String(RbString::from(data)))
     }
 
     fn serialize_symbol(self, sym: &Sym) -> Result<Self::Ok> {
@@ -126,6 +193,8 @@ impl SerializerTrait for Serializer {
         Ok(SerializeIvars {
             fields: RbFields::with_capacity(len),
             next_field: None,
+            len,
+            index: 0,
             value: SerializeIvarsValue::Object(class.to_symbol()),
         })
     }
@@ -134,6 +203,8 @@ impl SerializerTrait for Serializer {
         Ok(SerializeIvars {
             fields: RbFields::with_capacity(len),
             next_field: None,
+            len,
+            index: 0,
             value: SerializeIvarsValue::Struct(name.to_symbol()),
         })
     }
@@ -154,6 +225,8 @@ impl SerializerTrait for Serializer {
         Ok(SerializeIvars {
             fields: RbFields::with_capacity(len),
             next_field: None,
+            len,
+            index: 0,
             value: SerializeIvarsValue::Instance(value),
         })
     }
@@ -181,10 +254,7 @@ impl SerializerTrait for Serializer {
     }
 
     fn serialize_user_data(self, class: &Sym, data: &[u8]) -> Result<Self::Ok> {
-        Ok(Value::Userdata(Userdata {
-            class: class.to_symbol(),
-            data: data.to_vec(),
-        }))
+        Ok(Value::Userdata(Userdata::new(class.to_symbol(), data)))
     }
 
     fn serialize_user_marshal<V>(self, class: &Sym, value: &V) -> Result<Self::Ok>
@@ -214,6 +284,8 @@ impl crate::SerializeIvars for SerializeIvars {
     type Ok = Value;
 
     fn serialize_field(&mut self, k: &Sym) -> Result<()> {
+        self.index += 1;
+        check_overshot(self.index, self.len)?;
         if self.next_field.is_some() {
             return Err(Error {
                 kind: Kind::KeyAfterKey,
@@ -238,6 +310,13 @@ impl crate::SerializeIvars for SerializeIvars {
     }
 
     fn end(self) -> Result<Self::Ok> {
+        check_undershot(self.index, self.len)?;
+        if self.next_field.is_some() {
+            return Err(Error {
+                kind: Kind::KeyAfterKey,
+            });
+        }
+
         match self.value {
             SerializeIvarsValue::Object(class) => Ok(Value::Object(Object {
                 class,
@@ -262,6 +341,8 @@ impl crate::SerializeHash for SerializeHash {
     where
         K: Serialize + ?Sized,
     {
+        self.index += 1;
+        check_overshot(self.index, self.len)?;
         if self.next_key.is_some() {
             return Err(Error {
                 kind: Kind::KeyAfterKey,
@@ -286,8 +367,44 @@ impl crate::SerializeHash for SerializeHash {
         Ok(())
     }
 
+    fn serialize_default<V>(&mut self, v: &V) -> Result<()>
+    where
+        V: Serialize + ?Sized,
+    {
+        if !matches!(self.default, HashDefault::Pending) {
+            return Err(Error {
+                kind: Kind::UnexpectedHashDefault,
+            });
+        }
+        check_undershot(self.index, self.len)?;
+        if self.next_key.is_some() {
+            return Err(Error {
+                kind: Kind::KeyAfterKey,
+            });
+        }
+
+        self.default = HashDefault::Written(v.serialize(Serializer)?);
+        Ok(())
+    }
+
     fn end(self) -> Result<Self::Ok> {
-        Ok(Value::Hash(self.hash))
+        check_undershot(self.index, self.len)?;
+        if self.next_key.is_some() {
+            return Err(Error {
+                kind: Kind::KeyAfterKey,
+            });
+        }
+
+        match self.default {
+            HashDefault::None => Ok(Value::Hash(self.hash)),
+            HashDefault::Pending => Err(Error {
+                kind: Kind::MissingHashDefault,
+            }),
+            HashDefault::Written(default) => Ok(Value::HashWithDefault {
+                hash: self.hash,
+                default: Box::new(default),
+            }),
+        }
     }
 }
 
@@ -298,12 +415,14 @@ impl crate::SerializeArray for SerializeArray {
     where
         T: Serialize + ?Sized,
     {
+        check_overshot(self.array.len() + 1, self.len)?;
         let value = v.serialize(Serializer)?;
-        self.0.push(value);
+        self.array.push(value);
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(Value::Array(self.0))
+        check_undershot(self.array.len(), self.len)?;
+        Ok(Value::Array(self.array))
     }
 }